@@ -3,7 +3,7 @@
 use linkerd_dns_name::Name;
 use std::{
     fmt,
-    net::{IpAddr, SocketAddr},
+    net::{IpAddr, Ipv4Addr, SocketAddr},
     str::FromStr,
 };
 use thiserror::Error;
@@ -242,10 +242,65 @@ impl fmt::Display for NameAddr {
     }
 }
 
+/// Converts an IPv4-mapped IPv6 address (i.e. `::ffff:a.b.c.d`) to the IPv4
+/// address it represents. All other addresses are returned unchanged.
+///
+/// Dual-stack sockets report IPv4 peers with IPv4-mapped addresses, which
+/// must be converted before they can be matched against IPv4 networks.
+pub fn canonical_ip(ip: IpAddr) -> IpAddr {
+    if let IpAddr::V6(ip) = ip {
+        if let [0, 0, 0, 0, 0, 0xffff, hi, lo] = ip.segments() {
+            let [a, b] = hi.to_be_bytes();
+            let [c, d] = lo.to_be_bytes();
+            return IpAddr::V4(Ipv4Addr::new(a, b, c, d));
+        }
+    }
+    ip
+}
+
+/// Converts a socket address with an IPv4-mapped IPv6 address to an IPv4
+/// socket address. All other addresses are returned unchanged.
+pub fn canonical_socket_addr(addr: SocketAddr) -> SocketAddr {
+    match canonical_ip(addr.ip()) {
+        ip @ IpAddr::V4(_) => SocketAddr::new(ip, addr.port()),
+        IpAddr::V6(_) => addr,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_canonical_ip() {
+        let cases = &[
+            ("10.1.2.3", "10.1.2.3"),
+            ("::ffff:10.1.2.3", "10.1.2.3"),
+            ("::ffff:127.0.0.1", "127.0.0.1"),
+            ("::1", "::1"),
+            ("::10.1.2.3", "::10.1.2.3"), // IPv4-compatible, not mapped
+            ("2001:db8::ffff:a01:203", "2001:db8::ffff:a01:203"),
+        ];
+        for (ip, expected) in cases {
+            let ip = IpAddr::from_str(ip).unwrap();
+            assert_eq!(
+                canonical_ip(ip),
+                IpAddr::from_str(expected).unwrap(),
+                "{:?}",
+                ip
+            );
+        }
+    }
+
+    #[test]
+    fn test_canonical_socket_addr() {
+        let addr = SocketAddr::from_str("[::ffff:10.1.2.3]:8080").unwrap();
+        assert_eq!(
+            canonical_socket_addr(addr),
+            SocketAddr::from(([10, 1, 2, 3], 8080))
+        );
+    }
+
     #[test]
     fn test_is_loopback() {
         let cases = &[
@@ -0,0 +1,57 @@
+use hyper::Body;
+use linkerd_app_core::{metrics, Error};
+
+/// Flushes the stack caches that match the request's query parameters.
+///
+/// The `name` parameter is required and may be constrained further by the
+/// `direction` and `protocol` parameters, matching the labels of the
+/// `stack_cache_*` metrics.
+pub(super) fn serve<B>(
+    caches: &metrics::Cache,
+    req: http::Request<B>,
+) -> Result<http::Response<Body>, Error> {
+    if req.method() != http::Method::POST {
+        return Ok(http::Response::builder()
+            .status(http::StatusCode::METHOD_NOT_ALLOWED)
+            .header("allow", "POST")
+            .body(Body::empty())
+            .expect("builder with known status code must not fail"));
+    }
+
+    let mut name = None;
+    let mut direction = None;
+    let mut protocol = None;
+    for param in req.uri().query().unwrap_or_default().split('&') {
+        let mut kv = param.splitn(2, '=');
+        match (kv.next(), kv.next()) {
+            (Some("name"), Some(v)) => name = Some(v),
+            (Some("direction"), Some(v)) => direction = Some(v),
+            (Some("protocol"), Some(v)) => protocol = Some(v),
+            _ => {}
+        }
+    }
+
+    let name = match name {
+        Some(name) => name,
+        None => {
+            return Ok(http::Response::builder()
+                .status(http::StatusCode::BAD_REQUEST)
+                .header(http::header::CONTENT_TYPE, "text/plain")
+                .body("a cache name must be specified\n".into())
+                .expect("builder with known status code must not fail"))
+        }
+    };
+
+    let flushed = caches.flush(|labels: &metrics::StackLabels| {
+        labels.name == name
+            && direction.map_or(true, |d| labels.direction.to_string() == d)
+            && protocol.map_or(true, |p| labels.protocol == p)
+    });
+    tracing::info!(%name, ?direction, ?protocol, flushed, "Flushed stack caches");
+
+    Ok(http::Response::builder()
+        .status(http::StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "text/plain")
+        .body(format!("flushed {} entries\n", flushed).into())
+        .expect("builder with known status code must not fail"))
+}
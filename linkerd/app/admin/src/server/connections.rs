@@ -0,0 +1,66 @@
+use hyper::Body;
+use linkerd_app_core::{connections::Registry, Error};
+
+/// The number of connections described when the request doesn't specify a
+/// `limit`.
+const DEFAULT_LIMIT: usize = 100;
+
+/// Describes the proxy's open connections as JSON, oldest first.
+///
+/// At most `limit` connections are described.
+pub(super) fn serve<B>(
+    connections: &Registry,
+    req: http::Request<B>,
+) -> Result<http::Response<Body>, Error> {
+    if req.method() != http::Method::GET {
+        return Ok(http::Response::builder()
+            .status(http::StatusCode::METHOD_NOT_ALLOWED)
+            .header("allow", "GET")
+            .body(Body::empty())
+            .expect("builder with known status code must not fail"));
+    }
+
+    let mut limit = DEFAULT_LIMIT;
+    for param in req.uri().query().unwrap_or_default().split('&') {
+        let mut kv = param.splitn(2, '=');
+        if let (Some("limit"), Some(v)) = (kv.next(), kv.next()) {
+            limit = match v.parse() {
+                Ok(limit) => limit,
+                Err(_) => {
+                    return Ok(http::Response::builder()
+                        .status(http::StatusCode::BAD_REQUEST)
+                        .header(http::header::CONTENT_TYPE, "text/plain")
+                        .body("limit must be a non-negative integer\n".into())
+                        .expect("builder with known status code must not fail"))
+                }
+            };
+        }
+    }
+
+    let conns = connections
+        .connections(limit)
+        .into_iter()
+        .map(|conn| {
+            serde_json::json!({
+                "direction": conn.direction.to_string(),
+                "peer": conn.peer.to_string(),
+                "orig_dst": conn.orig_dst.to_string(),
+                "protocol": conn.protocol,
+                "identity": conn.identity.map(|id| id.to_string()),
+                "age_ms": conn.age.as_millis() as u64,
+                "read_bytes": conn.read_bytes,
+                "write_bytes": conn.write_bytes,
+            })
+        })
+        .collect::<Vec<_>>();
+    let body = serde_json::json!({
+        "total": connections.len(),
+        "connections": conns,
+    });
+    let body = serde_json::to_string_pretty(&body)?;
+    Ok(http::Response::builder()
+        .status(http::StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(body.into())
+        .expect("builder with known status code must not fail"))
+}
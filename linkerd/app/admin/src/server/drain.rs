@@ -0,0 +1,98 @@
+use futures::future;
+use std::{sync::Arc, time::Duration};
+use tokio::{sync::watch, time};
+
+/// Requests, via the admin server, that the proxy drain.
+///
+/// While a drain is requested, the proxy reports that it is not ready.
+#[derive(Clone, Debug)]
+pub struct DrainHandle {
+    tx: Arc<watch::Sender<bool>>,
+    rx: watch::Receiver<bool>,
+}
+
+/// Observes drain requests made via the admin server.
+#[derive(Clone, Debug)]
+pub struct Drain {
+    rx: watch::Receiver<bool>,
+    grace_period: Duration,
+}
+
+impl Drain {
+    pub fn new(grace_period: Duration) -> (DrainHandle, Drain) {
+        let (tx, rx) = watch::channel(false);
+        let handle = DrainHandle {
+            tx: Arc::new(tx),
+            rx: rx.clone(),
+        };
+        (handle, Drain { rx, grace_period })
+    }
+
+    /// Completes once a drain has been requested and has not been canceled
+    /// within the grace period.
+    ///
+    /// Never completes if the admin server is dropped.
+    pub async fn requested(&mut self) {
+        loop {
+            while !*self.rx.borrow() {
+                if self.rx.changed().await.is_err() {
+                    return future::pending().await;
+                }
+            }
+
+            let grace = time::sleep(self.grace_period);
+            tokio::pin!(grace);
+            loop {
+                tokio::select! {
+                    _ = &mut grace => return,
+                    res = self.rx.changed() => {
+                        if res.is_err() {
+                            return future::pending().await;
+                        }
+                        if !*self.rx.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl DrainHandle {
+    pub fn is_draining(&self) -> bool {
+        *self.rx.borrow()
+    }
+
+    /// Requests or cancels a drain, returning false if the proxy is no longer
+    /// observing drain requests.
+    pub(super) fn set_draining(&self, draining: bool) -> bool {
+        self.tx.send(draining).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::timeout;
+
+    #[tokio::test]
+    async fn drains_unless_canceled() {
+        let (handle, mut drain) = Drain::new(Duration::from_millis(100));
+        assert!(!handle.is_draining());
+
+        // A drain that is canceled within the grace period does not complete.
+        assert!(handle.set_draining(true));
+        assert!(handle.is_draining());
+        let requested = timeout(Duration::from_millis(20), drain.requested()).await;
+        assert!(requested.is_err());
+        assert!(handle.set_draining(false));
+        let requested = timeout(Duration::from_millis(200), drain.requested()).await;
+        assert!(requested.is_err());
+
+        assert!(handle.set_draining(true));
+        timeout(Duration::from_secs(1), drain.requested())
+            .await
+            .expect("drain must complete after the grace period");
+    }
+}
@@ -0,0 +1,103 @@
+use hyper::Body;
+use linkerd_app_core::{identity::CrtKey, Error};
+use std::{fmt::Write, time::UNIX_EPOCH};
+
+/// Describes the proxy's current identity certificate as JSON.
+pub(super) fn serve<B>(
+    crt_key: Option<CrtKey>,
+    req: http::Request<B>,
+) -> Result<http::Response<Body>, Error> {
+    if req.method() != http::Method::GET {
+        return Ok(http::Response::builder()
+            .status(http::StatusCode::METHOD_NOT_ALLOWED)
+            .header("allow", "GET")
+            .body(Body::empty())
+            .expect("builder with known status code must not fail"));
+    }
+
+    let crt_key = match crt_key {
+        Some(crt_key) => crt_key,
+        None => {
+            return Ok(http::Response::builder()
+                .status(http::StatusCode::SERVICE_UNAVAILABLE)
+                .header(http::header::CONTENT_TYPE, "text/plain")
+                .body("identity not yet certified\n".into())
+                .expect("builder with known status code must not fail"))
+        }
+    };
+
+    let serial = crt_key.serial().map(|serial| {
+        serial.iter().fold(String::new(), |mut s, b| {
+            let _ = write!(s, "{:02x}", b);
+            s
+        })
+    });
+    let not_after = crt_key
+        .expiry()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let body = serde_json::to_string_pretty(&serde_json::json!({
+        "san": crt_key.name().as_ref(),
+        "serial": serial,
+        "not_after_seconds": not_after,
+    }))?;
+
+    Ok(http::Response::builder()
+        .status(http::StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(body.into())
+        .expect("builder with known status code must not fail"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_identity::test_util::FOO_NS1;
+
+    fn get() -> http::Request<Body> {
+        http::Request::get("/identity").body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn describes_certificate() {
+        let crt_key = FOO_NS1.validate().expect("foo.ns1 must be valid");
+        let not_after = crt_key
+            .expiry()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let serial = crt_key.serial().expect("must have a serial").to_vec();
+
+        let rsp = serve(Some(crt_key), get()).unwrap();
+        assert_eq!(rsp.status(), http::StatusCode::OK);
+        assert_eq!(
+            rsp.headers()[http::header::CONTENT_TYPE],
+            "application/json"
+        );
+        let body = hyper::body::to_bytes(rsp.into_body()).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["san"], FOO_NS1.name);
+        assert_eq!(json["not_after_seconds"], not_after);
+        let hex = json["serial"].as_str().expect("serial must be a string");
+        assert_eq!(hex.len(), serial.len() * 2);
+        assert!(hex.starts_with(&format!("{:02x}", serial[0])));
+    }
+
+    #[test]
+    fn not_yet_certified() {
+        let rsp = serve(None, get()).unwrap();
+        assert_eq!(rsp.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[test]
+    fn only_get() {
+        let crt_key = FOO_NS1.validate().expect("foo.ns1 must be valid");
+        let req = http::Request::post("/identity")
+            .body(Body::empty())
+            .unwrap();
+        let rsp = serve(Some(crt_key), req).unwrap();
+        assert_eq!(rsp.status(), http::StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(rsp.headers()[http::header::ALLOW], "GET");
+    }
+}
@@ -2,12 +2,25 @@
 //!
 //! * `GET /metrics` -- reports prometheus-formatted metrics.
 //! * `GET /ready` -- returns 200 when the proxy is ready to participate in meshed
-//!   traffic.
+//!   traffic. With `wait=30s`, waits for the proxy to become ready; with
+//!   `format=json`, describes the readiness of each component.
 //! * `GET /live` -- returns 200 when the proxy is live.
+//! * `GET /identity` -- describes the proxy's current identity certificate.
 //! * `GET /proxy-log-level` -- returns the current proxy tracing filter.
 //! * `PUT /proxy-log-level` -- sets a new tracing filter.
 //! * `GET /tasks` -- returns a dump of spawned Tokio tasks (when enabled by the
 //!   tracing configuration).
+//! * `POST /proxy-cache-flush?name=...` -- drops the entries of the named stack
+//!   caches, optionally constrained by `direction` and `protocol`.
+//! * `GET /protocol-hints` -- describes the outbound protocol hints learned from
+//!   protocol detection.
+//! * `DELETE /protocol-hints` -- clears the learned protocol hints.
+//! * `GET /connections?limit=...` -- describes the connections that the proxy
+//!   has accepted and that are still open, oldest first.
+//! * `POST /drain` -- marks the proxy as not ready and, unless undrained
+//!   within the drain grace period, drains the proxy as if it had been sent
+//!   SIGTERM.
+//! * `POST /undrain` -- cancels a pending drain.
 //! * `POST /shutdown` -- shuts down the proxy.
 
 use futures::future;
@@ -17,8 +30,10 @@ use hyper::{
     Request, Response,
 };
 use linkerd_app_core::{
+    connections::Registry as Connections,
     metrics::{self as metrics, FmtMetrics},
-    proxy::http::ClientHandle,
+    protocol_hints::ProtocolHints,
+    proxy::{http::ClientHandle, identity::LocalCrtKey},
     trace, Error,
 };
 use std::{
@@ -29,17 +44,30 @@ use std::{
 };
 use tokio::sync::mpsc;
 
+mod cache;
+mod connections;
+mod drain;
+mod identity;
 mod level;
+mod protocol_hints;
 mod readiness;
 mod tasks;
 
-pub use self::readiness::{Latch, Readiness};
+pub use self::{
+    drain::{Drain, DrainHandle},
+    readiness::{Latch, Readiness},
+};
 
 #[derive(Clone)]
 pub struct Admin<M> {
     metrics: metrics::Serve<M>,
+    caches: metrics::Cache,
+    protocol_hints: ProtocolHints,
+    connections: Connections,
     tracing: trace::Handle,
+    identity: Option<LocalCrtKey>,
     ready: Readiness,
+    drain: DrainHandle,
     shutdown_tx: mpsc::UnboundedSender<()>,
 }
 
@@ -59,32 +87,28 @@ pub type ResponseFuture =
     Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send + 'static>>;
 
 impl<M> Admin<M> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         metrics: M,
+        caches: metrics::Cache,
+        protocol_hints: ProtocolHints,
+        connections: Connections,
         ready: Readiness,
+        drain: DrainHandle,
         shutdown_tx: mpsc::UnboundedSender<()>,
         tracing: trace::Handle,
+        identity: Option<LocalCrtKey>,
     ) -> Self {
         Self {
             metrics: metrics::Serve::new(metrics),
+            caches,
+            protocol_hints,
+            connections,
             ready,
+            drain,
             shutdown_tx,
             tracing,
-        }
-    }
-
-    fn ready_rsp(&self) -> Response<Body> {
-        if self.ready.is_ready() {
-            Response::builder()
-                .status(StatusCode::OK)
-                .header(http::header::CONTENT_TYPE, "text/plain")
-                .body("ready\n".into())
-                .expect("builder with known status code must not fail")
-        } else {
-            Response::builder()
-                .status(StatusCode::SERVICE_UNAVAILABLE)
-                .body("not ready\n".into())
-                .expect("builder with known status code must not fail")
+            identity,
         }
     }
 
@@ -112,6 +136,28 @@ impl<M> Admin<M> {
         }
     }
 
+    fn drain(&self, draining: bool) -> Response<Body> {
+        if self.drain.set_draining(draining) {
+            tracing::info!(draining, "Drain set via admin interface");
+            let body = if draining {
+                "draining\n"
+            } else {
+                "undrained\n"
+            };
+            Response::builder()
+                .status(StatusCode::OK)
+                .header(http::header::CONTENT_TYPE, "text/plain")
+                .body(body.into())
+                .expect("builder with known status code must not fail")
+        } else {
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .header(http::header::CONTENT_TYPE, "text/plain")
+                .body("drain listener dropped\n".into())
+                .expect("builder with known status code must not fail")
+        }
+    }
+
     fn internal_error_rsp(error: impl ToString) -> http::Response<Body> {
         http::Response::builder()
             .status(http::StatusCode::INTERNAL_SERVER_ERROR)
@@ -168,7 +214,29 @@ where
     fn call(&mut self, req: Request<B>) -> Self::Future {
         match req.uri().path() {
             "/live" => Box::pin(future::ok(Self::live_rsp())),
-            "/ready" => Box::pin(future::ok(self.ready_rsp())),
+            "/ready" => {
+                let ready = self.ready.clone();
+                let drain = self.drain.clone();
+                Box::pin(async move {
+                    let rsp = readiness::serve(ready, drain, req)
+                        .await
+                        .unwrap_or_else(|error| {
+                            tracing::error!(%error, "Failed to describe readiness");
+                            Self::internal_error_rsp(error)
+                        });
+                    Ok(rsp)
+                })
+            }
+            "/identity" => {
+                let rsp = match self.identity.as_ref() {
+                    Some(id) => identity::serve(id.crt_key(), req).unwrap_or_else(|error| {
+                        tracing::error!(%error, "Failed to describe identity");
+                        Self::internal_error_rsp(error)
+                    }),
+                    None => Self::not_found(),
+                };
+                Box::pin(future::ok(rsp))
+            }
             "/metrics" => {
                 let rsp = self.metrics.serve(req).unwrap_or_else(|error| {
                     ::tracing::error!(%error, "Failed to format metrics");
@@ -195,6 +263,54 @@ where
                     Box::pin(future::ok(Self::forbidden_not_localhost()))
                 }
             }
+            "/proxy-cache-flush" => {
+                if Self::client_is_localhost(&req) {
+                    let rsp = cache::serve(&self.caches, req).unwrap_or_else(|error| {
+                        tracing::error!(%error, "Failed to flush caches");
+                        Self::internal_error_rsp(error)
+                    });
+                    Box::pin(future::ok(rsp))
+                } else {
+                    Box::pin(future::ok(Self::forbidden_not_localhost()))
+                }
+            }
+            "/protocol-hints" => {
+                if !self.protocol_hints.is_enabled() {
+                    Box::pin(future::ok(Self::not_found()))
+                } else if Self::client_is_localhost(&req) {
+                    let rsp =
+                        protocol_hints::serve(&self.protocol_hints, req).unwrap_or_else(|error| {
+                            tracing::error!(%error, "Failed to serve protocol hints");
+                            Self::internal_error_rsp(error)
+                        });
+                    Box::pin(future::ok(rsp))
+                } else {
+                    Box::pin(future::ok(Self::forbidden_not_localhost()))
+                }
+            }
+            "/connections" => {
+                if Self::client_is_localhost(&req) {
+                    let rsp = connections::serve(&self.connections, req).unwrap_or_else(|error| {
+                        tracing::error!(%error, "Failed to describe connections");
+                        Self::internal_error_rsp(error)
+                    });
+                    Box::pin(future::ok(rsp))
+                } else {
+                    Box::pin(future::ok(Self::forbidden_not_localhost()))
+                }
+            }
+            "/drain" | "/undrain" => {
+                if req.method() == http::Method::POST {
+                    if Self::client_is_localhost(&req) {
+                        let draining = req.uri().path() == "/drain";
+                        Box::pin(future::ok(self.drain(draining)))
+                    } else {
+                        Box::pin(future::ok(Self::forbidden_not_localhost()))
+                    }
+                } else {
+                    Box::pin(future::ok(Self::method_not_allowed()))
+                }
+            }
             "/shutdown" => {
                 if req.method() == http::Method::POST {
                     if Self::client_is_localhost(&req) {
@@ -237,12 +353,23 @@ mod tests {
 
     #[tokio::test]
     async fn ready_when_latches_dropped() {
-        let (r, l0) = Readiness::new();
+        let (r, l0) = Readiness::new("identity");
         let l1 = l0.clone();
 
         let (_, t) = trace::Settings::default().build();
         let (s, _) = mpsc::unbounded_channel();
-        let admin = Admin::new((), r, s, t);
+        let (d, _drain) = Drain::new(Duration::from_secs(10));
+        let admin = Admin::new(
+            (),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            r,
+            d.clone(),
+            s,
+            t,
+            None,
+        );
         macro_rules! call {
             () => {{
                 let r = Request::builder()
@@ -262,5 +389,11 @@ mod tests {
 
         drop(l1);
         assert_eq!(call!().status(), StatusCode::OK);
+
+        assert!(d.set_draining(true));
+        assert_eq!(call!().status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        assert!(d.set_draining(false));
+        assert_eq!(call!().status(), StatusCode::OK);
     }
 }
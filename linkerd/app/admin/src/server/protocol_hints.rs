@@ -0,0 +1,45 @@
+use hyper::Body;
+use linkerd_app_core::{protocol_hints::ProtocolHints, Error};
+
+/// Describes the outbound protocol hints that have been learned from protocol
+/// detection as JSON, or clears them.
+pub(super) fn serve<B>(
+    hints: &ProtocolHints,
+    req: http::Request<B>,
+) -> Result<http::Response<Body>, Error> {
+    if req.method() == http::Method::GET {
+        let hints = hints
+            .hints()
+            .into_iter()
+            .map(|hint| {
+                serde_json::json!({
+                    "dst": hint.dst.to_string(),
+                    "protocol": hint.protocol.to_string(),
+                    "expires_in_seconds": hint.expires_in.as_secs(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let body = serde_json::to_string_pretty(&hints)?;
+        return Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(body.into())
+            .expect("builder with known status code must not fail"));
+    }
+
+    if req.method() == http::Method::DELETE {
+        let cleared = hints.clear();
+        tracing::info!(cleared, "Cleared protocol hints");
+        return Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .header(http::header::CONTENT_TYPE, "text/plain")
+            .body(format!("cleared {} hints\n", cleared).into())
+            .expect("builder with known status code must not fail"));
+    }
+
+    Ok(http::Response::builder()
+        .status(http::StatusCode::METHOD_NOT_ALLOWED)
+        .header("allow", "GET, DELETE")
+        .body(Body::empty())
+        .expect("builder with known status code must not fail"))
+}
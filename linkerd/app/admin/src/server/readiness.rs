@@ -1,30 +1,134 @@
-use std::sync::{Arc, Weak};
+use super::DrainHandle;
+use futures::future;
+use hyper::Body;
+use linkerd_app_core::Error;
+use std::{
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
+};
+use tokio::{sync::watch, time};
 
 /// Tracks the processes's readiness to serve traffic.
 ///
-/// Once `is_ready()` returns true, it will never return false.
+/// The process is ready once the latches of every required component have been
+/// released. Components should be registered before any latch is released, so
+/// that, once `is_ready()` returns true, it will never return false.
+///
+/// Optional components do not gate readiness, but their state is reported and
+/// may be waited upon.
 #[derive(Clone, Debug)]
-pub struct Readiness(Weak<()>);
+pub struct Readiness(Arc<Inner>);
 
-/// When all latches are dropped, the process is considered ready.
+/// When all latches are dropped, the component is considered ready.
 #[derive(Clone, Debug)]
-pub struct Latch(Arc<()>);
+pub struct Latch {
+    component: Option<Arc<()>>,
+    readiness: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Component {
+    name: &'static str,
+    required: bool,
+    latch: Weak<()>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    components: Mutex<Vec<Component>>,
+    released_tx: watch::Sender<()>,
+    released_rx: watch::Receiver<()>,
+}
 
 impl Readiness {
-    pub fn new() -> (Readiness, Latch) {
-        let r = Arc::new(());
-        (Readiness(Arc::downgrade(&r)), Latch(r))
+    /// Creates a new readiness tracker with a single component.
+    pub fn new(component: &'static str) -> (Readiness, Latch) {
+        let (released_tx, released_rx) = watch::channel(());
+        let readiness = Readiness(Arc::new(Inner {
+            components: Mutex::new(Vec::new()),
+            released_tx,
+            released_rx,
+        }));
+        let latch = readiness.latch(component);
+        (readiness, latch)
+    }
+
+    /// Registers a component that must be ready before the process is
+    /// ready.
+    pub fn latch(&self, component: &'static str) -> Latch {
+        self.register(component, true)
+    }
+
+    /// Registers a component that does not gate the process's readiness.
+    pub fn optional(&self, component: &'static str) -> Latch {
+        self.register(component, false)
+    }
+
+    fn register(&self, name: &'static str, required: bool) -> Latch {
+        let latch = Arc::new(());
+        self.0
+            .components
+            .lock()
+            .expect("readiness lock poisoned")
+            .push(Component {
+                name,
+                required,
+                latch: Arc::downgrade(&latch),
+            });
+        Latch {
+            component: Some(latch),
+            readiness: self.0.clone(),
+        }
     }
 
     pub fn is_ready(&self) -> bool {
-        self.0.upgrade().is_none()
+        self.0
+            .components
+            .lock()
+            .expect("readiness lock poisoned")
+            .iter()
+            .all(|c| !c.required || c.is_ready())
+    }
+
+    /// Returns true if all components, including optional ones, are ready.
+    pub fn is_complete(&self) -> bool {
+        self.components().into_iter().all(|(_, ready)| ready)
+    }
+
+    /// Describes whether each component is ready.
+    pub fn components(&self) -> Vec<(&'static str, bool)> {
+        self.0
+            .components
+            .lock()
+            .expect("readiness lock poisoned")
+            .iter()
+            .map(|c| (c.name, c.is_ready()))
+            .collect()
+    }
+
+    /// Completes when all components, including optional ones, are ready.
+    pub async fn complete(&self) {
+        let mut released = self.0.released_rx.clone();
+        while !self.is_complete() {
+            if released.changed().await.is_err() {
+                return future::pending().await;
+            }
+        }
     }
 }
 
 /// ALways ready.
 impl Default for Readiness {
     fn default() -> Self {
-        Self::new().0
+        let (readiness, latch) = Self::new("default");
+        latch.release();
+        readiness
+    }
+}
+
+impl Component {
+    fn is_ready(&self) -> bool {
+        self.latch.upgrade().is_none()
     }
 }
 
@@ -34,3 +138,147 @@ impl Latch {
         drop(self);
     }
 }
+
+impl Drop for Latch {
+    fn drop(&mut self) {
+        // Release the component before notifying waiters so that they observe
+        // its readiness.
+        drop(self.component.take());
+        let _ = self.readiness.released_tx.send(());
+    }
+}
+
+/// Reports the process's readiness.
+///
+/// When the `wait` parameter is set (e.g. `wait=30s`), the response is
+/// withheld until all components, including optional ones, are ready or the
+/// wait elapses; and the response only succeeds if all components are ready.
+/// When the `format=json` parameter is set, the response describes the
+/// readiness of each component.
+pub(super) async fn serve<B>(
+    readiness: Readiness,
+    drain: DrainHandle,
+    req: http::Request<B>,
+) -> Result<http::Response<Body>, Error> {
+    let mut wait = None;
+    let mut json = false;
+    for param in req.uri().query().unwrap_or_default().split('&') {
+        let mut kv = param.splitn(2, '=');
+        match (kv.next(), kv.next()) {
+            (Some("wait"), Some(v)) => match parse_wait(v) {
+                Some(w) => wait = Some(w),
+                None => {
+                    return Ok(http::Response::builder()
+                        .status(http::StatusCode::BAD_REQUEST)
+                        .header(http::header::CONTENT_TYPE, "text/plain")
+                        .body("invalid wait duration\n".into())
+                        .expect("builder with known status code must not fail"))
+                }
+            },
+            (Some("format"), Some("json")) => json = true,
+            _ => {}
+        }
+    }
+
+    let ready = match wait {
+        Some(wait) => {
+            let _ = time::timeout(wait, readiness.complete()).await;
+            readiness.is_complete()
+        }
+        None => readiness.is_ready(),
+    };
+    let draining = drain.is_draining();
+    let ready = ready && !draining;
+    let status = if ready {
+        http::StatusCode::OK
+    } else {
+        http::StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    if json {
+        let components = readiness
+            .components()
+            .into_iter()
+            .map(|(name, ready)| (name.to_string(), serde_json::Value::Bool(ready)))
+            .collect::<serde_json::Map<_, _>>();
+        let body = serde_json::to_string_pretty(&serde_json::json!({
+            "ready": ready,
+            "draining": draining,
+            "components": components,
+        }))?;
+        return Ok(http::Response::builder()
+            .status(status)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(body.into())
+            .expect("builder with known status code must not fail"));
+    }
+
+    let body = if ready { "ready\n" } else { "not ready\n" };
+    Ok(http::Response::builder()
+        .status(status)
+        .header(http::header::CONTENT_TYPE, "text/plain")
+        .body(body.into())
+        .expect("builder with known status code must not fail"))
+}
+
+/// Parses a wait duration like `500ms`, `30s`, or `1m`. Durations without a
+/// unit are interpreted as seconds.
+fn parse_wait(s: &str) -> Option<Duration> {
+    let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (n, unit) = s.split_at(digits);
+    let n = n.parse::<u64>().ok()?;
+    match unit {
+        "ms" => Some(Duration::from_millis(n)),
+        "" | "s" => Some(Duration::from_secs(n)),
+        "m" => Some(Duration::from_secs(n * 60)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn waits_for_components() {
+        let (readiness, identity) = Readiness::new("identity");
+        let dst = readiness.optional("destination");
+        assert_eq!(
+            readiness.components(),
+            vec![("identity", false), ("destination", false)]
+        );
+
+        identity.release();
+        assert!(readiness.is_ready());
+        assert!(!readiness.is_complete());
+        assert!(
+            time::timeout(Duration::from_millis(10), readiness.complete())
+                .await
+                .is_err()
+        );
+
+        let ready = tokio::spawn({
+            let readiness = readiness.clone();
+            async move { readiness.complete().await }
+        });
+        dst.release();
+        time::timeout(Duration::from_secs(1), ready)
+            .await
+            .expect("readiness must complete")
+            .expect("task must not fail");
+        assert_eq!(
+            readiness.components(),
+            vec![("identity", true), ("destination", true)]
+        );
+    }
+
+    #[test]
+    fn parses_wait() {
+        assert_eq!(parse_wait("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_wait("30"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_wait("250ms"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_wait("2m"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_wait("2h"), None);
+        assert_eq!(parse_wait("s"), None);
+    }
+}
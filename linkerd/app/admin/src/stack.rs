@@ -1,8 +1,9 @@
 use linkerd_app_core::{
     classify,
     config::ServerConfig,
-    detect, drain, errors,
+    connections, detect, drain, errors,
     metrics::{self, FmtMetrics},
+    protocol_hints::ProtocolHints,
     proxy::{http, identity::LocalCrtKey},
     serve,
     svc::{self, ExtractParam, InsertParam, Param},
@@ -19,11 +20,22 @@ use tracing::debug;
 pub struct Config {
     pub server: ServerConfig,
     pub metrics_retain_idle: Duration,
+    /// The maximum number of distinct peer identities used to label
+    /// transport metrics by peer.
+    pub metrics_peer_identity_limit: usize,
+    /// How long the proxy waits, after a drain is requested via the admin
+    /// server, before it drains. The drain may be canceled in the meantime.
+    pub drain_grace_period: Duration,
 }
 
 pub struct Task {
     pub listen_addr: Local<ServerAddr>,
+    /// Released once the proxy's identity is provisioned.
     pub latch: crate::Latch,
+    /// Additional components may be registered before the proxy becomes
+    /// ready.
+    pub readiness: crate::Readiness,
+    pub drain: crate::Drain,
     pub serve: Pin<Box<dyn std::future::Future<Output = ()> + Send + 'static>>,
 }
 
@@ -65,6 +77,8 @@ impl Config {
         identity: Option<LocalCrtKey>,
         report: R,
         metrics: metrics::Proxy,
+        protocol_hints: ProtocolHints,
+        connections: connections::Registry,
         trace: trace::Handle,
         drain: drain::Watch,
         shutdown: mpsc::UnboundedSender<()>,
@@ -76,17 +90,33 @@ impl Config {
     {
         let (listen_addr, listen) = bind.bind(&self.server)?;
 
-        let (ready, latch) = crate::server::Readiness::new();
-        let admin = crate::server::Admin::new(report, ready, shutdown, trace);
+        let (ready, latch) = crate::server::Readiness::new("identity");
+        let readiness = ready.clone();
+        let (drain_handle, admin_drain) = crate::server::Drain::new(self.drain_grace_period);
+        let admin = crate::server::Admin::new(
+            report,
+            metrics.cache.clone(),
+            protocol_hints,
+            connections,
+            ready,
+            drain_handle,
+            shutdown,
+            trace,
+            identity.clone(),
+        );
         let admin = svc::stack(move |_| admin.clone())
             .push(metrics.http_endpoint.to_layer::<classify::Response, _, Http>())
             .push_on_response(
                 svc::layers()
                     .push(metrics.http_errors.clone())
-                    .push(errors::layer())
+                    .push(errors::layer(Default::default()))
                     .push(http::BoxResponse::layer()),
             )
-            .push(http::NewServeHttp::layer(Default::default(), drain.clone()))
+            .push(http::NewServeHttp::layer(
+                Default::default(),
+                Default::default(),
+                drain.clone(),
+            ))
             .push_request_filter(
                 |(http, tcp): (
                     Result<Option<http::Version>, detect::DetectTimeoutError<_>>,
@@ -111,7 +141,10 @@ impl Config {
                                     sni,
                                 }) => {
                                     debug_assert!(false, "If we know the stream is non-mesh TLS, we should be able to prove its not HTTP.");
-                                    return Err(Error::from(UnexpectedSni(sni, tcp.client)));
+                                    return Err(match sni {
+                                        Some(sni) => Error::from(UnexpectedSni(sni, tcp.client)),
+                                        None => Error::from(NonHttpClient(tcp.client)),
+                                    });
                                 }
                             };
                             debug!(%version, "HTTP detection timed out; assuming HTTP");
@@ -120,9 +153,9 @@ impl Config {
                         // If the connection failed HTTP detection, check if we detected TLS for
                         // another target. This might indicate that the client is confused/stale.
                         Ok(None) => match tcp.tls {
-                            tls::ConditionalServerTls::Some(tls::ServerTls::Passthru { sni }) => {
-                                Err(UnexpectedSni(sni, tcp.client).into())
-                            }
+                            tls::ConditionalServerTls::Some(tls::ServerTls::Passthru {
+                                sni: Some(sni),
+                            }) => Err(UnexpectedSni(sni, tcp.client).into()),
                             _ => Err(NonHttpClient(tcp.client).into()),
                         },
                     }
@@ -144,10 +177,12 @@ impl Config {
             }))
             .into_inner();
 
-        let serve = Box::pin(serve::serve(listen, admin, drain.signaled()));
+        let serve = Box::pin(serve::serve(listen, admin, None, drain.signaled()));
         Ok(Task {
             listen_addr,
             latch,
+            readiness,
+            drain: admin_drain,
             serve,
         })
     }
@@ -200,6 +235,13 @@ impl<T> ExtractParam<Option<LocalCrtKey>, T> for TlsParams {
     }
 }
 
+impl<T> ExtractParam<tls::server::HandshakeLimit, T> for TlsParams {
+    #[inline]
+    fn extract_param(&self, _: &T) -> tls::server::HandshakeLimit {
+        tls::server::HandshakeLimit(None)
+    }
+}
+
 impl<T> InsertParam<tls::ConditionalServerTls, T> for TlsParams {
     type Target = (tls::ConditionalServerTls, T);
 
@@ -1,7 +1,7 @@
-use ipnet::IpNet;
-use linkerd_addr::Addr;
+use ipnet::{IpNet, Ipv4Net};
+use linkerd_addr::{canonical_ip, Addr, NameAddr};
 use linkerd_dns::{Name, Suffix};
-use std::{fmt, net::IpAddr, sync::Arc};
+use std::{fmt, net::IpAddr, str::FromStr, sync::Arc};
 
 #[derive(Clone, Debug, Default)]
 pub struct AddrMatch {
@@ -10,7 +10,30 @@ pub struct AddrMatch {
 }
 
 #[derive(Clone, Debug, Default)]
-pub struct NameMatch(Arc<Vec<Suffix>>);
+pub struct NameMatch(Arc<Vec<NameRule>>);
+
+/// A name pattern in a `NameMatch`, optionally constrained to a single port.
+///
+/// Rules are written as a domain suffix (`example.com`), which matches the name
+/// and all of its subdomains, or as a wildcard pattern (`*.internal.example.com`),
+/// where each `*` label matches exactly one label. Either form may be followed by
+/// `@<port>` so that it only matches addresses on that port.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NameRule {
+    pattern: NamePattern,
+    port: Option<u16>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum NamePattern {
+    Suffix(Suffix),
+    /// The pattern's labels, where `None` is a `*` label.
+    Wildcard(Vec<Option<String>>),
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("invalid name rule: {0}")]
+pub struct InvalidNameRule(String);
 
 #[derive(Clone, Debug, Default)]
 pub struct IpMatch(Arc<Vec<IpNet>>);
@@ -28,6 +51,16 @@ impl AddrMatch {
         }
     }
 
+    pub fn from_rules(
+        rules: impl IntoIterator<Item = NameRule>,
+        nets: impl IntoIterator<Item = IpNet>,
+    ) -> Self {
+        Self {
+            names: NameMatch::from_rules(rules),
+            nets: IpMatch::new(nets),
+        }
+    }
+
     pub fn names(&self) -> &NameMatch {
         &self.names
     }
@@ -39,7 +72,7 @@ impl AddrMatch {
     #[inline]
     pub fn matches(&self, addr: &Addr) -> bool {
         match addr {
-            Addr::Name(name) => self.names.matches(name.name()),
+            Addr::Name(name) => self.names.matches_addr(name),
             Addr::Socket(sa) => self.matches_ip(sa.ip()),
         }
     }
@@ -84,12 +117,25 @@ impl From<AddrMatch> for NameMatch {
 
 impl NameMatch {
     pub fn new(suffixes: impl IntoIterator<Item = Suffix>) -> Self {
-        Self(Arc::new(suffixes.into_iter().collect()))
+        Self::from_rules(suffixes.into_iter().map(NameRule::from))
     }
 
+    pub fn from_rules(rules: impl IntoIterator<Item = NameRule>) -> Self {
+        Self(Arc::new(rules.into_iter().collect()))
+    }
+
+    /// Matches a name without regard to its port, so port-qualified rules
+    /// never match.
     #[inline]
     pub fn matches(&self, name: &Name) -> bool {
-        self.0.iter().any(|sfx| sfx.contains(name))
+        self.0
+            .iter()
+            .any(|rule| rule.port.is_none() && rule.pattern.matches(name))
+    }
+
+    #[inline]
+    pub fn matches_addr(&self, addr: &NameAddr) -> bool {
+        self.0.iter().any(|rule| rule.matches(addr))
     }
 }
 
@@ -99,20 +145,127 @@ impl fmt::Display for NameMatch {
     }
 }
 
+// === impl NameRule ===
+
+impl NameRule {
+    pub fn matches(&self, addr: &NameAddr) -> bool {
+        self.port.map_or(true, |p| p == addr.port()) && self.pattern.matches(addr.name())
+    }
+}
+
+impl From<Suffix> for NameRule {
+    fn from(sfx: Suffix) -> Self {
+        Self {
+            pattern: NamePattern::Suffix(sfx),
+            port: None,
+        }
+    }
+}
+
+impl FromStr for NameRule {
+    type Err = InvalidNameRule;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidNameRule(s.to_string());
+
+        let (pattern, port) = match s.split_once('@') {
+            Some((pattern, port)) => (pattern, Some(port.parse().map_err(|_| invalid())?)),
+            None => (s, None),
+        };
+
+        let pattern = if pattern.split('.').any(|l| l == "*") {
+            // Validate the pattern as though each wildcard were a name label.
+            let labels = pattern.trim_end_matches('.').split('.');
+            let placeholder = labels
+                .clone()
+                .map(|l| if l == "*" { "x" } else { l })
+                .collect::<Vec<_>>()
+                .join(".");
+            Name::from_str(&placeholder).map_err(|_| invalid())?;
+            NamePattern::Wildcard(
+                labels
+                    .map(|l| Some(l.to_ascii_lowercase()).filter(|l| l != "*"))
+                    .collect(),
+            )
+        } else {
+            NamePattern::Suffix(Suffix::from_str(pattern).map_err(|_| invalid())?)
+        };
+
+        Ok(Self { pattern, port })
+    }
+}
+
+impl fmt::Display for NameRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.pattern {
+            NamePattern::Suffix(ref sfx) => fmt::Display::fmt(sfx, f)?,
+            NamePattern::Wildcard(ref labels) => {
+                for (i, label) in labels.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(".")?;
+                    }
+                    f.write_str(label.as_deref().unwrap_or("*"))?;
+                }
+            }
+        }
+        if let Some(port) = self.port {
+            write!(f, "@{}", port)?;
+        }
+        Ok(())
+    }
+}
+
+// === impl NamePattern ===
+
+impl NamePattern {
+    fn matches(&self, name: &Name) -> bool {
+        match self {
+            Self::Suffix(sfx) => sfx.contains(name),
+            Self::Wildcard(pattern) => {
+                let mut labels = name.without_trailing_dot().split('.');
+                pattern.iter().all(|p| match (p, labels.next()) {
+                    (_, None) => false,
+                    (None, Some(_)) => true,
+                    (Some(p), Some(l)) => p.eq_ignore_ascii_case(l),
+                }) && labels.next().is_none()
+            }
+        }
+    }
+}
+
 // === impl IpMatch ===
 
 impl IpMatch {
+    /// Networks of IPv4-mapped IPv6 addresses (e.g. `::ffff:10.0.0.0/104`) are
+    /// converted to the IPv4 networks they represent.
     pub fn new(nets: impl IntoIterator<Item = IpNet>) -> Self {
-        Self(Arc::new(nets.into_iter().collect()))
+        Self(Arc::new(
+            nets.into_iter().map(Self::canonical_net).collect(),
+        ))
     }
 
+    /// Matches IPv4-mapped IPv6 addresses by both their IPv6 and IPv4 forms,
+    /// so that addresses reported by dual-stack sockets match IPv4 networks.
     #[inline]
     pub fn matches(&self, addr: IpAddr) -> bool {
-        self.0.iter().any(|net| match (net, addr) {
-            (IpNet::V4(net), IpAddr::V4(ip)) => net.contains(&ip),
-            (IpNet::V6(net), IpAddr::V6(ip)) => net.contains(&ip),
-            _ => false,
-        })
+        let canonical = canonical_ip(addr);
+        self.0
+            .iter()
+            .any(|net| net.contains(&addr) || (canonical != addr && net.contains(&canonical)))
+    }
+
+    fn canonical_net(net: IpNet) -> IpNet {
+        if let IpNet::V6(v6) = net {
+            if v6.prefix_len() >= 96 {
+                if let IpAddr::V4(ip) = canonical_ip(v6.network().into()) {
+                    let prefix = v6.prefix_len() - 96;
+                    return Ipv4Net::new(ip, prefix)
+                        .expect("prefix must be valid")
+                        .into();
+                }
+            }
+        }
+        net
     }
 }
 
@@ -131,3 +284,101 @@ impl<T: fmt::Display> fmt::Debug for Display<'_, T> {
         fmt::Display::fmt(self.0, f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn name_match(rules: &[&str]) -> NameMatch {
+        NameMatch::from_rules(rules.iter().map(|r| NameRule::from_str(r).unwrap()))
+    }
+
+    fn name_addr(addr: &str) -> NameAddr {
+        NameAddr::from_str(addr).unwrap()
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        let names = name_match(&["*.internal.example.com", "api.*.example.org."]);
+        for (addr, expected) in &[
+            ("foo.internal.example.com:80", true),
+            ("FOO.internal.example.com:80", true),
+            ("internal.example.com:80", false),
+            ("foo.bar.internal.example.com:80", false),
+            ("api.us.example.org:443", true),
+            ("web.us.example.org:443", false),
+            ("api.example.org:443", false),
+        ] {
+            let addr = name_addr(addr);
+            assert_eq!(names.matches_addr(&addr), *expected, "{}", addr);
+            assert_eq!(names.matches(addr.name()), *expected, "{}", addr);
+        }
+    }
+
+    #[test]
+    fn matches_ports() {
+        let names = name_match(&["svc.cluster.local@8080", "*.example.com@443"]);
+        for (addr, expected) in &[
+            ("web.ns.svc.cluster.local:8080", true),
+            ("web.ns.svc.cluster.local:80", false),
+            ("www.example.com:443", true),
+            ("www.example.com:80", false),
+        ] {
+            let addr = name_addr(addr);
+            assert_eq!(names.matches_addr(&addr), *expected, "{}", addr);
+            // Port-qualified rules never match names without ports.
+            assert!(!names.matches(addr.name()), "{}", addr);
+        }
+    }
+
+    #[test]
+    fn parses_name_rules() {
+        for rule in &[".", "example.com", "*.example.com", "a.*.example.com@80"] {
+            assert_eq!(NameRule::from_str(rule).unwrap().to_string(), *rule);
+        }
+        for rule in &["", "x*.example.com", "example.com@", "example.com@http"] {
+            assert!(NameRule::from_str(rule).is_err(), "{}", rule);
+        }
+    }
+
+    fn ip_match(nets: &[&str]) -> IpMatch {
+        IpMatch::new(nets.iter().map(|n| IpNet::from_str(n).unwrap()))
+    }
+
+    #[test]
+    fn matches_dual_stack() {
+        let nets = ip_match(&["10.0.0.0/8", "fd00::/8"]);
+        for (ip, expected) in &[
+            ("10.1.2.3", true),
+            ("::ffff:10.1.2.3", true),
+            ("192.168.1.1", false),
+            ("::ffff:192.168.1.1", false),
+            ("fd00::1", true),
+            ("fe80::1", false),
+        ] {
+            let ip = IpAddr::from_str(ip).unwrap();
+            assert_eq!(nets.matches(ip), *expected, "{}", ip);
+        }
+    }
+
+    #[test]
+    fn matches_v4_mapped_networks() {
+        let nets = ip_match(&["::ffff:10.0.0.0/104"]);
+        assert!(nets.matches(IpAddr::from_str("10.1.2.3").unwrap()));
+        assert!(nets.matches(IpAddr::from_str("::ffff:10.1.2.3").unwrap()));
+        assert!(!nets.matches(IpAddr::from_str("11.1.2.3").unwrap()));
+    }
+
+    #[test]
+    fn matches_all_networks() {
+        let nets = ip_match(&["0.0.0.0/0", "::/0"]);
+        assert!(nets.matches(IpAddr::from_str("10.1.2.3").unwrap()));
+        assert!(nets.matches(IpAddr::from_str("::ffff:10.1.2.3").unwrap()));
+        assert!(nets.matches(IpAddr::from_str("2001:db8::1").unwrap()));
+
+        let v4 = ip_match(&["0.0.0.0/0"]);
+        assert!(v4.matches(IpAddr::from_str("::ffff:10.1.2.3").unwrap()));
+        assert!(!v4.matches(IpAddr::from_str("2001:db8::1").unwrap()));
+    }
+}
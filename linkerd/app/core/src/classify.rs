@@ -2,7 +2,11 @@ use crate::profiles;
 use linkerd_error::Error;
 use linkerd_http_classify as classify;
 pub use linkerd_http_classify::{CanClassify, NewClassify};
-use linkerd_proxy_http::HasH2Reason;
+use linkerd_proxy_http::{
+    deadline::DeadlineExceeded,
+    timeout::{IdleTimeout, PerTryTimeout},
+    HasH2Reason,
+};
 use linkerd_timeout::ResponseTimeout;
 use std::borrow::Cow;
 use tonic as grpc;
@@ -147,6 +151,12 @@ impl classify::ClassifyResponse for Response {
     fn error(self, err: &Error) -> Self::Class {
         let msg = if err.is::<ResponseTimeout>() {
             "timeout".into()
+        } else if err.is::<PerTryTimeout>() {
+            "per-try timeout".into()
+        } else if err.is::<IdleTimeout>() {
+            "idle timeout".into()
+        } else if err.is::<DeadlineExceeded>() {
+            "deadline exceeded".into()
         } else {
             h2_error(err).into()
         };
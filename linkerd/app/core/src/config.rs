@@ -1,15 +1,19 @@
 pub use crate::exp_backoff::ExponentialBackoff;
 use crate::{
+    errors, fd_pressure, http_tracing,
     proxy::http::{self, h1, h2},
+    request_id, request_limits,
     svc::Param,
     transport::{Keepalive, ListenAddr},
 };
+use linkerd_detect::{Sniffers, Sniffing};
 use std::time::Duration;
 
 #[derive(Clone, Debug)]
 pub struct ServerConfig {
     pub addr: ListenAddr,
     pub keepalive: Keepalive,
+    pub h1_settings: h1::ServerSettings,
     pub h2_settings: h2::Settings,
 }
 
@@ -31,6 +35,30 @@ pub struct ProxyConfig {
     pub dispatch_timeout: Duration,
     pub max_in_flight_requests: usize,
     pub detect_protocol_timeout: Duration,
+
+    /// Whether HTTP responses describe how long requests waited in the
+    /// proxy's buffers in an `l5d-queue-ms` header. Intended for debugging.
+    pub queue_time_header: bool,
+
+    /// Whether requests failed by fail-fast suggest that clients retry after
+    /// the dispatch timeout with a `Retry-After` header.
+    pub fail_fast_retry_after: bool,
+
+    /// Configures how responses describe proxy errors.
+    pub error_responses: errors::RespondConfig,
+
+    /// Limits the size of HTTP request heads accepted by the server.
+    pub request_limits: request_limits::Limits,
+
+    /// Identifies HTTP requests in the proxy's logs, if configured.
+    pub request_id: Option<request_id::Config>,
+
+    /// Stops listeners from accepting connections while too many file
+    /// descriptors are open, if configured.
+    pub fd_pressure: fd_pressure::FdPressure,
+
+    /// The mesh-specific attributes added to the proxy's spans.
+    pub trace_attributes: http_tracing::Attributes,
 }
 
 // === impl ProxyConfig ===
@@ -39,6 +67,21 @@ impl ProxyConfig {
     pub fn detect_http(&self) -> linkerd_detect::Config<http::DetectHttp> {
         linkerd_detect::Config::from_timeout(self.detect_protocol_timeout)
     }
+
+    /// Detects HTTP, falling back to labeling non-HTTP connections with any
+    /// of the builtin sniffed protocols.
+    pub fn detect_protocols(&self) -> linkerd_detect::Config<Sniffing<http::DetectHttp>> {
+        let linkerd_detect::Config {
+            detect,
+            capacity,
+            timeout,
+        } = self.detect_http();
+        linkerd_detect::Config {
+            detect: Sniffing::new(detect, Sniffers::builtin()),
+            capacity,
+            timeout,
+        }
+    }
 }
 
 // === impl ServerConfig ===
@@ -0,0 +1,346 @@
+use crate::{
+    addr, identity, io,
+    metrics::Direction,
+    svc::{self, NewService, Param},
+    transport::{ClientAddr, OrigDstAddr, Remote},
+};
+use futures::prelude::*;
+use linkerd_errno::Errno;
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+/// Tracks the connections accepted by the proxy's inbound and outbound
+/// listeners while they are open, so that they may be described by the admin
+/// server.
+///
+/// Connections are identified by their direction and the client's address.
+#[derive(Clone, Debug, Default)]
+pub struct Registry(Arc<Mutex<HashMap<(Direction, SocketAddr), Arc<Conn>>>>);
+
+/// Describes an open connection.
+#[derive(Clone, Debug)]
+pub struct Connection {
+    pub direction: Direction,
+    pub peer: SocketAddr,
+    pub orig_dst: SocketAddr,
+    pub protocol: Option<String>,
+    pub identity: Option<identity::Name>,
+    pub age: Duration,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+}
+
+/// Describes what the proxy has learned about a connection after accepting
+/// it.
+#[derive(Clone, Debug, Default)]
+pub struct Description {
+    pub protocol: Option<String>,
+    pub identity: Option<identity::Name>,
+}
+
+/// Records a connection's I/O and stops tracking it when dropped.
+#[derive(Debug)]
+pub struct Tracked {
+    conn: Arc<Conn>,
+    registry: Registry,
+}
+
+pub type TrackedIo<I> = io::SensorIo<I, Tracked>;
+
+/// Describes the connections served by an inner stack.
+#[derive(Clone, Debug)]
+pub struct NewDescribe<F, N> {
+    inner: N,
+    describe: F,
+    direction: Direction,
+    registry: Registry,
+}
+
+#[derive(Clone, Debug)]
+pub struct Describe<S> {
+    inner: S,
+    description: Description,
+    direction: Direction,
+    registry: Registry,
+}
+
+#[derive(Debug)]
+struct Conn {
+    direction: Direction,
+    peer: SocketAddr,
+    orig_dst: SocketAddr,
+    opened_at: Instant,
+    description: Mutex<Description>,
+    read_bytes: AtomicU64,
+    write_bytes: AtomicU64,
+}
+
+// === impl Registry ===
+
+impl Registry {
+    /// Tracks each connection accepted by a listener until its I/O is dropped.
+    pub fn track<A, I>(
+        &self,
+        direction: Direction,
+        listen: impl Stream<Item = io::Result<(A, I)>>,
+    ) -> impl Stream<Item = io::Result<(A, TrackedIo<I>)>>
+    where
+        A: Param<Remote<ClientAddr>> + Param<OrigDstAddr>,
+    {
+        let registry = self.clone();
+        listen.map_ok(move |(addrs, io)| {
+            let Remote(ClientAddr(peer)) = addrs.param();
+            let OrigDstAddr(orig_dst) = addrs.param();
+            let tracked = registry.open(direction, peer, orig_dst);
+            (addrs, io::SensorIo::new(io, tracked))
+        })
+    }
+
+    /// Returns a layer that records a description of each connection served
+    /// by the inner stack, as determined from its target.
+    pub fn layer_describe<F, N>(
+        &self,
+        direction: Direction,
+        describe: F,
+    ) -> impl svc::Layer<N, Service = NewDescribe<F, N>> + Clone
+    where
+        F: Clone,
+    {
+        let registry = self.clone();
+        svc::layer::mk(move |inner| NewDescribe {
+            inner,
+            describe: describe.clone(),
+            direction,
+            registry: registry.clone(),
+        })
+    }
+
+    /// Returns up to `limit` open connections, oldest first.
+    pub fn connections(&self, limit: usize) -> Vec<Connection> {
+        let now = Instant::now();
+        let mut conns = self
+            .0
+            .lock()
+            .values()
+            .map(|conn| {
+                let Description { protocol, identity } = conn.description.lock().clone();
+                Connection {
+                    direction: conn.direction,
+                    peer: conn.peer,
+                    orig_dst: conn.orig_dst,
+                    protocol,
+                    identity,
+                    age: now.saturating_duration_since(conn.opened_at),
+                    read_bytes: conn.read_bytes.load(Ordering::Relaxed),
+                    write_bytes: conn.write_bytes.load(Ordering::Relaxed),
+                }
+            })
+            .collect::<Vec<_>>();
+        conns.sort_by(|a, b| b.age.cmp(&a.age));
+        conns.truncate(limit);
+        conns
+    }
+
+    /// Returns the number of open connections.
+    pub fn len(&self) -> usize {
+        self.0.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn open(&self, direction: Direction, peer: SocketAddr, orig_dst: SocketAddr) -> Tracked {
+        let conn = Arc::new(Conn {
+            direction,
+            peer,
+            orig_dst,
+            opened_at: Instant::now(),
+            description: Default::default(),
+            read_bytes: AtomicU64::new(0),
+            write_bytes: AtomicU64::new(0),
+        });
+        self.0.lock().insert((direction, peer), conn.clone());
+        Tracked {
+            conn,
+            registry: self.clone(),
+        }
+    }
+
+    fn describe(&self, direction: Direction, peer: SocketAddr, description: &Description) {
+        if let Some(conn) = self.0.lock().get(&(direction, peer)) {
+            let mut desc = conn.description.lock();
+            if description.protocol.is_some() {
+                desc.protocol = description.protocol.clone();
+            }
+            if description.identity.is_some() {
+                desc.identity = description.identity.clone();
+            }
+        }
+    }
+}
+
+// === impl Description ===
+
+impl Description {
+    /// Describes a connection by its protocol alone.
+    pub fn from_protocol(protocol: impl ToString) -> Self {
+        Self {
+            protocol: Some(protocol.to_string()),
+            identity: None,
+        }
+    }
+}
+
+// === impl Tracked ===
+
+impl io::Sensor for Tracked {
+    fn record_read(&mut self, sz: usize) {
+        self.conn.read_bytes.fetch_add(sz as u64, Ordering::Relaxed);
+    }
+
+    fn record_write(&mut self, sz: usize) {
+        self.conn
+            .write_bytes
+            .fetch_add(sz as u64, Ordering::Relaxed);
+    }
+
+    fn record_close(&mut self, _: Option<Errno>) {}
+
+    fn record_error<T>(&mut self, op: io::Poll<T>) -> io::Poll<T> {
+        op
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        let key = (self.conn.direction, self.conn.peer);
+        let mut conns = self.registry.0.lock();
+        // A new connection from the same client address may have replaced
+        // this one.
+        if conns
+            .get(&key)
+            .map_or(false, |c| Arc::ptr_eq(c, &self.conn))
+        {
+            conns.remove(&key);
+        }
+    }
+}
+
+// === impl NewDescribe ===
+
+impl<T, F, N> NewService<T> for NewDescribe<F, N>
+where
+    F: Fn(&T) -> Description,
+    N: NewService<T>,
+{
+    type Service = Describe<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let description = (self.describe)(&target);
+        Describe {
+            inner: self.inner.new_service(target),
+            description,
+            direction: self.direction,
+            registry: self.registry.clone(),
+        }
+    }
+}
+
+// === impl Describe ===
+
+impl<I, S> svc::Service<I> for Describe<S>
+where
+    I: io::PeerAddr,
+    S: svc::Service<I>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, io: I) -> Self::Future {
+        // Accepted connections are tracked by their canonical client
+        // address.
+        if let Ok(peer) = io.peer_addr() {
+            let peer = addr::canonical_socket_addr(peer);
+            self.registry
+                .describe(self.direction, peer, &self.description);
+        }
+        self.inner.call(io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use io::{AsyncReadExt, AsyncWriteExt};
+
+    #[derive(Clone, Debug)]
+    struct Accepted {
+        client: SocketAddr,
+        orig_dst: SocketAddr,
+    }
+
+    impl Param<Remote<ClientAddr>> for Accepted {
+        fn param(&self) -> Remote<ClientAddr> {
+            Remote(ClientAddr(self.client))
+        }
+    }
+
+    impl Param<OrigDstAddr> for Accepted {
+        fn param(&self) -> OrigDstAddr {
+            OrigDstAddr(self.orig_dst)
+        }
+    }
+
+    #[tokio::test]
+    async fn tracks_connections() {
+        let registry = Registry::default();
+        let accepted = Accepted {
+            client: ([10, 0, 0, 2], 51234).into(),
+            orig_dst: ([10, 0, 0, 1], 8080).into(),
+        };
+        let (io, mut client) = io::duplex(64);
+        let listen = registry.track(
+            Direction::In,
+            stream::iter(vec![Ok((accepted.clone(), io))]),
+        );
+        futures::pin_mut!(listen);
+        let (_, mut io) = listen.next().await.unwrap().unwrap();
+
+        client.write_all(b"hello").await.unwrap();
+        let mut buf = [0u8; 5];
+        io.read_exact(&mut buf).await.unwrap();
+
+        registry.describe(
+            Direction::In,
+            accepted.client,
+            &Description::from_protocol("HTTP/1"),
+        );
+        let conns = registry.connections(10);
+        assert_eq!(conns.len(), 1);
+        assert_eq!(conns[0].peer, accepted.client);
+        assert_eq!(conns[0].orig_dst, accepted.orig_dst);
+        assert_eq!(conns[0].protocol.as_deref(), Some("HTTP/1"));
+        assert_eq!(conns[0].read_bytes, 5);
+        assert_eq!(conns[0].write_bytes, 0);
+        assert!(registry.connections(0).is_empty());
+
+        drop(io);
+        assert!(registry.is_empty());
+    }
+}
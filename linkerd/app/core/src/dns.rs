@@ -6,7 +6,17 @@ use std::time::Duration;
 pub struct Config {
     pub min_ttl: Option<Duration>,
     pub max_ttl: Option<Duration>,
+    /// Overrides `min_ttl` for negative responses (e.g. NXDOMAIN).
+    pub negative_min_ttl: Option<Duration>,
+    /// Overrides `max_ttl` for negative responses (e.g. NXDOMAIN).
+    pub negative_max_ttl: Option<Duration>,
+    /// The number of lookups cached by the resolver. When zero, every
+    /// resolution queries the nameservers.
+    pub cache_size: usize,
     pub resolv_conf_path: PathBuf,
+    pub servers: Servers,
+    /// Names with each suffix are resolved with a dedicated set of servers.
+    pub suffix_servers: Vec<(Suffix, Servers)>,
 }
 
 pub struct Dns {
@@ -17,19 +27,28 @@ pub struct Dns {
 
 impl Config {
     pub fn build(self) -> Dns {
-        let resolver =
-            Resolver::from_system_config_with(&self).expect("system DNS config must be valid");
+        let resolver = self.suffix_servers.iter().fold(
+            self.resolver(&self.servers),
+            |resolver, (suffix, servers)| {
+                resolver.with_suffix(suffix.clone(), self.resolver(servers))
+            },
+        );
         Dns { resolver }
     }
+
+    fn resolver(&self, servers: &Servers) -> Resolver {
+        Resolver::from_servers_with(servers, self).expect("system DNS config must be valid")
+    }
 }
 
 impl ConfigureResolver for Config {
     /// Modify a `trust-dns-resolver::config::ResolverOpts` to reflect
-    /// the configured minimum and maximum DNS TTL values.
+    /// the configured minimum and maximum DNS TTL values and cache size.
     fn configure_resolver(&self, opts: &mut ResolverOpts) {
         opts.positive_min_ttl = self.min_ttl;
         opts.positive_max_ttl = self.max_ttl;
-        opts.negative_min_ttl = self.min_ttl;
-        opts.negative_max_ttl = self.max_ttl;
+        opts.negative_min_ttl = self.negative_min_ttl.or(self.min_ttl);
+        opts.negative_max_ttl = self.negative_max_ttl.or(self.max_ttl);
+        opts.cache_size = self.cache_size;
     }
 }
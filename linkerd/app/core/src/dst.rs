@@ -1,5 +1,5 @@
 use super::classify;
-use crate::profiles;
+use crate::{http_cache, idempotency, profiles, svc::Param};
 use linkerd_http_classify::CanClassify;
 use linkerd_proxy_http::timeout;
 use std::time::Duration;
@@ -21,8 +21,34 @@ impl CanClassify for Route {
     }
 }
 
+impl Param<profiles::http::Route> for Route {
+    fn param(&self) -> profiles::http::Route {
+        self.route.clone()
+    }
+}
+
+impl Param<Option<idempotency::Ttl>> for Route {
+    fn param(&self) -> Option<idempotency::Ttl> {
+        self.route.idempotency_ttl().map(idempotency::Ttl)
+    }
+}
+
+impl Param<http_cache::Cacheable> for Route {
+    fn param(&self) -> http_cache::Cacheable {
+        http_cache::Cacheable(self.route.cache_responses())
+    }
+}
+
 impl timeout::HasTimeout for Route {
     fn timeout(&self) -> Option<Duration> {
         self.route.timeout()
     }
+
+    fn per_try_timeout(&self) -> Option<Duration> {
+        self.route.per_try_timeout()
+    }
+
+    fn idle_timeout(&self) -> Option<Duration> {
+        self.route.idle_timeout()
+    }
 }
@@ -1,11 +1,22 @@
+use crate::request_id::{self, RequestId};
+use bytes::Bytes;
 use http::{header::HeaderValue, StatusCode};
+use linkerd_addr::Addr;
 use linkerd_errno::Errno;
 use linkerd_error::Error;
 use linkerd_error_metrics::{self as error_metrics, RecordErrorLayer, Registry};
 use linkerd_error_respond as respond;
 pub use linkerd_error_respond::RespondLayer;
+use linkerd_http_ext_authz::AuthorizationFailed;
+use linkerd_http_jwt::InvalidToken;
+use linkerd_load_shed::Shed;
 use linkerd_metrics::{metrics, Counter, FmtLabels, FmtMetrics};
-use linkerd_proxy_http::{ClientHandle, HasH2Reason};
+use linkerd_proxy_http::{
+    deadline::DeadlineExceeded,
+    timeout::{IdleTimeout, PerTryTimeout},
+    ClientHandle, HasH2Reason,
+};
+use linkerd_service_profiles::DiscoveryRejected;
 use linkerd_timeout::{FailFastError, ResponseTimeout};
 use linkerd_tls as tls;
 use pin_project::pin_project;
@@ -18,6 +29,14 @@ use tracing::{debug, warn};
 
 pub const L5D_PROXY_ERROR: &str = "l5d-proxy-error";
 
+/// Marks responses synthesized by the proxy with the kind of error that
+/// caused them, so that they can be distinguished from application errors.
+pub const L5D_ERR: &str = "l5d-err";
+
+/// A request header that, when set by the client, is used to correlate error
+/// responses with the proxy's logs.
+const REQUEST_ID: &str = "x-request-id";
+
 metrics! {
     inbound_http_errors_total: Counter {
         "The total number of inbound HTTP requests that could not be processed due to a proxy error."
@@ -28,8 +47,27 @@ metrics! {
     }
 }
 
-pub fn layer() -> respond::RespondLayer<NewRespond> {
-    respond::RespondLayer::new(NewRespond(()))
+pub fn layer(config: RespondConfig) -> respond::RespondLayer<NewRespond> {
+    respond::RespondLayer::new(NewRespond(config))
+}
+
+/// Classifies an error by the most specific error in its chain of sources.
+pub fn category(error: &(dyn std::error::Error + 'static)) -> Category {
+    LabelError::reason(error).category()
+}
+
+/// Configures how responses are synthesized for proxy errors.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RespondConfig {
+    /// Whether non-gRPC error responses include a JSON body describing the
+    /// error's kind, the request's logical destination, and a request ID that
+    /// is also logged by the proxy. The body is only included when the
+    /// request's `accept` header permits JSON.
+    pub json_body: bool,
+
+    /// Whether error responses include an `l5d-err` header describing the
+    /// error's kind.
+    pub error_header: bool,
 }
 
 #[derive(Clone)]
@@ -57,22 +95,73 @@ pub struct HttpError {
 pub enum Reason {
     DispatchTimeout,
     ResponseTimeout,
+    PerTryTimeout,
+    IdleTimeout,
+    DeadlineExceeded,
     IdentityRequired,
+    IdentityMismatch,
+    Unauthenticated,
+    ExtAuthzFailed,
+    Unauthorized,
     Io(Option<Errno>),
+    ConnectTimeout,
+    Tls,
+    Protocol,
+    Discovery,
     FailFast,
+    LoadShed,
     GatewayLoop,
     NotFound,
+    TooManyHeaders,
+    HeaderTooLarge,
+    HeadersTooLarge,
+    UriTooLong,
+    QuotaExceeded,
+    Unexpected,
+}
+
+/// A coarse classification of proxy errors.
+///
+/// Errors are classified by the most specific error in their chain of sources,
+/// so that errors remain classifiable after being boxed by inner layers. The
+/// category is recorded in error metrics and logs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Category {
+    /// A connection could not be established or was lost.
+    Connect,
+    /// A TLS handshake failed or the peer's identity could not be verified.
+    Tls,
+    /// A peer violated the HTTP protocol or a request was malformed.
+    Protocol,
+    /// A request was denied by policy.
+    Policy,
+    /// A destination could not be discovered.
+    Discovery,
+    /// A request or response did not complete in time.
+    Timeout,
+    /// A service was unavailable or overloaded.
+    Unavailable,
     Unexpected,
 }
 
 #[derive(Copy, Clone, Debug)]
-pub struct NewRespond(());
+pub struct NewRespond(RespondConfig);
 
 #[derive(Clone, Debug)]
 pub struct Respond {
     version: http::Version,
+    /// Whether gRPC response bodies may carry trailers describing stream
+    /// failures.
     is_grpc: bool,
+    /// The content-type of a gRPC (or gRPC-Web) request, so that proxy errors
+    /// are described with a gRPC status rather than an HTTP status.
+    grpc_content_type: Option<HeaderValue>,
     client: Option<ClientHandle>,
+    config: RespondConfig,
+    /// Whether the error response includes a JSON body.
+    json_body: bool,
+    request_id: Option<HeaderValue>,
+    dst: Option<Addr>,
 }
 
 #[pin_project(project = ResponseBodyProj)]
@@ -83,23 +172,35 @@ pub enum ResponseBody<B> {
         inner: B,
         trailers: Option<http::HeaderMap>,
     },
+    /// A body synthesized to describe a proxy error.
+    Error(Option<Bytes>),
+}
+
+/// Data from either an inner response body or a synthesized error body.
+pub enum ResponseData<D> {
+    Inner(D),
+    Error(Bytes),
 }
 
 const GRPC_CONTENT_TYPE: &str = "application/grpc";
+const GRPC_WEB_CONTENT_TYPE: &str = "application/grpc-web";
 
 impl<B: hyper::body::HttpBody> hyper::body::HttpBody for ResponseBody<B>
 where
     B::Error: Into<Error>,
 {
-    type Data = B::Data;
+    type Data = ResponseData<B::Data>;
     type Error = B::Error;
 
     fn poll_data(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
-        match self.project() {
+        let data = match self.project() {
             ResponseBodyProj::NonGrpc(inner) => inner.poll_data(cx),
+            ResponseBodyProj::Error(body) => {
+                return Poll::Ready(body.take().map(|b| Ok(ResponseData::Error(b))))
+            }
             ResponseBodyProj::Grpc { inner, trailers } => {
                 // should not be calling poll_data if we have set trailers derived from an error
                 assert!(trailers.is_none());
@@ -115,7 +216,8 @@ where
                     data => data,
                 }
             }
-        }
+        };
+        data.map(|d| d.map(|d| d.map(ResponseData::Inner)))
     }
 
     fn poll_trailers(
@@ -126,8 +228,12 @@ where
             ResponseBodyProj::NonGrpc(inner) => inner.poll_trailers(cx),
             ResponseBodyProj::Grpc { inner, trailers } => match trailers.take() {
                 Some(t) => Poll::Ready(Ok(Some(t))),
+                // A failure to read the trailers is not replaced with a
+                // synthesized `grpc-status`, since that would misrepresent the
+                // server's response. It's surfaced as a stream error instead.
                 None => inner.poll_trailers(cx),
             },
+            ResponseBodyProj::Error(_) => Poll::Ready(Ok(None)),
         }
     }
 
@@ -135,6 +241,7 @@ where
         match self {
             Self::NonGrpc(inner) => inner.is_end_stream(),
             Self::Grpc { inner, trailers } => trailers.is_none() && inner.is_end_stream(),
+            Self::Error(body) => body.is_none(),
         }
     }
 
@@ -142,6 +249,32 @@ where
         match self {
             Self::NonGrpc(inner) => inner.size_hint(),
             Self::Grpc { inner, .. } => inner.size_hint(),
+            Self::Error(body) => {
+                http_body::SizeHint::with_exact(body.as_ref().map_or(0, |b| b.len() as u64))
+            }
+        }
+    }
+}
+
+impl<D: bytes::Buf> bytes::Buf for ResponseData<D> {
+    fn remaining(&self) -> usize {
+        match self {
+            Self::Inner(d) => d.remaining(),
+            Self::Error(b) => b.remaining(),
+        }
+    }
+
+    fn chunk(&self) -> &[u8] {
+        match self {
+            Self::Inner(d) => d.chunk(),
+            Self::Error(b) => b.chunk(),
+        }
+    }
+
+    fn advance(&mut self, cnt: usize) {
+        match self {
+            Self::Inner(d) => d.advance(cnt),
+            Self::Error(b) => b.advance(cnt),
         }
     }
 }
@@ -162,23 +295,50 @@ impl<ReqB, RspB: Default + hyper::body::HttpBody>
         let client = req.extensions().get::<ClientHandle>().cloned();
         debug_assert!(client.is_some(), "Missing client handle");
 
+        let config = self.0;
+        let request_id = req
+            .extensions()
+            .get::<RequestId>()
+            .map(|RequestId(id)| id.clone())
+            .or_else(|| req.headers().get(REQUEST_ID).cloned());
+        let json_body = config.json_body && accepts_json(req.headers());
+        // The destination is only described in JSON error bodies, so avoid
+        // determining it otherwise.
+        let dst = if json_body { logical_dst(req) } else { None };
+
+        let content_type_matches = |prefix: &str| {
+            req.headers()
+                .get(http::header::CONTENT_TYPE)
+                .filter(|v| v.to_str().map(|s| s.starts_with(prefix)).unwrap_or(false))
+                .cloned()
+        };
+
         match req.version() {
             http::Version::HTTP_2 => {
-                let is_grpc = req
-                    .headers()
-                    .get(http::header::CONTENT_TYPE)
-                    .and_then(|v| v.to_str().ok().map(|s| s.starts_with(GRPC_CONTENT_TYPE)))
-                    .unwrap_or(false);
+                let grpc_content_type = content_type_matches(GRPC_CONTENT_TYPE);
                 Respond {
-                    is_grpc,
+                    is_grpc: grpc_content_type.is_some(),
+                    grpc_content_type,
                     client,
                     version: http::Version::HTTP_2,
+                    config,
+                    json_body,
+                    request_id,
+                    dst,
                 }
             }
+            // gRPC-Web may be used over HTTP/1.1, though its trailers are
+            // encoded in the response body, so only errors that occur before
+            // the response is returned can be described.
             version => Respond {
                 version,
                 client,
                 is_grpc: false,
+                grpc_content_type: content_type_matches(GRPC_WEB_CONTENT_TYPE),
+                config,
+                json_body,
+                request_id,
+                dst,
             },
         }
     }
@@ -205,7 +365,16 @@ impl<RspB: Default + hyper::body::HttpBody> respond::Respond<http::Response<RspB
                         debug!("Missing client address");
                         ([0, 0, 0, 0], 0).into()
                     });
-                warn!(client.addr = %addr, "Failed to proxy request: {}", error);
+                let request_id = self.request_id.clone().unwrap_or_else(request_id::generate);
+                let reason = LabelError::reason(&*error);
+                let chain: &(dyn std::error::Error + 'static) = &*error;
+                warn!(
+                    client.addr = %addr,
+                    request.id = ?request_id,
+                    error.category = %reason.category(),
+                    error = chain,
+                    "Failed to proxy request"
+                );
 
                 if self.version == http::Version::HTTP_2 {
                     if let Some(reset) = error.h2_reason() {
@@ -223,12 +392,19 @@ impl<RspB: Default + hyper::body::HttpBody> respond::Respond<http::Response<RspB
                 // Set the l5d error header on all responses.
                 let mut builder = http::Response::builder();
                 builder = set_l5d_proxy_error_header(builder, &*error);
+                let kind = reason.kind();
+                if self.config.error_header {
+                    builder = builder.header(L5D_ERR, HeaderValue::from_static(kind));
+                }
 
-                if self.is_grpc {
+                // gRPC clients expect errors to be described by a
+                // `grpc-status` in an otherwise successful (trailers-only)
+                // response.
+                if let Some(content_type) = self.grpc_content_type.clone() {
                     let mut rsp = builder
-                        .version(http::Version::HTTP_2)
+                        .version(self.version)
                         .header(http::header::CONTENT_LENGTH, "0")
-                        .header(http::header::CONTENT_TYPE, GRPC_CONTENT_TYPE)
+                        .header(http::header::CONTENT_TYPE, content_type)
                         .body(ResponseBody::default())
                         .expect("app::errors response is valid");
                     let code = set_grpc_status(&*error, rsp.headers_mut());
@@ -236,11 +412,31 @@ impl<RspB: Default + hyper::body::HttpBody> respond::Respond<http::Response<RspB
                     return Ok(rsp);
                 }
 
-                let rsp = set_http_status(builder, &*error)
-                    .version(self.version)
-                    .header(http::header::CONTENT_LENGTH, "0")
-                    .body(ResponseBody::default())
-                    .expect("error response must be valid");
+                let builder = set_http_status(builder, &*error).version(self.version);
+                let rsp = if self.json_body {
+                    let message = builder
+                        .headers_ref()
+                        .and_then(|h| h.get(L5D_PROXY_ERROR)?.to_str().ok())
+                        .unwrap_or("proxy error");
+                    let body = serde_json::json!({
+                        "error": message,
+                        "kind": kind,
+                        "category": reason.category().as_str(),
+                        "destination": self.dst.as_ref().map(ToString::to_string),
+                        "request_id": request_id.to_str().ok(),
+                    });
+                    let body = Bytes::from(body.to_string());
+                    builder
+                        .header(http::header::CONTENT_LENGTH, body.len())
+                        .header(http::header::CONTENT_TYPE, "application/json")
+                        .header(REQUEST_ID, request_id)
+                        .body(ResponseBody::Error(Some(body)))
+                } else {
+                    builder
+                        .header(http::header::CONTENT_LENGTH, "0")
+                        .body(ResponseBody::default())
+                }
+                .expect("error response must be valid");
                 let status = rsp.status();
                 debug!(%status, version = ?self.version, "Handling error with HTTP response");
                 Ok(rsp)
@@ -249,6 +445,42 @@ impl<RspB: Default + hyper::body::HttpBody> respond::Respond<http::Response<RspB
     }
 }
 
+/// Determines the request's logical destination from the canonical-dst header
+/// set by a client proxy or from the request's `:authority` or `host`.
+fn logical_dst<B>(req: &http::Request<B>) -> Option<Addr> {
+    req.headers()
+        .get(crate::CANONICAL_DST_HEADER)
+        .and_then(|dst| dst.to_str().ok()?.parse().ok())
+        .or_else(|| crate::http_request_authority_addr(req).ok())
+        .or_else(|| crate::http_request_host_addr(req).ok())
+}
+
+/// Determines whether the client accepts JSON responses. Clients that do not
+/// set an `accept` header accept any content type.
+fn accepts_json(headers: &http::HeaderMap) -> bool {
+    let mut accept = headers.get_all(http::header::ACCEPT).iter().peekable();
+    if accept.peek().is_none() {
+        return true;
+    }
+
+    accept
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|range| {
+            let mut params = range.split(';');
+            let media_type = params.next().unwrap_or_default().trim();
+            // A quality of zero marks the media type as not acceptable.
+            let rejected = params.any(|p| match p.trim().strip_prefix("q=") {
+                Some(q) => q.trim().parse::<f32>().map(|q| q <= 0.0).unwrap_or(false),
+                None => false,
+            });
+            !rejected
+                && ["application/json", "application/*", "*/*"]
+                    .iter()
+                    .any(|t| media_type.eq_ignore_ascii_case(t))
+        })
+}
+
 fn set_l5d_proxy_error_header(
     mut builder: http::response::Builder,
     error: &(dyn std::error::Error + 'static),
@@ -260,6 +492,21 @@ fn set_l5d_proxy_error_header(
             L5D_PROXY_ERROR,
             HeaderValue::from_static("request timed out"),
         )
+    } else if error.is::<PerTryTimeout>() {
+        builder.header(
+            L5D_PROXY_ERROR,
+            HeaderValue::from_static("request attempt timed out"),
+        )
+    } else if error.is::<IdleTimeout>() {
+        builder.header(
+            L5D_PROXY_ERROR,
+            HeaderValue::from_static("response idle timed out"),
+        )
+    } else if error.is::<DeadlineExceeded>() {
+        builder.header(
+            L5D_PROXY_ERROR,
+            HeaderValue::from_static("request deadline exceeded"),
+        )
     } else if error.is::<ConnectTimeout>() {
         builder.header(
             L5D_PROXY_ERROR,
@@ -273,16 +520,31 @@ fn set_l5d_proxy_error_header(
                 HeaderValue::from_static("service in fail-fast")
             }),
         )
+    } else if error.is::<Shed>() {
+        builder.header(
+            L5D_PROXY_ERROR,
+            HeaderValue::from_static("service overloaded"),
+        )
     } else if error.is::<tower::timeout::error::Elapsed>() {
         builder.header(
             L5D_PROXY_ERROR,
             HeaderValue::from_static("proxy dispatch timed out"),
         )
-    } else if error.is::<IdentityRequired>() {
+    } else if error.is::<AuthorizationFailed>() {
+        builder.header(
+            L5D_PROXY_ERROR,
+            HeaderValue::from_static("external authorization failed"),
+        )
+    } else if error.is::<IdentityRequired>() || error.is::<InvalidToken>() {
         if let Ok(msg) = HeaderValue::from_str(&error.to_string()) {
             builder = builder.header(L5D_PROXY_ERROR, msg)
         }
         builder
+    } else if let Some(e) = identity_mismatch(error) {
+        if let Ok(msg) = HeaderValue::from_str(&e.to_string()) {
+            builder = builder.header(L5D_PROXY_ERROR, msg)
+        }
+        builder
     } else if let Some(source) = error.source() {
         set_l5d_proxy_error_header(builder, source)
     } else {
@@ -299,16 +561,37 @@ fn set_http_status(
 ) -> http::response::Builder {
     if let Some(HttpError { http, .. }) = error.downcast_ref::<HttpError>() {
         builder.status(*http)
-    } else if error.is::<ResponseTimeout>() {
+    } else if error.is::<ResponseTimeout>()
+        || error.is::<PerTryTimeout>()
+        || error.is::<IdleTimeout>()
+        || error.is::<DeadlineExceeded>()
+    {
         builder.status(StatusCode::GATEWAY_TIMEOUT)
     } else if error.is::<ConnectTimeout>() {
         builder.status(StatusCode::GATEWAY_TIMEOUT)
-    } else if error.is::<FailFastError>() {
-        builder.status(StatusCode::SERVICE_UNAVAILABLE)
+    } else if let Some(e) = error.downcast_ref::<FailFastError>() {
+        let builder = builder.status(StatusCode::SERVICE_UNAVAILABLE);
+        match e.retry_after() {
+            Some(retry_after) => set_retry_after(builder, retry_after),
+            None => builder,
+        }
+    } else if let Some(shed) = error.downcast_ref::<Shed>() {
+        set_retry_after(
+            builder.status(StatusCode::SERVICE_UNAVAILABLE),
+            shed.retry_after(),
+        )
     } else if error.is::<tower::timeout::error::Elapsed>() {
         builder.status(StatusCode::SERVICE_UNAVAILABLE)
     } else if error.is::<IdentityRequired>() {
         builder.status(StatusCode::FORBIDDEN)
+    } else if error.is::<AuthorizationFailed>() {
+        builder.status(StatusCode::FORBIDDEN)
+    } else if error.is::<InvalidToken>() {
+        builder
+            .status(StatusCode::UNAUTHORIZED)
+            .header(http::header::WWW_AUTHENTICATE, "Bearer")
+    } else if identity_mismatch(error).is_some() {
+        builder.status(StatusCode::BAD_GATEWAY)
     } else if let Some(source) = error.source() {
         set_http_status(builder, source)
     } else {
@@ -316,6 +599,15 @@ fn set_http_status(
     }
 }
 
+fn set_retry_after(
+    builder: http::response::Builder,
+    retry_after: std::time::Duration,
+) -> http::response::Builder {
+    // Retry-After is expressed in whole seconds, so round up.
+    let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    builder.header(http::header::RETRY_AFTER, secs)
+}
+
 fn set_grpc_status(
     error: &(dyn std::error::Error + 'static),
     headers: &mut http::HeaderMap,
@@ -332,6 +624,35 @@ fn set_grpc_status(
         headers.insert(GRPC_STATUS, code_header(code));
         headers.insert(GRPC_MESSAGE, HeaderValue::from_static("request timed out"));
         code
+    } else if error.is::<PerTryTimeout>() {
+        let code = Code::DeadlineExceeded;
+        headers.insert(GRPC_STATUS, code_header(code));
+        headers.insert(
+            GRPC_MESSAGE,
+            HeaderValue::from_static("request attempt timed out"),
+        );
+        code
+    } else if error.is::<IdleTimeout>() {
+        let code = Code::DeadlineExceeded;
+        headers.insert(GRPC_STATUS, code_header(code));
+        headers.insert(
+            GRPC_MESSAGE,
+            HeaderValue::from_static("response idle timed out"),
+        );
+        code
+    } else if error.is::<DeadlineExceeded>() {
+        let code = Code::DeadlineExceeded;
+        headers.insert(GRPC_STATUS, code_header(code));
+        headers.insert(
+            GRPC_MESSAGE,
+            HeaderValue::from_static("request deadline exceeded"),
+        );
+        code
+    } else if error.is::<ConnectTimeout>() {
+        let code = Code::Unavailable;
+        headers.insert(GRPC_STATUS, code_header(code));
+        headers.insert(GRPC_MESSAGE, HeaderValue::from_static("failed to connect"));
+        code
     } else if let Some(e) = error.downcast_ref::<FailFastError>() {
         let code = Code::Unavailable;
         headers.insert(GRPC_STATUS, code_header(code));
@@ -343,6 +664,11 @@ fn set_grpc_status(
             }),
         );
         code
+    } else if error.is::<Shed>() {
+        let code = Code::Unavailable;
+        headers.insert(GRPC_STATUS, code_header(code));
+        headers.insert(GRPC_MESSAGE, HeaderValue::from_static("service overloaded"));
+        code
     } else if error.is::<tower::timeout::error::Elapsed>() {
         let code = Code::Unavailable;
         headers.insert(GRPC_STATUS, code_header(code));
@@ -358,6 +684,28 @@ fn set_grpc_status(
             headers.insert(GRPC_MESSAGE, msg);
         }
         code
+    } else if error.is::<AuthorizationFailed>() {
+        let code = Code::PermissionDenied;
+        headers.insert(GRPC_STATUS, code_header(code));
+        headers.insert(
+            GRPC_MESSAGE,
+            HeaderValue::from_static("external authorization failed"),
+        );
+        code
+    } else if error.is::<InvalidToken>() {
+        let code = Code::Unauthenticated;
+        headers.insert(GRPC_STATUS, code_header(code));
+        if let Ok(msg) = HeaderValue::from_str(&error.to_string()) {
+            headers.insert(GRPC_MESSAGE, msg);
+        }
+        code
+    } else if let Some(e) = identity_mismatch(error) {
+        let code = Code::Unavailable;
+        headers.insert(GRPC_STATUS, code_header(code));
+        if let Ok(msg) = HeaderValue::from_str(&e.to_string()) {
+            headers.insert(GRPC_MESSAGE, msg);
+        }
+        code
     } else if error.is::<std::io::Error>() {
         let code = Code::Unavailable;
         headers.insert(GRPC_STATUS, code_header(code));
@@ -423,20 +771,61 @@ impl fmt::Display for IdentityRequired {
 
 impl std::error::Error for IdentityRequired {}
 
+/// Finds an `IdentityMismatch` raised by the TLS client. Connection errors are
+/// carried as an `io::Error`, which doesn't expose the error it wraps as its
+/// source.
+fn identity_mismatch(
+    err: &(dyn std::error::Error + 'static),
+) -> Option<&tls::client::IdentityMismatch> {
+    if let Some(e) = err.downcast_ref::<tls::client::IdentityMismatch>() {
+        return Some(e);
+    }
+    err.downcast_ref::<std::io::Error>()?
+        .get_ref()?
+        .downcast_ref::<tls::client::IdentityMismatch>()
+}
+
 impl LabelError {
     fn reason(err: &(dyn std::error::Error + 'static)) -> Reason {
         if let Some(HttpError { reason, .. }) = err.downcast_ref::<HttpError>() {
             *reason
         } else if err.is::<ResponseTimeout>() {
             Reason::ResponseTimeout
+        } else if err.is::<PerTryTimeout>() {
+            Reason::PerTryTimeout
+        } else if err.is::<IdleTimeout>() {
+            Reason::IdleTimeout
+        } else if err.is::<DeadlineExceeded>() {
+            Reason::DeadlineExceeded
+        } else if err.is::<ConnectTimeout>() {
+            Reason::ConnectTimeout
         } else if err.is::<FailFastError>() {
             Reason::FailFast
+        } else if err.is::<Shed>() {
+            Reason::LoadShed
         } else if err.is::<tower::timeout::error::Elapsed>() {
             Reason::DispatchTimeout
         } else if err.is::<IdentityRequired>() {
             Reason::IdentityRequired
+        } else if err.is::<InvalidToken>() {
+            Reason::Unauthenticated
+        } else if err.is::<AuthorizationFailed>() {
+            Reason::ExtAuthzFailed
+        } else if identity_mismatch(err).is_some() {
+            Reason::IdentityMismatch
+        } else if err.is::<tls::server::ServerTlsTimeoutError>() {
+            Reason::Tls
+        } else if err.is::<DiscoveryRejected>() || err.is::<grpc::Status>() {
+            Reason::Discovery
+        } else if err
+            .downcast_ref::<hyper::Error>()
+            .map_or(false, |e| e.is_parse() || e.is_parse_status())
+        {
+            Reason::Protocol
         } else if let Some(e) = err.downcast_ref::<std::io::Error>() {
             Reason::Io(e.raw_os_error().map(Errno::from))
+        } else if err.h2_reason().is_some() {
+            Reason::Protocol
         } else if let Some(e) = err.source() {
             Self::reason(e)
         } else {
@@ -453,6 +842,65 @@ impl error_metrics::LabelError<Error> for LabelError {
     }
 }
 
+impl Reason {
+    /// Describes the kind of error in responses.
+    fn kind(&self) -> &'static str {
+        match self {
+            Reason::FailFast => "fail_fast",
+            Reason::LoadShed => "load_shed",
+            Reason::DispatchTimeout => "dispatch_timeout",
+            Reason::ResponseTimeout => "response_timeout",
+            Reason::PerTryTimeout => "per_try_timeout",
+            Reason::IdleTimeout => "idle_timeout",
+            Reason::DeadlineExceeded => "deadline_exceeded",
+            Reason::IdentityRequired => "identity_required",
+            Reason::IdentityMismatch => "identity_mismatch",
+            Reason::Unauthenticated => "unauthenticated",
+            Reason::ExtAuthzFailed => "ext_authz_failed",
+            Reason::Unauthorized => "unauthorized",
+            Reason::GatewayLoop => "gateway_loop",
+            Reason::NotFound => "not_found",
+            Reason::TooManyHeaders => "too_many_headers",
+            Reason::HeaderTooLarge => "header_too_large",
+            Reason::HeadersTooLarge => "headers_too_large",
+            Reason::UriTooLong => "uri_too_long",
+            Reason::QuotaExceeded => "quota_exceeded",
+            Reason::Io(_) => "io",
+            Reason::ConnectTimeout => "connect_timeout",
+            Reason::Tls => "tls",
+            Reason::Protocol => "protocol",
+            Reason::Discovery => "discovery",
+            Reason::Unexpected => "unexpected",
+        }
+    }
+
+    pub fn category(&self) -> Category {
+        match self {
+            Reason::Io(_) | Reason::ConnectTimeout => Category::Connect,
+            Reason::IdentityMismatch | Reason::Tls => Category::Tls,
+            Reason::Protocol
+            | Reason::GatewayLoop
+            | Reason::TooManyHeaders
+            | Reason::HeaderTooLarge
+            | Reason::HeadersTooLarge
+            | Reason::UriTooLong => Category::Protocol,
+            Reason::IdentityRequired
+            | Reason::Unauthenticated
+            | Reason::ExtAuthzFailed
+            | Reason::Unauthorized
+            | Reason::QuotaExceeded => Category::Policy,
+            Reason::Discovery | Reason::NotFound => Category::Discovery,
+            Reason::DispatchTimeout
+            | Reason::ResponseTimeout
+            | Reason::PerTryTimeout
+            | Reason::IdleTimeout
+            | Reason::DeadlineExceeded => Category::Timeout,
+            Reason::FailFast | Reason::LoadShed => Category::Unavailable,
+            Reason::Unexpected => Category::Unexpected,
+        }
+    }
+}
+
 impl FmtLabels for Reason {
     fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -460,16 +908,35 @@ impl FmtLabels for Reason {
             "message=\"{}\"",
             match self {
                 Reason::FailFast => "failfast",
+                Reason::LoadShed => "load shed",
                 Reason::DispatchTimeout => "dispatch timeout",
                 Reason::ResponseTimeout => "response timeout",
+                Reason::PerTryTimeout => "per-try timeout",
+                Reason::IdleTimeout => "idle timeout",
+                Reason::DeadlineExceeded => "deadline exceeded",
                 Reason::IdentityRequired => "identity required",
+                Reason::IdentityMismatch => "identity mismatch",
+                Reason::Unauthenticated => "unauthenticated",
+                Reason::ExtAuthzFailed => "external authorization failed",
+                Reason::Unauthorized => "unauthorized",
                 Reason::GatewayLoop => "gateway loop",
                 Reason::NotFound => "not found",
+                Reason::TooManyHeaders => "too many headers",
+                Reason::HeaderTooLarge => "header too large",
+                Reason::HeadersTooLarge => "headers too large",
+                Reason::UriTooLong => "uri too long",
+                Reason::QuotaExceeded => "quota exceeded",
                 Reason::Io(_) => "i/o",
+                Reason::ConnectTimeout => "connect timeout",
+                Reason::Tls => "tls",
+                Reason::Protocol => "protocol error",
+                Reason::Discovery => "discovery",
                 Reason::Unexpected => "unexpected",
             }
         )?;
 
+        write!(f, ",category=\"{}\"", self.category())?;
+
         if let Reason::Io(Some(errno)) = self {
             write!(f, ",errno=\"{}\"", errno)?;
         }
@@ -478,6 +945,29 @@ impl FmtLabels for Reason {
     }
 }
 
+// === impl Category ===
+
+impl Category {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Category::Connect => "connect",
+            Category::Tls => "tls",
+            Category::Protocol => "protocol",
+            Category::Policy => "policy",
+            Category::Discovery => "discovery",
+            Category::Timeout => "timeout",
+            Category::Unavailable => "unavailable",
+            Category::Unexpected => "unexpected",
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 impl Default for Metrics {
     fn default() -> Metrics {
         Self {
@@ -511,6 +1001,15 @@ impl HttpError {
         }
     }
 
+    pub fn unauthorized(message: &'static str) -> Self {
+        Self {
+            message,
+            http: StatusCode::FORBIDDEN,
+            grpc: Code::PermissionDenied,
+            reason: Reason::Unauthorized,
+        }
+    }
+
     pub fn not_found(message: &'static str) -> Self {
         Self {
             message,
@@ -529,6 +1028,51 @@ impl HttpError {
         }
     }
 
+    pub fn too_many_headers() -> Self {
+        Self {
+            message: "request has too many headers",
+            http: StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+            grpc: Code::ResourceExhausted,
+            reason: Reason::TooManyHeaders,
+        }
+    }
+
+    pub fn header_too_large() -> Self {
+        Self {
+            message: "request header too large",
+            http: StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+            grpc: Code::ResourceExhausted,
+            reason: Reason::HeaderTooLarge,
+        }
+    }
+
+    pub fn headers_too_large() -> Self {
+        Self {
+            message: "request headers too large",
+            http: StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+            grpc: Code::ResourceExhausted,
+            reason: Reason::HeadersTooLarge,
+        }
+    }
+
+    pub fn uri_too_long() -> Self {
+        Self {
+            message: "request URI too long",
+            http: StatusCode::URI_TOO_LONG,
+            grpc: Code::InvalidArgument,
+            reason: Reason::UriTooLong,
+        }
+    }
+
+    pub fn quota_exceeded(message: &'static str) -> Self {
+        Self {
+            message,
+            http: StatusCode::TOO_MANY_REQUESTS,
+            grpc: Code::ResourceExhausted,
+            reason: Reason::QuotaExceeded,
+        }
+    }
+
     pub fn status(&self) -> StatusCode {
         self.http
     }
@@ -544,3 +1088,308 @@ impl fmt::Display for ConnectTimeout {
 }
 
 impl std::error::Error for ConnectTimeout {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Error)]
+    #[error("wrapped")]
+    struct Wrapped(#[source] Error);
+
+    fn category_of(error: impl Into<Error>) -> Category {
+        let error: Error = error.into();
+        category(&*error)
+    }
+
+    #[test]
+    fn classifies_source_chains() {
+        let refused = || std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+        assert_eq!(category_of(refused()), Category::Connect);
+        assert_eq!(
+            category_of(Wrapped(Wrapped(refused().into()).into())),
+            Category::Connect
+        );
+        assert_eq!(
+            category_of(Wrapped(
+                ConnectTimeout(std::time::Duration::from_secs(1)).into()
+            )),
+            Category::Connect
+        );
+        assert_eq!(
+            category_of(Wrapped(DiscoveryRejected::Message("no profile").into())),
+            Category::Discovery
+        );
+        assert_eq!(
+            category_of(Wrapped(grpc::Status::unavailable("no endpoints").into())),
+            Category::Discovery
+        );
+        assert_eq!(
+            category_of(Wrapped(HttpError::unauthorized("denied").into())),
+            Category::Policy
+        );
+        assert_eq!(
+            category_of(HttpError::too_many_headers()),
+            Category::Protocol
+        );
+        assert_eq!(
+            category_of(Wrapped("an error occurred".into())),
+            Category::Unexpected
+        );
+    }
+
+    /// A response body that yields `data` and then optionally fails.
+    #[derive(Default)]
+    struct TestBody {
+        data: Option<Bytes>,
+        data_error: bool,
+        trailers_error: bool,
+    }
+
+    impl hyper::body::HttpBody for TestBody {
+        type Data = Bytes;
+        type Error = Error;
+
+        fn poll_data(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Option<Result<Bytes, Error>>> {
+            let this = self.get_mut();
+            if let Some(data) = this.data.take() {
+                return Poll::Ready(Some(Ok(data)));
+            }
+            if std::mem::take(&mut this.data_error) {
+                return Poll::Ready(Some(Err("stream failed".into())));
+            }
+            Poll::Ready(None)
+        }
+
+        fn poll_trailers(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Result<Option<http::HeaderMap>, Error>> {
+            if self.trailers_error {
+                return Poll::Ready(Err("trailers failed".into()));
+            }
+            Poll::Ready(Ok(None))
+        }
+    }
+
+    fn responder(version: http::Version, content_type: Option<&'static str>) -> Respond {
+        let mut req = http::Request::builder()
+            .version(version)
+            .uri("http://foo.example.com/");
+        if let Some(content_type) = content_type {
+            req = req.header(http::header::CONTENT_TYPE, content_type);
+        }
+        let mut req = req.body(()).unwrap();
+        let (client, _) = ClientHandle::new(([10, 0, 0, 1], 5550).into());
+        req.extensions_mut().insert(client);
+
+        let new_respond = NewRespond(RespondConfig {
+            json_body: false,
+            error_header: false,
+        });
+        respond::NewRespond::<_, http::Response<TestBody>>::new_respond(&new_respond, &req)
+    }
+
+    fn respond_err(
+        version: http::Version,
+        content_type: Option<&'static str>,
+    ) -> http::Response<ResponseBody<TestBody>> {
+        let error = ConnectTimeout(std::time::Duration::from_secs(1)).into();
+        respond::Respond::<http::Response<TestBody>>::respond(
+            &responder(version, content_type),
+            Err(error),
+        )
+        .expect("error must be described by a response")
+    }
+
+    /// Responds to a request that accepts `accept` with a not-found error,
+    /// with JSON error bodies enabled.
+    fn respond_json(
+        version: http::Version,
+        content_type: Option<&'static str>,
+        accept: Option<&'static str>,
+    ) -> http::Response<ResponseBody<TestBody>> {
+        let mut req = http::Request::builder()
+            .version(version)
+            .uri("http://foo.example.com/")
+            .header(REQUEST_ID, "abc123");
+        if let Some(content_type) = content_type {
+            req = req.header(http::header::CONTENT_TYPE, content_type);
+        }
+        if let Some(accept) = accept {
+            req = req.header(http::header::ACCEPT, accept);
+        }
+        let mut req = req.body(()).unwrap();
+        let (client, _) = ClientHandle::new(([10, 0, 0, 1], 5550).into());
+        req.extensions_mut().insert(client);
+
+        let new_respond = NewRespond(RespondConfig {
+            json_body: true,
+            error_header: true,
+        });
+        let respond =
+            respond::NewRespond::<_, http::Response<TestBody>>::new_respond(&new_respond, &req);
+        respond::Respond::<http::Response<TestBody>>::respond(
+            &respond,
+            Err(HttpError::not_found("no route").into()),
+        )
+        .expect("error must be described by a response")
+    }
+
+    fn json_body(rsp: http::Response<ResponseBody<TestBody>>) -> Option<serde_json::Value> {
+        match rsp.into_body() {
+            ResponseBody::Error(Some(body)) => {
+                Some(serde_json::from_slice(&body).expect("body must be valid JSON"))
+            }
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn json_error_bodies() {
+        let rsp = respond_json(http::Version::HTTP_11, None, Some("application/json"));
+        assert_eq!(rsp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            rsp.headers()[http::header::CONTENT_TYPE],
+            "application/json"
+        );
+        assert_eq!(rsp.headers()[REQUEST_ID], "abc123");
+        assert_eq!(rsp.headers()[L5D_ERR], "not_found");
+        let content_length = rsp.headers()[http::header::CONTENT_LENGTH]
+            .to_str()
+            .unwrap()
+            .parse::<usize>()
+            .unwrap();
+
+        let body = match rsp.into_body() {
+            ResponseBody::Error(Some(body)) => body,
+            _ => panic!("response must have an error body"),
+        };
+        assert_eq!(body.len(), content_length);
+        let json = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "error": "no route",
+                "kind": "not_found",
+                "category": "discovery",
+                "destination": "foo.example.com:80",
+                "request_id": "abc123",
+            })
+        );
+    }
+
+    #[test]
+    fn json_error_bodies_are_negotiated() {
+        for accept in &[
+            None,
+            Some("application/json"),
+            Some("Application/JSON; charset=utf-8"),
+            Some("text/html, application/*;q=0.5"),
+            Some("text/html, */*;q=0.1"),
+        ] {
+            let rsp = respond_json(http::Version::HTTP_11, None, *accept);
+            assert!(json_body(rsp).is_some(), "{:?}", accept);
+        }
+
+        for accept in &[
+            "text/html",
+            "text/*",
+            "application/json;q=0",
+            "text/html, */*;q=0.0",
+        ] {
+            let rsp = respond_json(http::Version::HTTP_11, None, Some(*accept));
+            assert_eq!(rsp.status(), StatusCode::NOT_FOUND);
+            assert_eq!(rsp.headers()[http::header::CONTENT_LENGTH], "0");
+            assert!(rsp.headers().get(http::header::CONTENT_TYPE).is_none());
+            assert!(json_body(rsp).is_none(), "{}", accept);
+        }
+
+        // gRPC errors are always described by a status.
+        let rsp = respond_json(
+            http::Version::HTTP_2,
+            Some("application/grpc"),
+            Some("application/json"),
+        );
+        assert_eq!(rsp.status(), StatusCode::OK);
+        assert_eq!(
+            rsp.headers()[http::header::CONTENT_TYPE],
+            "application/grpc"
+        );
+        assert!(json_body(rsp).is_none());
+    }
+
+    #[test]
+    fn grpc_errors_are_trailers_only() {
+        for (version, content_type) in &[
+            (http::Version::HTTP_2, "application/grpc"),
+            (http::Version::HTTP_2, "application/grpc+proto"),
+            (http::Version::HTTP_11, "application/grpc-web"),
+            (http::Version::HTTP_11, "application/grpc-web-text+proto"),
+        ] {
+            let rsp = respond_err(*version, Some(*content_type));
+            assert_eq!(rsp.status(), StatusCode::OK, "{}", content_type);
+            assert_eq!(rsp.version(), *version);
+            assert_eq!(rsp.headers()[http::header::CONTENT_TYPE], *content_type);
+            // UNAVAILABLE
+            assert_eq!(rsp.headers()["grpc-status"], "14", "{}", content_type);
+        }
+    }
+
+    #[test]
+    fn non_grpc_errors_use_http_status() {
+        for (version, content_type) in &[
+            (http::Version::HTTP_11, None),
+            (http::Version::HTTP_2, None),
+            (http::Version::HTTP_2, Some("application/grpc-web")),
+            // gRPC requires HTTP/2.
+            (http::Version::HTTP_11, Some("application/grpc")),
+        ] {
+            let rsp = respond_err(*version, *content_type);
+            assert_ne!(rsp.status(), StatusCode::OK, "{:?}", content_type);
+            assert!(rsp.headers().get("grpc-status").is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn grpc_stream_errors_set_trailers() {
+        use hyper::body::HttpBody;
+
+        let body = TestBody {
+            data: Some(Bytes::from_static(b"hello")),
+            data_error: true,
+            ..TestBody::default()
+        };
+        let rsp = respond::Respond::respond(
+            &responder(http::Version::HTTP_2, Some("application/grpc")),
+            Ok(http::Response::new(body)),
+        )
+        .unwrap();
+        let mut body = rsp.into_body();
+        assert!(body.data().await.unwrap().is_ok());
+        assert!(body.data().await.is_none());
+        let trailers = body.trailers().await.unwrap().expect("must have trailers");
+        assert!(trailers.contains_key("grpc-status"));
+    }
+
+    #[tokio::test]
+    async fn grpc_trailers_errors_are_propagated() {
+        use hyper::body::HttpBody;
+
+        let body = TestBody {
+            trailers_error: true,
+            ..TestBody::default()
+        };
+        let rsp = respond::Respond::respond(
+            &responder(http::Version::HTTP_2, Some("application/grpc")),
+            Ok(http::Response::new(body)),
+        )
+        .unwrap();
+        let mut body = rsp.into_body();
+        assert!(body.data().await.is_none());
+        assert!(body.trailers().await.is_err());
+    }
+}
@@ -0,0 +1,292 @@
+use crate::metrics::{metrics, FmtMetrics, Gauge};
+use futures::{ready, Stream};
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use std::{
+    fmt,
+    future::Future,
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::{self, Instant, Sleep};
+use tracing::{info, warn};
+
+metrics! {
+    process_fd_shedding: Gauge {
+        "Whether listeners have stopped accepting connections because too many file descriptors are open."
+    },
+    process_fd_shedding_high_water: Gauge {
+        "The number of open file descriptors at which listeners stop accepting connections."
+    },
+    process_fd_shedding_low_water: Gauge {
+        "The number of open file descriptors below which listeners resume accepting connections."
+    }
+}
+
+/// Configures listeners to stop accepting connections when the process has too
+/// many file descriptors open, until enough of them have been closed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// The percentage of the process's file descriptor limit at which
+    /// listeners stop accepting connections.
+    pub high_water_percent: u8,
+
+    /// The percentage of the process's file descriptor limit below which
+    /// listeners resume accepting connections.
+    pub low_water_percent: u8,
+}
+
+/// Stops listeners from accepting connections while the process's open file
+/// descriptors are above a high-water mark, resuming once they fall below a
+/// low-water mark. Connections that have already been accepted are not
+/// affected.
+///
+/// Open file descriptors are sampled at most once per second.
+#[derive(Clone, Debug, Default)]
+pub struct FdPressure(Option<Arc<Inner>>);
+
+/// A stream of accepted connections that stops accepting while file
+/// descriptors are under pressure.
+#[pin_project]
+#[derive(Debug)]
+pub struct Shed<S> {
+    #[pin]
+    inner: S,
+    pressure: FdPressure,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+struct Inner {
+    high_water: u64,
+    low_water: u64,
+    open_fds: fn() -> io::Result<u64>,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    sampled: Option<Instant>,
+    shedding: bool,
+}
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+// === impl FdPressure ===
+
+impl FdPressure {
+    /// Configures shedding relative to the process's file descriptor limit.
+    /// Shedding is disabled if the limit cannot be determined.
+    pub fn new(config: Config) -> Self {
+        match sys::max_fds() {
+            Ok(Some(max_fds)) => Self::with_limit(config, max_fds, sys::open_fds),
+            Ok(None) => {
+                warn!("File descriptors are unlimited; connections will not be shed");
+                Self::default()
+            }
+            Err(error) => {
+                warn!(%error, "Could not determine the file descriptor limit; connections will not be shed");
+                Self::default()
+            }
+        }
+    }
+
+    fn with_limit(config: Config, max_fds: u64, open_fds: fn() -> io::Result<u64>) -> Self {
+        let high_water = max_fds * u64::from(config.high_water_percent.min(100)) / 100;
+        let low_water = max_fds * u64::from(config.low_water_percent.min(100)) / 100;
+        Self(Some(Arc::new(Inner {
+            high_water,
+            low_water: low_water.min(high_water),
+            open_fds,
+            state: Default::default(),
+        })))
+    }
+
+    /// Wraps a stream of accepted connections so that connections are not
+    /// accepted while file descriptors are under pressure.
+    pub fn shed<S: Stream>(&self, inner: S) -> Shed<S> {
+        Shed {
+            inner,
+            pressure: self.clone(),
+            sleep: None,
+        }
+    }
+
+    /// Returns true if connections should not be accepted.
+    fn is_shedding(&self) -> bool {
+        match self.0.as_ref() {
+            Some(inner) => inner.is_shedding(),
+            None => false,
+        }
+    }
+}
+
+impl FmtMetrics for FdPressure {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = match self.0.as_ref() {
+            Some(inner) => inner,
+            None => return Ok(()),
+        };
+
+        let shedding = inner.state.lock().shedding;
+        process_fd_shedding.fmt_help(f)?;
+        process_fd_shedding.fmt_metric(f, &Gauge::from(shedding as u64))?;
+        process_fd_shedding_high_water.fmt_help(f)?;
+        process_fd_shedding_high_water.fmt_metric(f, &Gauge::from(inner.high_water))?;
+        process_fd_shedding_low_water.fmt_help(f)?;
+        process_fd_shedding_low_water.fmt_metric(f, &Gauge::from(inner.low_water))
+    }
+}
+
+// === impl Inner ===
+
+impl Inner {
+    fn is_shedding(&self) -> bool {
+        let mut state = self.state.lock();
+        let now = Instant::now();
+        if let Some(sampled) = state.sampled {
+            if now.saturating_duration_since(sampled) < SAMPLE_INTERVAL {
+                return state.shedding;
+            }
+        }
+        state.sampled = Some(now);
+
+        let open_fds = match (self.open_fds)() {
+            Ok(open_fds) => open_fds,
+            Err(error) => {
+                warn!(%error, "Could not determine the number of open file descriptors");
+                return state.shedding;
+            }
+        };
+        if !state.shedding && open_fds >= self.high_water {
+            warn!(
+                open_fds,
+                high_water = self.high_water,
+                "Too many file descriptors are open; no longer accepting connections"
+            );
+            state.shedding = true;
+        } else if state.shedding && open_fds < self.low_water {
+            info!(
+                open_fds,
+                low_water = self.low_water,
+                "Resuming accepting connections"
+            );
+            state.shedding = false;
+        }
+        state.shedding
+    }
+}
+
+impl fmt::Debug for Inner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Inner")
+            .field("high_water", &self.high_water)
+            .field("low_water", &self.low_water)
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+// === impl Shed ===
+
+impl<S: Stream> Stream for Shed<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        let this = self.project();
+        loop {
+            if let Some(sleep) = this.sleep.as_mut() {
+                ready!(sleep.as_mut().poll(cx));
+                *this.sleep = None;
+            }
+
+            if !this.pressure.is_shedding() {
+                return this.inner.poll_next(cx);
+            }
+
+            // Check again once the number of open file descriptors may have
+            // been resampled.
+            *this.sleep = Some(Box::pin(time::sleep(SAMPLE_INTERVAL)));
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::io;
+
+    pub(super) fn max_fds() -> io::Result<Option<u64>> {
+        linkerd_system::max_fds()
+    }
+
+    pub(super) fn open_fds() -> io::Result<u64> {
+        linkerd_system::open_fds(std::process::id() as _)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    use std::io;
+
+    pub(super) fn max_fds() -> io::Result<Option<u64>> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "file descriptors can only be counted on Linux",
+        ))
+    }
+
+    pub(super) fn open_fds() -> io::Result<u64> {
+        max_fds().map(|_| 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static OPEN_FDS: AtomicU64 = AtomicU64::new(0);
+
+    fn open_fds() -> io::Result<u64> {
+        Ok(OPEN_FDS.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn sheds_between_water_marks() {
+        time::pause();
+        let pressure = FdPressure::with_limit(
+            Config {
+                high_water_percent: 90,
+                low_water_percent: 80,
+            },
+            100,
+            open_fds,
+        );
+
+        OPEN_FDS.store(89, Ordering::SeqCst);
+        assert!(!pressure.is_shedding());
+
+        OPEN_FDS.store(90, Ordering::SeqCst);
+        assert!(
+            !pressure.is_shedding(),
+            "open file descriptors must not be resampled within the interval"
+        );
+        time::advance(SAMPLE_INTERVAL).await;
+        assert!(pressure.is_shedding());
+
+        OPEN_FDS.store(85, Ordering::SeqCst);
+        time::advance(SAMPLE_INTERVAL).await;
+        assert!(
+            pressure.is_shedding(),
+            "shedding must continue until below the low-water mark"
+        );
+
+        OPEN_FDS.store(79, Ordering::SeqCst);
+        time::advance(SAMPLE_INTERVAL).await;
+        assert!(!pressure.is_shedding());
+
+        assert!(!FdPressure::default().is_shedding());
+    }
+}
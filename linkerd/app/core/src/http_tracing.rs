@@ -1,22 +1,25 @@
 use linkerd_error::Error;
-use linkerd_opencensus::proto::trace::v1 as oc;
+use linkerd_opencensus::{proto::trace::v1 as oc, SpanSender};
 use linkerd_stack::layer;
 use linkerd_trace_context::{self as trace_context, TraceContext};
+pub use linkerd_trace_context::{
+    scope_connection, Attribute, Attributes, DeferredReadySpan, InvalidAttribute, NewSetAttribute,
+    ReadySpan, SetConnectionReused, SpanAttributes,
+};
 use std::{collections::HashMap, sync::Arc};
 use thiserror::Error;
-use tokio::sync::mpsc;
 
-pub type OpenCensusSink = Option<mpsc::Sender<oc::Span>>;
+pub type OpenCensusSink = Option<SpanSender>;
 pub type Labels = Arc<HashMap<String, String>>;
 
 /// SpanConverter converts trace_context::Span objects into OpenCensus agent
 /// protobuf span objects. SpanConverter receives trace_context::Span objects by
 /// implmenting the SpanSink trait. For each span that it receives, it converts
-/// it to an OpenCensus span and then sends it on the provided mpsc::Sender.
+/// it to an OpenCensus span and then sends it on the provided SpanSender.
 #[derive(Clone)]
 pub struct SpanConverter {
     kind: Kind,
-    sink: mpsc::Sender<oc::Span>,
+    sink: SpanSender,
     labels: Labels,
 }
 
@@ -30,16 +33,18 @@ pub struct IdLengthError {
 
 pub fn server<S>(
     sink: OpenCensusSink,
+    attributes: Attributes,
     labels: impl Into<Labels>,
 ) -> impl layer::Layer<S, Service = TraceContext<Option<SpanConverter>, S>> + Clone {
-    SpanConverter::layer(Kind::Server, sink, labels)
+    SpanConverter::layer(Kind::Server, sink, attributes, labels)
 }
 
 pub fn client<S>(
     sink: OpenCensusSink,
+    attributes: Attributes,
     labels: impl Into<Labels>,
 ) -> impl layer::Layer<S, Service = TraceContext<Option<SpanConverter>, S>> + Clone {
-    SpanConverter::layer(Kind::Client, sink, labels)
+    SpanConverter::layer(Kind::Client, sink, attributes, labels)
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -52,13 +57,17 @@ impl SpanConverter {
     fn layer<S>(
         kind: Kind,
         sink: OpenCensusSink,
+        attributes: Attributes,
         labels: impl Into<Labels>,
     ) -> impl layer::Layer<S, Service = TraceContext<Option<Self>, S>> + Clone {
-        TraceContext::layer(sink.map(move |sink| Self {
-            kind,
-            sink,
-            labels: labels.into(),
-        }))
+        TraceContext::layer(
+            sink.map(move |sink| Self {
+                kind,
+                sink,
+                labels: labels.into(),
+            }),
+            attributes,
+        )
     }
 
     fn mk_span(&self, mut span: trace_context::Span) -> Result<oc::Span, IdLengthError> {
@@ -115,6 +124,15 @@ impl trace_context::SpanSink for SpanConverter {
         let span = self.mk_span(span)?;
         self.sink.try_send(span).map_err(Into::into)
     }
+
+    fn send(&mut self, span: trace_context::Span) -> trace_context::SendFuture {
+        let span = self.mk_span(span);
+        let sink = self.sink.clone();
+        Box::pin(async move {
+            let span = span?;
+            sink.send(span).await.map_err(Into::into)
+        })
+    }
 }
 
 fn into_bytes(id: trace_context::Id, size: usize) -> Result<Vec<u8>, IdLengthError> {
@@ -19,9 +19,17 @@ pub use linkerd_detect as detect;
 pub use linkerd_dns;
 pub use linkerd_error::{is_error, Error, Infallible, Recover, Result};
 pub use linkerd_exp_backoff as exp_backoff;
+pub use linkerd_http_cache as http_cache;
+pub use linkerd_http_compress as compress;
+pub use linkerd_http_ext_authz as ext_authz;
+pub use linkerd_http_idempotency as idempotency;
+pub use linkerd_http_jwt as jwt;
 pub use linkerd_http_metrics as http_metrics;
+#[cfg(feature = "wasm")]
+pub use linkerd_http_wasm as http_wasm;
 pub use linkerd_identity as identity;
 pub use linkerd_io as io;
+pub use linkerd_load_shed as load_shed;
 pub use linkerd_opencensus as opencensus;
 pub use linkerd_service_profiles as profiles;
 pub use linkerd_stack_metrics as stack_metrics;
@@ -35,20 +43,25 @@ use thiserror::Error;
 mod addr_match;
 pub mod classify;
 pub mod config;
+pub mod connections;
 pub mod control;
 pub mod dns;
 pub mod dst;
 pub mod errors;
+pub mod fd_pressure;
 pub mod http_tracing;
 pub mod metrics;
+pub mod protocol_hints;
 pub mod proxy;
+pub mod request_id;
+pub mod request_limits;
 pub mod retry;
 pub mod serve;
 pub mod svc;
 pub mod telemetry;
 pub mod transport;
 
-pub use self::addr_match::{AddrMatch, IpMatch, NameMatch};
+pub use self::addr_match::{AddrMatch, InvalidNameRule, IpMatch, NameMatch, NameRule};
 
 pub const CANONICAL_DST_HEADER: &str = "l5d-dst-canonical";
 
@@ -59,8 +72,10 @@ pub struct ProxyRuntime {
     pub identity: Option<proxy::identity::LocalCrtKey>,
     pub metrics: metrics::Proxy,
     pub tap: proxy::tap::Registry,
+    pub connections: connections::Registry,
     pub span_sink: http_tracing::OpenCensusSink,
     pub drain: drain::Watch,
+    pub extensions: svc::extension::Registry,
 }
 
 pub fn http_request_authority_addr<B>(req: &http::Request<B>) -> Result<Addr, addr::Error> {
@@ -0,0 +1,98 @@
+use crate::metrics::{metrics, Counter, FmtLabels, FmtMetrics, Gauge};
+use linkerd_addr::NameAddr;
+use parking_lot::Mutex;
+use std::{collections::HashMap, fmt, sync::Arc};
+
+metrics! {
+    outbound_failover_total: Counter {
+        "Total number of times traffic has failed over to a remote cluster's endpoints"
+    },
+    outbound_failover_recovered_total: Counter {
+        "Total number of times traffic has returned to local endpoints after a failover"
+    },
+    outbound_failover_active: Gauge {
+        "The number of balancers currently sending traffic to a remote cluster's endpoints"
+    }
+}
+
+/// Tracks failovers between local and remote-cluster endpoints.
+#[derive(Clone, Debug, Default)]
+pub struct Registry(Arc<Mutex<HashMap<Labels, Arc<Metrics>>>>);
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    failover_total: Counter,
+    recovered_total: Counter,
+    active: Gauge,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Labels {
+    dst: NameAddr,
+    failover_dst: NameAddr,
+}
+
+// === impl Registry ===
+
+impl Registry {
+    pub fn metrics(&self, dst: NameAddr, failover_dst: NameAddr) -> Arc<Metrics> {
+        self.0
+            .lock()
+            .entry(Labels { dst, failover_dst })
+            .or_insert_with(Default::default)
+            .clone()
+    }
+}
+
+impl FmtMetrics for Registry {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let metrics = self.0.lock();
+        if metrics.is_empty() {
+            return Ok(());
+        }
+
+        outbound_failover_total.fmt_help(f)?;
+        outbound_failover_total.fmt_scopes(f, metrics.iter(), |m| &m.failover_total)?;
+
+        outbound_failover_recovered_total.fmt_help(f)?;
+        outbound_failover_recovered_total.fmt_scopes(f, metrics.iter(), |m| &m.recovered_total)?;
+
+        outbound_failover_active.fmt_help(f)?;
+        outbound_failover_active.fmt_scopes(f, metrics.iter(), |m| &m.active)?;
+
+        Ok(())
+    }
+}
+
+// === impl Metrics ===
+
+impl Metrics {
+    /// Records that traffic has shifted to the remote endpoints.
+    pub fn failover(&self) {
+        self.failover_total.incr();
+        self.active.incr();
+    }
+
+    /// Records that traffic has returned to the local endpoints.
+    pub fn recovered(&self) {
+        self.recovered_total.incr();
+        self.active.decr();
+    }
+
+    /// Records that a balancer was dropped while failed over.
+    pub fn dropped(&self) {
+        self.active.decr();
+    }
+}
+
+// === impl Labels ===
+
+impl FmtLabels for Labels {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "dst=\"{}\",failover_dst=\"{}\"",
+            self.dst, self.failover_dst
+        )
+    }
+}
@@ -0,0 +1,14 @@
+use crate::{
+    metrics::{self, Counter},
+    proxy::http::framing,
+};
+
+metrics::metrics! {
+    inbound_http1_invalid_framing_requests_total: Counter {
+        "The total number of inbound HTTP/1 connections closed due to ambiguous request framing."
+    }
+}
+
+pub fn inbound() -> framing::Metrics {
+    framing::Metrics::new(inbound_http1_invalid_framing_requests_total)
+}
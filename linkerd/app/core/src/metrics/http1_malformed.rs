@@ -0,0 +1,14 @@
+use crate::{
+    metrics::{self, Counter},
+    proxy::http::malformed,
+};
+
+metrics::metrics! {
+    inbound_http1_malformed_requests_total: Counter {
+        "The total number of malformed inbound HTTP/1 requests that were normalized or rejected."
+    }
+}
+
+pub fn inbound() -> malformed::Metrics {
+    malformed::Metrics::new(inbound_http1_malformed_requests_total)
+}
@@ -0,0 +1,22 @@
+use crate::{
+    load_shed,
+    metrics::{self, Counter},
+};
+
+metrics::metrics! {
+    inbound_http_load_shed_total: Counter {
+        "The total number of inbound HTTP requests shed because the proxy was overloaded."
+    },
+
+    outbound_http_load_shed_total: Counter {
+        "The total number of outbound HTTP requests shed because the proxy was overloaded."
+    }
+}
+
+pub fn inbound() -> load_shed::Metrics {
+    load_shed::Metrics::new(inbound_http_load_shed_total)
+}
+
+pub fn outbound() -> load_shed::Metrics {
+    load_shed::Metrics::new(outbound_http_load_shed_total)
+}
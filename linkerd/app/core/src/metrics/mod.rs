@@ -1,8 +1,19 @@
+pub mod failover;
+mod http1_framing;
+mod http1_malformed;
+mod http_load_shed;
+mod policy_audit;
+pub mod tcp_accept;
 mod tcp_accept_errors;
+mod tcp_idle_timeouts;
+mod tls_connect_alpn;
 
 use crate::{
+    cache,
     classify::{Class, SuccessOrFailure},
-    control, dst, errors, http_metrics, http_metrics as metrics, opencensus, profiles,
+    control, dst, errors, http_cache, http_metrics, http_metrics as metrics, idempotency,
+    load_shed, opencensus, profiles,
+    proxy::http::{framing, malformed},
     stack_metrics,
     svc::Param,
     telemetry, tls,
@@ -28,8 +39,12 @@ pub type HttpRoute = http_metrics::Requests<RouteLabels, Class>;
 
 pub type HttpRouteRetry = http_metrics::Retries<RouteLabels>;
 
+pub type HttpQueueTime = http_metrics::QueueTime<StackLabels>;
+
 pub type Stack = stack_metrics::Registry<StackLabels>;
 
+pub type Cache = cache::Registry<StackLabels>;
+
 #[derive(Clone, Debug)]
 pub struct Proxy {
     pub http_route: HttpRoute,
@@ -37,9 +52,22 @@ pub struct Proxy {
     pub http_route_retry: HttpRouteRetry,
     pub http_endpoint: HttpEndpoint,
     pub http_errors: errors::MetricsLayer,
+    pub http_load_shed: load_shed::Metrics,
+    pub http1_malformed: malformed::Metrics,
+    pub http1_framing: framing::Metrics,
+    pub http_queue_time: HttpQueueTime,
     pub stack: Stack,
+    pub cache: Cache,
     pub transport: transport::Metrics,
     pub tcp_accept_errors: tcp_accept_errors::Registry,
+    pub tcp_accept: tcp_accept::Registry,
+    pub tcp_idle_timeouts: tcp_idle_timeouts::Registry,
+    pub policy_audit: policy_audit::Registry,
+    pub tls_connect_alpn: tls_connect_alpn::Registry,
+    pub failover: failover::Registry,
+    pub split: profiles::split::Registry,
+    pub http_idempotency: idempotency::Metrics,
+    pub http_response_cache: http_cache::Metrics,
 }
 
 #[derive(Clone, Debug)]
@@ -48,6 +76,7 @@ pub struct Metrics {
     pub outbound: Proxy,
     pub control: ControlHttp,
     pub opencensus: opencensus::metrics::Registry,
+    pub udp: transport::udp::Metrics,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -116,7 +145,14 @@ where
 // === impl Metrics ===
 
 impl Metrics {
-    pub fn new(retain_idle: Duration) -> (Self, impl FmtMetrics + Clone + Send + 'static) {
+    /// Creates the proxy's metrics.
+    ///
+    /// Transport metrics are also reported by peer, where at most
+    /// `peer_identity_limit` distinct peer identities are used as labels.
+    pub fn new(
+        retain_idle: Duration,
+        peer_identity_limit: usize,
+    ) -> (Self, impl FmtMetrics + Clone + Send + 'static) {
         let process = telemetry::process::Report::new(SystemTime::now());
 
         let build_info = telemetry::build_info::Report::new();
@@ -156,15 +192,53 @@ impl Metrics {
 
         let http_errors = errors::Metrics::default();
 
+        let http_queue_time = HttpQueueTime::default();
+
+        let inbound_http_load_shed = http_load_shed::inbound();
+        let outbound_http_load_shed = http_load_shed::outbound();
+
+        let http1_malformed = http1_malformed::inbound();
+
+        let http1_framing = http1_framing::inbound();
+
         let stack = stack_metrics::Registry::default();
 
-        let (transport, transport_report) = transport::metrics::new(retain_idle);
+        let cache = Cache::default();
+
+        let (transport_peers, transport_peers_report) = transport::metrics::rollup(retain_idle);
+        let (transport, transport_report) = {
+            let (transport, report) = transport::metrics::new(retain_idle);
+            let identities = transport::labels::PeerIdentities::new(peer_identity_limit);
+            let transport = transport.with_rollup(transport_peers, move |key| {
+                transport::labels::Peer::from_key(key, &identities)
+            });
+            (transport, report)
+        };
 
         let inbound_tcp_accept_errors = tcp_accept_errors::Registry::inbound();
         let outbound_tcp_accept_errors = tcp_accept_errors::Registry::outbound();
 
+        let inbound_tcp_accept = tcp_accept::Registry::inbound();
+        let outbound_tcp_accept = tcp_accept::Registry::outbound();
+
+        let inbound_tcp_idle_timeouts = tcp_idle_timeouts::Registry::inbound();
+        let outbound_tcp_idle_timeouts = tcp_idle_timeouts::Registry::outbound();
+
+        let policy_audit = policy_audit::Registry::default();
+
+        let tls_connect_alpn = tls_connect_alpn::Registry::default();
+
+        let failover = failover::Registry::default();
+
+        let split = profiles::split::Registry::default();
+
         let (opencensus, opencensus_report) = opencensus::metrics::new();
 
+        let udp = transport::udp::Metrics::default();
+
+        let http_idempotency = idempotency::Metrics::default();
+        let http_response_cache = http_cache::Metrics::default();
+
         let metrics = Metrics {
             inbound: Proxy {
                 http_endpoint: http_endpoint.clone(),
@@ -172,9 +246,22 @@ impl Metrics {
                 http_route_actual: http_route_actual.clone(),
                 http_route_retry: http_route_retry.clone(),
                 http_errors: http_errors.inbound(),
+                http_load_shed: inbound_http_load_shed.clone(),
+                http1_malformed: http1_malformed.clone(),
+                http1_framing: http1_framing.clone(),
+                http_queue_time: http_queue_time.clone(),
                 stack: stack.clone(),
+                cache: cache.clone(),
                 transport: transport.clone(),
                 tcp_accept_errors: inbound_tcp_accept_errors.clone(),
+                tcp_accept: inbound_tcp_accept.clone(),
+                tcp_idle_timeouts: inbound_tcp_idle_timeouts.clone(),
+                policy_audit: policy_audit.clone(),
+                tls_connect_alpn: tls_connect_alpn.clone(),
+                failover: failover.clone(),
+                split: split.clone(),
+                http_idempotency: http_idempotency.clone(),
+                http_response_cache: http_response_cache.clone(),
             },
             outbound: Proxy {
                 http_endpoint,
@@ -182,12 +269,26 @@ impl Metrics {
                 http_route_retry,
                 http_route_actual,
                 http_errors: http_errors.outbound(),
+                http_load_shed: outbound_http_load_shed.clone(),
+                http1_malformed: http1_malformed.clone(),
+                http1_framing: http1_framing.clone(),
+                http_queue_time: http_queue_time.clone(),
                 stack: stack.clone(),
+                cache: cache.clone(),
                 transport,
                 tcp_accept_errors: outbound_tcp_accept_errors.clone(),
+                tcp_accept: outbound_tcp_accept.clone(),
+                tcp_idle_timeouts: outbound_tcp_idle_timeouts.clone(),
+                policy_audit: policy_audit.clone(),
+                tls_connect_alpn: tls_connect_alpn.clone(),
+                failover: failover.clone(),
+                split: split.clone(),
+                http_idempotency: http_idempotency.clone(),
+                http_response_cache: http_response_cache.clone(),
             },
             control,
             opencensus,
+            udp: udp.clone(),
         };
 
         let report = (http_errors.report())
@@ -195,12 +296,30 @@ impl Metrics {
             .and_then(route_report)
             .and_then(retry_report)
             .and_then(actual_report)
+            .and_then(inbound_http_load_shed)
+            .and_then(outbound_http_load_shed)
+            .and_then(http1_malformed)
+            .and_then(http1_framing)
+            .and_then(http_queue_time)
             .and_then(control_report)
             .and_then(transport_report)
+            .and_then(transport_peers_report)
             .and_then(inbound_tcp_accept_errors)
             .and_then(outbound_tcp_accept_errors)
+            .and_then(inbound_tcp_accept)
+            .and_then(outbound_tcp_accept)
+            .and_then(inbound_tcp_idle_timeouts)
+            .and_then(outbound_tcp_idle_timeouts)
+            .and_then(policy_audit)
+            .and_then(tls_connect_alpn)
             .and_then(opencensus_report)
             .and_then(stack)
+            .and_then(cache)
+            .and_then(failover)
+            .and_then(split)
+            .and_then(udp)
+            .and_then(http_idempotency)
+            .and_then(http_response_cache)
             .and_then(process)
             .and_then(build_info);
 
@@ -0,0 +1,40 @@
+use crate::{
+    metrics::{self, Counter, FmtMetrics},
+    transport::{labels::TargetAddr, OrigDstAddr},
+};
+use parking_lot::Mutex;
+use std::{collections::HashMap, fmt, sync::Arc};
+
+metrics::metrics! {
+    inbound_policy_audit_denials_total: Counter {
+        "The total number of inbound connections that were permitted only because their server's policy is in audit mode."
+    }
+}
+
+/// Counts connections that would have been denied by policies in audit mode,
+/// by target address.
+#[derive(Clone, Debug, Default)]
+pub struct Registry(Arc<Mutex<HashMap<TargetAddr, Arc<Counter>>>>);
+
+// === impl Registry ===
+
+impl Registry {
+    pub fn record(&self, OrigDstAddr(addr): OrigDstAddr) {
+        self.0.lock().entry(TargetAddr(addr)).or_default().incr();
+    }
+}
+
+impl FmtMetrics for Registry {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counters = self.0.lock();
+        if counters.is_empty() {
+            return Ok(());
+        }
+
+        inbound_policy_audit_denials_total.fmt_help(f)?;
+        for (addr, counter) in counters.iter() {
+            inbound_policy_audit_denials_total.fmt_metric_labeled(f, counter, addr)?;
+        }
+        Ok(())
+    }
+}
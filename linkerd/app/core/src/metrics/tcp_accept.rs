@@ -0,0 +1,106 @@
+use crate::metrics::{self, Counter, FmtMetrics, Gauge};
+use std::{fmt, sync::Arc};
+
+metrics::metrics! {
+    inbound_tcp_accept_open_connections: Gauge {
+        "The number of connections accepted by the inbound listener that are currently open."
+    },
+
+    inbound_tcp_accept_failures_total: Counter {
+        "The total number of times the inbound listener failed to accept a connection."
+    },
+
+    inbound_tcp_accept_consecutive_failures: Gauge {
+        "The number of consecutive inbound accept failures. While non-zero, the listener backs off before accepting connections."
+    },
+
+    outbound_tcp_accept_open_connections: Gauge {
+        "The number of connections accepted by the outbound listener that are currently open."
+    },
+
+    outbound_tcp_accept_failures_total: Counter {
+        "The total number of times the outbound listener failed to accept a connection."
+    },
+
+    outbound_tcp_accept_consecutive_failures: Gauge {
+        "The number of consecutive outbound accept failures. While non-zero, the listener backs off before accepting connections."
+    }
+}
+
+/// Tracks the connections accepted by a listener and its failures to accept
+/// connections.
+#[derive(Clone, Debug)]
+pub struct Registry {
+    inner: Arc<Inner>,
+    open_metric: metrics::Metric<'static, &'static str, Gauge>,
+    failures_metric: metrics::Metric<'static, &'static str, Counter>,
+    consecutive_metric: metrics::Metric<'static, &'static str, Gauge>,
+}
+
+/// Counts an accepted connection as open until it is dropped.
+#[derive(Debug)]
+pub struct Open(Arc<Inner>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    open: Gauge,
+    failures: Counter,
+    consecutive: Gauge,
+}
+
+// === impl Registry ===
+
+impl Registry {
+    pub fn inbound() -> Self {
+        Self {
+            inner: Default::default(),
+            open_metric: inbound_tcp_accept_open_connections,
+            failures_metric: inbound_tcp_accept_failures_total,
+            consecutive_metric: inbound_tcp_accept_consecutive_failures,
+        }
+    }
+
+    pub fn outbound() -> Self {
+        Self {
+            inner: Default::default(),
+            open_metric: outbound_tcp_accept_open_connections,
+            failures_metric: outbound_tcp_accept_failures_total,
+            consecutive_metric: outbound_tcp_accept_consecutive_failures,
+        }
+    }
+
+    /// Records a failure to accept a connection, given the number of
+    /// consecutive failures, including this one.
+    pub fn failed(&self, consecutive: u64) {
+        self.inner.failures.incr();
+        self.inner.consecutive.set(consecutive);
+    }
+
+    /// Records that a connection was accepted, returning a handle that counts
+    /// the connection as open until it is dropped.
+    pub fn accepted(&self) -> Open {
+        self.inner.consecutive.set(0);
+        self.inner.open.incr();
+        Open(self.inner.clone())
+    }
+}
+
+impl FmtMetrics for Registry {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.open_metric.fmt_help(f)?;
+        self.open_metric.fmt_metric(f, &self.inner.open)?;
+        self.failures_metric.fmt_help(f)?;
+        self.failures_metric.fmt_metric(f, &self.inner.failures)?;
+        self.consecutive_metric.fmt_help(f)?;
+        self.consecutive_metric
+            .fmt_metric(f, &self.inner.consecutive)
+    }
+}
+
+// === impl Open ===
+
+impl Drop for Open {
+    fn drop(&mut self) {
+        self.0.open.decr();
+    }
+}
@@ -5,7 +5,7 @@ use crate::{
 };
 use linkerd_error::Error;
 use linkerd_error_metrics::{FmtLabels, LabelError, RecordError};
-use linkerd_tls::server::ServerTlsTimeoutError;
+use linkerd_tls::server::{HandshakeRateLimitedError, ServerTlsTimeoutError};
 use parking_lot::Mutex;
 use std::{collections::HashMap, fmt};
 
@@ -36,6 +36,7 @@ pub struct LabelAcceptErrors(());
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub enum AcceptErrors {
     TlsDetectTimeout,
+    TlsHandshakeRateLimited,
     Io,
     Other,
 }
@@ -91,6 +92,8 @@ impl LabelError<Error> for LabelAcceptErrors {
         while let Some(err) = curr {
             if err.is::<ServerTlsTimeoutError>() {
                 return AcceptErrors::TlsDetectTimeout;
+            } else if err.is::<HandshakeRateLimitedError>() {
+                return AcceptErrors::TlsHandshakeRateLimited;
             } else if err.is::<std::io::Error>() {
                 // We ignore the error code because we want all labels to be consistent.
                 return AcceptErrors::Io;
@@ -108,6 +111,9 @@ impl FmtLabels for AcceptErrors {
     fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::TlsDetectTimeout => fmt::Display::fmt("error=\"tls_detect_timeout\"", f),
+            Self::TlsHandshakeRateLimited => {
+                fmt::Display::fmt("error=\"tls_handshake_rate_limited\"", f)
+            }
             Self::Io => fmt::Display::fmt("error=\"io\"", f),
             Self::Other => fmt::Display::fmt("error=\"other\"", f),
         }
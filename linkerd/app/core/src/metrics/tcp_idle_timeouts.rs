@@ -0,0 +1,52 @@
+use crate::{
+    metrics::{self, Counter, FmtMetrics},
+    proxy::tcp,
+};
+use std::{fmt, sync::Arc, time::Duration};
+
+metrics::metrics! {
+    inbound_tcp_idle_timeouts_total: Counter {
+        "The total number of forwarded inbound TCP connections closed after being idle."
+    },
+
+    outbound_tcp_idle_timeouts_total: Counter {
+        "The total number of forwarded outbound TCP connections closed after being idle."
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Registry {
+    closed: Arc<Counter>,
+    metric: metrics::Metric<'static, &'static str, Counter>,
+}
+
+// === impl Registry ===
+
+impl Registry {
+    pub fn inbound() -> Self {
+        Self {
+            closed: Default::default(),
+            metric: inbound_tcp_idle_timeouts_total,
+        }
+    }
+
+    pub fn outbound() -> Self {
+        Self {
+            closed: Default::default(),
+            metric: outbound_tcp_idle_timeouts_total,
+        }
+    }
+
+    /// Configures forwarded connections to be closed after being idle for
+    /// `timeout`, if one is set.
+    pub fn idle(&self, timeout: Option<Duration>) -> Option<tcp::Idle> {
+        timeout.map(|t| tcp::Idle::new(t, self.closed.clone()))
+    }
+}
+
+impl FmtMetrics for Registry {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.metric.fmt_help(f)?;
+        self.metric.fmt_metric(f, &*self.closed)
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{
+    metrics::{self, Counter, FmtLabels, FmtMetrics},
+    svc, tls,
+    transport::labels,
+};
+use futures::prelude::*;
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    fmt,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+metrics::metrics! {
+    outbound_tls_connect_alpn_total: Counter {
+        "The total number of mTLS connections established to outbound endpoints, by negotiated ALPN protocol."
+    }
+}
+
+/// Counts mTLS connections by endpoint and negotiated ALPN protocol.
+#[derive(Clone, Debug, Default)]
+pub struct Registry(Arc<Mutex<HashMap<(labels::Key, Alpn), Arc<Counter>>>>);
+
+#[derive(Clone, Debug)]
+pub struct RecordAlpn<S> {
+    inner: S,
+    registry: Registry,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Alpn(Option<tls::NegotiatedProtocol>);
+
+// === impl Registry ===
+
+impl Registry {
+    pub fn layer<S>(&self) -> impl svc::Layer<S, Service = RecordAlpn<S>> + Clone {
+        let registry = self.clone();
+        svc::layer::mk(move |inner| RecordAlpn {
+            inner,
+            registry: registry.clone(),
+        })
+    }
+
+    fn record(&self, key: labels::Key, alpn: Option<tls::NegotiatedProtocolRef<'_>>) {
+        let alpn = Alpn(alpn.map(Into::into));
+        self.0.lock().entry((key, alpn)).or_default().incr();
+    }
+}
+
+impl FmtMetrics for Registry {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counters = self.0.lock();
+        if counters.is_empty() {
+            return Ok(());
+        }
+
+        outbound_tls_connect_alpn_total.fmt_help(f)?;
+        for (labels, counter) in counters.iter() {
+            outbound_tls_connect_alpn_total.fmt_metric_labeled(f, counter, labels)?;
+        }
+        Ok(())
+    }
+}
+
+// === impl RecordAlpn ===
+
+impl<T, S> svc::Service<T> for RecordAlpn<S>
+where
+    T: svc::Param<labels::Key> + svc::Param<tls::ConditionalClientTls>,
+    S: svc::Service<T>,
+    S::Response: tls::HasNegotiatedProtocol + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, target: T) -> Self::Future {
+        // Only connections on which mTLS is established are recorded.
+        let tls: tls::ConditionalClientTls = target.param();
+        let record = tls.is_some().then(|| {
+            (
+                self.registry.clone(),
+                svc::Param::<labels::Key>::param(&target),
+            )
+        });
+
+        Box::pin(self.inner.call(target).map_ok(move |io| {
+            if let Some((registry, key)) = record {
+                registry.record(key, io.negotiated_protocol());
+            }
+            io
+        }))
+    }
+}
+
+// === impl Alpn ===
+
+impl FmtLabels for Alpn {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(tls::NegotiatedProtocol(ref p)) => {
+                write!(f, "alpn=\"{}\"", String::from_utf8_lossy(p))
+            }
+            None => f.write_str("alpn=\"\""),
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use crate::{proxy::http, Addr};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::debug;
+
+/// Configures how protocol hints are learned from protocol detection.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// How long a learned hint is used before the destination's protocol is
+    /// detected again.
+    pub ttl: Duration,
+
+    /// The number of consecutive identical detections after which a
+    /// destination's protocol is learned.
+    pub threshold: usize,
+}
+
+/// A protocol learned for a destination.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    Opaque,
+    Http1,
+}
+
+/// Learns the protocols of destinations that are repeatedly detected as
+/// opaque or HTTP/1, so that detection may be skipped for them until the hint
+/// expires.
+///
+/// HTTP/2 is never learned, since detecting it only requires reading the
+/// client's preface.
+#[derive(Clone, Debug, Default)]
+pub struct ProtocolHints(Option<Arc<Inner>>);
+
+#[derive(Debug)]
+struct Inner {
+    config: Config,
+    dsts: Mutex<HashMap<Addr, Observed>>,
+}
+
+#[derive(Debug)]
+struct Observed {
+    protocol: Protocol,
+    count: usize,
+    learned: Option<Instant>,
+}
+
+/// A learned hint, as described by the admin server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hint {
+    pub dst: Addr,
+    pub protocol: Protocol,
+    pub expires_in: Duration,
+}
+
+const MAX_DESTINATIONS: usize = 10_000;
+
+// === impl ProtocolHints ===
+
+impl ProtocolHints {
+    pub fn new(config: Config) -> Self {
+        Self(Some(Arc::new(Inner {
+            config,
+            dsts: Mutex::new(HashMap::new()),
+        })))
+    }
+
+    /// Returns the protocol learned for a destination, if it has not expired.
+    pub fn get(&self, dst: &Addr) -> Option<Protocol> {
+        let inner = self.0.as_ref()?;
+        let mut dsts = inner.dsts.lock();
+        let observed = dsts.get(dst)?;
+        let learned = observed.learned?;
+        if learned.elapsed() >= inner.config.ttl {
+            debug!(%dst, protocol = ?observed.protocol, "Protocol hint expired");
+            dsts.remove(dst);
+            return None;
+        }
+        Some(observed.protocol)
+    }
+
+    /// Records the result of detecting a destination's protocol, where `None`
+    /// indicates that the connection was not HTTP.
+    pub fn record(&self, dst: Addr, version: Option<http::Version>) {
+        let inner = match self.0.as_ref() {
+            Some(inner) => inner,
+            None => return,
+        };
+        let protocol = match version {
+            None => Protocol::Opaque,
+            Some(http::Version::Http1) => Protocol::Http1,
+            Some(http::Version::H2) => {
+                inner.dsts.lock().remove(&dst);
+                return;
+            }
+        };
+
+        let mut dsts = inner.dsts.lock();
+        if !dsts.contains_key(&dst) && dsts.len() >= MAX_DESTINATIONS {
+            // Forget destinations whose protocol isn't currently learned
+            // before tracking another.
+            let ttl = inner.config.ttl;
+            dsts.retain(|_, o| o.learned.map_or(false, |l| l.elapsed() < ttl));
+            if dsts.len() >= MAX_DESTINATIONS {
+                return;
+            }
+        }
+
+        let observed = dsts.entry(dst.clone()).or_insert(Observed {
+            protocol,
+            count: 0,
+            learned: None,
+        });
+        if observed.protocol != protocol {
+            *observed = Observed {
+                protocol,
+                count: 0,
+                learned: None,
+            };
+        }
+        observed.count += 1;
+        if observed.learned.is_none() && observed.count >= inner.config.threshold {
+            debug!(%dst, ?protocol, "Learned protocol hint");
+            observed.learned = Some(Instant::now());
+        }
+    }
+
+    /// Returns the hints that have been learned and have not expired.
+    pub fn hints(&self) -> Vec<Hint> {
+        let inner = match self.0.as_ref() {
+            Some(inner) => inner,
+            None => return Vec::new(),
+        };
+        let ttl = inner.config.ttl;
+        let mut hints = inner
+            .dsts
+            .lock()
+            .iter()
+            .filter_map(|(dst, o)| {
+                let expires_in = ttl.checked_sub(o.learned?.elapsed())?;
+                Some(Hint {
+                    dst: dst.clone(),
+                    protocol: o.protocol,
+                    expires_in,
+                })
+            })
+            .collect::<Vec<_>>();
+        hints.sort_by_key(|h| h.dst.to_string());
+        hints
+    }
+
+    /// Forgets all learned hints and observations, returning the number of
+    /// hints that were cleared.
+    pub fn clear(&self) -> usize {
+        let inner = match self.0.as_ref() {
+            Some(inner) => inner,
+            None => return 0,
+        };
+        let ttl = inner.config.ttl;
+        let mut dsts = inner.dsts.lock();
+        let cleared = dsts
+            .values()
+            .filter(|o| o.learned.map_or(false, |l| l.elapsed() < ttl))
+            .count();
+        dsts.clear();
+        cleared
+    }
+
+    /// Returns true if hints are learned.
+    pub fn is_enabled(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+// === impl Protocol ===
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Opaque => "opaque".fmt(f),
+            Self::Http1 => "HTTP/1".fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dst(s: &str) -> Addr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn learns_after_threshold() {
+        let hints = ProtocolHints::new(Config {
+            ttl: Duration::from_secs(60),
+            threshold: 2,
+        });
+        let web = dst("web.example.com:80");
+        let db = dst("192.0.2.3:5432");
+
+        hints.record(web.clone(), Some(http::Version::Http1));
+        assert_eq!(hints.get(&web), None);
+        hints.record(web.clone(), Some(http::Version::Http1));
+        assert_eq!(hints.get(&web), Some(Protocol::Http1));
+
+        hints.record(db.clone(), None);
+        hints.record(db.clone(), Some(http::Version::Http1));
+        hints.record(db.clone(), None);
+        assert_eq!(hints.get(&db), None, "observations must be consecutive");
+        hints.record(db.clone(), None);
+        assert_eq!(hints.get(&db), Some(Protocol::Opaque));
+
+        assert_eq!(
+            hints.hints().iter().map(|h| &h.dst).collect::<Vec<_>>(),
+            vec![&db, &web]
+        );
+
+        hints.record(web.clone(), Some(http::Version::H2));
+        assert_eq!(hints.get(&web), None, "HTTP/2 must not be learned");
+
+        assert_eq!(hints.clear(), 1);
+        assert_eq!(hints.get(&db), None);
+        assert!(hints.hints().is_empty());
+    }
+
+    #[test]
+    fn hints_expire() {
+        let hints = ProtocolHints::new(Config {
+            ttl: Duration::from_secs(0),
+            threshold: 1,
+        });
+        let web = dst("web.example.com:80");
+        hints.record(web.clone(), Some(http::Version::Http1));
+        assert_eq!(hints.get(&web), None);
+        assert!(hints.hints().is_empty());
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let hints = ProtocolHints::default();
+        let web = dst("web.example.com:80");
+        hints.record(web.clone(), Some(http::Version::Http1));
+        assert_eq!(hints.get(&web), None);
+        assert!(!hints.is_enabled());
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{proxy::http, svc};
+use std::task::{Context, Poll};
+use tracing::{
+    info_span,
+    instrument::{Instrument, Instrumented},
+    trace,
+};
+
+/// Configures how requests are identified in the proxy's logs.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// The header that carries a request's ID.
+    pub header: http::HeaderName,
+
+    /// Whether an ID is generated for requests that don't have one. Generated
+    /// IDs are set on the request, so that they're observed by the
+    /// application and propagated with requests it makes.
+    pub generate: bool,
+}
+
+/// A request's ID, set as a request extension.
+#[derive(Clone, Debug)]
+pub struct RequestId(pub http::HeaderValue);
+
+/// Records the ID of each request on a tracing span, generating IDs for
+/// requests that don't have one, if configured.
+#[derive(Clone, Debug)]
+pub struct SetRequestId<S> {
+    inner: S,
+    config: Option<Config>,
+}
+
+/// Generates a random request ID.
+pub fn generate() -> http::HeaderValue {
+    let id = format!("{:016x}", rand::random::<u64>());
+    http::HeaderValue::from_str(&id).expect("request ID must be a valid header")
+}
+
+// === impl SetRequestId ===
+
+impl<S> SetRequestId<S> {
+    pub fn layer(config: Option<Config>) -> impl svc::Layer<S, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            config: config.clone(),
+        })
+    }
+}
+
+impl<S, B> svc::Service<http::Request<B>> for SetRequestId<S>
+where
+    S: svc::Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Instrumented<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        let config = match self.config.as_ref() {
+            Some(config) => config,
+            None => return self.inner.call(req).instrument(tracing::Span::none()),
+        };
+
+        let id = match req.headers().get(&config.header).cloned() {
+            Some(id) => Some(id),
+            None if config.generate => {
+                let id = generate();
+                trace!(header = %config.header, ?id, "Setting request ID");
+                req.headers_mut().insert(config.header.clone(), id.clone());
+                Some(id)
+            }
+            None => None,
+        };
+
+        match id {
+            Some(id) => {
+                let span = info_span!("request", id = ?id);
+                req.extensions_mut().insert(RequestId(id));
+                self.inner.call(req).instrument(span)
+            }
+            None => self.inner.call(req).instrument(tracing::Span::none()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+    use tower::ServiceExt;
+
+    fn config(generate: bool) -> Config {
+        Config {
+            header: http::HeaderName::from_static("x-request-id"),
+            generate,
+        }
+    }
+
+    async fn request_id(config: Option<Config>, id: Option<&str>) -> Option<String> {
+        let mut req = http::Request::builder();
+        if let Some(id) = id {
+            req = req.header("x-request-id", id);
+        }
+        let inner = svc::mk(|req: http::Request<()>| future::ok::<_, crate::Error>(req));
+        let req = SetRequestId { inner, config }
+            .oneshot(req.body(()).unwrap())
+            .await
+            .expect("request must succeed");
+        let header = req.headers().get("x-request-id").cloned();
+        let ext = req.extensions().get::<RequestId>().map(|RequestId(id)| id);
+        assert_eq!(header.as_ref(), ext, "extension must match header");
+        header.map(|id| id.to_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn generates_missing_ids() {
+        let id = request_id(Some(config(true)), None).await.unwrap();
+        assert_eq!(id.len(), 16);
+        assert_eq!(
+            request_id(Some(config(true)), Some("abc")).await.as_deref(),
+            Some("abc")
+        );
+    }
+
+    #[tokio::test]
+    async fn records_existing_ids() {
+        assert_eq!(request_id(Some(config(false)), None).await, None);
+        assert_eq!(
+            request_id(Some(config(false)), Some("abc"))
+                .await
+                .as_deref(),
+            Some("abc")
+        );
+        assert_eq!(request_id(None, None).await, None);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{errors::HttpError, proxy::http, svc, Error};
+use futures::{future, TryFutureExt};
+use std::task::{Context, Poll};
+use tracing::debug;
+
+/// Limits the size of the HTTP request heads accepted by a server.
+///
+/// Requests that exceed a limit fail with an `HttpError` so that they're
+/// answered with a 431 (or, for URIs, a 414) response and recorded by reason in
+/// error metrics.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Limits {
+    /// The maximum number of header fields in a request.
+    pub max_headers: Option<usize>,
+
+    /// The maximum size of a single header field, i.e. the combined length of
+    /// its name and value.
+    pub max_header_size: Option<usize>,
+
+    /// The maximum combined size of all of a request's header fields.
+    pub max_header_list_size: Option<usize>,
+
+    /// The maximum length of a request's URI.
+    pub max_uri_len: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EnforceLimits<S> {
+    inner: S,
+    limits: Limits,
+}
+
+// === impl Limits ===
+
+impl Limits {
+    fn check<B>(&self, req: &http::Request<B>) -> Result<(), HttpError> {
+        if let Some(max) = self.max_uri_len {
+            let len = uri_len(req.uri());
+            if len > max {
+                debug!(len, max, "Request URI too long");
+                return Err(HttpError::uri_too_long());
+            }
+        }
+
+        let headers = req.headers();
+        if let Some(max) = self.max_headers {
+            if headers.len() > max {
+                debug!(headers = headers.len(), max, "Request has too many headers");
+                return Err(HttpError::too_many_headers());
+            }
+        }
+
+        if self.max_header_size.is_none() && self.max_header_list_size.is_none() {
+            return Ok(());
+        }
+        let mut total = 0;
+        for (name, value) in headers.iter() {
+            let size = name.as_str().len() + value.len();
+            if let Some(max) = self.max_header_size {
+                if size > max {
+                    debug!(%name, size, max, "Request header too large");
+                    return Err(HttpError::header_too_large());
+                }
+            }
+            total += size;
+        }
+        if let Some(max) = self.max_header_list_size {
+            if total > max {
+                debug!(size = total, max, "Request headers too large");
+                return Err(HttpError::headers_too_large());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn uri_len(uri: &http::uri::Uri) -> usize {
+    // Includes the `://` separating the scheme from the authority.
+    let scheme = uri.scheme_str().map(|s| s.len() + 3).unwrap_or(0);
+    let authority = uri.authority().map(|a| a.as_str().len()).unwrap_or(0);
+    let path = uri.path_and_query().map(|p| p.as_str().len()).unwrap_or(0);
+    scheme + authority + path
+}
+
+// === impl EnforceLimits ===
+
+impl<S> EnforceLimits<S> {
+    pub fn layer(limits: Limits) -> impl svc::Layer<S, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self { inner, limits })
+    }
+}
+
+impl<S, B> svc::Service<http::Request<B>> for EnforceLimits<S>
+where
+    S: svc::Service<http::Request<B>>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::Either<
+        future::ErrInto<S::Future, Error>,
+        future::Ready<Result<S::Response, Error>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        if let Err(e) = self.limits.check(&req) {
+            return future::Either::Right(future::err(e.into()));
+        }
+
+        future::Either::Left(self.inner.call(req).err_into::<Error>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(uri: &str, headers: &[(&'static str, &str)]) -> http::Request<()> {
+        let mut req = http::Request::builder().uri(uri);
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        req.body(()).unwrap()
+    }
+
+    fn status(limits: Limits, req: &http::Request<()>) -> Option<http::StatusCode> {
+        limits.check(req).err().map(|e| e.status())
+    }
+
+    #[test]
+    fn unlimited() {
+        let req = request("http://example.com/foo?bar", &[("x-foo", "bar")]);
+        assert_eq!(status(Limits::default(), &req), None);
+    }
+
+    #[test]
+    fn uri_length() {
+        let limits = Limits {
+            max_uri_len: Some(22),
+            ..Default::default()
+        };
+        let req = request("http://example.com/foo", &[]);
+        assert_eq!(status(limits, &req), None);
+        let req = request("http://example.com/foo?", &[]);
+        assert_eq!(status(limits, &req), Some(http::StatusCode::URI_TOO_LONG));
+        let req = request("/foo?bar", &[]);
+        assert_eq!(status(limits, &req), None);
+    }
+
+    #[test]
+    fn headers() {
+        let req = request("/", &[("x-foo", "bar"), ("x-foo", "bazz")]);
+        let too_large = Some(http::StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+
+        let limits = Limits {
+            max_headers: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(status(limits, &req), too_large);
+
+        let limits = Limits {
+            max_header_size: Some(8),
+            ..Default::default()
+        };
+        assert_eq!(status(limits, &req), too_large);
+        let limits = Limits {
+            max_header_size: Some(9),
+            ..Default::default()
+        };
+        assert_eq!(status(limits, &req), None);
+
+        let limits = Limits {
+            max_header_list_size: Some(16),
+            ..Default::default()
+        };
+        assert_eq!(status(limits, &req), too_large);
+        let limits = Limits {
+            max_header_list_size: Some(17),
+            ..Default::default()
+        };
+        assert_eq!(status(limits, &req), None);
+    }
+}
@@ -2,12 +2,15 @@ use super::classify;
 use super::dst::Route;
 use super::http_metrics::retries::Handle;
 use super::metrics::HttpRouteRetry;
-use crate::profiles;
+use crate::{
+    http_tracing::{Attribute, SpanAttributes},
+    profiles,
+};
 use futures::future;
 use linkerd_error::Error;
 use linkerd_http_classify::{Classify, ClassifyEos, ClassifyResponse};
 use linkerd_http_retry::ReplayBody;
-use linkerd_proxy_http::ClientHandle;
+use linkerd_proxy_http::{timeout::PerTryTimeout, ClientHandle};
 use linkerd_retry as retry;
 use linkerd_stack::{layer, Either, Param};
 use std::sync::Arc;
@@ -80,6 +83,18 @@ impl RetryPolicy {
             return false;
         }
 
+        // Requests that expect a `100 Continue` response are never retried.
+        // The client withholds the body until the server responds, so if the
+        // server rejects the first attempt without reading the body, a retry
+        // would wait on a body that the client will never send.
+        if has_body && expects_continue(req) {
+            tracing::trace!(
+                req.has_body = has_body,
+                "not retryable: expects 100-continue"
+            );
+            return false;
+        }
+
         tracing::trace!(
             req.has_body = has_body,
             req.content_length = ?content_length(req),
@@ -89,7 +104,15 @@ impl RetryPolicy {
     }
 }
 
-impl<A, B, E> retry::Policy<http::Request<A>, http::Response<B>, E> for RetryPolicy
+fn expects_continue<A>(req: &http::Request<A>) -> bool {
+    req.headers()
+        .get(http::header::EXPECT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.eq_ignore_ascii_case("100-continue"))
+        .unwrap_or(false)
+}
+
+impl<A, B> retry::Policy<http::Request<A>, http::Response<B>, Error> for RetryPolicy
 where
     A: http_body::Body + Clone,
 {
@@ -98,10 +121,12 @@ where
     fn retry(
         &self,
         req: &http::Request<A>,
-        result: Result<&http::Response<B>, &E>,
+        result: Result<&http::Response<B>, &Error>,
     ) -> Option<Self::Future> {
         let retryable = match result {
-            Err(_) => false,
+            // Attempts that exceed the per-try timeout may be retried, as long
+            // as the total request timeout has not elapsed.
+            Err(e) => e.is::<PerTryTimeout>(),
             Ok(rsp) => classify::Request::from(self.response_classes.clone())
                 .classify(req)
                 .start(rsp)
@@ -145,11 +170,24 @@ where
             clone.extensions_mut().insert(client_handle);
         }
 
+        // The clone is sent as the request's next attempt, so its span records
+        // the attempt number when configured. The first attempt is not
+        // numbered.
+        if let Some(attributes) = req.extensions().get::<SpanAttributes>() {
+            let attempt = attributes
+                .get(Attribute::RetryAttempt)
+                .and_then(|a| a.parse::<usize>().ok())
+                .unwrap_or(1);
+            let attributes = attributes.child();
+            attributes.set(Attribute::RetryAttempt, attempt + 1);
+            clone.extensions_mut().insert(attributes);
+        }
+
         Some(clone)
     }
 }
 
-impl<A, B, E> retry::PrepareRequest<http::Request<A>, http::Response<B>, E> for RetryPolicy
+impl<A, B> retry::PrepareRequest<http::Request<A>, http::Response<B>, Error> for RetryPolicy
 where
     A: http_body::Body + Unpin,
     A::Error: Into<Error>,
@@ -166,3 +204,23 @@ where
         Either::B(req)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_expect_continue() {
+        let req = |expect: Option<&str>| {
+            let mut req = http::Request::builder();
+            if let Some(expect) = expect {
+                req = req.header(http::header::EXPECT, expect);
+            }
+            req.body(()).unwrap()
+        };
+        assert!(expects_continue(&req(Some("100-continue"))));
+        assert!(expects_continue(&req(Some("100-Continue"))));
+        assert!(!expects_continue(&req(Some("something-else"))));
+        assert!(!expects_continue(&req(None)));
+    }
+}
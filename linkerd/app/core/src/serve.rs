@@ -1,20 +1,38 @@
 use crate::{
-    io,
+    errors,
+    exp_backoff::{ExponentialBackoff, ExponentialBackoffStream},
+    http_tracing, io,
+    metrics::tcp_accept,
     svc::{self, Param},
     transport::{ClientAddr, Remote},
 };
 use futures::prelude::*;
 use linkerd_error::Error;
+use std::time::Duration;
 use tower::util::ServiceExt;
 use tracing::{debug, debug_span, info, instrument::Instrument, warn};
 
+/// Delays accepting connections after the listener fails (e.g. because the
+/// process has exhausted its file descriptors), so that the accept loop does
+/// not spin.
+const ACCEPT_BACKOFF: ExponentialBackoff = ExponentialBackoff {
+    min: Duration::from_millis(10),
+    max: Duration::from_secs(1),
+    jitter: 0.5,
+};
+
 /// Spawns a task that binds an `L`-typed listener with an `A`-typed
 /// connection-accepting service.
 ///
+/// When the listener fails to accept connections, accepting is delayed with
+/// a jittered exponential backoff until a connection is accepted. Accepted
+/// connections and failures are recorded in `metrics`, if it is set.
+///
 /// The task is driven until shutdown is signaled.
 pub async fn serve<M, S, I, A>(
     listen: impl Stream<Item = std::io::Result<(A, I)>>,
     mut new_accept: M,
+    metrics: Option<tcp_accept::Registry>,
     shutdown: impl Future,
 ) where
     I: Send + 'static,
@@ -26,15 +44,40 @@ pub async fn serve<M, S, I, A>(
 {
     let accept = async move {
         futures::pin_mut!(listen);
+        let mut backoff: Option<ExponentialBackoffStream> = None;
+        let mut consecutive_failures = 0u64;
         loop {
             match listen.next().await {
                 None => return,
                 Some(conn) => {
-                    // If the listener returned an error, complete the task.
+                    // If the listener returned an error, back off before
+                    // accepting another connection.
                     let (addrs, io) = match conn {
-                        Ok(conn) => conn,
+                        Ok(conn) => {
+                            if consecutive_failures > 0 {
+                                debug!(consecutive_failures, "Server accepted connection");
+                                consecutive_failures = 0;
+                                backoff = None;
+                            }
+                            conn
+                        }
                         Err(error) => {
-                            warn!(%error, "Server failed to accept connection");
+                            consecutive_failures += 1;
+                            if let Some(metrics) = metrics.as_ref() {
+                                metrics.failed(consecutive_failures);
+                            }
+                            // Warn only as the number of consecutive failures
+                            // doubles, so that a failing listener doesn't
+                            // flood the logs.
+                            if consecutive_failures.is_power_of_two() {
+                                warn!(%error, consecutive_failures, "Server failed to accept connection");
+                            } else {
+                                debug!(%error, consecutive_failures, "Server failed to accept connection");
+                            }
+                            backoff
+                                .get_or_insert_with(|| ACCEPT_BACKOFF.stream())
+                                .next()
+                                .await;
                             continue;
                         }
                     };
@@ -43,10 +86,14 @@ pub async fn serve<M, S, I, A>(
                     let span = debug_span!("accept", client.addr = %addrs.param());
 
                     let accept = span.in_scope(|| new_accept.new_service(addrs));
+                    let open = metrics.as_ref().map(tcp_accept::Registry::accepted);
 
                     // Dispatch all of the work for a given connection onto a connection-specific task.
+                    //
+                    // Spans recorded while the connection is established are
+                    // emitted with its first traced request.
                     tokio::spawn(
-                        async move {
+                        http_tracing::scope_connection(async move {
                             match accept.ready_oneshot().err_into::<Error>().await {
                                 Ok(mut accept) => {
                                     match accept
@@ -58,7 +105,14 @@ pub async fn serve<M, S, I, A>(
                                         Err(reason) if is_io(&*reason) => {
                                             debug!(%reason, "Connection closed")
                                         }
-                                        Err(error) => info!(%error, "Connection closed"),
+                                        Err(error) => {
+                                            let error: &(dyn std::error::Error + 'static) = &*error;
+                                            info!(
+                                                error.category = %errors::category(error),
+                                                error,
+                                                "Connection closed"
+                                            )
+                                        }
                                     }
                                     // Hold the service until the connection is
                                     // complete. This helps tie any inner cache
@@ -66,10 +120,16 @@ pub async fn serve<M, S, I, A>(
                                     drop(accept);
                                 }
                                 Err(error) => {
-                                    warn!(%error, "Server failed to become ready");
+                                    let error: &(dyn std::error::Error + 'static) = &*error;
+                                    warn!(
+                                        error.category = %errors::category(error),
+                                        error,
+                                        "Server failed to become ready"
+                                    );
                                 }
                             }
-                        }
+                            drop(open);
+                        })
                         .instrument(span),
                     );
                 }
@@ -89,3 +149,30 @@ pub async fn serve<M, S, I, A>(
 fn is_io(e: &(dyn std::error::Error + 'static)) -> bool {
     e.is::<io::Error>() || e.source().map(is_io).unwrap_or(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{metrics::FmtMetrics, transport::listen::Addrs};
+
+    #[tokio::test]
+    async fn records_accept_failures() {
+        let metrics = tcp_accept::Registry::inbound();
+        let listen = stream::iter(vec![
+            Err(std::io::ErrorKind::Other.into()),
+            Err(std::io::ErrorKind::Other.into()),
+        ]);
+        let new_accept = |_: Addrs| svc::mk(|_: io::ScopedIo<()>| future::ok::<(), Error>(()));
+        serve(
+            listen,
+            new_accept,
+            Some(metrics.clone()),
+            future::pending::<()>(),
+        )
+        .await;
+
+        let metrics = metrics.as_display().to_string();
+        assert!(metrics.contains("inbound_tcp_accept_failures_total 2\n"));
+        assert!(metrics.contains("inbound_tcp_accept_consecutive_failures 2\n"));
+    }
+}
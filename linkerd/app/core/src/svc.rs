@@ -26,6 +26,8 @@ pub use tower::{
     spawn_ready::SpawnReady, Service, ServiceExt,
 };
 
+pub mod extension;
+
 #[derive(Copy, Clone, Debug)]
 pub struct AlwaysReconnect(ExponentialBackoff);
 
@@ -201,13 +203,13 @@ impl<S> Stack<S> {
         self.push(http::insert::NewInsert::layer())
     }
 
-    pub fn push_cache<T>(self, idle: Duration) -> Stack<cache::Cache<T, S>>
+    pub fn push_cache<T>(self, idle: Duration, metrics: cache::Metrics) -> Stack<cache::Cache<T, S>>
     where
         T: Clone + Eq + std::fmt::Debug + std::hash::Hash + Send + Sync + 'static,
         S: NewService<T> + 'static,
         S::Service: Send + Sync + 'static,
     {
-        self.push(cache::Cache::layer(idle))
+        self.push(cache::Cache::layer(idle, metrics))
     }
 
     /// Push a service that either calls the inner service if it is ready, or
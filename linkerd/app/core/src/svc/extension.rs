@@ -0,0 +1,346 @@
+//! Out-of-tree extensions to the proxy's stacks.
+//!
+//! When the `extensions` feature is enabled, crates that build the proxy may
+//! register [`Extension`]s on the application's [`Registry`]. Each extension
+//! is invoked at named points in the inbound and outbound stacks, where it may
+//! observe or reject connections and observe, modify, or reject HTTP requests
+//! and responses.
+//!
+//! When the feature is disabled, the registry is always empty and the stacks'
+//! extension points pass connections and requests through unchanged.
+
+pub use crate::metrics::Direction;
+use crate::{svc, transport::OrigDstAddr, Error};
+use futures::{future, prelude::*, ready};
+use pin_project::pin_project;
+#[cfg(feature = "extensions")]
+use std::sync::Arc;
+use std::{
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// A named point in a stack at which extensions are invoked.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Point {
+    /// Connections as they are accepted by the proxy, before protocol
+    /// detection.
+    Accept,
+
+    /// HTTP requests as they are received by the proxy's HTTP server.
+    ServerHttp,
+
+    /// HTTP requests as they are dispatched to a logical destination.
+    Logical,
+
+    /// HTTP requests as they are dispatched to an individual endpoint.
+    Endpoint,
+}
+
+/// Where in the proxy an extension is invoked.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Location {
+    pub direction: Direction,
+    pub point: Point,
+}
+
+/// Hooks invoked at a stack's extension points.
+///
+/// All hooks have no-op defaults so that extensions need only implement the
+/// hooks they use.
+#[cfg(feature = "extensions")]
+pub trait Extension: Send + Sync + 'static {
+    /// Invoked when a connection is accepted. Returning an error refuses the
+    /// connection.
+    fn accept(&self, _direction: Direction, _orig_dst: OrigDstAddr) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Invoked with the head of each HTTP request. Returning an error fails
+    /// the request without dispatching it.
+    fn request(&self, _at: Location, _req: &mut http::Request<()>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Invoked with the head of each HTTP response.
+    fn response(&self, _at: Location, _rsp: &mut http::Response<()>) {}
+
+    /// Invoked when an HTTP request fails without a response.
+    fn error(&self, _at: Location, _error: &Error) {}
+}
+
+/// The extensions registered with the proxy.
+///
+/// Extensions are invoked in the order in which they are registered.
+#[derive(Clone, Default)]
+pub struct Registry {
+    #[cfg(feature = "extensions")]
+    extensions: Arc<Vec<Arc<dyn Extension>>>,
+}
+
+/// Refuses accepted connections that are rejected by an extension.
+#[derive(Clone, Debug)]
+pub struct AcceptFilter {
+    registry: Registry,
+    direction: Direction,
+}
+
+#[derive(Clone, Debug)]
+pub struct ExtendLayer {
+    registry: Registry,
+    at: Location,
+}
+
+/// Invokes extensions on the HTTP requests and responses of an inner service.
+#[derive(Clone, Debug)]
+pub struct Extend<S> {
+    inner: S,
+    registry: Registry,
+    at: Location,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    registry: Registry,
+    at: Location,
+}
+
+// === impl Registry ===
+
+impl Registry {
+    /// Registers an extension to be invoked after all previously registered
+    /// extensions.
+    #[cfg(feature = "extensions")]
+    pub fn register(&mut self, extension: impl Extension) {
+        Arc::make_mut(&mut self.extensions).push(Arc::new(extension));
+    }
+
+    /// Returns a request filter that invokes the extensions' `accept` hooks.
+    pub fn accept(&self, direction: Direction) -> AcceptFilter {
+        AcceptFilter {
+            registry: self.clone(),
+            direction,
+        }
+    }
+
+    /// Returns a layer that invokes the extensions' HTTP hooks at the given
+    /// point.
+    pub fn layer(&self, direction: Direction, point: Point) -> ExtendLayer {
+        ExtendLayer {
+            registry: self.clone(),
+            at: Location { direction, point },
+        }
+    }
+
+    #[cfg(feature = "extensions")]
+    fn is_empty(&self) -> bool {
+        self.extensions.is_empty()
+    }
+
+    #[cfg(not(feature = "extensions"))]
+    fn is_empty(&self) -> bool {
+        true
+    }
+
+    #[cfg_attr(not(feature = "extensions"), allow(unused_variables))]
+    fn on_accept(&self, direction: Direction, orig_dst: OrigDstAddr) -> Result<(), Error> {
+        #[cfg(feature = "extensions")]
+        for ext in self.extensions.iter() {
+            ext.accept(direction, orig_dst)?;
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(not(feature = "extensions"), allow(unused_variables))]
+    fn on_request<B>(
+        &self,
+        at: Location,
+        req: http::Request<B>,
+    ) -> Result<http::Request<B>, Error> {
+        if self.is_empty() {
+            return Ok(req);
+        }
+
+        let (parts, body) = req.into_parts();
+        #[allow(unused_mut)]
+        let mut head = http::Request::from_parts(parts, ());
+        #[cfg(feature = "extensions")]
+        for ext in self.extensions.iter() {
+            ext.request(at, &mut head)?;
+        }
+        let (parts, ()) = head.into_parts();
+        Ok(http::Request::from_parts(parts, body))
+    }
+
+    #[cfg_attr(not(feature = "extensions"), allow(unused_variables))]
+    fn on_response<B>(&self, at: Location, rsp: http::Response<B>) -> http::Response<B> {
+        if self.is_empty() {
+            return rsp;
+        }
+
+        let (parts, body) = rsp.into_parts();
+        #[allow(unused_mut)]
+        let mut head = http::Response::from_parts(parts, ());
+        #[cfg(feature = "extensions")]
+        for ext in self.extensions.iter() {
+            ext.response(at, &mut head);
+        }
+        let (parts, ()) = head.into_parts();
+        http::Response::from_parts(parts, body)
+    }
+
+    #[cfg_attr(not(feature = "extensions"), allow(unused_variables))]
+    fn on_error(&self, at: Location, error: &Error) {
+        #[cfg(feature = "extensions")]
+        for ext in self.extensions.iter() {
+            ext.error(at, error);
+        }
+    }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        #[cfg(feature = "extensions")]
+        let extensions = self.extensions.len();
+        #[cfg(not(feature = "extensions"))]
+        let extensions = 0;
+        f.debug_struct("Registry")
+            .field("extensions", &extensions)
+            .finish()
+    }
+}
+
+// === impl AcceptFilter ===
+
+impl<T: svc::Param<OrigDstAddr>> svc::Predicate<T> for AcceptFilter {
+    type Request = T;
+
+    fn check(&mut self, target: T) -> Result<T, Error> {
+        self.registry.on_accept(self.direction, target.param())?;
+        Ok(target)
+    }
+}
+
+// === impl ExtendLayer ===
+
+impl<S> svc::Layer<S> for ExtendLayer {
+    type Service = Extend<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Extend {
+            inner,
+            registry: self.registry.clone(),
+            at: self.at,
+        }
+    }
+}
+
+// === impl Extend ===
+
+impl<S, A, B> svc::Service<http::Request<A>> for Extend<S>
+where
+    S: svc::Service<http::Request<A>, Response = http::Response<B>>,
+    S::Error: Into<Error>,
+{
+    type Response = http::Response<B>;
+    type Error = Error;
+    type Future =
+        future::Either<future::Ready<Result<http::Response<B>, Error>>, ResponseFuture<S::Future>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<A>) -> Self::Future {
+        let req = match self.registry.on_request(self.at, req) {
+            Ok(req) => req,
+            Err(error) => return future::Either::Left(future::err(error)),
+        };
+        future::Either::Right(ResponseFuture {
+            inner: self.inner.call(req),
+            registry: self.registry.clone(),
+            at: self.at,
+        })
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F, B> Future for ResponseFuture<F>
+where
+    F: TryFuture<Ok = http::Response<B>>,
+    F::Error: Into<Error>,
+{
+    type Output = Result<http::Response<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        match ready!(this.inner.try_poll(cx)) {
+            Ok(rsp) => Poll::Ready(Ok(this.registry.on_response(*this.at, rsp))),
+            Err(error) => {
+                let error = error.into();
+                this.registry.on_error(*this.at, &error);
+                Poll::Ready(Err(error))
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "extensions"))]
+mod tests {
+    use super::*;
+    use svc::{Layer, ServiceExt};
+
+    struct Billing;
+
+    impl Extension for Billing {
+        fn request(&self, at: Location, req: &mut http::Request<()>) -> Result<(), Error> {
+            if req.headers().contains_key("x-blocked") {
+                return Err("blocked".into());
+            }
+            req.headers_mut()
+                .insert("x-billed", format!("{:?}", at.point).parse().unwrap());
+            Ok(())
+        }
+
+        fn response(&self, _: Location, rsp: &mut http::Response<()>) {
+            rsp.headers_mut()
+                .insert("x-billing-account", "acme".parse().unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn invokes_http_hooks() {
+        let mut registry = Registry::default();
+        registry.register(Billing);
+
+        let inner = svc::mk(|req: http::Request<()>| {
+            let billed = req.headers().get("x-billed").cloned();
+            async move {
+                assert_eq!(billed.unwrap(), "Logical");
+                Ok::<_, Error>(http::Response::new(()))
+            }
+        });
+        let svc = registry.layer(Direction::Out, Point::Logical).layer(inner);
+
+        let rsp = svc
+            .clone()
+            .oneshot(http::Request::new(()))
+            .await
+            .expect("request must succeed");
+        assert_eq!(rsp.headers()["x-billing-account"], "acme");
+
+        let req = http::Request::builder()
+            .header("x-blocked", "1")
+            .body(())
+            .unwrap();
+        svc.oneshot(req)
+            .await
+            .expect_err("request must be rejected");
+    }
+}
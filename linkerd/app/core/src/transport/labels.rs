@@ -1,8 +1,11 @@
 pub use crate::metrics::{Direction, OutboundEndpointLabels};
+use linkerd_addr::NameAddr;
 use linkerd_conditional::Conditional;
+use linkerd_identity as id;
 use linkerd_metrics::FmtLabels;
 use linkerd_tls as tls;
-use std::{fmt, net::SocketAddr};
+use parking_lot::Mutex;
+use std::{collections::HashSet, fmt, net::SocketAddr, sync::Arc};
 
 /// Describes a class of transport.
 ///
@@ -16,10 +19,60 @@ pub enum Key {
         tls: tls::ConditionalServerTls,
         target_addr: SocketAddr,
     },
+    /// Connections accepted by an inbound gateway on behalf of a remote
+    /// cluster.
+    GatewayAccept {
+        tls: tls::ConditionalServerTls,
+        target_addr: SocketAddr,
+        target_cluster: Arc<str>,
+    },
+    /// Connections accepted by the outbound proxy, labeled with the logical
+    /// address discovered for the connection's original destination, if any.
+    OutboundAccept {
+        target_addr: SocketAddr,
+        logical_addr: Option<NameAddr>,
+    },
     OutboundConnect(OutboundEndpointLabels),
     InboundConnect,
 }
 
+/// Describes the peer of a meshed transport by its TLS status and identity.
+///
+/// Transports accepted by the inbound proxy and those established by the
+/// outbound proxy are rolled up into a `Peer`, so that the proportion of
+/// traffic that is mutually authenticated may be observed without the
+/// cardinality of each transport's address labels.
+///
+/// Implements `FmtLabels`.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Peer {
+    direction: Direction,
+    tls: PeerTls,
+}
+
+/// Bounds the number of distinct peer identities that are used to label
+/// `Peer`s. Once the limit is reached, connections from or to other identities
+/// are labeled with the `*` peer identity.
+#[derive(Clone, Debug)]
+pub struct PeerIdentities {
+    limit: usize,
+    known: Arc<Mutex<HashSet<id::Name>>>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+enum PeerTls {
+    Disabled,
+    NoIdentity(String),
+    Opaque,
+    Established(Option<PeerId>),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+enum PeerId {
+    Known(id::Name),
+    Other,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub(crate) struct TlsAccept<'t>(&'t tls::ConditionalServerTls);
 
@@ -57,6 +110,30 @@ impl FmtLabels for Key {
                 f.write_str(",peer=\"src\",")?;
                 (TargetAddr(*target_addr), TlsAccept::from(tls)).fmt_labels(f)
             }
+            Self::GatewayAccept {
+                tls,
+                target_addr,
+                target_cluster,
+            } => {
+                Direction::In.fmt_labels(f)?;
+                f.write_str(",peer=\"src\",")?;
+                (TargetAddr(*target_addr), TlsAccept::from(tls)).fmt_labels(f)?;
+                write!(f, ",target_cluster=\"{}\"", target_cluster)
+            }
+            Self::OutboundAccept {
+                target_addr,
+                logical_addr,
+            } => {
+                const NO_TLS: tls::ConditionalServerTls =
+                    Conditional::None(tls::NoServerTls::Loopback);
+
+                Direction::Out.fmt_labels(f)?;
+                f.write_str(",peer=\"src\",")?;
+                if let Some(addr) = logical_addr {
+                    write!(f, "authority=\"{}\",", addr)?;
+                }
+                (TargetAddr(*target_addr), TlsAccept::from(&NO_TLS)).fmt_labels(f)
+            }
             Self::OutboundConnect(endpoint) => {
                 Direction::Out.fmt_labels(f)?;
                 write!(f, ",peer=\"dst\",")?;
@@ -74,6 +151,95 @@ impl FmtLabels for Key {
     }
 }
 
+// === impl Peer ===
+
+impl Peer {
+    /// Returns the peer of an inbound-accepted or outbound-connected transport.
+    ///
+    /// Transports between the proxy and its local application are not rolled
+    /// up.
+    pub fn from_key(key: &Key, identities: &PeerIdentities) -> Option<Self> {
+        let (direction, tls) = match key {
+            Key::Accept { direction, tls, .. } => (*direction, PeerTls::accept(tls, identities)),
+            Key::GatewayAccept { tls, .. } => (Direction::In, PeerTls::accept(tls, identities)),
+            Key::OutboundConnect(endpoint) => (
+                Direction::Out,
+                PeerTls::connect(&endpoint.server_id, identities),
+            ),
+            Key::OutboundAccept { .. } | Key::InboundConnect => return None,
+        };
+        Some(Self { direction, tls })
+    }
+}
+
+impl FmtLabels for Peer {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.direction.fmt_labels(f)?;
+        match &self.tls {
+            PeerTls::Disabled => write!(f, ",tls=\"disabled\""),
+            PeerTls::NoIdentity(why) => {
+                write!(f, ",tls=\"no_identity\",no_tls_reason=\"{}\"", why)
+            }
+            PeerTls::Opaque => write!(f, ",tls=\"opaque\""),
+            PeerTls::Established(None) => write!(f, ",tls=\"true\",peer_id=\"\""),
+            PeerTls::Established(Some(PeerId::Known(id))) => {
+                write!(f, ",tls=\"true\",peer_id=\"{}\"", id)
+            }
+            PeerTls::Established(Some(PeerId::Other)) => {
+                write!(f, ",tls=\"true\",peer_id=\"*\"")
+            }
+        }
+    }
+}
+
+// === impl PeerTls ===
+
+impl PeerTls {
+    fn accept(tls: &tls::ConditionalServerTls, identities: &PeerIdentities) -> Self {
+        match tls {
+            Conditional::None(tls::NoServerTls::Disabled) => Self::Disabled,
+            Conditional::None(why) => Self::NoIdentity(why.to_string()),
+            Conditional::Some(tls::ServerTls::Established { client_id, .. }) => {
+                Self::Established(client_id.as_ref().map(|id| identities.peer_id(&id.0)))
+            }
+            Conditional::Some(tls::ServerTls::Passthru { .. }) => Self::Opaque,
+        }
+    }
+
+    fn connect(tls: &tls::ConditionalClientTls, identities: &PeerIdentities) -> Self {
+        match tls {
+            Conditional::None(tls::NoClientTls::Disabled) => Self::Disabled,
+            Conditional::None(why) => Self::NoIdentity(why.to_string()),
+            Conditional::Some(tls::ClientTls { server_id, .. }) => {
+                Self::Established(Some(identities.peer_id(&server_id.0)))
+            }
+        }
+    }
+}
+
+// === impl PeerIdentities ===
+
+impl PeerIdentities {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            known: Default::default(),
+        }
+    }
+
+    fn peer_id(&self, id: &id::Name) -> PeerId {
+        let mut known = self.known.lock();
+        if known.contains(id) {
+            return PeerId::Known(id.clone());
+        }
+        if known.len() < self.limit {
+            known.insert(id.clone());
+            return PeerId::Known(id.clone());
+        }
+        PeerId::Other
+    }
+}
+
 // === impl TlsAccept ===
 
 impl<'t> From<&'t tls::ConditionalServerTls> for TlsAccept<'t> {
@@ -95,9 +261,10 @@ impl<'t> FmtLabels for TlsAccept<'t> {
                 Some(id) => write!(f, "tls=\"true\",client_id=\"{}\"", id),
                 None => write!(f, "tls=\"true\",client_id=\"\""),
             },
-            Conditional::Some(tls::ServerTls::Passthru { sni }) => {
-                write!(f, "tls=\"opaque\",sni=\"{}\"", sni)
-            }
+            Conditional::Some(tls::ServerTls::Passthru { sni }) => match sni {
+                Some(sni) => write!(f, "tls=\"opaque\",sni=\"{}\"", sni),
+                None => write!(f, "tls=\"opaque\",sni=\"\""),
+            },
         }
     }
 }
@@ -133,3 +300,21 @@ impl FmtLabels for TargetAddr {
         write!(f, "target_addr=\"{}\"", self.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn peer_identities_are_bounded() {
+        let identities = PeerIdentities::new(1);
+        let foo =
+            id::Name::from_str("foo.ns1.serviceaccount.identity.linkerd.cluster.local").unwrap();
+        let bar =
+            id::Name::from_str("bar.ns1.serviceaccount.identity.linkerd.cluster.local").unwrap();
+        assert_eq!(identities.peer_id(&foo), PeerId::Known(foo.clone()));
+        assert_eq!(identities.peer_id(&bar), PeerId::Other);
+        assert_eq!(identities.peer_id(&foo), PeerId::Known(foo));
+    }
+}
@@ -20,6 +20,7 @@ use tracing::{debug, warn};
 pub(crate) struct NewGateway<O> {
     outbound: O,
     local_id: Option<tls::LocalId>,
+    metadata_labels: outbound::endpoint::MetadataLabels,
 }
 
 #[derive(Clone, Debug)]
@@ -38,12 +39,23 @@ pub(crate) type Target = (Option<profiles::Receiver>, HttpTarget);
 // === impl NewGateway ===
 
 impl<O> NewGateway<O> {
-    pub fn new(outbound: O, local_id: Option<tls::LocalId>) -> Self {
-        Self { outbound, local_id }
+    pub fn new(
+        outbound: O,
+        local_id: Option<tls::LocalId>,
+        metadata_labels: outbound::endpoint::MetadataLabels,
+    ) -> Self {
+        Self {
+            outbound,
+            local_id,
+            metadata_labels,
+        }
     }
 
-    pub fn layer(local_id: Option<tls::LocalId>) -> impl layer::Layer<O, Service = Self> + Clone {
-        layer::mk(move |outbound| Self::new(outbound, local_id.clone()))
+    pub fn layer(
+        local_id: Option<tls::LocalId>,
+        metadata_labels: outbound::endpoint::MetadataLabels,
+    ) -> impl layer::Layer<O, Service = Self> + Clone {
+        layer::mk(move |outbound| Self::new(outbound, local_id.clone(), metadata_labels.clone()))
     }
 }
 
@@ -78,6 +90,7 @@ where
                         metadata,
                         tls::NoClientTls::NotProvidedByServiceDiscovery,
                         profile.is_opaque_protocol(),
+                        self.metadata_labels.clone(),
                     ),
                 ))));
             return Gateway::new(svc, http.target, local_id);
@@ -8,26 +8,28 @@ mod tests;
 use self::gateway::NewGateway;
 use linkerd_app_core::{
     config::ProxyConfig,
-    detect, identity, io, metrics,
+    detect, http_metrics, identity, io, load_shed, metrics,
     profiles::{self, DiscoveryRejected},
     proxy::{
-        api_resolve::{ConcreteAddr, Metadata},
+        api_resolve::{ConcreteAddr, Metadata, ProtocolHint},
         core::Resolve,
         http,
     },
     svc::{self, Param},
     tls,
+    transport::{ClientAddr, Remote},
     transport_header::SessionProtocol,
     Error, Infallible, NameAddr, NameMatch,
 };
 use linkerd_app_inbound::{
-    direct::{ClientInfo, GatewayConnection, GatewayTransportHeader},
-    Inbound,
+    direct::{ClientInfo, GatewayConnection, GatewayTransportHeader, RemoteCluster, RemoteGateway},
+    ForwardClientId, ForwardedFor, Inbound,
 };
 use linkerd_app_outbound::{self as outbound, Outbound};
 use std::{
     convert::{TryFrom, TryInto},
     fmt,
+    net::SocketAddr,
 };
 use thiserror::Error;
 use tracing::debug_span;
@@ -48,6 +50,8 @@ struct HttpTransportHeader {
     target: NameAddr,
     client: ClientInfo,
     version: http::Version,
+    /// The original client's address, as asserted by the transport header.
+    forwarded_for: Option<SocketAddr>,
 }
 
 #[derive(Clone, Debug)]
@@ -67,6 +71,10 @@ struct RefusedNoTarget(());
 #[error("the provided address could not be resolved: {}", self.0)]
 struct RefusedNotResolved(NameAddr);
 
+#[derive(Debug, Default, Error)]
+#[error("connections to remote clusters must provide a transport header")]
+struct RefusedLegacyCluster(());
+
 #[allow(clippy::too_many_arguments)]
 pub fn stack<I, O, P, R>(
     Config { allow_discovery }: Config,
@@ -94,9 +102,11 @@ where
         buffer_capacity,
         cache_max_idle_age,
         dispatch_timeout,
+        queue_time_header,
         ..
     } = inbound.config().proxy.clone();
     let local_id = inbound.runtime().identity.as_ref().map(|l| l.id().clone());
+    let metadata_labels = outbound.config().metadata_labels.clone();
 
     // For each gatewayed connection that is *not* HTTP, use the target from the
     // transport header to lookup a service profile. If the profile includes a
@@ -117,40 +127,53 @@ where
         .push_tcp_endpoint()
         .push_tcp_forward()
         .into_stack();
+
+    // Connections that target a remote cluster are forwarded, without
+    // discovery, to the cluster's gateway.
+    let remote = endpoint
+        .clone()
+        .push_on_response(svc::BoxService::layer())
+        .push(svc::BoxNewService::layer())
+        .check_new_service::<outbound::tcp::Endpoint, I>();
+
     let tcp = endpoint
         .push_switch(
-            move |(profile, _): (Option<profiles::Receiver>, _)| -> Result<_, Error> {
-                let profile = profile.ok_or_else(|| {
-                    DiscoveryRejected::new("no profile discovered for gateway target")
-                })?;
-
-                if let Some((addr, metadata)) = profile.endpoint() {
-                    return Ok(svc::Either::A(outbound::tcp::Endpoint::from_metadata(
-                        addr,
-                        metadata,
-                        tls::NoClientTls::NotProvidedByServiceDiscovery,
-                        profile.is_opaque_protocol(),
-                    )));
+            {
+                let metadata_labels = metadata_labels.clone();
+                move |(profile, _): (Option<profiles::Receiver>, _)| -> Result<_, Error> {
+                    let profile = profile.ok_or_else(|| {
+                        DiscoveryRejected::new("no profile discovered for gateway target")
+                    })?;
+
+                    if let Some((addr, metadata)) = profile.endpoint() {
+                        return Ok(svc::Either::A(outbound::tcp::Endpoint::from_metadata(
+                            addr,
+                            metadata,
+                            tls::NoClientTls::NotProvidedByServiceDiscovery,
+                            profile.is_opaque_protocol(),
+                            metadata_labels.clone(),
+                        )));
+                    }
+
+                    let logical_addr = profile.logical_addr().ok_or_else(|| {
+                        DiscoveryRejected::new(
+                            "profiles must have either an endpoint or a logical address",
+                        )
+                    })?;
+
+                    Ok(svc::Either::B(outbound::tcp::Logical {
+                        profile,
+                        protocol: (),
+                        logical_addr,
+                    }))
                 }
-
-                let logical_addr = profile.logical_addr().ok_or_else(|| {
-                    DiscoveryRejected::new(
-                        "profiles must have either an endpoint or a logical address",
-                    )
-                })?;
-
-                Ok(svc::Either::B(outbound::tcp::Logical {
-                    profile,
-                    protocol: (),
-                    logical_addr,
-                }))
             },
             logical.into_inner(),
         )
         .push(profiles::discover::layer(profiles.clone(), {
             let allow = allow_discovery.clone();
             move |addr: NameAddr| {
-                if allow.matches(addr.name()) {
+                if allow.matches_addr(&addr) {
                     Ok(profiles::LookupAddr(addr.into()))
                 } else {
                     Err(RefusedNotResolved(addr))
@@ -170,7 +193,14 @@ where
                 .push(svc::FailFast::layer("TCP Gateway", dispatch_timeout))
                 .push_spawn_buffer(buffer_capacity),
         )
-        .push_cache(cache_max_idle_age)
+        .push_cache(
+            cache_max_idle_age,
+            inbound
+                .runtime()
+                .metrics
+                .cache
+                .metrics(metrics::StackLabels::inbound("tcp", "gateway")),
+        )
         .check_new_service::<NameAddr, I>();
 
     // Cache an HTTP gateway service for each destination and HTTP version.
@@ -184,9 +214,9 @@ where
         .push_http_logical(resolve)
         .into_stack()
         .push_switch(Ok::<_, Infallible>, endpoint.into_stack())
-        .push(NewGateway::layer(local_id))
+        .push(NewGateway::layer(local_id, metadata_labels.clone()))
         .push(profiles::discover::layer(profiles, move |t: HttpTarget| {
-            if allow_discovery.matches(t.target.name()) {
+            if allow_discovery.matches_addr(&t.target) {
                 Ok(profiles::LookupAddr(t.target.into()))
             } else {
                 Err(RefusedNotResolved(t.target))
@@ -195,6 +225,10 @@ where
         .instrument(|h: &HttpTarget| debug_span!("gateway", target = %h.target, v = %h.version))
         .push_on_response(
             svc::layers()
+                .push(inbound.runtime().metrics.http_queue_time.layer(
+                    metrics::StackLabels::inbound("http", "gateway"),
+                    queue_time_header,
+                ))
                 .push(
                     inbound
                         .runtime()
@@ -204,9 +238,17 @@ where
                 )
                 .push(svc::layer::mk(svc::SpawnReady::new))
                 .push(svc::FailFast::layer("Gateway", dispatch_timeout))
-                .push_spawn_buffer(buffer_capacity),
+                .push_spawn_buffer(buffer_capacity)
+                .push(http_metrics::queue_time::Enqueue::layer()),
+        )
+        .push_cache(
+            cache_max_idle_age,
+            inbound
+                .runtime()
+                .metrics
+                .cache
+                .metrics(metrics::StackLabels::inbound("http", "gateway")),
         )
-        .push_cache(cache_max_idle_age)
         .push_on_response(
             svc::layers()
                 .push(http::Retain::layer())
@@ -251,11 +293,13 @@ where
             |GatewayTransportHeader {
                  target,
                  protocol,
+                 extensions,
                  client,
              }| match protocol {
                 Some(proto) => Ok(svc::Either::A(HttpTransportHeader {
                     target,
                     client,
+                    forwarded_for: extensions.client_addr,
                     version: match proto {
                         SessionProtocol::Http1 => http::Version::Http1,
                         SessionProtocol::Http2 => http::Version::H2,
@@ -267,6 +311,12 @@ where
                 .push(svc::BoxNewService::layer())
                 .into_inner(),
         )
+        .push_switch(
+            move |gw: GatewayTransportHeader| {
+                Ok::<_, Infallible>(route_remote_cluster(gw, &metadata_labels))
+            },
+            remote.into_inner(),
+        )
         .push_switch(
             |gw| match gw {
                 GatewayConnection::TransportHeader(t) => Ok::<_, Infallible>(svc::Either::A(t)),
@@ -279,6 +329,35 @@ where
         .into_inner()
 }
 
+/// Routes connections that target a remote cluster to the cluster's gateway.
+///
+/// The gateway is configured as an opaque-transport endpoint so that the
+/// connection is prefixed with a transport header naming the original target,
+/// which the remote gateway uses to route the connection.
+fn route_remote_cluster(
+    gw: GatewayTransportHeader,
+    metadata_labels: &outbound::endpoint::MetadataLabels,
+) -> svc::Either<GatewayTransportHeader, outbound::tcp::Endpoint> {
+    let RemoteGateway { addr, identity } = match gw.client.cluster {
+        Some(RemoteCluster { ref gateway, .. }) => gateway.clone(),
+        None => return svc::Either::A(gw),
+    };
+    let metadata = Metadata::new(
+        None,
+        ProtocolHint::Unknown,
+        Some(addr.port()),
+        Some(tls::ServerId(identity)),
+        Some(gw.target.as_http_authority()),
+    );
+    svc::Either::B(outbound::tcp::Endpoint::from_metadata(
+        addr,
+        metadata,
+        tls::NoClientTls::NotProvidedByServiceDiscovery,
+        true,
+        metadata_labels.clone(),
+    ))
+}
+
 // === impl HttpTransportHeader ===
 
 impl Param<http::normalize_uri::DefaultAuthority> for HttpTransportHeader {
@@ -293,6 +372,36 @@ impl Param<Option<identity::Name>> for HttpTransportHeader {
     }
 }
 
+impl Param<ForwardClientId> for HttpTransportHeader {
+    fn param(&self) -> ForwardClientId {
+        ForwardClientId(true)
+    }
+}
+
+impl Param<ForwardedFor> for HttpTransportHeader {
+    fn param(&self) -> ForwardedFor {
+        ForwardedFor(self.forwarded_for)
+    }
+}
+
+impl Param<Remote<ClientAddr>> for HttpTransportHeader {
+    fn param(&self) -> Remote<ClientAddr> {
+        self.client.client_addr
+    }
+}
+
+impl Param<u16> for HttpTransportHeader {
+    fn param(&self) -> u16 {
+        self.client.local_addr.port()
+    }
+}
+
+impl Param<load_shed::Priority> for HttpTransportHeader {
+    fn param(&self) -> load_shed::Priority {
+        load_shed::Priority::default()
+    }
+}
+
 impl Param<http::Version> for HttpTransportHeader {
     fn param(&self) -> http::Version {
         self.version
@@ -313,6 +422,9 @@ impl<E: Into<Error>> TryFrom<(Result<Option<http::Version>, E>, ClientInfo)> for
     fn try_from(
         (version, client): (Result<Option<http::Version>, E>, ClientInfo),
     ) -> Result<Self, Self::Error> {
+        if client.cluster.is_some() {
+            return Err(RefusedLegacyCluster(()).into());
+        }
         match version {
             Ok(Some(version)) => Ok(Self { version, client }),
             Ok(None) => Err(RefusedNoTarget(()).into()),
@@ -339,6 +451,36 @@ impl Param<Option<identity::Name>> for HttpLegacy {
     }
 }
 
+impl Param<ForwardClientId> for HttpLegacy {
+    fn param(&self) -> ForwardClientId {
+        ForwardClientId(true)
+    }
+}
+
+impl Param<ForwardedFor> for HttpLegacy {
+    fn param(&self) -> ForwardedFor {
+        ForwardedFor(None)
+    }
+}
+
+impl Param<Remote<ClientAddr>> for HttpLegacy {
+    fn param(&self) -> Remote<ClientAddr> {
+        self.client.client_addr
+    }
+}
+
+impl Param<u16> for HttpLegacy {
+    fn param(&self) -> u16 {
+        self.client.local_addr.port()
+    }
+}
+
+impl Param<load_shed::Priority> for HttpLegacy {
+    fn param(&self) -> load_shed::Priority {
+        load_shed::Priority::default()
+    }
+}
+
 impl Param<http::Version> for HttpLegacy {
     fn param(&self) -> http::Version {
         self.version
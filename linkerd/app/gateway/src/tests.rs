@@ -1,8 +1,9 @@
 use super::*;
 use linkerd_app_core::{
-    dns, errors::HttpError, identity as id, profiles, proxy::http, svc::NewService, tls, Error,
-    NameAddr, NameMatch,
+    dns, errors::HttpError, identity as id, profiles, proxy::http, svc::NewService, tls,
+    transport::ServerAddr, Conditional, Error, NameAddr, NameMatch,
 };
+use linkerd_app_inbound::direct::GatewayClusters;
 use linkerd_app_test as support;
 use std::str::FromStr;
 use tower::util::ServiceExt;
@@ -93,6 +94,78 @@ async fn forward_loop() {
     assert_eq!(status, http::StatusCode::LOOP_DETECTED);
 }
 
+#[test]
+fn remote_clusters() {
+    let local = tls::LocalId(id::Name::from_str("gateway.id.test").unwrap());
+    let east = RemoteGateway {
+        addr: ([192, 0, 2, 10], 4143).into(),
+        identity: id::Name::from_str("gateway.east.test").unwrap(),
+    };
+    let west = RemoteGateway {
+        addr: ([192, 0, 2, 20], 4143).into(),
+        identity: id::Name::from_str("gateway.west.test").unwrap(),
+    };
+    let allow = NameMatch::new(Some(dns::Suffix::from_str("id.test").unwrap()));
+    let clusters = GatewayClusters::new(vec![
+        ("east".into(), allow.clone(), east.clone()),
+        ("west".into(), allow, west.clone()),
+    ]);
+    let target = NameAddr::from_str("dst.test.example.com:4321").unwrap();
+
+    let route = |sni: &str| {
+        let sni = tls::ServerId(id::Name::from_str(sni).unwrap());
+        let cluster = clusters.from_sni(&sni, &local)?;
+        let gw = GatewayTransportHeader {
+            target: target.clone(),
+            protocol: None,
+            extensions: Default::default(),
+            client: ClientInfo {
+                client_id: tls::ClientId::from_str("client.id.test").unwrap(),
+                alpn: None,
+                client_addr: Remote(ClientAddr(([192, 0, 2, 30], 40000).into())),
+                local_addr: ([192, 0, 2, 1], 4143).into(),
+                cluster,
+            },
+        };
+        Ok::<_, Error>(route_remote_cluster(gw, &Default::default()))
+    };
+    let remote = |sni: &str, gateway: &RemoteGateway| match route(sni).unwrap() {
+        svc::Either::B(ep) => {
+            assert_eq!(ep.addr, Remote(ServerAddr(gateway.addr)));
+            match ep.tls {
+                Conditional::Some(tls::ClientTls { server_id, .. }) => {
+                    assert_eq!(server_id, tls::ServerId(gateway.identity.clone()))
+                }
+                Conditional::None(reason) => panic!("connection must use TLS: {}", reason),
+            }
+            assert_eq!(
+                ep.metadata.opaque_transport_port(),
+                Some(gateway.addr.port())
+            );
+            assert_eq!(
+                ep.metadata.authority_override(),
+                Some(&target.as_http_authority())
+            );
+            ep.addr
+        }
+        svc::Either::A(_) => panic!("{} must be routed to a remote gateway", sni),
+    };
+
+    // Each cluster's SNI is forwarded to that cluster's gateway.
+    let east_addr = remote("east.gateway.id.test", &east);
+    let west_addr = remote("west.gateway.id.test", &west);
+    assert_ne!(east_addr, west_addr);
+
+    // The gateway's own identity is handled locally.
+    assert!(matches!(
+        route("gateway.id.test").unwrap(),
+        svc::Either::A(_)
+    ));
+
+    // Unknown clusters are refused.
+    assert!(route("north.gateway.id.test").is_err());
+}
+
 struct Test {
     suffix: &'static str,
     target: NameAddr,
@@ -131,6 +204,7 @@ impl Test {
                 outbound.clone()
             },
             Some(tls::LocalId(id::Name::from_str("gateway.id.test").unwrap())),
+            Default::default(),
         );
 
         let t = HttpTarget {
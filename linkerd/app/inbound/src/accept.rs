@@ -1,6 +1,7 @@
 use crate::{port_policies::AllowPolicy, Inbound};
 use linkerd_app_core::{
-    io, svc,
+    io,
+    svc::{self, extension::Direction},
     transport::addrs::{ClientAddr, OrigDstAddr, Remote},
     Error,
 };
@@ -28,13 +29,13 @@ impl<N> Inbound<N> {
     where
         T: svc::Param<Remote<ClientAddr>> + svc::Param<OrigDstAddr>,
         T: Clone + Send + 'static,
-        I: io::AsyncRead + io::AsyncWrite + io::Peek + io::PeerAddr,
+        I: io::AsyncRead + io::AsyncWrite + io::Peek + io::PeerAddr + io::Reset,
         I: Debug + Send + Sync + Unpin + 'static,
         N: svc::NewService<Accept, Service = NSvc> + Clone + Send + Sync + Unpin + 'static,
         NSvc: svc::Service<I, Response = ()>,
         NSvc: Send + Unpin + 'static,
         NSvc::Error: Into<Error>,
-        NSvc::Future: Send,
+        NSvc::Future: Send + 'static,
         D: svc::NewService<T, Service = DSvc> + Clone + Send + Sync + Unpin + 'static,
         DSvc: svc::Service<I, Response = ()> + Send + 'static,
         DSvc::Error: Into<Error>,
@@ -43,6 +44,8 @@ impl<N> Inbound<N> {
         self.map_stack(|cfg, rt, accept| {
             let port_policies = cfg.port_policies.clone();
             accept
+                // Resets connections that would exceed the port's connection limits.
+                .push(cfg.connection_limits.layer())
                 .push_switch(
                     // Switch to the `direct` stack when a connection's original destination is the
                     // proxy's inbound port. Otherwise, check that connections are allowed on the
@@ -61,6 +64,8 @@ impl<N> Inbound<N> {
                     },
                     direct,
                 )
+                // Refuses connections that are rejected by an extension.
+                .push_request_filter(rt.extensions.accept(Direction::In))
                 .push(rt.metrics.tcp_accept_errors.layer())
                 .instrument(|t: &T| {
                     let OrigDstAddr(addr) = t.param();
@@ -74,6 +79,15 @@ impl<N> Inbound<N> {
 
 // === impl Accept ===
 
+impl Accept {
+    pub(crate) fn map_policy(self, f: impl FnOnce(AllowPolicy) -> AllowPolicy) -> Self {
+        Self {
+            policy: f(self.policy),
+            ..self
+        }
+    }
+}
+
 impl svc::Param<u16> for Accept {
     fn param(&self) -> u16 {
         self.orig_dst_addr.0.port()
@@ -107,7 +121,7 @@ mod tests {
         svc::{NewService, ServiceExt},
         Error,
     };
-    use linkerd_server_policy::{Authentication, Authorization, ServerPolicy};
+    use linkerd_server_policy::{Authentication, Authorization, Priority, ServerPolicy};
 
     #[tokio::test(flavor = "current_thread")]
     async fn default_allow() {
@@ -118,8 +132,15 @@ mod tests {
                 authentication: Authentication::Unauthenticated,
                 networks: vec![Default::default()],
                 labels: Default::default(),
+                priority: Priority::Normal,
             }],
             labels: Default::default(),
+            forward_client_id: true,
+            forward_addr: None,
+            exempt_routes: Vec::new(),
+            probes: Vec::new(),
+            deny_response: None,
+            audit: false,
         };
         inbound(allow)
             .with_stack(new_ok())
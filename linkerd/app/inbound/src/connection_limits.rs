@@ -0,0 +1,328 @@
+use futures::future;
+use linkerd_app_core::{
+    io,
+    metrics::{metrics, FmtLabels, FmtMetrics, Gauge},
+    svc::{self, stack::Param},
+    transport::{ClientAddr, OrigDstAddr, Remote},
+    Error,
+};
+use parking_lot::Mutex;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    future::Future,
+    net::IpAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tracing::{debug, warn};
+
+metrics! {
+    inbound_tcp_connection_limit_active: Gauge {
+        "The number of open inbound connections on a port with a connection limit"
+    },
+    inbound_tcp_connection_limit_max: Gauge {
+        "The maximum number of concurrent inbound connections permitted on a port"
+    },
+    inbound_tcp_connection_limit_max_per_client: Gauge {
+        "The maximum number of concurrent inbound connections permitted from each client IP address on a port"
+    }
+}
+
+/// Limits on the number of concurrent inbound connections, by destination
+/// port.
+///
+/// Per-port limits apply to all connections on a port combined, while
+/// per-client limits apply to the connections from each client IP address
+/// independently. Connections that would exceed a limit are reset as soon as
+/// they are accepted.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionLimits(Arc<BTreeMap<u16, Arc<PortLimits>>>);
+
+#[derive(Debug, Default)]
+struct PortLimits {
+    max_connections: Option<usize>,
+    max_per_client: Option<usize>,
+    active: Mutex<Active>,
+}
+
+#[derive(Debug, Default)]
+struct Active {
+    total: usize,
+    by_client: HashMap<IpAddr, usize>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct NewLimitConnections<N> {
+    inner: N,
+    limits: ConnectionLimits,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct LimitConnections<S> {
+    inner: S,
+    port: u16,
+    client: IpAddr,
+    limits: Option<Arc<PortLimits>>,
+}
+
+/// Holds a connection's share of its port's limits until the connection
+/// completes.
+#[derive(Debug)]
+struct Permit {
+    client: IpAddr,
+    limits: Arc<PortLimits>,
+}
+
+#[derive(Clone, Debug, Error)]
+#[error("connection limit exceeded on port {port} for client {client}")]
+pub struct ConnectionLimitExceeded {
+    port: u16,
+    client: IpAddr,
+}
+
+struct PortLabels(u16);
+
+// === impl ConnectionLimits ===
+
+impl ConnectionLimits {
+    /// Creates connection limits from per-port and per-client maximums, by
+    /// destination port.
+    pub fn new(
+        per_port: impl IntoIterator<Item = (u16, usize)>,
+        per_client: impl IntoIterator<Item = (u16, usize)>,
+    ) -> Self {
+        let mut ports = BTreeMap::<u16, PortLimits>::new();
+        for (port, max) in per_port {
+            ports.entry(port).or_default().max_connections = Some(max);
+        }
+        for (port, max) in per_client {
+            ports.entry(port).or_default().max_per_client = Some(max);
+        }
+        Self(Arc::new(
+            ports.into_iter().map(|(p, l)| (p, Arc::new(l))).collect(),
+        ))
+    }
+
+    pub(crate) fn layer<N>(&self) -> impl svc::Layer<N, Service = NewLimitConnections<N>> + Clone {
+        let limits = self.clone();
+        svc::layer::mk(move |inner| NewLimitConnections {
+            inner,
+            limits: limits.clone(),
+        })
+    }
+}
+
+impl FmtMetrics for ConnectionLimits {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return Ok(());
+        }
+
+        inbound_tcp_connection_limit_active.fmt_help(f)?;
+        for (port, limits) in self.0.iter() {
+            let active = Gauge::from(limits.active.lock().total as u64);
+            inbound_tcp_connection_limit_active.fmt_metric_labeled(
+                f,
+                &active,
+                &PortLabels(*port),
+            )?;
+        }
+
+        inbound_tcp_connection_limit_max.fmt_help(f)?;
+        for (port, limits) in self.0.iter() {
+            if let Some(max) = limits.max_connections {
+                inbound_tcp_connection_limit_max.fmt_metric_labeled(
+                    f,
+                    &Gauge::from(max as u64),
+                    &PortLabels(*port),
+                )?;
+            }
+        }
+
+        inbound_tcp_connection_limit_max_per_client.fmt_help(f)?;
+        for (port, limits) in self.0.iter() {
+            if let Some(max) = limits.max_per_client {
+                inbound_tcp_connection_limit_max_per_client.fmt_metric_labeled(
+                    f,
+                    &Gauge::from(max as u64),
+                    &PortLabels(*port),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// === impl PortLimits ===
+
+impl PortLimits {
+    fn acquire(self: &Arc<Self>, client: IpAddr) -> Option<Permit> {
+        let mut active = self.active.lock();
+        if let Some(max) = self.max_connections {
+            if active.total >= max {
+                return None;
+            }
+        }
+        let by_client = active.by_client.get(&client).copied().unwrap_or(0);
+        if let Some(max) = self.max_per_client {
+            if by_client >= max {
+                return None;
+            }
+        }
+
+        active.total += 1;
+        active.by_client.insert(client, by_client + 1);
+        Some(Permit {
+            client,
+            limits: self.clone(),
+        })
+    }
+}
+
+// === impl NewLimitConnections ===
+
+impl<T, N> svc::NewService<T> for NewLimitConnections<N>
+where
+    T: Param<OrigDstAddr> + Param<Remote<ClientAddr>>,
+    N: svc::NewService<T>,
+{
+    type Service = LimitConnections<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let OrigDstAddr(dst) = target.param();
+        let Remote(ClientAddr(client)) = target.param();
+        let limits = self.limits.0.get(&dst.port()).cloned();
+        LimitConnections {
+            port: dst.port(),
+            client: client.ip(),
+            limits,
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl LimitConnections ===
+
+impl<I, S> svc::Service<I> for LimitConnections<S>
+where
+    I: io::Reset,
+    S: svc::Service<I, Response = ()>,
+    S::Error: Into<Error>,
+    S::Future: Send + 'static,
+{
+    type Response = ();
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, io: I) -> Self::Future {
+        let limits = match self.limits.as_ref() {
+            Some(limits) => limits,
+            None => {
+                let call = self.inner.call(io);
+                return Box::pin(async move { call.await.map_err(Into::into) });
+            }
+        };
+
+        let permit = match limits.acquire(self.client) {
+            Some(permit) => permit,
+            None => {
+                debug!(port = self.port, client = %self.client, "Connection limit exceeded");
+                if let Err(error) = io.reset_on_close() {
+                    warn!(%error, "Failed to reset connection");
+                }
+                drop(io);
+                return Box::pin(future::err(
+                    ConnectionLimitExceeded {
+                        port: self.port,
+                        client: self.client,
+                    }
+                    .into(),
+                ));
+            }
+        };
+
+        let call = self.inner.call(io);
+        Box::pin(async move {
+            let res = call.await.map_err(Into::into);
+            drop(permit);
+            res
+        })
+    }
+}
+
+// === impl Permit ===
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut active = self.limits.active.lock();
+        active.total -= 1;
+        if let Some(n) = active.by_client.get_mut(&self.client) {
+            *n -= 1;
+            if *n == 0 {
+                active.by_client.remove(&self.client);
+            }
+        }
+    }
+}
+
+// === impl PortLabels ===
+
+impl FmtLabels for PortLabels {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "srv_port=\"{}\"", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_ports_and_clients() {
+        let limits = ConnectionLimits::new(vec![(80, 3)], vec![(80, 2), (81, 1)]);
+        let port = limits.0.get(&80).unwrap();
+        let client0 = IpAddr::from([192, 0, 2, 3]);
+        let client1 = IpAddr::from([192, 0, 2, 4]);
+
+        let p0 = port
+            .acquire(client0)
+            .expect("first connection must be permitted");
+        let p1 = port
+            .acquire(client0)
+            .expect("second connection must be permitted");
+        assert!(
+            port.acquire(client0).is_none(),
+            "a client must not exceed its limit"
+        );
+        let p2 = port
+            .acquire(client1)
+            .expect("other clients must be permitted");
+        assert!(
+            port.acquire(client1).is_none(),
+            "connections must not exceed the port's limit"
+        );
+        assert_eq!(port.active.lock().total, 3);
+
+        drop(p0);
+        let _p3 = port
+            .acquire(client1)
+            .expect("released permits must be reusable");
+        drop((p1, p2));
+        assert_eq!(port.active.lock().total, 1);
+        assert_eq!(port.active.lock().by_client.get(&client0), None);
+
+        let port = limits.0.get(&81).unwrap();
+        let _p = port.acquire(client0).expect("port without a total limit");
+        assert!(port.acquire(client0).is_none());
+        assert!(limits.0.get(&82).is_none());
+    }
+}
@@ -1,9 +1,11 @@
 use crate::{
-    port_policies::{AllowPolicy, DeniedUnauthorized, Permitted},
+    handshake_limits::HandshakeLimits,
+    http::{ExemptRoutes, ForwardClientId, ForwardedFor, Probes, UnauthorizedResponse},
+    port_policies::{AllowPolicy, DeniedUnauthorized, Permitted, Priority},
     Inbound,
 };
 use linkerd_app_core::{
-    detect, identity, io,
+    connections, detect, identity, io,
     proxy::{http, identity::LocalCrtKey},
     svc, tls,
     transport::{
@@ -11,7 +13,7 @@ use linkerd_app_core::{
         addrs::{ClientAddr, OrigDstAddr, Remote},
         ServerAddr,
     },
-    Error,
+    Error, Infallible,
 };
 use std::fmt::Debug;
 
@@ -20,6 +22,7 @@ pub struct Tls {
     client_addr: Remote<ClientAddr>,
     orig_dst_addr: OrigDstAddr,
     permit: Permitted,
+    protocol: Option<detect::sniff::Protocol>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -32,8 +35,13 @@ pub struct Http {
 struct TlsParams {
     timeout: tls::server::Timeout,
     identity: Option<LocalCrtKey>,
+    handshake_limits: HandshakeLimits,
 }
 
+/// Configures the TLS server to advertise HTTP protocols via ALPN.
+#[derive(Clone, Debug)]
+struct WithHttpAlpn(LocalCrtKey);
+
 // === impl Inbound ===
 
 impl<N> Inbound<N> {
@@ -65,11 +73,24 @@ impl<N> Inbound<N> {
 
         self.map_stack(|cfg, rt, tls| {
             let detect_timeout = cfg.proxy.detect_protocol_timeout;
+            // Records connections that are only permitted because their server's policy is in
+            // audit mode.
+            let audit = rt.metrics.policy_audit.clone();
+            let record_audit = move |permit: &Permitted, dst: OrigDstAddr| {
+                if permit.audited {
+                    audit.record(dst);
+                }
+            };
+            let record_tls = record_audit.clone();
+            let record_skipped = record_audit.clone();
             tls.check_new_service::<Tls, tls::server::Io<I>>()
                 .push_request_filter(
-                    |(tls, t): (tls::ConditionalServerTls, T)| -> Result<Tls, DeniedUnauthorized> {
+                    move |(tls, t): (tls::ConditionalServerTls, T)| -> Result<Tls, DeniedUnauthorized> {
                         let policy: AllowPolicy = t.param();
-                        let permit = policy.check_authorized(tls)?;
+                        // Unauthorized connections may still be permitted for the server's
+                        // exempt HTTP routes, since they are subject to HTTP detection.
+                        let permit = policy.check_authorized_or_exempt(tls)?;
+                        record_tls(&permit, t.param());
                         Ok(Tls::from_params(&t, permit))
                     },
                 )
@@ -77,21 +98,44 @@ impl<N> Inbound<N> {
                 .push(tls::NewDetectTls::layer(TlsParams {
                     timeout: tls::server::Timeout(detect_timeout),
                     identity: rt.identity.clone(),
+                    handshake_limits: cfg.tls_handshake_limits.clone(),
                 }))
                 .check_new_service::<T, I>()
                 .push_switch(
                     // If this port's policy indicates that authentication is not required and
-                    // detection should be skipped, use the TCP stack directly.
-                    |t: T| -> Result<_, DeniedUnauthorized> {
+                    // detection should be skipped (because the port is opaque or the server
+                    // speaks first), use the TCP stack directly.
+                    move |t: T| -> Result<_, DeniedUnauthorized> {
+                        let policy: AllowPolicy = t.param();
+                        if policy.skips_detection() {
+                            let permit = policy.check_authorized(TLS_PORT_SKIPPED)?;
+                            record_skipped(&permit, t.param());
+                            return Ok(svc::Either::B(Tls::from_params(&t, permit)));
+                        }
+                        Ok(svc::Either::A(t))
+                    },
+                    svc::stack(forward.clone())
+                        .push_on_response(svc::MapTargetLayer::new(io::BoxedIo::new))
+                        .into_inner(),
+                )
+                .check_new_service::<T, I>()
+                .push_switch(
+                    // If this port's policy indicates that the application terminates its own TLS,
+                    // forward connections without reading from them, so only network-based
+                    // authorizations may permit them. These connections bypass HTTP detection,
+                    // so their accept metrics are recorded here.
+                    move |t: T| -> Result<_, DeniedUnauthorized> {
                         let policy: AllowPolicy = t.param();
-                        if policy.is_opaque() {
+                        if policy.is_passthrough_tls() {
                             let permit = policy.check_authorized(TLS_PORT_SKIPPED)?;
+                            record_audit(&permit, t.param());
                             return Ok(svc::Either::B(Tls::from_params(&t, permit)));
                         }
                         Ok(svc::Either::A(t))
                     },
                     svc::stack(forward)
                         .push_on_response(svc::MapTargetLayer::new(io::BoxedIo::new))
+                        .push(rt.metrics.transport.layer_accept())
                         .into_inner(),
                 )
                 .check_new_service::<T, I>()
@@ -104,6 +148,9 @@ impl<N> Inbound<N> {
     /// connection is determined to be HTTP, the inner stack is used; otherwise the connection is
     /// passed to the provided 'forward' stack.
     ///
+    /// Detection is skipped when the client negotiated an HTTP protocol via
+    /// ALPN while establishing TLS.
+    ///
     /// TODO: use the target's protocol to bypass HTTP detection in more cases.
     pub(crate) fn push_detect_http<I, NSvc, F, FSvc>(
         self,
@@ -123,16 +170,64 @@ impl<N> Inbound<N> {
         FSvc::Future: Send,
     {
         self.map_stack(|cfg, rt, http| {
-            http.push_map_target(|(http, tls)| Http { http, tls })
+            let http = http
+                .push_map_target(|(http, tls)| Http { http, tls })
                 .push(svc::UnwrapOr::layer(
                     // When HTTP detection fails, forward the connection to the application as
                     // an opaque TCP stream.
                     forward.clone(),
                 ))
+                // Connections that are only permitted for exempt HTTP routes must not be
+                // forwarded opaquely.
+                .push_request_filter(
+                    |(http, tls): (Option<http::Version>, Tls)| -> Result<_, DeniedUnauthorized> {
+                        if http.is_none() && tls.permit.exempt_routes.is_some() {
+                            return Err(DeniedUnauthorized::new(
+                                tls.client_addr,
+                                tls.orig_dst_addr,
+                                tls.permit.tls,
+                            ));
+                        }
+                        Ok((http, tls))
+                    },
+                )
                 .push_on_response(svc::MapTargetLayer::new(io::BoxedIo::new))
-                .push(svc::BoxNewService::layer())
+                .push(svc::BoxNewService::layer());
+
+            http.clone()
+                .push_map_target(
+                    |(detected, tls): (Option<detect::Detected<http::Version>>, Tls)| {
+                        match detected {
+                            Some(detect::Detected::Known(http)) => (Some(http), tls),
+                            // Non-HTTP connections are still forwarded opaquely, but are
+                            // labeled with the protocol they were recognized as.
+                            Some(detect::Detected::Sniffed(protocol)) => (
+                                None,
+                                Tls {
+                                    protocol: Some(protocol),
+                                    ..tls
+                                },
+                            ),
+                            None => (None, tls),
+                        }
+                    },
+                )
                 .push_map_target(detect::allow_timeout)
-                .push(detect::NewDetectService::layer(cfg.proxy.detect_http()))
+                .push(detect::NewDetectService::layer(
+                    cfg.proxy.detect_protocols(),
+                ))
+                // If the client negotiated an HTTP protocol via ALPN, serve the
+                // connection without waiting to read from it.
+                .push_switch(
+                    |tls: Tls| -> Result<_, Infallible> {
+                        match tls.alpn_http() {
+                            Some(http) => Ok(svc::Either::B((Some(http), tls))),
+                            None => Ok(svc::Either::A(tls)),
+                        }
+                    },
+                    http.push_on_response(svc::MapTargetLayer::new(io::PrefixedIo::<I>::from))
+                        .into_inner(),
+                )
                 .push(rt.metrics.transport.layer_accept())
                 .push_on_response(svc::BoxService::layer())
                 .push(svc::BoxNewService::layer())
@@ -151,6 +246,31 @@ impl Tls {
             client_addr: t.param(),
             orig_dst_addr: t.param(),
             permit,
+            protocol: None,
+        }
+    }
+
+    /// Describes the connection with its protocol, if known, and its client's
+    /// identity.
+    fn describe(&self, protocol: Option<String>) -> connections::Description {
+        let identity = match self.permit.tls.value() {
+            Some(tls::ServerTls::Established {
+                client_id: Some(tls::ClientId(id)),
+                ..
+            }) => Some(id.clone()),
+            _ => None,
+        };
+        connections::Description { protocol, identity }
+    }
+
+    /// Returns the HTTP version negotiated via ALPN, if any.
+    fn alpn_http(&self) -> Option<http::Version> {
+        match self.permit.tls.value()? {
+            tls::ServerTls::Established {
+                negotiated_protocol: Some(tls::NegotiatedProtocol(protocol)),
+                ..
+            } => http::Version::from_alpn(protocol),
+            _ => None,
         }
     }
 }
@@ -161,6 +281,22 @@ impl svc::Param<u16> for Tls {
     }
 }
 
+impl svc::Param<Option<detect::sniff::Protocol>> for Tls {
+    fn param(&self) -> Option<detect::sniff::Protocol> {
+        self.protocol
+    }
+}
+
+impl svc::Param<connections::Description> for Tls {
+    fn param(&self) -> connections::Description {
+        let protocol = match self.protocol {
+            Some(protocol) => protocol.to_string(),
+            None => "opaque".to_string(),
+        };
+        self.describe(Some(protocol))
+    }
+}
+
 impl svc::Param<transport::labels::Key> for Tls {
     fn param(&self) -> transport::labels::Key {
         transport::labels::Key::Accept {
@@ -185,12 +321,30 @@ impl svc::Param<Remote<ServerAddr>> for Http {
     }
 }
 
+impl svc::Param<u16> for Http {
+    fn param(&self) -> u16 {
+        self.tls.orig_dst_addr.as_ref().port()
+    }
+}
+
+impl svc::Param<Priority> for Http {
+    fn param(&self) -> Priority {
+        self.tls.permit.priority
+    }
+}
+
 impl svc::Param<Remote<ClientAddr>> for Http {
     fn param(&self) -> Remote<ClientAddr> {
         self.tls.client_addr
     }
 }
 
+impl svc::Param<connections::Description> for Http {
+    fn param(&self) -> connections::Description {
+        self.tls.describe(Some(self.http.to_string()))
+    }
+}
+
 impl svc::Param<tls::ConditionalServerTls> for Http {
     fn param(&self) -> tls::ConditionalServerTls {
         self.tls.permit.tls.clone()
@@ -222,6 +376,36 @@ impl svc::Param<Option<identity::Name>> for Http {
     }
 }
 
+impl svc::Param<ForwardClientId> for Http {
+    fn param(&self) -> ForwardClientId {
+        ForwardClientId(self.tls.permit.forward_client_id)
+    }
+}
+
+impl svc::Param<ForwardedFor> for Http {
+    fn param(&self) -> ForwardedFor {
+        ForwardedFor(None)
+    }
+}
+
+impl svc::Param<ExemptRoutes> for Http {
+    fn param(&self) -> ExemptRoutes {
+        ExemptRoutes(self.tls.permit.exempt_routes.clone())
+    }
+}
+
+impl svc::Param<UnauthorizedResponse> for Http {
+    fn param(&self) -> UnauthorizedResponse {
+        UnauthorizedResponse(self.tls.permit.deny_response.clone())
+    }
+}
+
+impl svc::Param<Probes> for Http {
+    fn param(&self) -> Probes {
+        Probes(self.tls.permit.probes.clone())
+    }
+}
+
 // === TlsParams ===
 
 impl<T> svc::ExtractParam<tls::server::Timeout, T> for TlsParams {
@@ -231,10 +415,48 @@ impl<T> svc::ExtractParam<tls::server::Timeout, T> for TlsParams {
     }
 }
 
-impl<T> svc::ExtractParam<Option<LocalCrtKey>, T> for TlsParams {
+impl<T> svc::ExtractParam<Option<WithHttpAlpn>, T> for TlsParams {
     #[inline]
-    fn extract_param(&self, _: &T) -> Option<LocalCrtKey> {
-        self.identity.clone()
+    fn extract_param(&self, _: &T) -> Option<WithHttpAlpn> {
+        self.identity.clone().map(WithHttpAlpn)
+    }
+}
+
+// === impl WithHttpAlpn ===
+
+impl svc::Param<tls::server::Config> for WithHttpAlpn {
+    fn param(&self) -> tls::server::Config {
+        // Copy the underlying TLS config and set ALPN values.
+        //
+        // TODO: Avoid cloning the server config for every connection.
+        let mut config = self.0.server_config().as_ref().clone();
+        config.alpn_protocols.extend(
+            [http::Version::H2, http::Version::Http1]
+                .iter()
+                .map(|v| v.alpn().into()),
+        );
+        config.into()
+    }
+}
+
+impl svc::Param<tls::LocalId> for WithHttpAlpn {
+    fn param(&self) -> tls::LocalId {
+        self.0.id().clone()
+    }
+}
+
+impl svc::Param<tls::server::TerminateSubdomains> for WithHttpAlpn {
+    fn param(&self) -> tls::server::TerminateSubdomains {
+        svc::Param::<tls::server::TerminateSubdomains>::param(&self.0)
+    }
+}
+
+impl<T: svc::Param<Remote<ClientAddr>>> svc::ExtractParam<tls::server::HandshakeLimit, T>
+    for TlsParams
+{
+    #[inline]
+    fn extract_param(&self, t: &T) -> tls::server::HandshakeLimit {
+        self.handshake_limits.extract(t)
     }
 }
 
@@ -257,7 +479,7 @@ mod tests {
         svc::{NewService, ServiceExt},
         trace, Error,
     };
-    use linkerd_server_policy::{Authentication, Authorization, Protocol, ServerPolicy};
+    use linkerd_server_policy::{Authentication, Authorization, Priority, Protocol, ServerPolicy};
 
     const HTTP: &[u8] = b"GET / HTTP/1.1\r\nhost: example.com\r\n\r\n";
     const NOT_HTTP: &[u8] = b"foo\r\nbar\r\nblah\r\n";
@@ -275,8 +497,15 @@ mod tests {
                     authentication: Authentication::Unauthenticated,
                     networks: vec![client_addr().ip().into()],
                     labels: None.into_iter().collect(),
+                    priority: Priority::Normal,
                 }],
                 labels: None.into_iter().collect(),
+                forward_client_id: true,
+                forward_addr: None,
+                exempt_routes: Vec::new(),
+                probes: Vec::new(),
+                deny_response: None,
+                audit: false,
             },
         );
 
@@ -291,6 +520,95 @@ mod tests {
             .expect("should succeed");
     }
 
+    #[tokio::test(flavor = "current_thread")]
+    async fn detect_tls_server_speaks_first() {
+        let _trace = trace::test::trace_init();
+
+        let allow = AllowPolicy::new(
+            client_addr(),
+            orig_dst_addr(),
+            ServerPolicy {
+                protocol: Protocol::ServerSpeaksFirst,
+                authorizations: vec![Authorization {
+                    authentication: Authentication::Unauthenticated,
+                    networks: vec![client_addr().ip().into()],
+                    labels: None.into_iter().collect(),
+                    priority: Priority::Normal,
+                }],
+                labels: None.into_iter().collect(),
+                forward_client_id: true,
+                forward_addr: None,
+                exempt_routes: Vec::new(),
+                probes: Vec::new(),
+                deny_response: None,
+                audit: false,
+            },
+        );
+
+        // The client never writes, so the connection must be dispatched without
+        // waiting on detection.
+        let (io, _) = io::duplex(1);
+        inbound()
+            .with_stack(new_panic("detect stack must not be used"))
+            .push_detect_tls(new_ok())
+            .into_inner()
+            .new_service(Target(allow))
+            .oneshot(io)
+            .await
+            .expect("should succeed");
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn detect_tls_passthrough() {
+        let _trace = trace::test::trace_init();
+
+        let policy = |authentication| {
+            AllowPolicy::new(
+                client_addr(),
+                orig_dst_addr(),
+                ServerPolicy {
+                    protocol: Protocol::PassthroughTls,
+                    authorizations: vec![Authorization {
+                        authentication,
+                        networks: vec![client_addr().ip().into()],
+                        labels: None.into_iter().collect(),
+                        priority: Priority::Normal,
+                    }],
+                    labels: None.into_iter().collect(),
+                    forward_client_id: true,
+                    forward_addr: None,
+                    exempt_routes: Vec::new(),
+                    probes: Vec::new(),
+                    deny_response: None,
+                    audit: false,
+                },
+            )
+        };
+
+        // The client never writes, so the connection must be dispatched without
+        // waiting on TLS detection.
+        let (io, _) = io::duplex(1);
+        inbound()
+            .with_stack(new_panic("detect stack must not be used"))
+            .push_detect_tls(new_ok())
+            .into_inner()
+            .new_service(Target(policy(Authentication::Unauthenticated)))
+            .oneshot(io)
+            .await
+            .expect("should succeed");
+
+        // Connections can't be authenticated without TLS detection.
+        let (io, _) = io::duplex(1);
+        inbound()
+            .with_stack(new_panic("detect stack must not be used"))
+            .push_detect_tls(new_panic("forward stack must not be used"))
+            .into_inner()
+            .new_service(Target(policy(Authentication::TlsUnauthenticated)))
+            .oneshot(io)
+            .await
+            .expect_err("should be unauthorized");
+    }
+
     #[tokio::test(flavor = "current_thread")]
     async fn detect_http_non_http() {
         let _trace = trace::test::trace_init();
@@ -303,11 +621,18 @@ mod tests {
                     timeout: std::time::Duration::from_secs(10),
                 },
                 labels: None.into_iter().collect(),
+                forward_client_id: true,
+                priority: Priority::Normal,
+                exempt_routes: None,
+                deny_response: None,
+                audited: false,
+                probes: Vec::new().into(),
                 tls: tls::ConditionalServerTls::Some(tls::ServerTls::Established {
                     client_id: Some(client_id()),
                     negotiated_protocol: None,
                 }),
             },
+            protocol: None,
         };
 
         let (ior, mut iow) = io::duplex(100);
@@ -323,6 +648,45 @@ mod tests {
             .expect("should succeed");
     }
 
+    #[tokio::test(flavor = "current_thread")]
+    async fn detect_http_sniffed() {
+        let _trace = trace::test::trace_init();
+
+        let target = Tls {
+            client_addr: client_addr(),
+            orig_dst_addr: orig_dst_addr(),
+            permit: Permitted {
+                protocol: Protocol::Detect {
+                    timeout: std::time::Duration::from_secs(10),
+                },
+                labels: None.into_iter().collect(),
+                forward_client_id: true,
+                priority: Priority::Normal,
+                exempt_routes: None,
+                deny_response: None,
+                audited: false,
+                probes: Vec::new().into(),
+                tls: tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello),
+            },
+            protocol: None,
+        };
+
+        let (ior, mut iow) = io::duplex(100);
+        iow.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+
+        inbound()
+            .with_stack(new_panic("http stack must not be used"))
+            .push_detect_http(svc::BoxNewService::new(|t: Tls| {
+                assert_eq!(t.protocol, Some(detect::sniff::Protocol("redis")));
+                svc::BoxService::new(svc::mk(|_: io::BoxedIo| future::ok::<(), Error>(())))
+            }))
+            .into_inner()
+            .new_service(target)
+            .oneshot(ior)
+            .await
+            .expect("should succeed");
+    }
+
     #[tokio::test(flavor = "current_thread")]
     async fn detect_http() {
         let _trace = trace::test::trace_init();
@@ -335,11 +699,18 @@ mod tests {
                     timeout: std::time::Duration::from_secs(10),
                 },
                 labels: None.into_iter().collect(),
+                forward_client_id: true,
+                priority: Priority::Normal,
+                exempt_routes: None,
+                deny_response: None,
+                audited: false,
+                probes: Vec::new().into(),
                 tls: tls::ConditionalServerTls::Some(tls::ServerTls::Established {
                     client_id: Some(client_id()),
                     negotiated_protocol: None,
                 }),
             },
+            protocol: None,
         };
 
         let (ior, mut iow) = io::duplex(100);
@@ -355,6 +726,50 @@ mod tests {
             .expect("should succeed");
     }
 
+    #[tokio::test(flavor = "current_thread")]
+    async fn detect_http_alpn() {
+        let _trace = trace::test::trace_init();
+
+        let target = Tls {
+            client_addr: client_addr(),
+            orig_dst_addr: orig_dst_addr(),
+            permit: Permitted {
+                protocol: Protocol::Detect {
+                    timeout: std::time::Duration::from_secs(10),
+                },
+                labels: None.into_iter().collect(),
+                forward_client_id: true,
+                priority: Priority::Normal,
+                exempt_routes: None,
+                deny_response: None,
+                audited: false,
+                probes: Vec::new().into(),
+                tls: tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+                    client_id: Some(client_id()),
+                    negotiated_protocol: Some(tls::NegotiatedProtocol(
+                        http::Version::H2.alpn().into(),
+                    )),
+                }),
+            },
+            protocol: None,
+        };
+
+        // The client never writes, so the connection must be dispatched without
+        // waiting on HTTP detection.
+        let (ior, _iow) = io::duplex(100);
+        inbound()
+            .with_stack(svc::BoxNewService::new(|t: Http| {
+                assert_eq!(t.http, http::Version::H2);
+                svc::BoxService::new(svc::mk(|_: io::BoxedIo| future::ok::<(), Error>(())))
+            }))
+            .push_detect_http(new_panic("tcp stack must not be used"))
+            .into_inner()
+            .new_service(target)
+            .oneshot(ior)
+            .await
+            .expect("should succeed");
+    }
+
     fn client_id() -> tls::ClientId {
         "testsa.testns.serviceaccount.identity.linkerd.cluster.local"
             .parse()
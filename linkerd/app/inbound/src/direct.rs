@@ -1,19 +1,86 @@
-use crate::Inbound;
+use crate::{handshake_limits::HandshakeLimits, Inbound};
+use futures::prelude::*;
 use linkerd_app_core::{
-    io,
-    proxy::identity::LocalCrtKey,
-    svc::{self, ExtractParam, InsertParam, Param},
+    identity, io,
+    proxy::{identity::LocalCrtKey, tunnel},
+    svc::{self, ExtractParam, InsertParam, Param, ServiceExt},
     tls,
     transport::{self, metrics::SensorIo, ClientAddr, OrigDstAddr, Remote},
     transport_header::{self, NewTransportHeaderServer, SessionProtocol, TransportHeader},
-    Conditional, Error, Infallible, NameAddr,
+    Conditional, Error, Infallible, NameAddr, NameMatch,
+};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    fmt::{self, Debug},
+    net::SocketAddr,
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
 };
-use std::{convert::TryFrom, fmt::Debug, net::SocketAddr};
 use thiserror::Error;
-use tracing::{debug_span, info_span};
+use tracing::{debug, debug_span, info_span};
+
+#[derive(Clone, Debug)]
+struct WithTransportHeaderAlpn {
+    local: LocalCrtKey,
+    tunnel: bool,
+    terminate_subdomains: bool,
+    trust_domains: Arc<[identity::FederatedTrustDomain]>,
+}
+
+/// Identifies a remote cluster that is reached through the gateway.
+///
+/// Clients select a cluster by setting their TLS SNI to
+/// `<cluster>.<gateway-identity>`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ClusterId(Arc<str>);
+
+/// The gateway through which a remote cluster is reached.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RemoteGateway {
+    pub addr: SocketAddr,
+    pub identity: identity::Name,
+}
+
+/// A remote cluster targeted by a gateway client.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RemoteCluster {
+    pub id: ClusterId,
+    pub gateway: RemoteGateway,
+}
+
+/// Configures the remote clusters that may be targeted on gateway connections,
+/// each with the gateway that connections are forwarded to and the client
+/// identities that are permitted to target it.
+///
+/// When no clusters are configured, only the gateway's own identity is
+/// accepted as an SNI.
+#[derive(Clone, Debug, Default)]
+pub struct GatewayClusters(Arc<HashMap<ClusterId, ClusterConfig>>);
+
+#[derive(Clone, Debug)]
+struct ClusterConfig {
+    identities: NameMatch,
+    gateway: RemoteGateway,
+}
+
+/// Determines the target cluster of each connection from its SNI.
+#[derive(Clone, Debug)]
+struct NewClusterSni<N> {
+    local_id: Option<tls::LocalId>,
+    clusters: GatewayClusters,
+    inner: N,
+}
 
 #[derive(Clone, Debug)]
-struct WithTransportHeaderAlpn(LocalCrtKey);
+struct ClusterSni<T, N> {
+    target: T,
+    local_id: Option<tls::LocalId>,
+    clusters: GatewayClusters,
+    inner: N,
+}
 
 /// Creates I/O errors when a connection cannot be forwarded because no transport
 /// header was present.
@@ -28,6 +95,25 @@ pub struct RefusedNoIdentity(());
 #[error("a named target must be provided on gateway connections")]
 struct RefusedNoTarget;
 
+#[derive(Debug, Error)]
+#[error("client {0} is in a federated trust domain and may only use the gateway")]
+struct RefusedFederatedIdentity(tls::ClientId);
+
+#[derive(Debug, Error)]
+#[error("tunneled connections must target a name and port: {0}")]
+struct RefusedTunnelTarget(http::uri::Authority);
+
+#[derive(Debug, Error)]
+#[error("unknown target cluster: {0}")]
+struct RefusedUnknownCluster(ClusterId);
+
+#[derive(Debug, Error)]
+#[error("client {client} may not target cluster {cluster}")]
+struct RefusedClusterIdentity {
+    cluster: ClusterId,
+    client: tls::ClientId,
+}
+
 /// Gateway connections come in two variants: those with a transport header, and
 /// legacy connections, without a transport header.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -40,6 +126,8 @@ pub enum GatewayConnection {
 pub struct GatewayTransportHeader {
     pub target: NameAddr,
     pub protocol: Option<SessionProtocol>,
+    /// Metadata provided by clients that negotiated a version 2 header.
+    pub extensions: transport_header::Extensions,
     pub client: ClientInfo,
 }
 
@@ -50,15 +138,20 @@ pub struct ClientInfo {
     pub alpn: Option<tls::NegotiatedProtocol>,
     pub client_addr: Remote<ClientAddr>,
     pub local_addr: SocketAddr,
+
+    /// The remote cluster selected by the client's SNI, if any.
+    pub cluster: Option<RemoteCluster>,
 }
 
 type FwdIo<I> = io::PrefixedIo<SensorIo<tls::server::Io<I>>>;
-pub type GatewayIo<I> = io::EitherIo<FwdIo<I>, SensorIo<tls::server::Io<I>>>;
+pub type GatewayIo<I> =
+    io::EitherIo<FwdIo<I>, io::EitherIo<SensorIo<tls::server::Io<I>>, tunnel::Stream>>;
 
 #[derive(Clone)]
 struct TlsParams {
     timeout: tls::server::Timeout,
     identity: Option<WithTransportHeaderAlpn>,
+    handshake_limits: HandshakeLimits,
 }
 
 impl<N> Inbound<N> {
@@ -69,7 +162,9 @@ impl<N> Inbound<N> {
     /// 1. Protocol detection is always performed;
     /// 2. TLS is required;
     /// 3. A transport header is expected. It's not strictly required, as
-    ///    gateways may need to accept HTTP requests from older proxy versions
+    ///    gateways may need to accept HTTP requests from older proxy versions;
+    /// 4. If enabled, connections may instead be tunnels, on which each
+    ///    stream is a gateway connection to the target it names.
     pub fn push_direct<T, I, NSvc, G, GSvc>(self, gateway: G) -> Inbound<svc::BoxNewTcp<T, I>>
     where
         T: Param<Remote<ClientAddr>> + Param<OrigDstAddr>,
@@ -92,6 +187,8 @@ impl<N> Inbound<N> {
     {
         self.map_stack(|config, rt, tcp| {
             let detect_timeout = config.proxy.detect_protocol_timeout;
+            let clusters = config.gateway_clusters.clone();
+            let trust_domains = config.gateway_trust_domains.clone();
 
             tcp.instrument(|_: &_| debug_span!("opaque"))
                 // When the transport header is present, it may be used for either local
@@ -102,27 +199,41 @@ impl<N> Inbound<N> {
                 // TODO: Apply port policies. This isn't necessary for now, since these connections
                 // always have a client identity. We'll need to honor client restrictions once those
                 // are supported, though.
+                //
+                // Clients in federated trust domains are only permitted to use
+                // the gateway, so they may not forward to local ports.
                 .push_switch(
-                    |(h, client): (TransportHeader, ClientInfo)| match h {
-                        TransportHeader {
-                            port,
-                            name: None,
-                            protocol: None,
-                        } => Ok(svc::Either::A(port)),
-                        TransportHeader {
-                            port,
-                            name: Some(name),
-                            protocol,
-                        } => Ok(svc::Either::B(GatewayTransportHeader {
-                            target: NameAddr::from((name, port)),
-                            protocol,
-                            client,
-                        })),
-                        TransportHeader {
-                            name: None,
-                            protocol: Some(_),
-                            ..
-                        } => Err(RefusedNoTarget),
+                    move |(h, client): (TransportHeader, ClientInfo)| -> Result<_, Error> {
+                        match h {
+                            TransportHeader {
+                                port,
+                                name: None,
+                                protocol: None,
+                                ..
+                            } => {
+                                let tls::ClientId(ref id) = client.client_id;
+                                if trust_domains.iter().any(|d| d.contains(id)) {
+                                    return Err(RefusedFederatedIdentity(client.client_id).into());
+                                }
+                                Ok(svc::Either::A(port))
+                            }
+                            TransportHeader {
+                                port,
+                                name: Some(name),
+                                protocol,
+                                extensions,
+                            } => Ok(svc::Either::B(GatewayTransportHeader {
+                                target: NameAddr::from((name, port)),
+                                protocol,
+                                extensions,
+                                client,
+                            })),
+                            TransportHeader {
+                                name: None,
+                                protocol: Some(_),
+                                ..
+                            } => Err(RefusedNoTarget.into()),
+                        }
                     },
                     // HTTP detection is not necessary in this case, since the transport
                     // header indicates the connection's HTTP version.
@@ -149,19 +260,68 @@ impl<N> Inbound<N> {
                     },
                     // TODO: Remove this after we have at least one stable release out
                     // with transport header support.
-                    svc::stack(gateway)
-                        .push_on_response(svc::MapTargetLayer::new(io::EitherIo::Right))
+                    svc::stack(gateway.clone())
+                        .push_on_response(svc::MapTargetLayer::new(|io| {
+                            io::EitherIo::Right(io::EitherIo::Left(io))
+                        }))
                         .instrument(|_: &GatewayConnection| info_span!("gateway", legacy = true))
                         .into_inner(),
                 )
+                // Tunnels carry a stream for each gateway connection, named
+                // by the stream's authority.
+                .push_switch(
+                    |client: ClientInfo| {
+                        if client.tunnel_negotiated() {
+                            Ok::<_, Infallible>(svc::Either::B(client))
+                        } else {
+                            Ok(svc::Either::A(client))
+                        }
+                    },
+                    svc::stack(gateway)
+                        .push_on_response(svc::MapTargetLayer::new(|io| {
+                            io::EitherIo::Right(io::EitherIo::Right(io))
+                        }))
+                        .push_map_target(GatewayConnection::TransportHeader)
+                        .instrument(
+                            |g: &GatewayTransportHeader| info_span!("gateway", dst = %g.target),
+                        )
+                        .push_request_filter(
+                            |(authority, client): (http::uri::Authority, ClientInfo)| {
+                                let target = NameAddr::from_str(authority.as_str())
+                                    .map_err(|_| RefusedTunnelTarget(authority))?;
+                                Ok::<_, Error>(GatewayTransportHeader {
+                                    target,
+                                    protocol: None,
+                                    extensions: Default::default(),
+                                    client,
+                                })
+                            },
+                        )
+                        .push(tunnel::NewServeTunnel::layer())
+                        .instrument(|_: &ClientInfo| debug_span!("tunnel"))
+                        .into_inner(),
+                )
                 .push(rt.metrics.transport.layer_accept())
+                // Refuse connections that target a cluster that the client is
+                // not permitted to target.
+                .push_request_filter(clusters.clone())
                 // Build a ClientInfo target for each accepted connection. Refuse the
                 // connection if it doesn't include an mTLS identity.
                 .push_request_filter(ClientInfo::try_from)
                 .push(svc::BoxNewService::layer())
+                .push(NewClusterSni::layer(
+                    rt.identity.as_ref().map(|l| l.id().clone()),
+                    clusters.clone(),
+                ))
                 .push(tls::NewDetectTls::layer(TlsParams {
                     timeout: tls::server::Timeout(detect_timeout),
-                    identity: rt.identity.clone().map(WithTransportHeaderAlpn),
+                    identity: rt.identity.clone().map(|local| WithTransportHeaderAlpn {
+                        local,
+                        tunnel: config.tunnel,
+                        terminate_subdomains: !clusters.is_empty(),
+                        trust_domains: config.gateway_trust_domains.clone(),
+                    }),
+                    handshake_limits: config.tls_handshake_limits.clone(),
                 }))
                 .check_new_service::<T, I>()
                 .push_on_response(svc::BoxService::layer())
@@ -172,14 +332,16 @@ impl<N> Inbound<N> {
 
 // === impl ClientInfo ===
 
-impl<T> TryFrom<(tls::ConditionalServerTls, T)> for ClientInfo
+impl<T> TryFrom<(Option<RemoteCluster>, (tls::ConditionalServerTls, T))> for ClientInfo
 where
     T: Param<OrigDstAddr>,
     T: Param<Remote<ClientAddr>>,
 {
     type Error = Error;
 
-    fn try_from((tls, addrs): (tls::ConditionalServerTls, T)) -> Result<Self, Self::Error> {
+    fn try_from(
+        (cluster, (tls, addrs)): (Option<RemoteCluster>, (tls::ConditionalServerTls, T)),
+    ) -> Result<Self, Self::Error> {
         match tls {
             Conditional::Some(tls::ServerTls::Established {
                 client_id: Some(client_id),
@@ -191,6 +353,7 @@ where
                     alpn: negotiated_protocol,
                     client_addr: addrs.param(),
                     local_addr,
+                    cluster,
                 })
             }
             _ => Err(RefusedNoIdentity(()).into()),
@@ -202,21 +365,203 @@ impl ClientInfo {
     fn header_negotiated(&self) -> bool {
         self.alpn
             .as_ref()
-            .map(|tls::NegotiatedProtocol(p)| p == transport_header::PROTOCOL)
+            .map(|tls::NegotiatedProtocol(p)| transport_header::Version::from_alpn(p).is_some())
+            .unwrap_or(false)
+    }
+
+    fn tunnel_negotiated(&self) -> bool {
+        self.alpn
+            .as_ref()
+            .map(|tls::NegotiatedProtocol(p)| p == tunnel::PROTOCOL)
             .unwrap_or(false)
     }
 }
 
 impl Param<transport::labels::Key> for ClientInfo {
     fn param(&self) -> transport::labels::Key {
-        transport::labels::Key::accept(
-            transport::labels::Direction::In,
-            Conditional::Some(tls::ServerTls::Established {
-                client_id: Some(self.client_id.clone()),
-                negotiated_protocol: self.alpn.clone(),
-            }),
-            self.local_addr,
-        )
+        let tls = Conditional::Some(tls::ServerTls::Established {
+            client_id: Some(self.client_id.clone()),
+            negotiated_protocol: self.alpn.clone(),
+        });
+        match self.cluster {
+            Some(RemoteCluster {
+                id: ClusterId(ref cluster),
+                ..
+            }) => transport::labels::Key::GatewayAccept {
+                tls,
+                target_addr: self.local_addr,
+                target_cluster: cluster.clone(),
+            },
+            None => transport::labels::Key::accept(
+                transport::labels::Direction::In,
+                tls,
+                self.local_addr,
+            ),
+        }
+    }
+}
+
+// === impl ClusterId ===
+
+impl ClusterId {
+    /// Extracts a cluster from an SNI of the form `<cluster>.<local-id>`.
+    fn from_sni(
+        tls::ServerId(sni): &tls::ServerId,
+        tls::LocalId(local): &tls::LocalId,
+    ) -> Option<Self> {
+        let (sni, local): (&str, &str) = (sni.as_ref(), local.as_ref());
+        let cluster = sni.strip_suffix(local)?.strip_suffix('.')?;
+        if cluster.is_empty() {
+            return None;
+        }
+        Some(Self(cluster.into()))
+    }
+}
+
+impl From<&str> for ClusterId {
+    fn from(cluster: &str) -> Self {
+        Self(cluster.into())
+    }
+}
+
+impl fmt::Display for ClusterId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// === impl GatewayClusters ===
+
+impl GatewayClusters {
+    pub fn new(clusters: impl IntoIterator<Item = (ClusterId, NameMatch, RemoteGateway)>) -> Self {
+        Self(Arc::new(
+            clusters
+                .into_iter()
+                .map(|(id, identities, gateway)| {
+                    (
+                        id,
+                        ClusterConfig {
+                            identities,
+                            gateway,
+                        },
+                    )
+                })
+                .collect(),
+        ))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Determines the remote cluster targeted by a client's SNI.
+    ///
+    /// Returns `None` if the SNI does not name a cluster (i.e. it names the
+    /// gateway itself), and an error if it names an unknown cluster.
+    pub fn from_sni(
+        &self,
+        sni: &tls::ServerId,
+        local: &tls::LocalId,
+    ) -> Result<Option<RemoteCluster>, Error> {
+        let id = match ClusterId::from_sni(sni, local) {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        let ClusterConfig { gateway, .. } = self
+            .0
+            .get(&id)
+            .ok_or_else(|| RefusedUnknownCluster(id.clone()))?;
+        Ok(Some(RemoteCluster {
+            id,
+            gateway: gateway.clone(),
+        }))
+    }
+}
+
+impl svc::Predicate<ClientInfo> for GatewayClusters {
+    type Request = ClientInfo;
+
+    fn check(&mut self, client: ClientInfo) -> Result<ClientInfo, Error> {
+        let cluster = match client.cluster {
+            Some(RemoteCluster { ref id, .. }) => id,
+            None => return Ok(client),
+        };
+        let ClusterConfig { identities, .. } = self
+            .0
+            .get(cluster)
+            .ok_or_else(|| RefusedUnknownCluster(cluster.clone()))?;
+        let tls::ClientId(ref id) = client.client_id;
+        if !identities.matches(id) {
+            return Err(RefusedClusterIdentity {
+                cluster: cluster.clone(),
+                client: client.client_id.clone(),
+            }
+            .into());
+        }
+        Ok(client)
+    }
+}
+
+// === impl NewClusterSni ===
+
+impl<N> NewClusterSni<N> {
+    fn layer(
+        local_id: Option<tls::LocalId>,
+        clusters: GatewayClusters,
+    ) -> impl svc::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            local_id: local_id.clone(),
+            clusters: clusters.clone(),
+            inner,
+        })
+    }
+}
+
+impl<T, N: Clone> svc::NewService<T> for NewClusterSni<N> {
+    type Service = ClusterSni<T, N>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        ClusterSni {
+            target,
+            local_id: self.local_id.clone(),
+            clusters: self.clusters.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+// === impl ClusterSni ===
+
+impl<I, T, N, S> svc::Service<tls::server::Io<I>> for ClusterSni<T, N>
+where
+    I: Send + 'static,
+    T: Clone,
+    N: svc::NewService<(Option<RemoteCluster>, T), Service = S>,
+    S: svc::Service<tls::server::Io<I>, Response = ()> + Send + 'static,
+    S::Error: Into<Error>,
+    S::Future: Send,
+{
+    type Response = ();
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, io: tls::server::Io<I>) -> Self::Future {
+        let sni = tls::server::server_name(&io);
+        let cluster = match (self.local_id.as_ref(), sni) {
+            (Some(local), Some(sni)) => match self.clusters.from_sni(&sni, local) {
+                Ok(cluster) => cluster,
+                Err(e) => return Box::pin(future::err(e)),
+            },
+            _ => None,
+        };
+        debug!(?cluster);
+        let svc = self.inner.new_service((cluster, self.target.clone()));
+        Box::pin(svc.oneshot(io).err_into::<Error>())
     }
 }
 
@@ -229,17 +574,30 @@ impl svc::Param<tls::server::Config> for WithTransportHeaderAlpn {
         // TODO: Avoid cloning the server config for every connection. It would
         // be preferable if rustls::ServerConfig wrapped individual fields in an
         // Arc so they could be overridden independently.
-        let mut config = self.0.server_config().as_ref().clone();
+        let mut config = self
+            .local
+            .federated_server_config(&self.trust_domains)
+            .as_ref()
+            .clone();
+        if self.tunnel {
+            config.alpn_protocols.push(tunnel::PROTOCOL.into());
+        }
         config
             .alpn_protocols
-            .push(transport_header::PROTOCOL.into());
+            .extend(transport_header::PROTOCOLS.iter().map(|&p| p.into()));
         config.into()
     }
 }
 
 impl svc::Param<tls::LocalId> for WithTransportHeaderAlpn {
     fn param(&self) -> tls::LocalId {
-        self.0.id().clone()
+        self.local.id().clone()
+    }
+}
+
+impl svc::Param<tls::server::TerminateSubdomains> for WithTransportHeaderAlpn {
+    fn param(&self) -> tls::server::TerminateSubdomains {
+        tls::server::TerminateSubdomains(self.terminate_subdomains)
     }
 }
 
@@ -270,6 +628,13 @@ impl<T> ExtractParam<Option<WithTransportHeaderAlpn>, T> for TlsParams {
     }
 }
 
+impl<T: Param<Remote<ClientAddr>>> ExtractParam<tls::server::HandshakeLimit, T> for TlsParams {
+    #[inline]
+    fn extract_param(&self, t: &T) -> tls::server::HandshakeLimit {
+        self.handshake_limits.extract(t)
+    }
+}
+
 impl<T> InsertParam<tls::ConditionalServerTls, T> for TlsParams {
     type Target = (tls::ConditionalServerTls, T);
 
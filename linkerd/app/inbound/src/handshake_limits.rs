@@ -0,0 +1,159 @@
+use linkerd_app_core::{
+    metrics::{metrics, FmtMetrics, Gauge},
+    svc::stack::Param,
+    tls::server::{HandshakeLimit, HandshakeRate},
+    transport::{ClientAddr, Remote},
+    IpNet,
+};
+use parking_lot::Mutex;
+use std::{collections::HashMap, fmt, net::IpAddr, sync::Arc};
+
+metrics! {
+    inbound_tls_handshake_limit_networks: Gauge {
+        "The number of client networks for which inbound TLS handshakes are being rate limited"
+    }
+}
+
+/// Configures the rate at which inbound TLS handshakes are performed for each
+/// client network.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HandshakeLimitConfig {
+    /// The number of handshakes each network may initiate per second.
+    pub per_second: u32,
+
+    /// The prefix lengths by which IPv4 and IPv6 client addresses are grouped
+    /// into networks.
+    pub ipv4_prefix_len: u8,
+    pub ipv6_prefix_len: u8,
+}
+
+/// Limits the rate of inbound TLS handshakes by client network, so that
+/// handshake floods are shed before any cryptographic work is done for them.
+///
+/// Limits are tracked for a bounded number of networks. Once that many networks
+/// have initiated handshakes recently, clients from other networks share a
+/// single limit.
+#[derive(Clone, Debug, Default)]
+pub struct HandshakeLimits(Option<Arc<Inner>>);
+
+#[derive(Debug)]
+struct Inner {
+    config: HandshakeLimitConfig,
+    networks: Mutex<HashMap<IpNet, HandshakeRate>>,
+    overflow: HandshakeRate,
+}
+
+const MAX_NETWORKS: usize = 10_000;
+
+// === impl HandshakeLimits ===
+
+impl HandshakeLimits {
+    pub fn new(config: HandshakeLimitConfig) -> Self {
+        let config = HandshakeLimitConfig {
+            ipv4_prefix_len: config.ipv4_prefix_len.min(32),
+            ipv6_prefix_len: config.ipv6_prefix_len.min(128),
+            ..config
+        };
+        Self(Some(Arc::new(Inner {
+            config,
+            networks: Mutex::new(HashMap::new()),
+            overflow: HandshakeRate::new(config.per_second),
+        })))
+    }
+
+    /// Returns the handshake limit for a target's client, if handshakes are
+    /// limited.
+    pub(crate) fn extract<T: Param<Remote<ClientAddr>>>(&self, target: &T) -> HandshakeLimit {
+        let Remote(ClientAddr(addr)) = target.param();
+        HandshakeLimit(self.0.as_ref().map(|inner| inner.get(addr.ip())))
+    }
+}
+
+impl FmtMetrics for HandshakeLimits {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = match self.0.as_ref() {
+            Some(inner) => inner,
+            None => return Ok(()),
+        };
+
+        let networks = Gauge::from(inner.networks.lock().len() as u64);
+        inbound_tls_handshake_limit_networks.fmt_help(f)?;
+        inbound_tls_handshake_limit_networks.fmt_metric(f, &networks)
+    }
+}
+
+// === impl Inner ===
+
+impl Inner {
+    fn get(&self, client: IpAddr) -> HandshakeRate {
+        let prefix_len = match client {
+            IpAddr::V4(_) => self.config.ipv4_prefix_len,
+            IpAddr::V6(_) => self.config.ipv6_prefix_len,
+        };
+        let net = IpNet::new(client, prefix_len)
+            .expect("prefix lengths must be valid")
+            .trunc();
+
+        let mut networks = self.networks.lock();
+        if let Some(rate) = networks.get(&net) {
+            return rate.clone();
+        }
+
+        // Stop tracking networks that haven't initiated handshakes recently
+        // before tracking another.
+        if networks.len() >= MAX_NETWORKS {
+            networks.retain(|_, rate| !rate.is_idle());
+            if networks.len() >= MAX_NETWORKS {
+                return self.overflow.clone();
+            }
+        }
+
+        let rate = HandshakeRate::new(self.config.per_second);
+        networks.insert(net, rate.clone());
+        rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> HandshakeLimits {
+        HandshakeLimits::new(HandshakeLimitConfig {
+            per_second: 1,
+            ipv4_prefix_len: 24,
+            ipv6_prefix_len: 64,
+        })
+    }
+
+    fn client(ip: impl Into<IpAddr>) -> Remote<ClientAddr> {
+        Remote(ClientAddr((ip.into(), 54321).into()))
+    }
+
+    fn admit(limits: &HandshakeLimits, ip: impl Into<IpAddr>) -> bool {
+        match limits.extract(&client(ip)) {
+            HandshakeLimit(Some(rate)) => rate.try_acquire(),
+            HandshakeLimit(None) => panic!("handshakes must be limited"),
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn limits_by_network() {
+        tokio::time::pause();
+        let limits = limits();
+
+        assert!(admit(&limits, [192, 0, 2, 3]));
+        assert!(
+            !admit(&limits, [192, 0, 2, 4]),
+            "clients on the same network must share a limit"
+        );
+        assert!(admit(&limits, [198, 51, 100, 3]));
+        assert!(admit(&limits, "2001:db8::1".parse::<IpAddr>().unwrap()));
+        assert!(!admit(&limits, "2001:db8::2".parse::<IpAddr>().unwrap()));
+        assert!(admit(&limits, "2001:db8:0:1::1".parse::<IpAddr>().unwrap()));
+        assert_eq!(limits.0.as_ref().unwrap().networks.lock().len(), 4);
+
+        let HandshakeLimit(rate) = HandshakeLimits::default().extract(&client([192, 0, 2, 3]));
+        assert!(rate.is_none(), "handshakes must not be limited by default");
+    }
+}
@@ -0,0 +1,194 @@
+use futures::{future, TryFutureExt};
+use linkerd_app_core::{errors::HttpError, jwt, proxy::http, svc, Error};
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tracing::{debug, trace};
+
+/// Restricts routes to requests whose validated bearer tokens have the
+/// route's required claims.
+///
+/// Claims are read from the request's extensions, so this must be below the
+/// JWT validation layer.
+#[derive(Clone, Debug)]
+pub struct NewAuthorizeClaims<N> {
+    inner: N,
+    routes: Arc<[jwt::RequiredClaims]>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AuthorizeClaims<S> {
+    inner: S,
+    routes: Arc<[jwt::RequiredClaims]>,
+}
+
+// === impl NewAuthorizeClaims ===
+
+impl<N> NewAuthorizeClaims<N> {
+    pub fn layer(routes: Arc<[jwt::RequiredClaims]>) -> impl svc::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            routes: routes.clone(),
+        })
+    }
+}
+
+impl<T, N: svc::NewService<T>> svc::NewService<T> for NewAuthorizeClaims<N> {
+    type Service = AuthorizeClaims<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        AuthorizeClaims {
+            inner: self.inner.new_service(target),
+            routes: self.routes.clone(),
+        }
+    }
+}
+
+// === impl AuthorizeClaims ===
+
+impl<S, B> svc::Service<http::Request<B>> for AuthorizeClaims<S>
+where
+    S: svc::Service<http::Request<B>>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::Either<
+        future::ErrInto<S::Future, Error>,
+        future::Ready<Result<S::Response, Error>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let method = req.method().as_str();
+        let path = req.uri().path();
+        if let Some(route) = self.routes.iter().find(|r| r.matches(method, path)) {
+            let claims = req.extensions().get::<jwt::Claims>();
+            if !route.permits(claims) {
+                debug!(%route, %method, %path, "Token claims not authorized");
+                return future::Either::Right(future::err(
+                    HttpError::unauthorized("token claims not authorized").into(),
+                ));
+            }
+            trace!(%route, "Token claims authorized");
+        }
+
+        future::Either::Left(self.inner.call(req).err_into::<Error>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::svc::{Layer, NewService};
+    use linkerd_http_jwt::test_util::Signer;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    use tower::ServiceExt;
+
+    fn routes() -> Arc<[jwt::RequiredClaims]> {
+        vec![
+            jwt::RequiredClaims {
+                method: None,
+                path_prefix: "/admin".to_string(),
+                claims: vec![("groups".to_string(), "admin".to_string())],
+            },
+            jwt::RequiredClaims {
+                method: Some("GET".to_string()),
+                path_prefix: "/".to_string(),
+                claims: vec![],
+            },
+        ]
+        .into()
+    }
+
+    fn token(signer: &Signer, groups: &[&str]) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        signer.sign(
+            "a",
+            serde_json::json!({ "sub": "alice", "groups": groups, "exp": now + 60 }),
+        )
+    }
+
+    async fn send(
+        signer: &Signer,
+        token: Option<String>,
+        method: http::Method,
+        path: &str,
+    ) -> Result<(), Error> {
+        let validator = jwt::Validator::new(jwt::Config {
+            jwks: jwt::JwksSource::Static(signer.jwks("a")),
+            issuer: None,
+            audiences: vec![],
+            leeway: Duration::from_secs(30),
+            require_expiration: true,
+            required: false,
+            required_claims: vec![],
+        });
+        let mut new_svc = jwt::NewValidateJwt::layer(Some(validator)).layer(
+            NewAuthorizeClaims::layer(routes())
+                .layer(|_: ()| svc::mk(|_: http::Request<()>| future::ok::<_, Error>(()))),
+        );
+        let req = token
+            .into_iter()
+            .fold(http::Request::builder(), |req, token| {
+                req.header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+            })
+            .method(method)
+            .uri(path)
+            .body(())
+            .unwrap();
+        new_svc.new_service(()).oneshot(req).await
+    }
+
+    fn is_unauthorized(result: Result<(), Error>) -> bool {
+        result
+            .expect_err("request must be rejected")
+            .is::<HttpError>()
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn requires_claims_on_matching_routes() {
+        let signer = Signer::new();
+        send(
+            &signer,
+            Some(token(&signer, &["dev", "admin"])),
+            http::Method::POST,
+            "/admin/users",
+        )
+        .await
+        .expect("request must be permitted");
+        assert!(is_unauthorized(
+            send(
+                &signer,
+                Some(token(&signer, &["dev"])),
+                http::Method::POST,
+                "/admin/users",
+            )
+            .await
+        ));
+        assert!(is_unauthorized(
+            send(&signer, None, http::Method::POST, "/admin/users").await
+        ));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn first_matching_route_applies() {
+        let signer = Signer::new();
+        // Matches a route that requires no claims.
+        send(&signer, None, http::Method::GET, "/users")
+            .await
+            .expect("request must be permitted");
+        // Requests that match no route are not restricted.
+        send(&signer, None, http::Method::POST, "/users")
+            .await
+            .expect("request must be permitted");
+    }
+}
@@ -0,0 +1,225 @@
+use crate::port_policies::{DenyResponse, ExemptRoute};
+use futures::{future, TryFutureExt};
+use linkerd_app_core::{errors::HttpError, proxy::http, svc, Error};
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tracing::{debug, warn};
+
+/// The HTTP routes to which a target's requests are restricted, if its
+/// connection was only permitted because its server exempts these routes from
+/// authorization.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExemptRoutes(pub Option<Arc<[ExemptRoute]>>);
+
+/// The response returned to requests that are not authorized, if the target's
+/// server overrides the default error response.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UnauthorizedResponse(pub Option<Arc<DenyResponse>>);
+
+#[derive(Clone, Debug)]
+pub struct NewRestrictExemptRoutes<N> {
+    inner: N,
+}
+
+#[derive(Clone, Debug)]
+pub struct RestrictExemptRoutes<S> {
+    inner: S,
+    routes: Option<Arc<[ExemptRoute]>>,
+    deny_response: Option<Arc<DenyResponse>>,
+}
+
+// === impl NewRestrictExemptRoutes ===
+
+impl<N> NewRestrictExemptRoutes<N> {
+    pub fn layer() -> impl svc::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewRestrictExemptRoutes<N>
+where
+    T: svc::Param<ExemptRoutes> + svc::Param<UnauthorizedResponse>,
+    N: svc::NewService<T>,
+{
+    type Service = RestrictExemptRoutes<N::Service>;
+
+    fn new_service(&mut self, t: T) -> Self::Service {
+        let ExemptRoutes(routes) = t.param();
+        let UnauthorizedResponse(deny_response) = t.param();
+        RestrictExemptRoutes {
+            routes,
+            deny_response,
+            inner: self.inner.new_service(t),
+        }
+    }
+}
+
+// === impl RestrictExemptRoutes ===
+
+impl<S, B> svc::Service<http::Request<B>> for RestrictExemptRoutes<S>
+where
+    S: svc::Service<http::Request<B>, Response = http::Response<http::BoxBody>>,
+    S::Error: Into<Error>,
+{
+    type Response = http::Response<http::BoxBody>;
+    type Error = Error;
+    type Future = future::Either<
+        future::ErrInto<S::Future, Error>,
+        future::Ready<Result<Self::Response, Error>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        if let Some(routes) = self.routes.as_ref() {
+            let method = req.method().as_str();
+            let path = req.uri().path();
+            if !routes.iter().any(|r| r.matches(method, path)) {
+                debug!(%method, %path, "Request not authorized");
+                if let Some(rsp) = self.deny_response.as_deref().and_then(mk_response) {
+                    return future::Either::Right(future::ok(rsp));
+                }
+                return future::Either::Right(future::err(
+                    HttpError::unauthorized("request not authorized").into(),
+                ));
+            }
+        }
+
+        future::Either::Left(self.inner.call(req).err_into::<Error>())
+    }
+}
+
+/// Builds the server's response to unauthorized requests, or returns `None` if
+/// it is invalid so that the default error response is used.
+fn mk_response(deny: &DenyResponse) -> Option<http::Response<http::BoxBody>> {
+    let mut rsp = http::Response::builder().status(deny.status);
+    for (name, value) in deny.headers.iter() {
+        rsp = rsp.header(name.as_str(), value.as_str());
+    }
+    let body = hyper::Body::from(deny.body.clone());
+    match rsp.body(http::BoxBody::new(body)) {
+        Ok(rsp) => Some(rsp),
+        Err(error) => {
+            warn!(%error, "Invalid response for unauthorized requests");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::svc::{Layer, NewService};
+    use tower::ServiceExt;
+
+    #[derive(Clone)]
+    struct Target(ExemptRoutes, UnauthorizedResponse);
+
+    impl svc::Param<ExemptRoutes> for Target {
+        fn param(&self) -> ExemptRoutes {
+            self.0.clone()
+        }
+    }
+
+    impl svc::Param<UnauthorizedResponse> for Target {
+        fn param(&self) -> UnauthorizedResponse {
+            self.1.clone()
+        }
+    }
+
+    fn healthz() -> ExemptRoutes {
+        ExemptRoutes(Some(
+            vec![ExemptRoute {
+                method: Some("GET".to_string()),
+                path: "/healthz".to_string(),
+            }]
+            .into(),
+        ))
+    }
+
+    async fn send(
+        target: Target,
+        method: http::Method,
+        uri: &str,
+    ) -> Result<http::Response<http::BoxBody>, Error> {
+        let mut new_svc = NewRestrictExemptRoutes::layer().layer(|_: Target| {
+            svc::mk(|_: http::Request<()>| {
+                future::ok::<_, std::convert::Infallible>(http::Response::default())
+            })
+        });
+        let req = http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(())
+            .unwrap();
+        new_svc.new_service(target).oneshot(req).await
+    }
+
+    #[tokio::test]
+    async fn exempt_route_permitted() {
+        send(
+            Target(healthz(), Default::default()),
+            http::Method::GET,
+            "/healthz",
+        )
+        .await
+        .expect("exempt route must be permitted");
+    }
+
+    #[tokio::test]
+    async fn other_routes_denied() {
+        let err = send(
+            Target(healthz(), Default::default()),
+            http::Method::POST,
+            "/healthz",
+        )
+        .await
+        .expect_err("method must match");
+        assert!(err.is::<HttpError>());
+        send(
+            Target(healthz(), Default::default()),
+            http::Method::GET,
+            "/admin",
+        )
+        .await
+        .expect_err("path must match");
+    }
+
+    #[tokio::test]
+    async fn authorized_unrestricted() {
+        send(
+            Target(ExemptRoutes(None), Default::default()),
+            http::Method::POST,
+            "/admin",
+        )
+        .await
+        .expect("authorized connections must not be restricted");
+    }
+
+    #[tokio::test]
+    async fn deny_response() {
+        let deny = UnauthorizedResponse(Some(Arc::new(DenyResponse {
+            status: 403,
+            headers: vec![("content-type".to_string(), "application/json".to_string())],
+            body: r#"{"error":"contact #platform"}"#.to_string(),
+        })));
+
+        let rsp = send(Target(healthz(), deny.clone()), http::Method::GET, "/admin")
+            .await
+            .expect("denied requests must be answered");
+        assert_eq!(rsp.status(), http::StatusCode::FORBIDDEN);
+        assert_eq!(rsp.headers()["content-type"], "application/json");
+        let body = hyper::body::to_bytes(rsp.into_body()).await.unwrap();
+        assert_eq!(&body[..], br#"{"error":"contact #platform"}"#);
+
+        let rsp = send(Target(healthz(), deny), http::Method::GET, "/healthz")
+            .await
+            .expect("exempt route must be permitted");
+        assert_eq!(rsp.status(), http::StatusCode::OK);
+    }
+}
@@ -0,0 +1,263 @@
+use linkerd_app_core::{
+    control,
+    ext_authz::{self, GrpcService},
+    identity,
+    proxy::http::{self, ClientHandle, HttpBody},
+    svc, Error,
+};
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tracing::debug;
+
+/// Checks inbound requests with an external authorization service, reached
+/// via a control-plane-style client.
+pub type ExtAuthz = ext_authz::Authorizer<control::Client<ext_authz::BoxBody>>;
+
+#[derive(Clone, Debug)]
+pub struct NewExtAuthz<N, C = control::Client<ext_authz::BoxBody>> {
+    inner: N,
+    authz: Option<ext_authz::Authorizer<C>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CheckExtAuthz<S, C = control::Client<ext_authz::BoxBody>> {
+    inner: S,
+    authz: Option<ext_authz::Authorizer<C>>,
+    client_id: Option<String>,
+}
+
+// === impl NewExtAuthz ===
+
+impl<N, C: Clone> NewExtAuthz<N, C> {
+    pub fn layer(
+        authz: Option<ext_authz::Authorizer<C>>,
+    ) -> impl svc::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            authz: authz.clone(),
+        })
+    }
+}
+
+impl<T, N, C> svc::NewService<T> for NewExtAuthz<N, C>
+where
+    T: svc::Param<Option<identity::Name>>,
+    N: svc::NewService<T>,
+    C: Clone,
+{
+    type Service = CheckExtAuthz<N::Service, C>;
+
+    fn new_service(&mut self, t: T) -> Self::Service {
+        let client_id = self.authz.as_ref().and_then(|_| {
+            svc::Param::<Option<identity::Name>>::param(&t).map(|n| n.as_ref().to_string())
+        });
+        CheckExtAuthz {
+            client_id,
+            authz: self.authz.clone(),
+            inner: self.inner.new_service(t),
+        }
+    }
+}
+
+// === impl CheckExtAuthz ===
+
+impl<S, C> svc::Service<http::Request<http::BoxBody>> for CheckExtAuthz<S, C>
+where
+    S: svc::Service<http::Request<http::BoxBody>, Response = http::Response<http::BoxBody>>
+        + Clone
+        + Send
+        + 'static,
+    S::Error: Into<Error>,
+    S::Future: Send,
+    C: GrpcService<ext_authz::BoxBody> + Clone + Send + 'static,
+    C::ResponseBody: Send + Sync + 'static,
+    <C::ResponseBody as HttpBody>::Data: Send,
+    <C::ResponseBody as HttpBody>::Error: Into<Error> + Send,
+    C::Future: Send,
+{
+    type Response = http::Response<http::BoxBody>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut req: http::Request<http::BoxBody>) -> Self::Future {
+        let authz = match self.authz.as_ref() {
+            Some(authz) => authz,
+            None => {
+                let call = self.inner.call(req);
+                return Box::pin(async move { call.await.map_err(Into::into) });
+            }
+        };
+
+        let check = authz.check(ext_authz::CheckRequest {
+            source: req.extensions().get::<ClientHandle>().map(|c| c.addr),
+            source_principal: self.client_id.clone(),
+            method: req.method().clone(),
+            uri: req.uri().clone(),
+            version: req.version(),
+            headers: req.headers().clone(),
+        });
+
+        // The inner service has already been driven to readiness, so take it
+        // and leave a clone in its place.
+        let mut inner = {
+            let clone = self.inner.clone();
+            std::mem::replace(&mut self.inner, clone)
+        };
+        Box::pin(async move {
+            match check.await? {
+                ext_authz::Decision::Allow(mutations) => {
+                    mutations.apply(req.headers_mut());
+                    inner.call(req).await.map_err(Into::into)
+                }
+                ext_authz::Decision::Deny(rsp) => {
+                    debug!(status = %rsp.status(), "Request denied by authorization service");
+                    Ok(rsp.map(http::BoxBody::new))
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+    use linkerd_app_core::svc::{Layer, NewService};
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    #[derive(Clone)]
+    struct Target(Option<identity::Name>);
+
+    impl svc::Param<Option<identity::Name>> for Target {
+        fn param(&self) -> Option<identity::Name> {
+            self.0.clone()
+        }
+    }
+
+    fn config(failure_mode_allow: bool) -> ext_authz::Config {
+        ext_authz::Config {
+            timeout: Duration::from_secs(1),
+            failure_mode_allow,
+            allowed_request_headers: vec![http::header::AUTHORIZATION],
+            allowed_upstream_headers: vec![http::header::HeaderName::from_static("x-user")],
+        }
+    }
+
+    /// Permits requests that carry the expected credentials.
+    fn authz() -> impl GrpcService<
+        ext_authz::BoxBody,
+        ResponseBody = hyper::Body,
+        Error = Error,
+        Future = future::Ready<Result<http::Response<hyper::Body>, Error>>,
+    > + Clone {
+        svc::mk(|req: http::Request<ext_authz::BoxBody>| {
+            let rsp = if req.headers().get(http::header::AUTHORIZATION)
+                == Some(&http::HeaderValue::from_static("Bearer ok"))
+            {
+                http::Response::builder()
+                    .header("x-user", "alice")
+                    .body(hyper::Body::empty())
+            } else {
+                http::Response::builder()
+                    .status(http::StatusCode::FORBIDDEN)
+                    .body(hyper::Body::from("nope"))
+            };
+            future::ok(rsp.unwrap())
+        })
+    }
+
+    /// Fails as though the authorization service could not be reached.
+    fn unreachable() -> impl GrpcService<
+        ext_authz::BoxBody,
+        ResponseBody = hyper::Body,
+        Error = Error,
+        Future = future::Ready<Result<http::Response<hyper::Body>, Error>>,
+    > + Clone {
+        svc::mk(|_: http::Request<ext_authz::BoxBody>| {
+            let error = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+            future::err::<http::Response<hyper::Body>, _>(error.into())
+        })
+    }
+
+    async fn send<C>(
+        authz: ext_authz::Authorizer<C>,
+        credentials: &'static str,
+    ) -> Result<http::Response<http::BoxBody>, Error>
+    where
+        C: GrpcService<ext_authz::BoxBody> + Clone + Send + 'static,
+        C::ResponseBody: Send + Sync + 'static,
+        <C::ResponseBody as HttpBody>::Data: Send,
+        <C::ResponseBody as HttpBody>::Error: Into<Error> + Send,
+        C::Future: Send,
+    {
+        let mut new_svc = NewExtAuthz::layer(Some(authz)).layer(|_: Target| {
+            svc::mk(|req: http::Request<http::BoxBody>| {
+                // Echo the user set by the authorization service.
+                let mut rsp = http::Response::new(http::BoxBody::default());
+                if let Some(user) = req.headers().get("x-user") {
+                    rsp.headers_mut().insert("x-user", user.clone());
+                }
+                future::ok::<_, Error>(rsp)
+            })
+        });
+        let req = http::Request::builder()
+            .uri("http://foo.ns1.svc.cluster.local/bar")
+            .header(http::header::AUTHORIZATION, credentials)
+            .header("x-user", "mallory")
+            .body(http::BoxBody::default())
+            .unwrap();
+        new_svc.new_service(Target(None)).oneshot(req).await
+    }
+
+    #[tokio::test]
+    async fn allowed() {
+        let authz = ext_authz::Authorizer::http(authz(), "/auth".parse().unwrap(), config(false));
+        let rsp = send(authz, "Bearer ok")
+            .await
+            .expect("request must succeed");
+        assert_eq!(rsp.status(), http::StatusCode::OK);
+        assert_eq!(rsp.headers()["x-user"], "alice");
+    }
+
+    #[tokio::test]
+    async fn denied() {
+        let authz = ext_authz::Authorizer::http(authz(), "/auth".parse().unwrap(), config(false));
+        let rsp = send(authz, "Bearer bad")
+            .await
+            .expect("request must succeed");
+        assert_eq!(rsp.status(), http::StatusCode::FORBIDDEN);
+        assert!(rsp.headers().get("x-user").is_none());
+        let body = hyper::body::to_bytes(rsp.into_body()).await.unwrap();
+        assert_eq!(body, "nope");
+    }
+
+    #[tokio::test]
+    async fn unreachable_fails_closed() {
+        let authz =
+            ext_authz::Authorizer::http(unreachable(), "/auth".parse().unwrap(), config(false));
+        let error = send(authz, "Bearer ok")
+            .await
+            .expect_err("request must fail");
+        assert!(error.is::<ext_authz::AuthorizationFailed>());
+    }
+
+    #[tokio::test]
+    async fn unreachable_fails_open() {
+        let authz =
+            ext_authz::Authorizer::http(unreachable(), "/auth".parse().unwrap(), config(true));
+        let rsp = send(authz, "Bearer ok")
+            .await
+            .expect("request must succeed");
+        assert_eq!(rsp.status(), http::StatusCode::OK);
+        assert_eq!(rsp.headers()["x-user"], "mallory");
+    }
+}
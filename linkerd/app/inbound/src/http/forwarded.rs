@@ -0,0 +1,268 @@
+use linkerd_app_core::{
+    dns, identity,
+    proxy::http,
+    svc,
+    transport::{ClientAddr, Remote},
+    IpMatch, NameMatch,
+};
+use std::{
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    task::{Context, Poll},
+};
+use tracing::{debug, trace};
+
+/// Configures how `Forwarded` and `X-Forwarded-For` headers are set on inbound
+/// requests.
+///
+/// Each request's headers are extended with the address of the client on
+/// whose behalf it was sent. Headers set by untrusted clients are replaced, so
+/// that the application may rely on them.
+#[derive(Clone, Debug, Default)]
+pub struct ForwardedPolicy {
+    /// Clients that connect from these networks are trusted.
+    pub trusted_networks: IpMatch,
+
+    /// Clients with identities in these domains are trusted.
+    pub trusted_identities: NameMatch,
+}
+
+/// The address of the client on whose behalf a connection's requests are sent,
+/// as asserted by the connection's peer (e.g. in a transport header).
+///
+/// The asserted address is only used if the peer is trusted.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ForwardedFor(pub Option<SocketAddr>);
+
+#[derive(Clone, Debug)]
+pub struct NewSetForwardedHeaders<N> {
+    inner: N,
+    policy: Option<ForwardedPolicy>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SetForwardedHeaders<S> {
+    inner: S,
+    forwarded: Option<Forwarded>,
+}
+
+#[derive(Clone, Debug)]
+struct Forwarded {
+    trusted: bool,
+    client: IpAddr,
+}
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+// === impl ForwardedPolicy ===
+
+impl ForwardedPolicy {
+    fn trusts(&self, addr: IpAddr, id: Option<&identity::Name>) -> bool {
+        if self.trusted_networks.matches(addr) {
+            return true;
+        }
+        id.and_then(|id| dns::Name::from_str(id.as_ref()).ok())
+            .map(|name| self.trusted_identities.matches(&name))
+            .unwrap_or(false)
+    }
+}
+
+// === impl NewSetForwardedHeaders ===
+
+impl<N> NewSetForwardedHeaders<N> {
+    pub fn layer(policy: Option<ForwardedPolicy>) -> impl svc::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            policy: policy.clone(),
+        })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewSetForwardedHeaders<N>
+where
+    T: svc::Param<Remote<ClientAddr>>
+        + svc::Param<Option<identity::Name>>
+        + svc::Param<ForwardedFor>,
+    N: svc::NewService<T>,
+{
+    type Service = SetForwardedHeaders<N::Service>;
+
+    fn new_service(&mut self, t: T) -> Self::Service {
+        let forwarded = self.policy.as_ref().map(|policy| {
+            let Remote(ClientAddr(peer)) = t.param();
+            let id: Option<identity::Name> = t.param();
+            let trusted = policy.trusts(peer.ip(), id.as_ref());
+            let client = match t.param() {
+                ForwardedFor(Some(client)) if trusted => client.ip(),
+                _ => peer.ip(),
+            };
+            trace!(%peer, %client, trusted, "Forwarding client address");
+            Forwarded { trusted, client }
+        });
+        SetForwardedHeaders {
+            forwarded,
+            inner: self.inner.new_service(t),
+        }
+    }
+}
+
+// === impl SetForwardedHeaders ===
+
+impl<S, B> svc::Service<http::Request<B>> for SetForwardedHeaders<S>
+where
+    S: svc::Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        if let Some(Forwarded { trusted, client }) = self.forwarded {
+            let headers = req.headers_mut();
+            if !trusted {
+                let forwarded = headers.remove(http::header::FORWARDED);
+                let x_forwarded_for = headers.remove(X_FORWARDED_FOR);
+                if forwarded.is_some() || x_forwarded_for.is_some() {
+                    debug!(?forwarded, ?x_forwarded_for, "Stripped untrusted headers");
+                }
+            }
+
+            // RFC 7239 requires that IPv6 addresses are quoted and bracketed.
+            let node = match client {
+                IpAddr::V4(ip) => ip.to_string(),
+                IpAddr::V6(ip) => format!("\"[{}]\"", ip),
+            };
+            append(headers, http::header::FORWARDED, format!("for={}", node));
+            append(
+                headers,
+                http::HeaderName::from_static(X_FORWARDED_FOR),
+                client.to_string(),
+            );
+        }
+
+        self.inner.call(req)
+    }
+}
+
+/// Appends a value to a comma-separated header, combining any existing values
+/// into a single header field.
+fn append(headers: &mut http::header::HeaderMap, name: http::HeaderName, value: String) {
+    let mut values = headers
+        .get_all(&name)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .collect::<Vec<_>>()
+        .join(", ");
+    if !values.is_empty() {
+        values.push_str(", ");
+    }
+    values.push_str(&value);
+    let value = http::HeaderValue::from_str(&values).expect("header value must be valid");
+    headers.insert(name, value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+    use linkerd_app_core::{
+        svc::{Layer, NewService},
+        IpNet,
+    };
+    use tower::ServiceExt;
+
+    #[derive(Clone)]
+    struct Target {
+        peer: SocketAddr,
+        id: Option<identity::Name>,
+        forwarded_for: ForwardedFor,
+    }
+
+    impl svc::Param<Remote<ClientAddr>> for Target {
+        fn param(&self) -> Remote<ClientAddr> {
+            Remote(ClientAddr(self.peer))
+        }
+    }
+
+    impl svc::Param<Option<identity::Name>> for Target {
+        fn param(&self) -> Option<identity::Name> {
+            self.id.clone()
+        }
+    }
+
+    impl svc::Param<ForwardedFor> for Target {
+        fn param(&self) -> ForwardedFor {
+            self.forwarded_for
+        }
+    }
+
+    fn policy() -> ForwardedPolicy {
+        ForwardedPolicy {
+            trusted_networks: IpMatch::new(Some(IpNet::from_str("10.0.0.0/8").unwrap())),
+            trusted_identities: NameMatch::new(Some(
+                dns::Suffix::from_str("gateway.example.com").unwrap(),
+            )),
+        }
+    }
+
+    async fn forward(target: Target, req: http::Request<()>) -> http::header::HeaderMap {
+        let mut new_svc = NewSetForwardedHeaders::layer(Some(policy())).layer(|_: Target| {
+            svc::mk(|req: http::Request<()>| {
+                future::ok::<_, std::convert::Infallible>(req.headers().clone())
+            })
+        });
+        new_svc.new_service(target).oneshot(req).await.unwrap()
+    }
+
+    fn request(forwarded: &str, xff: &str) -> http::Request<()> {
+        http::Request::builder()
+            .header(http::header::FORWARDED, forwarded)
+            .header("x-forwarded-for", xff)
+            .body(())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn untrusted_peer_replaces_headers() {
+        let target = Target {
+            peer: ([192, 0, 2, 1], 5678).into(),
+            id: None,
+            forwarded_for: ForwardedFor(Some(([198, 51, 100, 1], 1234).into())),
+        };
+        let headers = forward(target, request("for=203.0.113.1", "203.0.113.1")).await;
+        assert_eq!(headers[http::header::FORWARDED], "for=192.0.2.1");
+        assert_eq!(headers["x-forwarded-for"], "192.0.2.1");
+    }
+
+    #[tokio::test]
+    async fn trusted_peer_extends_headers() {
+        let target = Target {
+            peer: ([192, 0, 2, 1], 5678).into(),
+            id: Some(identity::Name::from_str("east.gateway.example.com").unwrap()),
+            forwarded_for: ForwardedFor(Some(([198, 51, 100, 1], 1234).into())),
+        };
+        let headers = forward(target, request("for=203.0.113.1", "203.0.113.1")).await;
+        assert_eq!(
+            headers[http::header::FORWARDED],
+            "for=203.0.113.1, for=198.51.100.1"
+        );
+        assert_eq!(headers["x-forwarded-for"], "203.0.113.1, 198.51.100.1");
+    }
+
+    #[tokio::test]
+    async fn trusted_network() {
+        let target = Target {
+            peer: ([10, 1, 2, 3], 5678).into(),
+            id: None,
+            forwarded_for: ForwardedFor(None),
+        };
+        let headers = forward(target, http::Request::new(())).await;
+        assert_eq!(headers[http::header::FORWARDED], "for=10.1.2.3");
+        assert_eq!(headers["x-forwarded-for"], "10.1.2.3");
+    }
+}
@@ -1,9 +1,22 @@
+mod authorize_claims;
+mod exempt_routes;
+mod ext_authz;
+mod forwarded;
+mod probe;
 mod router;
 mod server;
 mod set_identity_header;
 #[cfg(test)]
 mod tests;
 
+pub use self::{
+    exempt_routes::{ExemptRoutes, UnauthorizedResponse},
+    ext_authz::ExtAuthz,
+    forwarded::{ForwardedFor, ForwardedPolicy},
+    probe::Probes,
+    set_identity_header::ForwardClientId,
+};
+
 fn trace_labels() -> std::collections::HashMap<String, String> {
     let mut l = std::collections::HashMap::new();
     l.insert("direction".to_string(), "inbound".to_string());
@@ -14,11 +27,12 @@ fn trace_labels() -> std::collections::HashMap<String, String> {
 pub mod fuzz {
     use crate::{
         http::router::Http,
+        port_policies::Priority,
         test_util::{
             support::{connect::Connect, http_util, profile, resolver},
             *,
         },
-        Config, Inbound,
+        Config, ExemptRoutes, ForwardClientId, ForwardedFor, Inbound, Probes, UnauthorizedResponse,
     };
     use hyper::{client::conn::Builder as ClientBuilder, Body, Request, Response};
     use libfuzzer_sys::arbitrary::Arbitrary;
@@ -188,6 +202,18 @@ pub mod fuzz {
         }
     }
 
+    impl svc::Param<u16> for Target {
+        fn param(&self) -> u16 {
+            Self::addr().port()
+        }
+    }
+
+    impl svc::Param<Priority> for Target {
+        fn param(&self) -> Priority {
+            Priority::Normal
+        }
+    }
+
     impl svc::Param<Remote<ClientAddr>> for Target {
         fn param(&self) -> Remote<ClientAddr> {
             Remote(ClientAddr(([192, 0, 2, 3], 50000).into()))
@@ -217,4 +243,34 @@ pub mod fuzz {
             None
         }
     }
+
+    impl svc::Param<ForwardClientId> for Target {
+        fn param(&self) -> ForwardClientId {
+            ForwardClientId(true)
+        }
+    }
+
+    impl svc::Param<ForwardedFor> for Target {
+        fn param(&self) -> ForwardedFor {
+            ForwardedFor(None)
+        }
+    }
+
+    impl svc::Param<ExemptRoutes> for Target {
+        fn param(&self) -> ExemptRoutes {
+            ExemptRoutes(None)
+        }
+    }
+
+    impl svc::Param<UnauthorizedResponse> for Target {
+        fn param(&self) -> UnauthorizedResponse {
+            UnauthorizedResponse(None)
+        }
+    }
+
+    impl svc::Param<Probes> for Target {
+        fn param(&self) -> Probes {
+            Probes(Vec::new().into())
+        }
+    }
 }
@@ -0,0 +1,246 @@
+use crate::port_policies::{Probe, ProbeCheck};
+use futures::{future, TryFutureExt};
+use linkerd_app_core::{
+    io::{self, AsyncReadExt, AsyncWriteExt},
+    proxy::http,
+    svc,
+    transport::{ConnectTcp, Keepalive, Remote, ServerAddr},
+    Error,
+};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tower::ServiceExt;
+use tracing::debug;
+
+/// Probe requests that are answered by the proxy for a target, rather than
+/// being forwarded to the application.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Probes(pub Arc<[Probe]>);
+
+#[derive(Clone, Debug)]
+pub struct NewServeProbes<N> {
+    inner: N,
+    timeout: Duration,
+}
+
+#[derive(Clone, Debug)]
+pub struct ServeProbes<S> {
+    inner: S,
+    probes: Arc<[Probe]>,
+    timeout: Duration,
+}
+
+type ProbeFuture =
+    Pin<Box<dyn Future<Output = Result<http::Response<http::BoxBody>, Error>> + Send>>;
+
+// === impl NewServeProbes ===
+
+impl<N> NewServeProbes<N> {
+    /// Local checks that don't complete within `timeout` indicate that the
+    /// application is not ready.
+    pub fn layer(timeout: Duration) -> impl svc::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self { inner, timeout })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewServeProbes<N>
+where
+    T: svc::Param<Probes>,
+    N: svc::NewService<T>,
+{
+    type Service = ServeProbes<N::Service>;
+
+    fn new_service(&mut self, t: T) -> Self::Service {
+        let Probes(probes) = t.param();
+        ServeProbes {
+            probes,
+            timeout: self.timeout,
+            inner: self.inner.new_service(t),
+        }
+    }
+}
+
+// === impl ServeProbes ===
+
+impl<S, B> svc::Service<http::Request<B>> for ServeProbes<S>
+where
+    S: svc::Service<http::Request<B>, Response = http::Response<http::BoxBody>>,
+    S::Error: Into<Error>,
+{
+    type Response = http::Response<http::BoxBody>;
+    type Error = Error;
+    type Future = future::Either<future::ErrInto<S::Future, Error>, ProbeFuture>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        if req.method() == http::Method::GET || req.method() == http::Method::HEAD {
+            if let Some(probe) = self.probes.iter().find(|p| p.path == req.uri().path()) {
+                let check = probe.check.clone();
+                let timeout = self.timeout;
+                return future::Either::Right(Box::pin(async move {
+                    let status = if is_ready(&check, timeout).await {
+                        http::StatusCode::OK
+                    } else {
+                        http::StatusCode::SERVICE_UNAVAILABLE
+                    };
+                    let rsp = http::Response::builder()
+                        .status(status)
+                        .body(http::BoxBody::default())
+                        .expect("probe response must be valid");
+                    Ok(rsp)
+                }));
+            }
+        }
+
+        future::Either::Left(self.inner.call(req).err_into::<Error>())
+    }
+}
+
+async fn is_ready(check: &ProbeCheck, timeout: Duration) -> bool {
+    match tokio::time::timeout(timeout, run_check(check)).await {
+        Ok(Ok(())) => true,
+        Ok(Err(error)) => {
+            debug!(%check, %error, "Probe check failed");
+            false
+        }
+        Err(_) => {
+            debug!(%check, ?timeout, "Probe check timed out");
+            false
+        }
+    }
+}
+
+async fn run_check(check: &ProbeCheck) -> io::Result<()> {
+    match check {
+        ProbeCheck::Tcp(addr) => {
+            connect(*addr).await?;
+            Ok(())
+        }
+
+        ProbeCheck::Http { addr, path } => {
+            let mut io = connect(*addr).await?;
+            let req = format!(
+                "GET {} HTTP/1.1\r\nhost: {}\r\nconnection: close\r\n\r\n",
+                path, addr
+            );
+            io.write_all(req.as_bytes()).await?;
+
+            // Only the status code is needed, e.g. `HTTP/1.1 200`.
+            let mut status_line = [0u8; 12];
+            io.read_exact(&mut status_line).await?;
+            let status = std::str::from_utf8(&status_line[9..])
+                .ok()
+                .and_then(|s| s.parse::<http::StatusCode>().ok())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid response"))?;
+            if status.is_success() || status.is_redirection() {
+                return Ok(());
+            }
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("unexpected status {}", status),
+            ))
+        }
+    }
+}
+
+async fn connect(
+    addr: std::net::SocketAddr,
+) -> io::Result<impl io::AsyncRead + io::AsyncWrite + Unpin> {
+    ConnectTcp::new(Keepalive(None))
+        .oneshot(Remote(ServerAddr(addr)))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::svc::{Layer, NewService};
+    use tokio::net::TcpListener;
+
+    #[derive(Clone)]
+    struct Target(Probes);
+
+    impl svc::Param<Probes> for Target {
+        fn param(&self) -> Probes {
+            self.0.clone()
+        }
+    }
+
+    async fn probe(check: ProbeCheck, path: &str) -> http::Response<http::BoxBody> {
+        let probes = Probes(
+            vec![Probe {
+                path: "/ready".to_string(),
+                check,
+            }]
+            .into(),
+        );
+        let mut new_svc = NewServeProbes::layer(Duration::from_secs(1)).layer(|_: Target| {
+            svc::mk(|_: http::Request<()>| {
+                future::ok::<_, std::convert::Infallible>(
+                    http::Response::builder()
+                        .status(http::StatusCode::IM_A_TEAPOT)
+                        .body(http::BoxBody::default())
+                        .unwrap(),
+                )
+            })
+        });
+        let req = http::Request::builder().uri(path).body(()).unwrap();
+        new_svc
+            .new_service(Target(probes))
+            .oneshot(req)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn tcp_check() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let rsp = probe(ProbeCheck::Tcp(addr), "/ready").await;
+        assert_eq!(rsp.status(), http::StatusCode::OK);
+
+        drop(listener);
+        let rsp = probe(ProbeCheck::Tcp(addr), "/ready").await;
+        assert_eq!(rsp.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn http_check() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for status in &["200 OK", "500 Internal Server Error"] {
+                let (mut io, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 1024];
+                let _ = io.read(&mut buf).await.unwrap();
+                let rsp = format!("HTTP/1.1 {}\r\ncontent-length: 0\r\n\r\n", status);
+                io.write_all(rsp.as_bytes()).await.unwrap();
+            }
+        });
+
+        let check = ProbeCheck::Http {
+            addr,
+            path: "/healthz".to_string(),
+        };
+        let rsp = probe(check.clone(), "/ready").await;
+        assert_eq!(rsp.status(), http::StatusCode::OK);
+        let rsp = probe(check, "/ready").await;
+        assert_eq!(rsp.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn other_paths_forwarded() {
+        let check = ProbeCheck::Tcp(([127, 0, 0, 1], 1).into());
+        let rsp = probe(check, "/other").await;
+        assert_eq!(rsp.status(), http::StatusCode::IM_A_TEAPOT);
+    }
+}
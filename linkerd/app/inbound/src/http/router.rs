@@ -1,9 +1,17 @@
+use super::{
+    exempt_routes::{ExemptRoutes, NewRestrictExemptRoutes, UnauthorizedResponse},
+    probe::{NewServeProbes, Probes},
+};
 use crate::{stack_labels, Inbound};
 use linkerd_app_core::{
-    classify, dst, http_tracing, io, metrics,
+    classify, dst, http_metrics, http_tracing, io, metrics,
     profiles::{self, DiscoveryRejected},
     proxy::{http, tap},
-    svc::{self, Param},
+    svc::{
+        self,
+        extension::{Direction, Point},
+        Param,
+    },
     tls,
     transport::{self, ClientAddr, Remote, ServerAddr},
     Error, Infallible, NameAddr,
@@ -65,7 +73,10 @@ impl<C> Inbound<C> {
         T: Param<http::Version>
             + Param<Remote<ServerAddr>>
             + Param<Remote<ClientAddr>>
-            + Param<tls::ConditionalServerTls>,
+            + Param<tls::ConditionalServerTls>
+            + Param<ExemptRoutes>
+            + Param<UnauthorizedResponse>
+            + Param<Probes>,
         T: Clone + Send + 'static,
         P: profiles::GetProfile<profiles::LookupAddr> + Clone + Send + Sync + 'static,
         P::Future: Send,
@@ -86,7 +97,13 @@ impl<C> Inbound<C> {
                     config.proxy.connect.h1_settings,
                     config.proxy.connect.h2_settings,
                 ))
-                .push_on_response(svc::MapErrLayer::new(Into::into))
+                .push_on_response(
+                    svc::layers()
+                        .push(svc::MapErrLayer::new(Into::into))
+                        // Records whether requests reuse the application's
+                        // connection in their spans, if configured.
+                        .push(http_tracing::SetConnectionReused::layer()),
+                )
                 .into_new_service()
                 .push_new_reconnect(config.proxy.connect.backoff)
                 .check_new_service::<Http, http::Request<_>>()
@@ -101,9 +118,14 @@ impl<C> Inbound<C> {
                 )
                 .push_on_response(http_tracing::client(
                     rt.span_sink.clone(),
+                    config.proxy.trace_attributes.clone(),
                     super::trace_labels(),
                 ))
-                .push_on_response(http::BoxResponse::layer())
+                .push_on_response(
+                    svc::layers()
+                        .push(http::BoxResponse::layer())
+                        .push(rt.extensions.layer(Direction::In, Point::Endpoint)),
+                )
                 .check_new_service::<Logical, http::Request<_>>();
 
             // Attempts to discover a service profile for each logical target (as
@@ -128,6 +150,14 @@ impl<C> Inbound<C> {
                         // Sets the per-route response classifier as a request
                         // extension.
                         .push(classify::NewClassify::layer())
+                        // Applies the route's request and response header
+                        // modifications.
+                        .push(profiles::http::NewModifyHeaders::layer())
+                        // Records the route in request spans, if configured.
+                        .push(http_tracing::NewSetAttribute::layer(
+                            http_tracing::Attribute::Route,
+                            |r: &dst::Route| r.route.labels().get("route").cloned(),
+                        ))
                         .check_new_clone::<dst::Route>()
                         .push_map_target(|(route, logical): (profiles::http::Route, Profile)| {
                             dst::Route {
@@ -167,7 +197,7 @@ impl<C> Inbound<C> {
                     let addr = t.logical.ok_or_else(|| {
                         DiscoveryRejected::new("inbound profile discovery requires DNS names")
                     })?;
-                    if !allow_profile.matches(addr.name()) {
+                    if !allow_profile.matches_addr(&addr) {
                         tracing::debug!(
                             %addr,
                             suffixes = %allow_profile,
@@ -193,20 +223,39 @@ impl<C> Inbound<C> {
                 .check_new_service::<Logical, http::Request<http::BoxBody>>()
                 .push_on_response(
                     svc::layers()
+                        .push(rt.metrics.http_queue_time.layer(
+                            stack_labels("http", "logical"),
+                            config.proxy.queue_time_header,
+                        ))
                         .push(rt.metrics.stack.layer(stack_labels("http", "logical")))
-                        .push(svc::FailFast::layer(
+                        .push(svc::FailFast::layer_with_retry_after(
                             "HTTP Logical",
                             config.proxy.dispatch_timeout,
+                            config
+                                .proxy
+                                .fail_fast_retry_after
+                                .then(|| config.proxy.dispatch_timeout),
                         ))
-                        .push_spawn_buffer(config.proxy.buffer_capacity),
+                        .push_spawn_buffer(config.proxy.buffer_capacity)
+                        .push(http_metrics::queue_time::Enqueue::layer()),
+                )
+                .push_cache(
+                    config.proxy.cache_max_idle_age,
+                    rt.metrics.cache.metrics(stack_labels("http", "logical")),
                 )
-                .push_cache(config.proxy.cache_max_idle_age)
                 .push_on_response(
                     svc::layers()
                         .push(http::Retain::layer())
-                        .push(http::BoxResponse::layer()),
+                        .push(http::BoxResponse::layer())
+                        .push(rt.extensions.layer(Direction::In, Point::Logical)),
                 )
                 .check_new_service::<Logical, http::Request<http::BoxBody>>()
+                // Records the logical destination in request spans, if
+                // configured.
+                .push(http_tracing::NewSetAttribute::layer(
+                    http_tracing::Attribute::LogicalDst,
+                    |t: &Logical| t.logical.clone(),
+                ))
                 .instrument(|t: &Logical| match (t.http, t.logical.as_ref()) {
                     (http::Version::H2, None) => debug_span!("http2"),
                     (http::Version::H2, Some(name)) => debug_span!("http2", %name),
@@ -224,6 +273,13 @@ impl<C> Inbound<C> {
                 // Used by tap.
                 .push_http_insert_target::<tls::ConditionalServerTls>()
                 .push_http_insert_target::<Remote<ClientAddr>>()
+                // Answers the server's probe requests, if configured, by
+                // checking the application's readiness directly.
+                .push(NewServeProbes::layer(config.proxy.connect.timeout))
+                // Rejects requests to routes that are not exempt from
+                // authorization on connections that were not authorized. These
+                // requests are still recorded by the server's metrics.
+                .push(NewRestrictExemptRoutes::layer())
                 .push(svc::BoxNewService::layer())
         })
     }
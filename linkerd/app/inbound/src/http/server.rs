@@ -1,24 +1,50 @@
-use super::set_identity_header::NewSetIdentityHeader;
+use super::{
+    authorize_claims::NewAuthorizeClaims,
+    ext_authz::NewExtAuthz,
+    forwarded::{ForwardedFor, NewSetForwardedHeaders},
+    set_identity_header::{ForwardClientId, NewSetIdentityHeader},
+};
 use crate::Inbound;
 pub use linkerd_app_core::proxy::http::{
     normalize_uri, strip_header, uri, BoxBody, BoxResponse, DetectHttp, Request, Response, Retain,
     Version,
 };
 use linkerd_app_core::{
+    compress,
     config::{ProxyConfig, ServerConfig},
-    errors, http_tracing, identity, io,
-    proxy::http,
-    svc::{self, Param},
+    errors, http_tracing, identity, io, jwt, load_shed,
+    proxy::http::{self, h1},
+    request_id, request_limits,
+    svc::{
+        self,
+        extension::{Direction, Point},
+        Param,
+    },
+    transport::{ClientAddr, Remote},
     Error,
 };
+use std::{collections::HashSet, sync::Arc};
 use tracing::debug_span;
 
+/// Extracts a target's HTTP/1 server settings, preserving the casing of header
+/// names on configured ports.
+#[derive(Clone, Debug)]
+struct H1Params {
+    settings: h1::ServerSettings,
+    preserve_header_case: Arc<HashSet<u16>>,
+}
+
 impl<H> Inbound<H> {
     pub fn push_http_server<T, I, HSvc>(self) -> Inbound<svc::BoxNewTcp<T, I>>
     where
         T: Param<Version>
             + Param<http::normalize_uri::DefaultAuthority>
-            + Param<Option<identity::Name>>,
+            + Param<Option<identity::Name>>
+            + Param<ForwardClientId>
+            + Param<ForwardedFor>
+            + Param<Remote<ClientAddr>>
+            + Param<u16>
+            + Param<load_shed::Priority>,
         T: Clone + Send + 'static,
         I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + Send + Unpin + 'static,
         H: svc::NewService<T, Service = HSvc> + Clone + Send + Sync + Unpin + 'static,
@@ -32,22 +58,85 @@ impl<H> Inbound<H> {
     {
         self.map_stack(|config, rt, http| {
             let ProxyConfig {
-                server: ServerConfig { h2_settings, .. },
+                server:
+                    ServerConfig {
+                        h1_settings,
+                        h2_settings,
+                        ..
+                    },
                 dispatch_timeout,
                 max_in_flight_requests,
+                fail_fast_retry_after,
+                error_responses,
+                request_limits,
                 ..
             } = config.proxy;
+            let request_id = config.proxy.request_id.clone();
+            let client_id_header = config.client_id_header.clone();
+            let jwt = config.jwt.clone();
+            let required_claims = config
+                .jwt
+                .as_ref()
+                .map(jwt::Validator::required_claims)
+                .unwrap_or_else(|| Vec::new().into());
+            let ext_authz = config.ext_authz.clone();
+            #[cfg(feature = "wasm")]
+            let wasm_filters =
+                linkerd_app_core::http_wasm::NewFilterHttp::layer(config.http_wasm.clone());
+            #[cfg(not(feature = "wasm"))]
+            let wasm_filters = svc::layers();
+            let forwarded = config.forwarded.clone();
+            let grpc_web = config.grpc_web;
+            let compression = config.compression.clone();
+            let load_shed = config.load_shed.clone();
+            let http1_malformed = config.http1_malformed.clone();
+            let http1_strict_framing = config.http1_strict_framing;
+            let h1_params = H1Params {
+                settings: h1_settings,
+                preserve_header_case: config.http1_preserve_header_case.clone(),
+            };
 
             http.check_new_service::<T, http::Request<_>>()
                 // Convert origin form HTTP/1 URIs to absolute form for Hyper's
                 // `Client`. This must be below the `orig_proto::Downgrade` layer, since
                 // the request may have been downgraded from a HTTP/2 orig-proto request.
                 .push(http::NewNormalizeUri::layer())
-                .push(NewSetIdentityHeader::layer())
+                // Applies WASM filters, if configured. This is below the
+                // authorization service so that it observes requests as they
+                // were sent by the client.
+                .push(wasm_filters)
+                // Checks requests with an external authorization service, if
+                // configured. This is below the identity header so that the
+                // service observes the verified client identity.
+                .push(NewExtAuthz::layer(ext_authz))
+                .push(NewSetIdentityHeader::layer(client_id_header))
+                // Records the client's identity in request spans, if
+                // configured.
+                .push(http_tracing::NewSetAttribute::layer(
+                    http_tracing::Attribute::PeerIdentity,
+                    |t: &T| Param::<Option<identity::Name>>::param(t),
+                ))
+                // Records the client's address in forwarding headers, if
+                // configured.
+                .push(NewSetForwardedHeaders::layer(forwarded))
+                // Restricts routes to requests whose validated tokens have
+                // the route's required claims, if configured.
+                .push(NewAuthorizeClaims::layer(required_claims))
+                // Validates bearer tokens, if configured, so that requests
+                // with invalid credentials are rejected by the errors layer.
+                .push(jwt::NewValidateJwt::layer(jwt))
                 .push_on_response(
                     svc::layers()
-                        // Downgrades the protocol if upgraded by an outbound proxy.
-                        .push(http::orig_proto::Downgrade::layer())
+                        // Translates gRPC-Web requests to gRPC, if enabled. This must be
+                        // below the `orig_proto::Downgrade` layer so that the request is sent
+                        // to the application over HTTP/2.
+                        .push(http::grpc_web::GrpcWeb::layer(grpc_web))
+                        // Downgrades the protocol if upgraded by an outbound proxy,
+                        // including WebSocket upgrades sent as extended CONNECT requests.
+                        .push(http::orig_proto::Downgrade::layer(rt.drain.clone()))
+                        // Compresses responses with an encoding accepted by the client, if
+                        // configured.
+                        .push(compress::Compress::layer(compression))
                         // Limit the number of in-flight requests. When the proxy is
                         // at capacity, go into failfast after a dispatch timeout.
                         // Note that the inner service _always_ returns ready (due
@@ -55,24 +144,81 @@ impl<H> Inbound<H> {
                         // driven outside of the request path, so there's no need
                         // for SpawnReady
                         .push(svc::ConcurrencyLimitLayer::new(max_in_flight_requests))
-                        .push(svc::FailFast::layer("HTTP Server", dispatch_timeout))
+                        .push(svc::FailFast::layer_with_retry_after(
+                            "HTTP Server",
+                            dispatch_timeout,
+                            fail_fast_retry_after.then(|| dispatch_timeout),
+                        )),
+                )
+                // Rejects requests, if configured, when response latencies
+                // indicate that the proxy is overloaded. This sits above the
+                // concurrency limit so that excess requests fail fast, before
+                // the proxy's in-flight limit is exhausted.
+                .push(load_shed::NewLoadShed::layer(
+                    load_shed,
+                    rt.metrics.http_load_shed.clone(),
+                ))
+                .push_on_response(
+                    svc::layers()
+                        // Rejects requests with heads that exceed the
+                        // configured limits.
+                        .push(request_limits::EnforceLimits::layer(request_limits))
                         .push(rt.metrics.http_errors.clone())
                         // Synthesizes responses for proxy errors.
-                        .push(errors::layer())
+                        .push(errors::layer(error_responses))
+                        // Records each request's ID, generating one if
+                        // configured. This is above the errors layer so that
+                        // error responses describe the same ID.
+                        .push(request_id::SetRequestId::layer(request_id))
+                        // Records whether requests reuse the client's
+                        // connection in their spans, if configured.
+                        .push(http_tracing::SetConnectionReused::layer())
                         .push(http_tracing::server(
                             rt.span_sink.clone(),
+                            config.proxy.trace_attributes.clone(),
                             super::trace_labels(),
                         ))
                         // Record when an HTTP/1 URI was in absolute form
                         .push(http::normalize_uri::MarkAbsoluteForm::layer())
                         .push(http::BoxRequest::layer())
-                        .push(http::BoxResponse::layer()),
+                        .push(http::BoxResponse::layer())
+                        .push(rt.extensions.layer(Direction::In, Point::ServerHttp)),
                 )
                 .check_new_service::<T, http::Request<_>>()
                 .instrument(|t: &T| debug_span!("http", v = %Param::<Version>::param(t)))
-                .push(http::NewServeHttp::layer(h2_settings, rt.drain.clone()))
+                .push(http::NewServeHttp::layer(
+                    h1_params,
+                    h2_settings,
+                    rt.drain.clone(),
+                ))
+                // Closes HTTP/1 connections that send requests with ambiguous
+                // framing, unless disabled. This is below the malformed request
+                // handling so that normalized request heads are checked.
+                .push(http::framing::NewStrictFraming::layer(
+                    http1_strict_framing,
+                    rt.metrics.http1_framing.clone(),
+                ))
+                // Normalizes or rejects malformed HTTP/1 request heads before
+                // they're parsed by the server, if configured for the port.
+                .push(http::malformed::NewNormalizeMalformed::layer(
+                    http1_malformed,
+                    rt.metrics.http1_malformed.clone(),
+                ))
                 .push_on_response(svc::BoxService::layer())
                 .push(svc::BoxNewService::layer())
         })
     }
 }
+
+// === impl H1Params ===
+
+impl<T: Param<u16>> svc::ExtractParam<h1::ServerSettings, T> for H1Params {
+    fn extract_param(&self, t: &T) -> h1::ServerSettings {
+        let port: u16 = t.param();
+        h1::ServerSettings {
+            preserve_header_case: self.settings.preserve_header_case
+                || self.preserve_header_case.contains(&port),
+            ..self.settings
+        }
+    }
+}
@@ -2,42 +2,62 @@ use linkerd_app_core::{identity, proxy::http, svc};
 use std::task::{Context, Poll};
 use tracing::{debug, trace};
 
-const HEADER_NAME: &str = "l5d-client-id";
+/// The default identity header, which is always stripped from requests, even
+/// when the verified identity is forwarded in another header.
+const L5D_CLIENT_ID: &str = "l5d-client-id";
+
+/// Indicates whether a target's verified client identity should be forwarded
+/// to the application.
+///
+/// Client-supplied values are always stripped, regardless of this setting.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ForwardClientId(pub bool);
 
 #[derive(Clone, Debug)]
 pub struct NewSetIdentityHeader<N> {
     inner: N,
+    header: http::HeaderName,
 }
 
 #[derive(Clone, Debug)]
 pub struct SetIdentityHeader<M> {
     inner: M,
+    header: http::HeaderName,
     value: Option<http::HeaderValue>,
 }
 
 // === impl NewSetIdentityHeader ===
 
 impl<N> NewSetIdentityHeader<N> {
-    pub fn layer() -> impl svc::Layer<N, Service = Self> + Clone {
-        svc::layer::mk(move |inner| Self { inner })
+    pub fn layer(header: http::HeaderName) -> impl svc::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            header: header.clone(),
+        })
     }
 }
 
 impl<T, N> svc::NewService<T> for NewSetIdentityHeader<N>
 where
-    T: svc::Param<Option<identity::Name>>,
+    T: svc::Param<Option<identity::Name>> + svc::Param<ForwardClientId>,
     N: svc::NewService<T>,
 {
     type Service = SetIdentityHeader<N::Service>;
 
     #[inline]
     fn new_service(&mut self, t: T) -> Self::Service {
-        let value = t.param().map(|name| {
-            http::HeaderValue::from_str(name.as_ref())
-                .expect("identity must be a valid header value")
-        });
+        let ForwardClientId(forward) = t.param();
+        let value = if forward {
+            svc::Param::<Option<identity::Name>>::param(&t).map(|name| {
+                http::HeaderValue::from_str(name.as_ref())
+                    .expect("identity must be a valid header value")
+            })
+        } else {
+            None
+        };
         SetIdentityHeader {
             value,
+            header: self.header.clone(),
             inner: self.inner.new_service(t),
         }
     }
@@ -59,16 +79,152 @@ where
     }
 
     fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        if self.header != L5D_CLIENT_ID {
+            if let Some(value) = req.headers_mut().remove(L5D_CLIENT_ID) {
+                debug!(header = %L5D_CLIENT_ID, ?value, "Stripped identity header");
+            }
+        }
+
         let prior = if let Some(id) = self.value.clone() {
-            trace!(header = %HEADER_NAME, ?id, "Setting identity header");
-            req.headers_mut().insert(HEADER_NAME, id)
+            trace!(header = %self.header, ?id, "Setting identity header");
+            req.headers_mut().insert(self.header.clone(), id)
         } else {
-            req.headers_mut().remove(HEADER_NAME)
+            req.headers_mut().remove(&self.header)
         };
         if let Some(value) = prior {
-            debug!(header = %HEADER_NAME, ?value, "Stripped identity header");
+            debug!(header = %self.header, ?value, "Stripped identity header");
         }
 
         self.inner.call(req)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+    use linkerd_app_core::svc::{Layer, NewService};
+    use std::str::FromStr;
+    use tower::ServiceExt;
+
+    #[derive(Clone)]
+    struct Target {
+        id: Option<identity::Name>,
+        forward: bool,
+    }
+
+    impl svc::Param<Option<identity::Name>> for Target {
+        fn param(&self) -> Option<identity::Name> {
+            self.id.clone()
+        }
+    }
+
+    impl svc::Param<ForwardClientId> for Target {
+        fn param(&self) -> ForwardClientId {
+            ForwardClientId(self.forward)
+        }
+    }
+
+    fn tls() -> Option<identity::Name> {
+        Some(
+            identity::Name::from_str("foo.ns.serviceaccount.identity.linkerd.cluster.local")
+                .unwrap(),
+        )
+    }
+
+    async fn headers(
+        header: &'static str,
+        target: Target,
+        req: http::Request<()>,
+    ) -> http::HeaderMap {
+        let mut new_svc = NewSetIdentityHeader::layer(http::HeaderName::from_static(header)).layer(
+            |_: Target| {
+                svc::mk(|req: http::Request<()>| {
+                    future::ok::<_, std::convert::Infallible>(req.headers().clone())
+                })
+            },
+        );
+        new_svc.new_service(target).oneshot(req).await.unwrap()
+    }
+
+    fn spoofed(headers: &[&str]) -> http::Request<()> {
+        headers
+            .iter()
+            .fold(http::Request::builder(), |req, h| {
+                req.header(*h, "spoofed.example.com")
+                    .header(*h, "spoofed2.example.com")
+            })
+            .body(())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn inserts_verified_identity() {
+        let target = Target {
+            id: tls(),
+            forward: true,
+        };
+        let headers = headers(L5D_CLIENT_ID, target, spoofed(&[L5D_CLIENT_ID])).await;
+        let values = headers.get_all(L5D_CLIENT_ID).iter().collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec!["foo.ns.serviceaccount.identity.linkerd.cluster.local"]
+        );
+    }
+
+    #[tokio::test]
+    async fn strips_without_tls() {
+        let target = Target {
+            id: None,
+            forward: true,
+        };
+        let headers = headers(L5D_CLIENT_ID, target, spoofed(&[L5D_CLIENT_ID])).await;
+        assert!(headers.get(L5D_CLIENT_ID).is_none());
+    }
+
+    #[tokio::test]
+    async fn strips_when_not_forwarded() {
+        let target = Target {
+            id: tls(),
+            forward: false,
+        };
+        let headers = headers(L5D_CLIENT_ID, target, spoofed(&[L5D_CLIENT_ID])).await;
+        assert!(headers.get(L5D_CLIENT_ID).is_none());
+    }
+
+    #[tokio::test]
+    async fn custom_header_inserts_and_strips_default() {
+        let target = Target {
+            id: tls(),
+            forward: true,
+        };
+        let headers = headers(
+            "x-client-id",
+            target,
+            spoofed(&["x-client-id", L5D_CLIENT_ID]),
+        )
+        .await;
+        let values = headers.get_all("x-client-id").iter().collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec!["foo.ns.serviceaccount.identity.linkerd.cluster.local"]
+        );
+        assert!(headers.get(L5D_CLIENT_ID).is_none());
+    }
+
+    #[tokio::test]
+    async fn custom_header_strips_both_without_tls() {
+        let target = Target {
+            id: None,
+            forward: true,
+        };
+        let headers = headers(
+            "x-client-id",
+            target,
+            spoofed(&["x-client-id", L5D_CLIENT_ID]),
+        )
+        .await;
+        assert!(headers.get("x-client-id").is_none());
+        assert!(headers.get(L5D_CLIENT_ID).is_none());
+    }
+}
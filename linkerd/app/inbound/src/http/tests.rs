@@ -1,9 +1,10 @@
 use crate::{
+    port_policies::Priority,
     test_util::{
         support::{connect::Connect, http_util, profile, resolver},
         *,
     },
-    Config, Inbound,
+    Config, ExemptRoutes, ForwardClientId, ForwardedFor, Inbound, Probes, UnauthorizedResponse,
 };
 use hyper::{client::conn::Builder as ClientBuilder, Body, Request, Response};
 use linkerd_app_core::{
@@ -391,6 +392,18 @@ impl svc::Param<Remote<ServerAddr>> for Target {
     }
 }
 
+impl svc::Param<u16> for Target {
+    fn param(&self) -> u16 {
+        Self::addr().port()
+    }
+}
+
+impl svc::Param<Priority> for Target {
+    fn param(&self) -> Priority {
+        Priority::Normal
+    }
+}
+
 impl svc::Param<Remote<ClientAddr>> for Target {
     fn param(&self) -> Remote<ClientAddr> {
         Remote(ClientAddr(([192, 0, 2, 3], 50000).into()))
@@ -420,3 +433,33 @@ impl svc::Param<Option<identity::Name>> for Target {
         None
     }
 }
+
+impl svc::Param<ForwardClientId> for Target {
+    fn param(&self) -> ForwardClientId {
+        ForwardClientId(true)
+    }
+}
+
+impl svc::Param<ForwardedFor> for Target {
+    fn param(&self) -> ForwardedFor {
+        ForwardedFor(None)
+    }
+}
+
+impl svc::Param<ExemptRoutes> for Target {
+    fn param(&self) -> ExemptRoutes {
+        ExemptRoutes(None)
+    }
+}
+
+impl svc::Param<UnauthorizedResponse> for Target {
+    fn param(&self) -> UnauthorizedResponse {
+        UnauthorizedResponse(None)
+    }
+}
+
+impl svc::Param<Probes> for Target {
+    fn param(&self) -> Probes {
+        Probes(Vec::new().into())
+    }
+}
@@ -7,24 +7,39 @@
 #![forbid(unsafe_code)]
 
 mod accept;
+mod connection_limits;
 mod detect;
 pub mod direct;
+mod handshake_limits;
 mod http;
+mod opaque_ports;
 pub mod port_policies;
 mod server;
 #[cfg(any(test, fuzzing))]
 pub(crate) mod test_util;
 
-pub use self::port_policies::{DefaultPolicy, PortPolicies, ServerPolicy};
+pub use self::{
+    connection_limits::ConnectionLimits,
+    handshake_limits::{HandshakeLimitConfig, HandshakeLimits},
+    http::{
+        ExemptRoutes, ExtAuthz, ForwardClientId, ForwardedFor, ForwardedPolicy, Probes,
+        UnauthorizedResponse,
+    },
+    port_policies::{DefaultPolicy, ExemptRoute, PortPolicies, Probe, ProbeCheck, ServerPolicy},
+};
 use linkerd_app_core::{
-    config::{ConnectConfig, ProxyConfig},
-    drain, io, metrics,
-    proxy::tcp,
+    compress,
+    config::{ConnectConfig, ProxyConfig, ServerConfig},
+    drain, identity, io, jwt, load_shed, metrics,
+    proxy::{
+        http::{malformed, HeaderName},
+        tcp,
+    },
     svc,
     transport::{self, Remote, ServerAddr},
     Error, NameMatch, ProxyRuntime,
 };
-use std::{fmt::Debug, time::Duration};
+use std::{collections::HashSet, fmt::Debug, sync::Arc, time::Duration};
 use tracing::debug_span;
 
 #[cfg(fuzzing)]
@@ -36,6 +51,93 @@ pub struct Config {
     pub proxy: ProxyConfig,
     pub port_policies: PortPolicies,
     pub profile_idle_timeout: Duration,
+
+    /// The name of the header used to forward a verified client identity to
+    /// the application.
+    pub client_id_header: HeaderName,
+
+    /// Validates bearer tokens on inbound HTTP requests, if configured.
+    pub jwt: Option<jwt::Validator>,
+
+    /// Checks inbound HTTP requests with an external authorization service,
+    /// if configured.
+    pub ext_authz: Option<ExtAuthz>,
+
+    /// Experimental WASM filters applied to inbound HTTP requests and
+    /// responses, if configured.
+    #[cfg(feature = "wasm")]
+    pub http_wasm: Option<linkerd_app_core::http_wasm::Filters>,
+
+    /// Sets `Forwarded` and `X-Forwarded-For` headers on inbound HTTP
+    /// requests, if configured.
+    pub forwarded: Option<ForwardedPolicy>,
+
+    /// Whether gRPC-Web requests are translated to native gRPC for the
+    /// application.
+    pub grpc_web: bool,
+
+    /// Compresses HTTP responses with an encoding accepted by the client, if
+    /// configured.
+    pub compression: Option<compress::Config>,
+
+    /// Sheds inbound HTTP requests when response latencies indicate that the
+    /// proxy is overloaded, if configured.
+    pub load_shed: Option<load_shed::Config>,
+
+    /// Normalizes or rejects malformed inbound HTTP/1 requests, by port.
+    pub http1_malformed: malformed::Policies,
+
+    /// Ports on which the casing of HTTP/1 header names is preserved, in both
+    /// requests and responses.
+    pub http1_preserve_header_case: Arc<HashSet<u16>>,
+
+    /// Whether HTTP/1 connections that send requests with ambiguous framing
+    /// are closed.
+    pub http1_strict_framing: bool,
+
+    /// The remote clusters that gateway clients may target via SNI.
+    pub gateway_clusters: direct::GatewayClusters,
+
+    /// Federated trust domains whose identities are accepted on gateway
+    /// connections, in addition to those issued by the local trust anchors.
+    pub gateway_trust_domains: Arc<[identity::FederatedTrustDomain]>,
+
+    /// Whether clients may multiplex gateway connections as streams on a
+    /// tunnel connection.
+    pub tunnel: bool,
+
+    /// Limits the byte rate of forwarded TCP connections by destination port.
+    pub tcp_rate_limits: tcp::RateLimits,
+
+    /// Closes forwarded TCP connections on which no data has been copied in
+    /// either direction for this long, if set.
+    pub tcp_idle_timeout: Option<Duration>,
+
+    /// Limits the number of concurrent connections accepted by destination
+    /// port, both in total and from each client IP address.
+    pub connection_limits: ConnectionLimits,
+
+    /// Limits the rate of TLS handshakes by client network, on both the
+    /// inbound and gateway ports.
+    pub tls_handshake_limits: HandshakeLimits,
+
+    /// Whether the control plane is consulted to determine whether ports are
+    /// opaque, in addition to the static port policies.
+    pub discover_opaque_ports: bool,
+
+    /// Listeners that accept inbound connections in addition to the main
+    /// inbound listener (e.g. on a node-local interface).
+    pub additional_listeners: Vec<ListenerConfig>,
+}
+
+/// Configures an additional inbound listener.
+#[derive(Clone, Debug)]
+pub struct ListenerConfig {
+    pub server: ServerConfig,
+
+    /// Overrides the default policy for connections accepted by this listener.
+    /// Ports with explicitly configured policies use them on all listeners.
+    pub default_policy: Option<DefaultPolicy>,
 }
 
 #[derive(Clone)]
@@ -64,6 +166,28 @@ impl<S> Inbound<S> {
         self.stack.into_inner()
     }
 
+    /// Returns an `Inbound` for each additional listener, configured with the
+    /// listener's server and default policy.
+    pub fn additional_listeners(&self) -> Vec<Self>
+    where
+        S: Clone,
+    {
+        self.config
+            .additional_listeners
+            .iter()
+            .map(|listener| {
+                let mut inbound = self.clone();
+                inbound.config.proxy.server = listener.server.clone();
+                inbound.config.additional_listeners = Vec::new();
+                if let Some(default) = listener.default_policy.clone() {
+                    inbound.config.port_policies =
+                        inbound.config.port_policies.with_default(default);
+                }
+                inbound
+            })
+            .collect()
+    }
+
     /// Creates a new `Inbound` by replacing the inner stack, as modified by `f`.
     fn map_stack<T>(
         self,
@@ -120,6 +244,7 @@ impl Inbound<()> {
             #[error("inbound connection must not target port {0}")]
             struct Loop(u16);
 
+            let port_policies = config.port_policies.clone();
             svc::stack(transport::ConnectTcp::new(*keepalive))
                 // Limits the time we wait for a connection to be established.
                 .push_connect_timeout(*timeout)
@@ -129,7 +254,15 @@ impl Inbound<()> {
                     if port == proxy_port {
                         return Err(Loop(port));
                     }
-                    Ok(Remote(ServerAddr(([127, 0, 0, 1], port).into())))
+                    // Connections are forwarded to the loopback address unless the port's
+                    // policy overrides the target (e.g. for host-network workloads).
+                    let addr = port_policies
+                        .forward_addr(port)
+                        .unwrap_or_else(|| ([127, 0, 0, 1], port).into());
+                    if addr.port() == proxy_port && addr.ip().is_loopback() {
+                        return Err(Loop(addr.port()));
+                    }
+                    Ok(Remote(ServerAddr(addr)))
                 })
         })
     }
@@ -152,7 +285,7 @@ impl<S> Inbound<S> {
         >,
     >
     where
-        T: svc::Param<transport::labels::Key> + Clone + Send + 'static,
+        T: svc::Param<transport::labels::Key> + svc::Param<u16> + Clone + Send + 'static,
         I: io::AsyncRead + io::AsyncWrite,
         I: Debug + Send + Unpin + 'static,
         S: svc::Service<T> + Clone + Send + Sync + Unpin + 'static,
@@ -160,14 +293,14 @@ impl<S> Inbound<S> {
         S::Error: Into<Error>,
         S::Future: Send,
     {
-        self.map_stack(|_, rt, conn| {
+        self.map_stack(|config, rt, conn| {
             conn.push(rt.metrics.transport.layer_connect())
                 .push_make_thunk()
-                .push_on_response(
-                    svc::layers()
-                        .push(tcp::Forward::layer())
-                        .push(drain::Retain::layer(rt.drain.clone())),
-                )
+                .push(tcp::NewForward::layer(
+                    config.tcp_rate_limits.clone(),
+                    rt.metrics.tcp_idle_timeouts.idle(config.tcp_idle_timeout),
+                ))
+                .push_on_response(drain::Retain::layer(rt.drain.clone()))
                 .instrument(|_: &_| debug_span!("tcp"))
                 .push(svc::BoxNewService::layer())
                 .check_new::<T>()
@@ -0,0 +1,120 @@
+use crate::{accept::Accept, Inbound};
+use linkerd_app_core::{
+    io, profiles,
+    svc::{self, Param},
+    transport::OrigDstAddr,
+    Error,
+};
+use parking_lot::Mutex;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt::Debug,
+    sync::Arc,
+};
+use tracing::{debug, Instrument};
+
+/// Watches the control plane for whether inbound ports are opaque.
+///
+/// Each port is discovered in the background the first time a connection
+/// targets it, so the port's configured policy applies until discovery
+/// completes. Thereafter, the profile's opaque-protocol hint is read as each
+/// connection is accepted, so updates take effect without restarting the
+/// proxy.
+#[derive(Clone)]
+struct OpaquePorts<P> {
+    profiles: P,
+    // A port maps to `None` while its profile is being discovered or if the
+    // control plane has no profile for it.
+    ports: Arc<Mutex<HashMap<u16, Option<profiles::Receiver>>>>,
+}
+
+// === impl Inbound ===
+
+impl<N> Inbound<N> {
+    /// Marks ports as opaque when the control plane indicates that they are,
+    /// if discovery is enabled.
+    pub(crate) fn push_discover_opaque_ports<I, NSvc, P>(
+        self,
+        profiles: P,
+    ) -> Inbound<svc::BoxNewTcp<Accept, I>>
+    where
+        I: io::AsyncRead + io::AsyncWrite + io::Peek + io::PeerAddr,
+        I: Debug + Send + Sync + Unpin + 'static,
+        N: svc::NewService<Accept, Service = NSvc> + Clone + Send + Sync + Unpin + 'static,
+        NSvc: svc::Service<I, Response = ()>,
+        NSvc: Send + Unpin + 'static,
+        NSvc::Error: Into<Error>,
+        NSvc::Future: Send,
+        P: profiles::GetProfile<profiles::LookupAddr> + Clone + Send + Sync + 'static,
+        P::Future: Send,
+        P::Error: Send,
+    {
+        self.map_stack(|cfg, _, accept| {
+            let opaque_ports = if cfg.discover_opaque_ports {
+                Some(OpaquePorts {
+                    profiles,
+                    ports: Default::default(),
+                })
+            } else {
+                None
+            };
+            accept
+                .push_map_target(move |a: Accept| match opaque_ports.as_ref() {
+                    Some(opaque_ports) if opaque_ports.is_opaque(a.param()) => {
+                        debug!("Port is opaque");
+                        a.map_policy(|p| p.into_opaque())
+                    }
+                    _ => a,
+                })
+                .push_on_response(svc::BoxService::layer())
+                .push(svc::BoxNewService::layer())
+        })
+    }
+}
+
+// === impl OpaquePorts ===
+
+impl<P> OpaquePorts<P>
+where
+    P: profiles::GetProfile<profiles::LookupAddr> + Clone + Send + 'static,
+    P::Future: Send,
+    P::Error: Send,
+{
+    fn is_opaque(&self, OrigDstAddr(addr): OrigDstAddr) -> bool {
+        let port = addr.port();
+        match self.ports.lock().entry(port) {
+            Entry::Occupied(entry) => entry
+                .get()
+                .as_ref()
+                .map(profiles::Receiver::is_opaque_protocol)
+                .unwrap_or(false),
+            Entry::Vacant(entry) => {
+                entry.insert(None);
+                let ports = self.ports.clone();
+                let mut profiles = self.profiles.clone();
+                tokio::spawn(
+                    async move {
+                        match profiles
+                            .get_profile(profiles::LookupAddr(addr.into()))
+                            .await
+                        {
+                            Ok(profile) => {
+                                debug!(found = profile.is_some(), "Discovered port");
+                                ports.lock().insert(port, profile);
+                            }
+                            Err(error) => {
+                                // Allow discovery to be retried by a subsequent
+                                // connection.
+                                let error: Error = error.into();
+                                debug!(%error, "Failed to discover port");
+                                ports.lock().remove(&port);
+                            }
+                        }
+                    }
+                    .instrument(tracing::debug_span!("opaque_ports", port)),
+                );
+                false
+            }
+        }
+    }
+}
@@ -1,16 +1,21 @@
 use linkerd_app_core::{
     tls,
     transport::{ClientAddr, OrigDstAddr, Remote},
-    Ipv4Net, Ipv6Net,
+    IpNet, Ipv4Net, Ipv6Net,
+};
+pub use linkerd_server_policy::{
+    Authentication, Authorization, DenyResponse, ExemptRoute, Network, Priority, Probe, ProbeCheck,
+    Protocol, ServerPolicy, Suffix,
 };
-pub use linkerd_server_policy::{Authentication, Authorization, Protocol, ServerPolicy, Suffix};
 use std::{
     collections::{BTreeMap, HashMap},
     hash::{BuildHasherDefault, Hasher},
+    net::SocketAddr,
     sync::Arc,
     time::Duration,
 };
 use thiserror::Error;
+use tracing::warn;
 
 #[derive(Clone, Debug)]
 pub struct PortPolicies {
@@ -35,6 +40,24 @@ pub(crate) struct AllowPolicy {
 pub(crate) struct Permitted {
     pub protocol: Protocol,
     pub tls: tls::ConditionalServerTls,
+    pub forward_client_id: bool,
+    pub priority: Priority,
+
+    /// Set when the connection is not authorized but its server exempts HTTP
+    /// routes from authorization. The connection's requests are restricted to
+    /// these routes.
+    pub exempt_routes: Option<Arc<[ExemptRoute]>>,
+
+    /// Set when the connection is not authorized but its server overrides
+    /// the response to unauthorized requests.
+    pub deny_response: Option<Arc<DenyResponse>>,
+
+    /// Set when the connection is not authorized but its server's policy is
+    /// in audit mode, so the connection is permitted.
+    pub audited: bool,
+
+    /// Probe requests that are answered by the proxy.
+    pub probes: Arc<[Probe]>,
 
     // We want predictable ordering of labels, so we use a BTreeMap.
     pub labels: BTreeMap<String, String>,
@@ -75,10 +98,17 @@ pub fn all_authenticated_server_policy(timeout: Duration) -> ServerPolicy {
             labels: Some(("authz".to_string(), "_all-authenticated".to_string()))
                 .into_iter()
                 .collect(),
+            priority: Priority::Normal,
         }],
         labels: Some(("server".to_string(), "_default".to_string()))
             .into_iter()
             .collect(),
+        forward_client_id: true,
+        forward_addr: None,
+        exempt_routes: Vec::new(),
+        probes: Vec::new(),
+        deny_response: None,
+        audit: false,
     }
 }
 
@@ -91,10 +121,17 @@ pub fn all_unauthenticated_server_policy(timeout: Duration) -> ServerPolicy {
             labels: Some(("authz".to_string(), "_all-unauthenticated".to_string()))
                 .into_iter()
                 .collect(),
+            priority: Priority::Normal,
         }],
         labels: Some(("server".to_string(), "_default".to_string()))
             .into_iter()
             .collect(),
+        forward_client_id: true,
+        forward_addr: None,
+        exempt_routes: Vec::new(),
+        probes: Vec::new(),
+        deny_response: None,
+        audit: false,
     }
 }
 
@@ -107,10 +144,17 @@ pub fn all_mtls_unauthenticated_server_policy(timeout: Duration) -> ServerPolicy
             labels: Some(("authz".to_string(), "_all-unauthenticated-tls".to_string()))
                 .into_iter()
                 .collect(),
+            priority: Priority::Normal,
         }],
         labels: Some(("server".to_string(), "_default".to_string()))
             .into_iter()
             .collect(),
+        forward_client_id: true,
+        forward_addr: None,
+        exempt_routes: Vec::new(),
+        probes: Vec::new(),
+        deny_response: None,
+        audit: false,
     }
 }
 
@@ -132,6 +176,82 @@ impl PortPolicies {
     }
 }
 
+impl PortPolicies {
+    /// Replaces the policy used for ports that are not explicitly configured.
+    pub fn with_default(self, default: DefaultPolicy) -> Self {
+        Self { default, ..self }
+    }
+
+    /// Assigns priorities to the requests of clients in the given networks,
+    /// on all ports.
+    ///
+    /// Each authorization that permits clients in a network is preceded by a
+    /// copy that is limited to that network and has its priority. The first
+    /// matching network determines a client's priority.
+    pub fn with_client_priorities(self, priorities: &[(IpNet, Priority)]) -> Self {
+        if priorities.is_empty() {
+            return self;
+        }
+
+        let prioritize = |server: &ServerPolicy| ServerPolicy {
+            authorizations: server
+                .authorizations
+                .iter()
+                .flat_map(|authz| {
+                    priorities
+                        .iter()
+                        .filter_map(move |(net, priority)| {
+                            let networks = authz
+                                .networks
+                                .iter()
+                                .filter_map(|n| intersect(n, net))
+                                .collect::<Vec<_>>();
+                            if networks.is_empty() {
+                                return None;
+                            }
+                            Some(Authorization {
+                                networks,
+                                priority: *priority,
+                                ..authz.clone()
+                            })
+                        })
+                        .chain(Some(authz.clone()))
+                })
+                .collect(),
+            ..server.clone()
+        };
+
+        let default = match self.default {
+            DefaultPolicy::Allow(server) => DefaultPolicy::Allow(Arc::new(prioritize(&*server))),
+            DefaultPolicy::Deny => DefaultPolicy::Deny,
+        };
+        let by_port = self
+            .by_port
+            .iter()
+            .map(|(port, server)| (*port, Arc::new(prioritize(&**server))))
+            .collect::<Map>();
+        Self {
+            default,
+            by_port: Arc::new(by_port),
+        }
+    }
+}
+
+/// Returns the portion of `network` that is in `net`, if it can be described
+/// as a single network.
+fn intersect(network: &Network, net: &IpNet) -> Option<Network> {
+    if network.net.contains(net) {
+        Some(Network {
+            net: *net,
+            except: network.except.clone(),
+        })
+    } else if net.contains(&network.net) {
+        Some(network.clone())
+    } else {
+        None
+    }
+}
+
 impl From<DefaultPolicy> for PortPolicies {
     fn from(default: DefaultPolicy) -> Self {
         Self::new(default, None)
@@ -172,6 +292,18 @@ impl PortPolicies {
             server,
         })
     }
+
+    /// Returns the address to which connections on the given port are
+    /// forwarded, if the port's policy overrides it.
+    pub(crate) fn forward_addr(&self, port: u16) -> Option<SocketAddr> {
+        match self.by_port.get(&port) {
+            Some(server) => server.forward_addr,
+            None => match &self.default {
+                DefaultPolicy::Allow(server) => server.forward_addr,
+                DefaultPolicy::Deny => None,
+            },
+        }
+    }
 }
 
 // === impl DefaultPolicy ===
@@ -194,8 +326,33 @@ impl AllowPolicy {
         }
     }
 
-    pub(crate) fn is_opaque(&self) -> bool {
-        self.server.protocol == Protocol::Opaque
+    /// Indicates whether protocol detection should be skipped for this port.
+    pub(crate) fn skips_detection(&self) -> bool {
+        matches!(
+            self.server.protocol,
+            Protocol::Opaque | Protocol::ServerSpeaksFirst
+        )
+    }
+
+    /// Marks the port as opaque if its policy would otherwise detect the
+    /// protocol.
+    pub(crate) fn into_opaque(self) -> Self {
+        if let Protocol::Detect { .. } = self.server.protocol {
+            return Self {
+                server: Arc::new(ServerPolicy {
+                    protocol: Protocol::Opaque,
+                    ..(*self.server).clone()
+                }),
+                ..self
+            };
+        }
+        self
+    }
+
+    /// Indicates whether connections to this port should be forwarded without
+    /// TLS detection or termination.
+    pub(crate) fn is_passthrough_tls(&self) -> bool {
+        self.server.protocol == Protocol::PassthroughTls
     }
 
     /// Checks whether the destination port's `AllowPolicy` is authorized to accept connections
@@ -205,48 +362,88 @@ impl AllowPolicy {
         tls: tls::ConditionalServerTls,
     ) -> Result<Permitted, DeniedUnauthorized> {
         let client = self.client.ip();
+        // Authorizations permit clients that match both their networks and
+        // their authentication; the first to permit the client applies.
         for authz in self.server.authorizations.iter() {
-            if authz.networks.iter().any(|n| n.contains(&client)) {
-                match authz.authentication {
-                    Authentication::Unauthenticated => {
-                        return Ok(Permitted::new(&self.server, authz, tls));
-                    }
-
-                    Authentication::TlsUnauthenticated => {
-                        if let tls::ConditionalServerTls::Some(tls::ServerTls::Established {
-                            ..
-                        }) = tls
-                        {
-                            return Ok(Permitted::new(&self.server, authz, tls));
-                        }
-                    }
-
-                    Authentication::TlsAuthenticated {
-                        ref identities,
-                        ref suffixes,
-                    } => {
-                        if let tls::ConditionalServerTls::Some(tls::ServerTls::Established {
-                            client_id: Some(tls::server::ClientId(ref id)),
-                            ..
-                        }) = tls
-                        {
-                            if identities.contains(id.as_ref())
-                                || suffixes.iter().any(|s| s.contains(id.as_ref()))
-                            {
-                                return Ok(Permitted::new(&self.server, authz, tls));
-                            }
-                        }
-                    }
-                }
+            if !authz.contains_client(&client) {
+                continue;
+            }
+            let permitted = match (&authz.authentication, &tls) {
+                (Authentication::Unauthenticated, _) => true,
+                (
+                    Authentication::TlsUnauthenticated,
+                    tls::ConditionalServerTls::Some(tls::ServerTls::Established { .. }),
+                ) => true,
+                (
+                    Authentication::TlsAuthenticated { .. },
+                    tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+                        client_id: Some(tls::server::ClientId(id)),
+                        ..
+                    }),
+                ) => authz.authentication.permits_name(id.as_ref()),
+                (
+                    Authentication::TlsServerName { .. },
+                    tls::ConditionalServerTls::Some(tls::ServerTls::Passthru {
+                        sni: Some(tls::ServerId(sni)),
+                    }),
+                ) => authz.authentication.permits_name(sni.as_ref()),
+                _ => false,
+            };
+            if permitted {
+                return Ok(Permitted::new(&self.server, authz, tls));
             }
         }
 
+        if self.server.audit {
+            warn!(
+                client.addr = %self.client,
+                dst.addr = %self.dst,
+                ?tls,
+                "Connection would be denied by policy",
+            );
+            return Ok(Permitted::audited(&self.server, tls));
+        }
+
         Err(DeniedUnauthorized {
             client_addr: self.client,
             dst_addr: self.dst,
             tls,
         })
     }
+
+    /// Like [`AllowPolicy::check_authorized`], except that unauthorized connections are permitted
+    /// if the server exempts HTTP routes from authorization or overrides the response to
+    /// unauthorized requests. Such connections must be restricted to the exempt routes, so this may
+    /// only be used when the connection's requests are inspected.
+    pub(crate) fn check_authorized_or_exempt(
+        &self,
+        tls: tls::ConditionalServerTls,
+    ) -> Result<Permitted, DeniedUnauthorized> {
+        match self.check_authorized(tls) {
+            Err(DeniedUnauthorized { tls, .. })
+                if !self.server.exempt_routes.is_empty() || self.server.deny_response.is_some() =>
+            {
+                Ok(Permitted::exempt(&self.server, tls))
+            }
+            res => res,
+        }
+    }
+}
+
+// === impl DeniedUnauthorized ===
+
+impl DeniedUnauthorized {
+    pub(crate) fn new(
+        client_addr: Remote<ClientAddr>,
+        dst_addr: OrigDstAddr,
+        tls: tls::ConditionalServerTls,
+    ) -> Self {
+        Self {
+            client_addr,
+            dst_addr,
+            tls,
+        }
+    }
 }
 
 // === impl Permitted ===
@@ -258,6 +455,46 @@ impl Permitted {
         labels.extend(authz.labels.clone());
         Self {
             protocol: server.protocol,
+            forward_client_id: server.forward_client_id,
+            priority: authz.priority,
+            exempt_routes: None,
+            deny_response: None,
+            audited: false,
+            probes: server.probes.iter().cloned().collect(),
+            labels,
+            tls,
+        }
+    }
+
+    fn exempt(server: &ServerPolicy, tls: tls::ConditionalServerTls) -> Self {
+        let mut labels = BTreeMap::new();
+        labels.extend(server.labels.clone());
+        labels.insert("authz".to_string(), "_exempt".to_string());
+        Self {
+            protocol: server.protocol,
+            forward_client_id: server.forward_client_id,
+            priority: Priority::Normal,
+            exempt_routes: Some(server.exempt_routes.iter().cloned().collect()),
+            deny_response: server.deny_response.clone().map(Arc::new),
+            audited: false,
+            probes: server.probes.iter().cloned().collect(),
+            labels,
+            tls,
+        }
+    }
+
+    fn audited(server: &ServerPolicy, tls: tls::ConditionalServerTls) -> Self {
+        let mut labels = BTreeMap::new();
+        labels.extend(server.labels.clone());
+        labels.insert("authz".to_string(), "_audit".to_string());
+        Self {
+            protocol: server.protocol,
+            forward_client_id: server.forward_client_id,
+            priority: Priority::Normal,
+            exempt_routes: None,
+            deny_response: None,
+            audited: true,
+            probes: server.probes.iter().cloned().collect(),
             labels,
             tls,
         }
@@ -285,7 +522,9 @@ impl Hasher for PortHasher {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use linkerd_server_policy::{Authentication, Authorization, Protocol, ServerPolicy, Suffix};
+    use linkerd_server_policy::{
+        Authentication, Authorization, Priority, Protocol, ServerPolicy, Suffix,
+    };
     use std::collections::HashSet;
 
     #[tokio::test(flavor = "current_thread")]
@@ -298,10 +537,17 @@ mod tests {
                 labels: vec![("authz".to_string(), "unauth".to_string())]
                     .into_iter()
                     .collect(),
+                priority: Priority::Normal,
             }],
             labels: vec![("server".to_string(), "test".to_string())]
                 .into_iter()
                 .collect(),
+            forward_client_id: true,
+            forward_addr: None,
+            exempt_routes: Vec::new(),
+            probes: Vec::new(),
+            deny_response: None,
+            audit: false,
         };
 
         let allowed = PortPolicies::from(policy.clone())
@@ -318,6 +564,12 @@ mod tests {
             Permitted {
                 tls,
                 protocol: policy.protocol,
+                forward_client_id: true,
+                priority: Priority::Normal,
+                exempt_routes: None,
+                deny_response: None,
+                audited: false,
+                probes: Vec::new().into(),
                 labels: vec![
                     ("authz".to_string(), "unauth".to_string()),
                     ("server".to_string(), "test".to_string())
@@ -341,10 +593,17 @@ mod tests {
                 labels: vec![("authz".to_string(), "tls-auth".to_string())]
                     .into_iter()
                     .collect(),
+                priority: Priority::Normal,
             }],
             labels: vec![("server".to_string(), "test".to_string())]
                 .into_iter()
                 .collect(),
+            forward_client_id: true,
+            forward_addr: None,
+            exempt_routes: Vec::new(),
+            probes: Vec::new(),
+            deny_response: None,
+            audit: false,
         };
 
         let allowed = PortPolicies::from(policy.clone())
@@ -364,6 +623,12 @@ mod tests {
             Permitted {
                 tls,
                 protocol: policy.protocol,
+                forward_client_id: true,
+                priority: Priority::Normal,
+                exempt_routes: None,
+                deny_response: None,
+                audited: false,
+                probes: Vec::new().into(),
                 labels: vec![
                     ("authz".to_string(), "tls-auth".to_string()),
                     ("server".to_string(), "test".to_string())
@@ -403,10 +668,17 @@ mod tests {
                 labels: vec![("authz".to_string(), "tls-auth".to_string())]
                     .into_iter()
                     .collect(),
+                priority: Priority::Normal,
             }],
             labels: vec![("server".to_string(), "test".to_string())]
                 .into_iter()
                 .collect(),
+            forward_client_id: true,
+            forward_addr: None,
+            exempt_routes: Vec::new(),
+            probes: Vec::new(),
+            deny_response: None,
+            audit: false,
         };
 
         let allowed = PortPolicies::from(policy.clone())
@@ -425,6 +697,12 @@ mod tests {
             Permitted {
                 tls,
                 protocol: policy.protocol,
+                forward_client_id: true,
+                priority: Priority::Normal,
+                exempt_routes: None,
+                deny_response: None,
+                audited: false,
+                probes: Vec::new().into(),
                 labels: vec![
                     ("authz".to_string(), "tls-auth".to_string()),
                     ("server".to_string(), "test".to_string())
@@ -457,10 +735,17 @@ mod tests {
                 labels: vec![("authz".to_string(), "tls-unauth".to_string())]
                     .into_iter()
                     .collect(),
+                priority: Priority::Normal,
             }],
             labels: vec![("server".to_string(), "test".to_string())]
                 .into_iter()
                 .collect(),
+            forward_client_id: true,
+            forward_addr: None,
+            exempt_routes: Vec::new(),
+            probes: Vec::new(),
+            deny_response: None,
+            audit: false,
         };
 
         let allowed = PortPolicies::from(policy.clone())
@@ -479,6 +764,12 @@ mod tests {
             Permitted {
                 tls,
                 protocol: policy.protocol,
+                forward_client_id: true,
+                priority: Priority::Normal,
+                exempt_routes: None,
+                deny_response: None,
+                audited: false,
+                probes: Vec::new().into(),
                 labels: vec![
                     ("authz".to_string(), "tls-unauth".to_string()),
                     ("server".to_string(), "test".to_string())
@@ -489,15 +780,351 @@ mod tests {
         );
 
         let tls = tls::ConditionalServerTls::Some(tls::ServerTls::Passthru {
-            sni: "othersa.testns.serviceaccount.identity.linkerd.cluster.example.com"
-                .parse()
-                .unwrap(),
+            sni: Some(
+                "othersa.testns.serviceaccount.identity.linkerd.cluster.example.com"
+                    .parse()
+                    .unwrap(),
+            ),
         });
         allowed
             .check_authorized(tls)
             .expect_err("policy must require a TLS termination identity");
     }
 
+    #[tokio::test(flavor = "current_thread")]
+    async fn tls_server_name() {
+        let policy = ServerPolicy {
+            protocol: Protocol::Tls,
+            authorizations: vec![Authorization {
+                authentication: Authentication::TlsServerName {
+                    names: vec!["api.example.com".to_string()].into_iter().collect(),
+                    suffixes: vec![Suffix::from(vec![
+                        "internal".to_string(),
+                        "example".to_string(),
+                        "com".to_string(),
+                    ])],
+                },
+                networks: vec!["192.0.2.0/24".parse().unwrap()],
+                labels: vec![("authz".to_string(), "tls-sni".to_string())]
+                    .into_iter()
+                    .collect(),
+                priority: Priority::Normal,
+            }],
+            labels: vec![("server".to_string(), "test".to_string())]
+                .into_iter()
+                .collect(),
+            forward_client_id: true,
+            forward_addr: None,
+            exempt_routes: Vec::new(),
+            probes: Vec::new(),
+            deny_response: None,
+            audit: false,
+        };
+
+        let allowed = PortPolicies::from(policy.clone())
+            .check_allowed(client_addr(), orig_dst_addr())
+            .expect("port must be known");
+        assert_eq!(*allowed.server, policy);
+
+        for sni in &["api.example.com", "db.internal.example.com"] {
+            let tls = tls::ConditionalServerTls::Some(tls::ServerTls::Passthru {
+                sni: Some(sni.parse().unwrap()),
+            });
+            assert_eq!(
+                allowed
+                    .check_authorized(tls.clone())
+                    .expect("matching SNI must be permitted"),
+                Permitted {
+                    tls,
+                    protocol: policy.protocol,
+                    forward_client_id: true,
+                    priority: Priority::Normal,
+                    exempt_routes: None,
+                    deny_response: None,
+                    audited: false,
+                    probes: Vec::new().into(),
+                    labels: vec![
+                        ("authz".to_string(), "tls-sni".to_string()),
+                        ("server".to_string(), "test".to_string())
+                    ]
+                    .into_iter()
+                    .collect()
+                }
+            );
+        }
+
+        for tls in &[
+            tls::ConditionalServerTls::Some(tls::ServerTls::Passthru {
+                sni: Some("other.example.com".parse().unwrap()),
+            }),
+            tls::ConditionalServerTls::Some(tls::ServerTls::Passthru { sni: None }),
+            tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello),
+        ] {
+            allowed
+                .check_authorized(tls.clone())
+                .expect_err("policy must require a matching SNI");
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn client_priorities() {
+        let policies = PortPolicies::from(all_unauthenticated_server_policy(
+            std::time::Duration::from_secs(10),
+        ))
+        .with_client_priorities(&[
+            ("192.0.2.3/32".parse().unwrap(), Priority::High),
+            ("192.0.2.0/24".parse().unwrap(), Priority::Low),
+        ]);
+        let tls = tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello);
+
+        let priority = |client: Remote<ClientAddr>| {
+            policies
+                .check_allowed(client, orig_dst_addr())
+                .expect("port must be known")
+                .check_authorized(tls.clone())
+                .expect("unauthenticated connection must be permitted")
+                .priority
+        };
+        assert_eq!(priority(client_addr()), Priority::High);
+        assert_eq!(
+            priority(Remote(ClientAddr(([192, 0, 2, 4], 54321).into()))),
+            Priority::Low
+        );
+        assert_eq!(
+            priority(Remote(ClientAddr(([198, 51, 100, 1], 54321).into()))),
+            Priority::Normal
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn identity_and_network() {
+        let authz = |authentication, network: &str, name: &str| Authorization {
+            authentication,
+            networks: vec![network.parse().unwrap()],
+            labels: vec![("authz".to_string(), name.to_string())]
+                .into_iter()
+                .collect(),
+            priority: Priority::Normal,
+        };
+        let policy = ServerPolicy {
+            authorizations: vec![
+                // The client's identity is only permitted from its address.
+                authz(
+                    Authentication::TlsAuthenticated {
+                        identities: vec![client_id().to_string()].into_iter().collect(),
+                        suffixes: vec![],
+                    },
+                    "192.0.2.3/32",
+                    "client",
+                ),
+                // Other clients in the network must use mTLS.
+                authz(Authentication::TlsUnauthenticated, "192.0.2.0/24", "mtls"),
+            ],
+            ..all_unauthenticated_server_policy(std::time::Duration::from_secs(10))
+        };
+        let policies = PortPolicies::from(policy);
+
+        let check = |client: Remote<ClientAddr>, id: Option<&str>| {
+            let tls = match id {
+                Some(id) => tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+                    client_id: Some(id.parse().unwrap()),
+                    negotiated_protocol: None,
+                }),
+                None => tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello),
+            };
+            policies
+                .check_allowed(client, orig_dst_addr())
+                .expect("port must be known")
+                .check_authorized(tls)
+                .ok()
+                .and_then(|p| p.labels.get("authz").cloned())
+        };
+        let client_id = client_id().to_string();
+        let other_id = "othersa.testns.serviceaccount.identity.linkerd.cluster.local";
+        let other_addr = Remote(ClientAddr(([192, 0, 2, 4], 54321).into()));
+        let remote_addr = Remote(ClientAddr(([198, 51, 100, 1], 54321).into()));
+
+        // The first authorization that matches both the client's network and
+        // identity applies.
+        assert_eq!(
+            check(client_addr(), Some(&client_id)).as_deref(),
+            Some("client")
+        );
+        assert_eq!(
+            check(client_addr(), Some(other_id)).as_deref(),
+            Some("mtls")
+        );
+        assert_eq!(check(other_addr, Some(&client_id)).as_deref(), Some("mtls"));
+        assert_eq!(check(client_addr(), None), None);
+        assert_eq!(check(remote_addr, Some(&client_id)), None);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn exempt_routes() {
+        let healthz = ExemptRoute {
+            method: Some("GET".to_string()),
+            path: "/healthz".to_string(),
+        };
+        let policy = ServerPolicy {
+            exempt_routes: vec![healthz.clone()],
+            ..all_authenticated_server_policy(std::time::Duration::from_secs(10))
+        };
+
+        let allowed = PortPolicies::from(policy.clone())
+            .check_allowed(client_addr(), orig_dst_addr())
+            .expect("port must be known");
+
+        let tls = tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello);
+        allowed
+            .check_authorized(tls.clone())
+            .expect_err("policy must require a client identity");
+        let permitted = allowed
+            .check_authorized_or_exempt(tls.clone())
+            .expect("connection must be permitted for exempt routes");
+        assert_eq!(
+            permitted,
+            Permitted {
+                tls,
+                protocol: policy.protocol,
+                forward_client_id: true,
+                priority: Priority::Normal,
+                exempt_routes: Some(vec![healthz].into()),
+                deny_response: None,
+                audited: false,
+                probes: Vec::new().into(),
+                labels: vec![
+                    ("authz".to_string(), "_exempt".to_string()),
+                    ("server".to_string(), "_default".to_string())
+                ]
+                .into_iter()
+                .collect()
+            }
+        );
+
+        let tls = tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+            client_id: Some(client_id()),
+            negotiated_protocol: None,
+        });
+        let permitted = allowed
+            .check_authorized_or_exempt(tls)
+            .expect("authenticated connection must be permitted");
+        assert_eq!(permitted.exempt_routes, None);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn deny_response() {
+        let deny = DenyResponse {
+            status: 403,
+            headers: vec![],
+            body: "contact #platform".to_string(),
+        };
+        let policy = ServerPolicy {
+            deny_response: Some(deny.clone()),
+            ..all_authenticated_server_policy(std::time::Duration::from_secs(10))
+        };
+
+        let allowed = PortPolicies::from(policy)
+            .check_allowed(client_addr(), orig_dst_addr())
+            .expect("port must be known");
+
+        // Unauthorized connections are permitted so that their requests may be
+        // denied with the server's response.
+        let tls = tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello);
+        allowed
+            .check_authorized(tls.clone())
+            .expect_err("policy must require a client identity");
+        let permitted = allowed
+            .check_authorized_or_exempt(tls)
+            .expect("connection must be permitted for the deny response");
+        assert_eq!(permitted.exempt_routes, Some(Vec::new().into()));
+        assert_eq!(permitted.deny_response, Some(Arc::new(deny)));
+
+        let tls = tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+            client_id: Some(client_id()),
+            negotiated_protocol: None,
+        });
+        let permitted = allowed
+            .check_authorized_or_exempt(tls)
+            .expect("authenticated connection must be permitted");
+        assert_eq!(permitted.exempt_routes, None);
+        assert_eq!(permitted.deny_response, None);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn audit() {
+        let policy = ServerPolicy {
+            audit: true,
+            ..all_authenticated_server_policy(std::time::Duration::from_secs(10))
+        };
+
+        let allowed = PortPolicies::from(policy.clone())
+            .check_allowed(client_addr(), orig_dst_addr())
+            .expect("port must be known");
+
+        // Connections that the policy would deny are permitted, but marked as
+        // audited.
+        let tls = tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello);
+        let permitted = allowed
+            .check_authorized(tls.clone())
+            .expect("audited policy must permit unauthorized connections");
+        assert_eq!(
+            permitted,
+            Permitted {
+                tls,
+                protocol: policy.protocol,
+                forward_client_id: true,
+                priority: Priority::Normal,
+                exempt_routes: None,
+                deny_response: None,
+                audited: true,
+                probes: Vec::new().into(),
+                labels: vec![
+                    ("authz".to_string(), "_audit".to_string()),
+                    ("server".to_string(), "_default".to_string())
+                ]
+                .into_iter()
+                .collect()
+            }
+        );
+
+        let tls = tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+            client_id: Some(client_id()),
+            negotiated_protocol: None,
+        });
+        let permitted = allowed
+            .check_authorized(tls)
+            .expect("authenticated connection must be permitted");
+        assert!(!permitted.audited);
+        assert_eq!(
+            permitted.labels.get("authz").map(String::as_str),
+            Some("_all-authenticated")
+        );
+    }
+
+    #[test]
+    fn forward_addrs() {
+        let default = all_unauthenticated_server_policy(std::time::Duration::from_secs(10));
+        let addr = SocketAddr::from(([127, 0, 0, 1], 8080));
+        let policies = PortPolicies::new(
+            DefaultPolicy::Allow(Arc::new(default.clone())),
+            Some((
+                1000,
+                ServerPolicy {
+                    forward_addr: Some(addr),
+                    ..default.clone()
+                },
+            )),
+        );
+        assert_eq!(policies.forward_addr(1000), Some(addr));
+        assert_eq!(policies.forward_addr(2000), None);
+
+        let policies = PortPolicies::from(ServerPolicy {
+            forward_addr: Some(addr),
+            ..default
+        });
+        assert_eq!(policies.forward_addr(2000), Some(addr));
+    }
+
     fn client_id() -> tls::ClientId {
         "testsa.testns.serviceaccount.identity.linkerd.cluster.local"
             .parse()
@@ -1,7 +1,7 @@
-use crate::{direct, Inbound};
+use crate::{detect, direct, Inbound};
 use linkerd_app_core::{
     config::ServerConfig,
-    io, profiles, serve, svc,
+    connections, io, metrics, profiles, serve, svc,
     transport::{self, listen::Bind, ClientAddr, Local, OrigDstAddr, Remote, ServerAddr},
     Error,
 };
@@ -29,7 +29,11 @@ impl Inbound<()> {
             + svc::Param<OrigDstAddr>,
         G: svc::NewService<direct::GatewayConnection, Service = GSvc>,
         G: Clone + Send + Sync + Unpin + 'static,
-        GSvc: svc::Service<direct::GatewayIo<io::ScopedIo<B::Io>>, Response = ()> + Send + 'static,
+        GSvc: svc::Service<
+                direct::GatewayIo<io::ScopedIo<connections::TrackedIo<B::Io>>>,
+                Response = (),
+            > + Send
+            + 'static,
         GSvc::Error: Into<Error>,
         GSvc::Future: Send,
         P: profiles::GetProfile<profiles::LookupAddr> + Clone + Send + Sync + Unpin + 'static,
@@ -39,9 +43,15 @@ impl Inbound<()> {
         let (Local(ServerAddr(la)), listen) = bind
             .bind(&self.config.proxy.server)
             .expect("Failed to bind inbound listener");
+        let listen = self.config.proxy.fd_pressure.shed(listen);
+        let listen = self
+            .runtime
+            .connections
+            .track(metrics::Direction::In, listen);
 
         let serve = async move {
             let shutdown = self.runtime.drain.clone().signaled();
+            let accept_metrics = self.runtime.metrics.tcp_accept.clone();
 
             // Handles connections to ports that can't be determined to be HTTP.
             let forward = self
@@ -50,7 +60,21 @@ impl Inbound<()> {
                 .push_tcp_forward()
                 .into_stack()
                 .push_map_target(TcpEndpoint::from_param)
-                .instrument(|_: &_| debug_span!("tcp"))
+                .push(
+                    self.runtime
+                        .connections
+                        .layer_describe(metrics::Direction::In, |t: &detect::Tls| {
+                            svc::Param::param(t)
+                        }),
+                )
+                .instrument(|t: &detect::Tls| {
+                    // Label opaque connections with their sniffed protocol, if any.
+                    let protocol: Option<_> = svc::Param::param(t);
+                    match protocol {
+                        Some(protocol) => debug_span!("tcp", %protocol),
+                        None => debug_span!("tcp"),
+                    }
+                })
                 .into_inner();
 
             // Handles connections that target the inbound proxy port.
@@ -67,18 +91,27 @@ impl Inbound<()> {
             // Handles HTTP connections.
             let http = self
                 .into_tcp_connect(la.port())
-                .push_http_router(profiles)
-                .push_http_server();
+                .push_http_router(profiles.clone())
+                .push_http_server()
+                .map_stack(|_, rt, http| {
+                    http.push(
+                        rt.connections
+                            .layer_describe(metrics::Direction::In, |t: &detect::Http| {
+                                svc::Param::param(t)
+                            }),
+                    )
+                });
 
             // Determines how to handle an inbound connection, dispatching it to the appropriate
             // stack.
             let server = http
                 .push_detect_http(forward.clone())
                 .push_detect_tls(forward)
+                .push_discover_opaque_ports(profiles)
                 .push_accept(la.port(), direct)
                 .into_inner();
 
-            serve::serve(listen, server, shutdown).await
+            serve::serve(listen, server, Some(accept_metrics), shutdown).await
         };
 
         (Local(ServerAddr(la)), serve)
@@ -5,14 +5,14 @@ use linkerd_app_core::{
     dns::Suffix,
     drain, exp_backoff, metrics,
     proxy::{
-        http::{h1, h2},
+        http::{h1, h2, HeaderName},
         tap,
     },
     transport::{Keepalive, ListenAddr},
     NameMatch, ProxyRuntime,
 };
 pub use linkerd_app_test as support;
-use linkerd_server_policy::{Authentication, Authorization, Protocol, ServerPolicy};
+use linkerd_server_policy::{Authentication, Authorization, Priority, Protocol, ServerPolicy};
 use std::time::Duration;
 
 pub fn default_config() -> Config {
@@ -25,6 +25,7 @@ pub fn default_config() -> Config {
             server: config::ServerConfig {
                 addr: ListenAddr(([0, 0, 0, 0], 0).into()),
                 keepalive: Keepalive(None),
+                h1_settings: h1::ServerSettings::default(),
                 h2_settings: h2::Settings::default(),
             },
             connect: config::ConnectConfig {
@@ -47,6 +48,13 @@ pub fn default_config() -> Config {
             dispatch_timeout: Duration::from_secs(1),
             max_in_flight_requests: 10_000,
             detect_protocol_timeout: Duration::from_secs(10),
+            queue_time_header: false,
+            fail_fast_retry_after: false,
+            error_responses: Default::default(),
+            request_limits: Default::default(),
+            request_id: None,
+            fd_pressure: Default::default(),
+            trace_attributes: Default::default(),
         },
         port_policies: ServerPolicy {
             protocol: Protocol::Detect {
@@ -56,24 +64,54 @@ pub fn default_config() -> Config {
                 authentication: Authentication::Unauthenticated,
                 networks: vec![Default::default()],
                 labels: Default::default(),
+                priority: Priority::Normal,
             }],
             labels: Default::default(),
+            forward_client_id: true,
+            forward_addr: None,
+            exempt_routes: Vec::new(),
+            probes: Vec::new(),
+            deny_response: None,
+            audit: false,
         }
         .into(),
         profile_idle_timeout: Duration::from_millis(500),
+        client_id_header: HeaderName::from_static("l5d-client-id"),
+        jwt: None,
+        ext_authz: None,
+        #[cfg(feature = "wasm")]
+        http_wasm: None,
+        forwarded: None,
+        grpc_web: false,
+        compression: None,
+        load_shed: None,
+        http1_malformed: Default::default(),
+        http1_preserve_header_case: Default::default(),
+        http1_strict_framing: true,
+        gateway_clusters: Default::default(),
+        gateway_trust_domains: Vec::new().into(),
+        tunnel: false,
+        tcp_rate_limits: Default::default(),
+        tcp_idle_timeout: None,
+        connection_limits: Default::default(),
+        tls_handshake_limits: Default::default(),
+        discover_opaque_ports: false,
+        additional_listeners: Vec::new(),
     }
 }
 
 pub fn runtime() -> (ProxyRuntime, drain::Signal) {
-    let (metrics, _) = metrics::Metrics::new(std::time::Duration::from_secs(10));
+    let (metrics, _) = metrics::Metrics::new(std::time::Duration::from_secs(10), 10);
     let (drain_tx, drain) = drain::channel();
     let (tap, _) = tap::new();
     let runtime = ProxyRuntime {
         identity: None,
         metrics: metrics.outbound,
         tap,
+        connections: Default::default(),
         span_sink: None,
         drain,
+        extensions: Default::default(),
     };
     (runtime, drain_tx)
 }
@@ -419,3 +419,99 @@ async fn timeout() {
         }
     }
 }
+
+#[tokio::test]
+async fn ingress_override_route_metrics() {
+    let _trace = trace_init();
+
+    let host = "profiles.test.svc.cluster.local";
+    let srv = server::http1().route("/hello", "hello").run().await;
+    let port = srv.addr.port();
+    let dst = format!("{}:{}", host, port);
+
+    let ctrl = controller::new();
+    let dst_tx = ctrl.destination_tx(&dst);
+    dst_tx.send_addr(srv.addr);
+    let profile_tx = ctrl.profile_tx(&dst);
+    profile_tx.send(controller::profile(
+        vec![controller::route()
+            .request_path("/hello")
+            .label("hello", "ingress")],
+        None,
+        vec![],
+        host,
+    ));
+
+    let mut env = TestEnv::default();
+    env.put(app::env::ENV_INGRESS_MODE, "true".to_owned());
+    let proxy = proxy::new()
+        .controller(ctrl.run().await)
+        .outbound(srv)
+        .run_with_test_env(env)
+        .await;
+
+    let client = client::http1(proxy.outbound, host);
+    let metrics = client::http1(proxy.metrics, "localhost");
+
+    let req = client
+        .request_builder("/hello")
+        .header("l5d-dst-override", dst.as_str());
+    let rsp = client.request(req).await.unwrap();
+    assert_eq!(rsp.status(), 200);
+
+    metrics::metric("route_response_total")
+        .label("direction", "outbound")
+        .label("dst", &dst)
+        .label("rt_hello", "ingress")
+        .label("classification", "success")
+        .value(1u64)
+        .assert_in(&metrics)
+        .await;
+}
+
+#[tokio::test]
+async fn ingress_host_route_metrics() {
+    let _trace = trace_init();
+
+    let host = "profiles.test.svc.cluster.local";
+    let srv = server::http1().route("/hello", "hello").run().await;
+    let port = srv.addr.port();
+    let dst = format!("{}:{}", host, port);
+
+    let ctrl = controller::new();
+    let dst_tx = ctrl.destination_tx(&dst);
+    dst_tx.send_addr(srv.addr);
+    let profile_tx = ctrl.profile_tx(&dst);
+    profile_tx.send(controller::profile(
+        vec![controller::route()
+            .request_path("/hello")
+            .label("hello", "host")],
+        None,
+        vec![],
+        host,
+    ));
+
+    let mut env = TestEnv::default();
+    env.put(app::env::ENV_INGRESS_MODE, "true".to_owned());
+    let proxy = proxy::new()
+        .controller(ctrl.run().await)
+        .outbound(srv)
+        .run_with_test_env(env)
+        .await;
+
+    let client = client::http1(proxy.outbound, host);
+    let metrics = client::http1(proxy.metrics, "localhost");
+
+    // Without an override header, the request is routed by its Host header,
+    // using the original destination's port.
+    assert_eq!(client.get("/hello").await, "hello");
+
+    metrics::metric("route_response_total")
+        .label("direction", "outbound")
+        .label("dst", &dst)
+        .label("rt_hello", "host")
+        .label("classification", "success")
+        .value(1u64)
+        .assert_in(&metrics)
+        .await;
+}
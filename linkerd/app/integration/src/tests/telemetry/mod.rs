@@ -1173,6 +1173,30 @@ mod transport {
         .await;
     }
 
+    #[tokio::test]
+    async fn outbound_http_accept_logical() {
+        let _trace = trace_init();
+        let Fixture {
+            client,
+            metrics,
+            proxy: _proxy,
+            _profile,
+            dst_tx: _dst_tx,
+            labels,
+            ..
+        } = Fixture::outbound().await;
+
+        // Accepted connections are labeled with the discovered logical
+        // service's authority.
+        let opens = labels
+            .label("peer", "src")
+            .metric("tcp_open_total")
+            .value(1u64);
+        info!("client.get(/)");
+        assert_eq!(client.get("/").await, "hello");
+        opens.assert_in(&metrics).await;
+    }
+
     #[tokio::test]
     async fn outbound_http_connect() {
         test_http_connect(Fixture::outbound(), metrics::labels()).await;
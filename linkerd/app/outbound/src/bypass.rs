@@ -0,0 +1,268 @@
+//! Bypasses the outbound stack for latency-critical destinations.
+//!
+//! Connections to bypassed destinations are forwarded directly to their
+//! original destination address, without protocol detection, discovery, or
+//! mTLS. They remain subject to the egress policy, which may only match their
+//! address.
+
+use crate::Outbound;
+use linkerd_app_core::{
+    io,
+    svc::{self, stack::Param},
+    transport::{ConnectTcp, OrigDstAddr, Remote, ServerAddr},
+    Error, Infallible, IpMatch,
+};
+use std::{fmt, net::SocketAddr, ops::RangeInclusive, sync::Arc};
+use tracing::debug;
+
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    /// A destination is bypassed if it matches any rule.
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Rule {
+    pub nets: IpMatch,
+
+    /// The ports to which the rule applies. When unset, the rule applies to
+    /// all ports.
+    pub ports: Option<RangeInclusive<u16>>,
+}
+
+/// A connection that bypasses the outbound stack.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Bypassed(OrigDstAddr);
+
+// === impl Outbound ===
+
+impl Outbound<()> {
+    /// Builds a stack that forwards bypassed connections to their original
+    /// destination.
+    ///
+    /// Unlike other outbound connections, bypassed connections may be made on
+    /// the loopback interface, since bypassed destinations are configured
+    /// explicitly.
+    pub fn to_bypass<I>(
+        &self,
+    ) -> Outbound<
+        svc::BoxNewService<
+            Bypassed,
+            impl svc::Service<I, Response = (), Error = Error, Future = impl Send> + Clone,
+        >,
+    >
+    where
+        I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + fmt::Debug + Send + Unpin + 'static,
+    {
+        let connect = ConnectTcp::new(self.config.proxy.connect.keepalive);
+        self.clone().with_stack(connect).push_tcp_forward()
+    }
+}
+
+impl<N> Outbound<N> {
+    /// Routes connections to bypassed destinations to the `bypass` stack.
+    ///
+    /// Bypassed destinations are not discovered, so the egress policy is
+    /// applied to their original destination address before they are
+    /// forwarded.
+    pub fn push_bypass<T, I, NSvc, B, BSvc>(self, bypass: B) -> Outbound<svc::BoxNewTcp<T, I>>
+    where
+        T: Param<OrigDstAddr> + 'static,
+        I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + fmt::Debug + Send + Unpin + 'static,
+        N: svc::NewService<T, Service = NSvc> + Clone + Send + Sync + 'static,
+        NSvc: svc::Service<I, Response = (), Error = Error> + Send + 'static,
+        NSvc::Future: Send,
+        B: svc::NewService<Bypassed, Service = BSvc> + Clone + Send + Sync + 'static,
+        BSvc: svc::Service<I, Response = (), Error = Error> + Send + 'static,
+        BSvc::Future: Send,
+    {
+        self.map_stack(|config, _, stack| {
+            let bypass_config = config.bypass.clone();
+            let policy = config.egress.clone().map(Arc::new);
+            let bypass = svc::stack(bypass)
+                .push_request_filter(move |target: Bypassed| -> Result<_, Error> {
+                    if let Some(policy) = policy.as_ref() {
+                        let Bypassed(OrigDstAddr(addr)) = target;
+                        policy.check(None, addr)?;
+                    }
+                    Ok(target)
+                })
+                .into_inner();
+            stack
+                .push_switch(
+                    move |target: T| -> Result<_, Infallible> {
+                        let OrigDstAddr(addr) = target.param();
+                        if bypass_config.matches(addr) {
+                            debug!(%addr, "Bypassing outbound stack");
+                            return Ok(svc::Either::B(Bypassed(OrigDstAddr(addr))));
+                        }
+                        Ok(svc::Either::A(target))
+                    },
+                    bypass,
+                )
+                .push_on_response(svc::BoxService::layer())
+                .push(svc::BoxNewService::layer())
+        })
+    }
+}
+
+// === impl Config ===
+
+impl Config {
+    pub fn matches(&self, addr: SocketAddr) -> bool {
+        self.rules.iter().any(|rule| rule.matches(addr))
+    }
+}
+
+// === impl Rule ===
+
+impl Rule {
+    fn matches(&self, addr: SocketAddr) -> bool {
+        if let Some(ports) = self.ports.as_ref() {
+            if !ports.contains(&addr.port()) {
+                return false;
+            }
+        }
+        self.nets.matches(addr.ip())
+    }
+}
+
+// === impl Bypassed ===
+
+impl Param<Remote<ServerAddr>> for Bypassed {
+    fn param(&self) -> Remote<ServerAddr> {
+        let Bypassed(OrigDstAddr(addr)) = *self;
+        Remote(ServerAddr(addr))
+    }
+}
+
+impl Param<u16> for Bypassed {
+    fn param(&self) -> u16 {
+        let Bypassed(OrigDstAddr(addr)) = *self;
+        addr.port()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{egress, test_util::*};
+    use futures::future;
+    use linkerd_app_core::{
+        is_error,
+        svc::{NewService, ServiceExt},
+        IpNet,
+    };
+    use std::str::FromStr;
+
+    fn config() -> Config {
+        let nets = |s: &str| IpMatch::new(Some(IpNet::from_str(s).unwrap()));
+        Config {
+            rules: vec![
+                Rule {
+                    nets: nets("127.0.0.1/32"),
+                    ports: Some(6379..=6379),
+                },
+                Rule {
+                    nets: nets("192.0.2.0/24"),
+                    ports: None,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn matches_rules() {
+        let config = config();
+        let addr = |ip: [u8; 4], port| SocketAddr::new(ip.into(), port);
+        assert!(config.matches(addr([127, 0, 0, 1], 6379)));
+        assert!(!config.matches(addr([127, 0, 0, 1], 8080)));
+        assert!(config.matches(addr([192, 0, 2, 20], 80)));
+        assert!(!config.matches(addr([198, 51, 100, 1], 6379)));
+        assert!(!Config::default().matches(addr([127, 0, 0, 1], 6379)));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn switches_bypassed_targets() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let (rt, _shutdown) = runtime();
+        let config = crate::Config {
+            bypass: config(),
+            ..default_config()
+        };
+        let bypassed = OrigDstAddr(SocketAddr::new([192, 0, 2, 20].into(), 80));
+        let proxied = OrigDstAddr(SocketAddr::new([198, 51, 100, 1].into(), 80));
+        let mut stack = Outbound::new(config, rt)
+            .with_stack(move |dst: OrigDstAddr| {
+                assert_eq!(dst, proxied, "only proxied targets use the stack");
+                svc::mk(|_: io::DuplexStream| future::ok::<(), Error>(()))
+            })
+            .push_bypass(move |b: Bypassed| {
+                assert_eq!(b, Bypassed(bypassed));
+                svc::mk(|_: io::DuplexStream| future::err::<(), Error>("bypassed".into()))
+            })
+            .into_inner();
+
+        let (server_io, _client_io) = io::duplex(1);
+        stack
+            .new_service(proxied)
+            .oneshot(server_io)
+            .await
+            .expect("proxied targets must use the stack");
+
+        let (server_io, _client_io) = io::duplex(1);
+        let err = stack
+            .new_service(bypassed)
+            .oneshot(server_io)
+            .await
+            .expect_err("bypassed targets must use the bypass stack");
+        assert_eq!(err.to_string(), "bypassed");
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn applies_egress_policy_to_bypassed_targets() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let (rt, _shutdown) = runtime();
+        let config = crate::Config {
+            bypass: config(),
+            egress: Some(egress::Config {
+                mode: egress::Mode::Enforce,
+                default: egress::Action::Allow,
+                rules: vec![egress::Rule {
+                    action: egress::Action::Deny,
+                    dst: IpMatch::new(Some(IpNet::from_str("192.0.2.0/24").unwrap())).into(),
+                    ports: None,
+                }],
+            }),
+            ..default_config()
+        };
+        let denied = OrigDstAddr(SocketAddr::new([192, 0, 2, 20].into(), 80));
+        let allowed = OrigDstAddr(SocketAddr::new([127, 0, 0, 1].into(), 6379));
+        let mut stack = Outbound::new(config, rt)
+            .with_stack(|_: OrigDstAddr| -> svc::BoxTcp<io::DuplexStream> {
+                panic!("bypassed targets must not use the stack")
+            })
+            .push_bypass(move |b: Bypassed| {
+                assert_eq!(b, Bypassed(allowed), "denied targets must not be forwarded");
+                svc::mk(|_: io::DuplexStream| future::ok::<(), Error>(()))
+            })
+            .into_inner();
+
+        let (server_io, _client_io) = io::duplex(1);
+        let err = stack
+            .new_service(denied)
+            .oneshot(server_io)
+            .await
+            .expect_err("egress must be denied");
+        assert!(is_error::<egress::EgressDenied>(&*err), "{:?}", err);
+
+        let (server_io, _client_io) = io::duplex(1);
+        stack
+            .new_service(allowed)
+            .oneshot(server_io)
+            .await
+            .expect("egress must be allowed");
+    }
+}
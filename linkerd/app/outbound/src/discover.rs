@@ -1,13 +1,20 @@
 use crate::{tcp, Outbound};
 use linkerd_app_core::{
-    io, profiles,
-    svc::{self, stack::Param},
-    transport::{metrics::SensorIo, OrigDstAddr},
+    http_tracing, io, profiles,
+    svc::{self, extension::Direction, stack::Param},
+    transport::{self, metrics::SensorIo, OrigDstAddr},
     Error,
 };
 use std::convert::TryFrom;
 use tracing::{debug, debug_span, info_span};
 
+/// A TCP target with its discovered profile.
+#[derive(Clone)]
+struct Discovered {
+    profile: Option<profiles::Receiver>,
+    accept: tcp::Accept,
+}
+
 impl<N> Outbound<N> {
     /// Discovers the profile for a TCP endpoint.
     ///
@@ -38,6 +45,20 @@ impl<N> Outbound<N> {
         self.map_stack(|config, rt, accept| {
             let allow = config.allow_discovery.clone();
             accept
+                // Accepted connections are recorded after discovery so that
+                // their metrics are labeled with the logical service, if any.
+                .push_map_target(|Discovered { profile, accept }| (profile, accept))
+                .push(rt.metrics.transport.layer_accept())
+                .push_map_target(
+                    |(profile, accept): (Option<profiles::Receiver>, tcp::Accept)| Discovered {
+                        profile,
+                        accept,
+                    },
+                )
+                // Servers are cached by their original destination, so they
+                // must be rebuilt when the control plane changes whether the
+                // destination is opaque.
+                .push(profiles::NewRebuildOnOpaqueChange::layer())
                 .push(profiles::discover::layer(
                     profiles,
                     move |a: tcp::Accept| {
@@ -65,6 +86,10 @@ impl<N> Outbound<N> {
                         // service in a background task so it becomes ready without
                         // new requests.
                         .push(svc::layer::mk(svc::SpawnReady::new))
+                        // Traces the time spent looking up the profile. Connections
+                        // aren't traced, so this is deferred until the
+                        // connection's first request.
+                        .push(http_tracing::DeferredReadySpan::layer("profile"))
                         .push(rt.metrics.stack.layer(crate::stack_labels("tcp", "server")))
                         .push(svc::FailFast::layer(
                             "TCP Server",
@@ -72,10 +97,16 @@ impl<N> Outbound<N> {
                         ))
                         .push_spawn_buffer(config.proxy.buffer_capacity),
                 )
-                .push(rt.metrics.transport.layer_accept())
-                .push_cache(config.proxy.cache_max_idle_age)
+                .push_cache(
+                    config.cache_idle_ages.profile,
+                    rt.metrics
+                        .cache
+                        .metrics(crate::stack_labels("tcp", "server")),
+                )
                 .instrument(|a: &tcp::Accept| info_span!("server", orig_dst = %a.orig_dst))
                 .push_request_filter(|t: T| tcp::Accept::try_from(t.param()))
+                // Refuses connections that are rejected by an extension.
+                .push_request_filter(rt.extensions.accept(Direction::Out))
                 .push(rt.metrics.tcp_accept_errors.layer())
                 .push(svc::BoxNewService::layer())
                 .check_new_service::<T, I>()
@@ -83,6 +114,22 @@ impl<N> Outbound<N> {
     }
 }
 
+// === impl Discovered ===
+
+impl Param<transport::labels::Key> for Discovered {
+    fn param(&self) -> transport::labels::Key {
+        let logical_addr = self
+            .profile
+            .as_ref()
+            .and_then(|p| p.logical_addr())
+            .map(|profiles::LogicalAddr(addr)| addr);
+        transport::labels::Key::OutboundAccept {
+            target_addr: self.accept.orig_dst.into(),
+            logical_addr,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -196,7 +243,7 @@ mod tests {
         // service after `idle_timeout`.
         let cfg = {
             let mut cfg = default_config();
-            cfg.proxy.cache_max_idle_age = idle_timeout;
+            cfg.cache_idle_ages.profile = idle_timeout;
             cfg
         };
         let (rt, _shutdown) = runtime();
@@ -0,0 +1,267 @@
+//! Egress policy for outbound connections.
+//!
+//! Unlike `allow_discovery`, which only determines whether a destination's
+//! profile may be discovered, the egress policy determines whether traffic may
+//! be forwarded to a destination at all.
+
+use crate::Outbound;
+use linkerd_app_core::{
+    dns, io, profiles,
+    svc::{self, stack::Param},
+    transport::OrigDstAddr,
+    AddrMatch, Error,
+};
+use std::{fmt, net::SocketAddr, ops::RangeInclusive, sync::Arc};
+use tracing::{debug, warn};
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub mode: Mode,
+
+    /// The action taken when no rule matches a destination.
+    pub default: Action,
+
+    /// Rules are evaluated in order and the first matching rule applies.
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Connections denied by the policy are refused.
+    Enforce,
+
+    /// Connections denied by the policy are logged but forwarded.
+    Audit,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Allow,
+    Deny,
+}
+
+#[derive(Clone, Debug)]
+pub struct Rule {
+    pub action: Action,
+
+    /// Matches a destination's logical name (when a profile provides one) or
+    /// its IP address.
+    pub dst: AddrMatch,
+
+    /// The ports to which the rule applies. When unset, the rule applies to
+    /// all ports.
+    pub ports: Option<RangeInclusive<u16>>,
+}
+
+#[derive(Debug)]
+pub struct EgressDenied {
+    addr: SocketAddr,
+    name: Option<profiles::LogicalAddr>,
+}
+
+// === impl Outbound ===
+
+impl<N> Outbound<N> {
+    /// Applies the configured egress policy to discovered targets, if any.
+    ///
+    /// The policy is evaluated after profile discovery so that rules may match
+    /// a destination's logical name as well as its original destination
+    /// address.
+    pub fn push_egress_policy<T, I, NSvc>(
+        self,
+    ) -> Outbound<svc::BoxNewTcp<(Option<profiles::Receiver>, T), I>>
+    where
+        T: Param<OrigDstAddr> + 'static,
+        I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + fmt::Debug + Send + Unpin + 'static,
+        N: svc::NewService<(Option<profiles::Receiver>, T), Service = NSvc>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        NSvc: svc::Service<I, Response = (), Error = Error> + Send + 'static,
+        NSvc::Future: Send,
+    {
+        self.map_stack(|config, _, stack| {
+            let policy = config.egress.clone().map(Arc::new);
+            stack
+                .push_request_filter(
+                    move |(profile, target): (Option<profiles::Receiver>, T)| -> Result<_, Error> {
+                        if let Some(policy) = policy.as_ref() {
+                            let OrigDstAddr(addr) = target.param();
+                            let name = profile.as_ref().and_then(|p| p.logical_addr());
+                            policy.check(name, addr)?;
+                        }
+                        Ok((profile, target))
+                    },
+                )
+                .push_on_response(svc::BoxService::layer())
+                .push(svc::BoxNewService::layer())
+        })
+    }
+}
+
+// === impl Config ===
+
+impl Config {
+    /// Determines the action for a destination, ignoring the policy's mode.
+    pub fn action(&self, name: Option<&dns::Name>, addr: SocketAddr) -> Action {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(name, addr))
+            .map(|rule| rule.action)
+            .unwrap_or(self.default)
+    }
+
+    pub(crate) fn check(
+        &self,
+        name: Option<profiles::LogicalAddr>,
+        addr: SocketAddr,
+    ) -> Result<(), EgressDenied> {
+        let action = self.action(name.as_ref().map(|n| n.0.name()), addr);
+        match (action, self.mode) {
+            (Action::Allow, _) => Ok(()),
+            (Action::Deny, Mode::Audit) => {
+                warn!(%addr, ?name, "Egress would be denied by policy");
+                Ok(())
+            }
+            (Action::Deny, Mode::Enforce) => {
+                debug!(%addr, ?name, "Egress denied by policy");
+                Err(EgressDenied { addr, name })
+            }
+        }
+    }
+}
+
+// === impl Rule ===
+
+impl Rule {
+    fn matches(&self, name: Option<&dns::Name>, addr: SocketAddr) -> bool {
+        if let Some(ports) = self.ports.as_ref() {
+            if !ports.contains(&addr.port()) {
+                return false;
+            }
+        }
+
+        let name_matches = name
+            .map(|name| self.dst.names().matches(name))
+            .unwrap_or(false);
+        name_matches || self.dst.matches_ip(addr.ip())
+    }
+}
+
+// === impl EgressDenied ===
+
+impl fmt::Display for EgressDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name.as_ref() {
+            Some(name) => write!(f, "egress to {} ({}) denied by policy", name, self.addr),
+            None => write!(f, "egress to {} denied by policy", self.addr),
+        }
+    }
+}
+
+impl std::error::Error for EgressDenied {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use linkerd_app_core::{
+        is_error,
+        svc::{NewService, ServiceExt},
+        IpMatch, IpNet, NameMatch,
+    };
+    use std::str::FromStr;
+
+    fn net(s: &str) -> AddrMatch {
+        IpMatch::new(Some(IpNet::from_str(s).unwrap())).into()
+    }
+
+    fn config(mode: Mode) -> Config {
+        Config {
+            mode,
+            default: Action::Allow,
+            rules: vec![
+                Rule {
+                    action: Action::Allow,
+                    dst: net("192.0.2.10/32"),
+                    ports: Some(443..=443),
+                },
+                Rule {
+                    action: Action::Deny,
+                    dst: net("192.0.2.0/24"),
+                    ports: None,
+                },
+                Rule {
+                    action: Action::Deny,
+                    dst: NameMatch::new(Some(dns::Suffix::from_str("example.com").unwrap())).into(),
+                    ports: None,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn first_matching_rule_applies() {
+        let config = config(Mode::Enforce);
+        let addr = |ip: [u8; 4], port| SocketAddr::new(ip.into(), port);
+
+        assert_eq!(
+            config.action(None, addr([192, 0, 2, 10], 443)),
+            Action::Allow
+        );
+        assert_eq!(config.action(None, addr([192, 0, 2, 10], 80)), Action::Deny);
+        assert_eq!(
+            config.action(None, addr([192, 0, 2, 20], 443)),
+            Action::Deny
+        );
+        assert_eq!(
+            config.action(None, addr([198, 51, 100, 1], 80)),
+            Action::Allow
+        );
+
+        let name = dns::Name::from_str("web.example.com").unwrap();
+        assert_eq!(
+            config.action(Some(&name), addr([198, 51, 100, 1], 80)),
+            Action::Deny
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn enforce_and_audit() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let mk_stack = |mode| {
+            let (rt, shutdown) = runtime();
+            let config = crate::Config {
+                egress: Some(config(mode)),
+                ..default_config()
+            };
+            let stack = Outbound::new(config, rt)
+                .with_stack(|_: (Option<profiles::Receiver>, OrigDstAddr)| {
+                    svc::mk(|_: io::DuplexStream| future::ok::<(), Error>(()))
+                })
+                .push_egress_policy()
+                .into_inner();
+            (stack, shutdown)
+        };
+        let denied = OrigDstAddr(SocketAddr::new([192, 0, 2, 20].into(), 80));
+
+        let (mut stack, _shutdown) = mk_stack(Mode::Enforce);
+        let (server_io, _client_io) = io::duplex(1);
+        let err = stack
+            .new_service((None, denied))
+            .oneshot(server_io)
+            .await
+            .expect_err("egress must be denied");
+        assert!(is_error::<EgressDenied>(&*err), "{:?}", err);
+
+        let (mut stack, _shutdown) = mk_stack(Mode::Audit);
+        let (server_io, _client_io) = io::duplex(1);
+        stack
+            .new_service((None, denied))
+            .oneshot(server_io)
+            .await
+            .expect("egress must be allowed in audit mode");
+    }
+}
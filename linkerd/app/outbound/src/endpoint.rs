@@ -2,12 +2,15 @@ use crate::{http, logical::Concrete, tcp, Outbound};
 use linkerd_app_core::{
     io, metrics,
     profiles::LogicalAddr,
-    proxy::{api_resolve::Metadata, resolve::map_endpoint::MapEndpoint},
+    proxy::{
+        api_resolve::{Labels, Metadata},
+        resolve::map_endpoint::MapEndpoint,
+    },
     svc, tls,
     transport::{self, addrs::*},
     transport_header, Conditional,
 };
-use std::{fmt, net::SocketAddr};
+use std::{fmt, net::SocketAddr, sync::Arc};
 
 #[derive(Clone, Debug)]
 pub struct Endpoint<P> {
@@ -17,11 +20,20 @@ pub struct Endpoint<P> {
     pub logical_addr: Option<LogicalAddr>,
     pub protocol: P,
     pub opaque_protocol: bool,
+    pub metadata_labels: MetadataLabels,
 }
 
-#[derive(Copy, Clone)]
+/// Selects the endpoint metadata labels (e.g. pod, zone, cluster) that are
+/// included in metrics and tracing spans.
+///
+/// By default, all labels are included.
+#[derive(Clone, Debug, Default)]
+pub struct MetadataLabels(Option<Arc<[String]>>);
+
+#[derive(Clone)]
 pub struct FromMetadata {
     pub identity_disabled: bool,
+    pub metadata_labels: MetadataLabels,
 }
 
 // === impl Endpoint ===
@@ -35,6 +47,7 @@ impl Endpoint<()> {
             logical_addr: None,
             opaque_protocol: false,
             protocol: (),
+            metadata_labels: MetadataLabels::default(),
         }
     }
 
@@ -43,6 +56,7 @@ impl Endpoint<()> {
         metadata: Metadata,
         reason: tls::NoClientTls,
         opaque_protocol: bool,
+        metadata_labels: MetadataLabels,
     ) -> Self {
         Self {
             addr: Remote(ServerAddr(addr.into())),
@@ -51,6 +65,22 @@ impl Endpoint<()> {
             logical_addr: None,
             opaque_protocol,
             protocol: (),
+            metadata_labels,
+        }
+    }
+}
+
+impl<P> Endpoint<P> {
+    /// Records the endpoint's selected metadata labels on a span's
+    /// `server.labels` field.
+    pub(crate) fn record_labels(&self, span: &tracing::Span) {
+        let labels = self
+            .metadata_labels
+            .select(self.metadata.labels())
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>();
+        if !labels.is_empty() {
+            span.record("server.labels", &labels.join(",").as_str());
         }
     }
 }
@@ -61,7 +91,14 @@ impl<P> svc::Param<Remote<ServerAddr>> for Endpoint<P> {
     }
 }
 
-impl<P> svc::Param<tls::ConditionalClientTls> for Endpoint<P> {
+/// Used to determine TCP rate limits.
+impl<P> svc::Param<u16> for Endpoint<P> {
+    fn param(&self) -> u16 {
+        self.addr.as_ref().port()
+    }
+}
+
+impl svc::Param<tls::ConditionalClientTls> for tcp::Endpoint {
     fn param(&self) -> tls::ConditionalClientTls {
         self.tls.clone()
     }
@@ -77,6 +114,27 @@ impl<P> svc::Param<Option<http::detect::Skip>> for Endpoint<P> {
     }
 }
 
+impl<P> svc::Param<http::detect::OverrideTarget> for Endpoint<P> {
+    fn param(&self) -> http::detect::OverrideTarget {
+        http::detect::OverrideTarget {
+            name: self.logical_addr.as_ref().map(|l| l.0.clone()),
+            addr: Some(self.addr.into()),
+        }
+    }
+}
+
+impl<P> svc::Param<Option<tcp::FallbackAddr>> for Endpoint<P> {
+    fn param(&self) -> Option<tcp::FallbackAddr> {
+        self.metadata.fallback_addr().map(tcp::FallbackAddr)
+    }
+}
+
+impl<P> svc::Param<tcp::ConnectLabels> for Endpoint<P> {
+    fn param(&self) -> tcp::ConnectLabels {
+        tcp::ConnectLabels(self.metadata.labels().clone())
+    }
+}
+
 impl<P> svc::Param<Option<tcp::opaque_transport::PortOverride>> for Endpoint<P> {
     fn param(&self) -> Option<tcp::opaque_transport::PortOverride> {
         self.metadata
@@ -108,7 +166,10 @@ impl<P> svc::Param<metrics::OutboundEndpointLabels> for Endpoint<P> {
             .map(|LogicalAddr(a)| a.as_http_authority());
         metrics::OutboundEndpointLabels {
             authority,
-            labels: metrics::prefix_labels("dst", self.metadata.labels().iter()),
+            labels: metrics::prefix_labels(
+                "dst",
+                self.metadata_labels.select(self.metadata.labels()),
+            ),
             server_id: self.tls.clone(),
             target_addr: self.addr.into(),
         }
@@ -130,6 +191,22 @@ impl<P: std::hash::Hash> std::hash::Hash for Endpoint<P> {
     }
 }
 
+// === impl MetadataLabels ===
+
+impl MetadataLabels {
+    /// Includes only the named labels.
+    pub fn only(names: impl IntoIterator<Item = String>) -> Self {
+        Self(Some(names.into_iter().collect()))
+    }
+
+    fn select<'l>(&'l self, labels: &'l Labels) -> impl Iterator<Item = (&'l String, &'l String)> {
+        labels.iter().filter(move |(k, _)| match self.0 {
+            Some(ref names) => names.iter().any(|n| n == *k),
+            None => true,
+        })
+    }
+}
+
 // === EndpointFromMetadata ===
 
 impl FromMetadata {
@@ -147,9 +224,12 @@ impl FromMetadata {
                 Conditional::Some(tls::ClientTls {
                     server_id,
                     alpn: if use_transport_header {
-                        Some(tls::client::AlpnProtocols(vec![
-                            transport_header::PROTOCOL.into()
-                        ]))
+                        Some(tls::client::AlpnProtocols(
+                            transport_header::PROTOCOLS
+                                .iter()
+                                .map(|&p| p.into())
+                                .collect(),
+                        ))
                     } else {
                         None
                     },
@@ -183,6 +263,7 @@ impl<P: Copy + std::fmt::Debug> MapEndpoint<Concrete<P>, Metadata> for FromMetad
             // XXX We never do protocol detection after resolving a concrete address to endpoints.
             // We should differentiate these target types statically.
             opaque_protocol: false,
+            metadata_labels: self.metadata_labels.clone(),
         }
     }
 }
@@ -219,6 +300,7 @@ pub mod tests {
     use crate::test_util::*;
     use hyper::{client::conn::Builder as ClientBuilder, Body, Request};
     use linkerd_app_core::{
+        proxy::api_resolve::ProtocolHint,
         svc::{NewService, Service, ServiceExt},
         Error,
     };
@@ -281,4 +363,38 @@ pub mod tests {
             .expect("Client must close gracefully");
         drop((client, shutdown));
     }
+
+    #[test]
+    fn metadata_labels() {
+        let metadata = Metadata::new(
+            vec![
+                ("pod".to_string(), "foo-abc".to_string()),
+                ("zone".to_string(), "us-west-1a".to_string()),
+            ],
+            ProtocolHint::Unknown,
+            None,
+            None,
+            None,
+        );
+        let labels = |ml: MetadataLabels| {
+            let ep = Endpoint::from_metadata(
+                ([10, 0, 0, 41], 5550),
+                metadata.clone(),
+                tls::NoClientTls::Disabled,
+                false,
+                ml,
+            );
+            svc::Param::<metrics::OutboundEndpointLabels>::param(&ep).labels
+        };
+
+        assert_eq!(
+            labels(MetadataLabels::default()).as_deref(),
+            Some("dst_pod=\"foo-abc\",dst_zone=\"us-west-1a\"")
+        );
+        assert_eq!(
+            labels(MetadataLabels::only(vec!["zone".to_string()])).as_deref(),
+            Some("dst_zone=\"us-west-1a\"")
+        );
+        assert_eq!(labels(MetadataLabels::only(None)), None);
+    }
 }
@@ -0,0 +1,430 @@
+//! Fails over from a service's local endpoints to the endpoints of the same
+//! service mirrored from a remote cluster.
+//!
+//! Each concrete balancer is paired with a balancer over the mirrored service
+//! (i.e. `<svc>-<cluster>.<ns>...`). Requests are dispatched to the local
+//! balancer unless it has not become ready within `failover_after` (because it
+//! has no endpoints or all of its endpoints are unavailable). Once traffic has
+//! shifted to the remote balancer, it stays there for at least
+//! `min_failover` before returning to the local balancer.
+
+use crate::logical::Concrete;
+use linkerd_app_core::{
+    metrics::failover::{Metrics, Registry},
+    proxy::api_resolve::ConcreteAddr,
+    svc, Error, NameAddr,
+};
+use parking_lot::Mutex;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::time;
+use tracing::{debug, trace};
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// The name of the remote cluster from which services are mirrored.
+    pub cluster: String,
+
+    /// How long the local balancer may be unavailable before traffic is sent
+    /// to the remote cluster.
+    pub failover_after: time::Duration,
+
+    /// The minimum amount of time that traffic is sent to the remote cluster
+    /// once it has failed over.
+    pub min_failover: time::Duration,
+}
+
+#[derive(Clone, Debug)]
+pub struct NewFailover<N> {
+    inner: N,
+    config: Option<Config>,
+    metrics: Registry,
+}
+
+/// Clones of a `Failover` share its failover state, so that every clone sends
+/// traffic to the same balancer.
+#[derive(Debug)]
+pub struct Failover<S> {
+    primary: S,
+    secondary: Option<(S, Arc<Mutex<Shared>>)>,
+    failover_after: time::Duration,
+    min_failover: time::Duration,
+    sleep: Pin<Box<time::Sleep>>,
+    ready: Option<Ready>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: State,
+    deadline: time::Instant,
+    metrics: Arc<Metrics>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+    Primary,
+    Waiting,
+    Secondary,
+}
+
+/// The inner service that this handle has most recently driven to readiness.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Ready {
+    Primary,
+    Secondary,
+}
+
+// === impl Config ===
+
+impl Config {
+    /// Returns the address of the mirrored service for the given concrete
+    /// address, if it names a service.
+    fn remote_addr(&self, ConcreteAddr(addr): &ConcreteAddr) -> Option<NameAddr> {
+        let name = addr.name().without_trailing_dot();
+        let (svc, rest) = name.split_at(name.find('.')?);
+        NameAddr::from_str_and_port(&format!("{}-{}{}", svc, self.cluster, rest), addr.port()).ok()
+    }
+}
+
+// === impl NewFailover ===
+
+impl<N> NewFailover<N> {
+    pub fn layer(
+        config: Option<Config>,
+        metrics: Registry,
+    ) -> impl svc::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            config: config.clone(),
+            metrics: metrics.clone(),
+        })
+    }
+}
+
+impl<P, N> svc::NewService<Concrete<P>> for NewFailover<N>
+where
+    P: Clone,
+    N: svc::NewService<Concrete<P>>,
+{
+    type Service = Failover<N::Service>;
+
+    fn new_service(&mut self, target: Concrete<P>) -> Self::Service {
+        let config = match self.config.as_ref() {
+            Some(config) => config,
+            None => return Failover::new(self.inner.new_service(target), None),
+        };
+        let remote = match config.remote_addr(&target.resolve) {
+            Some(remote) => remote,
+            None => return Failover::new(self.inner.new_service(target), None),
+        };
+
+        debug!(%remote, "Remote failover configured");
+        let metrics = self
+            .metrics
+            .metrics(target.resolve.0.clone(), remote.clone());
+        let secondary = self.inner.new_service(Concrete {
+            resolve: ConcreteAddr(remote),
+            logical: target.logical.clone(),
+        });
+        let mut failover =
+            Failover::new(self.inner.new_service(target), Some((secondary, metrics)));
+        failover.failover_after = config.failover_after;
+        failover.min_failover = config.min_failover;
+        failover
+    }
+}
+
+// === impl Failover ===
+
+impl<S> Failover<S> {
+    fn new(primary: S, secondary: Option<(S, Arc<Metrics>)>) -> Self {
+        let secondary = secondary.map(|(secondary, metrics)| {
+            let shared = Shared {
+                state: State::Primary,
+                deadline: time::Instant::now(),
+                metrics,
+            };
+            (secondary, Arc::new(Mutex::new(shared)))
+        });
+        Self {
+            primary,
+            secondary,
+            failover_after: time::Duration::default(),
+            min_failover: time::Duration::default(),
+            // The sleep is reset to the shared deadline before it is polled;
+            // this initial one will never actually be used.
+            sleep: Box::pin(time::sleep(time::Duration::default())),
+            ready: None,
+        }
+    }
+
+    /// Resets this handle's timer to the shared deadline, which may have been
+    /// set by another clone.
+    fn reset_sleep(&mut self, deadline: time::Instant) {
+        if self.sleep.deadline() != deadline {
+            self.sleep.as_mut().reset(deadline);
+        }
+    }
+}
+
+impl<S, Req> svc::Service<Req> for Failover<S>
+where
+    S: svc::Service<Req>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = futures::future::MapErr<S::Future, fn(S::Error) -> Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.ready = None;
+        let shared = match self.secondary.as_ref() {
+            Some((_, shared)) => shared.clone(),
+            None => {
+                let ready = futures::ready!(self.primary.poll_ready(cx).map_err(Into::into));
+                self.ready = Some(Ready::Primary);
+                return Poll::Ready(ready);
+            }
+        };
+        let mut shared = shared.lock();
+
+        loop {
+            trace!(state = ?shared.state, "Failover::poll");
+            match shared.state {
+                // When the local balancer is not ready, start a timer and wait
+                // for it to recover.
+                State::Primary => match self.primary.poll_ready(cx).map_err(Into::into) {
+                    Poll::Ready(ready) => {
+                        self.ready = Some(Ready::Primary);
+                        return Poll::Ready(ready);
+                    }
+                    Poll::Pending => {
+                        trace!(delay = ?self.failover_after, "Local balancer pending");
+                        shared.deadline = time::Instant::now() + self.failover_after;
+                        shared.state = State::Waiting;
+                    }
+                },
+
+                State::Waiting => {
+                    self.reset_sleep(shared.deadline);
+                    match self.sleep.as_mut().poll(cx) {
+                        Poll::Ready(()) => {
+                            debug!(after = ?self.failover_after, "Failing over to remote balancer");
+                            shared.metrics.failover();
+                            shared.deadline = time::Instant::now() + self.min_failover;
+                            shared.state = State::Secondary;
+                        }
+                        Poll::Pending => match self.primary.poll_ready(cx).map_err(Into::into) {
+                            Poll::Ready(ready) => {
+                                shared.state = State::Primary;
+                                self.ready = Some(Ready::Primary);
+                                return Poll::Ready(ready);
+                            }
+                            Poll::Pending => return Poll::Pending,
+                        },
+                    }
+                }
+
+                // Traffic is held on the remote balancer until `min_failover`
+                // elapses so that a flapping local balancer does not cause
+                // traffic to oscillate between clusters.
+                State::Secondary => {
+                    self.reset_sleep(shared.deadline);
+                    if self.sleep.as_mut().poll(cx).is_ready() {
+                        if let Poll::Ready(ready) = self.primary.poll_ready(cx).map_err(Into::into)
+                        {
+                            debug!(?ready, "Reverting to local balancer");
+                            shared.metrics.recovered();
+                            shared.state = State::Primary;
+                            self.ready = Some(Ready::Primary);
+                            return Poll::Ready(ready);
+                        }
+                    }
+                    let (secondary, _) = self.secondary.as_mut().expect("must have a secondary");
+                    let ready = futures::ready!(secondary.poll_ready(cx).map_err(Into::into));
+                    self.ready = Some(Ready::Secondary);
+                    return Poll::Ready(ready);
+                }
+            }
+        }
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        use futures::TryFutureExt;
+        trace!(ready = ?self.ready, "Failover::call");
+        let call = match (self.ready.take(), self.secondary.as_mut()) {
+            (Some(Ready::Secondary), Some((secondary, _))) => secondary.call(req),
+            (Some(Ready::Primary), _) => self.primary.call(req),
+            _ => panic!("called before ready!"),
+        };
+        call.map_err(Into::into as fn(_) -> _)
+    }
+}
+
+impl<S: Clone> Clone for Failover<S> {
+    fn clone(&self) -> Self {
+        Self {
+            primary: self.primary.clone(),
+            secondary: self.secondary.clone(),
+            failover_after: self.failover_after,
+            min_failover: self.min_failover,
+            sleep: Box::pin(time::sleep(time::Duration::default())),
+            ready: None,
+        }
+    }
+}
+
+// === impl Shared ===
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        if self.state == State::Secondary {
+            self.metrics.dropped();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::svc::Service;
+    use std::str::FromStr;
+    use tokio_test::{assert_pending, assert_ready_ok};
+    use tower_test::mock;
+
+    #[test]
+    fn remote_addr() {
+        let config = Config {
+            cluster: "east".to_string(),
+            failover_after: time::Duration::from_secs(1),
+            min_failover: time::Duration::from_secs(10),
+        };
+        let concrete = |s: &str| ConcreteAddr(NameAddr::from_str(s).unwrap());
+        assert_eq!(
+            config.remote_addr(&concrete("foo.ns1.svc.cluster.local:8080")),
+            Some(NameAddr::from_str("foo-east.ns1.svc.cluster.local:8080").unwrap())
+        );
+        assert_eq!(config.remote_addr(&concrete("localhost:8080")), None);
+    }
+
+    fn failover(
+        primary: mock::Mock<(), ()>,
+        secondary: mock::Mock<(), ()>,
+        metrics: Arc<Metrics>,
+    ) -> Failover<mock::Mock<(), ()>> {
+        let mut failover = Failover::new(primary, Some((secondary, metrics)));
+        failover.failover_after = time::Duration::from_secs(1);
+        failover.min_failover = time::Duration::from_secs(10);
+        failover
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn fails_over_and_recovers() {
+        let _trace = linkerd_tracing::test::trace_init();
+        time::pause();
+
+        let (primary, mut primary_handle) = mock::pair::<(), ()>();
+        let (secondary, mut secondary_handle) = mock::pair::<(), ()>();
+        let metrics = Registry::default().metrics(
+            NameAddr::from_str("foo.ns1:80").unwrap(),
+            NameAddr::from_str("foo-east.ns1:80").unwrap(),
+        );
+        let mut svc = failover(primary, secondary, metrics);
+        let mut task = tokio_test::task::spawn(());
+
+        // The local balancer is unavailable, so nothing happens until the
+        // failover timeout elapses.
+        primary_handle.allow(0);
+        secondary_handle.allow(1);
+        assert_pending!(task.enter(|cx, _| svc.poll_ready(cx)));
+        time::sleep(time::Duration::from_secs(2)).await;
+        assert_ready_ok!(task.enter(|cx, _| svc.poll_ready(cx)));
+        let call = svc.call(());
+        let (_, rsp) = secondary_handle
+            .next_request()
+            .await
+            .expect("secondary called");
+        rsp.send_response(());
+        call.await.expect("call succeeds");
+
+        // Even though the local balancer becomes ready, traffic stays on the
+        // remote balancer until the hysteresis period elapses.
+        primary_handle.allow(1);
+        secondary_handle.allow(1);
+        assert_ready_ok!(task.enter(|cx, _| svc.poll_ready(cx)));
+        let call = svc.call(());
+        let (_, rsp) = secondary_handle
+            .next_request()
+            .await
+            .expect("secondary called");
+        rsp.send_response(());
+        call.await.expect("call succeeds");
+
+        time::sleep(time::Duration::from_secs(10)).await;
+        assert_ready_ok!(task.enter(|cx, _| svc.poll_ready(cx)));
+        let call = svc.call(());
+        let (_, rsp) = primary_handle.next_request().await.expect("primary called");
+        rsp.send_response(());
+        call.await.expect("call succeeds");
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn clones_share_failover() {
+        let _trace = linkerd_tracing::test::trace_init();
+        time::pause();
+
+        let (primary, mut primary_handle) = mock::pair::<(), ()>();
+        let (secondary, mut secondary_handle) = mock::pair::<(), ()>();
+        let metrics = Registry::default().metrics(
+            NameAddr::from_str("foo.ns1:80").unwrap(),
+            NameAddr::from_str("foo-east.ns1:80").unwrap(),
+        );
+        let mut svc0 = failover(primary, secondary, metrics);
+        let mut svc1 = svc0.clone();
+        let mut task = tokio_test::task::spawn(());
+
+        // One clone observes the local balancer's unavailability and fails
+        // over.
+        primary_handle.allow(0);
+        secondary_handle.allow(1);
+        assert_pending!(task.enter(|cx, _| svc0.poll_ready(cx)));
+        time::sleep(time::Duration::from_secs(2)).await;
+        assert_ready_ok!(task.enter(|cx, _| svc0.poll_ready(cx)));
+        let call = svc0.call(());
+        let (_, rsp) = secondary_handle
+            .next_request()
+            .await
+            .expect("secondary called");
+        rsp.send_response(());
+        call.await.expect("call succeeds");
+
+        // Other clones, including those created after the failover, send
+        // traffic to the remote balancer even though the local balancer is
+        // ready.
+        primary_handle.allow(1);
+        for svc in &mut [svc1.clone(), svc0.clone()] {
+            secondary_handle.allow(1);
+            assert_ready_ok!(task.enter(|cx, _| svc.poll_ready(cx)));
+            let call = svc.call(());
+            let (_, rsp) = secondary_handle
+                .next_request()
+                .await
+                .expect("secondary called");
+            rsp.send_response(());
+            call.await.expect("call succeeds");
+        }
+
+        // Once the hysteresis period elapses, any clone may revert to the
+        // local balancer.
+        time::sleep(time::Duration::from_secs(10)).await;
+        assert_ready_ok!(task.enter(|cx, _| svc1.poll_ready(cx)));
+        let call = svc1.call(());
+        let (_, rsp) = primary_handle.next_request().await.expect("primary called");
+        rsp.send_response(());
+        call.await.expect("call succeeds");
+    }
+}
@@ -1,15 +1,49 @@
 use crate::{http, Outbound};
 use linkerd_app_core::{
     config::ServerConfig,
-    detect, io,
+    connections, detect, io, metrics,
+    protocol_hints::{self, ProtocolHints},
     svc::{self, Param},
-    Error, Infallible,
+    Addr, AddrMatch, Error, Infallible, NameAddr,
 };
-use tracing::debug_span;
+use std::{net::SocketAddr, ops::RangeInclusive, sync::Arc};
+use tracing::{debug, debug_span};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct Skip;
 
+/// Forces the protocol of matching destinations, overriding protocol detection
+/// and any hints from discovery.
+#[derive(Clone, Debug)]
+pub struct ProtocolOverrides(Arc<[ProtocolOverride]>);
+
+#[derive(Clone, Debug)]
+pub struct ProtocolOverride {
+    pub protocol: ForcedProtocol,
+
+    /// Matches a destination's logical name or its endpoint's IP address.
+    pub dst: AddrMatch,
+
+    /// The ports to which the override applies. When unset, the override
+    /// applies to all ports.
+    pub ports: Option<RangeInclusive<u16>>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ForcedProtocol {
+    Opaque,
+    Http1,
+    H2,
+}
+
+/// Describes a detection target so that protocol overrides may be matched
+/// against it.
+#[derive(Clone, Debug)]
+pub struct OverrideTarget {
+    pub name: Option<NameAddr>,
+    pub addr: Option<SocketAddr>,
+}
+
 impl<N> Outbound<N> {
     pub fn push_detect_http<T, U, NSvc, H, HSvc, I>(self, http: H) -> Outbound<svc::BoxNewTcp<T, I>>
     where
@@ -25,17 +59,59 @@ impl<N> Outbound<N> {
         HSvc: Clone + Send + Sync + Unpin + 'static,
         HSvc::Error: Into<Error>,
         HSvc::Future: Send,
-        T: Param<Option<Skip>> + Clone + Send + Sync + 'static,
+        T: Param<Option<Skip>> + Param<OverrideTarget> + Clone + Send + Sync + 'static,
         U: From<(http::Version, T)> + svc::Param<http::Version> + 'static,
     {
         self.map_stack(|config, rt, tcp| {
-            let ServerConfig { h2_settings, .. } = config.proxy.server;
+            let ServerConfig {
+                h1_settings,
+                h2_settings,
+                ..
+            } = config.proxy.server;
+            let overrides = config.protocol_overrides.clone();
+            let opaque_overrides = overrides.clone();
+            let server_speaks_first = config.server_speaks_first_ports.clone();
+            let hints = config.protocol_hints.clone();
+            let opaque_hints = hints.clone();
+            let learn_hints = hints.clone();
+
+            let tcp = tcp.push(
+                rt.connections
+                    .layer_describe(metrics::Direction::Out, |_: &T| {
+                        connections::Description::from_protocol("opaque")
+                    }),
+            );
 
             let skipped = tcp
                 .clone()
                 .push_on_response(svc::MapTargetLayer::new(io::EitherIo::Left))
                 .into_inner();
 
+            // Serves HTTP without detection when an override forces the
+            // target's HTTP version.
+            let forced = svc::stack(http.clone())
+                .push_on_response(
+                    svc::layers()
+                        .push(http::BoxRequest::layer())
+                        .push(svc::MapErrLayer::new(Into::into)),
+                )
+                .push(http::NewServeHttp::layer(
+                    h1_settings,
+                    h2_settings,
+                    rt.drain.clone(),
+                ))
+                .push(
+                    rt.connections
+                        .layer_describe(metrics::Direction::Out, |target: &U| {
+                            let version: http::Version = target.param();
+                            connections::Description::from_protocol(version)
+                        }),
+                )
+                .push_map_target(U::from)
+                .instrument(|(v, _): &(http::Version, _)| debug_span!("http", %v))
+                .push_on_response(svc::BoxService::layer())
+                .into_inner();
+
             svc::stack(http)
                 .push_on_response(
                     svc::layers()
@@ -43,7 +119,18 @@ impl<N> Outbound<N> {
                         .push(svc::MapErrLayer::new(Into::into)),
                 )
                 .check_new_service::<U, _>()
-                .push(http::NewServeHttp::layer(h2_settings, rt.drain.clone()))
+                .push(http::NewServeHttp::layer(
+                    h1_settings,
+                    h2_settings,
+                    rt.drain.clone(),
+                ))
+                .push(
+                    rt.connections
+                        .layer_describe(metrics::Direction::Out, |target: &U| {
+                            let version: http::Version = target.param();
+                            connections::Description::from_protocol(version)
+                        }),
+                )
                 .push_map_target(U::from)
                 .instrument(|(v, _): &(http::Version, _)| debug_span!("http", %v))
                 .push(svc::UnwrapOr::layer(
@@ -53,22 +140,202 @@ impl<N> Outbound<N> {
                 .push_on_response(svc::BoxService::layer())
                 .check_new_service::<(Option<http::Version>, T), _>()
                 .push_map_target(detect::allow_timeout)
+                .push_map_target(
+                    move |(res, target): (detect::DetectResult<http::Version>, T)| {
+                        // Timeouts aren't learned, since they indicate that the
+                        // client didn't write rather than what it wrote.
+                        if let Ok(version) = res.as_ref() {
+                            let dst: OverrideTarget = target.param();
+                            if let Some(addr) = dst.hint_addr() {
+                                learn_hints.record(addr, *version);
+                            }
+                        }
+                        (res, target)
+                    },
+                )
                 .push(svc::BoxNewService::layer())
                 .push(detect::NewDetectService::layer(config.proxy.detect_http()))
                 .push_switch(
                     // When the target is marked as as opaque, we skip HTTP
                     // detection and just use the TCP stack directly.
-                    |target: T| -> Result<_, Infallible> {
+                    move |target: T| -> Result<_, Infallible> {
                         if let Some(Skip) = target.param() {
                             return Ok(svc::Either::B(target));
                         }
+                        let dst: OverrideTarget = target.param();
+                        if opaque_overrides.get(&dst) == Some(ForcedProtocol::Opaque) {
+                            debug!("Protocol forced to be opaque");
+                            return Ok(svc::Either::B(target));
+                        }
+                        if dst.learned_protocol(&opaque_hints)
+                            == Some(protocol_hints::Protocol::Opaque)
+                        {
+                            debug!("Protocol learned to be opaque");
+                            return Ok(svc::Either::B(target));
+                        }
+                        // Waiting for the client to write first would stall
+                        // these connections until detection times out.
+                        if dst
+                            .port()
+                            .map_or(false, |p| server_speaks_first.contains(&p))
+                        {
+                            debug!("Server speaks first; skipping protocol detection");
+                            return Ok(svc::Either::B(target));
+                        }
                         Ok(svc::Either::A(target))
                     },
                     skipped,
                 )
                 .check_new_service::<T, _>()
                 .push_on_response(svc::BoxService::layer())
+                .push_switch(
+                    move |target: T| -> Result<_, Infallible> {
+                        let dst: OverrideTarget = target.param();
+                        let version = match overrides.get(&dst) {
+                            Some(ForcedProtocol::Http1) => http::Version::Http1,
+                            Some(ForcedProtocol::H2) => http::Version::H2,
+                            Some(ForcedProtocol::Opaque) => return Ok(svc::Either::A(target)),
+                            None if dst.learned_protocol(&hints)
+                                == Some(protocol_hints::Protocol::Http1) =>
+                            {
+                                debug!("Protocol learned to be HTTP/1");
+                                return Ok(svc::Either::B((http::Version::Http1, target)));
+                            }
+                            None => return Ok(svc::Either::A(target)),
+                        };
+                        debug!(%version, "Protocol forced");
+                        Ok(svc::Either::B((version, target)))
+                    },
+                    forced,
+                )
+                .push_on_response(svc::BoxService::layer())
                 .push(svc::BoxNewService::layer())
         })
     }
 }
+
+// === impl ProtocolOverrides ===
+
+impl ProtocolOverrides {
+    pub fn new(overrides: impl IntoIterator<Item = ProtocolOverride>) -> Self {
+        Self(overrides.into_iter().collect())
+    }
+
+    /// Returns the protocol forced by the first matching override, if any.
+    pub fn get(&self, target: &OverrideTarget) -> Option<ForcedProtocol> {
+        self.0
+            .iter()
+            .find(|o| o.matches(target))
+            .map(|o| o.protocol)
+    }
+}
+
+impl Default for ProtocolOverrides {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+// === impl ProtocolOverride ===
+
+impl ProtocolOverride {
+    fn matches(&self, target: &OverrideTarget) -> bool {
+        if let Some(ports) = self.ports.as_ref() {
+            if !target.port().map_or(false, |p| ports.contains(&p)) {
+                return false;
+            }
+        }
+
+        let name_matches = target
+            .name
+            .as_ref()
+            .map_or(false, |n| self.dst.names().matches(n.name()));
+        let addr_matches = target.addr.map_or(false, |a| self.dst.matches_ip(a.ip()));
+        name_matches || addr_matches
+    }
+}
+
+// === impl OverrideTarget ===
+
+impl OverrideTarget {
+    /// The target's port, preferring its endpoint's address over its logical
+    /// name.
+    pub fn port(&self) -> Option<u16> {
+        self.addr
+            .map(|a| a.port())
+            .or_else(|| self.name.as_ref().map(|n| n.port()))
+    }
+
+    /// The address by which protocol hints are learned for the target,
+    /// preferring its logical name over its endpoint's address.
+    fn hint_addr(&self) -> Option<Addr> {
+        self.name
+            .clone()
+            .map(Addr::from)
+            .or_else(|| self.addr.map(Addr::from))
+    }
+
+    fn learned_protocol(&self, hints: &ProtocolHints) -> Option<protocol_hints::Protocol> {
+        hints.get(&self.hint_addr()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::{dns, IpMatch, IpNet, NameMatch};
+    use std::str::FromStr;
+
+    #[test]
+    fn first_matching_override_applies() {
+        let overrides = ProtocolOverrides::new(vec![
+            ProtocolOverride {
+                protocol: ForcedProtocol::Opaque,
+                dst: IpMatch::new(Some(IpNet::from_str("10.0.0.0/8").unwrap())).into(),
+                ports: Some(4222..=4222),
+            },
+            ProtocolOverride {
+                protocol: ForcedProtocol::Http1,
+                dst: NameMatch::new(Some(dns::Suffix::from_str("legacy.example.com").unwrap()))
+                    .into(),
+                ports: None,
+            },
+        ]);
+        let target = |name: Option<&str>, addr: Option<&str>| OverrideTarget {
+            name: name.map(|n| NameAddr::from_str(n).unwrap()),
+            addr: addr.map(|a| a.parse().unwrap()),
+        };
+
+        assert_eq!(
+            overrides.get(&target(None, Some("10.1.2.3:4222"))),
+            Some(ForcedProtocol::Opaque)
+        );
+        assert_eq!(overrides.get(&target(None, Some("10.1.2.3:80"))), None);
+        assert_eq!(
+            overrides.get(&target(Some("legacy.example.com:80"), Some("192.0.2.1:80"))),
+            Some(ForcedProtocol::Http1)
+        );
+        assert_eq!(
+            overrides.get(&target(Some("web.example.com:80"), None)),
+            None
+        );
+        assert_eq!(
+            ProtocolOverrides::default().get(&target(None, Some("10.1.2.3:4222"))),
+            None
+        );
+    }
+
+    #[test]
+    fn target_ports() {
+        let target = |name: Option<&str>, addr: Option<&str>| OverrideTarget {
+            name: name.map(|n| NameAddr::from_str(n).unwrap()),
+            addr: addr.map(|a| a.parse().unwrap()),
+        };
+        assert_eq!(
+            target(Some("mysql.example.com:3306"), Some("192.0.2.1:3307")).port(),
+            Some(3307)
+        );
+        assert_eq!(target(Some("smtp.example.com:25"), None).port(), Some(25));
+        assert_eq!(target(None, None).port(), None);
+    }
+}
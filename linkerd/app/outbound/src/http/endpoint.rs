@@ -1,9 +1,13 @@
 use super::require_id_header;
 use crate::Outbound;
 use linkerd_app_core::{
-    classify, config, http_tracing, metrics,
+    classify, compress, config, http_tracing, metrics,
     proxy::{http, tap},
-    svc, tls, Error, CANONICAL_DST_HEADER,
+    svc::{
+        self,
+        extension::{Direction, Point},
+    },
+    tls, Error, CANONICAL_DST_HEADER,
 };
 use tokio::io;
 
@@ -36,9 +40,18 @@ impl<C> Outbound<C> {
             // HTTP/1.x fallback is supported as needed.
             connect
                 .push(http::client::layer(h1_settings, h2_settings))
-                .push_on_response(svc::MapErrLayer::new(Into::<Error>::into))
+                .push_on_response(
+                    svc::layers()
+                        .push(svc::MapErrLayer::new(Into::<Error>::into))
+                        // Records whether requests reuse the endpoint's
+                        // connection in their spans, if configured.
+                        .push(http_tracing::SetConnectionReused::layer()),
+                )
                 .check_service::<T>()
                 .into_new_service()
+                // Retires HTTP/2 connections that have exceeded their maximum
+                // age or number of requests so that traffic is rebalanced.
+                .push(http::NewRetire::layer(h2_settings))
                 .push_new_reconnect(backoff)
                 .push(tap::NewTapHttp::layer(rt.tap.clone()))
                 .push(
@@ -46,8 +59,16 @@ impl<C> Outbound<C> {
                         .http_endpoint
                         .to_layer::<classify::Response, _, _>(),
                 )
+                .push(http_tracing::NewSetAttribute::layer(
+                    http_tracing::Attribute::PeerIdentity,
+                    |t: &T| {
+                        let tls = svc::Param::<tls::ConditionalClientTls>::param(t);
+                        tls.value().map(|tls| tls.server_id.clone())
+                    },
+                ))
                 .push_on_response(http_tracing::client(
                     rt.span_sink.clone(),
+                    config.proxy.trace_attributes.clone(),
                     crate::trace_labels(),
                 ))
                 .push(require_id_header::NewRequireIdentity::layer())
@@ -57,7 +78,17 @@ impl<C> Outbound<C> {
                 ]))
                 .push_on_response(
                     svc::layers()
+                        // Deducts the time spent in this proxy from the
+                        // deadline headers of requests, if enabled.
+                        .push(http::deadline::Propagate::layer(
+                            config.http_deadline.clone(),
+                        ))
+                        // Requests compressed responses and decodes those
+                        // with encodings the application didn't accept, if
+                        // enabled.
+                        .push(compress::Decompress::layer(config.decompression))
                         .push(http::BoxResponse::layer())
+                        .push(rt.extensions.layer(Direction::Out, Point::Endpoint))
                         .push(svc::BoxService::layer()),
                 )
                 .push(svc::BoxNewService::layer())
@@ -103,6 +134,7 @@ mod test {
             opaque_protocol: false,
             tls: tls::ConditionalClientTls::None(tls::NoClientTls::Disabled),
             metadata: Metadata::default(),
+            metadata_labels: Default::default(),
         });
 
         let req = http::Request::builder()
@@ -139,6 +171,7 @@ mod test {
             opaque_protocol: false,
             tls: tls::ConditionalClientTls::None(tls::NoClientTls::Disabled),
             metadata: Metadata::default(),
+            metadata_labels: Default::default(),
         });
 
         let req = http::Request::builder()
@@ -183,6 +216,7 @@ mod test {
                 None,
                 None,
             ),
+            metadata_labels: Default::default(),
         });
 
         let req = http::Request::builder()
@@ -200,6 +234,49 @@ mod test {
         );
     }
 
+    /// Tests that meshed endpoints advertise the protocol they'll be sent via
+    /// ALPN.
+    #[test]
+    fn alpn_from_protocol_hint() {
+        let alpn = |protocol, hint| {
+            let ep = http::Endpoint {
+                addr: Remote(ServerAddr(SocketAddr::new([192, 0, 2, 41].into(), 2041))),
+                protocol,
+                logical_addr: None,
+                opaque_protocol: false,
+                tls: tls::ConditionalClientTls::Some(tls::ClientTls {
+                    server_id: "foo.ns.serviceaccount.identity.linkerd.cluster.local"
+                        .parse()
+                        .unwrap(),
+                    alpn: None,
+                }),
+                metadata: Metadata::new(None, hint, None, None, None),
+                metadata_labels: Default::default(),
+            };
+            match svc::Param::<tls::ConditionalClientTls>::param(&ep) {
+                tls::ConditionalClientTls::Some(tls::ClientTls {
+                    alpn: Some(tls::client::AlpnProtocols(protocols)),
+                    ..
+                }) => protocols,
+                tls => panic!("unexpected client TLS: {:?}", tls),
+            }
+        };
+
+        use support::resolver::ProtocolHint;
+        assert_eq!(
+            alpn(http::Version::Http1, ProtocolHint::Unknown),
+            vec![b"http/1.1".to_vec()]
+        );
+        assert_eq!(
+            alpn(http::Version::Http1, ProtocolHint::Http2),
+            vec![b"h2".to_vec()]
+        );
+        assert_eq!(
+            alpn(http::Version::H2, ProtocolHint::Unknown),
+            vec![b"h2".to_vec()]
+        );
+    }
+
     /// Tests that the the HTTP endpoint stack ignores protocol upgrade hinting for HTTP/2 traffic.
     #[tokio::test(flavor = "current_thread")]
     async fn orig_proto_http2_noop() {
@@ -231,6 +308,7 @@ mod test {
                 None,
                 None,
             ),
+            metadata_labels: Default::default(),
         });
 
         let req = http::Request::builder()
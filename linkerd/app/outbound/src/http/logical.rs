@@ -1,14 +1,20 @@
 use super::{CanonicalDstHeader, Concrete, Endpoint, Logical};
-use crate::{endpoint, resolve, stack_labels, Outbound};
+use crate::{endpoint, failover, resolve, stack_labels, Outbound};
 use linkerd_app_core::{
-    classify, config, dst, profiles,
+    classify, config, dst, http_cache, http_metrics, http_tracing, idempotency, profiles,
     proxy::{
-        api_resolve::{ConcreteAddr, Metadata},
+        api_resolve::{self, ConcreteAddr, Metadata},
         core::Resolve,
         http,
         resolve::map_endpoint,
     },
-    retry, svc, Error, Infallible,
+    retry,
+    svc::{
+        self,
+        extension::{Direction, Point},
+        Layer,
+    },
+    Error, Infallible,
 };
 use tracing::debug_span;
 
@@ -31,21 +37,45 @@ impl<E> Outbound<E> {
         self.map_stack(|config, rt, endpoint| {
             let config::ProxyConfig {
                 buffer_capacity,
-                cache_max_idle_age,
                 dispatch_timeout,
+                queue_time_header,
+                fail_fast_retry_after,
                 ..
             } = config.proxy;
-            let watchdog = cache_max_idle_age * 2;
+            let idle_age = config.cache_idle_ages.balancer;
+            let watchdog = idle_age * 2;
+            let affinity = config.http_affinity.clone();
+            let split_overrides = profiles::split::OverrideHeader::new(config.split_overrides);
+            let route_defaults = config.http_route_defaults.clone();
+            // These caches are shared by all of this stack's routes.
+            let idempotency_cache = config
+                .http_idempotency
+                .map(|config| idempotency::Cache::new(config, rt.metrics.http_idempotency.clone()));
+            let response_cache = config.http_response_cache.map(|config| {
+                http_cache::Cache::new(config, rt.metrics.http_response_cache.clone())
+            });
 
-            let endpoint =
-                endpoint.instrument(|e: &Endpoint| debug_span!("endpoint", server.addr = %e.addr));
+            let endpoint = endpoint.instrument(|e: &Endpoint| {
+                let span = debug_span!(
+                    "endpoint",
+                    server.addr = %e.addr,
+                    server.labels = tracing::field::Empty,
+                );
+                e.record_labels(&span);
+                span
+            });
 
-            let identity_disabled = rt.identity.is_none();
+            let from_metadata = endpoint::FromMetadata {
+                identity_disabled: rt.identity.is_none(),
+                metadata_labels: config.metadata_labels.clone(),
+            };
             let resolve = svc::stack(resolve.into_service())
                 .check_service::<ConcreteAddr>()
+                // Merges the IPv6 and IPv4 addresses of dual-stack endpoints.
+                .push(api_resolve::DualStack::layer())
                 .push_request_filter(|c: Concrete| Ok::<_, Infallible>(c.resolve))
                 .push(svc::layer::mk(move |inner| {
-                    map_endpoint::Resolve::new(endpoint::FromMetadata { identity_disabled }, inner)
+                    map_endpoint::Resolve::new(from_metadata.clone(), inner)
                 }))
                 .check_service::<Concrete>()
                 .into_inner();
@@ -63,7 +93,9 @@ impl<E> Outbound<E> {
                         )
                         // Ensure individual endpoints are driven to readiness so that
                         // the balancer need not drive them all directly.
-                        .push(svc::layer::mk(svc::SpawnReady::new)),
+                        .push(svc::layer::mk(svc::SpawnReady::new))
+                        // Traces the time spent connecting to each endpoint.
+                        .push(http_tracing::ReadySpan::layer("connect")),
                 )
                 .check_new_service::<Endpoint, http::Request<_>>()
                 // Resolve the service to its endpoints and balance requests over them.
@@ -74,23 +106,70 @@ impl<E> Outbound<E> {
                 .push(resolve::layer(resolve, watchdog))
                 .push_on_response(
                     svc::layers()
-                        .push(http::balance::layer(
-                            crate::EWMA_DEFAULT_RTT,
-                            crate::EWMA_DECAY,
-                        ))
+                        // Balances requests with session affinity, if
+                        // configured.
+                        .push(svc::layer::mk(move |discover| match affinity.clone() {
+                            Some(config) => svc::Either::B(
+                                http::balance::affinity::layer(
+                                    crate::EWMA_DEFAULT_RTT,
+                                    crate::EWMA_DECAY,
+                                    config,
+                                )
+                                .layer(discover),
+                            ),
+                            None => svc::Either::A(
+                                http::balance::layer(crate::EWMA_DEFAULT_RTT, crate::EWMA_DECAY)
+                                    .layer(discover),
+                            ),
+                        }))
                         .push(rt.metrics.stack.layer(stack_labels("http", "balancer")))
                         .push(svc::layer::mk(svc::SpawnReady::new))
-                        .push(svc::FailFast::layer("HTTP Balancer", dispatch_timeout))
-                        .push(http::BoxResponse::layer()),
+                        // Traces the time spent resolving the balancer's
+                        // endpoints until one of them is ready.
+                        .push(http_tracing::ReadySpan::layer("resolve")),
                 )
                 .check_make_service::<Concrete, http::Request<_>>()
                 .push(svc::MapErrLayer::new(Into::into))
                 // Drives the initial resolution via the service's readiness.
                 .into_new_service()
+                // If the local balancer has been unavailable, shift requests to
+                // the balancer for the remote cluster's mirrored service. This
+                // must be done before failfast is applied so that the failover
+                // can observe the local balancer's readiness.
+                .push(failover::NewFailover::layer(
+                    config.failover.clone(),
+                    rt.metrics.failover.clone(),
+                ))
+                .push_on_response(
+                    svc::layers()
+                        .push(svc::FailFast::layer("HTTP Balancer", dispatch_timeout))
+                        .push(http::BoxResponse::layer())
+                        .push_spawn_buffer(buffer_capacity),
+                )
                 // The concrete address is only set when the profile could be
                 // resolved. Endpoint resolution is skipped when there is no
                 // concrete address.
                 .instrument(|c: &Concrete| debug_span!("concrete", addr = %c.resolve))
+                // Balancers are cached independently of the traffic split so
+                // that profile updates, which may remove and re-add a concrete
+                // target, don't discard a balancer's resolution and endpoint
+                // connections while it is still in use.
+                .push_cache(
+                    idle_age,
+                    rt.metrics.cache.metrics(stack_labels("http", "concrete")),
+                )
+                // When enabled, responses describe the concrete target that the
+                // split chose to aid debugging.
+                .push(profiles::split::NewConcreteHeader::layer(
+                    config.split_concrete_header,
+                    split_overrides,
+                ))
+                // Records the concrete destination in request spans, if
+                // configured.
+                .push(http_tracing::NewSetAttribute::layer(
+                    http_tracing::Attribute::ConcreteDst,
+                    |c: &Concrete| Some(c.resolve.clone()),
+                ))
                 .push_map_target(Concrete::from)
                 .push(svc::BoxNewService::layer())
                 // Distribute requests over a distribution of balancers via a
@@ -99,19 +178,45 @@ impl<E> Outbound<E> {
                 // If the traffic split is empty/unavailable, eagerly fail requests.
                 // When the split is in failfast, spawn the service in a background
                 // task so it becomes ready without new requests.
+                //
+                // When enabled, individual requests may override the split's
+                // weights.
+                //
+                // When configured, targets that fail consistently are
+                // temporarily isolated from the split.
                 .check_new_service::<(ConcreteAddr, Logical), _>()
-                .push(profiles::split::layer())
+                .push(profiles::split::layer_with_isolation(
+                    split_overrides,
+                    config.split_isolation,
+                    rt.metrics.split.clone(),
+                ))
                 .push_on_response(
                     svc::layers()
+                        .push(
+                            rt.metrics
+                                .http_queue_time
+                                .layer(stack_labels("http", "logical"), queue_time_header),
+                        )
                         .push(svc::layer::mk(svc::SpawnReady::new))
                         .push(rt.metrics.stack.layer(stack_labels("http", "logical")))
-                        .push(svc::FailFast::layer("HTTP Logical", dispatch_timeout))
-                        .push_spawn_buffer(buffer_capacity),
+                        .push(svc::FailFast::layer_with_retry_after(
+                            "HTTP Logical",
+                            dispatch_timeout,
+                            fail_fast_retry_after.then(|| dispatch_timeout),
+                        ))
+                        .push_spawn_buffer(buffer_capacity)
+                        .push(http_metrics::queue_time::Enqueue::layer()),
+                )
+                .push_cache(
+                    idle_age,
+                    rt.metrics.cache.metrics(stack_labels("http", "logical")),
                 )
-                .push_cache(cache_max_idle_age)
                 // Note: routes can't exert backpressure.
                 .push(profiles::http::route_request::layer(
                     svc::proxies()
+                        // Sets the route as a request extension so that the
+                        // endpoint's `tap::Inspect::route_labels` can read it.
+                        .push_http_insert_target::<dst::Route>()
                         .push(
                             rt.metrics
                                 .http_route_actual
@@ -123,26 +228,164 @@ impl<E> Outbound<E> {
                         // stack doesn't have to implement `Service` for requests
                         // with both body types.
                         .push_on_response(http::BoxRequest::erased())
+                        // Sets an optional timeout on each attempt of a request.
+                        .push(http::timeout::NewPerTry::layer())
                         // Sets an optional retry policy.
                         .push(retry::layer(rt.metrics.http_route_retry.clone()))
+                        // Injects the route's faults, if any. Injected aborts
+                        // are not retried but are recorded in route metrics.
+                        .push(profiles::http::NewInjectFault::layer())
+                        // Replays responses to requests that repeat an
+                        // idempotency key, if the route deduplicates requests.
+                        .push(idempotency::NewIdempotent::layer(idempotency_cache))
+                        // Answers GET requests from the response cache, if the
+                        // route enables caching.
+                        .push(http_cache::NewCacheResponses::layer(response_cache))
                         // Sets an optional request timeout.
                         .push(http::MakeTimeoutLayer::default())
+                        // Fails requests whose responses make no progress
+                        // within an optional idle timeout.
+                        .push(http::timeout::NewIdle::layer())
                         // Records per-route metrics.
                         .push(rt.metrics.http_route.to_layer::<classify::Response, _, _>())
                         // Sets the per-route response classifier as a request
                         // extension.
                         .push(classify::NewClassify::layer())
-                        .push_map_target(Logical::mk_route)
+                        // Applies the route's request and response header
+                        // modifications.
+                        .push(profiles::http::NewModifyHeaders::layer())
+                        // Rewrites the request path if the route configures a
+                        // prefix rewrite.
+                        .push(profiles::http::NewRewritePath::layer())
+                        // Records the route in request spans, if configured.
+                        // This is above the retry layer so that retried
+                        // requests inherit it.
+                        .push(http_tracing::NewSetAttribute::layer(
+                            http_tracing::Attribute::Route,
+                            |r: &dst::Route| r.route.labels().get("route").cloned(),
+                        ))
+                        .push_map_target(
+                            move |(route, logical): (profiles::http::Route, Logical)| {
+                                Logical::mk_route((route_defaults.apply(route), logical))
+                            },
+                        )
                         .into_inner(),
                 ))
                 // Strips headers that may be set by this proxy and add an outbound
                 // canonical-dst-header. The response body is boxed unify the profile
                 // stack's response type with that of to endpoint stack.
                 .push(http::NewHeaderFromTarget::<CanonicalDstHeader, _>::layer())
+                // Records the logical destination in request spans, if
+                // configured.
+                .push(http_tracing::NewSetAttribute::layer(
+                    http_tracing::Attribute::LogicalDst,
+                    |l: &Logical| Some(l.logical_addr.clone()),
+                ))
                 .push_on_response(svc::layers().push(http::BoxResponse::layer()))
                 .instrument(|l: &Logical| debug_span!("logical", dst = %l.logical_addr))
-                .push_on_response(svc::BoxService::layer())
+                .push_on_response(
+                    svc::layers()
+                        .push(rt.extensions.layer(Direction::Out, Point::Logical))
+                        .push(svc::BoxService::layer()),
+                )
                 .push(svc::BoxNewService::layer())
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use linkerd_app_core::{
+        profiles::{LogicalAddr, Profile, Target},
+        svc::{NewService, Service, ServiceExt},
+        NameAddr,
+    };
+    use std::net::SocketAddr;
+    use tokio::{sync::watch, time};
+
+    /// Tests that a concrete balancer is reused by the logical targets that
+    /// a traffic split builds as it is updated, so that a concrete target
+    /// that is removed from the split and added back keeps its resolution.
+    ///
+    /// The mock resolver only resolves each concrete address once, so a
+    /// rebuilt balancer would fail to discover endpoints.
+    #[tokio::test(flavor = "current_thread")]
+    async fn reuses_concrete_balancer() {
+        let _trace = linkerd_tracing::test::trace_init();
+        time::pause();
+
+        let logical_addr = LogicalAddr("logical.example.com:8080".parse().unwrap());
+        let a_addr = "a.example.com:8080".parse::<NameAddr>().unwrap();
+        let b_addr = "b.example.com:8080".parse::<NameAddr>().unwrap();
+        let a_ep = SocketAddr::new([192, 0, 2, 10].into(), 8080);
+        let b_ep = SocketAddr::new([192, 0, 2, 20].into(), 8080);
+        let resolve = support::resolver()
+            .endpoint_exists(a_addr.clone(), a_ep, Default::default())
+            .endpoint_exists(b_addr.clone(), b_ep, Default::default());
+        let resolved = resolve.handle();
+
+        let split = |addr: &NameAddr| Profile {
+            addr: Some(logical_addr.clone()),
+            targets: vec![Target {
+                addr: addr.clone(),
+                weight: 1,
+            }],
+            ..Default::default()
+        };
+        let (profile_tx, profile) = watch::channel(split(&a_addr));
+        let logical = Logical {
+            profile: profile.into(),
+            logical_addr: logical_addr.clone(),
+            protocol: http::Version::Http1,
+        };
+
+        // Each endpoint identifies itself in its responses.
+        let (rt, _shutdown) = runtime();
+        let mut stack = Outbound::new(default_config(), rt)
+            .with_stack(|ep: Endpoint| {
+                let addr: SocketAddr = *ep.addr.as_ref();
+                svc::mk(move |_: http::Request<http::BoxBody>| {
+                    let rsp = http::Response::builder()
+                        .header("x-endpoint", addr.to_string())
+                        .body(http::BoxBody::default())
+                        .unwrap();
+                    future::ok::<_, Error>(rsp)
+                })
+            })
+            .push_http_logical::<http::BoxBody, _, _>(resolve)
+            .into_inner();
+        let mut svc = stack.new_service(logical);
+
+        assert_eq!(send(&mut svc).await, a_ep.to_string());
+
+        profile_tx.send(split(&b_addr)).unwrap();
+        assert_eq!(send(&mut svc).await, b_ep.to_string());
+
+        profile_tx.send(split(&a_addr)).unwrap();
+        assert_eq!(send(&mut svc).await, a_ep.to_string());
+        assert!(resolved.only_configured(), "balancer was not reused");
+    }
+
+    /// Sends a request, returning the address of the endpoint that served it.
+    async fn send(svc: &mut svc::BoxHttp) -> String {
+        let req = http::Request::builder()
+            .uri("http://logical.example.com:8080/")
+            .body(http::BoxBody::default())
+            .unwrap();
+        let rsp = svc
+            .ready()
+            .await
+            .expect("service must become ready")
+            .call(req)
+            .await
+            .expect("request must succeed");
+        rsp.headers()
+            .get("x-endpoint")
+            .expect("response must identify its endpoint")
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+}
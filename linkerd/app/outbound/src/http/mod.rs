@@ -3,8 +3,11 @@ mod endpoint;
 pub mod logical;
 mod peer_proxy_errors;
 mod require_id_header;
+mod route_defaults;
 mod server;
 
+pub use self::route_defaults::RouteDefaults;
+
 use crate::tcp;
 pub use linkerd_app_core::proxy::http::*;
 use linkerd_app_core::{
@@ -110,6 +113,7 @@ impl From<(Version, tcp::Endpoint)> for Endpoint {
             logical_addr: ep.logical_addr,
             // If we know an HTTP version, the protocol must not be opaque.
             opaque_protocol: false,
+            metadata_labels: ep.metadata_labels,
         }
     }
 }
@@ -148,6 +152,25 @@ impl Param<client::Settings> for Endpoint {
     }
 }
 
+impl Param<tls::ConditionalClientTls> for Endpoint {
+    fn param(&self) -> tls::ConditionalClientTls {
+        // Advertise the session protocol via ALPN so that the peer's proxy
+        // need not detect it, unless ALPN is already used to negotiate a
+        // transport header.
+        let version = match Param::<Option<SessionProtocol>>::param(self) {
+            Some(SessionProtocol::Http2) => Version::H2,
+            _ => Version::Http1,
+        };
+        self.tls.clone().map(|tls| match tls.alpn {
+            Some(_) => tls,
+            None => tls::ClientTls {
+                alpn: Some(tls::client::AlpnProtocols(vec![version.alpn().into()])),
+                ..tls
+            },
+        })
+    }
+}
+
 impl Param<Option<SessionProtocol>> for Endpoint {
     fn param(&self) -> Option<SessionProtocol> {
         match self.protocol {
@@ -0,0 +1,129 @@
+use linkerd_app_core::profiles;
+use std::time::Duration;
+
+/// Route settings that the destination controller does not describe, applied
+/// to each route of an outbound service profile.
+#[derive(Clone, Debug, Default)]
+pub struct RouteDefaults {
+    /// When set, faults are injected into requests.
+    pub fault: Option<profiles::http::Fault>,
+
+    /// When set, limits the time allowed for each attempt of a request.
+    pub per_try_timeout: Option<Duration>,
+
+    /// When set, limits the time allowed without progress on a response
+    /// stream.
+    pub idle_timeout: Option<Duration>,
+
+    /// When set, responses to requests with an idempotency key are replayed
+    /// to requests that repeat the key for this long.
+    pub idempotency_ttl: Option<Duration>,
+
+    /// When set, responses to GET requests may be cached.
+    pub cache_responses: bool,
+}
+
+// === impl RouteDefaults ===
+
+impl RouteDefaults {
+    /// Configures the route with each default that the route does not set.
+    pub fn apply(&self, mut route: profiles::http::Route) -> profiles::http::Route {
+        if route.fault().is_none() {
+            if let Some(fault) = self.fault.as_ref() {
+                route.set_fault(fault.clone());
+            }
+        }
+        if let (None, Some(timeout)) = (route.per_try_timeout(), self.per_try_timeout) {
+            route.set_per_try_timeout(timeout);
+        }
+        if let (None, Some(timeout)) = (route.idle_timeout(), self.idle_timeout) {
+            route.set_idle_timeout(timeout);
+        }
+        if let (None, Some(ttl)) = (route.idempotency_ttl(), self.idempotency_ttl) {
+            route.set_idempotency_ttl(ttl);
+        }
+        if self.cache_responses {
+            route.set_cache_responses(true);
+        }
+        route
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route() -> profiles::http::Route {
+        profiles::http::Route::new(std::iter::empty(), Vec::new())
+    }
+
+    #[test]
+    fn empty_defaults_leave_route_unchanged() {
+        assert_eq!(RouteDefaults::default().apply(route()), route());
+    }
+
+    #[test]
+    fn sets_idempotency_ttl() {
+        let defaults = RouteDefaults {
+            idempotency_ttl: Some(Duration::from_secs(30)),
+            ..Default::default()
+        };
+        assert_eq!(
+            defaults.apply(route()).idempotency_ttl(),
+            Some(Duration::from_secs(30))
+        );
+
+        let mut configured = route();
+        configured.set_idempotency_ttl(Duration::from_secs(5));
+        assert_eq!(
+            defaults.apply(configured).idempotency_ttl(),
+            Some(Duration::from_secs(5)),
+            "the route's own TTL takes precedence"
+        );
+    }
+
+    #[test]
+    fn enables_response_caching() {
+        let defaults = RouteDefaults {
+            cache_responses: true,
+            ..Default::default()
+        };
+        assert!(defaults.apply(route()).cache_responses());
+        assert!(!RouteDefaults::default().apply(route()).cache_responses());
+    }
+
+    #[test]
+    fn sets_timeouts() {
+        let defaults = RouteDefaults {
+            per_try_timeout: Some(Duration::from_secs(1)),
+            idle_timeout: Some(Duration::from_secs(2)),
+            ..Default::default()
+        };
+        let mut configured = route();
+        configured.set_timeout(Duration::from_secs(10));
+        let route = defaults.apply(configured);
+        assert_eq!(route.timeout(), Some(Duration::from_secs(10)));
+        assert_eq!(route.per_try_timeout(), Some(Duration::from_secs(1)));
+        assert_eq!(route.idle_timeout(), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn sets_fault() {
+        let fault = profiles::http::Fault::default()
+            .with_abort(crate::http::StatusCode::SERVICE_UNAVAILABLE, 0.5);
+        let defaults = RouteDefaults {
+            fault: Some(fault.clone()),
+            ..Default::default()
+        };
+        assert_eq!(defaults.apply(route()).fault(), Some(&fault));
+
+        let mut configured = route();
+        let delay = profiles::http::Fault::default().with_delay(Duration::from_secs(1), 1.0);
+        configured.set_fault(delay.clone());
+        assert_eq!(
+            defaults.apply(configured).fault(),
+            Some(&delay),
+            "the route's own fault takes precedence"
+        );
+    }
+}
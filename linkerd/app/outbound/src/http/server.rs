@@ -1,6 +1,13 @@
 use super::peer_proxy_errors::PeerProxyErrors;
-use crate::{http, trace_labels, Outbound};
-use linkerd_app_core::{config, errors, http_tracing, svc, Error};
+use crate::{http, stack_labels, trace_labels, Outbound};
+use linkerd_app_core::{
+    config, errors, http_metrics, http_tracing, request_id, request_limits,
+    svc::{
+        self,
+        extension::{Direction, Point},
+    },
+    Error,
+};
 
 impl<N> Outbound<N> {
     pub fn push_http_server<T, NSvc>(
@@ -29,12 +36,27 @@ impl<N> Outbound<N> {
                 dispatch_timeout,
                 max_in_flight_requests,
                 buffer_capacity,
+                queue_time_header,
+                fail_fast_retry_after,
+                error_responses,
+                request_limits,
                 ..
             } = config.proxy;
+            let request_id = config.proxy.request_id.clone();
+            #[cfg(feature = "wasm")]
+            let wasm_filters =
+                linkerd_app_core::http_wasm::FilterHttp::layer(config.http_wasm.clone());
+            #[cfg(not(feature = "wasm"))]
+            let wasm_filters = svc::layers();
 
             http.check_new_service::<T, _>()
                 .push_on_response(
                     svc::layers()
+                        .push(
+                            rt.metrics
+                                .http_queue_time
+                                .layer(stack_labels("http", "server"), queue_time_header),
+                        )
                         .push(http::BoxRequest::layer())
                         // Limit the number of in-flight requests. When the proxy is
                         // at capacity, go into failfast after a dispatch timeout. If
@@ -43,16 +65,40 @@ impl<N> Outbound<N> {
                         // requests being processed.
                         .push(svc::layer::mk(svc::SpawnReady::new))
                         .push(svc::ConcurrencyLimitLayer::new(max_in_flight_requests))
-                        .push(svc::FailFast::layer("HTTP Server", dispatch_timeout))
+                        .push(svc::FailFast::layer_with_retry_after(
+                            "HTTP Server",
+                            dispatch_timeout,
+                            fail_fast_retry_after.then(|| dispatch_timeout),
+                        ))
                         .push_spawn_buffer(buffer_capacity)
+                        .push(http_metrics::queue_time::Enqueue::layer())
+                        // Applies WASM filters, if configured.
+                        .push(wasm_filters)
+                        // Fails requests that are not answered before the
+                        // deadline set by their headers, if enabled.
+                        .push(http::deadline::Enforce::layer(config.http_deadline.clone()))
+                        // Rejects requests with heads that exceed the
+                        // configured limits.
+                        .push(request_limits::EnforceLimits::layer(request_limits))
                         .push(rt.metrics.http_errors.clone())
                         // Tear down server connections when a peer proxy generates an error.
                         .push(PeerProxyErrors::layer())
                         // Synthesizes responses for proxy errors.
-                        .push(errors::layer())
+                        .push(errors::layer(error_responses))
+                        // Records the ID of each request sent by the
+                        // application, if configured.
+                        .push(request_id::SetRequestId::layer(request_id))
+                        // Records whether requests reuse the application's
+                        // connection in their spans, if configured.
+                        .push(http_tracing::SetConnectionReused::layer())
                         // Initiates OpenCensus tracing.
-                        .push(http_tracing::server(rt.span_sink.clone(), trace_labels()))
-                        .push(http::BoxResponse::layer()),
+                        .push(http_tracing::server(
+                            rt.span_sink.clone(),
+                            config.proxy.trace_attributes.clone(),
+                            trace_labels(),
+                        ))
+                        .push(http::BoxResponse::layer())
+                        .push(rt.extensions.layer(Direction::Out, Point::ServerHttp)),
                 )
                 // Convert origin form HTTP/1 URIs to absolute form for Hyper's
                 // `Client`.
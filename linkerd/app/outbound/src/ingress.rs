@@ -1,19 +1,29 @@
 use crate::{http, stack_labels, tcp, trace_labels, Config, Outbound};
 use linkerd_app_core::{
     config::{ProxyConfig, ServerConfig},
-    detect, errors, http_tracing, io, profiles,
+    detect, errors, http_metrics, http_tracing, io, profiles,
     proxy::{
         api_resolve::{ConcreteAddr, Metadata},
         core::Resolve,
     },
+    request_id, request_limits,
     svc::{self, stack::Param},
     tls,
     transport::{OrigDstAddr, Remote, ServerAddr},
-    AddrMatch, Error, Infallible, NameAddr,
+    AddrMatch, Error, Infallible, NameAddr, NameMatch,
 };
+use std::sync::Arc;
 use thiserror::Error;
 use tracing::{debug_span, info_span};
 
+#[cfg(feature = "ingress-script")]
+mod script;
+mod tenant;
+
+#[cfg(feature = "ingress-script")]
+pub use self::script::{RouteScript, ScriptError};
+pub use self::tenant::{TenantConfig, Tenants};
+
 #[derive(Clone)]
 struct AllowHttpProfile(AddrMatch);
 
@@ -26,7 +36,17 @@ struct Http<T> {
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 enum Target {
     Forward(OrigDstAddr),
-    Override(NameAddr),
+    Override(Override),
+}
+
+/// A logical destination for ingress requests.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Override {
+    dst: NameAddr,
+    /// Set when the destination was read from the request's authority rather
+    /// than the override header. Requests are forwarded to the original
+    /// destination if the destination has no profile.
+    fallback: Option<OrigDstAddr>,
 }
 
 #[derive(Debug, Error)]
@@ -48,6 +68,11 @@ const DST_OVERRIDE_HEADER: &str = "l5d-dst-override";
 impl Outbound<svc::BoxNewHttp<http::Endpoint>> {
     /// Routes HTTP requests according to the l5d-dst-override header.
     ///
+    /// Requests without the header are routed to the destination selected by
+    /// the ingress routing script, if one is configured. Otherwise, they are
+    /// routed by their authority when it is in a discoverable domain, and are
+    /// otherwise forwarded to their original destination.
+    ///
     /// This is only intended for Ingress configurations, where we assume all
     /// outbound traffic is HTTP.
     pub fn into_ingress<T, I, P, R>(self, profiles: P, resolve: R) -> svc::BoxNewTcp<T, I>
@@ -71,51 +96,72 @@ impl Outbound<svc::BoxNewHttp<http::Endpoint>> {
         let http_endpoint = self.into_stack();
 
         let detect_http = config.proxy.detect_http();
+        #[cfg(feature = "ingress-script")]
+        let ingress_script = config.ingress_script.clone();
         let Config {
             allow_discovery,
+            cache_idle_ages,
+            ingress_tenants,
             proxy:
                 ProxyConfig {
-                    server: ServerConfig { h2_settings, .. },
+                    server:
+                        ServerConfig {
+                            h1_settings,
+                            h2_settings,
+                            ..
+                        },
                     dispatch_timeout,
                     max_in_flight_requests,
                     buffer_capacity,
-                    cache_max_idle_age,
+                    queue_time_header,
+                    fail_fast_retry_after,
+                    error_responses,
+                    request_limits,
+                    request_id,
+                    trace_attributes,
                     ..
                 },
             ..
         } = config;
         let profile_domains = allow_discovery.names().clone();
+        let host_domains = profile_domains.clone();
+        let retry_after = fail_fast_retry_after.then(|| dispatch_timeout);
 
         http_logical
-            // If a profile was discovered, use it to build a logical stack. Otherwise, the override
-            // header was present but no profile information could be discovered, so fail the
-            // request.
-            .push_request_filter(
-                |(profile, http): (Option<profiles::Receiver>, Http<NameAddr>)| {
+            // If a profile was discovered, use it to build a logical stack. Otherwise, if the
+            // target was read from the request's authority, forward the request to its original
+            // destination. If the override header was present but no profile information could be
+            // discovered, fail the request.
+            .push_switch(
+                |(profile, http): (Option<profiles::Receiver>, Http<Override>)| {
                     if let Some(profile) = profile {
                         if let Some(logical_addr) = profile.logical_addr() {
-                            return Ok(http::Logical {
+                            return Ok(svc::Either::A(http::Logical {
                                 profile,
                                 logical_addr,
                                 protocol: http.version,
-                            });
+                            }));
                         }
                     }
 
-                    Err(ProfileRequired)
+                    match http.target.fallback {
+                        Some(orig_dst) => Ok(svc::Either::B(forward(orig_dst, http.version))),
+                        None => Err(ProfileRequired),
+                    }
                 },
+                http_endpoint.clone().into_inner(),
             )
             .push(profiles::discover::layer(
                 profiles,
-                move |h: Http<NameAddr>| {
+                move |h: Http<Override>| {
                     // Lookup the profile if the override header was set and it is in the configured
                     // profile domains. Otherwise, profile discovery is skipped.
-                    if profile_domains.matches(h.target.name()) {
-                        return Ok(profiles::LookupAddr(h.target.into()));
+                    if profile_domains.matches_addr(&h.target.dst) {
+                        return Ok(profiles::LookupAddr(h.target.dst.into()));
                     }
 
                     tracing::debug!(
-                        dst = %h.target,
+                        dst = %h.target.dst,
                         domains = %profile_domains,
                         "Address not in a configured domain",
                     );
@@ -130,43 +176,53 @@ impl Outbound<svc::BoxNewHttp<http::Endpoint>> {
             // aren't received.
             .push_on_response(
                 svc::layers()
+                    .push(
+                        rt.metrics
+                            .http_queue_time
+                            .layer(stack_labels("http", "logical"), queue_time_header),
+                    )
                     .push(rt.metrics.stack.layer(stack_labels("http", "logical")))
                     .push(svc::layer::mk(svc::SpawnReady::new))
-                    .push(svc::FailFast::layer("HTTP Logical", dispatch_timeout))
-                    .push_spawn_buffer(buffer_capacity),
+                    // Traces the time spent looking up the profile.
+                    .push(http_tracing::ReadySpan::layer("profile"))
+                    .push(svc::FailFast::layer_with_retry_after(
+                        "HTTP Logical",
+                        dispatch_timeout,
+                        retry_after,
+                    ))
+                    .push_spawn_buffer(buffer_capacity)
+                    .push(http_metrics::queue_time::Enqueue::layer()),
+            )
+            .push_cache(
+                cache_idle_ages.ingress,
+                rt.metrics.cache.metrics(stack_labels("http", "override")),
             )
-            .push_cache(cache_max_idle_age)
             .push_on_response(
                 svc::layers()
                     .push(http::strip_header::request::layer(DST_OVERRIDE_HEADER))
                     .push(http::Retain::layer())
                     .push(http::BoxResponse::layer()),
             )
-            .instrument(|h: &Http<NameAddr>| info_span!("override", dst = %h.target))
+            .instrument(|h: &Http<Override>| info_span!("override", dst = %h.target.dst))
             // Route requests with destinations that can be discovered via the `l5d-dst-override`
-            // header through the (load balanced) logical stack. Route requests without the header
-            // through the endpoint stack.
+            // header or their authority through the (load balanced) logical stack. Route other
+            // requests through the endpoint stack.
             .push_switch(
                 |Http { target, version }: Http<Target>| match target {
                     Target::Override(target) => {
                         Ok::<_, Infallible>(svc::Either::A(Http { target, version }))
                     }
-                    Target::Forward(OrigDstAddr(addr)) => Ok(svc::Either::B(http::Endpoint {
-                        addr: Remote(ServerAddr(addr)),
-                        metadata: Metadata::default(),
-                        logical_addr: None,
-                        protocol: version,
-                        opaque_protocol: false,
-                        tls: tls::ConditionalClientTls::None(
-                            tls::NoClientTls::IngressWithoutOverride,
-                        ),
-                    })),
+                    Target::Forward(orig_dst) => Ok(svc::Either::B(forward(orig_dst, version))),
                 },
                 http_endpoint
                     .push_on_response(
                         svc::layers()
                             .push(svc::layer::mk(svc::SpawnReady::new))
-                            .push(svc::FailFast::layer("Ingress server", dispatch_timeout)),
+                            .push(svc::FailFast::layer_with_retry_after(
+                                "Ingress server",
+                                dispatch_timeout,
+                                retry_after,
+                            )),
                     )
                     .instrument(|_: &_| info_span!("forward"))
                     .into_inner(),
@@ -177,17 +233,38 @@ impl Outbound<svc::BoxNewHttp<http::Endpoint>> {
             // not cached explicitly, as there are no real resources we need to share across
             // connections. This allows us to avoid buffering requests to these endpoints.
             .push(svc::NewRouter::layer(
-                |http::Accept { orig_dst, protocol }| {
+                move |http::Accept { orig_dst, protocol }| {
+                    let host_domains = host_domains.clone();
+                    #[cfg(feature = "ingress-script")]
+                    let script = ingress_script.clone();
                     move |req: &http::Request<_>| {
-                        // Use either the override header or the original destination address.
+                        // Use either the override header, the destination selected by the routing
+                        // script, the request's authority, or the original destination address.
                         let target = match http::authority_from_header(req, DST_OVERRIDE_HEADER) {
-                            None => Target::Forward(orig_dst),
+                            None => {
+                                #[cfg(feature = "ingress-script")]
+                                let scripted = route_by_script(script.as_ref(), req, orig_dst)?;
+                                #[cfg(not(feature = "ingress-script"))]
+                                let scripted = None;
+                                scripted.unwrap_or_else(|| {
+                                    route_by_authority(req, orig_dst, &host_domains)
+                                })
+                            }
                             Some(a) => {
                                 let dst = NameAddr::from_authority_with_default_port(&a, 80)
                                     .map_err(|_| InvalidOverrideHeader)?;
-                                Target::Override(dst)
+                                Target::Override(Override {
+                                    dst,
+                                    fallback: None,
+                                })
                             }
                         };
+                        // Tenants may only discover the destinations in their allow-list.
+                        if let (Target::Override(Override { dst, .. }), Some(tenant)) =
+                            (&target, req.extensions().get::<Arc<tenant::Tenant>>())
+                        {
+                            tenant.check_destination(dst)?;
+                        }
                         Ok(Http {
                             target,
                             version: protocol,
@@ -203,20 +280,43 @@ impl Outbound<svc::BoxNewHttp<http::Endpoint>> {
                     // be driven to readiness on a background task (i.e., by `SpawnReady`).
                     // Otherwise, the inner service is always ready (because it's a router).
                     .push(svc::ConcurrencyLimitLayer::new(max_in_flight_requests))
-                    .push(svc::FailFast::layer("Ingress server", dispatch_timeout))
+                    .push(svc::FailFast::layer_with_retry_after(
+                        "Ingress server",
+                        dispatch_timeout,
+                        retry_after,
+                    ))
+                    .push(request_limits::EnforceLimits::layer(request_limits))
+                    // Rejects requests from unknown tenants or that exceed
+                    // their tenant's quota, if tenants are configured.
+                    .push(Tenants::layer(ingress_tenants))
                     .push(rt.metrics.http_errors.clone())
-                    .push(errors::layer())
-                    .push(http_tracing::server(rt.span_sink, trace_labels()))
+                    .push(errors::layer(error_responses))
+                    .push(request_id::SetRequestId::layer(request_id))
+                    // Records whether requests reuse the application's
+                    // connection in their spans, if configured.
+                    .push(http_tracing::SetConnectionReused::layer())
+                    .push(http_tracing::server(
+                        rt.span_sink,
+                        trace_attributes,
+                        trace_labels(),
+                    ))
                     .push(http::BoxResponse::layer())
                     .push(http::BoxRequest::layer()),
             )
             .instrument(|a: &http::Accept| debug_span!("http", v = %a.protocol))
-            .push(http::NewServeHttp::layer(h2_settings, rt.drain))
+            .push(http::NewServeHttp::layer(
+                h1_settings,
+                h2_settings,
+                rt.drain,
+            ))
             .push_request_filter(|(http, accept): (Option<http::Version>, _)| {
                 http.map(|h| http::Accept::from((h, accept)))
                     .ok_or(IngressHttpOnly)
             })
-            .push_cache(cache_max_idle_age)
+            .push_cache(
+                cache_idle_ages.ingress,
+                rt.metrics.cache.metrics(stack_labels("http", "ingress")),
+            )
             .push_map_target(detect::allow_timeout)
             .push(svc::BoxNewService::layer())
             .push(detect::NewDetectService::layer(detect_http))
@@ -233,3 +333,60 @@ impl Outbound<svc::BoxNewHttp<http::Endpoint>> {
             .into_inner()
     }
 }
+
+/// Routes a request to the destination selected by the routing script, if one
+/// is configured and it selects a destination.
+#[cfg(feature = "ingress-script")]
+fn route_by_script<B>(
+    script: Option<&RouteScript>,
+    req: &http::Request<B>,
+    orig_dst: OrigDstAddr,
+) -> Result<Option<Target>, ScriptError> {
+    let dst = match script {
+        Some(script) => script.route(req, orig_dst)?,
+        None => return Ok(None),
+    };
+    Ok(dst.map(|dst| {
+        tracing::debug!(%dst, "Routing by script");
+        Target::Override(Override {
+            dst,
+            fallback: None,
+        })
+    }))
+}
+
+/// Routes a request by its authority if it names a host in a discoverable
+/// domain. Otherwise, the request is forwarded to its original destination.
+fn route_by_authority<B>(
+    req: &http::Request<B>,
+    orig_dst: OrigDstAddr,
+    domains: &NameMatch,
+) -> Target {
+    let OrigDstAddr(addr) = orig_dst;
+    let dst = req
+        .uri()
+        .authority()
+        .and_then(|a| NameAddr::from_authority_with_default_port(a, addr.port()).ok());
+    match dst {
+        Some(dst) if domains.matches_addr(&dst) => {
+            tracing::debug!(%dst, "Routing by authority");
+            Target::Override(Override {
+                dst,
+                fallback: Some(orig_dst),
+            })
+        }
+        _ => Target::Forward(orig_dst),
+    }
+}
+
+fn forward(OrigDstAddr(addr): OrigDstAddr, protocol: http::Version) -> http::Endpoint {
+    http::Endpoint {
+        addr: Remote(ServerAddr(addr)),
+        metadata: Metadata::default(),
+        logical_addr: None,
+        protocol,
+        opaque_protocol: false,
+        tls: tls::ConditionalClientTls::None(tls::NoClientTls::IngressWithoutOverride),
+        metadata_labels: Default::default(),
+    }
+}
@@ -0,0 +1,188 @@
+use linkerd_app_core::{transport::OrigDstAddr, NameAddr};
+use std::{fmt, str::FromStr, sync::Arc};
+use thiserror::Error;
+
+/// A sandboxed script that selects the destination of ingress-mode requests.
+///
+/// The script is evaluated with the following constants in scope:
+///
+/// - `method`: the request's method;
+/// - `path`: the request's path, without its query;
+/// - `query`: the request's query string, or an empty string;
+/// - `authority`: the request's authority, or an empty string;
+/// - `headers`: a map of lowercase header names to values, where repeated
+///   headers are joined with commas;
+/// - `orig_dst`: the connection's original destination address.
+///
+/// The script returns either a `host:port` string naming the logical
+/// destination of the request or `()` to route the request as if no script
+/// were configured. Destinations that omit a port use port 80.
+///
+/// Scripts have no access to the filesystem or network, and each evaluation
+/// is bounded by a limit on the number of operations it may perform.
+#[derive(Clone)]
+pub struct RouteScript {
+    engine: Arc<rhai::Engine>,
+    ast: Arc<rhai::AST>,
+}
+
+#[derive(Debug, Error)]
+pub enum ScriptError {
+    #[error("invalid ingress routing script: {0}")]
+    Compile(String),
+
+    #[error("ingress routing script failed: {0}")]
+    Eval(String),
+
+    #[error("ingress routing script returned an invalid destination: {0}")]
+    InvalidDestination(String),
+}
+
+// === impl RouteScript ===
+
+impl RouteScript {
+    pub fn compile(script: &str, max_operations: u64) -> Result<Self, ScriptError> {
+        let mut engine = rhai::Engine::new();
+        engine
+            .set_max_operations(max_operations)
+            .set_max_call_levels(16)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(16 * 1024)
+            .set_max_array_size(1024)
+            .set_max_map_size(1024)
+            .disable_symbol("eval");
+        let ast = engine
+            .compile(script)
+            .map_err(|e| ScriptError::Compile(e.to_string()))?;
+        Ok(Self {
+            engine: Arc::new(engine),
+            ast: Arc::new(ast),
+        })
+    }
+
+    /// Evaluates the script for a request, returning the destination it
+    /// selected, if any.
+    pub(super) fn route<B>(
+        &self,
+        req: &http::Request<B>,
+        OrigDstAddr(orig_dst): OrigDstAddr,
+    ) -> Result<Option<NameAddr>, ScriptError> {
+        let mut headers = rhai::Map::new();
+        for name in req.headers().keys() {
+            let values = req
+                .headers()
+                .get_all(name)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .collect::<Vec<_>>();
+            headers.insert(name.as_str().into(), values.join(",").into());
+        }
+
+        let mut scope = rhai::Scope::new();
+        scope
+            .push_constant("method", req.method().to_string())
+            .push_constant("path", req.uri().path().to_string())
+            .push_constant("query", req.uri().query().unwrap_or("").to_string())
+            .push_constant(
+                "authority",
+                req.uri()
+                    .authority()
+                    .map(|a| a.to_string())
+                    .unwrap_or_default(),
+            )
+            .push_constant("headers", headers)
+            .push_constant("orig_dst", orig_dst.to_string());
+
+        let dst = self
+            .engine
+            .eval_ast_with_scope::<rhai::Dynamic>(&mut scope, &self.ast)
+            .map_err(|e| ScriptError::Eval(e.to_string()))?;
+        if dst.is::<()>() {
+            return Ok(None);
+        }
+
+        let dst = dst.into_string().map_err(|ty| {
+            ScriptError::InvalidDestination(format!("expected a string, got {}", ty))
+        })?;
+        http::uri::Authority::from_str(&dst)
+            .ok()
+            .and_then(|a| NameAddr::from_authority_with_default_port(&a, 80).ok())
+            .map(Some)
+            .ok_or(ScriptError::InvalidDestination(dst))
+    }
+}
+
+impl fmt::Debug for RouteScript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RouteScript").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+
+    const SCRIPT: &str = r#"
+        let tenant = headers["x-tenant"];
+        if tenant != () {
+            return `web.${tenant}.svc.cluster.local:8080`;
+        }
+        if path.starts_with("/api/") {
+            return "api.default.svc.cluster.local";
+        }
+        if query == "bogus" {
+            return 42;
+        }
+    "#;
+
+    fn route(req: http::Request<()>) -> Result<Option<NameAddr>, ScriptError> {
+        let script = RouteScript::compile(SCRIPT, 10_000).expect("script must compile");
+        let orig_dst = OrigDstAddr(SocketAddr::from(([10, 0, 0, 1], 8080)));
+        script.route(&req, orig_dst)
+    }
+
+    #[test]
+    fn routes_by_request_attributes() {
+        let req = http::Request::builder()
+            .uri("http://foo.example.com/")
+            .header("x-tenant", "acme")
+            .body(())
+            .unwrap();
+        assert_eq!(
+            route(req).unwrap().unwrap().to_string(),
+            "web.acme.svc.cluster.local:8080"
+        );
+
+        let req = http::Request::builder().uri("/api/users").body(()).unwrap();
+        assert_eq!(
+            route(req).unwrap().unwrap().to_string(),
+            "api.default.svc.cluster.local:80"
+        );
+
+        let req = http::Request::builder().uri("/?bogus").body(()).unwrap();
+        assert!(matches!(
+            route(req),
+            Err(ScriptError::InvalidDestination(_))
+        ));
+
+        let req = http::Request::builder().uri("/").body(()).unwrap();
+        assert!(route(req).unwrap().is_none());
+    }
+
+    #[test]
+    fn limits_operations() {
+        let script = RouteScript::compile("loop {}", 1_000).expect("script must compile");
+        let orig_dst = OrigDstAddr(SocketAddr::from(([10, 0, 0, 1], 8080)));
+        let req = http::Request::builder().uri("/").body(()).unwrap();
+        assert!(matches!(
+            script.route(&req, orig_dst),
+            Err(ScriptError::Eval(_))
+        ));
+
+        assert!(matches!(
+            RouteScript::compile("eval(\"1\")", 1_000),
+            Err(ScriptError::Compile(_))
+        ));
+    }
+}
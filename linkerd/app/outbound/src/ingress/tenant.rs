@@ -0,0 +1,350 @@
+use crate::http;
+use futures::{future, prelude::*, ready};
+use linkerd_app_core::{
+    errors::HttpError,
+    metrics::{metrics, Counter, FmtLabels, FmtMetrics, Gauge},
+    svc, Error, NameAddr, NameMatch,
+};
+use pin_project::pin_project;
+use std::{
+    collections::HashMap,
+    fmt,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::debug;
+
+metrics! {
+    ingress_tenant_requests_total: Counter {
+        "Total number of ingress requests admitted for each tenant"
+    },
+    ingress_tenant_rejected_total: Counter {
+        "Total number of ingress requests rejected for each tenant"
+    },
+    ingress_tenant_in_flight: Gauge {
+        "The number of ingress requests in flight for each tenant"
+    }
+}
+
+/// Configures a tenant of a shared ingress proxy.
+#[derive(Clone, Debug)]
+pub struct TenantConfig {
+    pub name: String,
+
+    /// The destinations to which the tenant's requests may be routed via
+    /// discovery.
+    pub allowed_suffixes: NameMatch,
+
+    /// Limits the number of the tenant's requests that may be in flight at
+    /// once. Requests that exceed the limit fail immediately.
+    pub max_concurrent_requests: Option<usize>,
+}
+
+/// The tenants served by a shared ingress proxy.
+///
+/// Each request names its tenant in a header. Requests for unknown tenants
+/// are rejected, as are requests that would exceed their tenant's quota or
+/// that are routed to destinations outside their tenant's allow-list.
+#[derive(Clone, Debug)]
+pub struct Tenants(Arc<Inner>);
+
+#[derive(Debug)]
+struct Inner {
+    header: http::HeaderName,
+    tenants: HashMap<String, Arc<Tenant>>,
+}
+
+/// A tenant's policy and metrics, set as a request extension on admitted
+/// requests.
+#[derive(Debug)]
+pub(super) struct Tenant {
+    name: String,
+    allowed_suffixes: NameMatch,
+    permits: Option<Arc<Semaphore>>,
+    metrics: Metrics,
+}
+
+#[derive(Debug, Default)]
+struct Metrics {
+    requests_total: Counter,
+    rejected_destination: Counter,
+    rejected_quota: Counter,
+    in_flight: Gauge,
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct Admit<S> {
+    inner: S,
+    tenants: Option<Tenants>,
+}
+
+/// Holds a tenant's quota until a response is received.
+#[derive(Debug)]
+struct InFlight {
+    tenant: Arc<Tenant>,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub(super) struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    in_flight: Option<InFlight>,
+}
+
+struct TenantLabels<'t>(&'t str);
+
+struct RejectLabels<'t> {
+    tenant: &'t str,
+    reason: &'static str,
+}
+
+// === impl Tenants ===
+
+impl Tenants {
+    pub fn new(header: http::HeaderName, tenants: impl IntoIterator<Item = TenantConfig>) -> Self {
+        let tenants = tenants
+            .into_iter()
+            .map(|config| {
+                let tenant = Tenant {
+                    name: config.name.clone(),
+                    allowed_suffixes: config.allowed_suffixes,
+                    permits: config
+                        .max_concurrent_requests
+                        .map(|max| Arc::new(Semaphore::new(max))),
+                    metrics: Metrics::default(),
+                };
+                (config.name, Arc::new(tenant))
+            })
+            .collect();
+        Self(Arc::new(Inner { header, tenants }))
+    }
+
+    /// Admits requests on behalf of their tenants, if tenants are
+    /// configured.
+    pub(super) fn layer<S>(
+        tenants: Option<Self>,
+    ) -> impl svc::Layer<S, Service = Admit<S>> + Clone {
+        svc::layer::mk(move |inner| Admit {
+            inner,
+            tenants: tenants.clone(),
+        })
+    }
+
+    fn admit<B>(&self, req: &mut http::Request<B>) -> Result<InFlight, Error> {
+        let tenant = req
+            .headers()
+            .get(&self.0.header)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|name| self.0.tenants.get(name.trim()))
+            .ok_or_else(|| HttpError::unauthorized("unknown ingress tenant"))?;
+
+        let permit = match tenant.permits.as_ref() {
+            Some(permits) => match permits.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => {
+                    debug!(tenant = %tenant.name, "Ingress tenant quota exceeded");
+                    tenant.metrics.rejected_quota.incr();
+                    return Err(HttpError::quota_exceeded("ingress tenant quota exceeded").into());
+                }
+            },
+            None => None,
+        };
+
+        tenant.metrics.requests_total.incr();
+        tenant.metrics.in_flight.incr();
+        req.extensions_mut().insert(tenant.clone());
+        Ok(InFlight {
+            tenant: tenant.clone(),
+            _permit: permit,
+        })
+    }
+}
+
+impl FmtMetrics for Tenants {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tenants = || self.0.tenants.values();
+
+        ingress_tenant_requests_total.fmt_help(f)?;
+        for tenant in tenants() {
+            ingress_tenant_requests_total.fmt_metric_labeled(
+                f,
+                &tenant.metrics.requests_total,
+                &TenantLabels(&tenant.name),
+            )?;
+        }
+
+        ingress_tenant_rejected_total.fmt_help(f)?;
+        for tenant in tenants() {
+            let rejected = [
+                ("destination", &tenant.metrics.rejected_destination),
+                ("quota", &tenant.metrics.rejected_quota),
+            ];
+            for &(reason, counter) in rejected.iter() {
+                ingress_tenant_rejected_total.fmt_metric_labeled(
+                    f,
+                    counter,
+                    &RejectLabels {
+                        tenant: &tenant.name,
+                        reason,
+                    },
+                )?;
+            }
+        }
+
+        ingress_tenant_in_flight.fmt_help(f)?;
+        for tenant in tenants() {
+            ingress_tenant_in_flight.fmt_metric_labeled(
+                f,
+                &tenant.metrics.in_flight,
+                &TenantLabels(&tenant.name),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+// === impl Tenant ===
+
+impl Tenant {
+    /// Fails if the tenant's requests may not be routed to `dst`.
+    pub(super) fn check_destination(&self, dst: &NameAddr) -> Result<(), HttpError> {
+        if self.allowed_suffixes.matches_addr(dst) {
+            return Ok(());
+        }
+
+        debug!(tenant = %self.name, %dst, "Destination not allowed for ingress tenant");
+        self.metrics.rejected_destination.incr();
+        Err(HttpError::unauthorized(
+            "destination not allowed for ingress tenant",
+        ))
+    }
+}
+
+// === impl Admit ===
+
+impl<S, B> svc::Service<http::Request<B>> for Admit<S>
+where
+    S: svc::Service<http::Request<B>>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future =
+        future::Either<future::Ready<Result<S::Response, Error>>, ResponseFuture<S::Future>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        let in_flight = match self.tenants.as_ref().map(|t| t.admit(&mut req)) {
+            Some(Ok(in_flight)) => Some(in_flight),
+            Some(Err(error)) => return future::Either::Left(future::err(error)),
+            None => None,
+        };
+        future::Either::Right(ResponseFuture {
+            inner: self.inner.call(req),
+            in_flight,
+        })
+    }
+}
+
+// === impl InFlight ===
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.tenant.metrics.in_flight.decr();
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F: TryFuture> Future for ResponseFuture<F>
+where
+    F::Error: Into<Error>,
+{
+    type Output = Result<F::Ok, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.inner.try_poll(cx)).map_err(Into::into);
+        // Release the tenant's quota as soon as the response is available.
+        drop(this.in_flight.take());
+        Poll::Ready(res)
+    }
+}
+
+// === impl TenantLabels ===
+
+impl FmtLabels for TenantLabels<'_> {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tenant=\"{}\"", self.0)
+    }
+}
+
+impl FmtLabels for RejectLabels<'_> {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tenant=\"{}\",reason=\"{}\"", self.tenant, self.reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::dns;
+    use std::str::FromStr;
+
+    fn tenants() -> Tenants {
+        let suffix = dns::Suffix::from_str("acme.svc.cluster.local").unwrap();
+        Tenants::new(
+            http::HeaderName::from_static("x-tenant"),
+            vec![TenantConfig {
+                name: "acme".to_string(),
+                allowed_suffixes: NameMatch::new(Some(suffix)),
+                max_concurrent_requests: Some(1),
+            }],
+        )
+    }
+
+    fn request(tenant: Option<&str>) -> http::Request<()> {
+        let mut req = http::Request::builder();
+        if let Some(tenant) = tenant {
+            req = req.header("x-tenant", tenant);
+        }
+        req.body(()).unwrap()
+    }
+
+    #[test]
+    fn admits_known_tenants_within_quota() {
+        let tenants = tenants();
+
+        let mut req = request(Some("acme"));
+        let in_flight = tenants.admit(&mut req).expect("tenant must be admitted");
+        let tenant = req.extensions().get::<Arc<Tenant>>().unwrap().clone();
+        assert_eq!(tenant.metrics.in_flight.value(), 1);
+
+        let allowed = NameAddr::from_str("web.acme.svc.cluster.local:80").unwrap();
+        assert!(tenant.check_destination(&allowed).is_ok());
+        let denied = NameAddr::from_str("web.other.svc.cluster.local:80").unwrap();
+        assert!(tenant.check_destination(&denied).is_err());
+        assert_eq!(tenant.metrics.rejected_destination.value(), 1.0);
+
+        // The tenant's quota is exhausted until the in-flight request
+        // completes.
+        assert!(tenants.admit(&mut request(Some("acme"))).is_err());
+        assert_eq!(tenant.metrics.rejected_quota.value(), 1.0);
+        drop(in_flight);
+        assert_eq!(tenant.metrics.in_flight.value(), 0);
+        assert!(tenants.admit(&mut request(Some("acme"))).is_ok());
+
+        assert!(tenants.admit(&mut request(Some("other"))).is_err());
+        assert!(tenants.admit(&mut request(None)).is_err());
+        assert_eq!(tenant.metrics.requests_total.value(), 2.0);
+    }
+}
@@ -5,23 +5,28 @@
 #![deny(warnings, rust_2018_idioms)]
 #![forbid(unsafe_code)]
 
+pub mod bypass;
 mod discover;
+pub mod egress;
 pub mod endpoint;
+pub mod failover;
 pub mod http;
-mod ingress;
+pub mod ingress;
 pub mod logical;
 mod resolve;
 mod switch_logical;
 pub mod tcp;
 #[cfg(test)]
 pub(crate) mod test_util;
+mod warm;
 
 use linkerd_app_core::{
     config::{ProxyConfig, ServerConfig},
-    metrics, profiles,
+    http_cache, idempotency, metrics, profiles, protocol_hints,
     proxy::{
         api_resolve::{ConcreteAddr, Metadata},
         core::Resolve,
+        http::{balance::affinity, deadline},
     },
     serve,
     svc::{self, stack::Param},
@@ -29,7 +34,12 @@ use linkerd_app_core::{
     transport::{self, addrs::*, listen::Bind},
     AddrMatch, Conditional, Error, ProxyRuntime,
 };
-use std::{collections::HashMap, future::Future, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::Arc,
+    time::Duration,
+};
 use tracing::info;
 
 const EWMA_DEFAULT_RTT: Duration = Duration::from_millis(30);
@@ -44,6 +54,128 @@ pub struct Config {
     // not perform per-target-address discovery. Non-HTTP connections are
     // forwarded without discovery/routing/mTLS.
     pub ingress_mode: bool,
+
+    // When set, ingress-mode requests without an l5d-dst-override header are
+    // routed to the destination selected by this script.
+    #[cfg(feature = "ingress-script")]
+    pub ingress_script: Option<ingress::RouteScript>,
+
+    // When set, ingress-mode requests are admitted on behalf of the tenant
+    // named in a request header, subject to per-tenant destination
+    // allow-lists and concurrency quotas.
+    pub ingress_tenants: Option<ingress::Tenants>,
+
+    // When set, concrete balancers fail over to the endpoints of services
+    // mirrored from a remote cluster.
+    pub failover: Option<failover::Config>,
+
+    // Selects the endpoint metadata labels included in metrics and traces.
+    pub metadata_labels: endpoint::MetadataLabels,
+
+    // When set, compressed responses are requested from endpoints and
+    // decompressed if the application did not accept their encoding.
+    pub decompression: bool,
+
+    // When set, outbound connections are allowed or denied by destination.
+    pub egress: Option<egress::Config>,
+
+    // Connections to matching destinations are forwarded directly, without
+    // protocol detection, discovery, or mTLS.
+    pub bypass: bypass::Config,
+
+    // Forces the protocol of matching destinations, overriding protocol
+    // detection and discovery hints.
+    pub protocol_overrides: http::detect::ProtocolOverrides,
+
+    // Ports on which the server is expected to write before the client (e.g.
+    // SMTP or MySQL). Protocol detection is skipped for these ports so that
+    // connections are not stalled waiting for the client's first bytes.
+    pub server_speaks_first_ports: Arc<HashSet<u16>>,
+
+    // Learns the protocols of destinations that are repeatedly detected as
+    // opaque or HTTP/1, so that detection is skipped for them until the
+    // learned hint expires.
+    pub protocol_hints: protocol_hints::ProtocolHints,
+
+    // Limits the byte rate of forwarded TCP connections by destination port.
+    pub tcp_rate_limits: tcp::RateLimits,
+
+    // When set, forwarded TCP connections are closed after being idle in both
+    // directions for this long.
+    pub tcp_idle_timeout: Option<Duration>,
+
+    // When set, opaque connections to remote gateways are multiplexed as
+    // streams on a single long-lived connection to each gateway, if the
+    // gateway supports it.
+    pub tcp_tunnel: bool,
+
+    // Overrides the connect timeout for endpoints in matching networks or
+    // with matching metadata labels.
+    pub connect_timeouts: tcp::ConnectTimeouts,
+
+    // Configures how long each of the outbound stack's caches retains idle
+    // services.
+    pub cache_idle_ages: CacheIdleAges,
+
+    // Original destination addresses that are discovered when the proxy
+    // starts, before the application connects to them.
+    pub warm_destinations: Vec<std::net::SocketAddr>,
+
+    // How long a connection to a dual-stack endpoint's primary (IPv6)
+    // address is attempted before its fallback (IPv4) address is also tried.
+    pub happy_eyeballs_delay: Duration,
+
+    // When set, HTTP balancers route requests that carry an affinity cookie
+    // to the endpoint it names, and set the cookie on other responses.
+    pub http_affinity: Option<affinity::Config>,
+
+    // When set, requests may override the weights of a service's traffic
+    // split with an `l5d-dst-weights` header.
+    pub split_overrides: bool,
+
+    // When set, responses to split requests carry an `l5d-dst-concrete`
+    // header describing the concrete target and weight that was chosen.
+    pub split_concrete_header: bool,
+
+    // When set, targets of a traffic split that fail consistently are
+    // temporarily removed from the split.
+    pub split_isolation: Option<profiles::split::Isolation>,
+
+    // Settings applied to HTTP routes that do not configure their own.
+    pub http_route_defaults: http::RouteDefaults,
+
+    // When set, HTTP requests fail once the deadline described by their
+    // `grpc-timeout` or deadline header passes, and these headers are
+    // rewritten with the remaining time as requests are forwarded.
+    pub http_deadline: Option<deadline::Config>,
+
+    // When set, responses on routes that deduplicate requests by idempotency
+    // key are stored in a cache of this size.
+    pub http_idempotency: Option<idempotency::Config>,
+
+    // When set, responses to GET requests on routes that enable caching are
+    // stored in a cache of this size, according to their Cache-Control
+    // headers.
+    pub http_response_cache: Option<http_cache::Config>,
+
+    // When set, experimental WASM filters are applied to outbound HTTP
+    // requests and responses.
+    #[cfg(feature = "wasm")]
+    pub http_wasm: Option<linkerd_app_core::http_wasm::Filters>,
+}
+
+/// How long the outbound stack's caches retain services that are not in use.
+#[derive(Copy, Clone, Debug)]
+pub struct CacheIdleAges {
+    /// Retains the profile discovered for each original destination address.
+    pub profile: Duration,
+
+    /// Retains logical stacks, including their balancers and endpoint
+    /// resolutions.
+    pub balancer: Duration,
+
+    /// Retains the targets of ingress-mode requests.
+    pub ingress: Duration,
 }
 
 #[derive(Clone, Debug)]
@@ -139,8 +271,14 @@ impl Outbound<()> {
         let (listen_addr, listen) = bind
             .bind(&self.config.proxy.server)
             .expect("Failed to bind outbound listener");
+        let listen = self.config.proxy.fd_pressure.shed(listen);
+        let listen = self
+            .runtime
+            .connections
+            .track(metrics::Direction::Out, listen);
 
         let serve = async move {
+            let accept_metrics = self.runtime.metrics.tcp_accept.clone();
             if self.config.ingress_mode {
                 info!("Outbound routing in ingress-mode");
                 let stack = self
@@ -149,16 +287,32 @@ impl Outbound<()> {
                     .push_http_endpoint()
                     .into_ingress(profiles, resolve);
                 let shutdown = self.runtime.drain.signaled();
-                serve::serve(listen, stack, shutdown).await;
+                serve::serve(listen, stack, Some(accept_metrics), shutdown).await;
             } else {
-                let logical = self.to_tcp_connect().push_logical(resolve);
+                // Endpoints are indexed by address as they are resolved, so
+                // that connections to them are labeled with their service.
+                let by_addr = switch_logical::LogicalByAddr::default();
+                let resolve = by_addr.resolve(resolve);
+                let logical = self.to_tcp_connect().push_logical(resolve.clone());
                 let endpoint = self.to_tcp_connect().push_endpoint();
-                let server = endpoint
-                    .push_switch_logical(logical.into_inner())
-                    .push_discover(profiles)
+                let bypass = self.to_bypass().into_inner();
+                let discover = endpoint
+                    .push_switch_logical(logical.into_inner(), by_addr)
+                    .push_egress_policy()
+                    .push_discover(profiles.clone());
+                // Warmed servers are cached for the server's connections.
+                discover.spawn_warm(profiles, resolve);
+                let server = discover
+                    .map_stack(|_, _, stack| {
+                        stack.push_map_target(|a: B::Addrs| {
+                            let orig_dst: OrigDstAddr = a.param();
+                            tcp::Accept::from(orig_dst)
+                        })
+                    })
+                    .push_bypass(bypass)
                     .into_inner();
                 let shutdown = self.runtime.drain.signaled();
-                serve::serve(listen, server, shutdown).await;
+                serve::serve(listen, server, Some(accept_metrics), shutdown).await;
             }
         };
 
@@ -15,7 +15,7 @@ pub struct Logical<P> {
     pub protocol: P,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Concrete<P> {
     pub resolve: ConcreteAddr,
     pub logical: Logical<P>,
@@ -66,6 +66,16 @@ impl svc::Param<Option<http::detect::Skip>> for Logical<()> {
     }
 }
 
+// Used for overriding HTTP detection
+impl svc::Param<http::detect::OverrideTarget> for Logical<()> {
+    fn param(&self) -> http::detect::OverrideTarget {
+        http::detect::OverrideTarget {
+            name: Some(self.logical_addr.0.clone()),
+            addr: None,
+        }
+    }
+}
+
 impl<P> Logical<P> {
     pub fn addr(&self) -> Addr {
         Addr::from(self.logical_addr.clone().0)
@@ -111,6 +121,20 @@ impl<P> svc::Param<ConcreteAddr> for Concrete<P> {
     }
 }
 
+/// Used to describe the split's weights in debugging headers.
+impl<P> svc::Param<profiles::Receiver> for Concrete<P> {
+    fn param(&self) -> profiles::Receiver {
+        self.logical.profile.clone()
+    }
+}
+
+/// Used to determine TCP rate limits.
+impl<P> svc::Param<u16> for Concrete<P> {
+    fn param(&self) -> u16 {
+        self.logical.logical_addr.0.port()
+    }
+}
+
 // === impl Outbound ===
 
 impl<C> Outbound<C> {
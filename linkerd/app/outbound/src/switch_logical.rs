@@ -1,18 +1,78 @@
 use crate::{endpoint::Endpoint, logical::Logical, tcp, transport::OrigDstAddr, Outbound};
-use linkerd_app_core::{io, profiles, svc, Error, Infallible};
-use std::fmt;
+use futures::{prelude::*, ready};
+use linkerd_app_core::{
+    io, profiles,
+    proxy::{
+        api_resolve::ConcreteAddr,
+        core::{Resolve, Update},
+    },
+    svc, Error, Infallible, NameAddr,
+};
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+/// Looks up the logical service of an endpoint by its address.
+///
+/// The destination service describes the endpoint at a pod's address, but not
+/// the services that select it, so the endpoints of each service that the
+/// proxy resolves are indexed by address. When the application connects to an
+/// endpoint directly, the endpoint is labeled with a service that resolved it.
+#[derive(Clone, Debug, Default)]
+pub struct LogicalByAddr(Arc<Mutex<HashMap<SocketAddr, Vec<NameAddr>>>>);
+
+/// Resolves endpoints, recording them in a `LogicalByAddr` index.
+#[derive(Clone, Debug)]
+pub struct IndexResolve<R> {
+    inner: R,
+    index: LogicalByAddr,
+}
+
+#[pin_project]
+pub struct IndexFuture<F> {
+    #[pin]
+    inner: F,
+    logical: Option<NameAddr>,
+    index: LogicalByAddr,
+}
+
+#[pin_project]
+pub struct IndexResolution<S> {
+    #[pin]
+    inner: S,
+    indexed: Indexed,
+}
+
+/// The endpoints that a resolution has recorded in the index, which are
+/// removed from the index when the resolution is dropped.
+struct Indexed {
+    logical: NameAddr,
+    endpoints: HashSet<SocketAddr>,
+    index: LogicalByAddr,
+}
 
 impl<S> Outbound<S> {
     /// Wraps an endpoint stack to switch to an alternate logical stack when an appropriate profile
     /// is provided:
     ///
-    /// - When a profile includes endpoint information, it is used to build an endpoint stack;
+    /// - When a profile includes endpoint information, it is used to build an endpoint stack. The
+    ///   endpoint is labeled with the service named by the profile or, if the profile does not
+    ///   name one, a service whose resolution includes the endpoint, so that direct-to-pod
+    ///   traffic is reported like load-balanced traffic;
     /// - Otherwise, if the profile indicates the target is logical, a logical stack is built;
     /// - Otherwise, we assume the target is not part of the mesh and we should connect to the
     ///   original destination.
     pub fn push_switch_logical<T, I, N, NSvc, SSvc>(
         self,
         logical: N,
+        by_addr: LogicalByAddr,
     ) -> Outbound<svc::BoxNewTcp<(Option<profiles::Receiver>, T), I>>
     where
         Self: Clone + 'static,
@@ -26,7 +86,8 @@ impl<S> Outbound<S> {
         SSvc::Future: Send,
     {
         let no_tls_reason = self.no_tls_reason();
-        self.map_stack(|_, _, endpoint| {
+        self.map_stack(|config, _, endpoint| {
+            let metadata_labels = config.metadata_labels.clone();
             endpoint
                 .push_switch(
                     move |(profile, target): (Option<profiles::Receiver>, T)| -> Result<_, Infallible> {
@@ -34,12 +95,16 @@ impl<S> Outbound<S> {
                             // If the profile provides an endpoint, then the target is single endpoint and
                             // not a logical/load-balanced service.
                             if let Some((addr, metadata)) = rx.endpoint() {
-                                return Ok(svc::Either::A(Endpoint::from_metadata(
+                                let mut endpoint = Endpoint::from_metadata(
                                     addr,
                                     metadata,
                                     no_tls_reason,
                                     rx.is_opaque_protocol(),
-                                )));
+                                    metadata_labels.clone(),
+                                );
+                                endpoint.logical_addr =
+                                    rx.logical_addr().or_else(|| by_addr.get(addr));
+                                return Ok(svc::Either::A(endpoint));
                             }
 
                             // Otherwise, if the profile provides a (named) logical address, then we build a
@@ -64,6 +129,148 @@ impl<S> Outbound<S> {
     }
 }
 
+// === impl LogicalByAddr ===
+
+impl LogicalByAddr {
+    /// Wraps a resolver so that the endpoints it resolves are indexed.
+    pub fn resolve<R>(&self, inner: R) -> IndexResolve<R> {
+        IndexResolve {
+            inner,
+            index: self.clone(),
+        }
+    }
+
+    pub(crate) fn get(&self, addr: SocketAddr) -> Option<profiles::LogicalAddr> {
+        let index = self.0.lock();
+        let logical = index.get(&addr)?.first()?;
+        Some(profiles::LogicalAddr(logical.clone()))
+    }
+
+    fn insert(&self, addr: SocketAddr, logical: &NameAddr) {
+        self.0.lock().entry(addr).or_default().push(logical.clone());
+    }
+
+    fn remove(&self, addr: SocketAddr, logical: &NameAddr) {
+        let mut index = self.0.lock();
+        if let Some(names) = index.get_mut(&addr) {
+            if let Some(i) = names.iter().position(|n| n == logical) {
+                names.swap_remove(i);
+            }
+            if names.is_empty() {
+                index.remove(&addr);
+            }
+        }
+    }
+}
+
+// === impl IndexResolve ===
+
+impl<R> svc::Service<ConcreteAddr> for IndexResolve<R>
+where
+    R: Resolve<ConcreteAddr>,
+{
+    type Response = IndexResolution<R::Resolution>;
+    type Error = R::Error;
+    type Future = IndexFuture<R::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, target: ConcreteAddr) -> Self::Future {
+        let ConcreteAddr(logical) = target.clone();
+        IndexFuture {
+            inner: self.inner.resolve(target),
+            logical: Some(logical),
+            index: self.index.clone(),
+        }
+    }
+}
+
+impl<F: TryFuture> Future for IndexFuture<F> {
+    type Output = Result<IndexResolution<F::Ok>, F::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let inner = ready!(this.inner.try_poll(cx))?;
+        Poll::Ready(Ok(IndexResolution {
+            inner,
+            indexed: Indexed {
+                logical: this.logical.take().expect("polled after ready"),
+                endpoints: HashSet::new(),
+                index: this.index.clone(),
+            },
+        }))
+    }
+}
+
+// === impl IndexResolution ===
+
+impl<S, E> Stream for IndexResolution<S>
+where
+    S: TryStream<Ok = Update<E>>,
+{
+    type Item = Result<Update<E>, S::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let update = match ready!(this.inner.try_poll_next(cx)) {
+            Some(Ok(update)) => update,
+            res => return Poll::Ready(res),
+        };
+        let indexed = this.indexed;
+        match &update {
+            Update::Reset(endpoints) => {
+                indexed.clear();
+                for (addr, _) in endpoints {
+                    indexed.insert(*addr);
+                }
+            }
+            Update::Add(endpoints) => {
+                for (addr, _) in endpoints {
+                    indexed.insert(*addr);
+                }
+            }
+            Update::Remove(addrs) => {
+                for addr in addrs {
+                    indexed.remove(addr);
+                }
+            }
+            Update::DoesNotExist => indexed.clear(),
+        }
+        Poll::Ready(Some(Ok(update)))
+    }
+}
+
+// === impl Indexed ===
+
+impl Indexed {
+    fn insert(&mut self, addr: SocketAddr) {
+        if self.endpoints.insert(addr) {
+            self.index.insert(addr, &self.logical);
+        }
+    }
+
+    fn remove(&mut self, addr: &SocketAddr) {
+        if self.endpoints.remove(addr) {
+            self.index.remove(*addr, &self.logical);
+        }
+    }
+
+    fn clear(&mut self) {
+        for addr in self.endpoints.drain() {
+            self.index.remove(addr, &self.logical);
+        }
+    }
+}
+
+impl Drop for Indexed {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,7 +301,10 @@ mod tests {
         let (rt, _shutdown) = runtime();
         let mut stack = Outbound::new(default_config(), rt)
             .with_stack(endpoint)
-            .push_switch_logical(svc::Fail::<_, WrongStack>::default())
+            .push_switch_logical(
+                svc::Fail::<_, WrongStack>::default(),
+                LogicalByAddr::default(),
+            )
             .into_inner();
 
         let orig_dst = OrigDstAddr(SocketAddr::new([192, 0, 2, 20].into(), 2020));
@@ -111,13 +321,21 @@ mod tests {
             assert_eq!(ep.addr.as_ref().ip(), IpAddr::from([192, 0, 2, 10]));
             assert_eq!(ep.addr.as_ref().port(), 1010);
             assert!(ep.opaque_protocol);
+            assert_eq!(
+                ep.logical_addr
+                    .map(|profiles::LogicalAddr(a)| a.to_string()),
+                Some("foo.example.com:3030".to_string())
+            );
             svc::mk(|_: io::DuplexStream| future::ok::<(), Error>(()))
         };
 
         let (rt, _shutdown) = runtime();
         let mut stack = Outbound::new(default_config(), rt)
             .with_stack(endpoint)
-            .push_switch_logical(svc::Fail::<_, WrongStack>::default())
+            .push_switch_logical(
+                svc::Fail::<_, WrongStack>::default(),
+                LogicalByAddr::default(),
+            )
             .into_inner();
 
         let (_tx, profile) = tokio::sync::watch::channel(profiles::Profile {
@@ -126,7 +344,8 @@ mod tests {
                 Metadata::default(),
             )),
             opaque_protocol: true,
-            // logical addr does not influence use of endpoint
+            // logical addr does not influence use of endpoint, though it
+            // labels the endpoint
             addr: Some(profiles::LogicalAddr(
                 NameAddr::from_str_and_port("foo.example.com", 3030).unwrap(),
             )),
@@ -153,7 +372,7 @@ mod tests {
         let (rt, _shutdown) = runtime();
         let mut stack = Outbound::new(default_config(), rt)
             .with_stack(svc::Fail::<_, WrongStack>::default())
-            .push_switch_logical(logical)
+            .push_switch_logical(logical, LogicalByAddr::default())
             .into_inner();
 
         let (_tx, profile) = tokio::sync::watch::channel(profiles::Profile {
@@ -169,4 +388,65 @@ mod tests {
         let (server_io, _client_io) = io::duplex(1);
         svc.oneshot(server_io).await.expect("service must succeed");
     }
+
+    /// Tests that endpoints are labeled with a service that resolved them
+    /// when their profile doesn't name one.
+    #[tokio::test(flavor = "current_thread")]
+    async fn profile_endpoint_by_addr() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let ep_addr = SocketAddr::new([192, 0, 2, 10].into(), 1010);
+        let logical_addr = NameAddr::from_str_and_port("foo.example.com", 3030).unwrap();
+        let resolve = support::resolver::<Metadata>();
+        let mut dst = resolve.endpoint_tx(logical_addr.clone());
+        let by_addr = LogicalByAddr::default();
+        let mut resolution = by_addr
+            .resolve(resolve)
+            .oneshot(ConcreteAddr(logical_addr.clone()))
+            .await
+            .expect("resolution must succeed");
+
+        dst.add(vec![(ep_addr, Metadata::default())]).unwrap();
+        resolution.next().await.unwrap().unwrap();
+        assert_eq!(
+            by_addr.get(ep_addr),
+            Some(profiles::LogicalAddr(logical_addr.clone()))
+        );
+
+        let endpoint = |ep: tcp::Endpoint| {
+            assert_eq!(ep.addr.as_ref().ip(), IpAddr::from([192, 0, 2, 10]));
+            assert_eq!(
+                ep.logical_addr
+                    .map(|profiles::LogicalAddr(a)| a.to_string()),
+                Some("foo.example.com:3030".to_string())
+            );
+            svc::mk(|_: io::DuplexStream| future::ok::<(), Error>(()))
+        };
+        let (rt, _shutdown) = runtime();
+        let mut stack = Outbound::new(default_config(), rt)
+            .with_stack(endpoint)
+            .push_switch_logical(svc::Fail::<_, WrongStack>::default(), by_addr.clone())
+            .into_inner();
+
+        let (_tx, profile) = tokio::sync::watch::channel(profiles::Profile {
+            endpoint: Some((ep_addr, Metadata::default())),
+            ..Default::default()
+        });
+        let orig_dst = OrigDstAddr(ep_addr);
+        let svc = stack.new_service((Some(profile.into()), orig_dst));
+        let (server_io, _client_io) = io::duplex(1);
+        svc.oneshot(server_io).await.expect("service must succeed");
+
+        // Endpoints are no longer indexed once they are removed from the
+        // resolution or the resolution is dropped.
+        dst.remove(vec![ep_addr]).unwrap();
+        resolution.next().await.unwrap().unwrap();
+        assert_eq!(by_addr.get(ep_addr), None);
+
+        dst.add(vec![(ep_addr, Metadata::default())]).unwrap();
+        resolution.next().await.unwrap().unwrap();
+        assert!(by_addr.get(ep_addr).is_some());
+        drop(resolution);
+        assert_eq!(by_addr.get(ep_addr), None);
+    }
 }
@@ -1,21 +1,79 @@
-use super::opaque_transport::{self, OpaqueTransport};
+use super::{opaque_transport, tunnel::Multiplex};
 use crate::Outbound;
-use futures::future;
+use futures::{future, prelude::*};
 use linkerd_app_core::{
     io,
-    proxy::http,
+    proxy::{api_resolve::Labels, http},
     svc, tls,
     transport::{self, ConnectTcp, Remote, ServerAddr},
     transport_header::SessionProtocol,
-    Error,
+    Error, IpNet,
 };
-use std::task::{Context, Poll};
-use tracing::debug_span;
+use std::{
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time;
+use tracing::{debug, debug_span};
 
 #[derive(Clone, Debug)]
 pub struct Connect {
     pub addr: Remote<ServerAddr>,
     pub tls: tls::ConditionalClientTls,
+    pub fallback: Option<Remote<ServerAddr>>,
+}
+
+/// An endpoint's address of the other IP family, used if connecting to the
+/// endpoint's primary address is slow or fails.
+#[derive(Copy, Clone, Debug)]
+pub struct FallbackAddr(pub SocketAddr);
+
+/// An endpoint's metadata labels, used to select its connect timeout.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectLabels(pub Labels);
+
+/// Overrides the connect timeout for endpoints in matching networks or with
+/// matching metadata labels, e.g. so that endpoints on the local node fail
+/// fast while endpoints in remote clusters are given longer to connect.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectTimeouts(Arc<[ConnectTimeoutOverride]>);
+
+#[derive(Clone, Debug)]
+pub struct ConnectTimeoutOverride {
+    pub dst: ConnectTimeoutMatch,
+    pub timeout: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConnectTimeoutMatch {
+    /// Matches endpoints whose address is in the network.
+    Network(IpNet),
+
+    /// Matches endpoints with the metadata label.
+    Label { name: String, value: String },
+}
+
+/// Limits the time spent establishing a connection, by endpoint.
+#[derive(Clone, Debug)]
+pub struct ConnectTimeout<S> {
+    inner: S,
+    default: Duration,
+    overrides: ConnectTimeouts,
+}
+
+/// Races connections to a target's primary and fallback addresses, as
+/// described by RFC 8305 ("Happy Eyeballs").
+///
+/// The primary address is given a head start; if it has not connected when
+/// the delay elapses (or if it fails sooner), the fallback address is
+/// attempted as well and the first connection to be established is used.
+#[derive(Clone, Debug)]
+pub struct HappyEyeballs<S> {
+    inner: S,
+    delay: Duration,
 }
 
 /// Prevents outbound connections on the loopback interface, unless the
@@ -46,10 +104,13 @@ impl<C> Outbound<C> {
     where
         T: svc::Param<Remote<ServerAddr>>
             + svc::Param<tls::ConditionalClientTls>
+            + svc::Param<Option<FallbackAddr>>
             + svc::Param<Option<opaque_transport::PortOverride>>
             + svc::Param<Option<http::AuthorityOverride>>
             + svc::Param<Option<SessionProtocol>>
+            + svc::Param<ConnectLabels>
             + svc::Param<transport::labels::Key>,
+        T: Send + 'static,
         C: svc::Service<Connect, Error = io::Error> + Clone + Send + 'static,
         C::Response: tls::HasNegotiatedProtocol,
         C::Response: io::AsyncRead + io::AsyncWrite + Send + Unpin + 'static,
@@ -57,16 +118,25 @@ impl<C> Outbound<C> {
     {
         self.map_stack(|config, rt, connect| {
             connect
+                // Races dual-stack endpoints' addresses so that a broken
+                // address family does not delay the connection.
+                .push(HappyEyeballs::layer(config.happy_eyeballs_delay))
                 // Initiates mTLS if the target is configured with identity. The
                 // endpoint configures ALPN when there is an opaque transport hint OR
                 // when an authority override is present (indicating the target is a
                 // remote cluster gateway).
                 .push(tls::Client::layer(rt.identity.clone()))
+                // Records the protocol negotiated via ALPN on mTLS connections.
+                .push(rt.metrics.tls_connect_alpn.layer())
                 // Encodes a transport header if the established connection is TLS'd and
-                // ALPN negotiation indicates support.
-                .push(OpaqueTransport::layer())
+                // ALPN negotiation indicates support. Opaque connections to remote
+                // gateways may instead be multiplexed on a shared tunnel.
+                .push(Multiplex::layer(config.tcp_tunnel))
                 // Limits the time we wait for a connection to be established.
-                .push_timeout(config.proxy.connect.timeout)
+                .push(ConnectTimeout::layer(
+                    config.proxy.connect.timeout,
+                    config.connect_timeouts.clone(),
+                ))
                 .push(svc::stack::BoxFuture::layer())
                 .push(rt.metrics.transport.layer_connect())
         })
@@ -81,16 +151,19 @@ impl<C> Outbound<C> {
         >,
     >
     where
-        T: Clone + Send + 'static,
+        T: svc::Param<u16> + Clone + Send + 'static,
         I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + std::fmt::Debug + Send + Unpin + 'static,
         C: svc::Service<T> + Clone + Send + Sync + 'static,
         C::Response: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin,
         C::Error: Into<Error>,
         C::Future: Send,
     {
-        self.map_stack(|_, _, conn| {
+        self.map_stack(|config, rt, conn| {
             conn.push_make_thunk()
-                .push_on_response(super::Forward::layer())
+                .push(super::NewForward::layer(
+                    config.tcp_rate_limits.clone(),
+                    rt.metrics.tcp_idle_timeouts.idle(config.tcp_idle_timeout),
+                ))
                 .instrument(|_: &_| debug_span!("tcp.forward"))
                 .push(svc::BoxNewService::layer())
                 .check_new_service::<T, I>()
@@ -103,7 +176,8 @@ impl<C> Outbound<C> {
 impl<S> PreventLoopback<S> {
     #[cfg(not(feature = "allow-loopback"))]
     fn check_loopback(Remote(ServerAddr(addr)): Remote<ServerAddr>) -> io::Result<()> {
-        if addr.ip().is_loopback() {
+        // Discovered endpoints may have IPv4-mapped IPv6 addresses.
+        if linkerd_app_core::addr::canonical_ip(addr.ip()).is_loopback() {
             return Err(io::Error::new(
                 io::ErrorKind::ConnectionRefused,
                 "Outbound proxy cannot initiate connections on the loopback interface",
@@ -145,6 +219,153 @@ where
     }
 }
 
+// === impl ConnectTimeouts ===
+
+impl ConnectTimeouts {
+    pub fn new(overrides: impl IntoIterator<Item = ConnectTimeoutOverride>) -> Self {
+        Self(overrides.into_iter().collect())
+    }
+
+    /// Returns the timeout of the first override that matches the endpoint, if
+    /// any.
+    pub fn get(&self, addr: IpAddr, labels: &Labels) -> Option<Duration> {
+        self.0
+            .iter()
+            .find(|o| match o.dst {
+                ConnectTimeoutMatch::Network(ref net) => net.contains(&addr),
+                ConnectTimeoutMatch::Label {
+                    ref name,
+                    ref value,
+                } => labels.get(name) == Some(value),
+            })
+            .map(|o| o.timeout)
+    }
+}
+
+// === impl ConnectTimeout ===
+
+impl<S> ConnectTimeout<S> {
+    pub fn layer(
+        default: Duration,
+        overrides: ConnectTimeouts,
+    ) -> impl svc::Layer<S, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            default,
+            overrides: overrides.clone(),
+        })
+    }
+}
+
+impl<T, S> svc::Service<T> for ConnectTimeout<S>
+where
+    T: svc::Param<Remote<ServerAddr>> + svc::Param<ConnectLabels>,
+    S: svc::Service<T>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = tower::timeout::future::ResponseFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, ep: T) -> Self::Future {
+        let timeout = if self.overrides.0.is_empty() {
+            self.default
+        } else {
+            let Remote(ServerAddr(addr)) = ep.param();
+            let ConnectLabels(labels) = ep.param();
+            self.overrides
+                .get(addr.ip(), &labels)
+                .unwrap_or(self.default)
+        };
+        // The inner service has already been driven to readiness.
+        svc::Service::call(
+            &mut tower::timeout::Timeout::new(&mut self.inner, timeout),
+            ep,
+        )
+    }
+}
+
+// === impl HappyEyeballs ===
+
+impl<S> HappyEyeballs<S> {
+    pub fn layer(delay: Duration) -> impl svc::Layer<S, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self { inner, delay })
+    }
+}
+
+impl<S> svc::Service<Connect> for HappyEyeballs<S>
+where
+    S: svc::Service<Connect, Error = io::Error> + Clone + Send + 'static,
+    S::Response: Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<S::Response>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, connect: Connect) -> Self::Future {
+        let fallback = match connect.fallback {
+            Some(addr) => Connect {
+                addr,
+                tls: connect.tls.clone(),
+                fallback: None,
+            },
+            None => return Box::pin(self.inner.call(connect)),
+        };
+
+        let primary = self.inner.call(Connect {
+            fallback: None,
+            ..connect
+        });
+        let inner = self.inner.clone();
+        let delay = self.delay;
+        Box::pin(async move {
+            futures::pin_mut!(primary);
+
+            // Give the primary address a head start.
+            let sleep = time::sleep(delay);
+            futures::pin_mut!(sleep);
+            let primary_error = match future::select(primary.as_mut(), sleep).await {
+                future::Either::Left((Ok(io), _)) => return Ok(io),
+                future::Either::Left((Err(error), _)) => Some(error),
+                future::Either::Right(((), _)) => None,
+            };
+
+            debug!(addr = %fallback.addr, "Connecting to fallback address");
+            let fallback = svc::ServiceExt::oneshot(inner, fallback);
+            futures::pin_mut!(fallback);
+            if let Some(error) = primary_error {
+                debug!(%error, "Failed to connect to primary address");
+                return fallback.await;
+            }
+
+            // Use whichever connection is established first, falling back to
+            // the other if one fails.
+            match future::select(primary, fallback).await {
+                future::Either::Left((Ok(io), _)) | future::Either::Right((Ok(io), _)) => Ok(io),
+                future::Either::Left((Err(error), fallback)) => {
+                    debug!(%error, "Failed to connect to primary address");
+                    fallback.await
+                }
+                future::Either::Right((Err(error), primary)) => {
+                    debug!(%error, "Failed to connect to fallback address");
+                    primary.await
+                }
+            }
+        })
+    }
+}
+
 // === impl Connect ===
 
 impl svc::Param<Remote<ServerAddr>> for Connect {
@@ -164,8 +385,10 @@ mod tests {
     use super::*;
     use crate::{
         svc::{self, NewService, ServiceExt},
+        tcp,
         test_util::*,
     };
+    use linkerd_app_core::transport::OrigDstAddr;
     use std::net::SocketAddr;
 
     #[tokio::test]
@@ -175,8 +398,8 @@ mod tests {
         let addr = SocketAddr::new([192, 0, 2, 2].into(), 2222);
         let (rt, _shutdown) = runtime();
         let mut stack = Outbound::new(default_config(), rt)
-            .with_stack(svc::mk(move |a: SocketAddr| {
-                assert_eq!(a, addr);
+            .with_stack(svc::mk(move |ep: tcp::Endpoint| {
+                assert_eq!(ep.addr, Remote(ServerAddr(addr)));
                 let mut io = support::io();
                 io.write(b"hello").read(b"world");
                 future::ok::<_, support::io::Error>(io.build())
@@ -187,9 +410,50 @@ mod tests {
         let mut io = support::io();
         io.read(b"hello").write(b"world");
         stack
-            .new_service(addr)
+            .new_service(tcp::Endpoint::forward(
+                OrigDstAddr(addr),
+                tls::NoClientTls::Disabled,
+            ))
             .oneshot(io.build())
             .await
             .expect("forward must complete successfully");
     }
+
+    #[test]
+    fn connect_timeout_overrides() {
+        let timeouts = ConnectTimeouts::new(vec![
+            ConnectTimeoutOverride {
+                dst: ConnectTimeoutMatch::Network("10.0.0.0/8".parse().unwrap()),
+                timeout: Duration::from_millis(100),
+            },
+            ConnectTimeoutOverride {
+                dst: ConnectTimeoutMatch::Label {
+                    name: "mirror.linkerd.io/cluster-name".to_string(),
+                    value: "west".to_string(),
+                },
+                timeout: Duration::from_secs(5),
+            },
+        ]);
+        let west = Some((
+            "mirror.linkerd.io/cluster-name".to_string(),
+            "west".to_string(),
+        ))
+        .into_iter()
+        .collect::<Labels>();
+
+        assert_eq!(
+            timeouts.get([10, 1, 2, 3].into(), &west),
+            Some(Duration::from_millis(100)),
+            "the first matching override must apply"
+        );
+        assert_eq!(
+            timeouts.get([192, 0, 2, 3].into(), &west),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(timeouts.get([192, 0, 2, 3].into(), &Labels::new()), None);
+        assert_eq!(
+            ConnectTimeouts::default().get([10, 1, 2, 3].into(), &west),
+            None
+        );
+    }
 }
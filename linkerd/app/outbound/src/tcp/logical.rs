@@ -1,9 +1,9 @@
 use super::{Concrete, Endpoint, Logical};
-use crate::{endpoint, resolve, Outbound};
+use crate::{endpoint, failover, resolve, Outbound};
 use linkerd_app_core::{
     config, drain, io, profiles,
     proxy::{
-        api_resolve::{ConcreteAddr, Metadata},
+        api_resolve::{self, ConcreteAddr, Metadata},
         core::Resolve,
         resolve::map_endpoint,
         tcp,
@@ -43,32 +43,46 @@ where
         self.map_stack(|config, rt, connect| {
             let config::ProxyConfig {
                 buffer_capacity,
-                cache_max_idle_age,
                 dispatch_timeout,
                 ..
             } = config.proxy;
+            let idle_age = config.cache_idle_ages.balancer;
 
-            let identity_disabled = rt.identity.is_none();
+            let from_metadata = endpoint::FromMetadata {
+                identity_disabled: rt.identity.is_none(),
+                metadata_labels: config.metadata_labels.clone(),
+            };
             let resolve = svc::stack(resolve.into_service())
                 .check_service::<ConcreteAddr>()
+                // Merges the IPv6 and IPv4 addresses of dual-stack endpoints.
+                .push(api_resolve::DualStack::layer())
                 .push_request_filter(|c: Concrete| Ok::<_, Infallible>(c.resolve))
                 .push(svc::layer::mk(move |inner| {
-                    map_endpoint::Resolve::new(endpoint::FromMetadata { identity_disabled }, inner)
+                    map_endpoint::Resolve::new(from_metadata.clone(), inner)
                 }))
                 .check_service::<Concrete>()
                 .into_inner();
 
             connect
                 .push_make_thunk()
-                .instrument(|t: &Endpoint| match t.tls.as_ref() {
-                    Conditional::Some(tls) => {
-                        debug_span!("endpoint", server.addr = %t.addr, server.id = ?tls.server_id)
-                    }
-                    Conditional::None(_) => {
-                        debug_span!("endpoint", server.addr = %t.addr)
-                    }
+                .instrument(|t: &Endpoint| {
+                    let span = match t.tls.as_ref() {
+                        Conditional::Some(tls) => debug_span!(
+                            "endpoint",
+                            server.addr = %t.addr,
+                            server.id = ?tls.server_id,
+                            server.labels = tracing::field::Empty,
+                        ),
+                        Conditional::None(_) => debug_span!(
+                            "endpoint",
+                            server.addr = %t.addr,
+                            server.labels = tracing::field::Empty,
+                        ),
+                    };
+                    t.record_labels(&span);
+                    span
                 })
-                .push(resolve::layer(resolve, config.proxy.cache_max_idle_age * 2))
+                .push(resolve::layer(resolve, idle_age * 2))
                 .push_on_response(
                     svc::layers()
                         .push(tcp::balance::layer(
@@ -79,15 +93,30 @@ where
                             rt.metrics
                                 .stack
                                 .layer(crate::stack_labels("tcp", "balancer")),
-                        )
-                        .push(tcp::Forward::layer())
-                        .push(drain::Retain::layer(rt.drain.clone())),
+                        ),
                 )
                 .into_new_service()
+                // Forwards connections to the balancer, limiting their rate if
+                // configured for the logical port.
+                .push(tcp::NewForward::layer(
+                    config.tcp_rate_limits.clone(),
+                    rt.metrics.tcp_idle_timeouts.idle(config.tcp_idle_timeout),
+                ))
+                .push_on_response(drain::Retain::layer(rt.drain.clone()))
+                // If the local balancer has been unavailable, shift connections
+                // to the balancer for the remote cluster's mirrored service.
+                .push(failover::NewFailover::layer(
+                    config.failover.clone(),
+                    rt.metrics.failover.clone(),
+                ))
                 .push_map_target(Concrete::from)
                 .push(svc::BoxNewService::layer())
                 .check_new_service::<(ConcreteAddr, Logical), I>()
-                .push(profiles::split::layer())
+                .push(profiles::split::layer_with_isolation(
+                    profiles::split::NoOverrides::default(),
+                    config.split_isolation,
+                    rt.metrics.split.clone(),
+                ))
                 .push_on_response(
                     svc::layers()
                         .push(
@@ -99,7 +128,12 @@ where
                         .push(svc::FailFast::layer("TCP Logical", dispatch_timeout))
                         .push_spawn_buffer(buffer_capacity),
                 )
-                .push_cache(cache_max_idle_age)
+                .push_cache(
+                    idle_age,
+                    rt.metrics
+                        .cache
+                        .metrics(crate::stack_labels("tcp", "logical")),
+                )
                 .check_new_service::<Logical, I>()
                 .instrument(|_: &Logical| debug_span!("tcp"))
                 .check_new_service::<Logical, I>()
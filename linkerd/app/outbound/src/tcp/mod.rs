@@ -1,9 +1,13 @@
 pub mod connect;
 pub mod logical;
 pub mod opaque_transport;
+pub mod tunnel;
 
-pub use self::connect::Connect;
-pub use linkerd_app_core::proxy::tcp::Forward;
+pub use self::connect::{
+    Connect, ConnectLabels, ConnectTimeoutMatch, ConnectTimeoutOverride, ConnectTimeouts,
+    FallbackAddr,
+};
+pub use linkerd_app_core::proxy::tcp::{Forward, NewForward, RateLimits};
 use linkerd_app_core::{svc::Param, transport::OrigDstAddr, transport_header::SessionProtocol};
 
 pub type Accept = crate::Accept<()>;
@@ -1,11 +1,11 @@
-use crate::tcp::Connect;
+use crate::tcp::{Connect, FallbackAddr};
 use futures::prelude::*;
 use linkerd_app_core::{
     dns, io,
     proxy::http,
     svc, tls,
     transport::{Remote, ServerAddr},
-    transport_header::{SessionProtocol, TransportHeader, PROTOCOL},
+    transport_header::{self, SessionProtocol, TransportHeader},
     Error,
 };
 use std::{
@@ -31,15 +31,14 @@ impl<S> OpaqueTransport<S> {
         svc::layer::mk(|inner| OpaqueTransport { inner })
     }
 
-    /// Determines whether the connection has negotiated support for the
-    /// transport header.
+    /// Determines which version of the transport header, if any, the
+    /// connection has negotiated support for.
     #[inline]
-    fn header_negotiated<I: tls::HasNegotiatedProtocol>(io: &I) -> bool {
-        if let Some(tls::NegotiatedProtocolRef(protocol)) = io.negotiated_protocol() {
-            protocol == PROTOCOL
-        } else {
-            false
-        }
+    fn header_negotiated<I: tls::HasNegotiatedProtocol>(
+        io: &I,
+    ) -> Option<transport_header::Version> {
+        let tls::NegotiatedProtocolRef(protocol) = io.negotiated_protocol()?;
+        transport_header::Version::from_alpn(protocol)
     }
 }
 
@@ -47,6 +46,7 @@ impl<T, S> svc::Service<T> for OpaqueTransport<S>
 where
     T: svc::Param<tls::ConditionalClientTls>
         + svc::Param<Remote<ServerAddr>>
+        + svc::Param<Option<FallbackAddr>>
         + svc::Param<Option<PortOverride>>
         + svc::Param<Option<http::AuthorityOverride>>
         + svc::Param<Option<SessionProtocol>>,
@@ -66,11 +66,13 @@ where
 
     fn call(&mut self, ep: T) -> Self::Future {
         let tls: tls::ConditionalClientTls = ep.param();
+        let fallback: Option<FallbackAddr> = ep.param();
         if let tls::ConditionalClientTls::None(reason) = tls {
             trace!(%reason, "Not attempting opaque transport");
             let target = Connect {
                 addr: ep.param(),
                 tls,
+                fallback: fallback.map(|FallbackAddr(addr)| Remote(ServerAddr(addr))),
             };
             return Box::pin(self.inner.call(target).err_into::<Error>());
         }
@@ -113,21 +115,24 @@ where
         let connect = self.inner.call(Connect {
             addr: Remote(ServerAddr((addr.ip(), connect_port).into())),
             tls,
+            fallback: fallback
+                .map(|FallbackAddr(addr)| Remote(ServerAddr((addr.ip(), connect_port).into()))),
         });
         Box::pin(async move {
             let mut io = connect.await.map_err(Into::into)?;
 
             // If transport header support has been negotiated via ALPN, encode
             // the header and then return the socket.
-            if Self::header_negotiated(&io) {
+            if let Some(version) = Self::header_negotiated(&io) {
                 let header = TransportHeader {
                     port: target_port,
                     name,
                     protocol,
+                    extensions: Default::default(),
                 };
-                trace!(?header, "Writing transport header");
-                let sz = header.write(&mut io).await?;
-                debug!(sz, "Wrote transport header");
+                trace!(?header, ?version, "Writing transport header");
+                let sz = header.write(version, &mut io).await?;
+                debug!(sz, ?version, "Wrote transport header");
             } else {
                 trace!("Connection does not expect a transport header");
             }
@@ -148,7 +153,7 @@ mod test {
         proxy::api_resolve::{Metadata, ProtocolHint},
         tls,
         transport::{Remote, ServerAddr},
-        transport_header::TransportHeader,
+        transport_header::{TransportHeader, Version, PROTOCOL, PROTOCOL_V2},
     };
     use pin_project::pin_project;
     use std::task::Context;
@@ -160,6 +165,7 @@ mod test {
             metadata,
             tls::NoClientTls::NotProvidedByServiceDiscovery,
             false,
+            Default::default(),
         )
     }
 
@@ -198,8 +204,9 @@ mod test {
                     port: 4321,
                     name: None,
                     protocol: None,
+                    extensions: Default::default(),
                 };
-                let buf = hdr.encode_prefaced_buf().expect("Must encode");
+                let buf = hdr.encode_prefaced_buf(Version::V1).expect("Must encode");
                 future::ready(Ok::<_, io::Error>(Io {
                     alpn: Some(tls::NegotiatedProtocolRef(PROTOCOL)),
                     io: tokio_test::io::Builder::new()
@@ -236,8 +243,9 @@ mod test {
                     port: 5555,
                     name: Some(dns::Name::from_str("foo.bar.example.com").unwrap()),
                     protocol: None,
+                    extensions: Default::default(),
                 };
-                let buf = hdr.encode_prefaced_buf().expect("Must encode");
+                let buf = hdr.encode_prefaced_buf(Version::V1).expect("Must encode");
                 future::ready(Ok::<_, io::Error>(Io {
                     alpn: Some(tls::NegotiatedProtocolRef(PROTOCOL)),
                     io: tokio_test::io::Builder::new()
@@ -274,8 +282,9 @@ mod test {
                     port: 4321,
                     name: None,
                     protocol: None,
+                    extensions: Default::default(),
                 };
-                let buf = hdr.encode_prefaced_buf().expect("Must encode");
+                let buf = hdr.encode_prefaced_buf(Version::V1).expect("Must encode");
                 future::ready(Ok::<_, io::Error>(Io {
                     alpn: Some(tls::NegotiatedProtocolRef(PROTOCOL)),
                     io: tokio_test::io::Builder::new()
@@ -299,6 +308,45 @@ mod test {
         io.write_all(b"hello").await.expect("Write must succeed");
     }
 
+    #[tokio::test(flavor = "current_thread")]
+    async fn opaque_v2() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let svc = OpaqueTransport {
+            inner: service_fn(|ep: Connect| {
+                let Remote(ServerAddr(sa)) = ep.addr;
+                assert_eq!(sa.port(), 4143);
+                assert!(ep.tls.is_some());
+                let hdr = TransportHeader {
+                    port: 4321,
+                    name: None,
+                    protocol: None,
+                    extensions: Default::default(),
+                };
+                let buf = hdr.encode_prefaced_buf(Version::V2).expect("Must encode");
+                future::ready(Ok::<_, io::Error>(Io {
+                    alpn: Some(tls::NegotiatedProtocolRef(PROTOCOL_V2)),
+                    io: tokio_test::io::Builder::new()
+                        .write(&buf[..])
+                        .write(b"hello")
+                        .build(),
+                }))
+            }),
+        };
+
+        let e = ep(Metadata::new(
+            None,
+            ProtocolHint::Unknown,
+            Some(4143),
+            Some(tls::ServerId(
+                identity::Name::from_str("server.id").unwrap(),
+            )),
+            None,
+        ));
+        let mut io = svc.oneshot(e).await.expect("Connect must not fail");
+        io.write_all(b"hello").await.expect("Write must succeed");
+    }
+
     #[pin_project]
     pub struct Io {
         #[pin]
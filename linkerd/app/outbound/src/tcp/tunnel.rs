@@ -0,0 +1,228 @@
+use super::{
+    opaque_transport::{OpaqueTransport, PortOverride},
+    Connect, FallbackAddr,
+};
+use futures::prelude::*;
+use linkerd_app_core::{
+    io,
+    proxy::{http, tunnel},
+    svc::{self, Layer, ServiceExt},
+    tls,
+    transport::{Remote, ServerAddr},
+    transport_header::SessionProtocol,
+    Conditional, Error,
+};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tracing::{debug, trace};
+
+/// Multiplexes opaque connections to remote gateways as streams on a single
+/// tunnel connection to each gateway.
+///
+/// Other connections, and connections to gateways that do not support
+/// tunnels, are established by an `OpaqueTransport`.
+#[derive(Clone, Debug)]
+pub struct Multiplex<S> {
+    tunnels: Option<Tunnels>,
+    connect: S,
+    inner: OpaqueTransport<S>,
+}
+
+type Tunnels = Arc<Mutex<HashMap<Key, Arc<tokio::sync::Mutex<Tunnel>>>>>;
+
+/// Identifies a gateway's tunnel.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Key {
+    addr: SocketAddr,
+    server_id: tls::ServerId,
+}
+
+#[derive(Debug)]
+enum Tunnel {
+    Disconnected,
+    Connected(tunnel::Client),
+    /// The gateway did not negotiate the tunnel protocol, so connections to
+    /// it are not multiplexed.
+    Unsupported,
+}
+
+// === impl Multiplex ===
+
+impl<S: Clone> Multiplex<S> {
+    pub fn layer(enabled: bool) -> impl svc::Layer<S, Service = Self> + Clone {
+        let tunnels = if enabled {
+            Some(Tunnels::default())
+        } else {
+            None
+        };
+        svc::layer::mk(move |connect: S| Self {
+            tunnels: tunnels.clone(),
+            inner: OpaqueTransport::layer().layer(connect.clone()),
+            connect,
+        })
+    }
+}
+
+impl<S> Multiplex<S> {
+    /// Describes the tunnel for an endpoint, if the endpoint is a remote
+    /// gateway that is reached with mTLS and the connection is opaque.
+    fn tunnel_target<T>(ep: &T) -> Option<(Key, Connect, http::uri::Authority)>
+    where
+        T: svc::Param<tls::ConditionalClientTls>
+            + svc::Param<Remote<ServerAddr>>
+            + svc::Param<Option<FallbackAddr>>
+            + svc::Param<Option<PortOverride>>
+            + svc::Param<Option<http::AuthorityOverride>>
+            + svc::Param<Option<SessionProtocol>>,
+    {
+        let tls: tls::ConditionalClientTls = ep.param();
+        let server_id = tls.value()?.server_id.clone();
+        let protocol: Option<SessionProtocol> = ep.param();
+        if protocol.is_some() {
+            return None;
+        }
+        let authority = match ep.param() {
+            Some(http::AuthorityOverride(authority)) if authority.port_u16().is_some() => authority,
+            _ => return None,
+        };
+
+        // Tunnels target the gateway proxy's inbound port.
+        let Remote(ServerAddr(addr)) = ep.param();
+        let port = match ep.param() {
+            Some(PortOverride(port)) => port,
+            None => addr.port(),
+        };
+        let addr = SocketAddr::new(addr.ip(), port);
+        let fallback: Option<FallbackAddr> = ep.param();
+        let connect = Connect {
+            addr: Remote(ServerAddr(addr)),
+            tls: Conditional::Some(tls::ClientTls {
+                server_id: server_id.clone(),
+                alpn: Some(tls::client::AlpnProtocols(vec![tunnel::PROTOCOL.into()])),
+            }),
+            fallback: fallback
+                .map(|FallbackAddr(fallback)| Remote(ServerAddr((fallback.ip(), port).into()))),
+        };
+        Some((Key { addr, server_id }, connect, authority))
+    }
+}
+
+impl<T, S> svc::Service<T> for Multiplex<S>
+where
+    T: svc::Param<tls::ConditionalClientTls>
+        + svc::Param<Remote<ServerAddr>>
+        + svc::Param<Option<FallbackAddr>>
+        + svc::Param<Option<PortOverride>>
+        + svc::Param<Option<http::AuthorityOverride>>
+        + svc::Param<Option<SessionProtocol>>,
+    T: Send + 'static,
+    S: svc::Service<Connect> + Clone + Send + 'static,
+    S::Error: Into<Error>,
+    S::Response: io::AsyncRead + io::AsyncWrite + tls::HasNegotiatedProtocol + Send + Unpin,
+    S::Response: 'static,
+    S::Future: Send + 'static,
+{
+    type Response = io::EitherIo<S::Response, tunnel::Stream>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, ep: T) -> Self::Future {
+        let tunnels = match self.tunnels.as_ref() {
+            Some(tunnels) => tunnels,
+            None => return Box::pin(self.inner.call(ep).map_ok(io::EitherIo::Left)),
+        };
+        let (key, target, authority) = match Self::tunnel_target(&ep) {
+            Some(target) => target,
+            None => return Box::pin(self.inner.call(ep).map_ok(io::EitherIo::Left)),
+        };
+
+        let state = tunnels.lock().entry(key).or_default().clone();
+        let connect = self.connect.clone();
+        let inner = self.inner.clone();
+        Box::pin(async move {
+            // If the tunnel has closed, a new one is established, but only
+            // once per connection.
+            let mut reconnected = false;
+            loop {
+                let client = match Self::client(&state, connect.clone(), target.clone()).await? {
+                    Some(client) => client,
+                    None => return inner.oneshot(ep).await.map(io::EitherIo::Left),
+                };
+                match client.open(authority.clone()).await {
+                    Ok(stream) => return Ok(io::EitherIo::Right(stream)),
+                    Err(error) if client.is_closed() && !reconnected => {
+                        debug!(%error, "Tunnel closed");
+                        reconnected = true;
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+        })
+    }
+}
+
+impl<S> Multiplex<S>
+where
+    S: svc::Service<Connect>,
+    S::Error: Into<Error>,
+    S::Response: io::AsyncRead + io::AsyncWrite + tls::HasNegotiatedProtocol + Send + Unpin,
+    S::Response: 'static,
+{
+    /// Returns the gateway's tunnel, establishing it if necessary.
+    ///
+    /// Returns `None` if the gateway does not support tunnels.
+    async fn client(
+        state: &tokio::sync::Mutex<Tunnel>,
+        connect: S,
+        target: Connect,
+    ) -> Result<Option<tunnel::Client>, Error> {
+        // Connections wait while the tunnel is established so that only one
+        // connection is made to each gateway.
+        let mut state = state.lock().await;
+        match *state {
+            Tunnel::Connected(ref client) if !client.is_closed() => {
+                return Ok(Some(client.clone()))
+            }
+            Tunnel::Unsupported => return Ok(None),
+            _ => {}
+        }
+
+        let Remote(ServerAddr(addr)) = target.addr;
+        debug!(%addr, "Establishing tunnel");
+        let io = connect.oneshot(target).await.map_err(Into::into)?;
+        let negotiated = match io.negotiated_protocol() {
+            Some(tls::NegotiatedProtocolRef(protocol)) => protocol == tunnel::PROTOCOL,
+            None => false,
+        };
+        if !negotiated {
+            debug!(%addr, "Gateway does not support tunnels");
+            *state = Tunnel::Unsupported;
+            return Ok(None);
+        }
+
+        let client = tunnel::Client::handshake(io, addr).await?;
+        trace!(%addr, "Established tunnel");
+        *state = Tunnel::Connected(client.clone());
+        Ok(Some(client))
+    }
+}
+
+// === impl Tunnel ===
+
+impl Default for Tunnel {
+    fn default() -> Self {
+        Self::Disconnected
+    }
+}
@@ -15,11 +15,44 @@ use std::{str::FromStr, time::Duration};
 pub fn default_config() -> Config {
     Config {
         ingress_mode: false,
+        #[cfg(feature = "ingress-script")]
+        ingress_script: None,
+        ingress_tenants: None,
+        failover: None,
+        metadata_labels: Default::default(),
+        decompression: false,
+        egress: None,
+        bypass: Default::default(),
+        protocol_overrides: Default::default(),
+        server_speaks_first_ports: Default::default(),
+        protocol_hints: Default::default(),
+        tcp_rate_limits: Default::default(),
+        tcp_idle_timeout: None,
+        tcp_tunnel: false,
+        connect_timeouts: Default::default(),
+        cache_idle_ages: crate::CacheIdleAges {
+            profile: Duration::from_secs(60),
+            balancer: Duration::from_secs(60),
+            ingress: Duration::from_secs(60),
+        },
+        warm_destinations: Vec::new(),
+        happy_eyeballs_delay: Duration::from_millis(250),
+        http_affinity: None,
+        split_overrides: false,
+        split_concrete_header: false,
+        split_isolation: None,
+        http_route_defaults: Default::default(),
+        http_deadline: None,
+        http_idempotency: None,
+        http_response_cache: None,
+        #[cfg(feature = "wasm")]
+        http_wasm: None,
         allow_discovery: IpMatch::new(Some(IpNet::from_str("0.0.0.0/0").unwrap())).into(),
         proxy: config::ProxyConfig {
             server: config::ServerConfig {
                 addr: ListenAddr(([0, 0, 0, 0], 0).into()),
                 keepalive: Keepalive(None),
+                h1_settings: h1::ServerSettings::default(),
                 h2_settings: h2::Settings::default(),
             },
             connect: config::ConnectConfig {
@@ -42,20 +75,29 @@ pub fn default_config() -> Config {
             dispatch_timeout: Duration::from_secs(3),
             max_in_flight_requests: 10_000,
             detect_protocol_timeout: Duration::from_secs(3),
+            queue_time_header: false,
+            fail_fast_retry_after: false,
+            error_responses: Default::default(),
+            request_limits: Default::default(),
+            request_id: None,
+            fd_pressure: Default::default(),
+            trace_attributes: Default::default(),
         },
     }
 }
 
 pub fn runtime() -> (ProxyRuntime, drain::Signal) {
-    let (metrics, _) = metrics::Metrics::new(std::time::Duration::from_secs(10));
+    let (metrics, _) = metrics::Metrics::new(std::time::Duration::from_secs(10), 10);
     let (drain_tx, drain) = drain::channel();
     let (tap, _) = tap::new();
     let runtime = ProxyRuntime {
         identity: None,
         metrics: metrics.outbound,
         tap,
+        connections: Default::default(),
         span_sink: None,
         drain,
+        extensions: Default::default(),
     };
     (runtime, drain_tx)
 }
@@ -0,0 +1,198 @@
+use crate::{tcp, Outbound};
+use futures::prelude::*;
+use linkerd_app_core::{
+    profiles,
+    proxy::{
+        api_resolve::{ConcreteAddr, Metadata},
+        core::{Resolve, Update},
+    },
+    svc::{self, ServiceExt},
+    transport::OrigDstAddr,
+    AddrMatch, Error,
+};
+use std::net::SocketAddr;
+use tokio::time;
+use tracing::{debug, info, info_span, Instrument};
+
+impl<N> Outbound<N> {
+    /// Warms the discover stack's cache for the configured warm destinations.
+    ///
+    /// A server is built for each destination and held for the profile cache's
+    /// idle timeout, so that the application's first connections to the
+    /// destination reuse it. Meanwhile, the destination's profile is
+    /// discovered and its endpoints are resolved so that the control plane
+    /// connection is established and the destination controller is watching
+    /// the destination before the application connects to it.
+    pub(crate) fn spawn_warm<P, R>(&self, profiles: P, resolve: R)
+    where
+        N: svc::NewService<tcp::Accept> + Clone,
+        N::Service: Send + 'static,
+        P: profiles::GetProfile<profiles::LookupAddr> + Clone + Send + 'static,
+        P::Future: Send,
+        P::Error: Send,
+        R: Resolve<ConcreteAddr, Endpoint = Metadata, Error = Error> + Clone + Send + 'static,
+        R::Resolution: Send,
+        R::Future: Send,
+    {
+        let idle = self.config.cache_idle_ages.profile;
+        let mut discover = self.stack.clone().into_inner();
+        for &addr in self.config.warm_destinations.iter() {
+            let server = discover.new_service(tcp::Accept::from(OrigDstAddr(addr)));
+            let warm = warm(
+                addr,
+                self.config.allow_discovery.clone(),
+                profiles.clone(),
+                resolve.clone(),
+            );
+            tokio::spawn(
+                async move {
+                    let deadline = time::Instant::now() + idle;
+                    warm.await;
+                    // The cached server is retained for another idle timeout
+                    // after it is dropped.
+                    time::sleep_until(deadline).await;
+                    drop(server);
+                }
+                .instrument(info_span!("warm", dst = %addr)),
+            );
+        }
+    }
+}
+
+async fn warm<P, R>(addr: SocketAddr, allow: AddrMatch, mut profiles: P, resolve: R)
+where
+    P: profiles::GetProfile<profiles::LookupAddr>,
+    R: Resolve<ConcreteAddr, Endpoint = Metadata, Error = Error>,
+{
+    if !allow.matches_ip(addr.ip()) {
+        debug!(networks = %allow.nets(), "Not in configured search networks");
+        return;
+    }
+
+    let start = time::Instant::now();
+    let profile = match profiles
+        .get_profile(profiles::LookupAddr(addr.into()))
+        .await
+    {
+        Ok(profile) => profile,
+        Err(error) => {
+            let error: Error = error.into();
+            info!(%error, "Failed to discover profile");
+            return;
+        }
+    };
+
+    // Only logical destinations have endpoints to resolve.
+    let addr = match profile.as_ref().and_then(|p| p.logical_addr()) {
+        Some(profiles::LogicalAddr(addr)) => addr,
+        None => {
+            debug!(elapsed = ?start.elapsed(), "Warmed");
+            return;
+        }
+    };
+    let resolution = match resolve.into_service().oneshot(ConcreteAddr(addr)).await {
+        Ok(resolution) => resolution,
+        Err(error) => {
+            info!(%error, "Failed to resolve endpoints");
+            return;
+        }
+    };
+    futures::pin_mut!(resolution);
+    let endpoints = match resolution.next().await {
+        Some(Ok(Update::Reset(endpoints))) | Some(Ok(Update::Add(endpoints))) => endpoints.len(),
+        Some(Ok(_)) => 0,
+        Some(Err(error)) => {
+            info!(%error, "Failed to resolve endpoints");
+            return;
+        }
+        None => 0,
+    };
+
+    debug!(endpoints, elapsed = ?start.elapsed(), "Warmed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use linkerd_app_core::{
+        io,
+        metrics::FmtMetrics,
+        svc::{NewService, Service},
+        transport::metrics::SensorIo,
+    };
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    /// Tests that the application's first connection to a warm destination
+    /// reuses the server that was built when the proxy started, even after
+    /// the profile cache's idle timeout.
+    #[tokio::test(flavor = "current_thread")]
+    async fn first_connection_reuses_warmed_server() {
+        let _trace = linkerd_tracing::test::trace_init();
+        time::pause();
+
+        let addr = SocketAddr::new([192, 0, 2, 22].into(), 5550);
+        let idle_timeout = time::Duration::from_secs(1);
+
+        let new_count = Arc::new(AtomicUsize::new(0));
+        let (handle, stack) = {
+            let new_count = new_count.clone();
+            support::track::new_service(move |_| {
+                new_count.fetch_add(1, Ordering::SeqCst);
+                svc::mk(move |_: SensorIo<io::DuplexStream>| future::pending::<Result<(), Error>>())
+            })
+        };
+
+        let profiles = support::profile::resolver().profile(addr, profiles::Profile::default());
+
+        let cfg = {
+            let mut cfg = default_config();
+            cfg.cache_idle_ages.profile = idle_timeout;
+            cfg.warm_destinations = vec![addr];
+            cfg
+        };
+        let (rt, _shutdown) = runtime();
+        let cache = rt.metrics.cache.clone();
+        let discover = Outbound::new(cfg, rt)
+            .with_stack(stack)
+            .push_discover(profiles.clone());
+        discover.spawn_warm(profiles, support::resolver());
+        let mut stack = discover.into_inner();
+
+        // Let the warm destination's idle timeout elapse before the
+        // application connects.
+        time::sleep(idle_timeout + time::Duration::from_millis(1)).await;
+
+        let svc = stack.new_service(tcp::Accept::from(OrigDstAddr(addr)));
+        let (server_io, _client_io) = io::duplex(1);
+        let task = tokio::spawn(async move {
+            let mut svc = svc;
+            svc.ready().await?.call(server_io).await
+        });
+        time::advance(time::Duration::from_millis(100)).await;
+        assert_eq!(
+            new_count.load(Ordering::SeqCst),
+            1,
+            "exactly one service has been created"
+        );
+        assert_eq!(handle.tracked_services(), 1, "the service should be active");
+
+        let metrics = cache.as_display().to_string();
+        let labels = r#"{direction="outbound",protocol="tcp",name="server"}"#;
+        assert!(
+            metrics.contains(&format!("stack_cache_misses_total{} 1\n", labels)),
+            "the warmed server should be the only one built:\n{}",
+            metrics
+        );
+        assert!(
+            metrics.contains(&format!("stack_cache_hits_total{} 1\n", labels)),
+            "the connection should reuse the warmed server:\n{}",
+            metrics
+        );
+
+        task.abort();
+    }
+}
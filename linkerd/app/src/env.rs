@@ -1,11 +1,16 @@
 use crate::core::{
-    addr,
+    addr, compress,
     config::*,
     control::{Config as ControlConfig, ControlAddr},
-    proxy::http::{h1, h2},
-    tls,
+    errors, ext_authz, fd_pressure, http_cache, http_tracing, idempotency, jwt, load_shed,
+    opencensus, profiles, protocol_hints,
+    proxy::{
+        http::{self, balance::affinity, deadline, h1, h2},
+        tcp,
+    },
+    request_id, request_limits, tls,
     transport::{Keepalive, ListenAddr},
-    Addr, AddrMatch, Conditional, IpNet, NameMatch,
+    Addr, AddrMatch, Conditional, IpMatch, IpNet, Ipv4Net, Ipv6Net, NameMatch, NameRule,
 };
 use crate::{dns, gateway, identity, inbound, oc_collector, outbound};
 use inbound::port_policies;
@@ -13,6 +18,8 @@ use std::{
     collections::{HashMap, HashSet},
     fs,
     net::SocketAddr,
+    num::NonZeroUsize,
+    ops::RangeInclusive,
     path::PathBuf,
     str::FromStr,
     time::Duration,
@@ -20,6 +27,9 @@ use std::{
 use thiserror::Error;
 use tracing::{debug, error, warn};
 
+#[cfg(feature = "wasm")]
+use crate::core::http_wasm;
+
 /// The strings used to build a configuration.
 pub trait Strings {
     /// Retrieves the value for the key `key`.
@@ -76,8 +86,55 @@ pub enum ParseError {
     InvalidTokenSource,
     #[error("invalid trust anchors")]
     InvalidTrustAnchors,
+    #[error("reload interval must be greater than zero")]
+    ZeroReloadInterval,
     #[error("not a valid port policy: {0}")]
     InvalidPortPolicy(String),
+    #[error("not a valid header name")]
+    InvalidHeaderName,
+    #[error("not a valid URI")]
+    InvalidUri,
+    #[error(transparent)]
+    InvalidJwks(jwt::InvalidJwks),
+    #[error("not a valid required claims rule: {0}")]
+    InvalidRequiredClaims(String),
+    #[error("not a valid gateway cluster: {0}")]
+    InvalidGatewayCluster(String),
+    #[error("not a valid federated trust domain: {0}")]
+    InvalidTrustDomain(String),
+    #[error("not a valid failover cluster: {0}")]
+    InvalidFailoverCluster(String),
+    #[error("not a valid egress policy: {0}")]
+    InvalidEgressPolicy(String),
+    #[error("not a valid outbound protocol override: {0}")]
+    InvalidProtocolOverride(String),
+    #[error("not a valid outbound bypass rule: {0}")]
+    InvalidBypassRule(String),
+    #[error("not a valid connect timeout override: {0}")]
+    InvalidConnectTimeout(String),
+    #[error("not a valid rate limit: {0}")]
+    InvalidRateLimit(String),
+    #[error("not a valid connection limit: {0}")]
+    InvalidConnectionLimit(String),
+    #[error("not a valid client priority: {0}")]
+    InvalidPriority(String),
+    #[error("not a valid DNS nameserver: {0}")]
+    InvalidNameserver(String),
+    #[error("not a valid DNS transport: {0}")]
+    InvalidDnsTransport(String),
+    #[error("not a valid cookie name: {0}")]
+    InvalidCookieName(String),
+    #[error("not a valid fault: {0}")]
+    InvalidFault(String),
+    #[error("not a valid malformed request policy: {0}")]
+    InvalidMalformedPolicy(String),
+    #[cfg(feature = "wasm")]
+    #[error("not a valid WASM filter: {0}")]
+    InvalidWasmFilter(String),
+    #[error("not a valid ingress tenant: {0}")]
+    InvalidIngressTenant(String),
+    #[error(transparent)]
+    InvalidSpanAttribute(http_tracing::InvalidAttribute),
 }
 
 // Environment variables to look at when loading the configuration
@@ -88,43 +145,386 @@ pub const ENV_ADMIN_LISTEN_ADDR: &str = "LINKERD2_PROXY_ADMIN_LISTEN_ADDR";
 
 pub const ENV_METRICS_RETAIN_IDLE: &str = "LINKERD2_PROXY_METRICS_RETAIN_IDLE";
 
-const ENV_INGRESS_MODE: &str = "LINKERD2_PROXY_INGRESS_MODE";
+/// Limits the number of distinct peer identities that label the transport
+/// metrics that are reported by peer. Connections with other peers are
+/// reported under the `*` identity.
+pub const ENV_METRICS_PEER_IDENTITY_LIMIT: &str = "LINKERD2_PROXY_METRICS_PEER_IDENTITY_LIMIT";
+
+/// Configures how long the proxy waits, after a drain is requested via the
+/// admin server's `/drain` endpoint, before it drains. A drain may be
+/// canceled via the `/undrain` endpoint until then.
+pub const ENV_ADMIN_DRAIN_GRACE_PERIOD: &str = "LINKERD2_PROXY_ADMIN_DRAIN_GRACE_PERIOD";
+
+pub const ENV_INGRESS_MODE: &str = "LINKERD2_PROXY_INGRESS_MODE";
+
+/// Configures a script (in the Rhai language) that selects the destination of
+/// ingress-mode requests that do not set the `l5d-dst-override` header. The
+/// script returns a `host:port` string, or `()` to route the request by its
+/// authority.
+///
+/// Requires the proxy to be built with the `ingress-script` feature.
+pub const ENV_INGRESS_ROUTE_SCRIPT: &str = "LINKERD2_PROXY_INGRESS_ROUTE_SCRIPT";
+
+/// Limits the number of operations the ingress routing script may perform
+/// for each request. Defaults to 10,000.
+pub const ENV_INGRESS_ROUTE_SCRIPT_MAX_OPERATIONS: &str =
+    "LINKERD2_PROXY_INGRESS_ROUTE_SCRIPT_MAX_OPERATIONS";
+
+/// Configures the tenants of a shared ingress proxy.
+///
+/// The value is a comma-separated list of `<tenant>=<destination-suffix>`
+/// entries, each permitting the tenant's requests to be routed to
+/// destinations in the suffix. A tenant may be listed more than once.
+///
+/// When set, ingress-mode requests that do not name a configured tenant are
+/// rejected.
+pub const ENV_INGRESS_TENANTS: &str = "LINKERD2_PROXY_INGRESS_TENANTS";
+
+/// Names the request header that identifies an ingress request's tenant.
+/// Defaults to `l5d-tenant`.
+pub const ENV_INGRESS_TENANT_HEADER: &str = "LINKERD2_PROXY_INGRESS_TENANT_HEADER";
+
+/// Limits the number of each tenant's ingress requests that may be in flight
+/// at once, as a comma-separated list of `<tenant>=<max-requests>` entries.
+/// Tenants without an entry are not limited.
+pub const ENV_INGRESS_TENANT_QUOTAS: &str = "LINKERD2_PROXY_INGRESS_TENANT_QUOTAS";
 
 const ENV_INBOUND_DISPATCH_TIMEOUT: &str = "LINKERD2_PROXY_INBOUND_DISPATCH_TIMEOUT";
 const ENV_OUTBOUND_DISPATCH_TIMEOUT: &str = "LINKERD2_PROXY_OUTBOUND_DISPATCH_TIMEOUT";
 
+/// Enables `Retry-After` headers, derived from the dispatch timeout, on
+/// responses to HTTP requests that fail because a stack is in fail-fast.
+/// Defaults to false.
+pub const ENV_INBOUND_FAIL_FAST_RETRY_AFTER_ENABLED: &str =
+    "LINKERD2_PROXY_INBOUND_FAIL_FAST_RETRY_AFTER_ENABLED";
+pub const ENV_OUTBOUND_FAIL_FAST_RETRY_AFTER_ENABLED: &str =
+    "LINKERD2_PROXY_OUTBOUND_FAIL_FAST_RETRY_AFTER_ENABLED";
+
 pub const ENV_INBOUND_DETECT_TIMEOUT: &str = "LINKERD2_PROXY_INBOUND_DETECT_TIMEOUT";
 const ENV_OUTBOUND_DETECT_TIMEOUT: &str = "LINKERD2_PROXY_OUTBOUND_DETECT_TIMEOUT";
 
 const ENV_INBOUND_CONNECT_TIMEOUT: &str = "LINKERD2_PROXY_INBOUND_CONNECT_TIMEOUT";
 const ENV_OUTBOUND_CONNECT_TIMEOUT: &str = "LINKERD2_PROXY_OUTBOUND_CONNECT_TIMEOUT";
 
+/// Overrides the outbound connect timeout for endpoints in matching networks or
+/// with matching metadata labels, as a comma-separated list of
+/// `<network>=<timeout>` and `<label>:<value>=<timeout>` entries, e.g.
+/// `10.0.0.0/8=100ms,mirror.linkerd.io/cluster-name:west=5s`. The first
+/// matching entry applies.
+const ENV_OUTBOUND_CONNECT_TIMEOUT_OVERRIDES: &str =
+    "LINKERD2_PROXY_OUTBOUND_CONNECT_TIMEOUT_OVERRIDES";
+
 const ENV_INBOUND_ACCEPT_KEEPALIVE: &str = "LINKERD2_PROXY_INBOUND_ACCEPT_KEEPALIVE";
 const ENV_OUTBOUND_ACCEPT_KEEPALIVE: &str = "LINKERD2_PROXY_OUTBOUND_ACCEPT_KEEPALIVE";
 
+/// Configures how long an accepted HTTP/2 connection may be used before the
+/// client is sent a GOAWAY, so that long-lived clients periodically reconnect.
+/// If unset, accepted connections are not closed by age.
+const ENV_INBOUND_ACCEPT_MAX_CONNECTION_AGE: &str =
+    "LINKERD2_PROXY_INBOUND_ACCEPT_MAX_CONNECTION_AGE";
+const ENV_OUTBOUND_ACCEPT_MAX_CONNECTION_AGE: &str =
+    "LINKERD2_PROXY_OUTBOUND_ACCEPT_MAX_CONNECTION_AGE";
+
+/// Configures how long an accepted HTTP/1 connection may be idle before it is
+/// closed. If unset, idle connections are held open until the client closes
+/// them.
+const ENV_INBOUND_ACCEPT_HTTP1_IDLE_TIMEOUT: &str =
+    "LINKERD2_PROXY_INBOUND_ACCEPT_HTTP1_IDLE_TIMEOUT";
+const ENV_OUTBOUND_ACCEPT_HTTP1_IDLE_TIMEOUT: &str =
+    "LINKERD2_PROXY_OUTBOUND_ACCEPT_HTTP1_IDLE_TIMEOUT";
+
+/// Configures how many requests an accepted HTTP/1 connection may serve. The
+/// final response is sent with `Connection: close`. If unset, connections may
+/// be reused indefinitely.
+const ENV_INBOUND_ACCEPT_HTTP1_MAX_REQUESTS: &str =
+    "LINKERD2_PROXY_INBOUND_ACCEPT_HTTP1_MAX_REQUESTS";
+const ENV_OUTBOUND_ACCEPT_HTTP1_MAX_REQUESTS: &str =
+    "LINKERD2_PROXY_OUTBOUND_ACCEPT_HTTP1_MAX_REQUESTS";
+
+/// Configures whether responses on accepted HTTP/1 connections are sent with
+/// `Connection: close` once the proxy begins shutting down. Defaults to false.
+const ENV_INBOUND_ACCEPT_HTTP1_CLOSE_ON_DRAIN: &str =
+    "LINKERD2_PROXY_INBOUND_ACCEPT_HTTP1_CLOSE_ON_DRAIN";
+const ENV_OUTBOUND_ACCEPT_HTTP1_CLOSE_ON_DRAIN: &str =
+    "LINKERD2_PROXY_OUTBOUND_ACCEPT_HTTP1_CLOSE_ON_DRAIN";
+
+/// Limits the size of HTTP request heads accepted by the proxy: the number of
+/// header fields, the size of each field (its name and value), the combined
+/// size of all fields, and the length of the URI, respectively. Requests that
+/// exceed a limit fail with a 431 (or, for URIs, 414) response and are recorded
+/// by reason in error metrics. If unset, only the HTTP library's limits apply.
+const ENV_INBOUND_MAX_REQUEST_HEADERS: &str = "LINKERD2_PROXY_INBOUND_MAX_REQUEST_HEADERS";
+const ENV_OUTBOUND_MAX_REQUEST_HEADERS: &str = "LINKERD2_PROXY_OUTBOUND_MAX_REQUEST_HEADERS";
+const ENV_INBOUND_MAX_REQUEST_HEADER_SIZE: &str = "LINKERD2_PROXY_INBOUND_MAX_REQUEST_HEADER_SIZE";
+const ENV_OUTBOUND_MAX_REQUEST_HEADER_SIZE: &str =
+    "LINKERD2_PROXY_OUTBOUND_MAX_REQUEST_HEADER_SIZE";
+const ENV_INBOUND_MAX_REQUEST_HEADER_LIST_SIZE: &str =
+    "LINKERD2_PROXY_INBOUND_MAX_REQUEST_HEADER_LIST_SIZE";
+const ENV_OUTBOUND_MAX_REQUEST_HEADER_LIST_SIZE: &str =
+    "LINKERD2_PROXY_OUTBOUND_MAX_REQUEST_HEADER_LIST_SIZE";
+const ENV_INBOUND_MAX_REQUEST_URI_LENGTH: &str = "LINKERD2_PROXY_INBOUND_MAX_REQUEST_URI_LENGTH";
+const ENV_OUTBOUND_MAX_REQUEST_URI_LENGTH: &str = "LINKERD2_PROXY_OUTBOUND_MAX_REQUEST_URI_LENGTH";
+
 const ENV_INBOUND_CONNECT_KEEPALIVE: &str = "LINKERD2_PROXY_INBOUND_CONNECT_KEEPALIVE";
 const ENV_OUTBOUND_CONNECT_KEEPALIVE: &str = "LINKERD2_PROXY_OUTBOUND_CONNECT_KEEPALIVE";
 
+/// Configures how long an outbound HTTP/2 connection to an endpoint may be used
+/// before it is gracefully closed and replaced, so that long-lived connections
+/// do not prevent traffic from being rebalanced. If unset, connections are not
+/// retired by age.
+const ENV_OUTBOUND_MAX_CONNECTION_AGE: &str = "LINKERD2_PROXY_OUTBOUND_MAX_CONNECTION_AGE";
+
+/// Configures how many requests an outbound HTTP/2 connection to an endpoint
+/// may dispatch before it is gracefully closed and replaced. If unset,
+/// connections are not retired by request count.
+const ENV_OUTBOUND_MAX_CONNECTION_REQUESTS: &str =
+    "LINKERD2_PROXY_OUTBOUND_MAX_CONNECTION_REQUESTS";
+
 pub const ENV_BUFFER_CAPACITY: &str = "LINKERD2_PROXY_BUFFER_CAPACITY";
 
+/// Enables the `l5d-queue-ms` response header, which describes how long each
+/// HTTP request waited in the proxy's buffers, in milliseconds. This is
+/// intended for debugging. Defaults to false.
+pub const ENV_QUEUE_TIME_HEADER_ENABLED: &str = "LINKERD2_PROXY_QUEUE_TIME_HEADER_ENABLED";
+
+/// Enables JSON bodies on responses synthesized for proxy errors. These bodies
+/// describe the kind of error, the request's logical destination, and a request
+/// ID (from the `x-request-id` header, or generated by the proxy) that is also
+/// logged. Bodies are only sent to clients whose `accept` header permits JSON.
+/// Defaults to false.
+pub const ENV_ERROR_JSON_ENABLED: &str = "LINKERD2_PROXY_ERROR_JSON_ENABLED";
+
+/// Enables the `l5d-err` header on responses synthesized for proxy errors, so
+/// that they can be distinguished from application errors. Defaults to false.
+pub const ENV_ERROR_HEADER_ENABLED: &str = "LINKERD2_PROXY_ERROR_HEADER_ENABLED";
+
+/// Configures the name of a header that carries request IDs, so that requests
+/// can be correlated across services' logs.
+///
+/// When set, the inbound proxy generates an ID for each request that doesn't
+/// have one, setting it on the request forwarded to the application, and both
+/// proxies record each request's ID on a `request` span in their logs. Error
+/// responses describe the same ID. Disabled by default.
+pub const ENV_REQUEST_ID_HEADER: &str = "LINKERD2_PROXY_REQUEST_ID_HEADER";
+
 pub const ENV_INBOUND_ROUTER_MAX_IDLE_AGE: &str = "LINKERD2_PROXY_INBOUND_ROUTER_MAX_IDLE_AGE";
 pub const ENV_OUTBOUND_ROUTER_MAX_IDLE_AGE: &str = "LINKERD2_PROXY_OUTBOUND_ROUTER_MAX_IDLE_AGE";
 
+/// Configures how long the outbound proxy retains the profile discovered for
+/// an original destination address after it is no longer used. Defaults to
+/// `LINKERD2_PROXY_OUTBOUND_ROUTER_MAX_IDLE_AGE`.
+pub const ENV_OUTBOUND_PROFILE_CACHE_MAX_IDLE_AGE: &str =
+    "LINKERD2_PROXY_OUTBOUND_PROFILE_CACHE_MAX_IDLE_AGE";
+
+/// Configures how long the outbound proxy retains a logical service's balancer
+/// and endpoint resolution after it is no longer used. Defaults to
+/// `LINKERD2_PROXY_OUTBOUND_ROUTER_MAX_IDLE_AGE`.
+pub const ENV_OUTBOUND_BALANCER_MAX_IDLE_AGE: &str =
+    "LINKERD2_PROXY_OUTBOUND_BALANCER_MAX_IDLE_AGE";
+
+/// Configures how long an ingress-mode proxy retains the stack for a request's
+/// target after it is no longer used. Defaults to
+/// `LINKERD2_PROXY_OUTBOUND_ROUTER_MAX_IDLE_AGE`.
+pub const ENV_INGRESS_CACHE_MAX_IDLE_AGE: &str = "LINKERD2_PROXY_INGRESS_CACHE_MAX_IDLE_AGE";
+
+/// A comma-separated list of `ip:port` destinations (e.g. service cluster IPs)
+/// that the outbound proxy discovers when it starts, so that the first
+/// connections to latency-sensitive services need not wait for discovery.
+/// Ignored in ingress mode.
+pub const ENV_OUTBOUND_WARM_DESTINATIONS: &str = "LINKERD2_PROXY_OUTBOUND_WARM_DESTINATIONS";
+
+/// Configures how long a connection to a dual-stack endpoint's IPv6 address is
+/// attempted before its IPv4 address is also tried. Defaults to 250ms, as
+/// recommended by RFC 8305.
+pub const ENV_OUTBOUND_HAPPY_EYEBALLS_DELAY: &str = "LINKERD2_PROXY_OUTBOUND_HAPPY_EYEBALLS_DELAY";
+
+/// Configures the name of a cookie that pins clients to the endpoint that
+/// served their first request, e.g. for browser-facing ingress traffic.
+///
+/// When set, outbound HTTP balancers route requests that carry the cookie to
+/// the endpoint it names, if that endpoint is still available. Otherwise, the
+/// request is balanced and the response sets the cookie. If unset, requests
+/// are balanced without affinity.
+pub const ENV_OUTBOUND_HTTP_AFFINITY_COOKIE: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_AFFINITY_COOKIE";
+
+/// Configures the lifetime of the affinity cookie. Defaults to 1 hour.
+pub const ENV_OUTBOUND_HTTP_AFFINITY_COOKIE_TTL: &str =
+    "LINKERD2_PROXY_OUTBOUND_HTTP_AFFINITY_COOKIE_TTL";
+
+/// Allows outbound HTTP requests to override the weights of a service's
+/// traffic split with an `l5d-dst-weights` header, e.g.
+/// `l5d-dst-weights: svc-a=90,svc-b=10`. Disabled by default.
+pub const ENV_OUTBOUND_SPLIT_OVERRIDES_ENABLED: &str =
+    "LINKERD2_PROXY_OUTBOUND_SPLIT_OVERRIDES_ENABLED";
+
+/// Adds an `l5d-dst-concrete` header to the responses of outbound HTTP
+/// requests that are split over a service's concrete targets, naming the
+/// target and its weight, e.g.
+/// `l5d-dst-concrete: svc-b.ns.svc.cluster.local:8080;weight=10/100`. This is
+/// intended for debugging and is disabled by default.
+pub const ENV_OUTBOUND_SPLIT_CONCRETE_HEADER_ENABLED: &str =
+    "LINKERD2_PROXY_OUTBOUND_SPLIT_CONCRETE_HEADER_ENABLED";
+
+/// Enables failure isolation in outbound traffic splits: a concrete target
+/// that fails this many consecutive requests (or connections) is removed from
+/// its split until its probation elapses. Disabled by default.
+pub const ENV_OUTBOUND_SPLIT_ISOLATION_FAILURES: &str =
+    "LINKERD2_PROXY_OUTBOUND_SPLIT_ISOLATION_FAILURES";
+
+/// How long a concrete target that has been isolated from a traffic split
+/// receives no traffic before it is returned to the split.
+pub const ENV_OUTBOUND_SPLIT_ISOLATION_PROBATION: &str =
+    "LINKERD2_PROXY_OUTBOUND_SPLIT_ISOLATION_PROBATION";
+
+/// Injects aborts into outbound HTTP requests on routes that do not configure
+/// their own fault, e.g. for chaos testing.
+///
+/// The value is a response status and the probability that a request is
+/// aborted with it, as `STATUS:PROBABILITY`, e.g. `503:0.1`.
+pub const ENV_OUTBOUND_HTTP_FAULT_ABORT: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_FAULT_ABORT";
+
+/// Injects delays into outbound HTTP requests on routes that do not configure
+/// their own fault.
+///
+/// The value is a duration and the probability that a request is delayed by
+/// it, as `DURATION:PROBABILITY`, e.g. `500ms:0.25`.
+pub const ENV_OUTBOUND_HTTP_FAULT_DELAY: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_FAULT_DELAY";
+
+/// Enables deadline propagation for outbound HTTP requests. Disabled by
+/// default.
+///
+/// When enabled, requests that set a `grpc-timeout` header, or the header
+/// configured by `LINKERD2_PROXY_OUTBOUND_HTTP_DEADLINE_HEADER`, fail with a
+/// 504 (or a gRPC `DEADLINE_EXCEEDED` status) if they are not answered in
+/// time, and these headers are rewritten with the remaining time when the
+/// request is forwarded.
+pub const ENV_OUTBOUND_HTTP_DEADLINE_ENABLED: &str =
+    "LINKERD2_PROXY_OUTBOUND_HTTP_DEADLINE_ENABLED";
+
+/// Configures the header that carries the milliseconds remaining before a
+/// plain HTTP request's deadline. Defaults to `l5d-deadline`.
+pub const ENV_OUTBOUND_HTTP_DEADLINE_HEADER: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_DEADLINE_HEADER";
+
+/// Configures the number of responses retained for outbound routes that
+/// deduplicate requests by their `Idempotency-Key` header. If unset, requests
+/// are not deduplicated.
+pub const ENV_OUTBOUND_HTTP_IDEMPOTENCY_CACHE_CAPACITY: &str =
+    "LINKERD2_PROXY_OUTBOUND_HTTP_IDEMPOTENCY_CACHE_CAPACITY";
+
+/// Configures the time allowed for each attempt of a retried request on every
+/// outbound route. If unset, attempts are only limited by the route's timeout.
+pub const ENV_OUTBOUND_HTTP_PER_TRY_TIMEOUT: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_PER_TRY_TIMEOUT";
+
+/// Configures the time allowed without progress on a response stream on every
+/// outbound route. If unset, response streams may be idle indefinitely.
+pub const ENV_OUTBOUND_HTTP_IDLE_TIMEOUT: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_IDLE_TIMEOUT";
+
+/// Configures how long responses to requests with an `Idempotency-Key` header
+/// are replayed to requests that repeat the key, on every outbound route.
+/// Requires `LINKERD2_PROXY_OUTBOUND_HTTP_IDEMPOTENCY_CACHE_CAPACITY`. If
+/// unset, requests are not deduplicated.
+pub const ENV_OUTBOUND_HTTP_IDEMPOTENCY_TTL: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_IDEMPOTENCY_TTL";
+
+/// Configures the largest response body retained by the idempotency cache.
+/// Defaults to 64KiB.
+pub const ENV_OUTBOUND_HTTP_IDEMPOTENCY_CACHE_MAX_BODY_BYTES: &str =
+    "LINKERD2_PROXY_OUTBOUND_HTTP_IDEMPOTENCY_CACHE_MAX_BODY_BYTES";
+
+/// Configures the total size of the responses retained for outbound routes
+/// that enable response caching. If unset, responses are not cached.
+pub const ENV_OUTBOUND_HTTP_RESPONSE_CACHE_MAX_BYTES: &str =
+    "LINKERD2_PROXY_OUTBOUND_HTTP_RESPONSE_CACHE_MAX_BYTES";
+
+/// When true, responses to GET requests on every outbound route are cached
+/// according to their Cache-Control headers. Requires
+/// `LINKERD2_PROXY_OUTBOUND_HTTP_RESPONSE_CACHE_MAX_BYTES`. Defaults to false.
+pub const ENV_OUTBOUND_HTTP_CACHE_RESPONSES: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_CACHE_RESPONSES";
+
+/// Configures the largest response body retained by the response cache.
+/// Defaults to 1MiB.
+pub const ENV_OUTBOUND_HTTP_RESPONSE_CACHE_MAX_BODY_BYTES: &str =
+    "LINKERD2_PROXY_OUTBOUND_HTTP_RESPONSE_CACHE_MAX_BODY_BYTES";
+
+/// Configures experimental WASM filters that are applied to inbound HTTP
+/// requests and responses, as a comma-separated list of `name=module-path`
+/// entries. A filter's configuration may be read from a file by appending
+/// `:config-path` to its entry. Filters are applied to requests in order.
+///
+/// Requires the proxy to be built with the `wasm` feature.
+pub const ENV_INBOUND_HTTP_WASM_FILTERS: &str = "LINKERD2_PROXY_INBOUND_HTTP_WASM_FILTERS";
+
+/// Configures experimental WASM filters that are applied to outbound HTTP
+/// requests and responses, in the same form as
+/// `LINKERD2_PROXY_INBOUND_HTTP_WASM_FILTERS`.
+pub const ENV_OUTBOUND_HTTP_WASM_FILTERS: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_WASM_FILTERS";
+
+/// Limits the fuel (roughly, the number of WASM instructions) a WASM filter
+/// may consume while processing a single request or response head.
+pub const ENV_HTTP_WASM_FUEL: &str = "LINKERD2_PROXY_HTTP_WASM_FUEL";
+
+/// Limits how long a WASM filter may run while processing a single request or
+/// response head.
+pub const ENV_HTTP_WASM_TIMEOUT: &str = "LINKERD2_PROXY_HTTP_WASM_TIMEOUT";
+
+/// Configures the number of idle instances retained for each WASM filter.
+pub const ENV_HTTP_WASM_POOL_SIZE: &str = "LINKERD2_PROXY_HTTP_WASM_POOL_SIZE";
+
 const ENV_INBOUND_MAX_IDLE_CONNS_PER_ENDPOINT: &str = "LINKERD2_PROXY_MAX_IDLE_CONNS_PER_ENDPOINT";
 const ENV_OUTBOUND_MAX_IDLE_CONNS_PER_ENDPOINT: &str =
     "LINKERD2_PROXY_OUTBOUND_MAX_IDLE_CONNS_PER_ENDPOINT";
 
+/// Configures how long the proxy continues to serve traffic after it receives
+/// a shutdown signal before it begins to drain, so that the application's own
+/// shutdown hooks may complete.
+pub const ENV_SHUTDOWN_DELAY: &str = "LINKERD2_PROXY_SHUTDOWN_DELAY";
+
+/// Configures how long the outbound proxy continues to serve traffic once the
+/// proxy begins to drain, so that requests made by the application while it
+/// shuts down (e.g. to deregister itself) may still succeed.
+pub const ENV_OUTBOUND_DRAIN_LINGER: &str = "LINKERD2_PROXY_OUTBOUND_DRAIN_LINGER";
+
 pub const ENV_INBOUND_MAX_IN_FLIGHT: &str = "LINKERD2_PROXY_INBOUND_MAX_IN_FLIGHT";
 pub const ENV_OUTBOUND_MAX_IN_FLIGHT: &str = "LINKERD2_PROXY_OUTBOUND_MAX_IN_FLIGHT";
 
 pub const ENV_TRACE_ATTRIBUTES_PATH: &str = "LINKERD2_PROXY_TRACE_ATTRIBUTES_PATH";
 
+/// A comma-separated list of mesh-specific attributes added to the proxy's
+/// spans: `peer.identity`, `dst.logical`, `dst.concrete`, `route`,
+/// `retry.attempt`, and `connection.reused`.
+///
+/// No attributes are added by default.
+pub const ENV_TRACE_SPAN_ATTRIBUTES: &str = "LINKERD2_PROXY_TRACE_SPAN_ATTRIBUTES";
+
+/// The maximum number of spans sent to the trace collector in each export
+/// request.
+pub const ENV_TRACE_BATCH_SIZE: &str = "LINKERD2_PROXY_TRACE_BATCH_SIZE";
+
+/// The maximum time that a span is held before its batch is sent to the trace
+/// collector.
+pub const ENV_TRACE_FLUSH_INTERVAL: &str = "LINKERD2_PROXY_TRACE_FLUSH_INTERVAL";
+
+/// The number of spans buffered while the trace collector is busy. Spans are
+/// dropped when the buffer is full.
+pub const ENV_TRACE_BUFFER_CAPACITY: &str = "LINKERD2_PROXY_TRACE_BUFFER_CAPACITY";
+
+/// If set, responses wait up to this long for their spans to fit in a full
+/// buffer before the spans are dropped, so that low-volume traces aren't lost
+/// to bursts of spans.
+///
+/// Spans are dropped immediately by default.
+pub const ENV_TRACE_SEND_TIMEOUT: &str = "LINKERD2_PROXY_TRACE_SEND_TIMEOUT";
+
 /// Constrains which destination names may be used for profile/route discovery.
 ///
 /// The value is a comma-separated list of domain name suffixes that may be
 /// resolved via the destination service. A value of `.` indicates that all
 /// domains should be discovered via the service.
 ///
+/// Entries may also be wildcard patterns, like `*.internal.example.com`, where
+/// each `*` label matches exactly one label, so that specific subdomains may be
+/// discovered without discovering the parent domain. Any entry may be followed
+/// by `@<port>` so that it only applies to that port.
+///
 /// If specified and empty, the destination service is not used for route discovery.
 ///
 /// If unspecified, a default value is used.
@@ -133,7 +533,12 @@ pub const ENV_DESTINATION_PROFILE_SUFFIXES: &str = "LINKERD2_PROXY_DESTINATION_P
 /// Constrains which destination addresses may be used for profile/route discovery.
 ///
 /// The value is a comma-separated list of networks that may be
-/// resolved via the destination service.
+/// resolved via the destination service. When an outbound connection targets
+/// a pod IP in one of these networks, the destination service describes the
+/// pod so that its identity, metrics labels, and protocol hints are used.
+///
+/// IPv4 and IPv6 networks may both be listed. IPv4 networks also match
+/// IPv4-mapped IPv6 addresses (i.e. `::ffff:a.b.c.d`).
 ///
 /// If specified and empty, the destination service is not used for route discovery.
 ///
@@ -145,14 +550,334 @@ pub const ENV_DESTINATION_PROFILE_NETWORKS: &str = "LINKERD2_PROXY_DESTINATION_P
 /// If unspecified or empty, no inbound gateway is configured.
 pub const ENV_INBOUND_GATEWAY_SUFFIXES: &str = "LINKERD2_PROXY_INBOUND_GATEWAY_SUFFIXES";
 
+/// Configures the remote clusters that gateway clients may target by setting
+/// their SNI to `<cluster>.<local-identity>`.
+///
+/// The value is a comma-separated list of `<cluster>=<identity-suffix>`
+/// entries, each permitting clients with identities in the suffix to target
+/// the cluster. A cluster may be listed more than once.
+///
+/// If unspecified or empty, clients may not target remote clusters via SNI.
+pub const ENV_INBOUND_GATEWAY_CLUSTERS: &str = "LINKERD2_PROXY_INBOUND_GATEWAY_CLUSTERS";
+
+/// Configures the gateway of each remote cluster in
+/// `LINKERD2_PROXY_INBOUND_GATEWAY_CLUSTERS`, to which connections targeting
+/// the cluster are forwarded.
+///
+/// The value is a comma-separated list of `<cluster>=<identity>@<ip>:<port>`
+/// entries. Every configured cluster must have exactly one gateway.
+pub const ENV_INBOUND_GATEWAY_CLUSTER_GATEWAYS: &str =
+    "LINKERD2_PROXY_INBOUND_GATEWAY_CLUSTER_GATEWAYS";
+
+/// Configures federated trust domains whose identities are accepted on gateway
+/// connections.
+///
+/// The value is a comma-separated list of `<trust-domain>=<path>` entries,
+/// where the path refers to a PEM-encoded bundle of the trust domain's
+/// anchors. Certificates issued by these anchors are only accepted for
+/// identities within the trust domain, and such clients may not use the
+/// gateway port to reach local workloads.
+pub const ENV_INBOUND_GATEWAY_TRUST_DOMAINS: &str = "LINKERD2_PROXY_INBOUND_GATEWAY_TRUST_DOMAINS";
+
+/// Configures outbound balancers to fail over to the endpoints of services
+/// mirrored from the named remote cluster (i.e. `<svc>-<cluster>.<ns>...`)
+/// when the local endpoints are unavailable.
+///
+/// If unspecified, failover is disabled.
+pub const ENV_OUTBOUND_FAILOVER_CLUSTER: &str = "LINKERD2_PROXY_OUTBOUND_FAILOVER_CLUSTER";
+
+/// How long a balancer's local endpoints may be unavailable before traffic
+/// fails over to the remote cluster.
+const ENV_OUTBOUND_FAILOVER_TIMEOUT: &str = "LINKERD2_PROXY_OUTBOUND_FAILOVER_TIMEOUT";
+
+/// The minimum amount of time that traffic remains on the remote cluster after
+/// failing over.
+const ENV_OUTBOUND_FAILOVER_MIN_DURATION: &str = "LINKERD2_PROXY_OUTBOUND_FAILOVER_MIN_DURATION";
+
+/// Constrains which endpoint metadata labels from the destination service are
+/// included in outbound metrics and tracing spans.
+///
+/// The value is a comma-separated list of label names. If unspecified, all
+/// labels are included; if empty, no labels are included.
+pub const ENV_OUTBOUND_METADATA_LABELS: &str = "LINKERD2_PROXY_OUTBOUND_METADATA_LABELS";
+
+/// Enables requesting compressed responses from outbound endpoints. Responses
+/// are decompressed when the application did not accept their encoding.
+/// Defaults to false.
+pub const ENV_OUTBOUND_DECOMPRESSION_ENABLED: &str =
+    "LINKERD2_PROXY_OUTBOUND_DECOMPRESSION_ENABLED";
+
+/// Enables the outbound egress policy. Either `enforce`, to refuse connections
+/// that the policy denies, or `audit`, to log them. If unset, no egress policy
+/// is applied.
+pub const ENV_OUTBOUND_EGRESS_POLICY_MODE: &str = "LINKERD2_PROXY_OUTBOUND_EGRESS_POLICY_MODE";
+
+/// The egress policy action, `allow` or `deny`, for destinations that match no
+/// rule. Defaults to `allow`.
+pub const ENV_OUTBOUND_EGRESS_POLICY_DEFAULT: &str =
+    "LINKERD2_PROXY_OUTBOUND_EGRESS_POLICY_DEFAULT";
+
+/// A comma-separated list of egress policy rules, evaluated in order, in the
+/// form `<allow|deny>=<network or DNS suffix>[@<port>[-<port>]]`. For example:
+/// `allow=api.example.com.@443,deny=0.0.0.0/0`.
+pub const ENV_OUTBOUND_EGRESS_POLICY_RULES: &str = "LINKERD2_PROXY_OUTBOUND_EGRESS_POLICY_RULES";
+
+/// A comma-separated list of destinations, in the form
+/// `<network>[@<port>[-<port>]]`, to which outbound connections are forwarded
+/// directly, without protocol detection, discovery, or mTLS. For example:
+/// `127.0.0.1/32@6379,10.1.0.0/16`. Connections on the loopback interface are
+/// only permitted to bypassed destinations.
+pub const ENV_OUTBOUND_BYPASS: &str = "LINKERD2_PROXY_OUTBOUND_BYPASS";
+
+/// A comma-separated list of outbound protocol overrides, evaluated in order,
+/// in the form `<opaque|http1|h2>=<network or DNS suffix>[@<port>[-<port>]]`.
+/// For example: `opaque=10.0.0.0/8@4222,http1=legacy.example.com.`. Networks
+/// match endpoint addresses and DNS suffixes match logical names. Overrides
+/// take precedence over protocol detection and discovery hints.
+pub const ENV_OUTBOUND_PROTOCOL_OVERRIDES: &str = "LINKERD2_PROXY_OUTBOUND_PROTOCOL_OVERRIDES";
+
+/// A comma-separated list of `<port>=<bytes per second>` limits. Each
+/// forwarded TCP connection to a listed port is limited to the given rate,
+/// counting bytes in both directions.
+pub const ENV_INBOUND_TCP_CONNECTION_RATE_LIMITS: &str =
+    "LINKERD2_PROXY_INBOUND_TCP_CONNECTION_RATE_LIMITS";
+
+/// A comma-separated list of `<port>=<bytes per second>` limits. All forwarded
+/// TCP connections to a listed port share the given rate.
+pub const ENV_INBOUND_TCP_PORT_RATE_LIMITS: &str = "LINKERD2_PROXY_INBOUND_TCP_PORT_RATE_LIMITS";
+
+/// Like `LINKERD2_PROXY_INBOUND_TCP_CONNECTION_RATE_LIMITS`, for outbound
+/// connections by destination port.
+pub const ENV_OUTBOUND_TCP_CONNECTION_RATE_LIMITS: &str =
+    "LINKERD2_PROXY_OUTBOUND_TCP_CONNECTION_RATE_LIMITS";
+
+/// Like `LINKERD2_PROXY_INBOUND_TCP_PORT_RATE_LIMITS`, for outbound
+/// connections by destination port.
+pub const ENV_OUTBOUND_TCP_PORT_RATE_LIMITS: &str = "LINKERD2_PROXY_OUTBOUND_TCP_PORT_RATE_LIMITS";
+
+/// A comma-separated list of `<port>=<connections>` limits. Connections to a
+/// listed port are reset when the port already has the given number of open
+/// connections.
+pub const ENV_INBOUND_TCP_PORT_CONNECTION_LIMITS: &str =
+    "LINKERD2_PROXY_INBOUND_TCP_PORT_CONNECTION_LIMITS";
+
+/// A comma-separated list of `<port>=<connections>` limits. Connections to a
+/// listed port are reset when the port already has the given number of open
+/// connections from the same client IP address.
+pub const ENV_INBOUND_TCP_CLIENT_CONNECTION_LIMITS: &str =
+    "LINKERD2_PROXY_INBOUND_TCP_CLIENT_CONNECTION_LIMITS";
+
+/// Limits the number of TLS handshakes that clients in each network may
+/// initiate per second, on both the inbound and gateway ports. Handshakes that
+/// exceed the limit are shed before they are performed. If unset, handshakes
+/// are not limited.
+pub const ENV_INBOUND_TLS_HANDSHAKE_RATE_LIMIT: &str =
+    "LINKERD2_PROXY_INBOUND_TLS_HANDSHAKE_RATE_LIMIT";
+
+/// The prefix length by which IPv4 client addresses are grouped into networks
+/// for `LINKERD2_PROXY_INBOUND_TLS_HANDSHAKE_RATE_LIMIT`. Defaults to 24.
+pub const ENV_INBOUND_TLS_HANDSHAKE_RATE_LIMIT_IPV4_PREFIX_LEN: &str =
+    "LINKERD2_PROXY_INBOUND_TLS_HANDSHAKE_RATE_LIMIT_IPV4_PREFIX_LEN";
+
+/// The prefix length by which IPv6 client addresses are grouped into networks
+/// for `LINKERD2_PROXY_INBOUND_TLS_HANDSHAKE_RATE_LIMIT`. Defaults to 64.
+pub const ENV_INBOUND_TLS_HANDSHAKE_RATE_LIMIT_IPV6_PREFIX_LEN: &str =
+    "LINKERD2_PROXY_INBOUND_TLS_HANDSHAKE_RATE_LIMIT_IPV6_PREFIX_LEN";
+
+/// Configures how long a forwarded TCP connection may go without copying data
+/// in either direction before it is closed. If unset, idle connections are not
+/// closed.
+pub const ENV_INBOUND_TCP_IDLE_TIMEOUT: &str = "LINKERD2_PROXY_INBOUND_TCP_IDLE_TIMEOUT";
+
+/// Like `LINKERD2_PROXY_INBOUND_TCP_IDLE_TIMEOUT`, for outbound connections.
+pub const ENV_OUTBOUND_TCP_IDLE_TIMEOUT: &str = "LINKERD2_PROXY_OUTBOUND_TCP_IDLE_TIMEOUT";
+
+/// Enables multiplexing opaque connections to remote gateways as streams on a
+/// single HTTP/2 tunnel connection to each gateway, when the gateway supports
+/// it. Defaults to false.
+pub const ENV_OUTBOUND_TCP_TUNNEL_ENABLED: &str = "LINKERD2_PROXY_OUTBOUND_TCP_TUNNEL_ENABLED";
+
+/// Enables accepting tunnel connections, on which clients multiplex gateway
+/// connections as HTTP/2 streams. Defaults to false.
+pub const ENV_INBOUND_TUNNEL_ENABLED: &str = "LINKERD2_PROXY_INBOUND_TUNNEL_ENABLED";
+
+/// Configures the address on which outbound UDP datagrams, redirected to the
+/// proxy with TPROXY, are received and forwarded to their original
+/// destinations. If unset, UDP forwarding is disabled.
+pub const ENV_OUTBOUND_UDP_LISTEN_ADDR: &str = "LINKERD2_PROXY_OUTBOUND_UDP_LISTEN_ADDR";
+
+/// Configures how long a UDP session may go without forwarding a datagram in
+/// either direction before it is closed.
+pub const ENV_OUTBOUND_UDP_IDLE_TIMEOUT: &str = "LINKERD2_PROXY_OUTBOUND_UDP_IDLE_TIMEOUT";
+
+/// Configures the maximum number of UDP sessions that may be open at once.
+/// When the limit is reached, the session that has been idle the longest is
+/// closed to make room for a new session.
+pub const ENV_OUTBOUND_UDP_MAX_SESSIONS: &str = "LINKERD2_PROXY_OUTBOUND_UDP_MAX_SESSIONS";
+
 // These *disable* our protocol detection for connections whose SO_ORIGINAL_DST
 // has a port in the provided list.
 pub const ENV_INBOUND_PORTS_DISABLE_PROTOCOL_DETECTION: &str =
     "LINKERD2_PROXY_INBOUND_PORTS_DISABLE_PROTOCOL_DETECTION";
 
+/// Enables discovery of opaque inbound ports from the control plane, so that
+/// ports marked opaque after the proxy starts skip protocol detection without
+/// a restart. Defaults to false.
+pub const ENV_INBOUND_OPAQUE_PORTS_DISCOVERY_ENABLED: &str =
+    "LINKERD2_PROXY_INBOUND_OPAQUE_PORTS_DISCOVERY_ENABLED";
+
+/// Inbound ports on which the server is expected to write before the client
+/// (e.g. MySQL or SMTP). Protocol detection is skipped for these ports so that
+/// connections are not stalled waiting for the client's first bytes.
+pub const ENV_INBOUND_PORTS_SERVER_SPEAKS_FIRST: &str =
+    "LINKERD2_PROXY_INBOUND_PORTS_SERVER_SPEAKS_FIRST";
+
+/// Like `LINKERD2_PROXY_INBOUND_PORTS_SERVER_SPEAKS_FIRST`, for outbound
+/// connections by destination port.
+pub const ENV_OUTBOUND_PORTS_SERVER_SPEAKS_FIRST: &str =
+    "LINKERD2_PROXY_OUTBOUND_PORTS_SERVER_SPEAKS_FIRST";
+
+/// When set, outbound destinations whose protocol is repeatedly detected as
+/// opaque or HTTP/1 skip protocol detection for this long. Unset by default,
+/// in which case protocol hints are not learned.
+pub const ENV_OUTBOUND_PROTOCOL_HINT_TTL: &str = "LINKERD2_PROXY_OUTBOUND_PROTOCOL_HINT_TTL";
+
+/// The number of consecutive identical detections after which an outbound
+/// destination's protocol is learned. Defaults to 3.
+pub const ENV_OUTBOUND_PROTOCOL_HINT_THRESHOLD: &str =
+    "LINKERD2_PROXY_OUTBOUND_PROTOCOL_HINT_THRESHOLD";
+
+/// When set, the inbound and outbound listeners stop accepting connections
+/// once the process has this percentage of its file descriptor limit open.
+/// Connections that have already been accepted are not affected. Unset by
+/// default, in which case connections are always accepted.
+pub const ENV_FD_SHED_HIGH_WATER_PERCENT: &str = "LINKERD2_PROXY_FD_SHED_HIGH_WATER_PERCENT";
+
+/// The percentage of the file descriptor limit below which listeners resume
+/// accepting connections after shedding. Defaults to 10 less than
+/// `LINKERD2_PROXY_FD_SHED_HIGH_WATER_PERCENT`.
+pub const ENV_FD_SHED_LOW_WATER_PERCENT: &str = "LINKERD2_PROXY_FD_SHED_LOW_WATER_PERCENT";
+
+/// Inbound ports on which the application terminates its own TLS. Connections
+/// to these ports are forwarded without TLS detection or termination, so they
+/// may only be authorized by the client's network.
+pub const ENV_INBOUND_PORTS_PASSTHROUGH_TLS: &str = "LINKERD2_PROXY_INBOUND_PORTS_PASSTHROUGH_TLS";
+
 pub const ENV_INBOUND_PORTS_REQUIRE_IDENTITY: &str =
     "LINKERD2_PROXY_INBOUND_PORTS_REQUIRE_IDENTITY";
 
+/// Disables forwarding of the verified client identity to the application for
+/// inbound connections whose SO_ORIGINAL_DST has a port in the provided list.
+///
+/// Client-supplied values of the client identity header are stripped
+/// regardless of this setting.
+pub const ENV_INBOUND_PORTS_DISABLE_CLIENT_ID_HEADER: &str =
+    "LINKERD2_PROXY_INBOUND_PORTS_DISABLE_CLIENT_ID_HEADER";
+
+/// Overrides the address to which inbound connections are forwarded, by the
+/// port of their SO_ORIGINAL_DST.
+///
+/// The value is a comma-separated list of `PORT=IP:PORT` entries, e.g.
+/// `8080=127.0.0.1:8080`. This is necessary for host-network workloads, whose
+/// connections' original destination is the node's address. By default,
+/// connections are forwarded to the loopback address on their original
+/// destination port.
+pub const ENV_INBOUND_PORTS_FORWARD_ADDRS: &str = "LINKERD2_PROXY_INBOUND_PORTS_FORWARD_ADDRS";
+
+/// Configures HTTP routes that are exempt from inbound authorization, by port,
+/// e.g. so that health checks are permitted from unauthenticated clients.
+///
+/// The value is a comma-separated list of `PORT=[METHOD ]PATH` entries, e.g.
+/// `8080=GET /healthz`. Requests to other routes on unauthorized connections
+/// are rejected. If no method is specified, requests with any method match.
+pub const ENV_INBOUND_PORTS_EXEMPT_ROUTES: &str = "LINKERD2_PROXY_INBOUND_PORTS_EXEMPT_ROUTES";
+
+/// Configures HTTP probe paths that are answered by the proxy, by port.
+///
+/// The value is a comma-separated list of `PORT=PATH CHECK` entries, where
+/// `CHECK` is either `tcp:IP:PORT` or `http:IP:PORT/PATH`, e.g.
+/// `8080=/ready tcp:127.0.0.1:3306`. Probe requests succeed if a connection
+/// can be established to the application or if it responds to an HTTP request
+/// successfully, respectively.
+pub const ENV_INBOUND_PORTS_PROBES: &str = "LINKERD2_PROXY_INBOUND_PORTS_PROBES";
+
+/// Configures the clients that are authorized on each port, replacing the
+/// port's configured (or default) authorizations.
+///
+/// The value is a comma-separated list of `PORT=CLIENT[@NETWORK]` entries,
+/// where `CLIENT` is either `unauthenticated`, `tls` (for any mTLS client), an
+/// identity, or an identity suffix (e.g. `*.ns.serviceaccount.identity.linkerd.cluster.local`);
+/// and `NETWORK` restricts the client to the given CIDR network, e.g.
+/// `8080=web.ns.serviceaccount.identity.linkerd.cluster.local@10.0.0.0/8`.
+/// Clients must match both an entry's identity and its network. A port's
+/// entries are evaluated in order, and the first that matches a client
+/// applies.
+pub const ENV_INBOUND_PORTS_AUTHORIZATIONS: &str = "LINKERD2_PROXY_INBOUND_PORTS_AUTHORIZATIONS";
+
+/// Configures the response to HTTP requests that are denied because their
+/// connection is not authorized, by port.
+///
+/// Because response bodies may contain commas, the value is a
+/// newline-separated list of `PORT=STATUS[ BODY]` entries, e.g.
+/// `8080=403 {"error": "unauthorized", "help": "contact #platform"}`. Bodies
+/// that start with `{` are served as `application/json`; others as
+/// `text/plain`. By default, these requests fail with a generic `403`
+/// response.
+pub const ENV_INBOUND_PORTS_DENY_RESPONSES: &str = "LINKERD2_PROXY_INBOUND_PORTS_DENY_RESPONSES";
+
+/// Configures additional headers on the responses configured by
+/// `LINKERD2_PROXY_INBOUND_PORTS_DENY_RESPONSES`.
+///
+/// The value is a comma-separated list of `PORT=NAME:VALUE` entries, e.g.
+/// `8080=link:<https://wiki.example.com/platform>`.
+pub const ENV_INBOUND_PORTS_DENY_RESPONSE_HEADERS: &str =
+    "LINKERD2_PROXY_INBOUND_PORTS_DENY_RESPONSE_HEADERS";
+
+/// Configures how malformed HTTP/1 requests are handled, by port.
+///
+/// The value is a comma-separated list of `PORT=POLICY` entries, where
+/// `POLICY` is either `normalize` or `reject`, e.g. `8080=normalize`. Requests
+/// with obsolete line folding or bare carriage returns in their headers are
+/// normalized by replacing these bytes with spaces, or are rejected and
+/// counted by the `inbound_http1_malformed_requests_total` metric. By default,
+/// these requests fail with a generic protocol error.
+pub const ENV_INBOUND_PORTS_HTTP1_MALFORMED: &str = "LINKERD2_PROXY_INBOUND_PORTS_HTTP1_MALFORMED";
+
+/// A comma-separated list of ports on which the casing of HTTP/1 header names
+/// is preserved, e.g. for applications that require `SOAPAction` headers with
+/// their original casing. By default, header names are lowercased.
+///
+/// Casing is preserved on requests received over HTTP/1 and on their
+/// responses; it is not preserved on requests that are sent between proxies
+/// over HTTP/2.
+pub const ENV_INBOUND_PORTS_HTTP1_PRESERVE_HEADER_CASE: &str =
+    "LINKERD2_PROXY_INBOUND_PORTS_HTTP1_PRESERVE_HEADER_CASE";
+
+/// Disables strict checking of inbound HTTP/1 request framing when set to
+/// `false`.
+///
+/// By default, connections that send requests with both `Content-Length` and
+/// `Transfer-Encoding` headers, multiple `Content-Length` values, unsupported
+/// transfer codings, or malformed chunk extensions are closed and counted by
+/// the `inbound_http1_invalid_framing_requests_total` metric. Applications
+/// that rely on clients that send these requests may disable this check.
+pub const ENV_INBOUND_HTTP1_STRICT_FRAMING_ENABLED: &str =
+    "LINKERD2_PROXY_INBOUND_HTTP1_STRICT_FRAMING_ENABLED";
+
+/// Configures additional inbound listeners, e.g. a listener bound to the
+/// node-local interface for host-network traffic.
+///
+/// The value is a comma-separated list of `IP:PORT[=POLICY]` entries, where
+/// `POLICY` is one of the values accepted by `LINKERD2_PROXY_INBOUND_DEFAULT_POLICY`.
+/// Listeners without a policy use the inbound default policy.
+pub const ENV_INBOUND_ADDITIONAL_LISTENERS: &str = "LINKERD2_PROXY_INBOUND_ADDITIONAL_LISTENERS";
+
+/// Configures the name of the header used to forward the verified client
+/// identity to the application on inbound HTTP requests.
+///
+/// By default, this is `l5d-client-id`.
+pub const ENV_INBOUND_CLIENT_ID_HEADER: &str = "LINKERD2_PROXY_INBOUND_CLIENT_ID_HEADER";
+
 /// Configures the default port policy for inbound connections.
 ///
 /// This must parse to a valid port policy (one of: `deny`, `authenticated`,
@@ -161,12 +886,154 @@ pub const ENV_INBOUND_PORTS_REQUIRE_IDENTITY: &str =
 /// By default, this is `unauthenticated`.
 pub const ENV_INBOUND_DEFAULT_POLICY: &str = "LINKERD2_PROXY_INBOUND_DEFAULT_POLICY";
 
+/// Configures the default port policy to be audited rather than enforced:
+/// connections that it would deny are logged and counted by the
+/// `inbound_policy_audit_denials_total` metric, but are permitted. When the
+/// default policy is `deny`, all connections to unconfigured ports are
+/// permitted and counted.
+///
+/// By default, the default policy is enforced.
+pub const ENV_INBOUND_DEFAULT_POLICY_AUDIT: &str = "LINKERD2_PROXY_INBOUND_DEFAULT_POLICY_AUDIT";
+
+/// A comma-separated list of ports whose policies are audited rather than
+/// enforced, as with `LINKERD2_PROXY_INBOUND_DEFAULT_POLICY_AUDIT`. This
+/// permits the rollout of a `deny` default policy to be audited port by port.
+pub const ENV_INBOUND_PORTS_AUDIT: &str = "LINKERD2_PROXY_INBOUND_PORTS_AUDIT";
+
+/// Enables setting `Forwarded` and `X-Forwarded-For` headers on inbound HTTP
+/// requests with the address of the client on whose behalf each request was
+/// sent. Headers set by untrusted clients are replaced. Defaults to false.
+pub const ENV_INBOUND_FORWARDED_HEADERS_ENABLED: &str =
+    "LINKERD2_PROXY_INBOUND_FORWARDED_HEADERS_ENABLED";
+
+/// A comma-separated list of networks from which clients are trusted to set
+/// forwarding headers and to assert an original client address in a transport
+/// header.
+pub const ENV_INBOUND_FORWARDED_TRUSTED_NETWORKS: &str =
+    "LINKERD2_PROXY_INBOUND_FORWARDED_TRUSTED_NETWORKS";
+
+/// A comma-separated list of identity suffixes (e.g. of gateways) whose clients
+/// are trusted to set forwarding headers and to assert an original client
+/// address in a transport header.
+pub const ENV_INBOUND_FORWARDED_TRUSTED_IDENTITIES: &str =
+    "LINKERD2_PROXY_INBOUND_FORWARDED_TRUSTED_IDENTITIES";
+
+/// Enables translation of inbound gRPC-Web requests (e.g. from browsers) to
+/// native gRPC. Defaults to false.
+pub const ENV_INBOUND_GRPC_WEB_ENABLED: &str = "LINKERD2_PROXY_INBOUND_GRPC_WEB_ENABLED";
+
+/// Enables gzip and brotli compression of inbound HTTP responses, honoring
+/// the client's `accept-encoding` header. Defaults to false.
+pub const ENV_INBOUND_COMPRESSION_ENABLED: &str = "LINKERD2_PROXY_INBOUND_COMPRESSION_ENABLED";
+
+/// The minimum size, in bytes, of a response with a known length for it to
+/// be compressed. Defaults to 1024.
+pub const ENV_INBOUND_COMPRESSION_MIN_SIZE: &str = "LINKERD2_PROXY_INBOUND_COMPRESSION_MIN_SIZE";
+
+/// A comma-separated list of the content types that are compressed. Entries
+/// ending in `/` (e.g. `text/`) match all types with that prefix.
+pub const ENV_INBOUND_COMPRESSION_CONTENT_TYPES: &str =
+    "LINKERD2_PROXY_INBOUND_COMPRESSION_CONTENT_TYPES";
+
+/// Enables shedding of inbound HTTP requests when response latencies indicate
+/// that the proxy is overloaded. Shed requests fail with a 503 response.
+/// Defaults to false.
+pub const ENV_INBOUND_LOAD_SHED_ENABLED: &str = "LINKERD2_PROXY_INBOUND_LOAD_SHED_ENABLED";
+
+/// The minimum number of concurrent requests admitted on each port while
+/// shedding load. Defaults to 10.
+pub const ENV_INBOUND_LOAD_SHED_MIN_LIMIT: &str = "LINKERD2_PROXY_INBOUND_LOAD_SHED_MIN_LIMIT";
+
+/// The maximum number of concurrent requests admitted on each port while
+/// shedding load. Defaults to 1000.
+pub const ENV_INBOUND_LOAD_SHED_MAX_LIMIT: &str = "LINKERD2_PROXY_INBOUND_LOAD_SHED_MAX_LIMIT";
+
+/// How long clients are asked to wait, via the `retry-after` header, before
+/// retrying a shed request. Defaults to 1s.
+pub const ENV_INBOUND_LOAD_SHED_RETRY_AFTER: &str = "LINKERD2_PROXY_INBOUND_LOAD_SHED_RETRY_AFTER";
+
+/// A comma-separated list of `<network>=<priority>` entries that assign a
+/// priority (one of `low`, `normal`, or `high`) to the requests of inbound
+/// clients in each network. When load is shed, low-priority requests are
+/// rejected first and high-priority requests are never rejected. The first
+/// matching network determines a client's priority.
+///
+/// By default, all requests have normal priority.
+pub const ENV_INBOUND_CLIENT_PRIORITIES: &str = "LINKERD2_PROXY_INBOUND_CLIENT_PRIORITIES";
+
+/// Configures a URL from which a JSON Web Key Set is fetched to validate
+/// bearer tokens on inbound HTTP requests. The URL must use `https`.
+///
+/// Only one of this and `LINKERD2_PROXY_INBOUND_JWT_JWKS` may be set. When
+/// neither is set, bearer tokens are not validated.
+pub const ENV_INBOUND_JWT_JWKS_URL: &str = "LINKERD2_PROXY_INBOUND_JWT_JWKS_URL";
+
+/// Configures a static, JSON-encoded JSON Web Key Set used to validate bearer
+/// tokens on inbound HTTP requests.
+pub const ENV_INBOUND_JWT_JWKS: &str = "LINKERD2_PROXY_INBOUND_JWT_JWKS";
+
+/// Configures how frequently the key set is fetched from
+/// `LINKERD2_PROXY_INBOUND_JWT_JWKS_URL`.
+pub const ENV_INBOUND_JWT_JWKS_REFRESH_INTERVAL: &str =
+    "LINKERD2_PROXY_INBOUND_JWT_JWKS_REFRESH_INTERVAL";
+
+/// If set, inbound bearer tokens must have been issued by this issuer.
+pub const ENV_INBOUND_JWT_ISSUER: &str = "LINKERD2_PROXY_INBOUND_JWT_ISSUER";
+
+/// A comma-separated list of audiences. If set, inbound bearer tokens must be
+/// intended for at least one of them.
+pub const ENV_INBOUND_JWT_AUDIENCES: &str = "LINKERD2_PROXY_INBOUND_JWT_AUDIENCES";
+
+/// Configures how much clock skew is tolerated when checking a token's
+/// validity period.
+pub const ENV_INBOUND_JWT_LEEWAY: &str = "LINKERD2_PROXY_INBOUND_JWT_LEEWAY";
+
+/// If false, inbound bearer tokens without an expiration time (`exp`) are
+/// accepted.
+///
+/// By default, tokens must expire.
+pub const ENV_INBOUND_JWT_REQUIRE_EXPIRATION: &str =
+    "LINKERD2_PROXY_INBOUND_JWT_REQUIRE_EXPIRATION";
+
+/// If true, inbound HTTP requests without a bearer token are rejected.
+///
+/// By default, requests without a bearer token are permitted and only
+/// presented tokens are validated.
+pub const ENV_INBOUND_JWT_REQUIRED: &str = "LINKERD2_PROXY_INBOUND_JWT_REQUIRED";
+
+/// A comma-separated list of `[METHOD ]PATH=NAME:VALUE[ NAME:VALUE...]` rules
+/// that restrict routes to requests whose validated bearer tokens have the
+/// given claims. A list claim matches if it contains the value.
+///
+/// A rule applies to requests with its path or one of its subpaths and, if
+/// set, its method. The first rule that matches a request applies; requests
+/// that match no rule are not restricted. Requires bearer token validation to
+/// be configured.
+pub const ENV_INBOUND_JWT_REQUIRED_CLAIMS: &str = "LINKERD2_PROXY_INBOUND_JWT_REQUIRED_CLAIMS";
+
 // pub const ENV_INBOUND_POLICY_ADDR: &str = "LINKERD2_PROXY_INBOUND_POLICY_ADDR";
 // pub const ENV_INBOUND_POLICY_IDENTITY: &str = "LINKERD2_PROXY_INBOUND_POLICY_IDENTITY";
 
 pub const ENV_IDENTITY_DISABLED: &str = "LINKERD2_PROXY_IDENTITY_DISABLED";
 pub const ENV_IDENTITY_DIR: &str = "LINKERD2_PROXY_IDENTITY_DIR";
 pub const ENV_IDENTITY_TRUST_ANCHORS: &str = "LINKERD2_PROXY_IDENTITY_TRUST_ANCHORS";
+
+/// Configures a path to a PEM-encoded trust anchors bundle.
+///
+/// When set, the bundle is read from this file instead of
+/// `LINKERD2_PROXY_IDENTITY_TRUST_ANCHORS` and the file is watched for changes
+/// so that rotated trust anchors are used without restarting the proxy.
+pub const ENV_IDENTITY_TRUST_ANCHORS_PATH: &str = "LINKERD2_PROXY_IDENTITY_TRUST_ANCHORS_PATH";
+
+/// Configures how frequently the trust anchors file is checked for changes.
+pub const ENV_IDENTITY_TRUST_ANCHORS_RELOAD_INTERVAL: &str =
+    "LINKERD2_PROXY_IDENTITY_TRUST_ANCHORS_RELOAD_INTERVAL";
+
+/// Limits the number of intermediate CA certificates that may be present in
+/// the proxy's certificate chain and in chains presented by mTLS clients.
+///
+/// If unspecified, chains are only bounded by the verifier's own limits.
+pub const ENV_IDENTITY_MAX_INTERMEDIATES: &str = "LINKERD2_PROXY_IDENTITY_MAX_INTERMEDIATES";
 pub const ENV_IDENTITY_IDENTITY_LOCAL_NAME: &str = "LINKERD2_PROXY_IDENTITY_LOCAL_NAME";
 pub const ENV_IDENTITY_TOKEN_FILE: &str = "LINKERD2_PROXY_IDENTITY_TOKEN_FILE";
 pub const ENV_IDENTITY_MIN_REFRESH: &str = "LINKERD2_PROXY_IDENTITY_MIN_REFRESH";
@@ -180,6 +1047,43 @@ pub const ENV_HOSTNAME: &str = "HOSTNAME";
 
 pub const ENV_TRACE_COLLECTOR_SVC_BASE: &str = "LINKERD2_PROXY_TRACE_COLLECTOR_SVC";
 
+/// Configures the service that authorizes inbound HTTP requests.
+///
+/// The service is reached over HTTP/2 and, unless identity is disabled, mTLS
+/// with the identity named by `LINKERD2_PROXY_INBOUND_EXT_AUTHZ_SVC_NAME`. By
+/// default, it must implement the `envoy.service.auth.v3.Authorization` gRPC
+/// API.
+pub const ENV_INBOUND_EXT_AUTHZ_SVC_BASE: &str = "LINKERD2_PROXY_INBOUND_EXT_AUTHZ_SVC";
+
+/// If set, the authorization service is a plain HTTP service rather than a
+/// gRPC service, and each request's path is appended to this prefix.
+///
+/// Each request's method and path are forwarded to the service, which permits
+/// the request by responding with a 2xx status. Otherwise, the service's
+/// response is returned to the client.
+pub const ENV_INBOUND_EXT_AUTHZ_HTTP_PATH_PREFIX: &str =
+    "LINKERD2_PROXY_INBOUND_EXT_AUTHZ_HTTP_PATH_PREFIX";
+
+/// Bounds the time spent waiting for an external authorization decision.
+pub const ENV_INBOUND_EXT_AUTHZ_TIMEOUT: &str = "LINKERD2_PROXY_INBOUND_EXT_AUTHZ_TIMEOUT";
+
+/// If true, requests are permitted when the external authorization service
+/// fails or times out. By default, such requests are rejected.
+pub const ENV_INBOUND_EXT_AUTHZ_FAILURE_MODE_ALLOW: &str =
+    "LINKERD2_PROXY_INBOUND_EXT_AUTHZ_FAILURE_MODE_ALLOW";
+
+/// A comma-separated list of request headers forwarded to an HTTP
+/// authorization service.
+///
+/// By default, only the `authorization` header is forwarded.
+pub const ENV_INBOUND_EXT_AUTHZ_ALLOWED_REQUEST_HEADERS: &str =
+    "LINKERD2_PROXY_INBOUND_EXT_AUTHZ_ALLOWED_REQUEST_HEADERS";
+
+/// A comma-separated list of headers copied from an HTTP authorization
+/// service's response onto permitted requests.
+pub const ENV_INBOUND_EXT_AUTHZ_ALLOWED_UPSTREAM_HEADERS: &str =
+    "LINKERD2_PROXY_INBOUND_EXT_AUTHZ_ALLOWED_UPSTREAM_HEADERS";
+
 pub const ENV_DESTINATION_CONTEXT: &str = "LINKERD2_PROXY_DESTINATION_CONTEXT";
 pub const ENV_DESTINATION_PROFILE_INITIAL_TIMEOUT: &str =
     "LINKERD2_PROXY_DESTINATION_PROFILE_INITIAL_TIMEOUT";
@@ -196,6 +1100,60 @@ const ENV_DNS_MIN_TTL: &str = "LINKERD2_PROXY_DNS_MIN_TTL";
 /// Lookups with TTLs above this value will use this value instead.
 const ENV_DNS_MAX_TTL: &str = "LINKERD2_PROXY_DNS_MAX_TTL";
 
+/// Configures a minimum value for the TTL of negative DNS responses (e.g.
+/// NXDOMAIN), which bounds how often nonexistent names are re-resolved.
+///
+/// Defaults to `LINKERD2_PROXY_DNS_MIN_TTL`.
+pub const ENV_DNS_NEGATIVE_MIN_TTL: &str = "LINKERD2_PROXY_DNS_NEGATIVE_MIN_TTL";
+
+/// Configures a maximum value for the TTL of negative DNS responses.
+///
+/// Defaults to `LINKERD2_PROXY_DNS_MAX_TTL`.
+pub const ENV_DNS_NEGATIVE_MAX_TTL: &str = "LINKERD2_PROXY_DNS_NEGATIVE_MAX_TTL";
+
+/// Configures the number of DNS lookups cached by the resolver, so that
+/// concurrent and repeated resolutions of a name share a response until its
+/// (clamped) TTL expires.
+///
+/// Defaults to 0, which disables caching.
+pub const ENV_DNS_CACHE_SIZE: &str = "LINKERD2_PROXY_DNS_CACHE_SIZE";
+
+/// A comma-separated list of nameserver addresses (`<ip>` or `<ip>:<port>`)
+/// used instead of those in resolv.conf.
+///
+/// Nameservers without a port use the default port of the configured
+/// transport: 53 for `udp`, 853 for `tls`, and 443 for `https`.
+pub const ENV_DNS_NAMESERVERS: &str = "LINKERD2_PROXY_DNS_NAMESERVERS";
+
+/// Configures how the resolver communicates with the nameservers configured by
+/// `LINKERD2_PROXY_DNS_NAMESERVERS`: `udp` (plain DNS, the default), `tls`
+/// (DNS-over-TLS), or `https` (DNS-over-HTTPS).
+///
+/// Encrypted transports require `LINKERD2_PROXY_DNS_NAMESERVERS` and
+/// `LINKERD2_PROXY_DNS_TLS_NAME` to be set.
+pub const ENV_DNS_TRANSPORT: &str = "LINKERD2_PROXY_DNS_TRANSPORT";
+
+/// The name used to verify the certificates of encrypted DNS nameservers.
+pub const ENV_DNS_TLS_NAME: &str = "LINKERD2_PROXY_DNS_TLS_NAME";
+
+/// When true, lookups that cannot connect to the nameservers over an encrypted
+/// transport (i.e. because the connection is refused or times out) are retried
+/// over plain DNS (port 53) to the same nameservers. Lookups that fail for
+/// other reasons, such as invalid certificates, are never retried. Defaults to
+/// false.
+pub const ENV_DNS_PLAIN_FALLBACK: &str = "LINKERD2_PROXY_DNS_PLAIN_FALLBACK";
+
+/// A comma-separated list of search domains used instead of those in
+/// resolv.conf.
+pub const ENV_DNS_SEARCH: &str = "LINKERD2_PROXY_DNS_SEARCH";
+
+/// A comma-separated list of `<suffix>=<nameserver>[ <nameserver>...]` entries
+/// that resolve names with each suffix using dedicated nameservers. For
+/// example: `liqo.=10.96.0.20 10.96.0.21:5353`.
+///
+/// When a name matches several suffixes, the first matching entry is used.
+pub const ENV_DNS_SUFFIX_NAMESERVERS: &str = "LINKERD2_PROXY_DNS_SUFFIX_NAMESERVERS";
+
 /// Configure the stream or connection level flow control setting for HTTP2.
 ///
 /// If unspecified, the default value of 65,535 is used.
@@ -209,8 +1167,13 @@ pub const DEFAULT_INBOUND_LISTEN_ADDR: &str = "0.0.0.0:4143";
 pub const DEFAULT_CONTROL_LISTEN_ADDR: &str = "0.0.0.0:4190";
 const DEFAULT_ADMIN_LISTEN_ADDR: &str = "127.0.0.1:4191";
 const DEFAULT_METRICS_RETAIN_IDLE: Duration = Duration::from_secs(10 * 60);
+const DEFAULT_METRICS_PEER_IDENTITY_LIMIT: usize = 100;
+const DEFAULT_ADMIN_DRAIN_GRACE_PERIOD: Duration = Duration::from_secs(10);
+const DEFAULT_SHUTDOWN_DELAY: Duration = Duration::from_secs(0);
+const DEFAULT_OUTBOUND_DRAIN_LINGER: Duration = Duration::from_secs(0);
 const DEFAULT_INBOUND_DISPATCH_TIMEOUT: Duration = Duration::from_secs(1);
 const DEFAULT_INBOUND_DETECT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_INBOUND_CLIENT_ID_HEADER: &str = "l5d-client-id";
 const DEFAULT_INBOUND_CONNECT_TIMEOUT: Duration = Duration::from_millis(300);
 const DEFAULT_INBOUND_CONNECT_BACKOFF: ExponentialBackoff = ExponentialBackoff {
     min: Duration::from_millis(100),
@@ -220,11 +1183,27 @@ const DEFAULT_INBOUND_CONNECT_BACKOFF: ExponentialBackoff = ExponentialBackoff {
 const DEFAULT_OUTBOUND_DISPATCH_TIMEOUT: Duration = Duration::from_secs(3);
 const DEFAULT_OUTBOUND_DETECT_TIMEOUT: Duration = Duration::from_secs(10);
 const DEFAULT_OUTBOUND_CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+const DEFAULT_OUTBOUND_HAPPY_EYEBALLS_DELAY: Duration = Duration::from_millis(250);
+const DEFAULT_OUTBOUND_HTTP_AFFINITY_COOKIE_TTL: Duration = Duration::from_secs(60 * 60);
+const DEFAULT_OUTBOUND_SPLIT_ISOLATION_PROBATION: Duration = Duration::from_secs(30);
+const DEFAULT_OUTBOUND_HTTP_IDEMPOTENCY_CACHE_MAX_BODY_BYTES: usize = 64 * 1024;
+const DEFAULT_OUTBOUND_HTTP_RESPONSE_CACHE_MAX_BODY_BYTES: usize = 1024 * 1024;
+#[cfg(feature = "ingress-script")]
+const DEFAULT_INGRESS_ROUTE_SCRIPT_MAX_OPERATIONS: u64 = 10_000;
+const DEFAULT_INGRESS_TENANT_HEADER: &str = "l5d-tenant";
+#[cfg(feature = "wasm")]
+const DEFAULT_HTTP_WASM_FUEL: u64 = 10_000_000;
+#[cfg(feature = "wasm")]
+const DEFAULT_HTTP_WASM_TIMEOUT: Duration = Duration::from_millis(20);
+#[cfg(feature = "wasm")]
+const DEFAULT_HTTP_WASM_POOL_SIZE: usize = 16;
 const DEFAULT_OUTBOUND_CONNECT_BACKOFF: ExponentialBackoff = ExponentialBackoff {
     min: Duration::from_millis(100),
     max: Duration::from_millis(500),
     jitter: 0.1,
 };
+const DEFAULT_OUTBOUND_FAILOVER_TIMEOUT: Duration = Duration::from_secs(1);
+const DEFAULT_OUTBOUND_FAILOVER_MIN_DURATION: Duration = Duration::from_secs(30);
 const DEFAULT_RESOLV_CONF: &str = "/etc/resolv.conf";
 
 const DEFAULT_INITIAL_STREAM_WINDOW_SIZE: u32 = 65_535; // Protocol default
@@ -267,11 +1246,27 @@ const DEFAULT_OUTBOUND_MAX_IN_FLIGHT: usize = 100_000;
 // buffer requests for high-load services.
 const DEFAULT_BUFFER_CAPACITY: usize = 10_000;
 
+// UDP sessions (e.g. for DNS lookups) are typically short-lived, but some
+// protocols, like syslog, send datagrams only periodically.
+const DEFAULT_OUTBOUND_UDP_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_OUTBOUND_UDP_MAX_SESSIONS: usize = 10_000;
+
 const DEFAULT_DESTINATION_PROFILE_SUFFIXES: &str = "svc.cluster.local.";
 const DEFAULT_DESTINATION_PROFILE_IDLE_TIMEOUT: Duration = Duration::from_millis(500);
 
 const DEFAULT_IDENTITY_MIN_REFRESH: Duration = Duration::from_secs(10);
 const DEFAULT_IDENTITY_MAX_REFRESH: Duration = Duration::from_secs(60 * 60 * 24);
+const DEFAULT_IDENTITY_TRUST_ANCHORS_RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+const DEFAULT_INBOUND_JWT_JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const DEFAULT_INBOUND_JWT_LEEWAY: Duration = Duration::from_secs(60);
+
+const DEFAULT_INBOUND_EXT_AUTHZ_TIMEOUT: Duration = Duration::from_millis(200);
+const DEFAULT_INBOUND_EXT_AUTHZ_ALLOWED_REQUEST_HEADERS: &str = "authorization";
+
+const DEFAULT_TRACE_BATCH_SIZE: usize = 1000;
+const DEFAULT_TRACE_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+const DEFAULT_TRACE_BUFFER_CAPACITY: usize = 100;
 
 const INBOUND_CONNECT_BASE: &str = "INBOUND_CONNECT";
 const OUTBOUND_CONNECT_BASE: &str = "OUTBOUND_CONNECT";
@@ -283,6 +1278,15 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
     let outbound_listener_addr = parse(strings, ENV_OUTBOUND_LISTEN_ADDR, parse_socket_addr);
     let inbound_listener_addr = parse(strings, ENV_INBOUND_LISTEN_ADDR, parse_socket_addr);
     let admin_listener_addr = parse(strings, ENV_ADMIN_LISTEN_ADDR, parse_socket_addr);
+    let udp_listener_addr = parse(strings, ENV_OUTBOUND_UDP_LISTEN_ADDR, parse_socket_addr);
+    let udp_idle_timeout = parse(strings, ENV_OUTBOUND_UDP_IDLE_TIMEOUT, parse_duration);
+    let udp_max_sessions = parse(
+        strings,
+        ENV_OUTBOUND_UDP_MAX_SESSIONS,
+        parse_number::<NonZeroUsize>,
+    );
+    let shutdown_delay = parse(strings, ENV_SHUTDOWN_DELAY, parse_duration);
+    let outbound_drain_linger = parse(strings, ENV_OUTBOUND_DRAIN_LINGER, parse_duration);
 
     let inbound_detect_timeout = parse(strings, ENV_INBOUND_DETECT_TIMEOUT, parse_duration);
     let inbound_dispatch_timeout = parse(strings, ENV_INBOUND_DISPATCH_TIMEOUT, parse_duration);
@@ -290,6 +1294,17 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
 
     let outbound_detect_timeout = parse(strings, ENV_OUTBOUND_DETECT_TIMEOUT, parse_duration);
     let outbound_dispatch_timeout = parse(strings, ENV_OUTBOUND_DISPATCH_TIMEOUT, parse_duration);
+
+    let inbound_fail_fast_retry_after = parse(
+        strings,
+        ENV_INBOUND_FAIL_FAST_RETRY_AFTER_ENABLED,
+        parse_bool,
+    );
+    let outbound_fail_fast_retry_after = parse(
+        strings,
+        ENV_OUTBOUND_FAIL_FAST_RETRY_AFTER_ENABLED,
+        parse_bool,
+    );
     let outbound_connect_timeout = parse(strings, ENV_OUTBOUND_CONNECT_TIMEOUT, parse_duration);
 
     let inbound_accept_keepalive = parse(strings, ENV_INBOUND_ACCEPT_KEEPALIVE, parse_duration);
@@ -304,7 +1319,80 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         parse_port_set,
     );
 
+    let inbound_server_first_ports = parse(
+        strings,
+        ENV_INBOUND_PORTS_SERVER_SPEAKS_FIRST,
+        parse_port_set,
+    );
+
+    let inbound_passthrough_tls_ports =
+        parse(strings, ENV_INBOUND_PORTS_PASSTHROUGH_TLS, parse_port_set);
+
+    let inbound_disable_client_id_ports = parse(
+        strings,
+        ENV_INBOUND_PORTS_DISABLE_CLIENT_ID_HEADER,
+        parse_port_set,
+    );
+    let inbound_forward_addrs = parse(
+        strings,
+        ENV_INBOUND_PORTS_FORWARD_ADDRS,
+        parse_port_forward_addrs,
+    );
+    let inbound_exempt_routes = parse(
+        strings,
+        ENV_INBOUND_PORTS_EXEMPT_ROUTES,
+        parse_port_exempt_routes,
+    );
+    let inbound_probes = parse(strings, ENV_INBOUND_PORTS_PROBES, parse_port_probes);
+    let inbound_authorizations = parse(
+        strings,
+        ENV_INBOUND_PORTS_AUTHORIZATIONS,
+        parse_port_authorizations,
+    );
+    let inbound_default_policy_audit = parse(strings, ENV_INBOUND_DEFAULT_POLICY_AUDIT, parse_bool);
+    let inbound_audit_ports = parse(strings, ENV_INBOUND_PORTS_AUDIT, parse_port_set);
+    let inbound_deny_responses = parse(
+        strings,
+        ENV_INBOUND_PORTS_DENY_RESPONSES,
+        parse_port_deny_responses,
+    );
+    let inbound_deny_response_headers = parse(
+        strings,
+        ENV_INBOUND_PORTS_DENY_RESPONSE_HEADERS,
+        parse_port_deny_response_headers,
+    );
+    let inbound_client_id_header = parse(strings, ENV_INBOUND_CLIENT_ID_HEADER, parse_header_name);
+    let inbound_jwt = parse_jwt_config(strings);
+    let inbound_grpc_web = parse(strings, ENV_INBOUND_GRPC_WEB_ENABLED, parse_bool);
+    let inbound_http1_strict_framing = parse(
+        strings,
+        ENV_INBOUND_HTTP1_STRICT_FRAMING_ENABLED,
+        parse_bool,
+    );
+    let inbound_forwarded = parse_forwarded_policy(strings);
+    let inbound_compression = parse_compression_config(strings);
+    let inbound_load_shed = parse_load_shed_config(strings);
+    let inbound_http1_malformed = parse(
+        strings,
+        ENV_INBOUND_PORTS_HTTP1_MALFORMED,
+        parse_port_malformed_policies,
+    );
+    let inbound_http1_preserve_header_case = parse(
+        strings,
+        ENV_INBOUND_PORTS_HTTP1_PRESERVE_HEADER_CASE,
+        parse_port_set,
+    );
+    let inbound_client_priorities = parse(
+        strings,
+        ENV_INBOUND_CLIENT_PRIORITIES,
+        parse_client_priorities,
+    );
+
     let buffer_capacity = parse(strings, ENV_BUFFER_CAPACITY, parse_number);
+    let queue_time_header = parse(strings, ENV_QUEUE_TIME_HEADER_ENABLED, parse_bool);
+    let error_json = parse(strings, ENV_ERROR_JSON_ENABLED, parse_bool);
+    let error_header = parse(strings, ENV_ERROR_HEADER_ENABLED, parse_bool);
+    let request_id_header = parse(strings, ENV_REQUEST_ID_HEADER, parse_header_name);
 
     let inbound_cache_max_idle_age =
         parse(strings, ENV_INBOUND_ROUTER_MAX_IDLE_AGE, parse_duration);
@@ -326,6 +1414,8 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
     let outbound_max_in_flight = parse(strings, ENV_OUTBOUND_MAX_IN_FLIGHT, parse_number);
 
     let metrics_retain_idle = parse(strings, ENV_METRICS_RETAIN_IDLE, parse_duration);
+    let metrics_peer_identity_limit = parse(strings, ENV_METRICS_PEER_IDENTITY_LIMIT, parse_number);
+    let admin_drain_grace_period = parse(strings, ENV_ADMIN_DRAIN_GRACE_PERIOD, parse_duration);
 
     // DNS
 
@@ -333,6 +1423,15 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
 
     let dns_min_ttl = parse(strings, ENV_DNS_MIN_TTL, parse_duration);
     let dns_max_ttl = parse(strings, ENV_DNS_MAX_TTL, parse_duration);
+    let dns_negative_min_ttl = parse(strings, ENV_DNS_NEGATIVE_MIN_TTL, parse_duration);
+    let dns_negative_max_ttl = parse(strings, ENV_DNS_NEGATIVE_MAX_TTL, parse_duration);
+    let dns_cache_size = parse(strings, ENV_DNS_CACHE_SIZE, parse_number::<usize>);
+    let dns_servers = parse_dns_servers(strings);
+    let dns_suffix_servers = parse(
+        strings,
+        ENV_DNS_SUFFIX_NAMESERVERS,
+        parse_dns_suffix_nameservers,
+    );
 
     let identity_config = parse_identity_config(strings);
 
@@ -350,6 +1449,14 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
     let hostname = strings.get(ENV_HOSTNAME);
 
     let oc_attributes_file_path = strings.get(ENV_TRACE_ATTRIBUTES_PATH);
+    let trace_batch_size = parse(strings, ENV_TRACE_BATCH_SIZE, parse_number::<NonZeroUsize>);
+    let trace_flush_interval = parse(strings, ENV_TRACE_FLUSH_INTERVAL, parse_duration);
+    let trace_buffer_capacity = parse(
+        strings,
+        ENV_TRACE_BUFFER_CAPACITY,
+        parse_number::<NonZeroUsize>,
+    );
+    let trace_send_timeout = parse(strings, ENV_TRACE_SEND_TIMEOUT, parse_duration);
 
     let trace_collector_addr = if id_disabled {
         parse_control_addr_disable_identity(strings, ENV_TRACE_COLLECTOR_SVC_BASE)
@@ -357,19 +1464,52 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         parse_control_addr(strings, ENV_TRACE_COLLECTOR_SVC_BASE)
     };
 
+    let ext_authz_addr = if id_disabled {
+        parse_control_addr_disable_identity(strings, ENV_INBOUND_EXT_AUTHZ_SVC_BASE)
+    } else {
+        parse_control_addr(strings, ENV_INBOUND_EXT_AUTHZ_SVC_BASE)
+    };
+    let ext_authz_prefix = parse(
+        strings,
+        ENV_INBOUND_EXT_AUTHZ_HTTP_PATH_PREFIX,
+        parse_path_prefix,
+    );
+    let ext_authz_timeout = parse(strings, ENV_INBOUND_EXT_AUTHZ_TIMEOUT, parse_duration);
+    let ext_authz_failure_mode_allow = parse(
+        strings,
+        ENV_INBOUND_EXT_AUTHZ_FAILURE_MODE_ALLOW,
+        parse_bool,
+    );
+    let ext_authz_request_headers = parse(
+        strings,
+        ENV_INBOUND_EXT_AUTHZ_ALLOWED_REQUEST_HEADERS,
+        parse_header_names,
+    );
+    let ext_authz_upstream_headers = parse(
+        strings,
+        ENV_INBOUND_EXT_AUTHZ_ALLOWED_UPSTREAM_HEADERS,
+        parse_header_names,
+    );
+
     let dst_token = strings.get(ENV_DESTINATION_CONTEXT);
 
     let gateway_suffixes = parse(strings, ENV_INBOUND_GATEWAY_SUFFIXES, parse_dns_suffixes);
+    let gateway_clusters = parse(
+        strings,
+        ENV_INBOUND_GATEWAY_CLUSTERS,
+        parse_gateway_clusters,
+    );
+    let gateway_cluster_gateways = parse(
+        strings,
+        ENV_INBOUND_GATEWAY_CLUSTER_GATEWAYS,
+        parse_gateway_cluster_gateways,
+    );
     let dst_profile_idle_timeout = parse(
         strings,
         ENV_DESTINATION_PROFILE_INITIAL_TIMEOUT,
         parse_duration,
     );
-    let dst_profile_suffixes = parse(
-        strings,
-        ENV_DESTINATION_PROFILE_SUFFIXES,
-        parse_dns_suffixes,
-    );
+    let dst_profile_suffixes = parse(strings, ENV_DESTINATION_PROFILE_SUFFIXES, parse_name_rules);
     let dst_profile_networks = parse(strings, ENV_DESTINATION_PROFILE_NETWORKS, parse_networks);
 
     let initial_stream_window_size = parse(strings, ENV_INITIAL_STREAM_WINDOW_SIZE, parse_number);
@@ -389,13 +1529,97 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
     };
 
     let buffer_capacity = buffer_capacity?.unwrap_or(DEFAULT_BUFFER_CAPACITY);
+    let queue_time_header = queue_time_header?.unwrap_or(false);
+    let error_responses = errors::RespondConfig {
+        json_body: error_json?.unwrap_or(false),
+        error_header: error_header?.unwrap_or(false),
+    };
+    let request_id_header = request_id_header?;
+    let request_id = |generate| {
+        request_id_header
+            .clone()
+            .map(|header| request_id::Config { header, generate })
+    };
+
+    let fd_pressure = parse_fd_pressure(strings)?;
+
+    let trace_attributes =
+        parse(strings, ENV_TRACE_SPAN_ATTRIBUTES, parse_span_attributes)?.unwrap_or_default();
 
     let dst_profile_suffixes = dst_profile_suffixes?
-        .unwrap_or_else(|| parse_dns_suffixes(DEFAULT_DESTINATION_PROFILE_SUFFIXES).unwrap());
+        .unwrap_or_else(|| parse_name_rules(DEFAULT_DESTINATION_PROFILE_SUFFIXES).unwrap());
     let dst_profile_networks = dst_profile_networks?.unwrap_or_default();
 
     let outbound = {
         let ingress_mode = parse(strings, ENV_INGRESS_MODE, parse_bool)?.unwrap_or(false);
+        #[cfg(feature = "ingress-script")]
+        let ingress_script = {
+            let max_operations = parse(
+                strings,
+                ENV_INGRESS_ROUTE_SCRIPT_MAX_OPERATIONS,
+                parse_number,
+            )?
+            .unwrap_or(DEFAULT_INGRESS_ROUTE_SCRIPT_MAX_OPERATIONS);
+            match strings.get(ENV_INGRESS_ROUTE_SCRIPT)? {
+                Some(script) => {
+                    match outbound::ingress::RouteScript::compile(&script, max_operations) {
+                        Ok(script) => Some(script),
+                        Err(error) => {
+                            error!(%error, "{} is not valid", ENV_INGRESS_ROUTE_SCRIPT);
+                            return Err(EnvError::InvalidEnvVar);
+                        }
+                    }
+                }
+                None => None,
+            }
+        };
+        #[cfg(not(feature = "ingress-script"))]
+        reject_without_feature(strings, ENV_INGRESS_ROUTE_SCRIPT, "ingress-script")?;
+        let ingress_tenants = {
+            let header = parse(strings, ENV_INGRESS_TENANT_HEADER, parse_header_name)?
+                .unwrap_or_else(|| http::HeaderName::from_static(DEFAULT_INGRESS_TENANT_HEADER));
+            let quotas = parse(
+                strings,
+                ENV_INGRESS_TENANT_QUOTAS,
+                parse_ingress_tenant_quotas,
+            )?
+            .unwrap_or_default();
+            match parse(strings, ENV_INGRESS_TENANTS, parse_ingress_tenants)? {
+                Some(tenants) if !tenants.is_empty() => {
+                    if let Some(tenant) = quotas.keys().find(|t| !tenants.contains_key(*t)) {
+                        error!(
+                            "{} configures a quota for unknown tenant {}",
+                            ENV_INGRESS_TENANT_QUOTAS, tenant
+                        );
+                        return Err(EnvError::InvalidEnvVar);
+                    }
+                    let tenants = tenants.into_iter().map(|(name, suffixes)| {
+                        outbound::ingress::TenantConfig {
+                            max_concurrent_requests: quotas.get(&name).copied(),
+                            allowed_suffixes: NameMatch::new(suffixes),
+                            name,
+                        }
+                    });
+                    Some(outbound::ingress::Tenants::new(header, tenants))
+                }
+                _ => None,
+            }
+        };
+
+        let failover = match parse(
+            strings,
+            ENV_OUTBOUND_FAILOVER_CLUSTER,
+            parse_failover_cluster,
+        )? {
+            Some(cluster) => Some(outbound::failover::Config {
+                cluster,
+                failover_after: parse(strings, ENV_OUTBOUND_FAILOVER_TIMEOUT, parse_duration)?
+                    .unwrap_or(DEFAULT_OUTBOUND_FAILOVER_TIMEOUT),
+                min_failover: parse(strings, ENV_OUTBOUND_FAILOVER_MIN_DURATION, parse_duration)?
+                    .unwrap_or(DEFAULT_OUTBOUND_FAILOVER_MIN_DURATION),
+            }),
+            None => None,
+        };
 
         let addr = ListenAddr(
             outbound_listener_addr?
@@ -405,7 +1629,20 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         let server = ServerConfig {
             addr,
             keepalive,
-            h2_settings,
+            h1_settings: parse_h1_server_settings(
+                strings,
+                ENV_OUTBOUND_ACCEPT_HTTP1_IDLE_TIMEOUT,
+                ENV_OUTBOUND_ACCEPT_HTTP1_MAX_REQUESTS,
+                ENV_OUTBOUND_ACCEPT_HTTP1_CLOSE_ON_DRAIN,
+            )?,
+            h2_settings: h2::Settings {
+                max_connection_age: parse(
+                    strings,
+                    ENV_OUTBOUND_ACCEPT_MAX_CONNECTION_AGE,
+                    parse_duration,
+                )?,
+                ..h2_settings
+            },
         };
         let cache_max_idle_age =
             outbound_cache_max_idle_age?.unwrap_or(DEFAULT_OUTBOUND_ROUTER_MAX_IDLE_AGE);
@@ -420,7 +1657,19 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 OUTBOUND_CONNECT_BASE,
                 DEFAULT_OUTBOUND_CONNECT_BACKOFF,
             )?,
-            h2_settings,
+            h2_settings: h2::Settings {
+                max_connection_age: parse(
+                    strings,
+                    ENV_OUTBOUND_MAX_CONNECTION_AGE,
+                    parse_duration,
+                )?,
+                max_connection_requests: parse(
+                    strings,
+                    ENV_OUTBOUND_MAX_CONNECTION_REQUESTS,
+                    parse_number,
+                )?,
+                ..h2_settings
+            },
             h1_settings: h1::PoolSettings {
                 max_idle,
                 idle_timeout: cache_max_idle_age,
@@ -431,10 +1680,229 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             outbound_detect_timeout?.unwrap_or(DEFAULT_OUTBOUND_DETECT_TIMEOUT);
         let dispatch_timeout =
             outbound_dispatch_timeout?.unwrap_or(DEFAULT_OUTBOUND_DISPATCH_TIMEOUT);
+        let fail_fast_retry_after = outbound_fail_fast_retry_after?.unwrap_or(false);
+
+        let metadata_labels = parse(strings, ENV_OUTBOUND_METADATA_LABELS, parse_metadata_labels)?
+            .unwrap_or_default();
+        let decompression =
+            parse(strings, ENV_OUTBOUND_DECOMPRESSION_ENABLED, parse_bool)?.unwrap_or(false);
+        let egress = parse_egress_config(strings)?;
+        let protocol_overrides = parse(
+            strings,
+            ENV_OUTBOUND_PROTOCOL_OVERRIDES,
+            parse_protocol_overrides,
+        )?
+        .unwrap_or_default();
+        let server_speaks_first_ports = parse(
+            strings,
+            ENV_OUTBOUND_PORTS_SERVER_SPEAKS_FIRST,
+            parse_port_set,
+        )?
+        .unwrap_or_default()
+        .into();
+        let protocol_hints = parse_protocol_hints(strings)?;
+        let connect_timeouts = parse(
+            strings,
+            ENV_OUTBOUND_CONNECT_TIMEOUT_OVERRIDES,
+            parse_connect_timeouts,
+        )?
+        .unwrap_or_default();
+        let bypass = outbound::bypass::Config {
+            rules: parse(strings, ENV_OUTBOUND_BYPASS, parse_bypass_rules)?.unwrap_or_default(),
+        };
+        let tcp_rate_limits = parse_tcp_rate_limits(
+            strings,
+            ENV_OUTBOUND_TCP_CONNECTION_RATE_LIMITS,
+            ENV_OUTBOUND_TCP_PORT_RATE_LIMITS,
+        )?;
+        let tcp_idle_timeout = parse(strings, ENV_OUTBOUND_TCP_IDLE_TIMEOUT, parse_duration)?;
+        let tcp_tunnel =
+            parse(strings, ENV_OUTBOUND_TCP_TUNNEL_ENABLED, parse_bool)?.unwrap_or(false);
+        let warm_destinations =
+            parse(strings, ENV_OUTBOUND_WARM_DESTINATIONS, parse_socket_addrs)?.unwrap_or_default();
+        let happy_eyeballs_delay =
+            parse(strings, ENV_OUTBOUND_HAPPY_EYEBALLS_DELAY, parse_duration)?
+                .unwrap_or(DEFAULT_OUTBOUND_HAPPY_EYEBALLS_DELAY);
+        let http_affinity = {
+            let ttl = parse(
+                strings,
+                ENV_OUTBOUND_HTTP_AFFINITY_COOKIE_TTL,
+                parse_duration,
+            )?
+            .unwrap_or(DEFAULT_OUTBOUND_HTTP_AFFINITY_COOKIE_TTL);
+            parse(
+                strings,
+                ENV_OUTBOUND_HTTP_AFFINITY_COOKIE,
+                parse_cookie_name,
+            )?
+            .map(|cookie_name| affinity::Config { cookie_name, ttl })
+        };
+        let split_overrides =
+            parse(strings, ENV_OUTBOUND_SPLIT_OVERRIDES_ENABLED, parse_bool)?.unwrap_or(false);
+        let split_concrete_header = parse(
+            strings,
+            ENV_OUTBOUND_SPLIT_CONCRETE_HEADER_ENABLED,
+            parse_bool,
+        )?
+        .unwrap_or(false);
+        let split_isolation = {
+            let probation = parse(
+                strings,
+                ENV_OUTBOUND_SPLIT_ISOLATION_PROBATION,
+                parse_duration,
+            )?
+            .unwrap_or(DEFAULT_OUTBOUND_SPLIT_ISOLATION_PROBATION);
+            parse(strings, ENV_OUTBOUND_SPLIT_ISOLATION_FAILURES, parse_number)?
+                .filter(|failures| *failures > 0)
+                .map(|consecutive_failures| profiles::split::Isolation {
+                    consecutive_failures,
+                    probation,
+                })
+        };
+        let http_fault = {
+            let abort = parse(strings, ENV_OUTBOUND_HTTP_FAULT_ABORT, |s| {
+                parse_fault(s, parse_status_code)
+            })?;
+            let delay = parse(strings, ENV_OUTBOUND_HTTP_FAULT_DELAY, |s| {
+                parse_fault(s, parse_duration)
+            })?;
+            let mut fault = profiles::http::Fault::default();
+            if let Some((status, probability)) = abort {
+                fault = fault.with_abort(status, probability);
+            }
+            if let Some((duration, probability)) = delay {
+                fault = fault.with_delay(duration, probability);
+            }
+            Some(fault).filter(|f| !f.is_empty())
+        };
+        let http_deadline = {
+            let enabled =
+                parse(strings, ENV_OUTBOUND_HTTP_DEADLINE_ENABLED, parse_bool)?.unwrap_or(false);
+            let header = parse(
+                strings,
+                ENV_OUTBOUND_HTTP_DEADLINE_HEADER,
+                parse_header_name,
+            )?;
+            enabled.then(|| match header {
+                Some(header) => deadline::Config { header },
+                None => deadline::Config::default(),
+            })
+        };
+        let http_idempotency = {
+            let max_body_bytes = parse(
+                strings,
+                ENV_OUTBOUND_HTTP_IDEMPOTENCY_CACHE_MAX_BODY_BYTES,
+                parse_number,
+            )?
+            .unwrap_or(DEFAULT_OUTBOUND_HTTP_IDEMPOTENCY_CACHE_MAX_BODY_BYTES);
+            parse(
+                strings,
+                ENV_OUTBOUND_HTTP_IDEMPOTENCY_CACHE_CAPACITY,
+                parse_number,
+            )?
+            .map(|capacity| idempotency::Config {
+                capacity,
+                max_body_bytes,
+            })
+        };
+        let http_response_cache = {
+            let max_body_bytes = parse(
+                strings,
+                ENV_OUTBOUND_HTTP_RESPONSE_CACHE_MAX_BODY_BYTES,
+                parse_number,
+            )?
+            .unwrap_or(DEFAULT_OUTBOUND_HTTP_RESPONSE_CACHE_MAX_BODY_BYTES);
+            parse(
+                strings,
+                ENV_OUTBOUND_HTTP_RESPONSE_CACHE_MAX_BYTES,
+                parse_number,
+            )?
+            .map(|max_bytes| http_cache::Config {
+                max_bytes,
+                max_body_bytes,
+            })
+        };
+        let http_route_defaults = {
+            let idempotency_ttl =
+                parse(strings, ENV_OUTBOUND_HTTP_IDEMPOTENCY_TTL, parse_duration)?;
+            if idempotency_ttl.is_some() && http_idempotency.is_none() {
+                error!(
+                    "{} requires {}",
+                    ENV_OUTBOUND_HTTP_IDEMPOTENCY_TTL, ENV_OUTBOUND_HTTP_IDEMPOTENCY_CACHE_CAPACITY
+                );
+                return Err(EnvError::InvalidEnvVar);
+            }
+            let cache_responses =
+                parse(strings, ENV_OUTBOUND_HTTP_CACHE_RESPONSES, parse_bool)?.unwrap_or(false);
+            if cache_responses && http_response_cache.is_none() {
+                error!(
+                    "{} requires {}",
+                    ENV_OUTBOUND_HTTP_CACHE_RESPONSES, ENV_OUTBOUND_HTTP_RESPONSE_CACHE_MAX_BYTES
+                );
+                return Err(EnvError::InvalidEnvVar);
+            }
+            let per_try_timeout =
+                parse(strings, ENV_OUTBOUND_HTTP_PER_TRY_TIMEOUT, parse_duration)?;
+            let idle_timeout = parse(strings, ENV_OUTBOUND_HTTP_IDLE_TIMEOUT, parse_duration)?;
+            outbound::http::RouteDefaults {
+                fault: http_fault,
+                per_try_timeout,
+                idle_timeout,
+                idempotency_ttl,
+                cache_responses,
+            }
+        };
+        #[cfg(feature = "wasm")]
+        let http_wasm = parse_http_wasm_config(strings, ENV_OUTBOUND_HTTP_WASM_FILTERS)?;
+        #[cfg(not(feature = "wasm"))]
+        reject_without_feature(strings, ENV_OUTBOUND_HTTP_WASM_FILTERS, "wasm")?;
+        let cache_idle_ages = outbound::CacheIdleAges {
+            profile: parse(
+                strings,
+                ENV_OUTBOUND_PROFILE_CACHE_MAX_IDLE_AGE,
+                parse_duration,
+            )?
+            .unwrap_or(cache_max_idle_age),
+            balancer: parse(strings, ENV_OUTBOUND_BALANCER_MAX_IDLE_AGE, parse_duration)?
+                .unwrap_or(cache_max_idle_age),
+            ingress: parse(strings, ENV_INGRESS_CACHE_MAX_IDLE_AGE, parse_duration)?
+                .unwrap_or(cache_max_idle_age),
+        };
 
         outbound::Config {
             ingress_mode,
-            allow_discovery: AddrMatch::new(dst_profile_suffixes.clone(), dst_profile_networks),
+            #[cfg(feature = "ingress-script")]
+            ingress_script,
+            ingress_tenants,
+            failover,
+            metadata_labels,
+            decompression,
+            egress,
+            bypass,
+            protocol_overrides,
+            server_speaks_first_ports,
+            protocol_hints,
+            tcp_rate_limits,
+            tcp_idle_timeout,
+            tcp_tunnel,
+            connect_timeouts,
+            cache_idle_ages,
+            warm_destinations,
+            happy_eyeballs_delay,
+            http_affinity,
+            split_overrides,
+            split_concrete_header,
+            split_isolation,
+            http_route_defaults,
+            http_deadline,
+            http_idempotency,
+            http_response_cache,
+            #[cfg(feature = "wasm")]
+            http_wasm,
+            allow_discovery: AddrMatch::from_rules(
+                dst_profile_suffixes.clone(),
+                dst_profile_networks,
+            ),
             proxy: ProxyConfig {
                 server,
                 connect,
@@ -444,6 +1912,19 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 max_in_flight_requests: outbound_max_in_flight?
                     .unwrap_or(DEFAULT_OUTBOUND_MAX_IN_FLIGHT),
                 detect_protocol_timeout,
+                queue_time_header,
+                fail_fast_retry_after,
+                error_responses,
+                request_limits: parse_request_limits(
+                    strings,
+                    ENV_OUTBOUND_MAX_REQUEST_HEADERS,
+                    ENV_OUTBOUND_MAX_REQUEST_HEADER_SIZE,
+                    ENV_OUTBOUND_MAX_REQUEST_HEADER_LIST_SIZE,
+                    ENV_OUTBOUND_MAX_REQUEST_URI_LENGTH,
+                )?,
+                request_id: request_id(false),
+                fd_pressure: fd_pressure.clone(),
+                trace_attributes: trace_attributes.clone(),
             },
         }
     };
@@ -452,6 +1933,9 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         allow_discovery: NameMatch::new(gateway_suffixes?.unwrap_or_default()),
     };
 
+    #[cfg(not(feature = "wasm"))]
+    reject_without_feature(strings, ENV_INBOUND_HTTP_WASM_FILTERS, "wasm")?;
+
     let inbound = {
         let addr = ListenAddr(
             inbound_listener_addr?
@@ -461,7 +1945,23 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         let server = ServerConfig {
             addr,
             keepalive,
-            h2_settings,
+            h1_settings: parse_h1_server_settings(
+                strings,
+                ENV_INBOUND_ACCEPT_HTTP1_IDLE_TIMEOUT,
+                ENV_INBOUND_ACCEPT_HTTP1_MAX_REQUESTS,
+                ENV_INBOUND_ACCEPT_HTTP1_CLOSE_ON_DRAIN,
+            )?,
+            // Outbound proxies send WebSocket upgrades as extended CONNECT
+            // requests when the inbound proxy supports it.
+            h2_settings: h2::Settings {
+                enable_connect_protocol: true,
+                max_connection_age: parse(
+                    strings,
+                    ENV_INBOUND_ACCEPT_MAX_CONNECTION_AGE,
+                    parse_duration,
+                )?,
+                ..h2_settings
+            },
         };
         let cache_max_idle_age =
             inbound_cache_max_idle_age?.unwrap_or(DEFAULT_INBOUND_ROUTER_MAX_IDLE_AGE);
@@ -483,10 +1983,39 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             },
         };
 
+        let gateway_clusters = {
+            let mut gateways = gateway_cluster_gateways?.unwrap_or_default();
+            let mut clusters = Vec::new();
+            for (cluster, suffixes) in gateway_clusters?.unwrap_or_default() {
+                let gateway = match gateways.remove(&cluster) {
+                    Some(gateway) => gateway,
+                    None => {
+                        error!(
+                            "{} configures cluster {} without a gateway in {}",
+                            ENV_INBOUND_GATEWAY_CLUSTERS,
+                            cluster,
+                            ENV_INBOUND_GATEWAY_CLUSTER_GATEWAYS
+                        );
+                        return Err(EnvError::InvalidEnvVar);
+                    }
+                };
+                clusters.push((cluster.as_str().into(), NameMatch::new(suffixes), gateway));
+            }
+            if let Some(cluster) = gateways.keys().next() {
+                error!(
+                    "{} configures cluster {} that is not in {}",
+                    ENV_INBOUND_GATEWAY_CLUSTER_GATEWAYS, cluster, ENV_INBOUND_GATEWAY_CLUSTERS
+                );
+                return Err(EnvError::InvalidEnvVar);
+            }
+            inbound::direct::GatewayClusters::new(clusters)
+        };
+
         let detect_protocol_timeout =
             inbound_detect_timeout?.unwrap_or(DEFAULT_INBOUND_DETECT_TIMEOUT);
         let dispatch_timeout =
             inbound_dispatch_timeout?.unwrap_or(DEFAULT_INBOUND_DISPATCH_TIMEOUT);
+        let fail_fast_retry_after = inbound_fail_fast_retry_after?.unwrap_or(false);
 
         let mut require_identity_for_inbound_ports =
             parse(strings, ENV_INBOUND_PORTS_REQUIRE_IDENTITY, parse_port_set)?.unwrap_or_default();
@@ -536,19 +2065,82 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 }
             }
 
-            let default = parse(strings, ENV_INBOUND_DEFAULT_POLICY, |s| {
-                parse_default_policy(s, detect_protocol_timeout)
-            })?
-            .unwrap_or_else(|| {
-                port_policies::all_unauthenticated_server_policy(detect_protocol_timeout).into()
-            });
-
-            let allow_authed =
-                port_policies::all_mtls_unauthenticated_server_policy(detect_protocol_timeout);
-            let allow_opaque = match default.clone() {
-                port_policies::DefaultPolicy::Allow(p) => {
-                    let mut p = (*p).clone();
-                    p.protocol = inbound::port_policies::Protocol::Opaque;
+            // Server-speaks-first ports skip TLS detection, too, so they are subject to the
+            // same constraints as opaque ports.
+            let inbound_server_first_ports = inbound_server_first_ports?.unwrap_or_default();
+            if inbound_server_first_ports.contains(&inbound_port) {
+                error!(
+                    "{} must not contain {} ({})",
+                    ENV_INBOUND_PORTS_SERVER_SPEAKS_FIRST, ENV_INBOUND_LISTEN_ADDR, inbound_port
+                );
+                return Err(EnvError::InvalidEnvVar);
+            }
+            for p in require_identity_for_inbound_ports.iter() {
+                if inbound_server_first_ports.contains(p) {
+                    error!(
+                        "{} must not overlap with {} ({})",
+                        ENV_INBOUND_PORTS_SERVER_SPEAKS_FIRST,
+                        ENV_INBOUND_PORTS_REQUIRE_IDENTITY,
+                        p
+                    );
+                    return Err(EnvError::InvalidEnvVar);
+                }
+            }
+
+            // Passthrough ports skip TLS detection, too, so they are subject to the same
+            // constraints as opaque ports.
+            let inbound_passthrough_tls_ports = inbound_passthrough_tls_ports?.unwrap_or_default();
+            if inbound_passthrough_tls_ports.contains(&inbound_port) {
+                error!(
+                    "{} must not contain {} ({})",
+                    ENV_INBOUND_PORTS_PASSTHROUGH_TLS, ENV_INBOUND_LISTEN_ADDR, inbound_port
+                );
+                return Err(EnvError::InvalidEnvVar);
+            }
+            for p in require_identity_for_inbound_ports.iter() {
+                if inbound_passthrough_tls_ports.contains(p) {
+                    error!(
+                        "{} must not overlap with {} ({})",
+                        ENV_INBOUND_PORTS_PASSTHROUGH_TLS, ENV_INBOUND_PORTS_REQUIRE_IDENTITY, p
+                    );
+                    return Err(EnvError::InvalidEnvVar);
+                }
+            }
+
+            let default = parse(strings, ENV_INBOUND_DEFAULT_POLICY, |s| {
+                parse_default_policy(s, detect_protocol_timeout)
+            })?
+            .unwrap_or_else(|| {
+                port_policies::all_unauthenticated_server_policy(detect_protocol_timeout).into()
+            });
+
+            // An audited policy permits the connections that it would deny, so a 'deny' default
+            // policy is audited as a policy without any authorizations.
+            let audit_deny = inbound::ServerPolicy {
+                authorizations: Vec::new(),
+                audit: true,
+                ..port_policies::all_unauthenticated_server_policy(detect_protocol_timeout)
+            };
+            let default = if inbound_default_policy_audit?.unwrap_or(false) {
+                let policy = match default {
+                    port_policies::DefaultPolicy::Allow(p) => (*p).clone(),
+                    port_policies::DefaultPolicy::Deny => audit_deny.clone(),
+                };
+                inbound::ServerPolicy {
+                    audit: true,
+                    ..policy
+                }
+                .into()
+            } else {
+                default
+            };
+
+            let allow_authed =
+                port_policies::all_mtls_unauthenticated_server_policy(detect_protocol_timeout);
+            let allow_opaque = match default.clone() {
+                port_policies::DefaultPolicy::Allow(p) => {
+                    let mut p = (*p).clone();
+                    p.protocol = inbound::port_policies::Protocol::Opaque;
                     Some(p)
                 }
                 port_policies::DefaultPolicy::Deny => {
@@ -556,21 +2148,194 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                     None
                 }
             };
-            inbound::PortPolicies::new(
-                default,
-                require_identity_for_inbound_ports
-                    .into_iter()
-                    .map(|p| (p, allow_authed.clone()))
-                    .chain(
-                        inbound_opaque_ports
-                            .into_iter()
-                            .filter_map(|p| allow_opaque.clone().map(move |a| (p, a))),
-                    ),
-            )
+            let allow_server_first = match default.clone() {
+                port_policies::DefaultPolicy::Allow(p) => {
+                    let mut p = (*p).clone();
+                    p.protocol = inbound::port_policies::Protocol::ServerSpeaksFirst;
+                    Some(p)
+                }
+                port_policies::DefaultPolicy::Deny => {
+                    tracing::warn!("inbound server-speaks-first ports configuration is ignored when the default policy is 'deny'");
+                    None
+                }
+            };
+            let allow_passthrough_tls = match default.clone() {
+                port_policies::DefaultPolicy::Allow(p) => {
+                    let mut p = (*p).clone();
+                    p.protocol = inbound::port_policies::Protocol::PassthroughTls;
+                    Some(p)
+                }
+                port_policies::DefaultPolicy::Deny => {
+                    tracing::warn!("inbound passthrough TLS ports configuration is ignored when the default policy is 'deny'");
+                    None
+                }
+            };
+            let mut by_port = require_identity_for_inbound_ports
+                .into_iter()
+                .map(|p| (p, allow_authed.clone()))
+                .chain(
+                    inbound_opaque_ports
+                        .into_iter()
+                        .filter_map(|p| allow_opaque.clone().map(move |a| (p, a))),
+                )
+                .chain(
+                    inbound_server_first_ports
+                        .into_iter()
+                        .filter_map(|p| allow_server_first.clone().map(move |a| (p, a))),
+                )
+                .chain(
+                    inbound_passthrough_tls_ports
+                        .into_iter()
+                        .filter_map(|p| allow_passthrough_tls.clone().map(move |a| (p, a))),
+                )
+                .collect::<HashMap<_, _>>();
+
+            // Ports that don't forward the client identity use their configured policy (or the
+            // default policy, if the port isn't otherwise configured). When the default policy is
+            // 'deny', unconfigured ports are never permitted, so they need no policy.
+            for p in inbound_disable_client_id_ports?.unwrap_or_default() {
+                let policy = match (by_port.get(&p), &default) {
+                    (Some(policy), _) => policy.clone(),
+                    (None, port_policies::DefaultPolicy::Allow(d)) => (**d).clone(),
+                    (None, port_policies::DefaultPolicy::Deny) => continue,
+                };
+                by_port.insert(
+                    p,
+                    inbound::ServerPolicy {
+                        forward_client_id: false,
+                        ..policy
+                    },
+                );
+            }
+
+            // Ports with a forward address override their configured (or default) policy's
+            // forwarding target.
+            for (p, addr) in inbound_forward_addrs?.unwrap_or_default() {
+                let policy = match (by_port.get(&p), &default) {
+                    (Some(policy), _) => policy.clone(),
+                    (None, port_policies::DefaultPolicy::Allow(d)) => (**d).clone(),
+                    (None, port_policies::DefaultPolicy::Deny) => continue,
+                };
+                by_port.insert(
+                    p,
+                    inbound::ServerPolicy {
+                        forward_addr: Some(addr),
+                        ..policy
+                    },
+                );
+            }
+
+            // Ports with exempt routes extend their configured (or default) policy so that these
+            // routes are permitted without authorization.
+            for (p, routes) in inbound_exempt_routes?.unwrap_or_default() {
+                let policy = match (by_port.get(&p), &default) {
+                    (Some(policy), _) => policy.clone(),
+                    (None, port_policies::DefaultPolicy::Allow(d)) => (**d).clone(),
+                    (None, port_policies::DefaultPolicy::Deny) => continue,
+                };
+                by_port.insert(
+                    p,
+                    inbound::ServerPolicy {
+                        exempt_routes: routes,
+                        ..policy
+                    },
+                );
+            }
+
+            // Ports with probes extend their configured (or default) policy so that the proxy
+            // answers these requests.
+            for (p, probes) in inbound_probes?.unwrap_or_default() {
+                let policy = match (by_port.get(&p), &default) {
+                    (Some(policy), _) => policy.clone(),
+                    (None, port_policies::DefaultPolicy::Allow(d)) => (**d).clone(),
+                    (None, port_policies::DefaultPolicy::Deny) => continue,
+                };
+                by_port.insert(p, inbound::ServerPolicy { probes, ..policy });
+            }
+
+            // Ports with authorizations replace their configured (or default) policy's
+            // authorizations.
+            for (p, authorizations) in inbound_authorizations?.unwrap_or_default() {
+                let policy = match (by_port.get(&p), &default) {
+                    (Some(policy), _) => policy.clone(),
+                    (None, port_policies::DefaultPolicy::Allow(d)) => (**d).clone(),
+                    (None, port_policies::DefaultPolicy::Deny) => continue,
+                };
+                by_port.insert(
+                    p,
+                    inbound::ServerPolicy {
+                        authorizations,
+                        ..policy
+                    },
+                );
+            }
+
+            // Ports with deny responses override their configured (or default) policy's response
+            // to unauthorized requests.
+            let mut deny_response_headers = inbound_deny_response_headers?.unwrap_or_default();
+            for (p, mut rsp) in inbound_deny_responses?.unwrap_or_default() {
+                rsp.headers
+                    .extend(deny_response_headers.remove(&p).unwrap_or_default());
+                let policy = match (by_port.get(&p), &default) {
+                    (Some(policy), _) => policy.clone(),
+                    (None, port_policies::DefaultPolicy::Allow(d)) => (**d).clone(),
+                    (None, port_policies::DefaultPolicy::Deny) => continue,
+                };
+                by_port.insert(
+                    p,
+                    inbound::ServerPolicy {
+                        deny_response: Some(rsp),
+                        ..policy
+                    },
+                );
+            }
+            // Audited ports log and count the connections that their configured (or default)
+            // policy would deny, but permit them.
+            for p in inbound_audit_ports?.unwrap_or_default() {
+                let policy = match (by_port.get(&p), &default) {
+                    (Some(policy), _) => policy.clone(),
+                    (None, port_policies::DefaultPolicy::Allow(d)) => (**d).clone(),
+                    (None, port_policies::DefaultPolicy::Deny) => audit_deny.clone(),
+                };
+                by_port.insert(
+                    p,
+                    inbound::ServerPolicy {
+                        audit: true,
+                        ..policy
+                    },
+                );
+            }
+
+            if let Some(p) = deny_response_headers.keys().next() {
+                error!(
+                    "{} configures port {} without a response in {}",
+                    ENV_INBOUND_PORTS_DENY_RESPONSE_HEADERS, p, ENV_INBOUND_PORTS_DENY_RESPONSES
+                );
+                return Err(EnvError::InvalidEnvVar);
+            }
+
+            inbound::PortPolicies::new(default, by_port)
+                .with_client_priorities(&inbound_client_priorities?.unwrap_or_default())
         };
 
+        let additional_listeners = parse(strings, ENV_INBOUND_ADDITIONAL_LISTENERS, |s| {
+            parse_inbound_listeners(s, detect_protocol_timeout)
+        })?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(addr, default_policy)| inbound::ListenerConfig {
+            server: ServerConfig {
+                addr: ListenAddr(addr),
+                keepalive: server.keepalive,
+                h1_settings: server.h1_settings,
+                h2_settings: server.h2_settings,
+            },
+            default_policy,
+        })
+        .collect();
+
         inbound::Config {
-            allow_discovery: NameMatch::new(dst_profile_suffixes),
+            allow_discovery: NameMatch::from_rules(dst_profile_suffixes),
             proxy: ProxyConfig {
                 server,
                 connect,
@@ -580,10 +2345,72 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 max_in_flight_requests: inbound_max_in_flight?
                     .unwrap_or(DEFAULT_INBOUND_MAX_IN_FLIGHT),
                 detect_protocol_timeout,
+                queue_time_header,
+                fail_fast_retry_after,
+                error_responses,
+                request_limits: parse_request_limits(
+                    strings,
+                    ENV_INBOUND_MAX_REQUEST_HEADERS,
+                    ENV_INBOUND_MAX_REQUEST_HEADER_SIZE,
+                    ENV_INBOUND_MAX_REQUEST_HEADER_LIST_SIZE,
+                    ENV_INBOUND_MAX_REQUEST_URI_LENGTH,
+                )?,
+                request_id: request_id(true),
+                fd_pressure,
+                trace_attributes,
             },
             port_policies,
             profile_idle_timeout: dst_profile_idle_timeout?
                 .unwrap_or(DEFAULT_DESTINATION_PROFILE_IDLE_TIMEOUT),
+            client_id_header: inbound_client_id_header?
+                .unwrap_or_else(|| http::HeaderName::from_static(DEFAULT_INBOUND_CLIENT_ID_HEADER)),
+            jwt: inbound_jwt?.map(jwt::Validator::new),
+            // The authorization client is built with the rest of the app.
+            ext_authz: None,
+            #[cfg(feature = "wasm")]
+            http_wasm: parse_http_wasm_config(strings, ENV_INBOUND_HTTP_WASM_FILTERS)?,
+            forwarded: inbound_forwarded?,
+            grpc_web: inbound_grpc_web?.unwrap_or(false),
+            compression: inbound_compression?,
+            load_shed: inbound_load_shed?,
+            http1_malformed: http::malformed::Policies::new(
+                inbound_http1_malformed?.unwrap_or_default(),
+            ),
+            http1_preserve_header_case: inbound_http1_preserve_header_case?
+                .unwrap_or_default()
+                .into(),
+            http1_strict_framing: inbound_http1_strict_framing?.unwrap_or(true),
+            gateway_clusters,
+            gateway_trust_domains: gateway_trust_domains?.unwrap_or_default().into(),
+            tunnel: parse(strings, ENV_INBOUND_TUNNEL_ENABLED, parse_bool)?.unwrap_or(false),
+            tcp_rate_limits: parse_tcp_rate_limits(
+                strings,
+                ENV_INBOUND_TCP_CONNECTION_RATE_LIMITS,
+                ENV_INBOUND_TCP_PORT_RATE_LIMITS,
+            )?,
+            tcp_idle_timeout: parse(strings, ENV_INBOUND_TCP_IDLE_TIMEOUT, parse_duration)?,
+            connection_limits: inbound::ConnectionLimits::new(
+                parse(
+                    strings,
+                    ENV_INBOUND_TCP_PORT_CONNECTION_LIMITS,
+                    parse_port_connection_limits,
+                )?
+                .unwrap_or_default(),
+                parse(
+                    strings,
+                    ENV_INBOUND_TCP_CLIENT_CONNECTION_LIMITS,
+                    parse_port_connection_limits,
+                )?
+                .unwrap_or_default(),
+            ),
+            tls_handshake_limits: parse_tls_handshake_limits(strings)?,
+            discover_opaque_ports: parse(
+                strings,
+                ENV_INBOUND_OPAQUE_PORTS_DISCOVERY_ENABLED,
+                parse_bool,
+            )?
+            .unwrap_or(false),
+            additional_listeners,
         }
     };
 
@@ -606,12 +2433,16 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
 
     let admin = super::admin::Config {
         metrics_retain_idle: metrics_retain_idle?.unwrap_or(DEFAULT_METRICS_RETAIN_IDLE),
+        metrics_peer_identity_limit: metrics_peer_identity_limit?
+            .unwrap_or(DEFAULT_METRICS_PEER_IDENTITY_LIMIT),
+        drain_grace_period: admin_drain_grace_period?.unwrap_or(DEFAULT_ADMIN_DRAIN_GRACE_PERIOD),
         server: ServerConfig {
             addr: ListenAddr(
                 admin_listener_addr?
                     .unwrap_or_else(|| parse_socket_addr(DEFAULT_ADMIN_LISTEN_ADDR).unwrap()),
             ),
             keepalive: inbound.proxy.server.keepalive,
+            h1_settings: Default::default(),
             h2_settings,
         },
     };
@@ -619,9 +2450,14 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
     let dns = dns::Config {
         min_ttl: dns_min_ttl?,
         max_ttl: dns_max_ttl?,
+        negative_min_ttl: dns_negative_min_ttl?,
+        negative_max_ttl: dns_negative_max_ttl?,
+        cache_size: dns_cache_size?.unwrap_or(0),
         resolv_conf_path: resolv_conf_path?
             .unwrap_or_else(|| DEFAULT_RESOLV_CONF.into())
             .into(),
+        servers: dns_servers?,
+        suffix_servers: dns_suffix_servers?.unwrap_or_default(),
     };
 
     let oc_collector = match trace_collector_addr? {
@@ -643,6 +2479,16 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             oc_collector::Config::Enabled(Box::new(oc_collector::EnabledConfig {
                 attributes,
                 hostname: hostname?,
+                batch: opencensus::BatchConfig {
+                    max_size: trace_batch_size?
+                        .map(NonZeroUsize::get)
+                        .unwrap_or(DEFAULT_TRACE_BATCH_SIZE),
+                    flush_interval: trace_flush_interval?.unwrap_or(DEFAULT_TRACE_FLUSH_INTERVAL),
+                },
+                buffer_capacity: trace_buffer_capacity?
+                    .map(NonZeroUsize::get)
+                    .unwrap_or(DEFAULT_TRACE_BUFFER_CAPACITY),
+                send_timeout: trace_send_timeout?,
                 control: ControlConfig {
                     addr,
                     connect,
@@ -652,17 +2498,75 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         }
     };
 
+    let ext_authz = {
+        let authz = ext_authz::Config {
+            timeout: ext_authz_timeout?.unwrap_or(DEFAULT_INBOUND_EXT_AUTHZ_TIMEOUT),
+            failure_mode_allow: ext_authz_failure_mode_allow?.unwrap_or(false),
+            allowed_request_headers: match ext_authz_request_headers? {
+                Some(headers) => headers,
+                None => parse_header_names(DEFAULT_INBOUND_EXT_AUTHZ_ALLOWED_REQUEST_HEADERS)
+                    .expect("default headers must be valid"),
+            },
+            allowed_upstream_headers: ext_authz_upstream_headers?.unwrap_or_default(),
+        };
+        match (ext_authz_addr?, ext_authz_prefix?) {
+            (Some(addr), prefix) => {
+                let connect = if addr.addr.is_loopback() {
+                    inbound.proxy.connect.clone()
+                } else {
+                    outbound.proxy.connect.clone()
+                };
+                let control = ControlConfig {
+                    addr,
+                    connect,
+                    buffer_capacity: inbound.proxy.buffer_capacity,
+                };
+                match prefix {
+                    Some(prefix) => super::ext_authz::Config::Http {
+                        control,
+                        prefix,
+                        authz,
+                    },
+                    None => super::ext_authz::Config::Grpc { control, authz },
+                }
+            }
+            (None, Some(_)) => {
+                error!(
+                    "{} requires {}_ADDR to be set",
+                    ENV_INBOUND_EXT_AUTHZ_HTTP_PATH_PREFIX, ENV_INBOUND_EXT_AUTHZ_SVC_BASE
+                );
+                return Err(EnvError::InvalidEnvVar);
+            }
+            (None, None) => super::ext_authz::Config::Disabled,
+        }
+    };
+
     let tap = tap?
         .map(|(addr, ids)| super::tap::Config::Enabled {
             permitted_client_ids: ids,
             config: ServerConfig {
                 addr: ListenAddr(addr),
                 keepalive: inbound.proxy.server.keepalive,
+                h1_settings: Default::default(),
                 h2_settings,
             },
         })
         .unwrap_or(super::tap::Config::Disabled);
 
+    let udp = {
+        let idle_timeout = udp_idle_timeout?.unwrap_or(DEFAULT_OUTBOUND_UDP_IDLE_TIMEOUT);
+        let max_sessions = udp_max_sessions?
+            .map(NonZeroUsize::get)
+            .unwrap_or(DEFAULT_OUTBOUND_UDP_MAX_SESSIONS);
+        udp_listener_addr?
+            .map(|addr| super::udp::Config::Enabled {
+                addr: ListenAddr(addr),
+                idle_timeout,
+                max_sessions,
+            })
+            .unwrap_or(super::udp::Config::Disabled)
+    };
+
     let identity = identity_config?
         .map(|(addr, certify)| {
             // If the address doesn't have a server identity, then we're on localhost.
@@ -692,6 +2596,11 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         outbound,
         gateway,
         inbound,
+        ext_authz,
+        udp,
+        extensions: Default::default(),
+        shutdown_delay: shutdown_delay?.unwrap_or(DEFAULT_SHUTDOWN_DELAY),
+        outbound_drain_linger: outbound_drain_linger?.unwrap_or(DEFAULT_OUTBOUND_DRAIN_LINGER),
     })
 }
 
@@ -780,6 +2689,26 @@ fn parse_tap_config(
     Ok(None)
 }
 
+/// Fails if a variable that configures an optional feature is set but the
+/// proxy was built without that feature.
+#[cfg(any(not(feature = "wasm"), not(feature = "ingress-script")))]
+fn reject_without_feature<S: Strings>(
+    strings: &S,
+    name: &str,
+    feature: &str,
+) -> Result<(), EnvError> {
+    match strings.get(name)? {
+        Some(s) if !s.trim().is_empty() => {
+            error!(
+                "{} requires the proxy to be built with the `{}` feature",
+                name, feature
+            );
+            Err(EnvError::InvalidEnvVar)
+        }
+        _ => Ok(()),
+    }
+}
+
 fn parse_bool(s: &str) -> Result<bool, ParseError> {
     s.parse().map_err(Into::into)
 }
@@ -811,6 +2740,13 @@ fn parse_duration(s: &str) -> Result<Duration, ParseError> {
     }
 }
 
+fn parse_reload_interval(s: &str) -> Result<Duration, ParseError> {
+    match parse_duration(s)? {
+        d if d == Duration::from_secs(0) => Err(ParseError::ZeroReloadInterval),
+        d => Ok(d),
+    }
+}
+
 fn parse_socket_addr(s: &str) -> Result<SocketAddr, ParseError> {
     match parse_addr(s)? {
         Addr::Socket(a) => Ok(a),
@@ -828,6 +2764,43 @@ fn parse_addr(s: &str) -> Result<Addr, ParseError> {
     })
 }
 
+fn parse_socket_addrs(s: &str) -> Result<Vec<SocketAddr>, ParseError> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(parse_socket_addr)
+        .collect()
+}
+
+fn parse_h1_server_settings<S: Strings>(
+    strings: &S,
+    idle_timeout_env: &str,
+    max_requests_env: &str,
+    close_on_drain_env: &str,
+) -> Result<h1::ServerSettings, EnvError> {
+    Ok(h1::ServerSettings {
+        idle_timeout: parse(strings, idle_timeout_env, parse_duration)?,
+        max_requests: parse(strings, max_requests_env, parse_number)?,
+        close_on_drain: parse(strings, close_on_drain_env, parse_bool)?.unwrap_or(false),
+        preserve_header_case: false,
+    })
+}
+
+fn parse_request_limits<S: Strings>(
+    strings: &S,
+    max_headers_env: &str,
+    max_header_size_env: &str,
+    max_header_list_size_env: &str,
+    max_uri_len_env: &str,
+) -> Result<request_limits::Limits, EnvError> {
+    Ok(request_limits::Limits {
+        max_headers: parse(strings, max_headers_env, parse_number)?,
+        max_header_size: parse(strings, max_header_size_env, parse_number)?,
+        max_header_list_size: parse(strings, max_header_list_size_env, parse_number)?,
+        max_uri_len: parse(strings, max_uri_len_env, parse_number)?,
+    })
+}
+
 fn parse_port_set(s: &str) -> Result<HashSet<u16>, ParseError> {
     let mut set = HashSet::new();
     for num in s.split(',') {
@@ -836,114 +2809,875 @@ fn parse_port_set(s: &str) -> Result<HashSet<u16>, ParseError> {
     Ok(set)
 }
 
-pub(super) fn parse_identity(s: &str) -> Result<identity::Name, ParseError> {
-    identity::Name::from_str(s).map_err(|identity::InvalidName| {
-        error!("Not a valid identity name: {}", s);
-        ParseError::NameError
-    })
+fn parse_span_attributes(s: &str) -> Result<http_tracing::Attributes, ParseError> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().map_err(ParseError::InvalidSpanAttribute))
+        .collect()
 }
 
-pub(super) fn parse<T, Parse>(
-    strings: &dyn Strings,
-    name: &str,
-    parse: Parse,
-) -> Result<Option<T>, EnvError>
-where
-    Parse: FnOnce(&str) -> Result<T, ParseError>,
-{
-    match strings.get(name)? {
-        Some(ref s) => {
-            let r = parse(s).map_err(|parse_error| {
-                error!("{}={:?} is not valid: {:?}", name, s, parse_error);
-                EnvError::InvalidEnvVar
-            })?;
-            Ok(Some(r))
-        }
-        None => Ok(None),
+fn parse_port_forward_addrs(s: &str) -> Result<HashMap<u16, SocketAddr>, ParseError> {
+    let mut addrs = HashMap::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (port, addr) = entry.split_once('=').ok_or_else(|| {
+            error!("Expected PORT=IP:PORT; found: {}", entry);
+            ParseError::InvalidPortPolicy(entry.to_string())
+        })?;
+        let port = parse_number::<u16>(port.trim())?;
+        let addr = parse_socket_addr(addr.trim())?;
+        addrs.insert(port, addr);
     }
+    Ok(addrs)
 }
 
-#[allow(dead_code)]
-fn parse_deprecated<T, Parse>(
-    strings: &dyn Strings,
-    name: &str,
-    deprecated_name: &str,
-    f: Parse,
-) -> Result<Option<T>, EnvError>
-where
-    Parse: Copy,
-    Parse: Fn(&str) -> Result<T, ParseError>,
-{
-    match parse(strings, name, f)? {
-        Some(v) => Ok(Some(v)),
-        None => {
-            let v = parse(strings, deprecated_name, f)?;
-            if v.is_some() {
-                warn!("{} has been deprecated; use {}", deprecated_name, name);
-            }
-            Ok(v)
+fn parse_port_exempt_routes(
+    s: &str,
+) -> Result<HashMap<u16, Vec<inbound::ExemptRoute>>, ParseError> {
+    let mut routes = HashMap::<u16, Vec<_>>::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let invalid = || {
+            error!("Expected PORT=[METHOD ]PATH; found: {}", entry);
+            ParseError::InvalidPortPolicy(entry.to_string())
+        };
+        let (port, route) = entry.split_once('=').ok_or_else(invalid)?;
+        let port = parse_number::<u16>(port.trim())?;
+        let (method, path) = match route.trim().split_once(' ') {
+            Some((method, path)) => (Some(method.to_string()), path.trim()),
+            None => (None, route.trim()),
+        };
+        if !path.starts_with('/') {
+            return Err(invalid());
         }
+        routes.entry(port).or_default().push(inbound::ExemptRoute {
+            method,
+            path: path.to_string(),
+        });
     }
+    Ok(routes)
 }
 
-fn parse_dns_suffixes(list: &str) -> Result<HashSet<dns::Suffix>, ParseError> {
-    let mut suffixes = HashSet::new();
-    for item in list.split(',') {
-        let item = item.trim();
-        if !item.is_empty() {
-            let sfx = parse_dns_suffix(item)?;
-            suffixes.insert(sfx);
+fn parse_port_probes(s: &str) -> Result<HashMap<u16, Vec<inbound::Probe>>, ParseError> {
+    let mut probes = HashMap::<u16, Vec<_>>::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let invalid = || {
+            error!("Expected PORT=PATH CHECK; found: {}", entry);
+            ParseError::InvalidPortPolicy(entry.to_string())
+        };
+        let (port, probe) = entry.split_once('=').ok_or_else(invalid)?;
+        let port = parse_number::<u16>(port.trim())?;
+        let (path, check) = probe.trim().split_once(' ').ok_or_else(invalid)?;
+        if !path.starts_with('/') {
+            return Err(invalid());
         }
+        let check = match check.trim().split_once(':') {
+            Some(("tcp", addr)) => inbound::ProbeCheck::Tcp(parse_socket_addr(addr)?),
+            Some(("http", target)) => {
+                let (addr, path) = match target.find('/') {
+                    Some(idx) => target.split_at(idx),
+                    None => (target, "/"),
+                };
+                inbound::ProbeCheck::Http {
+                    addr: parse_socket_addr(addr)?,
+                    path: path.to_string(),
+                }
+            }
+            _ => return Err(invalid()),
+        };
+        probes.entry(port).or_default().push(inbound::Probe {
+            path: path.to_string(),
+            check,
+        });
     }
-
-    Ok(suffixes)
+    Ok(probes)
 }
 
-fn parse_dns_suffix(s: &str) -> Result<dns::Suffix, ParseError> {
-    if s == "." {
-        return Ok(dns::Suffix::Root);
+fn parse_port_authorizations(
+    s: &str,
+) -> Result<HashMap<u16, Vec<port_policies::Authorization>>, ParseError> {
+    let mut authorizations = HashMap::<u16, Vec<_>>::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let invalid = || {
+            error!("Expected PORT=CLIENT[@NETWORK]; found: {}", entry);
+            ParseError::InvalidPortPolicy(entry.to_string())
+        };
+        let (port, rule) = entry.split_once('=').ok_or_else(invalid)?;
+        let port = parse_number::<u16>(port.trim())?;
+        let rule = rule.trim();
+        let (client, networks) = match rule.split_once('@') {
+            Some((client, net)) => {
+                let net = IpNet::from_str(net.trim()).map_err(|_| invalid())?;
+                (client.trim(), vec![net.into()])
+            }
+            None => (
+                rule,
+                vec![Ipv4Net::default().into(), Ipv6Net::default().into()],
+            ),
+        };
+        let authentication = match client {
+            "unauthenticated" => port_policies::Authentication::Unauthenticated,
+            "tls" => port_policies::Authentication::TlsUnauthenticated,
+            "" => return Err(invalid()),
+            suffix if suffix.starts_with('*') => {
+                let parts = match suffix.strip_prefix("*.") {
+                    Some(parts) => parts.split('.').map(String::from).collect(),
+                    None if suffix == "*" => vec![],
+                    None => return Err(invalid()),
+                };
+                port_policies::Authentication::TlsAuthenticated {
+                    identities: Default::default(),
+                    suffixes: vec![port_policies::Suffix::from(parts)],
+                }
+            }
+            id => port_policies::Authentication::TlsAuthenticated {
+                identities: Some(parse_identity(id)?.to_string()).into_iter().collect(),
+                suffixes: vec![],
+            },
+        };
+        authorizations
+            .entry(port)
+            .or_default()
+            .push(port_policies::Authorization {
+                networks,
+                authentication,
+                labels: Some(("authz".to_string(), rule.to_string()))
+                    .into_iter()
+                    .collect(),
+                priority: port_policies::Priority::Normal,
+            });
     }
+    Ok(authorizations)
+}
 
-    dns::Suffix::from_str(s).map_err(|_| ParseError::NotADomainSuffix)
+fn parse_port_deny_responses(
+    s: &str,
+) -> Result<HashMap<u16, port_policies::DenyResponse>, ParseError> {
+    let mut responses = HashMap::new();
+    for entry in s.lines().map(str::trim).filter(|s| !s.is_empty()) {
+        let invalid = || {
+            error!("Expected PORT=STATUS[ BODY]; found: {}", entry);
+            ParseError::InvalidPortPolicy(entry.to_string())
+        };
+        let (port, rsp) = entry.split_once('=').ok_or_else(invalid)?;
+        let port = parse_number::<u16>(port.trim())?;
+        let (status, body) = match rsp.trim().split_once(' ') {
+            Some((status, body)) => (status, body.trim()),
+            None => (rsp.trim(), ""),
+        };
+        let status = parse_number::<u16>(status)?;
+        if http::StatusCode::from_u16(status).is_err() {
+            return Err(invalid());
+        }
+        let headers = if body.is_empty() {
+            vec![]
+        } else if body.starts_with('{') {
+            vec![("content-type".to_string(), "application/json".to_string())]
+        } else {
+            vec![("content-type".to_string(), "text/plain".to_string())]
+        };
+        responses.insert(
+            port,
+            port_policies::DenyResponse {
+                status,
+                headers,
+                body: body.to_string(),
+            },
+        );
+    }
+    Ok(responses)
 }
 
-fn parse_networks(list: &str) -> Result<HashSet<IpNet>, ParseError> {
-    let mut nets = HashSet::new();
-    for input in list.split(',') {
-        let input = input.trim();
-        if !input.is_empty() {
-            let net = IpNet::from_str(input).map_err(|error| {
-                error!(%input, %error, "Invalid network");
-                ParseError::NotANetwork
-            })?;
-            nets.insert(net);
+fn parse_port_deny_response_headers(
+    s: &str,
+) -> Result<HashMap<u16, Vec<(String, String)>>, ParseError> {
+    let mut headers = HashMap::<u16, Vec<_>>::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let invalid = || {
+            error!("Expected PORT=NAME:VALUE; found: {}", entry);
+            ParseError::InvalidPortPolicy(entry.to_string())
+        };
+        let (port, header) = entry.split_once('=').ok_or_else(invalid)?;
+        let port = parse_number::<u16>(port.trim())?;
+        let (name, value) = header.split_once(':').ok_or_else(invalid)?;
+        let name = parse_header_name(name.trim())?;
+        let value = value.trim();
+        if http::HeaderValue::from_str(value).is_err() {
+            return Err(invalid());
         }
+        headers
+            .entry(port)
+            .or_default()
+            .push((name.as_str().to_string(), value.to_string()));
     }
-    Ok(nets)
+    Ok(headers)
 }
 
-fn parse_default_policy(
+fn parse_inbound_listeners(
     s: &str,
     detect_timeout: Duration,
-) -> Result<port_policies::DefaultPolicy, ParseError> {
-    match s {
-        "deny" => Ok(port_policies::DefaultPolicy::Deny),
-        "all-authenticated" => {
-            Ok(port_policies::all_authenticated_server_policy(detect_timeout).into())
-        }
-        "all-unauthenticated" => {
-            Ok(port_policies::all_unauthenticated_server_policy(detect_timeout).into())
-        }
-        "all-mtls-unauthenticated" => {
-            Ok(port_policies::all_mtls_unauthenticated_server_policy(detect_timeout).into())
-        }
-        name => Err(ParseError::InvalidPortPolicy(name.to_string())),
+) -> Result<Vec<(SocketAddr, Option<port_policies::DefaultPolicy>)>, ParseError> {
+    let mut listeners = Vec::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (addr, policy) = match entry.split_once('=') {
+            Some((addr, policy)) => (addr, Some(policy.trim())),
+            None => (entry, None),
+        };
+        let addr = parse_socket_addr(addr.trim())?;
+        let policy = policy
+            .map(|p| parse_default_policy(p, detect_timeout))
+            .transpose()?;
+        listeners.push((addr, policy));
     }
+    Ok(listeners)
 }
-pub fn parse_backoff<S: Strings>(
-    strings: &S,
-    base: &str,
-    default: ExponentialBackoff,
+
+fn parse_cookie_name(s: &str) -> Result<String, ParseError> {
+    // Cookie names are RFC 7230 tokens.
+    let is_tchar = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c);
+    if s.is_empty() || !s.chars().all(is_tchar) {
+        error!("Not a valid cookie name: {}", s);
+        return Err(ParseError::InvalidCookieName(s.to_string()));
+    }
+    Ok(s.to_string())
+}
+
+/// Parses a `VALUE:PROBABILITY` fault, where the probability is in `[0, 1]`.
+fn parse_fault<T>(
+    s: &str,
+    parse_value: impl Fn(&str) -> Result<T, ParseError>,
+) -> Result<(T, f64), ParseError> {
+    let invalid = || {
+        error!("Not a valid fault: {}", s);
+        ParseError::InvalidFault(s.to_string())
+    };
+    let (value, probability) = match s.rsplitn(2, ':').collect::<Vec<_>>().as_slice() {
+        [probability, value] => (value.trim(), probability.trim()),
+        _ => return Err(invalid()),
+    };
+    let value = parse_value(value).map_err(|_| invalid())?;
+    let probability = probability
+        .parse::<f64>()
+        .ok()
+        .filter(|p| (0.0..=1.0).contains(p))
+        .ok_or_else(invalid)?;
+    Ok((value, probability))
+}
+
+fn parse_status_code(s: &str) -> Result<http::StatusCode, ParseError> {
+    let code = parse_number(s)?;
+    http::StatusCode::from_u16(code).map_err(|_| ParseError::InvalidFault(s.to_string()))
+}
+
+fn parse_header_name(s: &str) -> Result<http::HeaderName, ParseError> {
+    http::HeaderName::from_str(s).map_err(|_| {
+        error!("Not a valid header name: {}", s);
+        ParseError::InvalidHeaderName
+    })
+}
+
+fn parse_header_names(s: &str) -> Result<Vec<http::HeaderName>, ParseError> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(parse_header_name)
+        .collect()
+}
+
+fn parse_path_prefix(s: &str) -> Result<http::uri::PathAndQuery, ParseError> {
+    if !s.starts_with('/') || s.contains('?') {
+        return Err(ParseError::InvalidUri);
+    }
+    http::uri::PathAndQuery::from_str(s).map_err(|_| ParseError::InvalidUri)
+}
+
+fn parse_gateway_clusters(s: &str) -> Result<HashMap<String, Vec<dns::Suffix>>, ParseError> {
+    let mut clusters = HashMap::<String, Vec<dns::Suffix>>::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (cluster, suffix) = match entry.split_once('=') {
+            Some((c, s)) if !c.trim().is_empty() && !c.contains('.') => (c.trim(), s.trim()),
+            _ => {
+                error!("Not a valid gateway cluster: {}", entry);
+                return Err(ParseError::InvalidGatewayCluster(entry.to_string()));
+            }
+        };
+        clusters
+            .entry(cluster.to_ascii_lowercase())
+            .or_default()
+            .push(parse_dns_suffix(suffix)?);
+    }
+
+    Ok(clusters)
+}
+
+fn parse_gateway_cluster_gateways(
+    s: &str,
+) -> Result<HashMap<String, inbound::direct::RemoteGateway>, ParseError> {
+    let mut gateways = HashMap::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (cluster, identity, addr) = match entry.split_once('=') {
+            Some((c, gw)) if !c.trim().is_empty() && !c.contains('.') => {
+                match gw.trim().split_once('@') {
+                    Some((id, addr)) => (c.trim(), id, addr),
+                    None => {
+                        error!("Not a valid gateway cluster: {}", entry);
+                        return Err(ParseError::InvalidGatewayCluster(entry.to_string()));
+                    }
+                }
+            }
+            _ => {
+                error!("Not a valid gateway cluster: {}", entry);
+                return Err(ParseError::InvalidGatewayCluster(entry.to_string()));
+            }
+        };
+        let gateway = inbound::direct::RemoteGateway {
+            identity: parse_identity(identity)?,
+            addr: parse_socket_addr(addr)?,
+        };
+        if gateways
+            .insert(cluster.to_ascii_lowercase(), gateway)
+            .is_some()
+        {
+            error!("Gateway cluster configured more than once: {}", cluster);
+            return Err(ParseError::InvalidGatewayCluster(entry.to_string()));
+        }
+    }
+    Ok(gateways)
+}
+
+fn parse_ingress_tenants(s: &str) -> Result<HashMap<String, Vec<dns::Suffix>>, ParseError> {
+    let mut tenants = HashMap::<String, Vec<dns::Suffix>>::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (tenant, suffix) = match entry.split_once('=') {
+            Some((t, s)) if !t.trim().is_empty() => (t.trim(), s.trim()),
+            _ => {
+                error!("Not a valid ingress tenant: {}", entry);
+                return Err(ParseError::InvalidIngressTenant(entry.to_string()));
+            }
+        };
+        tenants
+            .entry(tenant.to_string())
+            .or_default()
+            .push(parse_dns_suffix(suffix)?);
+    }
+    Ok(tenants)
+}
+
+fn parse_ingress_tenant_quotas(s: &str) -> Result<HashMap<String, usize>, ParseError> {
+    let mut quotas = HashMap::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (tenant, max) = match entry.split_once('=') {
+            Some((t, m)) if !t.trim().is_empty() => (t.trim(), m),
+            _ => {
+                error!("Not a valid ingress tenant quota: {}", entry);
+                return Err(ParseError::InvalidIngressTenant(entry.to_string()));
+            }
+        };
+        quotas.insert(tenant.to_string(), parse_number(max.trim())?);
+    }
+    Ok(quotas)
+}
+
+fn parse_port_rates(s: &str) -> Result<Vec<(u16, u64)>, ParseError> {
+    let mut rates = Vec::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let invalid = || {
+            error!("Not a valid rate limit: {}", entry);
+            ParseError::InvalidRateLimit(entry.to_string())
+        };
+
+        let (port, rate) = entry.split_once('=').ok_or_else(invalid)?;
+        let port = parse_number::<u16>(port.trim())?;
+        let rate = parse_number::<u64>(rate.trim())?;
+        if rate == 0 {
+            return Err(invalid());
+        }
+        rates.push((port, rate));
+    }
+    Ok(rates)
+}
+
+fn parse_port_connection_limits(s: &str) -> Result<Vec<(u16, usize)>, ParseError> {
+    let mut limits = Vec::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let invalid = || {
+            error!("Not a valid connection limit: {}", entry);
+            ParseError::InvalidConnectionLimit(entry.to_string())
+        };
+
+        let (port, max) = entry.split_once('=').ok_or_else(invalid)?;
+        let port = parse_number::<u16>(port.trim())?;
+        let max = parse_number::<usize>(max.trim())?;
+        if max == 0 {
+            return Err(invalid());
+        }
+        limits.push((port, max));
+    }
+    Ok(limits)
+}
+
+fn parse_port_malformed_policies(
+    s: &str,
+) -> Result<Vec<(u16, http::malformed::Policy)>, ParseError> {
+    let mut policies = Vec::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let invalid = || {
+            error!("Not a valid malformed request policy: {}", entry);
+            ParseError::InvalidMalformedPolicy(entry.to_string())
+        };
+
+        let (port, policy) = entry.split_once('=').ok_or_else(invalid)?;
+        let port = parse_number::<u16>(port.trim())?;
+        let policy = match policy.trim() {
+            "normalize" => http::malformed::Policy::Normalize,
+            "reject" => http::malformed::Policy::Reject,
+            _ => return Err(invalid()),
+        };
+        policies.push((port, policy));
+    }
+    Ok(policies)
+}
+
+fn parse_client_priorities(s: &str) -> Result<Vec<(IpNet, port_policies::Priority)>, ParseError> {
+    let mut priorities = Vec::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let invalid = || {
+            error!("Not a valid client priority: {}", entry);
+            ParseError::InvalidPriority(entry.to_string())
+        };
+
+        let (net, priority) = entry.split_once('=').ok_or_else(invalid)?;
+        let net = IpNet::from_str(net.trim()).map_err(|_| invalid())?;
+        let priority = match priority.trim() {
+            "low" => port_policies::Priority::Low,
+            "normal" => port_policies::Priority::Normal,
+            "high" => port_policies::Priority::High,
+            _ => return Err(invalid()),
+        };
+        priorities.push((net, priority));
+    }
+    Ok(priorities)
+}
+
+fn parse_metadata_labels(s: &str) -> Result<outbound::endpoint::MetadataLabels, ParseError> {
+    Ok(outbound::endpoint::MetadataLabels::only(
+        s.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from),
+    ))
+}
+
+fn parse_failover_cluster(s: &str) -> Result<String, ParseError> {
+    let cluster = s.trim();
+    if cluster.is_empty()
+        || !cluster
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        error!("Not a valid failover cluster: {}", s);
+        return Err(ParseError::InvalidFailoverCluster(s.to_string()));
+    }
+    Ok(cluster.to_ascii_lowercase())
+}
+
+#[cfg(feature = "wasm")]
+fn parse_http_wasm_filters(s: &str) -> Result<Vec<http_wasm::FilterConfig>, ParseError> {
+    let mut filters = Vec::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let invalid = || {
+            error!("Not a valid WASM filter: {}", entry);
+            ParseError::InvalidWasmFilter(entry.to_string())
+        };
+
+        let (name, paths) = entry.split_once('=').ok_or_else(invalid)?;
+        let (module, configuration) = match paths.split_once(':') {
+            Some((module, configuration)) => (module.trim(), Some(configuration.trim())),
+            None => (paths.trim(), None),
+        };
+        let name = name.trim();
+        if name.is_empty() || module.is_empty() || configuration == Some("") {
+            return Err(invalid());
+        }
+
+        filters.push(http_wasm::FilterConfig {
+            name: name.to_string(),
+            module: PathBuf::from(module),
+            configuration: configuration.map(PathBuf::from),
+        });
+    }
+    Ok(filters)
+}
+
+fn parse_egress_action(s: &str) -> Result<outbound::egress::Action, ParseError> {
+    match s.trim() {
+        "allow" => Ok(outbound::egress::Action::Allow),
+        "deny" => Ok(outbound::egress::Action::Deny),
+        action => {
+            error!("Not a valid egress policy action: {}", action);
+            Err(ParseError::InvalidEgressPolicy(action.to_string()))
+        }
+    }
+}
+
+fn parse_egress_mode(s: &str) -> Result<outbound::egress::Mode, ParseError> {
+    match s.trim() {
+        "enforce" => Ok(outbound::egress::Mode::Enforce),
+        "audit" => Ok(outbound::egress::Mode::Audit),
+        mode => {
+            error!("Not a valid egress policy mode: {}", mode);
+            Err(ParseError::InvalidEgressPolicy(mode.to_string()))
+        }
+    }
+}
+
+fn parse_egress_rules(s: &str) -> Result<Vec<outbound::egress::Rule>, ParseError> {
+    let mut rules = Vec::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let invalid = || {
+            error!("Not a valid egress policy rule: {}", entry);
+            ParseError::InvalidEgressPolicy(entry.to_string())
+        };
+
+        let (action, dst) = entry.split_once('=').ok_or_else(invalid)?;
+        let action = parse_egress_action(action)?;
+        let (dst, ports) = parse_dst_ports(dst, invalid)?;
+        rules.push(outbound::egress::Rule { action, dst, ports });
+    }
+    Ok(rules)
+}
+
+/// Parses a port or an inclusive range of ports, e.g. `8000-8999`.
+fn parse_port_range(
+    s: &str,
+    invalid: impl Fn() -> ParseError,
+) -> Result<RangeInclusive<u16>, ParseError> {
+    let ports = match s.split_once('-') {
+        Some((lo, hi)) => parse_number::<u16>(lo)?..=parse_number::<u16>(hi)?,
+        None => {
+            let port = parse_number::<u16>(s)?;
+            port..=port
+        }
+    };
+    if ports.is_empty() {
+        return Err(invalid());
+    }
+    Ok(ports)
+}
+
+/// Parses a destination in the form `<network or DNS suffix>[@<ports>]`.
+fn parse_dst_ports(
+    s: &str,
+    invalid: impl Fn() -> ParseError,
+) -> Result<(AddrMatch, Option<RangeInclusive<u16>>), ParseError> {
+    let (dst, ports) = match s.split_once('@') {
+        Some((dst, ports)) => (dst.trim(), Some(parse_port_range(ports, invalid)?)),
+        None => (s.trim(), None),
+    };
+    let dst = match IpNet::from_str(dst) {
+        Ok(net) => AddrMatch::new(None, Some(net)),
+        Err(_) => AddrMatch::new(Some(parse_dns_suffix(dst)?), None),
+    };
+    Ok((dst, ports))
+}
+
+fn parse_protocol_overrides(
+    s: &str,
+) -> Result<outbound::http::detect::ProtocolOverrides, ParseError> {
+    use outbound::http::detect::{ForcedProtocol, ProtocolOverride};
+
+    let mut overrides = Vec::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let invalid = || {
+            error!("Not a valid outbound protocol override: {}", entry);
+            ParseError::InvalidProtocolOverride(entry.to_string())
+        };
+
+        let (protocol, dst) = entry.split_once('=').ok_or_else(invalid)?;
+        let protocol = match protocol.trim().to_ascii_lowercase().as_str() {
+            "opaque" => ForcedProtocol::Opaque,
+            "http1" => ForcedProtocol::Http1,
+            "h2" => ForcedProtocol::H2,
+            _ => return Err(invalid()),
+        };
+        let (dst, ports) = parse_dst_ports(dst, invalid)?;
+        overrides.push(ProtocolOverride {
+            protocol,
+            dst,
+            ports,
+        });
+    }
+    Ok(outbound::http::detect::ProtocolOverrides::new(overrides))
+}
+
+fn parse_connect_timeouts(s: &str) -> Result<outbound::tcp::ConnectTimeouts, ParseError> {
+    use outbound::tcp::{ConnectTimeoutMatch, ConnectTimeoutOverride};
+
+    let mut overrides = Vec::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let invalid = || {
+            error!("Not a valid connect timeout override: {}", entry);
+            ParseError::InvalidConnectTimeout(entry.to_string())
+        };
+
+        let (dst, timeout) = entry.rsplit_once('=').ok_or_else(invalid)?;
+        let timeout = parse_duration(timeout).map_err(|_| invalid())?;
+        let dst = dst.trim();
+        // IPv6 networks contain colons, so networks are parsed before labels.
+        let dst = match IpNet::from_str(dst) {
+            Ok(net) => ConnectTimeoutMatch::Network(net),
+            Err(_) => {
+                let (name, value) = dst.split_once(':').ok_or_else(invalid)?;
+                if name.trim().is_empty() {
+                    return Err(invalid());
+                }
+                ConnectTimeoutMatch::Label {
+                    name: name.trim().to_string(),
+                    value: value.trim().to_string(),
+                }
+            }
+        };
+        overrides.push(ConnectTimeoutOverride { dst, timeout });
+    }
+    Ok(outbound::tcp::ConnectTimeouts::new(overrides))
+}
+
+fn parse_bypass_rules(s: &str) -> Result<Vec<outbound::bypass::Rule>, ParseError> {
+    let mut rules = Vec::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let invalid = || {
+            error!("Not a valid outbound bypass rule: {}", entry);
+            ParseError::InvalidBypassRule(entry.to_string())
+        };
+
+        let (net, ports) = match entry.split_once('@') {
+            Some((net, ports)) => (net.trim(), Some(parse_port_range(ports, invalid)?)),
+            None => (entry, None),
+        };
+        let net = IpNet::from_str(net).map_err(|_| invalid())?;
+
+        rules.push(outbound::bypass::Rule {
+            nets: IpMatch::new(Some(net)),
+            ports,
+        });
+    }
+    Ok(rules)
+}
+
+fn parse_federated_trust_domains(
+    s: &str,
+) -> Result<Vec<identity::FederatedTrustDomain>, ParseError> {
+    let mut domains = Vec::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (domain, path) = match entry.split_once('=') {
+            Some((d, p)) if !p.trim().is_empty() => (d.trim(), p.trim()),
+            _ => {
+                error!("Not a valid federated trust domain: {}", entry);
+                return Err(ParseError::InvalidTrustDomain(entry.to_string()));
+            }
+        };
+        let domain = parse_dns_suffix(domain)?;
+        let pem = fs::read_to_string(path).map_err(|e| {
+            error!("Failed to read trust anchors for {}: {}", domain, e);
+            ParseError::InvalidTrustAnchors
+        })?;
+        let anchors = identity::FederatedTrustDomain::from_pem(domain, &pem).ok_or_else(|| {
+            error!("Invalid trust anchors in {}", path);
+            ParseError::InvalidTrustAnchors
+        })?;
+        domains.push(anchors);
+    }
+    Ok(domains)
+}
+
+pub(super) fn parse_identity(s: &str) -> Result<identity::Name, ParseError> {
+    identity::Name::from_str(s).map_err(|identity::InvalidName| {
+        error!("Not a valid identity name: {}", s);
+        ParseError::NameError
+    })
+}
+
+pub(super) fn parse<T, Parse>(
+    strings: &dyn Strings,
+    name: &str,
+    parse: Parse,
+) -> Result<Option<T>, EnvError>
+where
+    Parse: FnOnce(&str) -> Result<T, ParseError>,
+{
+    match strings.get(name)? {
+        Some(ref s) => {
+            let r = parse(s).map_err(|parse_error| {
+                error!("{}={:?} is not valid: {:?}", name, s, parse_error);
+                EnvError::InvalidEnvVar
+            })?;
+            Ok(Some(r))
+        }
+        None => Ok(None),
+    }
+}
+
+#[allow(dead_code)]
+fn parse_deprecated<T, Parse>(
+    strings: &dyn Strings,
+    name: &str,
+    deprecated_name: &str,
+    f: Parse,
+) -> Result<Option<T>, EnvError>
+where
+    Parse: Copy,
+    Parse: Fn(&str) -> Result<T, ParseError>,
+{
+    match parse(strings, name, f)? {
+        Some(v) => Ok(Some(v)),
+        None => {
+            let v = parse(strings, deprecated_name, f)?;
+            if v.is_some() {
+                warn!("{} has been deprecated; use {}", deprecated_name, name);
+            }
+            Ok(v)
+        }
+    }
+}
+
+fn parse_dns_suffixes(list: &str) -> Result<HashSet<dns::Suffix>, ParseError> {
+    let mut suffixes = HashSet::new();
+    for item in list.split(',') {
+        let item = item.trim();
+        if !item.is_empty() {
+            let sfx = parse_dns_suffix(item)?;
+            suffixes.insert(sfx);
+        }
+    }
+
+    Ok(suffixes)
+}
+
+fn parse_dns_suffix(s: &str) -> Result<dns::Suffix, ParseError> {
+    if s == "." {
+        return Ok(dns::Suffix::Root);
+    }
+
+    dns::Suffix::from_str(s).map_err(|_| ParseError::NotADomainSuffix)
+}
+
+fn parse_name_rules(list: &str) -> Result<HashSet<NameRule>, ParseError> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            NameRule::from_str(s).map_err(|error| {
+                error!(%error);
+                ParseError::NotADomainSuffix
+            })
+        })
+        .collect()
+}
+
+fn parse_dns_names(list: &str) -> Result<Vec<dns::Name>, ParseError> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            dns::Name::from_str(s).map_err(|_| {
+                error!("Not a valid domain: {}", s);
+                ParseError::NotADomainSuffix
+            })
+        })
+        .collect()
+}
+
+fn parse_nameserver(s: &str, default_port: u16) -> Result<SocketAddr, ParseError> {
+    if let Ok(ip) = std::net::IpAddr::from_str(s) {
+        return Ok(SocketAddr::new(ip, default_port));
+    }
+    SocketAddr::from_str(s).map_err(|_| {
+        error!("Not a valid nameserver: {}", s);
+        ParseError::InvalidNameserver(s.to_string())
+    })
+}
+
+fn parse_nameservers(list: &str, default_port: u16) -> Result<Vec<SocketAddr>, ParseError> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| parse_nameserver(s, default_port))
+        .collect()
+}
+
+fn parse_dns_protocol(s: &str) -> Result<Option<dns::EncryptedProtocol>, ParseError> {
+    match s.trim() {
+        "udp" => Ok(None),
+        "tls" => Ok(Some(dns::EncryptedProtocol::Tls)),
+        "https" => Ok(Some(dns::EncryptedProtocol::Https)),
+        s => {
+            error!("Not a valid DNS transport: {}", s);
+            Err(ParseError::InvalidDnsTransport(s.to_string()))
+        }
+    }
+}
+
+fn parse_dns_suffix_nameservers(s: &str) -> Result<Vec<(dns::Suffix, dns::Servers)>, ParseError> {
+    let mut suffixes = Vec::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (suffix, nameservers) = entry.split_once('=').ok_or_else(|| {
+            error!("Not a valid suffix nameserver: {}", entry);
+            ParseError::InvalidNameserver(entry.to_string())
+        })?;
+        let suffix = parse_dns_suffix(suffix.trim())?;
+        let nameservers = nameservers
+            .split_whitespace()
+            .map(|s| parse_nameserver(s, dns::Transport::Plain.default_port()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if nameservers.is_empty() {
+            return Err(ParseError::InvalidNameserver(entry.to_string()));
+        }
+        let servers = dns::Servers {
+            nameservers,
+            search: Vec::new(),
+            transport: dns::Transport::Plain,
+        };
+        suffixes.push((suffix, servers));
+    }
+    Ok(suffixes)
+}
+
+fn parse_networks(list: &str) -> Result<HashSet<IpNet>, ParseError> {
+    let mut nets = HashSet::new();
+    for input in list.split(',') {
+        let input = input.trim();
+        if !input.is_empty() {
+            let net = IpNet::from_str(input).map_err(|error| {
+                error!(%input, %error, "Invalid network");
+                ParseError::NotANetwork
+            })?;
+            nets.insert(net);
+        }
+    }
+    Ok(nets)
+}
+
+fn parse_default_policy(
+    s: &str,
+    detect_timeout: Duration,
+) -> Result<port_policies::DefaultPolicy, ParseError> {
+    match s {
+        "deny" => Ok(port_policies::DefaultPolicy::Deny),
+        "all-authenticated" => {
+            Ok(port_policies::all_authenticated_server_policy(detect_timeout).into())
+        }
+        "all-unauthenticated" => {
+            Ok(port_policies::all_unauthenticated_server_policy(detect_timeout).into())
+        }
+        "all-mtls-unauthenticated" => {
+            Ok(port_policies::all_mtls_unauthenticated_server_policy(detect_timeout).into())
+        }
+        name => Err(ParseError::InvalidPortPolicy(name.to_string())),
+    }
+}
+pub fn parse_backoff<S: Strings>(
+    strings: &S,
+    base: &str,
+    default: ExponentialBackoff,
 ) -> Result<ExponentialBackoff, EnvError> {
     let min_env = format!("LINKERD2_PROXY_{}_EXP_BACKOFF_MIN", base);
     let min = parse(strings, &min_env, parse_duration);
@@ -952,57 +3686,478 @@ pub fn parse_backoff<S: Strings>(
     let jitter_env = format!("LINKERD2_PROXY_{}_EXP_BACKOFF_JITTER", base);
     let jitter = parse(strings, &jitter_env, parse_number::<f64>);
 
-    match (min?, max?, jitter?) {
-        (None, None, None) => Ok(default),
-        (Some(min), Some(max), jitter) => {
-            ExponentialBackoff::new(min, max, jitter.unwrap_or_default()).map_err(|error| {
-                error!(message="Invalid backoff", %error, %min_env, ?min, %max_env, ?max, %jitter_env, ?jitter);
-                EnvError::InvalidEnvVar
-            })
-        }
-        _ => {
-            error!("You need to specify either all of {} {} {} or none of them to use the default backoff", min_env, max_env,jitter_env );
-            Err(EnvError::InvalidEnvVar)
-        }
+    match (min?, max?, jitter?) {
+        (None, None, None) => Ok(default),
+        (Some(min), Some(max), jitter) => {
+            ExponentialBackoff::new(min, max, jitter.unwrap_or_default()).map_err(|error| {
+                error!(message="Invalid backoff", %error, %min_env, ?min, %max_env, ?max, %jitter_env, ?jitter);
+                EnvError::InvalidEnvVar
+            })
+        }
+        _ => {
+            error!("You need to specify either all of {} {} {} or none of them to use the default backoff", min_env, max_env,jitter_env );
+            Err(EnvError::InvalidEnvVar)
+        }
+    }
+}
+
+pub fn parse_control_addr<S: Strings>(
+    strings: &S,
+    base: &str,
+) -> Result<Option<ControlAddr>, EnvError> {
+    let a_env = format!("{}_ADDR", base);
+    let a = parse(strings, &a_env, parse_addr);
+    let n_env = format!("{}_NAME", base);
+    let n = parse(strings, &n_env, parse_identity);
+    match (a?, n?) {
+        (None, None) => Ok(None),
+        (Some(ref addr), _) if addr.is_loopback() => Ok(Some(ControlAddr {
+            addr: addr.clone(),
+            identity: Conditional::None(tls::NoClientTls::Loopback),
+        })),
+        (Some(addr), Some(name)) => Ok(Some(ControlAddr {
+            addr,
+            identity: Conditional::Some(tls::ServerId(name).into()),
+        })),
+        (Some(_), None) => {
+            error!("{} must be specified when {} is set", n_env, a_env);
+            Err(EnvError::InvalidEnvVar)
+        }
+        (None, Some(_)) => {
+            error!("{} must be specified when {} is set", a_env, n_env);
+            Err(EnvError::InvalidEnvVar)
+        }
+    }
+}
+
+pub fn parse_control_addr_disable_identity<S: Strings>(
+    strings: &S,
+    base: &str,
+) -> Result<Option<ControlAddr>, EnvError> {
+    let a = parse(strings, &format!("{}_ADDR", base), parse_addr)?;
+    let identity = Conditional::None(tls::NoClientTls::Disabled);
+    Ok(a.map(|addr| ControlAddr { addr, identity }))
+}
+
+pub fn parse_jwt_config<S: Strings>(strings: &S) -> Result<Option<jwt::Config>, EnvError> {
+    let url = parse(strings, ENV_INBOUND_JWT_JWKS_URL, |s| {
+        let uri = http::uri::Uri::from_str(s).map_err(|_| ParseError::InvalidUri)?;
+        if uri.scheme() != Some(&http::uri::Scheme::HTTPS) {
+            error!("JWKS URL must use https: {}", s);
+            return Err(ParseError::InvalidUri);
+        }
+        Ok(uri)
+    });
+    let jwks = parse(strings, ENV_INBOUND_JWT_JWKS, |s| {
+        jwt::Jwks::from_json(s.as_bytes()).map_err(ParseError::InvalidJwks)
+    });
+    let refresh = parse(
+        strings,
+        ENV_INBOUND_JWT_JWKS_REFRESH_INTERVAL,
+        parse_duration,
+    );
+    let issuer = parse(strings, ENV_INBOUND_JWT_ISSUER, |s| Ok(s.to_string()));
+    let audiences = parse(strings, ENV_INBOUND_JWT_AUDIENCES, |s| {
+        Ok(s.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect::<Vec<_>>())
+    });
+    let leeway = parse(strings, ENV_INBOUND_JWT_LEEWAY, parse_duration);
+    let require_expiration = parse(strings, ENV_INBOUND_JWT_REQUIRE_EXPIRATION, parse_bool);
+    let required = parse(strings, ENV_INBOUND_JWT_REQUIRED, parse_bool);
+    let required_claims = parse(
+        strings,
+        ENV_INBOUND_JWT_REQUIRED_CLAIMS,
+        parse_jwt_required_claims,
+    );
+
+    let jwks = match (url?, jwks?) {
+        (Some(_), Some(_)) => {
+            error!(
+                "{} and {} must not both be set",
+                ENV_INBOUND_JWT_JWKS_URL, ENV_INBOUND_JWT_JWKS
+            );
+            return Err(EnvError::InvalidEnvVar);
+        }
+        (Some(uri), None) => jwt::JwksSource::Url {
+            uri,
+            refresh: refresh?.unwrap_or(DEFAULT_INBOUND_JWT_JWKS_REFRESH_INTERVAL),
+        },
+        (None, Some(jwks)) => jwt::JwksSource::Static(jwks),
+        (None, None) => {
+            if required?.unwrap_or(false) {
+                error!(
+                    "{} requires either {} or {} to be set",
+                    ENV_INBOUND_JWT_REQUIRED, ENV_INBOUND_JWT_JWKS_URL, ENV_INBOUND_JWT_JWKS
+                );
+                return Err(EnvError::InvalidEnvVar);
+            }
+            if required_claims?.map(|r| !r.is_empty()).unwrap_or(false) {
+                error!(
+                    "{} requires either {} or {} to be set",
+                    ENV_INBOUND_JWT_REQUIRED_CLAIMS, ENV_INBOUND_JWT_JWKS_URL, ENV_INBOUND_JWT_JWKS
+                );
+                return Err(EnvError::InvalidEnvVar);
+            }
+            return Ok(None);
+        }
+    };
+
+    Ok(Some(jwt::Config {
+        jwks,
+        issuer: issuer?,
+        audiences: audiences?.unwrap_or_default(),
+        leeway: leeway?.unwrap_or(DEFAULT_INBOUND_JWT_LEEWAY),
+        require_expiration: require_expiration?.unwrap_or(true),
+        required: required?.unwrap_or(false),
+        required_claims: required_claims?.unwrap_or_default(),
+    }))
+}
+
+fn parse_jwt_required_claims(s: &str) -> Result<Vec<jwt::RequiredClaims>, ParseError> {
+    let mut rules = Vec::new();
+    for entry in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let invalid = || {
+            error!(
+                "Expected [METHOD ]PATH=NAME:VALUE[ NAME:VALUE...]; found: {}",
+                entry
+            );
+            ParseError::InvalidRequiredClaims(entry.to_string())
+        };
+        let (route, claims) = entry.split_once('=').ok_or_else(invalid)?;
+        let (method, path) = match route.trim().split_once(' ') {
+            Some((method, path)) => (Some(method.to_string()), path.trim()),
+            None => (None, route.trim()),
+        };
+        if !path.starts_with('/') {
+            return Err(invalid());
+        }
+        let claims = claims
+            .split_whitespace()
+            .map(|claim| match claim.split_once(':') {
+                Some((name, value)) if !name.is_empty() && !value.is_empty() => {
+                    Ok((name.to_string(), value.to_string()))
+                }
+                _ => Err(invalid()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if claims.is_empty() {
+            return Err(invalid());
+        }
+        rules.push(jwt::RequiredClaims {
+            method,
+            path_prefix: path.to_string(),
+            claims,
+        });
+    }
+    Ok(rules)
+}
+
+#[cfg(feature = "wasm")]
+pub fn parse_http_wasm_config<S: Strings>(
+    strings: &S,
+    filters_env: &str,
+) -> Result<Option<http_wasm::Filters>, EnvError> {
+    let filters = parse(strings, filters_env, parse_http_wasm_filters);
+    let fuel = parse(strings, ENV_HTTP_WASM_FUEL, parse_number);
+    let timeout = parse(strings, ENV_HTTP_WASM_TIMEOUT, parse_duration);
+    let pool_size = parse(strings, ENV_HTTP_WASM_POOL_SIZE, parse_number);
+
+    let filters = match filters? {
+        Some(filters) if !filters.is_empty() => filters,
+        _ => return Ok(None),
+    };
+    let limits = http_wasm::Limits {
+        fuel: fuel?.unwrap_or(DEFAULT_HTTP_WASM_FUEL),
+        timeout: timeout?.unwrap_or(DEFAULT_HTTP_WASM_TIMEOUT),
+        pool_size: pool_size?.unwrap_or(DEFAULT_HTTP_WASM_POOL_SIZE),
+    };
+    match http_wasm::Filters::load(filters, limits) {
+        Ok(filters) => Ok(Some(filters)),
+        Err(error) => {
+            error!(%error, "{} is not valid", filters_env);
+            Err(EnvError::InvalidEnvVar)
+        }
+    }
+}
+
+pub fn parse_compression_config<S: Strings>(
+    strings: &S,
+) -> Result<Option<compress::Config>, EnvError> {
+    let enabled = parse(strings, ENV_INBOUND_COMPRESSION_ENABLED, parse_bool);
+    let min_size = parse(strings, ENV_INBOUND_COMPRESSION_MIN_SIZE, parse_number);
+    let content_types = parse(strings, ENV_INBOUND_COMPRESSION_CONTENT_TYPES, |s| {
+        Ok(s.split(',')
+            .map(|t| t.trim().to_ascii_lowercase())
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>())
+    });
+
+    if !enabled?.unwrap_or(false) {
+        return Ok(None);
+    }
+
+    let default = compress::Config::default();
+    Ok(Some(compress::Config {
+        min_size: min_size?.unwrap_or(default.min_size),
+        content_types: content_types?.unwrap_or(default.content_types),
+    }))
+}
+
+pub fn parse_forwarded_policy<S: Strings>(
+    strings: &S,
+) -> Result<Option<inbound::ForwardedPolicy>, EnvError> {
+    let enabled = parse(strings, ENV_INBOUND_FORWARDED_HEADERS_ENABLED, parse_bool);
+    let networks = parse(
+        strings,
+        ENV_INBOUND_FORWARDED_TRUSTED_NETWORKS,
+        parse_networks,
+    );
+    let identities = parse(
+        strings,
+        ENV_INBOUND_FORWARDED_TRUSTED_IDENTITIES,
+        parse_dns_suffixes,
+    );
+
+    if !enabled?.unwrap_or(false) {
+        return Ok(None);
     }
+
+    Ok(Some(inbound::ForwardedPolicy {
+        trusted_networks: IpMatch::new(networks?.unwrap_or_default()),
+        trusted_identities: NameMatch::new(identities?.unwrap_or_default()),
+    }))
 }
 
-pub fn parse_control_addr<S: Strings>(
+pub fn parse_load_shed_config<S: Strings>(
     strings: &S,
-    base: &str,
-) -> Result<Option<ControlAddr>, EnvError> {
-    let a_env = format!("{}_ADDR", base);
-    let a = parse(strings, &a_env, parse_addr);
-    let n_env = format!("{}_NAME", base);
-    let n = parse(strings, &n_env, parse_identity);
-    match (a?, n?) {
-        (None, None) => Ok(None),
-        (Some(ref addr), _) if addr.is_loopback() => Ok(Some(ControlAddr {
-            addr: addr.clone(),
-            identity: Conditional::None(tls::NoClientTls::Loopback),
-        })),
-        (Some(addr), Some(name)) => Ok(Some(ControlAddr {
-            addr,
-            identity: Conditional::Some(tls::ServerId(name).into()),
-        })),
+) -> Result<Option<load_shed::Config>, EnvError> {
+    let enabled = parse(strings, ENV_INBOUND_LOAD_SHED_ENABLED, parse_bool);
+    let min_limit = parse(strings, ENV_INBOUND_LOAD_SHED_MIN_LIMIT, parse_number);
+    let max_limit = parse(strings, ENV_INBOUND_LOAD_SHED_MAX_LIMIT, parse_number);
+    let retry_after = parse(strings, ENV_INBOUND_LOAD_SHED_RETRY_AFTER, parse_duration);
+
+    if !enabled?.unwrap_or(false) {
+        return Ok(None);
+    }
+
+    let default = load_shed::Config::default();
+    let min_limit = min_limit?.unwrap_or(default.min_limit);
+    let max_limit = max_limit?.unwrap_or(default.max_limit);
+    if min_limit > max_limit {
+        error!(
+            "{} must not exceed {}",
+            ENV_INBOUND_LOAD_SHED_MIN_LIMIT, ENV_INBOUND_LOAD_SHED_MAX_LIMIT
+        );
+        return Err(EnvError::InvalidEnvVar);
+    }
+
+    Ok(Some(load_shed::Config {
+        min_limit,
+        max_limit,
+        initial_limit: default.initial_limit.clamp(min_limit, max_limit),
+        retry_after: retry_after?.unwrap_or(default.retry_after),
+    }))
+}
+
+pub fn parse_egress_config<S: Strings>(
+    strings: &S,
+) -> Result<Option<outbound::egress::Config>, EnvError> {
+    let mode = parse(strings, ENV_OUTBOUND_EGRESS_POLICY_MODE, parse_egress_mode);
+    let default = parse(
+        strings,
+        ENV_OUTBOUND_EGRESS_POLICY_DEFAULT,
+        parse_egress_action,
+    );
+    let rules = parse(
+        strings,
+        ENV_OUTBOUND_EGRESS_POLICY_RULES,
+        parse_egress_rules,
+    );
+
+    let mode = match mode? {
+        Some(mode) => mode,
+        None => return Ok(None),
+    };
+
+    Ok(Some(outbound::egress::Config {
+        mode,
+        default: default?.unwrap_or(outbound::egress::Action::Allow),
+        rules: rules?.unwrap_or_default(),
+    }))
+}
+
+fn parse_dns_servers(strings: &dyn Strings) -> Result<dns::Servers, EnvError> {
+    let protocol = parse(strings, ENV_DNS_TRANSPORT, parse_dns_protocol)?.flatten();
+    let tls_name = parse(strings, ENV_DNS_TLS_NAME, |s| {
+        dns::Name::from_str(s.trim()).map_err(|_| ParseError::NameError)
+    })?;
+    let plain_fallback = parse(strings, ENV_DNS_PLAIN_FALLBACK, parse_bool)?.unwrap_or(false);
+
+    let transport = match (protocol, tls_name) {
+        (None, _) => dns::Transport::Plain,
+        (Some(protocol), Some(tls_name)) => dns::Transport::Encrypted {
+            protocol,
+            tls_name,
+            plain_fallback_port: if plain_fallback {
+                Some(dns::Transport::Plain.default_port())
+            } else {
+                None
+            },
+        },
         (Some(_), None) => {
-            error!("{} must be specified when {} is set", n_env, a_env);
-            Err(EnvError::InvalidEnvVar)
+            error!("{} must be set for encrypted DNS", ENV_DNS_TLS_NAME);
+            return Err(EnvError::InvalidEnvVar);
         }
-        (None, Some(_)) => {
-            error!("{} must be specified when {} is set", a_env, n_env);
-            Err(EnvError::InvalidEnvVar)
+    };
+
+    let default_port = transport.default_port();
+    let nameservers = parse(strings, ENV_DNS_NAMESERVERS, |s| {
+        parse_nameservers(s, default_port)
+    })?
+    .unwrap_or_default();
+    if transport != dns::Transport::Plain && nameservers.is_empty() {
+        error!("{} must be set for encrypted DNS", ENV_DNS_NAMESERVERS);
+        return Err(EnvError::InvalidEnvVar);
+    }
+
+    let search = parse(strings, ENV_DNS_SEARCH, parse_dns_names)?.unwrap_or_default();
+
+    Ok(dns::Servers {
+        nameservers,
+        search,
+        transport,
+    })
+}
+
+fn parse_tcp_rate_limits<S: Strings>(
+    strings: &S,
+    connection_env: &str,
+    port_env: &str,
+) -> Result<tcp::RateLimits, EnvError> {
+    let per_connection = parse(strings, connection_env, parse_port_rates);
+    let per_port = parse(strings, port_env, parse_port_rates);
+    Ok(tcp::RateLimits::new(
+        per_connection?.unwrap_or_default(),
+        per_port?.unwrap_or_default(),
+    ))
+}
+
+fn parse_tls_handshake_limits<S: Strings>(
+    strings: &S,
+) -> Result<inbound::HandshakeLimits, EnvError> {
+    let per_second = parse(
+        strings,
+        ENV_INBOUND_TLS_HANDSHAKE_RATE_LIMIT,
+        parse_number::<u32>,
+    );
+    let ipv4_prefix_len = parse(
+        strings,
+        ENV_INBOUND_TLS_HANDSHAKE_RATE_LIMIT_IPV4_PREFIX_LEN,
+        parse_number::<u8>,
+    );
+    let ipv6_prefix_len = parse(
+        strings,
+        ENV_INBOUND_TLS_HANDSHAKE_RATE_LIMIT_IPV6_PREFIX_LEN,
+        parse_number::<u8>,
+    );
+
+    let per_second = match per_second? {
+        Some(0) => {
+            error!("{} must be positive", ENV_INBOUND_TLS_HANDSHAKE_RATE_LIMIT);
+            return Err(EnvError::InvalidEnvVar);
         }
+        Some(per_second) => per_second,
+        None => return Ok(inbound::HandshakeLimits::default()),
+    };
+    let ipv4_prefix_len = ipv4_prefix_len?.unwrap_or(24);
+    if ipv4_prefix_len > 32 {
+        error!(
+            "{} must not exceed 32",
+            ENV_INBOUND_TLS_HANDSHAKE_RATE_LIMIT_IPV4_PREFIX_LEN
+        );
+        return Err(EnvError::InvalidEnvVar);
+    }
+    let ipv6_prefix_len = ipv6_prefix_len?.unwrap_or(64);
+    if ipv6_prefix_len > 128 {
+        error!(
+            "{} must not exceed 128",
+            ENV_INBOUND_TLS_HANDSHAKE_RATE_LIMIT_IPV6_PREFIX_LEN
+        );
+        return Err(EnvError::InvalidEnvVar);
     }
+
+    Ok(inbound::HandshakeLimits::new(
+        inbound::HandshakeLimitConfig {
+            per_second,
+            ipv4_prefix_len,
+            ipv6_prefix_len,
+        },
+    ))
 }
 
-pub fn parse_control_addr_disable_identity<S: Strings>(
+fn parse_protocol_hints<S: Strings>(
     strings: &S,
-    base: &str,
-) -> Result<Option<ControlAddr>, EnvError> {
-    let a = parse(strings, &format!("{}_ADDR", base), parse_addr)?;
-    let identity = Conditional::None(tls::NoClientTls::Disabled);
-    Ok(a.map(|addr| ControlAddr { addr, identity }))
+) -> Result<protocol_hints::ProtocolHints, EnvError> {
+    let ttl = parse(strings, ENV_OUTBOUND_PROTOCOL_HINT_TTL, parse_duration);
+    let threshold = parse(
+        strings,
+        ENV_OUTBOUND_PROTOCOL_HINT_THRESHOLD,
+        parse_number::<usize>,
+    );
+
+    let ttl = match ttl? {
+        Some(ttl) => ttl,
+        None => return Ok(protocol_hints::ProtocolHints::default()),
+    };
+    let threshold = match threshold? {
+        Some(0) => {
+            error!("{} must be positive", ENV_OUTBOUND_PROTOCOL_HINT_THRESHOLD);
+            return Err(EnvError::InvalidEnvVar);
+        }
+        Some(threshold) => threshold,
+        None => 3,
+    };
+
+    Ok(protocol_hints::ProtocolHints::new(protocol_hints::Config {
+        ttl,
+        threshold,
+    }))
+}
+
+fn parse_fd_pressure<S: Strings>(strings: &S) -> Result<fd_pressure::FdPressure, EnvError> {
+    let high_water = parse(strings, ENV_FD_SHED_HIGH_WATER_PERCENT, parse_number::<u8>);
+    let low_water = parse(strings, ENV_FD_SHED_LOW_WATER_PERCENT, parse_number::<u8>);
+
+    let high_water_percent = match high_water? {
+        Some(high) if high == 0 || high > 100 => {
+            error!(
+                "{} must be between 1 and 100",
+                ENV_FD_SHED_HIGH_WATER_PERCENT
+            );
+            return Err(EnvError::InvalidEnvVar);
+        }
+        Some(high) => high,
+        None => return Ok(fd_pressure::FdPressure::default()),
+    };
+    let low_water_percent = match low_water? {
+        Some(low) if low >= high_water_percent => {
+            error!(
+                "{} must be less than {}",
+                ENV_FD_SHED_LOW_WATER_PERCENT, ENV_FD_SHED_HIGH_WATER_PERCENT
+            );
+            return Err(EnvError::InvalidEnvVar);
+        }
+        Some(low) => low,
+        None => high_water_percent.saturating_sub(10),
+    };
+
+    Ok(fd_pressure::FdPressure::new(fd_pressure::Config {
+        high_water_percent,
+        low_water_percent,
+    }))
 }
 
 pub fn parse_identity_config<S: Strings>(
@@ -1012,6 +4167,53 @@ pub fn parse_identity_config<S: Strings>(
     let ta = parse(strings, ENV_IDENTITY_TRUST_ANCHORS, |s| {
         identity::TrustAnchors::from_pem(s).ok_or(ParseError::InvalidTrustAnchors)
     });
+    let ta_path = parse(strings, ENV_IDENTITY_TRUST_ANCHORS_PATH, |s| {
+        Ok(PathBuf::from(s))
+    });
+    let ta_reload_interval = parse(
+        strings,
+        ENV_IDENTITY_TRUST_ANCHORS_RELOAD_INTERVAL,
+        parse_reload_interval,
+    );
+    let max_intermediates = parse(strings, ENV_IDENTITY_MAX_INTERMEDIATES, parse_number);
+    // The interval is validated even when no path is set so that a typo is
+    // not silently ignored.
+    let ta_reload_interval = ta_reload_interval?;
+    let (ta, ta_reload) = match (ta?, ta_path?) {
+        (Some(_), Some(_)) => {
+            error!(
+                "{} and {} must not both be set",
+                ENV_IDENTITY_TRUST_ANCHORS, ENV_IDENTITY_TRUST_ANCHORS_PATH
+            );
+            return Err(EnvError::InvalidEnvVar);
+        }
+        (Some(ta), None) => (Some(ta), None),
+        (None, Some(path)) => {
+            let loaded = fs::read(&path).map_err(|e| {
+                error!("Failed to read trust anchors: {}", e);
+                EnvError::InvalidEnvVar
+            })?;
+            let ta = std::str::from_utf8(&loaded)
+                .ok()
+                .and_then(identity::TrustAnchors::from_pem)
+                .ok_or_else(|| {
+                    error!("Invalid trust anchors in {}", path.display());
+                    EnvError::InvalidEnvVar
+                })?;
+            let reload = identity::trust_anchors::Reload {
+                path,
+                interval: ta_reload_interval
+                    .unwrap_or(DEFAULT_IDENTITY_TRUST_ANCHORS_RELOAD_INTERVAL),
+                loaded,
+            };
+            (Some(ta), Some(reload))
+        }
+        (None, None) => (None, None),
+    };
+    let ta = match max_intermediates? {
+        Some(max) => ta.map(|ta| ta.with_max_intermediates(max)),
+        None => ta,
+    };
     let dir = parse(strings, ENV_IDENTITY_DIR, |ref s| Ok(PathBuf::from(s)));
     let tok = parse(strings, ENV_IDENTITY_TOKEN_FILE, |ref s| {
         identity::TokenSource::if_nonempty_file(s.to_string()).map_err(|e| {
@@ -1031,7 +4233,7 @@ pub fn parse_identity_config<S: Strings>(
     match (
         disabled,
         control?,
-        ta?,
+        ta,
         dir?,
         li?,
         tok?,
@@ -1105,6 +4307,7 @@ pub fn parse_identity_config<S: Strings>(
                     key: key?,
                     min_refresh: min_refresh.unwrap_or(DEFAULT_IDENTITY_MIN_REFRESH),
                     max_refresh: max_refresh.unwrap_or(DEFAULT_IDENTITY_MAX_REFRESH),
+                    trust_anchors_reload: ta_reload,
                 },
             )))
         }
@@ -1139,6 +4342,7 @@ pub fn parse_identity_config<S: Strings>(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use linkerd_app_core::NameAddr;
 
     fn test_unit<F: Fn(u64) -> Duration>(unit: &str, to_duration: F) {
         for v in &[0, 1, 23, 456_789] {
@@ -1162,97 +4366,428 @@ mod tests {
     }
 
     #[test]
-    fn parse_duration_unit_m() {
-        test_unit("m", |v| Duration::from_secs(v * 60));
-    }
+    fn parse_duration_unit_m() {
+        test_unit("m", |v| Duration::from_secs(v * 60));
+    }
+
+    #[test]
+    fn parse_duration_unit_h() {
+        test_unit("h", |v| Duration::from_secs(v * 60 * 60));
+    }
+
+    #[test]
+    fn parse_duration_unit_d() {
+        test_unit("d", |v| Duration::from_secs(v * 60 * 60 * 24));
+    }
+
+    #[test]
+    fn parse_duration_floats_invalid() {
+        assert_eq!(parse_duration(".123h"), Err(ParseError::NotADuration));
+        assert_eq!(parse_duration("1.23h"), Err(ParseError::NotADuration));
+    }
+
+    #[test]
+    fn parse_duration_space_before_unit_invalid() {
+        assert_eq!(parse_duration("1 ms"), Err(ParseError::NotADuration));
+    }
+
+    #[test]
+    fn parse_duration_overflows_invalid() {
+        assert!(matches!(
+            parse_duration("123456789012345678901234567890ms"),
+            Err(ParseError::NotAnInteger(_))
+        ));
+    }
+
+    #[test]
+    fn parse_duration_invalid_unit() {
+        assert_eq!(parse_duration("12moons"), Err(ParseError::NotADuration));
+        assert_eq!(parse_duration("12y"), Err(ParseError::NotADuration));
+    }
+
+    #[test]
+    fn parse_duration_zero_without_unit() {
+        assert_eq!(parse_duration("0"), Ok(Duration::from_secs(0)));
+    }
+
+    #[test]
+    fn parse_duration_number_without_unit_is_invalid() {
+        assert_eq!(parse_duration("1"), Err(ParseError::NotADuration));
+    }
+
+    #[test]
+    fn convert_attributes_string_to_map_different_values() {
+        let attributes_string = "\
+            cluster=\"test-cluster1\"\n\
+            rack=\"rack-22\"\n\
+            zone=us-est-coast\n\
+            linkerd.io/control-plane-component=\"controller\"\n\
+            linkerd.io/proxy-deployment=\"linkerd-controller\"\n\
+            workload=\n\
+            kind=\"\"\n\
+            key1=\"=\"\n\
+            key2==value2\n\
+            key3\n\
+            =key4\n\
+            "
+        .to_string();
+
+        let expected = [
+            ("cluster".to_string(), "test-cluster1".to_string()),
+            ("rack".to_string(), "rack-22".to_string()),
+            ("zone".to_string(), "us-est-coast".to_string()),
+            (
+                "linkerd.io/control-plane-component".to_string(),
+                "controller".to_string(),
+            ),
+            (
+                "linkerd.io/proxy-deployment".to_string(),
+                "linkerd-controller".to_string(),
+            ),
+            ("workload".to_string(), "".to_string()),
+            ("kind".to_string(), "".to_string()),
+            ("key1".to_string(), "=".to_string()),
+            ("key2".to_string(), "=value2".to_string()),
+            ("".to_string(), "key4".to_string()),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+
+        assert_eq!(
+            convert_attributes_string_to_map(attributes_string),
+            expected
+        );
+    }
+
+    #[test]
+    fn gateway_clusters() {
+        assert!(parse_gateway_clusters("").unwrap().is_empty());
+        assert!(
+            !parse_gateway_clusters("cluster-b=cluster-b.local, cluster-b=example.com")
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            parse_gateway_clusters("cluster-b").err(),
+            Some(ParseError::InvalidGatewayCluster("cluster-b".to_string()))
+        );
+        assert_eq!(
+            parse_gateway_clusters("a.b=cluster-b.local").err(),
+            Some(ParseError::InvalidGatewayCluster(
+                "a.b=cluster-b.local".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_gateway_clusters("cluster-b=a .b").err(),
+            Some(ParseError::NotADomainSuffix)
+        );
+    }
+
+    #[test]
+    fn gateway_cluster_gateways() {
+        assert!(parse_gateway_cluster_gateways("").unwrap().is_empty());
+        let gateways = parse_gateway_cluster_gateways(
+            "Cluster-B=gateway.cluster-b.local@192.0.2.10:4143, cluster-c=gateway.cluster-c.local@192.0.2.20:4143",
+        )
+        .unwrap();
+        assert_eq!(
+            gateways.get("cluster-b"),
+            Some(&inbound::direct::RemoteGateway {
+                identity: parse_identity("gateway.cluster-b.local").unwrap(),
+                addr: ([192, 0, 2, 10], 4143).into(),
+            })
+        );
+        assert_eq!(
+            gateways.get("cluster-c").map(|gw| gw.addr),
+            Some(([192, 0, 2, 20], 4143).into())
+        );
+        assert_eq!(
+            parse_gateway_cluster_gateways("cluster-b=192.0.2.10:4143").err(),
+            Some(ParseError::InvalidGatewayCluster(
+                "cluster-b=192.0.2.10:4143".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_gateway_cluster_gateways(
+                "cluster-b=gw.b.local@192.0.2.10:4143,cluster-b=gw.b.local@192.0.2.11:4143"
+            )
+            .err(),
+            Some(ParseError::InvalidGatewayCluster(
+                "cluster-b=gw.b.local@192.0.2.11:4143".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_gateway_cluster_gateways("cluster-b=gw.b.local@gw.b.local:4143").err(),
+            Some(ParseError::HostIsNotAnIpAddress)
+        );
+    }
+
+    #[test]
+    fn egress_rules() {
+        assert!(parse_egress_rules("").unwrap().is_empty());
+
+        let rules =
+            parse_egress_rules("allow=api.example.com.@443, deny=10.0.0.0/8@8000-8999, deny=.")
+                .unwrap();
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules[0].action, outbound::egress::Action::Allow);
+        assert_eq!(rules[0].ports, Some(443..=443));
+        assert_eq!(rules[1].action, outbound::egress::Action::Deny);
+        assert_eq!(rules[1].ports, Some(8000..=8999));
+        assert_eq!(rules[2].ports, None);
+
+        assert_eq!(
+            parse_egress_rules("10.0.0.0/8").err(),
+            Some(ParseError::InvalidEgressPolicy("10.0.0.0/8".to_string()))
+        );
+        assert_eq!(
+            parse_egress_rules("drop=10.0.0.0/8").err(),
+            Some(ParseError::InvalidEgressPolicy("drop".to_string()))
+        );
+        assert_eq!(
+            parse_egress_rules("deny=10.0.0.0/8@90-80").err(),
+            Some(ParseError::InvalidEgressPolicy(
+                "deny=10.0.0.0/8@90-80".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn protocol_overrides() {
+        use outbound::http::detect::{ForcedProtocol, OverrideTarget};
+
+        let overrides =
+            parse_protocol_overrides("opaque=10.0.0.0/8@4222, http1=legacy.example.com.").unwrap();
+        let target = |name: Option<&str>, addr: Option<&str>| OverrideTarget {
+            name: name.map(|n| NameAddr::from_str(n).unwrap()),
+            addr: addr.map(|a| a.parse().unwrap()),
+        };
+        assert_eq!(
+            overrides.get(&target(None, Some("10.1.2.3:4222"))),
+            Some(ForcedProtocol::Opaque)
+        );
+        assert_eq!(
+            overrides.get(&target(Some("legacy.example.com:80"), None)),
+            Some(ForcedProtocol::Http1)
+        );
 
-    #[test]
-    fn parse_duration_unit_h() {
-        test_unit("h", |v| Duration::from_secs(v * 60 * 60));
+        assert_eq!(
+            parse_protocol_overrides("h3=10.0.0.0/8").err(),
+            Some(ParseError::InvalidProtocolOverride(
+                "h3=10.0.0.0/8".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_protocol_overrides("opaque=10.0.0.0/8@90-80").err(),
+            Some(ParseError::InvalidProtocolOverride(
+                "opaque=10.0.0.0/8@90-80".to_string()
+            ))
+        );
     }
 
     #[test]
-    fn parse_duration_unit_d() {
-        test_unit("d", |v| Duration::from_secs(v * 60 * 60 * 24));
+    fn connect_timeouts() {
+        use crate::core::proxy::api_resolve::Labels;
+
+        let timeouts = parse_connect_timeouts(
+            "10.0.0.0/8=100ms, fd00::/8=200ms, mirror.linkerd.io/cluster-name:west=5s",
+        )
+        .unwrap();
+        let west = Some((
+            "mirror.linkerd.io/cluster-name".to_string(),
+            "west".to_string(),
+        ))
+        .into_iter()
+        .collect::<Labels>();
+        assert_eq!(
+            timeouts.get([10, 1, 2, 3].into(), &Labels::new()),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            timeouts.get("fd00::1".parse().unwrap(), &Labels::new()),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(
+            timeouts.get([192, 0, 2, 3].into(), &west),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(timeouts.get([192, 0, 2, 3].into(), &Labels::new()), None);
+
+        for invalid in &["10.0.0.0/8", "10.0.0.0/8=fast", "west=5s", ":west=5s"] {
+            assert_eq!(
+                parse_connect_timeouts(invalid).err(),
+                Some(ParseError::InvalidConnectTimeout(invalid.to_string()))
+            );
+        }
     }
 
     #[test]
-    fn parse_duration_floats_invalid() {
-        assert_eq!(parse_duration(".123h"), Err(ParseError::NotADuration));
-        assert_eq!(parse_duration("1.23h"), Err(ParseError::NotADuration));
+    fn bypass_rules() {
+        assert!(parse_bypass_rules("").unwrap().is_empty());
+
+        let rules =
+            parse_bypass_rules("127.0.0.1/32@6379, 10.1.0.0/16@8000-8999, ::1/128").unwrap();
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules[0].ports, Some(6379..=6379));
+        assert_eq!(rules[1].ports, Some(8000..=8999));
+        assert_eq!(rules[2].ports, None);
+
+        assert_eq!(
+            parse_bypass_rules("localhost").err(),
+            Some(ParseError::InvalidBypassRule("localhost".to_string()))
+        );
+        assert_eq!(
+            parse_bypass_rules("10.0.0.0/8@90-80").err(),
+            Some(ParseError::InvalidBypassRule(
+                "10.0.0.0/8@90-80".to_string()
+            ))
+        );
     }
 
     #[test]
-    fn parse_duration_space_before_unit_invalid() {
-        assert_eq!(parse_duration("1 ms"), Err(ParseError::NotADuration));
+    fn client_priorities() {
+        assert!(parse_client_priorities("").unwrap().is_empty());
+
+        let priorities = parse_client_priorities("10.1.0.0/16=high, 10.0.0.0/8=low").unwrap();
+        assert_eq!(
+            priorities,
+            vec![
+                (
+                    "10.1.0.0/16".parse().unwrap(),
+                    port_policies::Priority::High
+                ),
+                ("10.0.0.0/8".parse().unwrap(), port_policies::Priority::Low),
+            ]
+        );
+
+        assert_eq!(
+            parse_client_priorities("10.0.0.0/8").err(),
+            Some(ParseError::InvalidPriority("10.0.0.0/8".to_string()))
+        );
+        assert_eq!(
+            parse_client_priorities("10.0.0.0/8=urgent").err(),
+            Some(ParseError::InvalidPriority("10.0.0.0/8=urgent".to_string()))
+        );
+        assert_eq!(
+            parse_client_priorities("example.com=low").err(),
+            Some(ParseError::InvalidPriority("example.com=low".to_string()))
+        );
     }
 
     #[test]
-    fn parse_duration_overflows_invalid() {
+    fn port_rates() {
+        assert!(parse_port_rates("").unwrap().is_empty());
+        assert_eq!(
+            parse_port_rates("5432=1048576, 9000 = 65536").unwrap(),
+            vec![(5432, 1048576), (9000, 65536)]
+        );
+        assert_eq!(
+            parse_port_rates("5432").err(),
+            Some(ParseError::InvalidRateLimit("5432".to_string()))
+        );
+        assert_eq!(
+            parse_port_rates("5432=0").err(),
+            Some(ParseError::InvalidRateLimit("5432=0".to_string()))
+        );
         assert!(matches!(
-            parse_duration("123456789012345678901234567890ms"),
+            parse_port_rates("5432=fast"),
             Err(ParseError::NotAnInteger(_))
         ));
     }
 
     #[test]
-    fn parse_duration_invalid_unit() {
-        assert_eq!(parse_duration("12moons"), Err(ParseError::NotADuration));
-        assert_eq!(parse_duration("12y"), Err(ParseError::NotADuration));
+    fn port_connection_limits() {
+        assert!(parse_port_connection_limits("").unwrap().is_empty());
+        assert_eq!(
+            parse_port_connection_limits("8080=100, 9000 = 5").unwrap(),
+            vec![(8080, 100), (9000, 5)]
+        );
+        assert_eq!(
+            parse_port_connection_limits("8080").err(),
+            Some(ParseError::InvalidConnectionLimit("8080".to_string()))
+        );
+        assert_eq!(
+            parse_port_connection_limits("8080=0").err(),
+            Some(ParseError::InvalidConnectionLimit("8080=0".to_string()))
+        );
+        assert!(matches!(
+            parse_port_connection_limits("8080=many"),
+            Err(ParseError::NotAnInteger(_))
+        ));
     }
 
     #[test]
-    fn parse_duration_zero_without_unit() {
-        assert_eq!(parse_duration("0"), Ok(Duration::from_secs(0)));
+    fn federated_trust_domains() {
+        assert!(parse_federated_trust_domains("").unwrap().is_empty());
+        assert_eq!(
+            parse_federated_trust_domains("cluster-b.example").err(),
+            Some(ParseError::InvalidTrustDomain(
+                "cluster-b.example".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_federated_trust_domains("cluster-b.example=/nonexistent/ca.pem").err(),
+            Some(ParseError::InvalidTrustAnchors)
+        );
     }
 
     #[test]
-    fn parse_duration_number_without_unit_is_invalid() {
-        assert_eq!(parse_duration("1"), Err(ParseError::NotADuration));
+    fn failover_cluster() {
+        assert_eq!(parse_failover_cluster(" East-1 "), Ok("east-1".to_string()));
+        assert_eq!(
+            parse_failover_cluster("east.1"),
+            Err(ParseError::InvalidFailoverCluster("east.1".to_string()))
+        );
+        assert_eq!(
+            parse_failover_cluster(""),
+            Err(ParseError::InvalidFailoverCluster("".to_string()))
+        );
     }
 
     #[test]
-    fn convert_attributes_string_to_map_different_values() {
-        let attributes_string = "\
-            cluster=\"test-cluster1\"\n\
-            rack=\"rack-22\"\n\
-            zone=us-est-coast\n\
-            linkerd.io/control-plane-component=\"controller\"\n\
-            linkerd.io/proxy-deployment=\"linkerd-controller\"\n\
-            workload=\n\
-            kind=\"\"\n\
-            key1=\"=\"\n\
-            key2==value2\n\
-            key3\n\
-            =key4\n\
-            "
-        .to_string();
+    fn dns_nameservers() {
+        assert_eq!(
+            parse_nameservers("10.96.0.10, 10.96.0.11:5353, [fd00::10]:53, fd00::11", 53).unwrap(),
+            vec![
+                SocketAddr::from(([10, 96, 0, 10], 53)),
+                SocketAddr::from(([10, 96, 0, 11], 5353)),
+                "[fd00::10]:53".parse().unwrap(),
+                "[fd00::11]:53".parse().unwrap(),
+            ]
+        );
+        assert_eq!(
+            parse_nameservers("dns.example.com", 53).err(),
+            Some(ParseError::InvalidNameserver("dns.example.com".to_string()))
+        );
 
-        let expected = [
-            ("cluster".to_string(), "test-cluster1".to_string()),
-            ("rack".to_string(), "rack-22".to_string()),
-            ("zone".to_string(), "us-est-coast".to_string()),
-            (
-                "linkerd.io/control-plane-component".to_string(),
-                "controller".to_string(),
-            ),
-            (
-                "linkerd.io/proxy-deployment".to_string(),
-                "linkerd-controller".to_string(),
-            ),
-            ("workload".to_string(), "".to_string()),
-            ("kind".to_string(), "".to_string()),
-            ("key1".to_string(), "=".to_string()),
-            ("key2".to_string(), "=value2".to_string()),
-            ("".to_string(), "key4".to_string()),
-        ]
-        .iter()
-        .cloned()
-        .collect();
+        let suffixes =
+            parse_dns_suffix_nameservers("liqo.=10.96.0.20 10.96.0.21:5353, .=10.96.0.10").unwrap();
+        assert_eq!(suffixes.len(), 2);
+        assert_eq!(suffixes[0].0, dns::Suffix::from_str("liqo.").unwrap());
+        assert_eq!(
+            suffixes[0].1.nameservers,
+            vec![
+                SocketAddr::from(([10, 96, 0, 20], 53)),
+                SocketAddr::from(([10, 96, 0, 21], 5353)),
+            ]
+        );
+        assert_eq!(suffixes[1].0, dns::Suffix::Root);
+        assert!(parse_dns_suffix_nameservers("liqo.=").is_err());
+        assert!(parse_dns_suffix_nameservers("liqo.").is_err());
 
+        assert_eq!(parse_dns_protocol("udp"), Ok(None));
         assert_eq!(
-            convert_attributes_string_to_map(attributes_string),
-            expected
+            parse_dns_protocol("tls"),
+            Ok(Some(dns::EncryptedProtocol::Tls))
+        );
+        assert_eq!(
+            parse_dns_protocol("https"),
+            Ok(Some(dns::EncryptedProtocol::Https))
+        );
+        assert_eq!(
+            parse_dns_protocol("quic"),
+            Err(ParseError::InvalidDnsTransport("quic".to_string()))
         );
     }
 
@@ -1296,4 +4831,402 @@ mod tests {
             "names are coerced to lowercase"
         );
     }
+
+    #[test]
+    fn name_rules() {
+        fn p(s: &str) -> Result<Vec<String>, ParseError> {
+            let mut rules = parse_name_rules(s)?
+                .into_iter()
+                .map(|r| r.to_string())
+                .collect::<Vec<_>>();
+            rules.sort();
+            Ok(rules)
+        }
+
+        assert_eq!(p(""), Ok(vec![]), "empty string");
+        assert_eq!(
+            p("svc.cluster.local., *.internal.example.com"),
+            Ok(vec![
+                "*.internal.example.com".to_owned(),
+                "svc.cluster.local.".to_owned()
+            ]),
+            "suffixes and wildcards"
+        );
+        assert_eq!(
+            p("example.com@8080,*.example.org@443"),
+            Ok(vec![
+                "*.example.org@443".to_owned(),
+                "example.com@8080".to_owned()
+            ]),
+            "port-qualified rules"
+        );
+        assert_eq!(
+            p("example.com@http"),
+            Err(ParseError::NotADomainSuffix),
+            "ports must be numeric"
+        );
+        assert_eq!(
+            p("foo*.example.com"),
+            Err(ParseError::NotADomainSuffix),
+            "wildcards must be whole labels"
+        );
+    }
+
+    #[test]
+    fn port_forward_addrs() {
+        let addrs = parse_port_forward_addrs("8080=127.0.0.1:8080, 9090=[::1]:9091").unwrap();
+        assert_eq!(addrs.len(), 2);
+        assert_eq!(addrs[&8080], SocketAddr::from(([127, 0, 0, 1], 8080)));
+        assert_eq!(addrs[&9090], "[::1]:9091".parse().unwrap());
+
+        assert!(parse_port_forward_addrs("").unwrap().is_empty());
+        assert!(parse_port_forward_addrs("8080").is_err());
+        assert!(parse_port_forward_addrs("8080=localhost:8080").is_err());
+        assert!(parse_port_forward_addrs("http=127.0.0.1:8080").is_err());
+    }
+
+    #[test]
+    fn port_exempt_routes() {
+        let routes =
+            parse_port_exempt_routes("8080=GET /healthz, 8080=/ready, 9090=GET /").unwrap();
+        assert_eq!(routes.len(), 2);
+        assert_eq!(
+            routes[&8080],
+            vec![
+                inbound::ExemptRoute {
+                    method: Some("GET".to_string()),
+                    path: "/healthz".to_string(),
+                },
+                inbound::ExemptRoute {
+                    method: None,
+                    path: "/ready".to_string(),
+                },
+            ]
+        );
+        assert_eq!(routes[&9090].len(), 1);
+
+        assert!(parse_port_exempt_routes("").unwrap().is_empty());
+        assert!(parse_port_exempt_routes("8080").is_err());
+        assert!(parse_port_exempt_routes("8080=GET healthz").is_err());
+        assert!(parse_port_exempt_routes("http=/healthz").is_err());
+    }
+
+    #[test]
+    fn jwt_required_claims() {
+        let rules = parse_jwt_required_claims(
+            "POST /admin=groups:admin iss:https://issuer.example.com, /reports=scope:read",
+        )
+        .unwrap();
+        assert_eq!(
+            rules,
+            vec![
+                jwt::RequiredClaims {
+                    method: Some("POST".to_string()),
+                    path_prefix: "/admin".to_string(),
+                    claims: vec![
+                        ("groups".to_string(), "admin".to_string()),
+                        ("iss".to_string(), "https://issuer.example.com".to_string()),
+                    ],
+                },
+                jwt::RequiredClaims {
+                    method: None,
+                    path_prefix: "/reports".to_string(),
+                    claims: vec![("scope".to_string(), "read".to_string())],
+                },
+            ]
+        );
+
+        assert!(parse_jwt_required_claims("").unwrap().is_empty());
+        assert!(parse_jwt_required_claims("/admin").is_err());
+        assert!(parse_jwt_required_claims("/admin=").is_err());
+        assert!(parse_jwt_required_claims("/admin=groups").is_err());
+        assert!(parse_jwt_required_claims("admin=groups:admin").is_err());
+    }
+
+    #[test]
+    fn port_authorizations() {
+        let id = "web.ns.serviceaccount.identity.linkerd.cluster.local";
+        let authzs = parse_port_authorizations(&format!(
+            "8080={}@10.0.0.0/8, 8080=*.ns.serviceaccount.identity.linkerd.cluster.local, 9090=unauthenticated@127.0.0.1/32, 9090=tls",
+            id
+        ))
+        .unwrap();
+        assert_eq!(authzs.len(), 2);
+
+        let web = &authzs[&8080];
+        assert_eq!(web.len(), 2);
+        assert!(web[0].contains_client(&[10, 1, 2, 3].into()));
+        assert!(!web[0].contains_client(&[192, 0, 2, 3].into()));
+        assert!(web[0].authentication.permits_name(id));
+        assert!(!web[0]
+            .authentication
+            .permits_name("api.ns.serviceaccount.identity.linkerd.cluster.local"));
+        assert_eq!(
+            web[0].labels.get("authz").map(String::as_str),
+            Some(&*format!("{}@10.0.0.0/8", id))
+        );
+        assert!(web[1].contains_client(&[192, 0, 2, 3].into()));
+        assert!(web[1]
+            .authentication
+            .permits_name("api.ns.serviceaccount.identity.linkerd.cluster.local"));
+
+        let local = &authzs[&9090];
+        assert_eq!(
+            local[0].authentication,
+            port_policies::Authentication::Unauthenticated
+        );
+        assert!(local[0].contains_client(&[127, 0, 0, 1].into()));
+        assert!(!local[0].contains_client(&[127, 0, 0, 2].into()));
+        assert_eq!(
+            local[1].authentication,
+            port_policies::Authentication::TlsUnauthenticated
+        );
+
+        assert!(parse_port_authorizations("").unwrap().is_empty());
+        assert!(parse_port_authorizations("8080").is_err());
+        assert!(parse_port_authorizations("8080=").is_err());
+        assert!(parse_port_authorizations("8080=tls@10.0.0.0").is_err());
+        assert!(parse_port_authorizations("8080=*ns.cluster.local").is_err());
+    }
+
+    #[test]
+    fn port_deny_responses() {
+        let responses = parse_port_deny_responses(
+            "8080=403 {\"error\": \"unauthorized\", \"help\": \"contact #platform\"}\n\n9090=401 denied\n9091=404",
+        )
+        .unwrap();
+        assert_eq!(
+            responses[&8080],
+            port_policies::DenyResponse {
+                status: 403,
+                headers: vec![("content-type".to_string(), "application/json".to_string())],
+                body: r#"{"error": "unauthorized", "help": "contact #platform"}"#.to_string(),
+            }
+        );
+        assert_eq!(
+            responses[&9090],
+            port_policies::DenyResponse {
+                status: 401,
+                headers: vec![("content-type".to_string(), "text/plain".to_string())],
+                body: "denied".to_string(),
+            }
+        );
+        assert_eq!(
+            responses[&9091],
+            port_policies::DenyResponse {
+                status: 404,
+                headers: vec![],
+                body: "".to_string(),
+            }
+        );
+
+        assert!(parse_port_deny_responses("").unwrap().is_empty());
+        assert!(parse_port_deny_responses("8080").is_err());
+        assert!(parse_port_deny_responses("8080=forbidden").is_err());
+        assert!(parse_port_deny_responses("8080=42").is_err());
+
+        let headers =
+            parse_port_deny_response_headers("8080=link:<https://wiki.example.com/platform>")
+                .unwrap();
+        assert_eq!(
+            headers[&8080],
+            vec![(
+                "link".to_string(),
+                "<https://wiki.example.com/platform>".to_string()
+            )]
+        );
+        assert!(parse_port_deny_response_headers("8080=link").is_err());
+        assert!(parse_port_deny_response_headers("8080=bad header:value").is_err());
+    }
+
+    #[test]
+    fn port_malformed_policies() {
+        use http::malformed::Policy;
+
+        assert!(parse_port_malformed_policies("").unwrap().is_empty());
+        assert_eq!(
+            parse_port_malformed_policies("8080=normalize, 9090 = reject").unwrap(),
+            vec![(8080, Policy::Normalize), (9090, Policy::Reject)]
+        );
+        assert_eq!(
+            parse_port_malformed_policies("8080=lenient").err(),
+            Some(ParseError::InvalidMalformedPolicy(
+                "8080=lenient".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_port_malformed_policies("8080").err(),
+            Some(ParseError::InvalidMalformedPolicy("8080".to_string()))
+        );
+    }
+
+    #[test]
+    fn port_probes() {
+        let probes = parse_port_probes(
+            "3306=/ready tcp:127.0.0.1:3306, 8080=/live http:127.0.0.1:9090/healthz",
+        )
+        .unwrap();
+        assert_eq!(
+            probes[&3306],
+            vec![inbound::Probe {
+                path: "/ready".to_string(),
+                check: inbound::ProbeCheck::Tcp(([127, 0, 0, 1], 3306).into()),
+            }]
+        );
+        assert_eq!(
+            probes[&8080],
+            vec![inbound::Probe {
+                path: "/live".to_string(),
+                check: inbound::ProbeCheck::Http {
+                    addr: ([127, 0, 0, 1], 9090).into(),
+                    path: "/healthz".to_string(),
+                },
+            }]
+        );
+
+        assert!(parse_port_probes("").unwrap().is_empty());
+        assert!(parse_port_probes("8080=/ready").is_err());
+        assert!(parse_port_probes("8080=/ready udp:127.0.0.1:53").is_err());
+        assert!(parse_port_probes("8080=ready tcp:127.0.0.1:8080").is_err());
+    }
+
+    #[test]
+    fn cookie_names() {
+        assert_eq!(
+            parse_cookie_name("l5d-affinity"),
+            Ok("l5d-affinity".to_string())
+        );
+        assert!(parse_cookie_name("").is_err());
+        assert!(parse_cookie_name("l5d affinity").is_err());
+        assert!(parse_cookie_name("l5d=affinity").is_err());
+        assert!(parse_cookie_name("l5d;affinity").is_err());
+    }
+
+    struct TestEnv(std::collections::HashMap<&'static str, &'static str>);
+
+    impl Strings for TestEnv {
+        fn get(&self, key: &str) -> Result<Option<String>, EnvError> {
+            Ok(self.0.get(key).map(|v| v.to_string()))
+        }
+    }
+
+    #[test]
+    fn trust_anchors_reload_interval() {
+        assert_eq!(parse_reload_interval("10s"), Ok(Duration::from_secs(10)));
+        assert!(parse_reload_interval("0s").is_err());
+
+        // Invalid intervals are rejected even without a trust anchors path.
+        for interval in &["0", "bogus"] {
+            let env = TestEnv(
+                vec![(ENV_IDENTITY_TRUST_ANCHORS_RELOAD_INTERVAL, *interval)]
+                    .into_iter()
+                    .collect(),
+            );
+            assert!(parse_identity_config(&env).is_err());
+        }
+    }
+
+    #[test]
+    fn path_prefixes() {
+        assert_eq!(parse_path_prefix("/auth").unwrap(), "/auth");
+        assert!(parse_path_prefix("auth").is_err());
+        assert!(parse_path_prefix("http://authz/auth").is_err());
+        assert!(parse_path_prefix("/auth?foo=bar").is_err());
+    }
+
+    #[test]
+    fn faults() {
+        assert_eq!(
+            parse_fault("503:0.1", parse_status_code),
+            Ok((http::StatusCode::SERVICE_UNAVAILABLE, 0.1))
+        );
+        assert_eq!(
+            parse_fault(" 500ms : 1 ", parse_duration),
+            Ok((Duration::from_millis(500), 1.0))
+        );
+        assert!(parse_fault("503", parse_status_code).is_err());
+        assert!(parse_fault("503:", parse_status_code).is_err());
+        assert!(parse_fault("503:1.5", parse_status_code).is_err());
+        assert!(parse_fault("503:-0.1", parse_status_code).is_err());
+        assert!(parse_fault("503:NaN", parse_status_code).is_err());
+        assert!(parse_fault("99:0.1", parse_status_code).is_err());
+        assert!(parse_fault("500:0.1", parse_duration).is_err());
+    }
+
+    #[test]
+    fn inbound_listeners() {
+        let timeout = Duration::from_secs(10);
+        let listeners =
+            parse_inbound_listeners("10.0.0.1:4143=deny, 0.0.0.0:4144", timeout).unwrap();
+        assert_eq!(listeners.len(), 2);
+        assert_eq!(listeners[0].0, SocketAddr::from(([10, 0, 0, 1], 4143)));
+        assert!(matches!(
+            listeners[0].1,
+            Some(port_policies::DefaultPolicy::Deny)
+        ));
+        assert_eq!(listeners[1].0, SocketAddr::from(([0, 0, 0, 0], 4144)));
+        assert!(listeners[1].1.is_none());
+
+        assert!(parse_inbound_listeners("", timeout).unwrap().is_empty());
+        assert!(parse_inbound_listeners("10.0.0.1:4143=bogus", timeout).is_err());
+        assert!(parse_inbound_listeners("localhost:4143", timeout).is_err());
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn http_wasm_filters() {
+        let filters =
+            parse_http_wasm_filters("auth=/var/wasm/auth.wasm:/etc/auth.json, rewrite=rw.wasm")
+                .unwrap();
+        assert_eq!(
+            filters,
+            vec![
+                http_wasm::FilterConfig {
+                    name: "auth".to_string(),
+                    module: PathBuf::from("/var/wasm/auth.wasm"),
+                    configuration: Some(PathBuf::from("/etc/auth.json")),
+                },
+                http_wasm::FilterConfig {
+                    name: "rewrite".to_string(),
+                    module: PathBuf::from("rw.wasm"),
+                    configuration: None,
+                },
+            ]
+        );
+
+        assert!(parse_http_wasm_filters("").unwrap().is_empty());
+        assert!(parse_http_wasm_filters("auth.wasm").is_err());
+        assert!(parse_http_wasm_filters("=auth.wasm").is_err());
+        assert!(parse_http_wasm_filters("auth=").is_err());
+        assert!(parse_http_wasm_filters("auth=auth.wasm:").is_err());
+    }
+
+    #[test]
+    fn ingress_tenants() {
+        let tenants = parse_ingress_tenants(
+            "team-a=team-a.svc.cluster.local, team-a=shared.svc.cluster.local., team-b=svc.cluster.local",
+        )
+        .unwrap();
+        assert_eq!(tenants.len(), 2);
+        assert_eq!(tenants["team-a"].len(), 2);
+        assert_eq!(tenants["team-b"].len(), 1);
+        assert!(parse_ingress_tenants("team-a").is_err());
+        assert!(parse_ingress_tenants("=svc.cluster.local").is_err());
+
+        let quotas = parse_ingress_tenant_quotas("team-a=100").unwrap();
+        assert_eq!(quotas["team-a"], 100);
+        assert!(parse_ingress_tenant_quotas("team-a=lots").is_err());
+    }
+
+    #[test]
+    fn span_attributes() {
+        let attrs = parse_span_attributes("peer.identity, route,retry.attempt").unwrap();
+        assert!(attrs.contains(http_tracing::Attribute::PeerIdentity));
+        assert!(attrs.contains(http_tracing::Attribute::Route));
+        assert!(attrs.contains(http_tracing::Attribute::RetryAttempt));
+        assert!(!attrs.contains(http_tracing::Attribute::LogicalDst));
+
+        assert!(parse_span_attributes("").unwrap().is_empty());
+        assert!(parse_span_attributes("peer.identity,dst").is_err());
+    }
 }
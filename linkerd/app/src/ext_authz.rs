@@ -0,0 +1,49 @@
+use crate::{dns, identity::LocalCrtKey};
+use linkerd_app_core::{
+    control, ext_authz, metrics::ControlHttp as HttpMetrics, proxy::http, svc::NewService,
+};
+use linkerd_app_inbound::ExtAuthz;
+
+/// Configures an external authorization service for inbound HTTP requests.
+///
+/// Both gRPC and HTTP authorization services are reached with a
+/// control-plane-style client, so checks are sent over mTLS to the configured
+/// identity unless identity is disabled.
+#[derive(Clone, Debug)]
+pub enum Config {
+    Disabled,
+    Grpc {
+        control: control::Config,
+        authz: ext_authz::Config,
+    },
+    Http {
+        control: control::Config,
+        prefix: http::uri::PathAndQuery,
+        authz: ext_authz::Config,
+    },
+}
+
+impl Config {
+    pub fn build(
+        self,
+        dns: dns::Resolver,
+        metrics: HttpMetrics,
+        identity: Option<LocalCrtKey>,
+    ) -> Option<ExtAuthz> {
+        match self {
+            Config::Disabled => None,
+            Config::Grpc { control, authz } => {
+                let svc = control.build(dns, metrics, identity).new_service(());
+                Some(ExtAuthz::grpc(svc, authz))
+            }
+            Config::Http {
+                control,
+                prefix,
+                authz,
+            } => {
+                let svc = control.build(dns, metrics, identity).new_service(());
+                Some(ExtAuthz::http(svc, prefix, authz))
+            }
+        }
+    }
+}
@@ -5,9 +5,11 @@
 
 pub mod dst;
 pub mod env;
+pub mod ext_authz;
 pub mod identity;
 pub mod oc_collector;
 pub mod tap;
+pub mod udp;
 
 pub use self::metrics::Metrics;
 use futures::{future, FutureExt, TryFutureExt};
@@ -15,9 +17,10 @@ use linkerd_app_admin as admin;
 pub use linkerd_app_core::{self as core, metrics, trace};
 use linkerd_app_core::{
     config::ServerConfig,
+    connections,
     control::ControlAddr,
-    dns, drain,
-    svc::Param,
+    dns, drain, profiles,
+    svc::{self, Param, ServiceExt},
     transport::{listen::Bind, ClientAddr, Local, OrigDstAddr, Remote, ServerAddr},
     Error, ProxyRuntime,
 };
@@ -56,6 +59,20 @@ pub struct Config {
     pub admin: admin::Config,
     pub tap: tap::Config,
     pub oc_collector: oc_collector::Config,
+    pub ext_authz: ext_authz::Config,
+    pub udp: udp::Config,
+
+    /// Out-of-tree extensions to the inbound and outbound stacks. Extensions
+    /// may only be registered when the `extensions` feature is enabled.
+    pub extensions: svc::extension::Registry,
+
+    /// How long the proxy continues to serve traffic after it receives a
+    /// shutdown signal, before it begins to drain.
+    pub shutdown_delay: Duration,
+
+    /// How long the outbound proxy continues to serve traffic once the proxy
+    /// begins to drain.
+    pub outbound_drain_linger: Duration,
 }
 
 pub struct App {
@@ -64,10 +81,13 @@ pub struct App {
     dst: ControlAddr,
     identity: identity::Identity,
     inbound_addr: Local<ServerAddr>,
+    additional_inbound_addrs: Vec<Local<ServerAddr>>,
     oc_collector: oc_collector::OcCollector,
     outbound_addr: Local<ServerAddr>,
+    shutdown_delay: Duration,
     start_proxy: Pin<Box<dyn std::future::Future<Output = ()> + Send + 'static>>,
     tap: tap::Tap,
+    udp_addr: Option<Local<ServerAddr>>,
 }
 
 impl Config {
@@ -90,7 +110,7 @@ impl Config {
         log_level: trace::Handle,
     ) -> Result<App, Error>
     where
-        BIn: Bind<ServerConfig> + 'static,
+        BIn: Bind<ServerConfig> + Clone + 'static,
         BIn::Addrs: Param<Remote<ClientAddr>> + Param<Local<ServerAddr>> + Param<OrigDstAddr>,
         BOut: Bind<ServerConfig> + 'static,
         BOut::Addrs: Param<Remote<ClientAddr>> + Param<Local<ServerAddr>> + Param<OrigDstAddr>,
@@ -109,9 +129,15 @@ impl Config {
             outbound,
             gateway,
             tap,
+            ext_authz,
+            udp,
+            extensions,
+            shutdown_delay,
+            outbound_drain_linger,
         } = self;
         debug!("building app");
-        let (metrics, report) = Metrics::new(admin.metrics_retain_idle);
+        let (metrics, report) =
+            Metrics::new(admin.metrics_retain_idle, admin.metrics_peer_identity_limit);
 
         let dns = dns.build();
 
@@ -120,6 +146,7 @@ impl Config {
         let report = identity.metrics().and_then(report);
 
         let (drain_tx, drain_rx) = drain::channel();
+        let connections = connections::Registry::default();
 
         let tap = {
             let bind = bind_admin.clone();
@@ -131,6 +158,32 @@ impl Config {
             let dns = dns.resolver.clone();
             info_span!("dst").in_scope(|| dst.build(dns, metrics, identity.local()))
         }?;
+        let report = dst.profiles.metrics().and_then(report);
+        let report = report.and_then(outbound.ingress_tenants.clone());
+        let report = report.and_then(inbound.connection_limits.clone());
+        let report = report.and_then(inbound.tls_handshake_limits.clone());
+        let report = report.and_then(inbound.proxy.fd_pressure.clone());
+
+        let inbound = {
+            let metrics = metrics.control.clone();
+            let dns = dns.resolver.clone();
+            let ext_authz = info_span!("ext_authz")
+                .in_scope(|| ext_authz.build(dns, metrics, identity.local()));
+            inbound::Config {
+                ext_authz,
+                ..inbound
+            }
+        };
+
+        let udp = {
+            let metrics = metrics.udp.clone();
+            let drain = drain_rx.clone();
+            info_span!("udp").in_scope(|| udp.build(metrics, drain))?
+        };
+        let (udp_addr, udp_serve) = match udp {
+            udp::Udp::Disabled => (None, None),
+            udp::Udp::Enabled { listen_addr, serve } => (Some(listen_addr), Some(serve)),
+        };
 
         let oc_collector = {
             let identity = identity.local();
@@ -145,12 +198,16 @@ impl Config {
             let identity = identity.local();
             let drain = drain_rx.clone();
             let metrics = metrics.inbound.clone();
+            let protocol_hints = outbound.protocol_hints.clone();
+            let connections = connections.clone();
             info_span!("admin").in_scope(move || {
                 admin.build(
                     bind_admin,
                     identity,
                     report,
                     metrics,
+                    protocol_hints,
+                    connections,
                     log_level,
                     drain,
                     shutdown_tx,
@@ -166,19 +223,26 @@ impl Config {
                 identity: identity.local(),
                 metrics: metrics.inbound,
                 tap: tap.registry(),
+                connections: connections.clone(),
                 span_sink: oc_collector.span_sink(),
                 drain: drain_rx.clone(),
+                extensions: extensions.clone(),
             },
         );
 
+        // The outbound proxy drains separately so that it may continue to
+        // serve the application's requests while the rest of the proxy drains.
+        let (outbound_drain_tx, outbound_drain_rx) = drain::channel();
         let outbound = Outbound::new(
             outbound,
             ProxyRuntime {
                 identity: identity.local(),
                 metrics: metrics.outbound,
                 tap: tap.registry(),
+                connections,
                 span_sink: oc_collector.span_sink(),
-                drain: drain_rx,
+                drain: outbound_drain_rx,
+                extensions,
             },
         );
 
@@ -190,13 +254,62 @@ impl Config {
             dst.resolve.clone(),
         );
 
+        // If inbound bearer tokens are validated against a remote key set,
+        // keep it up-to-date in the background.
+        let jwks_refresh = inbound.config().jwt.as_ref().and_then(|v| v.refresh());
+
+        // Report whether the destination service has answered a lookup, so
+        // that callers may wait for outbound routing to be functional. This
+        // does not gate the proxy's readiness probe, which should not fail
+        // when the control plane is unavailable.
+        let dst_ready = {
+            let latch = admin.readiness.optional("destination");
+            let lookup = profiles::LookupAddr(dst_addr.addr.clone());
+            dst.profiles.clone().oneshot(lookup).map(move |_| {
+                debug!("Destination service ready");
+                latch.release();
+            })
+        };
+
+        let additional_inbound = inbound
+            .additional_listeners()
+            .into_iter()
+            .map(|listener| {
+                listener.serve(bind_in.clone(), dst.profiles.clone(), gateway_stack.clone())
+            })
+            .collect::<Vec<_>>();
+        let additional_inbound_addrs = additional_inbound.iter().map(|(addr, _)| *addr).collect();
         let (inbound_addr, inbound_serve) =
             inbound.serve(bind_in, dst.profiles.clone(), gateway_stack);
         let (outbound_addr, outbound_serve) = outbound.serve(bind_out, dst.profiles, dst.resolve);
 
         let start_proxy = Box::pin(async move {
             tokio::spawn(outbound_serve.instrument(info_span!("outbound")));
+            tokio::spawn(
+                async move {
+                    // Hold the proxy's drain open until the outbound proxy
+                    // has lingered and drained.
+                    let release = drain_rx.signaled().await;
+                    if outbound_drain_linger > Duration::from_secs(0) {
+                        debug!(linger = ?outbound_drain_linger, "Lingering before drain");
+                        time::sleep(outbound_drain_linger).await;
+                    }
+                    outbound_drain_tx.drain().await;
+                    drop(release);
+                }
+                .instrument(info_span!("outbound")),
+            );
             tokio::spawn(inbound_serve.instrument(info_span!("inbound")));
+            for (Local(ServerAddr(addr)), serve) in additional_inbound {
+                tokio::spawn(serve.instrument(info_span!("inbound", listen.addr = %addr)));
+            }
+            if let Some(serve) = udp_serve {
+                tokio::spawn(serve.instrument(info_span!("udp")));
+            }
+            tokio::spawn(dst_ready.instrument(info_span!("dst")));
+            if let Some(refresh) = jwks_refresh {
+                tokio::spawn(refresh.instrument(info_span!("jwks")));
+            }
         });
 
         Ok(App {
@@ -205,10 +318,13 @@ impl Config {
             drain: drain_tx,
             identity,
             inbound_addr,
+            additional_inbound_addrs,
             oc_collector,
             outbound_addr,
+            shutdown_delay,
             start_proxy,
             tap,
+            udp_addr,
         })
     }
 }
@@ -222,6 +338,10 @@ impl App {
         self.inbound_addr
     }
 
+    pub fn additional_inbound_addrs(&self) -> &[Local<ServerAddr>] {
+        &self.additional_inbound_addrs
+    }
+
     pub fn outbound_addr(&self) -> Local<ServerAddr> {
         self.outbound_addr
     }
@@ -233,6 +353,10 @@ impl App {
         }
     }
 
+    pub fn udp_addr(&self) -> Option<Local<ServerAddr>> {
+        self.udp_addr
+    }
+
     pub fn dst_addr(&self) -> &ControlAddr {
         &self.dst
     }
@@ -251,6 +375,17 @@ impl App {
         }
     }
 
+    /// How long the proxy should continue to serve traffic after it receives a
+    /// shutdown signal, before it begins to drain.
+    pub fn shutdown_delay(&self) -> Duration {
+        self.shutdown_delay
+    }
+
+    /// Observes drain requests made via the admin server.
+    pub fn admin_drain(&self) -> admin::Drain {
+        self.admin.drain.clone()
+    }
+
     pub fn opencensus_addr(&self) -> Option<&ControlAddr> {
         match self.oc_collector {
             oc_collector::OcCollector::Disabled { .. } => None,
@@ -1,8 +1,12 @@
 use crate::{dns, identity::LocalCrtKey};
 use linkerd_app_core::{control, metrics::ControlHttp as HttpMetrics, svc::NewService, Error};
 use linkerd_opencensus::{self as opencensus, metrics, proto};
-use std::{collections::HashMap, future::Future, pin::Pin, time::SystemTime};
-use tokio::sync::mpsc;
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    time::{Duration, SystemTime},
+};
 use tokio_stream::wrappers::ReceiverStream;
 use tracing::Instrument;
 
@@ -17,11 +21,14 @@ pub struct EnabledConfig {
     pub control: control::Config,
     pub attributes: HashMap<String, String>,
     pub hostname: Option<String>,
+    pub batch: opencensus::BatchConfig,
+    pub buffer_capacity: usize,
+    pub send_timeout: Option<Duration>,
 }
 
 pub type Task = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
 
-pub type SpanSink = mpsc::Sender<proto::trace::v1::Span>;
+pub type SpanSink = opencensus::SpanSender;
 
 pub enum OcCollector {
     Disabled,
@@ -35,7 +42,6 @@ pub struct EnabledCollector {
 }
 
 impl Config {
-    const SPAN_BUFFER_CAPACITY: usize = 100;
     const SERVICE_NAME: &'static str = "linkerd-proxy";
 
     pub fn build(
@@ -54,7 +60,8 @@ impl Config {
                     .build(dns, client_metrics, identity)
                     .new_service(());
 
-                let (span_sink, spans_rx) = mpsc::channel(Self::SPAN_BUFFER_CAPACITY);
+                let (span_sink, spans_rx) =
+                    opencensus::channel(inner.buffer_capacity, inner.send_timeout, metrics.clone());
                 let spans_rx = ReceiverStream::new(spans_rx);
 
                 let task = {
@@ -75,7 +82,7 @@ impl Config {
 
                     let addr = addr.clone();
                     Box::pin(
-                        opencensus::export_spans(svc, node, spans_rx, metrics)
+                        opencensus::export_spans(svc, node, spans_rx, inner.batch, metrics)
                             .instrument(tracing::debug_span!("opencensus", peer.addr = %addr)),
                     )
                 };
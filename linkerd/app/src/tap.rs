@@ -83,7 +83,7 @@ impl Config {
                     .check_new_service::<B::Addrs, _>()
                     .into_inner();
 
-                let serve = Box::pin(serve::serve(listen, accept, drain.signaled()));
+                let serve = Box::pin(serve::serve(listen, accept, None, drain.signaled()));
 
                 Ok(Tap::Enabled {
                     listen_addr,
@@ -120,6 +120,13 @@ impl<T> ExtractParam<Option<LocalCrtKey>, T> for TlsParams {
     }
 }
 
+impl<T> ExtractParam<tls::server::HandshakeLimit, T> for TlsParams {
+    #[inline]
+    fn extract_param(&self, _: &T) -> tls::server::HandshakeLimit {
+        tls::server::HandshakeLimit(None)
+    }
+}
+
 impl<T> InsertParam<tls::ConditionalServerTls, T> for TlsParams {
     type Target = (tls::ConditionalServerTls, T);
 
@@ -0,0 +1,49 @@
+use futures::prelude::*;
+use linkerd_app_core::{
+    drain,
+    transport::{udp, ListenAddr, Local, ServerAddr},
+    Error,
+};
+use std::{pin::Pin, time::Duration};
+
+#[derive(Clone, Debug)]
+pub enum Config {
+    Disabled,
+    Enabled {
+        addr: ListenAddr,
+        idle_timeout: Duration,
+        max_sessions: usize,
+    },
+}
+
+pub enum Udp {
+    Disabled,
+    Enabled {
+        listen_addr: Local<ServerAddr>,
+        serve: Pin<Box<dyn std::future::Future<Output = ()> + Send + 'static>>,
+    },
+}
+
+impl Config {
+    pub fn build(self, metrics: udp::Metrics, drain: drain::Watch) -> Result<Udp, Error> {
+        match self {
+            Config::Disabled => Ok(Udp::Disabled),
+            Config::Enabled {
+                addr,
+                idle_timeout,
+                max_sessions,
+            } => {
+                let (listen_addr, listener) = udp::bind(addr)?;
+                let serve = Box::pin(async move {
+                    // Stop forwarding datagrams when shutdown is signaled.
+                    let serve = listener.serve(idle_timeout, max_sessions, metrics);
+                    futures::pin_mut!(serve);
+                    let shutdown = drain.signaled();
+                    futures::pin_mut!(shutdown);
+                    future::select(serve, shutdown).await;
+                });
+                Ok(Udp::Enabled { listen_addr, serve })
+            }
+        }
+    }
+}
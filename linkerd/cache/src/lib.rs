@@ -1,6 +1,9 @@
 #![deny(warnings, rust_2018_idioms)]
 #![forbid(unsafe_code)]
 
+mod metrics;
+
+pub use self::metrics::{Metrics, Registry};
 use linkerd_stack::{layer, NewService};
 use parking_lot::RwLock;
 use std::{
@@ -21,6 +24,7 @@ where
     inner: N,
     services: Arc<Services<T, N::Service>>,
     idle: time::Duration,
+    metrics: Metrics,
 }
 
 #[derive(Clone, Debug)]
@@ -43,16 +47,21 @@ where
     N: NewService<T> + 'static,
     N::Service: Send + Sync + 'static,
 {
-    pub fn layer(idle: time::Duration) -> impl layer::Layer<N, Service = Self> + Clone {
-        layer::mk(move |inner| Self::new(idle, inner))
+    pub fn layer(
+        idle: time::Duration,
+        metrics: Metrics,
+    ) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self::new(idle, metrics.clone(), inner))
     }
 
-    fn new(idle: time::Duration, inner: N) -> Self {
+    fn new(idle: time::Duration, metrics: Metrics, inner: N) -> Self {
         let services = Arc::new(Services::default());
+        metrics.register(Arc::downgrade(&services) as Weak<dyn metrics::Entries>);
         Self {
             inner,
             services,
             idle,
+            metrics,
         }
     }
 
@@ -60,6 +69,7 @@ where
         target: T,
         idle: time::Duration,
         cache: &Arc<Services<T, N::Service>>,
+        metrics: &Metrics,
     ) -> Arc<Notify> {
         // Spawn a background task that holds the handle. Every time the handle
         // is notified, it resets the idle timeout. Every time teh idle timeout
@@ -71,16 +81,18 @@ where
             idle,
             handle.clone(),
             Arc::downgrade(cache),
+            metrics.clone(),
         ));
         handle
     }
 
-    #[instrument(level = "debug", skip(idle, reset, cache))]
+    #[instrument(level = "debug", skip(idle, reset, cache, metrics))]
     async fn evict(
         target: T,
         idle: time::Duration,
         mut reset: Arc<Notify>,
         cache: Weak<Services<T, N::Service>>,
+        metrics: Metrics,
     ) {
         // Wait for the handle to be notified before starting to track idleness.
         reset.notified().await;
@@ -97,22 +109,33 @@ where
                     trace!("Reset");
                 }
                 _ = time::sleep(idle) => match cache.upgrade() {
-                    Some(cache) => match Arc::try_unwrap(reset) {
-                        // If this is the last reference to the handle after the
-                        // idle timeout, remove the cache entry.
-                        Ok(_) => {
-                            let removed = cache.write().remove(&target).is_some();
-                            debug_assert!(removed, "Cache item must exist: {:?}", target);
-                            debug!("Cache entry dropped");
-                            return;
+                    Some(cache) => {
+                        // Hold the lock so that the handle cannot be acquired
+                        // while the entry is being removed.
+                        let mut services = cache.write();
+                        match Arc::try_unwrap(reset) {
+                            // If this is the last reference to the handle after
+                            // the idle timeout, remove the cache entry. The
+                            // entry may have already been flushed and replaced,
+                            // in which case the replacement is retained.
+                            Ok(_) => {
+                                if let Entry::Occupied(entry) = services.entry(target) {
+                                    if entry.get().1.strong_count() == 0 {
+                                        entry.remove();
+                                        metrics.idle_eviction();
+                                        debug!("Cache entry dropped");
+                                    }
+                                }
+                                return;
+                            }
+                            // Otherwise, another handle has been acquired, so
+                            // restore our reset reference for the next iteration.
+                            Err(r) => {
+                                trace!("The handle is still active");
+                                reset = r;
+                            }
                         }
-                        // Otherwise, another handle has been acquired, so
-                        // restore our reset reference for the next iteration.
-                        Err(r) => {
-                            trace!("The handle is still active");
-                            reset = r;
-                        }
-                    },
+                    }
                     None => {
                         trace!("Cache already dropped");
                         return;
@@ -137,6 +160,7 @@ where
         if let Some((svc, weak)) = self.services.read().get(&target) {
             if let Some(handle) = weak.upgrade() {
                 trace!("Using cached service");
+                self.metrics.hit();
                 return Cached {
                     inner: svc.clone(),
                     handle,
@@ -153,6 +177,7 @@ where
                 match weak.upgrade() {
                     Some(handle) => {
                         trace!(?target, "Using cached service");
+                        self.metrics.hit();
                         Cached {
                             inner: svc.clone(),
                             handle,
@@ -160,7 +185,13 @@ where
                     }
                     None => {
                         debug!(?target, "Replacing defunct service");
-                        let handle = Self::spawn_idle(target.clone(), self.idle, &self.services);
+                        self.metrics.miss();
+                        let handle = Self::spawn_idle(
+                            target.clone(),
+                            self.idle,
+                            &self.services,
+                            &self.metrics,
+                        );
                         let inner = self.inner.new_service(target);
                         entry.insert((inner.clone(), Arc::downgrade(&handle)));
                         Cached { inner, handle }
@@ -169,7 +200,9 @@ where
             }
             Entry::Vacant(entry) => {
                 debug!(?target, "Caching new service");
-                let handle = Self::spawn_idle(target.clone(), self.idle, &self.services);
+                self.metrics.miss();
+                let handle =
+                    Self::spawn_idle(target.clone(), self.idle, &self.services, &self.metrics);
                 let inner = self.inner.new_service(target);
                 entry.insert((inner.clone(), Arc::downgrade(&handle)));
                 Cached { inner, handle }
@@ -178,6 +211,26 @@ where
     }
 }
 
+// === impl Services ===
+
+impl<T, S> metrics::Entries for Services<T, S>
+where
+    T: Eq + Hash + Send + Sync,
+    S: Send + Sync,
+{
+    fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    fn flush(&self) -> usize {
+        let mut services = self.write();
+        let flushed = services.len();
+        services.clear();
+        debug!(flushed, "Cache flushed");
+        flushed
+    }
+}
+
 // === impl Cached ===
 
 impl<Req, S> tower::Service<Req> for Cached<S>
@@ -216,7 +269,8 @@ async fn test_idle_retain() {
     let idle = time::Duration::from_secs(10);
     let cache = Arc::new(Services::default());
 
-    let handle = Cache::<(), fn(()) -> ()>::spawn_idle((), idle, &cache);
+    let metrics = Metrics::default();
+    let handle = Cache::<(), fn(()) -> ()>::spawn_idle((), idle, &cache, &metrics);
     cache.write().insert((), ((), Arc::downgrade(&handle)));
     let c0 = Cached { inner: (), handle };
 
@@ -256,3 +310,40 @@ async fn test_idle_retain() {
     assert!(handle.upgrade().is_none());
     assert!(!cache.read().contains_key(&()));
 }
+
+#[cfg(test)]
+#[tokio::test(flavor = "current_thread")]
+async fn test_flush() {
+    time::pause();
+
+    let idle = time::Duration::from_secs(10);
+    let metrics = Metrics::default();
+    let built = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let mut cache = Cache::new(idle, metrics.clone(), {
+        let built = built.clone();
+        move |()| built.fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+    });
+
+    // Build a service and ensure that it's reused.
+    let c0 = cache.new_service(());
+    assert_eq!(c0.inner, 0);
+    assert_eq!(cache.new_service(()).inner, 0);
+
+    // Flush the cache while the original service is still held and ensure that
+    // a new service is built.
+    assert_eq!(metrics.flush(), 1);
+    assert!(!cache.services.read().contains_key(&()));
+    let c1 = cache.new_service(());
+    assert_eq!(c1.inner, 1);
+
+    // Drop the original service and ensure that its idle timeout does not
+    // evict the replacement.
+    drop(c0);
+    time::sleep(idle * 2).await;
+    assert!(cache.services.read().contains_key(&()));
+
+    // Once the replacement is dropped, it is evicted after becoming idle.
+    drop(c1);
+    time::sleep(idle * 2).await;
+    assert!(!cache.services.read().contains_key(&()));
+}
@@ -0,0 +1,158 @@
+use linkerd_metrics::{metrics, Counter, FmtLabels, FmtMetrics, Gauge};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::Hash,
+    sync::{Arc, Weak},
+};
+
+metrics! {
+    stack_cache_entries: Gauge { "The number of services held in a stack cache" },
+    stack_cache_hits_total: Counter { "Total number of times a cached service was reused" },
+    stack_cache_misses_total: Counter { "Total number of times a service was built because it was not cached" },
+    stack_cache_evictions_total: Counter { "Total number of services evicted from a stack cache, including idle evictions" },
+    stack_cache_idle_evictions_total: Counter { "Total number of services evicted from a stack cache after becoming idle" }
+}
+
+/// Tracks the state of all caches that share a set of labels.
+///
+/// Caches register themselves so that they may be inspected and flushed
+/// without holding a reference to the cache itself.
+#[derive(Clone, Debug, Default)]
+pub struct Metrics(Arc<Inner>);
+
+#[derive(Debug)]
+pub struct Registry<L: Hash + Eq>(Arc<Mutex<HashMap<L, Metrics>>>);
+
+/// A cache's entries, as observed by its metrics.
+pub(crate) trait Entries: Send + Sync {
+    fn len(&self) -> usize;
+
+    /// Drops all entries from the cache, returning the number of entries that
+    /// were dropped.
+    fn flush(&self) -> usize;
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    hits: Counter,
+    misses: Counter,
+    evictions: Counter,
+    idle_evictions: Counter,
+    caches: Mutex<Vec<Weak<dyn Entries>>>,
+}
+
+// === impl Metrics ===
+
+impl Metrics {
+    pub(crate) fn register(&self, cache: Weak<dyn Entries>) {
+        let mut caches = self.0.caches.lock();
+        caches.retain(|c| c.strong_count() > 0);
+        caches.push(cache);
+    }
+
+    pub(crate) fn hit(&self) {
+        self.0.hits.incr();
+    }
+
+    pub(crate) fn miss(&self) {
+        self.0.misses.incr();
+    }
+
+    pub(crate) fn idle_eviction(&self) {
+        self.0.evictions.incr();
+        self.0.idle_evictions.incr();
+    }
+
+    /// Drops all entries from the registered caches, returning the number of
+    /// entries that were dropped.
+    ///
+    /// Services that are in use are retained by their users, but they are
+    /// rebuilt the next time they are obtained from the cache.
+    pub fn flush(&self) -> usize {
+        let flushed = self
+            .0
+            .caches
+            .lock()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .map(|c| c.flush())
+            .sum::<usize>();
+        self.0.evictions.add(flushed as u64);
+        flushed
+    }
+
+    fn entries(&self) -> Gauge {
+        let n = self
+            .0
+            .caches
+            .lock()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .map(|c| c.len())
+            .sum::<usize>();
+        Gauge::from(n as u64)
+    }
+}
+
+// === impl Registry ===
+
+impl<L: Hash + Eq> Registry<L> {
+    pub fn metrics(&self, labels: L) -> Metrics {
+        self.0.lock().entry(labels).or_default().clone()
+    }
+
+    /// Flushes all caches with labels that match the given predicate,
+    /// returning the number of entries that were dropped.
+    pub fn flush(&self, mut matches: impl FnMut(&L) -> bool) -> usize {
+        self.0
+            .lock()
+            .iter()
+            .filter(|(l, _)| matches(l))
+            .map(|(_, m)| m.flush())
+            .sum()
+    }
+}
+
+impl<L: Hash + Eq> Default for Registry<L> {
+    fn default() -> Self {
+        Registry(Default::default())
+    }
+}
+
+impl<L: Hash + Eq> Clone for Registry<L> {
+    fn clone(&self) -> Self {
+        Registry(self.0.clone())
+    }
+}
+
+impl<L: FmtLabels + Hash + Eq> FmtMetrics for Registry<L> {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let metrics = self.0.lock();
+        if metrics.is_empty() {
+            return Ok(());
+        }
+
+        let entries = metrics
+            .iter()
+            .map(|(l, m)| (l, m.entries()))
+            .collect::<Vec<_>>();
+        stack_cache_entries.fmt_help(f)?;
+        stack_cache_entries.fmt_scopes(f, entries.iter().map(|(l, g)| (*l, g)), |g| g)?;
+
+        stack_cache_hits_total.fmt_help(f)?;
+        stack_cache_hits_total.fmt_scopes(f, metrics.iter(), |m| &m.0.hits)?;
+
+        stack_cache_misses_total.fmt_help(f)?;
+        stack_cache_misses_total.fmt_scopes(f, metrics.iter(), |m| &m.0.misses)?;
+
+        stack_cache_evictions_total.fmt_help(f)?;
+        stack_cache_evictions_total.fmt_scopes(f, metrics.iter(), |m| &m.0.evictions)?;
+
+        stack_cache_idle_evictions_total.fmt_help(f)?;
+        stack_cache_idle_evictions_total.fmt_scopes(f, metrics.iter(), |m| &m.0.idle_evictions)?;
+
+        Ok(())
+    }
+}
@@ -1,6 +1,9 @@
 #![deny(warnings, rust_2018_idioms)]
 #![forbid(unsafe_code)]
 
+pub mod sniff;
+
+pub use self::sniff::{Detected, Sniffers, Sniffing};
 use bytes::BytesMut;
 use linkerd_error::Error;
 use linkerd_io as io;
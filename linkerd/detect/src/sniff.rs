@@ -0,0 +1,264 @@
+//! Recognizes protocols that the proxy does not otherwise handle.
+//!
+//! A `Sniffers` registry holds a set of `Sniff` implementations that inspect
+//! the bytes read during protocol detection. When a primary detector (e.g.
+//! HTTP) fails to identify a connection, each sniffer is consulted in order
+//! so that the connection may be labeled with the protocol it carries, even
+//! though it is still proxied as an opaque stream.
+//!
+//! Note that sniffers can only recognize client-first protocols. Server-first
+//! protocols (like MySQL, whose server sends a greeting before the client
+//! writes anything) cannot be identified from the client's initial bytes.
+
+use crate::Detect;
+use bytes::BytesMut;
+use linkerd_error::Error;
+use std::{fmt, sync::Arc};
+use tracing::{debug, trace};
+
+/// The name of a protocol recognized by a `Sniff`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Protocol(pub &'static str);
+
+/// Inspects the initial bytes of a connection to determine whether it carries
+/// a given protocol.
+pub trait Sniff: Send + Sync + 'static {
+    fn protocol(&self) -> Protocol;
+
+    /// Returns true if the buffered bytes are the start of a stream of this
+    /// protocol.
+    fn sniff(&self, buf: &[u8]) -> bool;
+}
+
+/// An ordered registry of `Sniff` implementations.
+#[derive(Clone, Default)]
+pub struct Sniffers(Arc<Vec<Arc<dyn Sniff>>>);
+
+/// Wraps a `Detect` so that connections it fails to identify are checked
+/// against a `Sniffers` registry.
+#[derive(Clone, Debug)]
+pub struct Sniffing<D> {
+    detect: D,
+    sniffers: Sniffers,
+}
+
+/// The result of a `Sniffing` detection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Detected<P> {
+    /// The protocol was identified by the primary detector.
+    Known(P),
+
+    /// The protocol was recognized by a sniffer.
+    Sniffed(Protocol),
+}
+
+/// Recognizes a TLS ClientHello record.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Tls(());
+
+/// Recognizes an SSH protocol version exchange.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Ssh(());
+
+/// Recognizes a PostgreSQL startup, SSL, GSSAPI, or cancel request.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Postgres(());
+
+/// Recognizes a Redis RESP command array.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Redis(());
+
+// === impl Protocol ===
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// === impl Sniffers ===
+
+impl Sniffers {
+    /// Returns a registry including all of the sniffers provided by this
+    /// crate.
+    pub fn builtin() -> Self {
+        Self::default()
+            .with(Tls::default())
+            .with(Ssh::default())
+            .with(Postgres::default())
+            .with(Redis::default())
+    }
+
+    /// Adds a sniffer to the registry. Sniffers are consulted in the order
+    /// in which they are added.
+    pub fn with(mut self, sniff: impl Sniff) -> Self {
+        Arc::make_mut(&mut self.0).push(Arc::new(sniff));
+        self
+    }
+
+    pub fn sniff(&self, buf: &[u8]) -> Option<Protocol> {
+        self.0.iter().find(|s| s.sniff(buf)).map(|s| s.protocol())
+    }
+}
+
+impl fmt::Debug for Sniffers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|s| s.protocol()))
+            .finish()
+    }
+}
+
+// === impl Sniffing ===
+
+impl<D> Sniffing<D> {
+    pub fn new(detect: D, sniffers: Sniffers) -> Self {
+        Self { detect, sniffers }
+    }
+}
+
+#[async_trait::async_trait]
+impl<I, D> Detect<I> for Sniffing<D>
+where
+    I: Send + 'static,
+    D: Detect<I>,
+{
+    type Protocol = Detected<D::Protocol>;
+
+    async fn detect(
+        &self,
+        io: &mut I,
+        buf: &mut BytesMut,
+    ) -> Result<Option<Detected<D::Protocol>>, Error> {
+        if let Some(protocol) = self.detect.detect(io, buf).await? {
+            return Ok(Some(Detected::Known(protocol)));
+        }
+
+        trace!(sniffers = ?self.sniffers, read = buf.len(), "Sniffing");
+        let sniffed = self.sniffers.sniff(&buf[..]);
+        debug!(protocol = ?sniffed, "Sniffed");
+        Ok(sniffed.map(Detected::Sniffed))
+    }
+}
+
+// === impl Tls ===
+
+impl Sniff for Tls {
+    fn protocol(&self) -> Protocol {
+        Protocol("tls")
+    }
+
+    fn sniff(&self, buf: &[u8]) -> bool {
+        // A handshake record (22) with a legacy version of SSL 3.0 through
+        // TLS 1.3, followed by a ClientHello (1) handshake message.
+        matches!(buf, [22, 3, 0..=4, _, _, 1, ..])
+    }
+}
+
+// === impl Ssh ===
+
+impl Sniff for Ssh {
+    fn protocol(&self) -> Protocol {
+        Protocol("ssh")
+    }
+
+    fn sniff(&self, buf: &[u8]) -> bool {
+        buf.starts_with(b"SSH-")
+    }
+}
+
+// === impl Postgres ===
+
+impl Postgres {
+    const PROTOCOL_V3: u32 = 196608;
+    const CANCEL_REQUEST: u32 = 80877102;
+    const SSL_REQUEST: u32 = 80877103;
+    const GSSENC_REQUEST: u32 = 80877104;
+}
+
+impl Sniff for Postgres {
+    fn protocol(&self) -> Protocol {
+        Protocol("postgres")
+    }
+
+    fn sniff(&self, buf: &[u8]) -> bool {
+        // Every message a client may send first is an int32 length
+        // (including itself) followed by an int32 request code.
+        if buf.len() < 8 {
+            return false;
+        }
+        let len = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
+        let code = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]);
+        match code {
+            Self::PROTOCOL_V3 => (8..=10_000).contains(&len),
+            Self::CANCEL_REQUEST => len == 16,
+            Self::SSL_REQUEST | Self::GSSENC_REQUEST => len == 8,
+            _ => false,
+        }
+    }
+}
+
+// === impl Redis ===
+
+impl Sniff for Redis {
+    fn protocol(&self) -> Protocol {
+        Protocol("redis")
+    }
+
+    fn sniff(&self, buf: &[u8]) -> bool {
+        // Clients send commands as an array of bulk strings, e.g.
+        // `*1\r\n$4\r\nPING\r\n`.
+        let buf = match buf.strip_prefix(b"*") {
+            Some(buf) => buf,
+            None => return false,
+        };
+        let digits = buf.iter().take_while(|b| b.is_ascii_digit()).count();
+        digits > 0 && buf[digits..].starts_with(b"\r\n$")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HTTP: &[u8] = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+
+    #[test]
+    fn builtin() {
+        let sniffers = Sniffers::builtin();
+        for (input, expected) in &[
+            (&[22, 3, 1, 2, 0, 1, 0, 1, 252, 3, 3][..], Some("tls")),
+            (b"SSH-2.0-OpenSSH_8.4\r\n", Some("ssh")),
+            (
+                &[0, 0, 0, 41, 0, 3, 0, 0, b'u', b's', b'e', b'r', 0][..],
+                Some("postgres"),
+            ),
+            (&[0, 0, 0, 8, 4, 210, 22, 47][..], Some("postgres")),
+            (b"*1\r\n$4\r\nPING\r\n", Some("redis")),
+            (b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$1\r\nv\r\n", Some("redis")),
+            (HTTP, None),
+            (b"*\r\n", None),
+            (&[0, 0, 0, 8, 0, 0, 0, 0][..], None),
+            (b"", None),
+        ] {
+            assert_eq!(sniffers.sniff(input), expected.map(Protocol), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn ordered() {
+        struct Any(&'static str);
+        impl Sniff for Any {
+            fn protocol(&self) -> Protocol {
+                Protocol(self.0)
+            }
+            fn sniff(&self, _: &[u8]) -> bool {
+                true
+            }
+        }
+
+        assert_eq!(Sniffers::default().sniff(HTTP), None);
+        let sniffers = Sniffers::default().with(Any("a")).with(Any("b"));
+        assert_eq!(sniffers.sniff(HTTP), Some(Protocol("a")));
+    }
+}
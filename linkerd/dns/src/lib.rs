@@ -3,21 +3,87 @@
 
 pub use linkerd_dns_name::{InvalidName, Name, Suffix};
 use linkerd_error::Error;
-use std::{fmt, net};
+use std::{fmt, io, net, sync::Arc};
 use thiserror::Error;
 use tokio::time::{self, Instant};
-use tracing::{debug, trace};
-use trust_dns_resolver::{
-    config::ResolverConfig, proto::rr::rdata, system_conf, AsyncResolver, TokioAsyncResolver,
-};
+use tracing::{debug, trace, warn};
 pub use trust_dns_resolver::{
     config::ResolverOpts,
     error::{ResolveError, ResolveErrorKind},
 };
+use trust_dns_resolver::{
+    config::{NameServerConfigGroup, ResolverConfig},
+    lookup::SrvLookup,
+    lookup_ip::LookupIp,
+    proto::{
+        error::ProtoErrorKind,
+        rr::{self as proto, rdata},
+    },
+    system_conf, AsyncResolver, TokioAsyncResolver,
+};
 
 #[derive(Clone)]
 pub struct Resolver {
-    dns: TokioAsyncResolver,
+    dns: Dns,
+    /// Resolvers that are used instead of `dns` for names with a given
+    /// suffix. The first matching suffix is used.
+    suffixes: Arc<Vec<(Suffix, Dns)>>,
+}
+
+/// Overrides the nameservers and search domains read from the system's
+/// configuration (i.e. `/etc/resolv.conf`).
+///
+/// When either list is empty, the system's configuration is used for it.
+#[derive(Clone, Debug, Default)]
+pub struct Servers {
+    pub nameservers: Vec<net::SocketAddr>,
+    pub search: Vec<Name>,
+    pub transport: Transport,
+}
+
+/// How the resolver communicates with its nameservers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Transport {
+    /// Plain DNS over UDP, retried over TCP.
+    Plain,
+
+    /// Encrypted DNS (DNS-over-TLS or DNS-over-HTTPS).
+    ///
+    /// Connections to nameservers are reused across lookups.
+    Encrypted {
+        protocol: EncryptedProtocol,
+        /// The name used to verify the nameservers' certificates.
+        tls_name: Name,
+        /// When set, lookups that cannot connect to the nameservers over the
+        /// encrypted transport are retried over plain DNS on this port.
+        /// Lookups that fail for any other reason, including invalid
+        /// nameserver certificates, are not retried.
+        plain_fallback_port: Option<u16>,
+    },
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EncryptedProtocol {
+    Tls,
+    Https,
+}
+
+/// A resolver, with an optional plain DNS resolver that is used when the
+/// primary resolver cannot connect to its nameservers.
+#[derive(Clone)]
+struct Dns {
+    resolver: TokioAsyncResolver,
+    fallback: Option<Fallback>,
+}
+
+/// A plain DNS resolver for the same nameservers as an encrypted resolver.
+#[derive(Clone)]
+struct Fallback {
+    resolver: TokioAsyncResolver,
+    /// The encrypted resolver's nameservers, which are probed when its
+    /// lookups time out.
+    nameservers: Arc<[net::SocketAddr]>,
+    connect_timeout: time::Duration,
 }
 
 pub trait ConfigureResolver {
@@ -28,6 +94,14 @@ pub trait ConfigureResolver {
 #[error("invalid SRV record {:?}", self.0)]
 struct InvalidSrv(rdata::SRV);
 
+/// Applies `c` to `opts`. Trust-DNS's caching is disabled unless `c`
+/// configures it explicitly.
+fn configure<C: ConfigureResolver>(mut opts: ResolverOpts, c: &C) -> ResolverOpts {
+    opts.cache_size = 0;
+    c.configure_resolver(&mut opts);
+    opts
+}
+
 impl Resolver {
     /// Construct a new `Resolver` from environment variables and system
     /// configuration.
@@ -39,20 +113,88 @@ impl Resolver {
     ///
     /// TODO: This should be infallible like it is in the `domain` crate.
     pub fn from_system_config_with<C: ConfigureResolver>(c: &C) -> Result<Self, ResolveError> {
-        let (config, mut opts) = system_conf::read_system_conf()?;
-        c.configure_resolver(&mut opts);
+        let (config, opts) = system_conf::read_system_conf()?;
+        let opts = configure(opts, c);
+        trace!("DNS config: {:?}", &config);
+        trace!("DNS opts: {:?}", &opts);
+        Ok(Self::with_opts(config, opts))
+    }
+
+    /// Construct a new `Resolver` that uses the given nameservers and search
+    /// domains, falling back to the system configuration for those that are
+    /// not specified.
+    pub fn from_servers_with<C: ConfigureResolver>(
+        servers: &Servers,
+        c: &C,
+    ) -> Result<Self, ResolveError> {
+        let (system, opts) = match system_conf::read_system_conf() {
+            Ok(conf) => conf,
+            // The system configuration isn't needed if nameservers are
+            // configured explicitly.
+            Err(error) if !servers.nameservers.is_empty() => {
+                debug!(%error, "Failed to read system DNS config");
+                Default::default()
+            }
+            Err(error) => return Err(error.into()),
+        };
+        let opts = configure(opts, c);
+        let config = servers.resolver_config(&system)?;
         trace!("DNS config: {:?}", &config);
         trace!("DNS opts: {:?}", &opts);
-        Ok(Self::new(config, opts))
+        let fallback = servers.plain_fallback(&system).map(|config| {
+            trace!("DNS fallback config: {:?}", &config);
+            Fallback {
+                resolver: Self::async_resolver(config, opts),
+                nameservers: servers.nameservers.clone().into(),
+                connect_timeout: opts.timeout,
+            }
+        });
+        Ok(Resolver {
+            dns: Dns {
+                resolver: Self::async_resolver(config, opts),
+                fallback,
+            },
+            suffixes: Default::default(),
+        })
     }
 
     pub fn new(config: ResolverConfig, mut opts: ResolverOpts) -> Self {
         // Disable Trust-DNS's caching.
         opts.cache_size = 0;
+        Self::with_opts(config, opts)
+    }
+
+    fn with_opts(config: ResolverConfig, opts: ResolverOpts) -> Self {
+        Resolver {
+            dns: Dns {
+                resolver: Self::async_resolver(config, opts),
+                fallback: None,
+            },
+            suffixes: Default::default(),
+        }
+    }
+
+    /// Resolves names with the given suffix with another resolver.
+    ///
+    /// Suffixes are matched in the order they are added, so more specific
+    /// suffixes should be added first.
+    pub fn with_suffix(mut self, suffix: Suffix, resolver: Resolver) -> Self {
+        Arc::make_mut(&mut self.suffixes).push((suffix, resolver.dns));
+        self
+    }
+
+    fn async_resolver(config: ResolverConfig, opts: ResolverOpts) -> TokioAsyncResolver {
         // This function is synchronous, but needs to be called within the Tokio
         // 0.2 runtime context, since it gets a handle.
-        let dns = AsyncResolver::tokio(config, opts).expect("system DNS config must be valid");
-        Resolver { dns }
+        AsyncResolver::tokio(config, opts).expect("system DNS config must be valid")
+    }
+
+    fn resolver_for(&self, name: &Name) -> &Dns {
+        self.suffixes
+            .iter()
+            .find(|(sfx, _)| sfx.contains(name))
+            .map(|(_, dns)| dns)
+            .unwrap_or(&self.dns)
     }
 
     /// Resolves a name to a set of addresses, preferring SRV records to normal A
@@ -81,7 +223,7 @@ impl Resolver {
         name: &Name,
     ) -> Result<(Vec<net::IpAddr>, time::Sleep), ResolveError> {
         debug!(%name, "resolve_a");
-        let lookup = self.dns.lookup_ip(name.as_ref()).await?;
+        let lookup = self.resolver_for(name).lookup_ip(name.as_ref()).await?;
         let valid_until = Instant::from_std(lookup.valid_until());
         let ips = lookup.iter().collect::<Vec<_>>();
         Ok((ips, time::sleep_until(valid_until)))
@@ -89,7 +231,7 @@ impl Resolver {
 
     async fn resolve_srv(&self, name: &Name) -> Result<(Vec<net::SocketAddr>, time::Sleep), Error> {
         debug!(%name, "resolve_srv");
-        let srv = self.dns.srv_lookup(name.as_ref()).await?;
+        let srv = self.resolver_for(name).srv_lookup(name.as_ref()).await?;
         let valid_until = Instant::from_std(srv.as_lookup().valid_until());
         let addrs = srv
             .into_iter()
@@ -124,15 +266,238 @@ impl fmt::Debug for Resolver {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Resolver")
             .field("resolver", &"...")
+            .field(
+                "suffixes",
+                &self.suffixes.iter().map(|(s, _)| s).collect::<Vec<_>>(),
+            )
             .finish()
     }
 }
 
+// === impl Dns ===
+
+impl Dns {
+    async fn lookup_ip(&self, name: &str) -> Result<LookupIp, ResolveError> {
+        let error = match self.resolver.lookup_ip(name).await {
+            Ok(lookup) => return Ok(lookup),
+            Err(error) => error,
+        };
+        match self.fallback.as_ref() {
+            Some(fallback) if fallback.should_retry(&error).await => {
+                warn!(%error, "Encrypted DNS lookup failed; retrying with plain DNS");
+                fallback.resolver.lookup_ip(name).await
+            }
+            _ => Err(error),
+        }
+    }
+
+    async fn srv_lookup(&self, name: &str) -> Result<SrvLookup, ResolveError> {
+        let error = match self.resolver.srv_lookup(name).await {
+            Ok(lookup) => return Ok(lookup),
+            Err(error) => error,
+        };
+        match self.fallback.as_ref() {
+            Some(fallback) if fallback.should_retry(&error).await => {
+                warn!(%error, "Encrypted DNS lookup failed; retrying with plain DNS");
+                fallback.resolver.srv_lookup(name).await
+            }
+            _ => Err(error),
+        }
+    }
+}
+
+// === impl Fallback ===
+
+impl Fallback {
+    /// Returns true if the error indicates that a connection to the encrypted
+    /// nameservers was refused or timed out.
+    ///
+    /// Other failures, notably TLS failures, must not be retried over plain
+    /// DNS: otherwise, an attacker that can tamper with the encrypted
+    /// transport could force lookups onto the unauthenticated one. The
+    /// resolver reports some TLS failures as timeouts, so lookups that time
+    /// out are only retried if none of the nameservers accept connections.
+    async fn should_retry(&self, error: &ResolveError) -> bool {
+        if Self::is_refused(error) {
+            return true;
+        }
+        Self::is_timeout(error) && !self.is_reachable().await
+    }
+
+    /// Returns true if the operating system failed to connect to a
+    /// nameserver.
+    ///
+    /// The resolver reports TLS failures as refused connections, but only
+    /// errors from the operating system carry an error code.
+    fn is_refused(error: &ResolveError) -> bool {
+        let io = match error.kind() {
+            ResolveErrorKind::Io(e) => e,
+            ResolveErrorKind::Proto(e) => match e.kind() {
+                ProtoErrorKind::Io(e) => e,
+                _ => return false,
+            },
+            _ => return false,
+        };
+        io.raw_os_error().is_some()
+            && matches!(
+                io.kind(),
+                io::ErrorKind::ConnectionRefused
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::NotConnected
+                    | io::ErrorKind::TimedOut
+            )
+    }
+
+    fn is_timeout(error: &ResolveError) -> bool {
+        match error.kind() {
+            ResolveErrorKind::Timeout => true,
+            ResolveErrorKind::Proto(e) => matches!(e.kind(), ProtoErrorKind::Timeout),
+            _ => false,
+        }
+    }
+
+    /// Returns true if any of the encrypted nameservers accepts a TCP
+    /// connection.
+    async fn is_reachable(&self) -> bool {
+        for &addr in self.nameservers.iter() {
+            let connect = tokio::net::TcpStream::connect(addr);
+            if let Ok(Ok(_)) = time::timeout(self.connect_timeout, connect).await {
+                debug!(%addr, "Encrypted DNS nameserver is reachable");
+                return true;
+            }
+        }
+        false
+    }
+}
+
+// === impl Servers ===
+
+impl Servers {
+    fn resolver_config(&self, system: &ResolverConfig) -> Result<ResolverConfig, ResolveError> {
+        let search = if self.search.is_empty() {
+            system.search().to_vec()
+        } else {
+            self.search
+                .iter()
+                .map(|n| proto::Name::from_ascii(n.as_ref()))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let nameservers = match self.transport {
+            Transport::Plain if self.nameservers.is_empty() => {
+                system.name_servers().to_vec().into()
+            }
+            Transport::Plain => Self::group(&self.nameservers, |ip, port| {
+                NameServerConfigGroup::from_ips_clear(ip, port, true)
+            }),
+            Transport::Encrypted {
+                protocol,
+                ref tls_name,
+                ..
+            } => Self::group(&self.nameservers, |ip, port| {
+                let tls_name = tls_name.without_trailing_dot().to_string();
+                match protocol {
+                    EncryptedProtocol::Tls => {
+                        NameServerConfigGroup::from_ips_tls(ip, port, tls_name, true)
+                    }
+                    EncryptedProtocol::Https => {
+                        NameServerConfigGroup::from_ips_https(ip, port, tls_name, true)
+                    }
+                }
+            }),
+        };
+
+        Ok(ResolverConfig::from_parts(
+            system.domain().cloned(),
+            search,
+            nameservers,
+        ))
+    }
+
+    /// Configures plain DNS to the same nameservers, if an encrypted transport
+    /// is configured to fall back to it.
+    fn plain_fallback(&self, system: &ResolverConfig) -> Option<ResolverConfig> {
+        if let Transport::Encrypted {
+            plain_fallback_port: Some(port),
+            ..
+        } = self.transport
+        {
+            let servers = Servers {
+                nameservers: self
+                    .nameservers
+                    .iter()
+                    .map(|addr| net::SocketAddr::new(addr.ip(), port))
+                    .collect(),
+                search: self.search.clone(),
+                transport: Transport::Plain,
+            };
+            return servers.resolver_config(system).ok();
+        }
+        None
+    }
+
+    fn group(
+        addrs: &[net::SocketAddr],
+        mk: impl Fn(&[net::IpAddr], u16) -> NameServerConfigGroup,
+    ) -> NameServerConfigGroup {
+        addrs
+            .iter()
+            .fold(NameServerConfigGroup::new(), |mut group, addr| {
+                group.merge(mk(&[addr.ip()], addr.port()));
+                group
+            })
+    }
+}
+
+// === impl Transport ===
+
+impl Default for Transport {
+    fn default() -> Self {
+        Self::Plain
+    }
+}
+
+impl Transport {
+    /// The port used by nameservers that are configured without one.
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Self::Plain => 53,
+            Self::Encrypted {
+                protocol: EncryptedProtocol::Tls,
+                ..
+            } => 853,
+            Self::Encrypted {
+                protocol: EncryptedProtocol::Https,
+                ..
+            } => 443,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{Name, Suffix};
+    use super::*;
     use std::str::FromStr;
 
+    #[test]
+    fn cache_disabled_unless_configured() {
+        struct CacheSize(Option<usize>);
+
+        impl ConfigureResolver for CacheSize {
+            fn configure_resolver(&self, opts: &mut ResolverOpts) {
+                if let Some(size) = self.0 {
+                    opts.cache_size = size;
+                }
+            }
+        }
+
+        let defaults = ResolverOpts::default();
+        assert_ne!(defaults.cache_size, 0, "trust-dns caches by default");
+        assert_eq!(configure(defaults, &CacheSize(None)).cache_size, 0);
+        assert_eq!(configure(defaults, &CacheSize(Some(128))).cache_size, 128);
+    }
+
     #[test]
     fn test_dns_name_parsing() {
         // Make sure `dns::Name`'s validation isn't too strict. It is
@@ -234,6 +599,159 @@ mod tests {
 
         assert!(Suffix::from_str("").is_err(), "suffix must not be empty");
     }
+
+    fn encrypted(addr: net::SocketAddr) -> Servers {
+        Servers {
+            nameservers: vec![addr],
+            search: Vec::new(),
+            transport: Transport::Encrypted {
+                protocol: EncryptedProtocol::Tls,
+                tls_name: Name::from_str("dns.example.com").unwrap(),
+                plain_fallback_port: Some(53),
+            },
+        }
+    }
+
+    #[test]
+    fn classifies_connection_errors() {
+        use trust_dns_resolver::proto::error::ProtoError;
+
+        for error in vec![
+            ResolveError::from(ResolveErrorKind::Timeout),
+            ResolveError::from(ProtoError::from(ProtoErrorKind::Timeout)),
+        ] {
+            assert!(Fallback::is_timeout(&error), "{} must time out", error);
+            assert!(
+                !Fallback::is_refused(&error),
+                "{} must not be refused",
+                error
+            );
+        }
+
+        // The resolver reports TLS failures as refused connections without
+        // an OS error code.
+        let tls = || io::Error::new(io::ErrorKind::ConnectionRefused, "tls error: bad cert");
+        for error in vec![
+            ResolveError::from(tls()),
+            ResolveError::from(ProtoError::from(tls())),
+            ResolveError::from(ProtoError::from("bad response")),
+            ResolveError::from("no records"),
+        ] {
+            assert!(
+                !Fallback::is_refused(&error),
+                "{} must not be refused",
+                error
+            );
+            assert!(!Fallback::is_timeout(&error), "{} must not time out", error);
+        }
+    }
+
+    #[test]
+    fn encrypted_config() {
+        use trust_dns_resolver::config::Protocol;
+
+        let addr = net::SocketAddr::from(([192, 0, 2, 53], 853));
+        let servers = encrypted(addr);
+        let system = ResolverConfig::new();
+
+        let config = servers.resolver_config(&system).unwrap();
+        assert_eq!(config.name_servers().len(), 1);
+        let ns = &config.name_servers()[0];
+        assert_eq!(ns.socket_addr, addr);
+        assert_eq!(ns.protocol, Protocol::Tls);
+        assert_eq!(ns.tls_dns_name.as_deref(), Some("dns.example.com"));
+
+        let fallback = servers.plain_fallback(&system).expect("must fall back");
+        let addrs = fallback
+            .name_servers()
+            .iter()
+            .map(|ns| (ns.socket_addr, ns.protocol))
+            .collect::<Vec<_>>();
+        let plain = net::SocketAddr::from(([192, 0, 2, 53], 53));
+        assert_eq!(addrs, vec![(plain, Protocol::Udp), (plain, Protocol::Tcp)]);
+
+        let servers = Servers {
+            transport: Transport::Encrypted {
+                protocol: EncryptedProtocol::Https,
+                tls_name: Name::from_str("dns.example.com").unwrap(),
+                plain_fallback_port: None,
+            },
+            ..servers
+        };
+        assert!(servers.plain_fallback(&system).is_none());
+    }
+
+    /// Builds a resolver that uses DNS-over-TLS with `tls` and falls back to a
+    /// plain nameserver, returning the socket that receives the fallback's
+    /// queries.
+    async fn with_fallback(tls: net::SocketAddr) -> (Dns, tokio::net::UdpSocket) {
+        let plain = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut servers = encrypted(tls);
+        servers.transport = Transport::Encrypted {
+            protocol: EncryptedProtocol::Tls,
+            tls_name: Name::from_str("dns.example.com").unwrap(),
+            plain_fallback_port: Some(plain.local_addr().unwrap().port()),
+        };
+        let system = ResolverConfig::new();
+        let mut opts = ResolverOpts::default();
+        opts.timeout = std::time::Duration::from_secs(1);
+        opts.attempts = 0;
+        opts.cache_size = 0;
+        let dns = Dns {
+            resolver: Resolver::async_resolver(servers.resolver_config(&system).unwrap(), opts),
+            fallback: Some(Fallback {
+                resolver: Resolver::async_resolver(servers.plain_fallback(&system).unwrap(), opts),
+                nameservers: vec![tls].into(),
+                connect_timeout: opts.timeout,
+            }),
+        };
+        (dns, plain)
+    }
+
+    #[tokio::test]
+    async fn falls_back_when_refused() {
+        // Reserve a port on which connections are refused.
+        let tls = {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap()
+        };
+        let (dns, plain) = with_fallback(tls).await;
+
+        let lookup = tokio::spawn(async move { dns.lookup_ip("foo.example.com.").await });
+        let mut buf = [0u8; 512];
+        tokio::time::timeout(std::time::Duration::from_secs(5), plain.recv_from(&mut buf))
+            .await
+            .expect("the plain nameserver must be queried")
+            .unwrap();
+        lookup.abort();
+    }
+
+    #[tokio::test]
+    async fn does_not_fall_back_on_tls_failure() {
+        use tokio::io::AsyncWriteExt;
+
+        // A nameserver that fails every TLS handshake.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let tls = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut io, _) = listener.accept().await.unwrap();
+                let _ = io.write_all(b"this is not a TLS handshake").await;
+            }
+        });
+        let (dns, plain) = with_fallback(tls).await;
+
+        let error = dns
+            .lookup_ip("foo.example.com.")
+            .await
+            .expect_err("lookup must fail");
+        assert!(!Fallback::is_refused(&error), "{}", error);
+        let mut buf = [0u8; 512];
+        assert!(
+            plain.try_recv_from(&mut buf).is_err(),
+            "the plain nameserver must not be queried"
+        );
+    }
 }
 
 #[cfg(fuzzing)]
@@ -0,0 +1,89 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::{self, Instant, Sleep};
+
+/// Indicates that no data was copied in either direction for the idle
+/// timeout.
+#[derive(Copy, Clone, Debug)]
+pub struct IdleTimeout(Duration);
+
+/// Tracks how long a duplex has been idle.
+pub(crate) struct Idle {
+    timeout: Duration,
+    sleep: Pin<Box<Sleep>>,
+}
+
+// === impl IdleTimeout ===
+
+impl IdleTimeout {
+    /// Returns true if the given IO error was caused by an idle timeout.
+    pub fn is(err: &std::io::Error) -> bool {
+        err.get_ref().map_or(false, |e| e.is::<Self>())
+    }
+}
+
+impl std::fmt::Display for IdleTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "connection idle for {:?}", self.0)
+    }
+}
+
+impl std::error::Error for IdleTimeout {}
+
+// === impl Idle ===
+
+impl Idle {
+    pub(crate) fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            sleep: Box::pin(time::sleep(timeout)),
+        }
+    }
+
+    /// Resets the timer if data was copied since the last poll and fails if
+    /// the timeout has elapsed.
+    pub(crate) fn poll_idle(
+        &mut self,
+        progressed: bool,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::io::Error> {
+        if progressed {
+            self.sleep.as_mut().reset(Instant::now() + self.timeout);
+        }
+        futures::ready!(self.sleep.as_mut().poll(cx));
+        Poll::Ready(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            IdleTimeout(self.timeout),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Duplex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn copying_resets_timeout() {
+        let (in_io, mut client) = tokio::io::duplex(1024);
+        let (out_io, mut server) = tokio::io::duplex(1024);
+        let start = Instant::now();
+        let duplex =
+            tokio::spawn(Duplex::new(in_io, out_io).with_idle_timeout(Duration::from_secs(10)));
+
+        time::sleep(Duration::from_secs(5)).await;
+        client.write_all(b"hello").await.unwrap();
+        let mut buf = [0u8; 5];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+
+        let err = duplex.await.unwrap().expect_err("duplex must time out");
+        assert!(IdleTimeout::is(&err), "{:?}", err);
+        assert_eq!(start.elapsed(), Duration::from_secs(15));
+    }
+}
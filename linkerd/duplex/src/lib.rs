@@ -9,14 +9,21 @@ use futures::ready;
 use linkerd_io::{self as io, AsyncRead, AsyncWrite};
 use pin_project::pin_project;
 use std::task::{Context, Poll};
-use std::{future::Future, pin::Pin};
+use std::{future::Future, pin::Pin, time::Duration};
 use tracing::{error, trace};
 
+mod idle;
+mod rate_limit;
+
+use self::{idle::Idle, rate_limit::Limiter};
+pub use self::{idle::IdleTimeout, rate_limit::RateLimit};
+
 /// A future piping data bi-directionally to In and Out.
 #[pin_project]
 pub struct Duplex<In, Out> {
     half_in: HalfDuplex<In>,
     half_out: HalfDuplex<Out>,
+    idle: Option<Idle>,
 }
 
 #[pin_project]
@@ -28,6 +35,9 @@ struct HalfDuplex<T> {
     io: T,
     direction: &'static str,
     flushing: bool,
+    limiter: Option<Limiter>,
+    // Set when data is read, so that idle connections may be detected.
+    progressed: bool,
 }
 
 /// A buffer used to copy bytes from one IO to another.
@@ -64,8 +74,25 @@ where
         Duplex {
             half_in: HalfDuplex::new(in_io, "client->server"),
             half_out: HalfDuplex::new(out_io, "server->client"),
+            idle: None,
         }
     }
+
+    /// Limits the rate at which data is copied.
+    ///
+    /// Bytes copied in either direction are counted against each limit.
+    pub fn with_rate_limits(mut self, limits: Vec<RateLimit>) -> Self {
+        self.half_in.limiter = Limiter::new(limits.clone());
+        self.half_out.limiter = Limiter::new(limits);
+        self
+    }
+
+    /// Fails with an [`IdleTimeout`] if no data is copied in either direction
+    /// for `timeout`.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle = Some(Idle::new(timeout));
+        self
+    }
 }
 
 impl<In, Out> Future for Duplex<In, Out>
@@ -84,10 +111,19 @@ where
         let _ = this.half_in.copy_into(&mut this.half_out, cx)?;
         let _ = this.half_out.copy_into(&mut this.half_in, cx)?;
         if this.half_in.is_done() && this.half_out.is_done() {
-            Poll::Ready(Ok(()))
-        } else {
-            Poll::Pending
+            return Poll::Ready(Ok(()));
+        }
+
+        if let Some(idle) = this.idle.as_mut() {
+            // Both halves must be checked so that neither retains stale
+            // progress.
+            let progressed = this.half_in.take_progress() | this.half_out.take_progress();
+            let err = ready!(idle.poll_idle(progressed, cx));
+            trace!("idle");
+            return Poll::Ready(Err(err));
         }
+
+        Poll::Pending
     }
 }
 
@@ -102,6 +138,8 @@ where
             io,
             direction,
             flushing: false,
+            limiter: None,
+            progressed: false,
         }
     }
 
@@ -213,12 +251,28 @@ where
             }
 
             buf.reset();
-            trace!(direction = %self.direction, "reading");
-            let sz = ready!(io::poll_read_buf(Pin::new(&mut self.io), cx, buf))?;
+
+            // If the copy is rate limited, wait until data may be read and
+            // read no more than is permitted.
+            let sz = if let Some(limiter) = self.limiter.as_mut() {
+                let permitted = ready!(limiter.poll_acquire(cx));
+                trace!(direction = %self.direction, permitted, "reading");
+                let sz = ready!(io::poll_read_buf(
+                    Pin::new(&mut self.io),
+                    cx,
+                    &mut buf.limit(permitted)
+                ))?;
+                limiter.consume(sz);
+                sz
+            } else {
+                trace!(direction = %self.direction, "reading");
+                ready!(io::poll_read_buf(Pin::new(&mut self.io), cx, buf))?
+            };
             trace!(direction = %self.direction, "read {}B", sz);
 
             // If data was read, return the number of bytes read.
             if sz > 0 {
+                self.progressed = true;
                 return Poll::Ready(Ok(Buffered::Read(sz)));
             }
         }
@@ -278,6 +332,10 @@ where
     fn is_done(&self) -> bool {
         self.is_shutdown
     }
+
+    fn take_progress(&mut self) -> bool {
+        std::mem::take(&mut self.progressed)
+    }
 }
 
 fn write_zero() -> io::Error {
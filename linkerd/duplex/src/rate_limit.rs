@@ -0,0 +1,154 @@
+use parking_lot::Mutex;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::{self, Instant, Sleep};
+
+/// A token bucket that limits the rate at which bytes are copied.
+///
+/// Clones share the same bucket, so a single `RateLimit` may be applied to
+/// many connections to limit their combined rate.
+#[derive(Clone, Debug)]
+pub struct RateLimit(Arc<Mutex<Bucket>>);
+
+/// Throttles reads on one half of a duplex according to a set of rate limits.
+pub(crate) struct Limiter {
+    limits: Vec<RateLimit>,
+    sleep: Pin<Box<Sleep>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    bytes_per_second: f64,
+    capacity: f64,
+    // May be negative when a shared bucket is overdrawn by concurrent reads.
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Reads wait until at least this many bytes (or the bucket's capacity, if
+/// it is smaller) may be read, to avoid many tiny reads on slow limits.
+const MIN_READ: f64 = 4096.0;
+
+// === impl RateLimit ===
+
+impl RateLimit {
+    /// Limits copying to `bytes_per_second`, permitting bursts of up to one
+    /// second's worth of bytes.
+    pub fn new(bytes_per_second: u64) -> Self {
+        assert!(bytes_per_second > 0, "rate limit must be positive");
+        let rate = bytes_per_second as f64;
+        Self(Arc::new(Mutex::new(Bucket {
+            bytes_per_second: rate,
+            capacity: rate,
+            tokens: rate,
+            refilled_at: Instant::now(),
+        })))
+    }
+
+    /// Returns the number of bytes that may be read now, or the time to wait
+    /// before a read is worthwhile.
+    fn acquire(&self, now: Instant) -> Result<u64, Duration> {
+        let mut bucket = self.0.lock();
+        bucket.refill(now);
+        let min = MIN_READ.min(bucket.capacity);
+        if bucket.tokens >= min {
+            return Ok(bucket.tokens as u64);
+        }
+        let wait = (min - bucket.tokens) / bucket.bytes_per_second;
+        Err(Duration::from_secs_f64(wait))
+    }
+
+    fn consume(&self, bytes: usize) {
+        self.0.lock().tokens -= bytes as f64;
+    }
+}
+
+// === impl Bucket ===
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.refilled_at = now;
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * self.bytes_per_second).min(self.capacity);
+    }
+}
+
+// === impl Limiter ===
+
+impl Limiter {
+    pub(crate) fn new(limits: Vec<RateLimit>) -> Option<Self> {
+        if limits.is_empty() {
+            return None;
+        }
+        Some(Self {
+            limits,
+            sleep: Box::pin(time::sleep(Duration::from_secs(0))),
+        })
+    }
+
+    /// Polls until data may be read, returning the number of bytes that may
+    /// be read.
+    pub(crate) fn poll_acquire(&mut self, cx: &mut Context<'_>) -> Poll<usize> {
+        loop {
+            let now = Instant::now();
+            let mut permitted = u64::MAX;
+            let mut wait = None::<Duration>;
+            for limit in &self.limits {
+                match limit.acquire(now) {
+                    Ok(n) => permitted = permitted.min(n),
+                    Err(w) => wait = Some(wait.map_or(w, |wait| wait.max(w))),
+                }
+            }
+
+            let wait = match wait {
+                None => return Poll::Ready(permitted.min(usize::MAX as u64) as usize),
+                Some(wait) => wait,
+            };
+            self.sleep.as_mut().reset(now + wait);
+            futures::ready!(self.sleep.as_mut().poll(cx));
+        }
+    }
+
+    pub(crate) fn consume(&self, bytes: usize) {
+        for limit in &self.limits {
+            limit.consume(bytes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn throttles_reads() {
+        let limit = RateLimit::new(64 * 1024);
+        let mut limiter = Limiter::new(vec![limit.clone()]).unwrap();
+
+        // The bucket starts full, permitting a burst.
+        let n = futures::future::poll_fn(|cx| limiter.poll_acquire(cx)).await;
+        assert_eq!(n, 64 * 1024);
+        limiter.consume(n);
+
+        // Once drained, the limiter waits for the minimum read size to refill.
+        let start = Instant::now();
+        let n = futures::future::poll_fn(|cx| limiter.poll_acquire(cx)).await;
+        assert!(n >= 4 * 1024);
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed >= Duration::from_millis(62) && elapsed <= Duration::from_millis(64),
+            "{:?}",
+            elapsed
+        );
+
+        // Clones share the bucket.
+        limit.consume(n);
+        assert!(limit.acquire(Instant::now()).is_err());
+    }
+}
@@ -0,0 +1,418 @@
+use crate::Metrics;
+use bytes::Bytes;
+use http::{
+    header::{self, HeaderName, HeaderValue},
+    HeaderMap,
+};
+use linkerd_http_box::BoxBody;
+use parking_lot::Mutex;
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::Infallible,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::Instant;
+use tracing::trace;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Config {
+    /// The maximum total size of the responses that are stored.
+    pub max_bytes: usize,
+
+    /// The largest response body that is stored.
+    pub max_body_bytes: usize,
+}
+
+/// A store of responses, bounded by their total size.
+///
+/// When the cache is full, the least-recently-used responses are evicted to
+/// make room for new ones.
+#[derive(Clone, Debug)]
+pub struct Cache {
+    config: Config,
+    entries: Arc<Mutex<Entries>>,
+    metrics: Metrics,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Key {
+    authority: Option<http::uri::Authority>,
+    path: String,
+}
+
+/// The request header values that a response varies on.
+pub(crate) type Vary = Vec<(HeaderName, Option<HeaderValue>)>;
+
+/// A complete response.
+#[derive(Debug)]
+pub(crate) struct Stored {
+    pub(crate) status: http::StatusCode,
+    pub(crate) version: http::Version,
+    pub(crate) headers: HeaderMap,
+    pub(crate) body: Bytes,
+    pub(crate) trailers: Option<HeaderMap>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    by_key: HashMap<Key, Entry>,
+    /// Keys ordered by when they were last used.
+    lru: BTreeMap<u64, Key>,
+    bytes: usize,
+    next_seq: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    seq: u64,
+    size: usize,
+    stored_at: Instant,
+    expires: Instant,
+    /// The response's age when it was stored.
+    age: Duration,
+    vary: Vary,
+    response: Arc<Stored>,
+}
+
+/// Replays a stored response's body and trailers.
+#[derive(Debug)]
+struct Replay {
+    data: Option<Bytes>,
+    trailers: Option<HeaderMap>,
+}
+
+// === impl Cache ===
+
+impl Cache {
+    pub fn new(config: Config, metrics: Metrics) -> Self {
+        Self {
+            config,
+            entries: Default::default(),
+            metrics,
+        }
+    }
+
+    pub(crate) fn max_body_bytes(&self) -> usize {
+        self.config.max_body_bytes
+    }
+
+    /// Returns a replay of the stored response for `key`, if it is fresh and
+    /// was stored for a request with the same `headers`.
+    pub(crate) fn get(&self, key: &Key, headers: &HeaderMap) -> Option<http::Response<BoxBody>> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock();
+        let Entries {
+            by_key,
+            lru,
+            bytes,
+            next_seq,
+        } = &mut *entries;
+
+        let replay = match by_key.get_mut(key) {
+            Some(entry) if entry.expires <= now => {
+                lru.remove(&entry.seq);
+                *bytes -= entry.size;
+                by_key.remove(key);
+                self.metrics.removed();
+                None
+            }
+            Some(entry) if entry.matches(headers) => {
+                lru.remove(&entry.seq);
+                entry.seq = *next_seq;
+                *next_seq += 1;
+                lru.insert(entry.seq, key.clone());
+                let age = entry.age + now.saturating_duration_since(entry.stored_at);
+                Some((entry.response.clone(), age))
+            }
+            _ => None,
+        };
+        drop(entries);
+
+        match replay {
+            Some((stored, age)) => {
+                trace!(?key, ?age, "Replaying response");
+                self.metrics.hit();
+                Some(stored.replay(age))
+            }
+            None => {
+                self.metrics.miss();
+                None
+            }
+        }
+    }
+
+    /// Stores a response for `key`, replacing any response that is already
+    /// stored for it.
+    pub(crate) fn insert(
+        &self,
+        key: Key,
+        vary: Vary,
+        response: Stored,
+        freshness: Duration,
+        age: Duration,
+    ) {
+        let ttl = match freshness.checked_sub(age) {
+            Some(ttl) if ttl > Duration::ZERO => ttl,
+            _ => return,
+        };
+        let size = response.size();
+        if size > self.config.max_bytes {
+            trace!(?key, size, "Response too large to store");
+            return;
+        }
+
+        let now = Instant::now();
+        let mut entries = self.entries.lock();
+        let Entries {
+            by_key,
+            lru,
+            bytes,
+            next_seq,
+        } = &mut *entries;
+
+        if let Some(entry) = by_key.remove(&key) {
+            lru.remove(&entry.seq);
+            *bytes -= entry.size;
+            self.metrics.removed();
+        }
+
+        // Evict the least-recently-used entries until there is room.
+        while *bytes + size > self.config.max_bytes {
+            let seq = match lru.keys().next() {
+                Some(seq) => *seq,
+                None => break,
+            };
+            let oldest = lru.remove(&seq).expect("key must exist");
+            if let Some(entry) = by_key.remove(&oldest) {
+                *bytes -= entry.size;
+                if entry.expires <= now {
+                    self.metrics.removed();
+                } else {
+                    trace!(key = ?oldest, "Evicting response");
+                    self.metrics.evicted();
+                }
+            }
+        }
+
+        let seq = *next_seq;
+        *next_seq += 1;
+        *bytes += size;
+        lru.insert(seq, key.clone());
+        by_key.insert(
+            key,
+            Entry {
+                seq,
+                size,
+                stored_at: now,
+                expires: now + ttl,
+                age,
+                vary,
+                response: Arc::new(response),
+            },
+        );
+        self.metrics.stored();
+    }
+}
+
+// === impl Key ===
+
+impl Key {
+    pub(crate) fn from_request<B>(req: &http::Request<B>) -> Self {
+        Self {
+            authority: req.uri().authority().cloned(),
+            path: req
+                .uri()
+                .path_and_query()
+                .map(|pq| pq.as_str().to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+// === impl Entry ===
+
+impl Entry {
+    fn matches(&self, headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| headers.get(name) == value.as_ref())
+    }
+}
+
+// === impl Stored ===
+
+impl Stored {
+    /// Approximates the memory used by the response.
+    fn size(&self) -> usize {
+        fn headers_size(headers: &HeaderMap) -> usize {
+            headers
+                .iter()
+                .map(|(k, v)| k.as_str().len() + v.len())
+                .sum()
+        }
+
+        self.body.len()
+            + headers_size(&self.headers)
+            + self.trailers.as_ref().map(headers_size).unwrap_or(0)
+    }
+
+    fn replay(&self, age: Duration) -> http::Response<BoxBody> {
+        let body = Replay {
+            data: Some(self.body.clone()).filter(|b| !b.is_empty()),
+            trailers: self.trailers.clone(),
+        };
+        let mut rsp = http::Response::new(BoxBody::new(body));
+        *rsp.status_mut() = self.status;
+        *rsp.version_mut() = self.version;
+        *rsp.headers_mut() = self.headers.clone();
+        rsp.headers_mut()
+            .insert(header::AGE, HeaderValue::from(age.as_secs()));
+        rsp
+    }
+}
+
+// === impl Replay ===
+
+impl http_body::Body for Replay {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Poll::Ready(self.get_mut().data.take().map(Ok))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(self.get_mut().trailers.take()))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.data.is_none() && self.trailers.is_none()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        let len = self.data.as_ref().map(|d| d.len()).unwrap_or(0);
+        http_body::SizeHint::with_exact(len as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(path: &'static str) -> Key {
+        let req = http::Request::builder()
+            .uri(format!("http://example.com{}", path))
+            .body(())
+            .unwrap();
+        Key::from_request(&req)
+    }
+
+    fn stored(body: &'static str) -> Stored {
+        Stored {
+            status: http::StatusCode::OK,
+            version: http::Version::HTTP_11,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(body.as_bytes()),
+            trailers: None,
+        }
+    }
+
+    fn cache(max_bytes: usize) -> Cache {
+        Cache::new(
+            Config {
+                max_bytes,
+                max_body_bytes: 1024,
+            },
+            Metrics::default(),
+        )
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn expires_entries() {
+        let cache = cache(1024);
+        let headers = HeaderMap::new();
+        assert!(cache.get(&key("/a"), &headers).is_none());
+
+        cache.insert(
+            key("/a"),
+            Vary::default(),
+            stored("a"),
+            Duration::from_secs(10),
+            Duration::from_secs(2),
+        );
+        tokio::time::advance(Duration::from_secs(3)).await;
+        let rsp = cache.get(&key("/a"), &headers).expect("must be cached");
+        assert_eq!(rsp.status(), http::StatusCode::OK);
+        assert_eq!(rsp.headers()[header::AGE], "5");
+        let body = hyper::body::to_bytes(rsp.into_body()).await.unwrap();
+        assert_eq!(body, "a");
+
+        // The response's initial age counts against its freshness.
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert!(cache.get(&key("/a"), &headers).is_none());
+        let entries = cache.entries.lock();
+        assert!(entries.by_key.is_empty());
+        assert!(entries.lru.is_empty());
+        assert_eq!(entries.bytes, 0);
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn evicts_least_recently_used() {
+        let cache = cache(3);
+        let headers = HeaderMap::new();
+        let ttl = Duration::from_secs(10);
+        cache.insert(key("/a"), Vary::default(), stored("a"), ttl, Duration::ZERO);
+        cache.insert(key("/b"), Vary::default(), stored("b"), ttl, Duration::ZERO);
+        cache.insert(key("/c"), Vary::default(), stored("c"), ttl, Duration::ZERO);
+        assert!(cache.get(&key("/a"), &headers).is_some());
+
+        cache.insert(key("/d"), Vary::default(), stored("d"), ttl, Duration::ZERO);
+        assert!(cache.get(&key("/b"), &headers).is_none());
+        assert!(cache.get(&key("/a"), &headers).is_some());
+        assert!(cache.get(&key("/c"), &headers).is_some());
+        assert!(cache.get(&key("/d"), &headers).is_some());
+
+        // Responses larger than the cache are not stored.
+        cache.insert(
+            key("/e"),
+            Vary::default(),
+            stored("eeee"),
+            ttl,
+            Duration::ZERO,
+        );
+        assert!(cache.get(&key("/e"), &headers).is_none());
+        assert_eq!(cache.entries.lock().bytes, 3);
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn matches_vary() {
+        let cache = cache(1024);
+        let vary = vec![(
+            header::ACCEPT_ENCODING,
+            Some(HeaderValue::from_static("gzip")),
+        )];
+        cache.insert(
+            key("/a"),
+            vary,
+            stored("a"),
+            Duration::from_secs(10),
+            Duration::ZERO,
+        );
+
+        let mut headers = HeaderMap::new();
+        assert!(cache.get(&key("/a"), &headers).is_none());
+        headers.insert(header::ACCEPT_ENCODING, HeaderValue::from_static("br"));
+        assert!(cache.get(&key("/a"), &headers).is_none());
+        headers.insert(header::ACCEPT_ENCODING, HeaderValue::from_static("gzip"));
+        assert!(cache.get(&key("/a"), &headers).is_some());
+    }
+}
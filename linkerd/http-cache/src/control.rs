@@ -0,0 +1,145 @@
+use http::header::{self, HeaderMap};
+use std::time::Duration;
+
+/// The Cache-Control directives that affect caching.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct CacheControl {
+    pub(crate) no_store: bool,
+    pub(crate) no_cache: bool,
+    pub(crate) private: bool,
+    pub(crate) public: bool,
+    pub(crate) max_age: Option<Duration>,
+    pub(crate) s_maxage: Option<Duration>,
+}
+
+// === impl CacheControl ===
+
+impl CacheControl {
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Self {
+        let mut cc = Self::default();
+        let directives = headers
+            .get_all(header::CACHE_CONTROL)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','));
+        for directive in directives {
+            let mut parts = directive.splitn(2, '=');
+            let name = parts.next().unwrap_or_default().trim();
+            let mut seconds = || {
+                let value = parts.next()?.trim().trim_matches('"');
+                value.parse::<u64>().ok().map(Duration::from_secs)
+            };
+            if name.eq_ignore_ascii_case("no-store") {
+                cc.no_store = true;
+            } else if name.eq_ignore_ascii_case("no-cache") {
+                cc.no_cache = true;
+            } else if name.eq_ignore_ascii_case("private") {
+                cc.private = true;
+            } else if name.eq_ignore_ascii_case("public") {
+                cc.public = true;
+            } else if name.eq_ignore_ascii_case("max-age") {
+                cc.max_age = seconds();
+            } else if name.eq_ignore_ascii_case("s-maxage") {
+                cc.s_maxage = seconds();
+            }
+        }
+
+        // HTTP/1.0 caches honor `Pragma: no-cache` when Cache-Control is not
+        // set.
+        if !headers.contains_key(header::CACHE_CONTROL) {
+            cc.no_cache = headers
+                .get_all(header::PRAGMA)
+                .iter()
+                .any(|v| v.to_str().map(|v| v.contains("no-cache")).unwrap_or(false));
+        }
+
+        cc
+    }
+
+    /// Indicates whether a request may be answered from the cache.
+    pub(crate) fn allows_cached_response(&self) -> bool {
+        !self.no_store && !self.no_cache && self.max_age != Some(Duration::from_secs(0))
+    }
+
+    /// Returns how long a response is fresh in a shared cache, if it may be
+    /// stored.
+    pub(crate) fn shared_freshness(&self, authorized: bool) -> Option<Duration> {
+        if self.no_store || self.no_cache || self.private {
+            return None;
+        }
+        // Responses to authorized requests may only be shared if they are
+        // explicitly marked as shareable.
+        if authorized && !self.public && self.s_maxage.is_none() {
+            return None;
+        }
+        self.s_maxage
+            .or(self.max_age)
+            .filter(|d| *d > Duration::from_secs(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    fn cc(values: &[&'static str]) -> CacheControl {
+        let mut headers = HeaderMap::new();
+        for v in values {
+            headers.append(header::CACHE_CONTROL, HeaderValue::from_static(v));
+        }
+        CacheControl::from_headers(&headers)
+    }
+
+    #[test]
+    fn parses_directives() {
+        assert_eq!(
+            cc(&["public, max-age=60", "S-MAXAGE=\"120\""]),
+            CacheControl {
+                public: true,
+                max_age: Some(Duration::from_secs(60)),
+                s_maxage: Some(Duration::from_secs(120)),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            cc(&["no-store,no-cache, private, max-age=bogus"]),
+            CacheControl {
+                no_store: true,
+                no_cache: true,
+                private: true,
+                ..Default::default()
+            }
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::PRAGMA, HeaderValue::from_static("no-cache"));
+        assert!(CacheControl::from_headers(&headers).no_cache);
+    }
+
+    #[test]
+    fn freshness() {
+        assert_eq!(cc(&[]).shared_freshness(false), None);
+        assert_eq!(
+            cc(&["max-age=60"]).shared_freshness(false),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            cc(&["max-age=60, s-maxage=10"]).shared_freshness(false),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(cc(&["max-age=0"]).shared_freshness(false), None);
+        assert_eq!(cc(&["max-age=60, private"]).shared_freshness(false), None);
+        assert_eq!(cc(&["max-age=60, no-cache"]).shared_freshness(false), None);
+        assert_eq!(cc(&["max-age=60"]).shared_freshness(true), None);
+        assert_eq!(
+            cc(&["max-age=60, public"]).shared_freshness(true),
+            Some(Duration::from_secs(60))
+        );
+
+        assert!(cc(&[]).allows_cached_response());
+        assert!(!cc(&["no-cache"]).allows_cached_response());
+        assert!(!cc(&["max-age=0"]).allows_cached_response());
+        assert!(!cc(&["no-store"]).allows_cached_response());
+    }
+}
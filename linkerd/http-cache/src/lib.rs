@@ -0,0 +1,38 @@
+//! Caches responses to HTTP GET requests according to their Cache-Control
+//! headers.
+//!
+//! This is a shared cache in the spirit of RFC 7234, though it implements only
+//! a subset of it:
+//!
+//! - Only `GET` requests are answered from the cache, and only responses with
+//!   an explicit freshness lifetime (`s-maxage` or `max-age`) are stored.
+//!   Responses are never revalidated; they are evicted once they are stale.
+//! - Requests with `Cache-Control: no-store` bypass the cache entirely, and
+//!   requests with `no-cache` (or `max-age=0`) are forwarded, though their
+//!   responses may be stored.
+//! - Responses marked `no-store`, `no-cache`, or `private` are not stored, nor
+//!   are responses to requests with an `Authorization` header unless they are
+//!   marked `public` or set `s-maxage`.
+//! - Responses with a `Vary` header are only replayed to requests with the
+//!   same values for the listed headers. `Vary: *` responses are not stored.
+//!
+//! The cache is bounded by the total size of the responses it holds, and the
+//! least-recently-used responses are evicted to make room for new ones.
+
+#![deny(warnings, rust_2018_idioms)]
+#![forbid(unsafe_code)]
+
+mod cache;
+mod control;
+mod metrics;
+mod service;
+
+pub use self::{
+    cache::{Cache, Config},
+    metrics::Metrics,
+    service::{CacheResponses, NewCacheResponses, ResponseFuture},
+};
+
+/// Marks targets whose responses may be cached.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Cacheable(pub bool);
@@ -0,0 +1,84 @@
+use linkerd_metrics::{metrics, Counter, FmtMetrics, Gauge};
+use std::{fmt, sync::Arc};
+
+metrics! {
+    http_response_cache_hits_total: Counter {
+        "Total count of requests answered with a cached response"
+    },
+    http_response_cache_misses_total: Counter {
+        "Total count of cacheable requests that were forwarded"
+    },
+    http_response_cache_stores_total: Counter {
+        "Total count of responses stored in the response cache"
+    },
+    http_response_cache_evictions_total: Counter {
+        "Total count of responses evicted from the response cache before they became stale"
+    },
+    http_response_cache_entries: Gauge {
+        "Number of responses currently stored in the response cache"
+    }
+}
+
+/// Records the requests and responses handled by a response cache.
+#[derive(Clone, Debug, Default)]
+pub struct Metrics(Arc<Inner>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    hits: Counter,
+    misses: Counter,
+    stores: Counter,
+    evictions: Counter,
+    entries: Gauge,
+}
+
+// === impl Metrics ===
+
+impl Metrics {
+    pub(crate) fn hit(&self) {
+        self.0.hits.incr();
+    }
+
+    pub(crate) fn miss(&self) {
+        self.0.misses.incr();
+    }
+
+    pub(crate) fn stored(&self) {
+        self.0.stores.incr();
+        self.0.entries.incr();
+    }
+
+    pub(crate) fn evicted(&self) {
+        self.0.evictions.incr();
+        self.0.entries.decr();
+    }
+
+    pub(crate) fn removed(&self) {
+        self.0.entries.decr();
+    }
+}
+
+impl FmtMetrics for Metrics {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Inner {
+            hits,
+            misses,
+            stores,
+            evictions,
+            entries,
+        } = &*self.0;
+
+        http_response_cache_hits_total.fmt_help(f)?;
+        http_response_cache_hits_total.fmt_metric(f, hits)?;
+        http_response_cache_misses_total.fmt_help(f)?;
+        http_response_cache_misses_total.fmt_metric(f, misses)?;
+        http_response_cache_stores_total.fmt_help(f)?;
+        http_response_cache_stores_total.fmt_metric(f, stores)?;
+        http_response_cache_evictions_total.fmt_help(f)?;
+        http_response_cache_evictions_total.fmt_metric(f, evictions)?;
+        http_response_cache_entries.fmt_help(f)?;
+        http_response_cache_entries.fmt_metric(f, entries)?;
+
+        Ok(())
+    }
+}
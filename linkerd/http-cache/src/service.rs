@@ -0,0 +1,468 @@
+use crate::{
+    cache::{Key, Stored, Vary},
+    control::CacheControl,
+    Cache, Cacheable,
+};
+use bytes::{Buf, Bytes, BytesMut};
+use futures::ready;
+use http::{header, HeaderMap};
+use http_body::Body;
+use linkerd_error::Error;
+use linkerd_http_box::BoxBody;
+use linkerd_stack::{layer, NewService, Param, Proxy};
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tracing::trace;
+
+/// Builds `CacheResponses` proxies for targets that are `Cacheable`.
+#[derive(Clone, Debug)]
+pub struct NewCacheResponses<N> {
+    cache: Option<Cache>,
+    inner: N,
+}
+
+/// Answers GET requests with cached responses when possible, and stores the
+/// responses to those that are forwarded.
+#[derive(Clone, Debug)]
+pub struct CacheResponses<P> {
+    cache: Option<Cache>,
+    inner: P,
+}
+
+#[pin_project(project = ResponseFutureProj)]
+#[derive(Debug)]
+pub enum ResponseFuture<F> {
+    Forward(#[pin] F),
+    Record {
+        #[pin]
+        inner: F,
+        request: Option<Request>,
+    },
+    Cached(Option<http::Response<BoxBody>>),
+}
+
+/// Describes a forwarded request whose response may be stored.
+#[derive(Debug)]
+pub struct Request {
+    cache: Cache,
+    key: Key,
+    headers: HeaderMap,
+}
+
+/// Accumulates a response so that it can be stored once it completes.
+#[derive(Debug)]
+struct Recording {
+    cache: Cache,
+    key: Key,
+    vary: Vary,
+    freshness: Duration,
+    age: Duration,
+    status: http::StatusCode,
+    version: http::Version,
+    headers: HeaderMap,
+    body: BytesMut,
+}
+
+/// Records a response body as it is read.
+#[pin_project]
+#[derive(Debug)]
+struct RecordBody<B> {
+    #[pin]
+    inner: B,
+    recording: Option<Recording>,
+}
+
+// === impl NewCacheResponses ===
+
+impl<N> NewCacheResponses<N> {
+    pub fn layer(cache: Option<Cache>) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            cache: cache.clone(),
+            inner,
+        })
+    }
+}
+
+impl<T, N> NewService<T> for NewCacheResponses<N>
+where
+    T: Param<Cacheable>,
+    N: NewService<T>,
+{
+    type Service = CacheResponses<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let Cacheable(cacheable) = target.param();
+        CacheResponses {
+            cache: self.cache.clone().filter(|_| cacheable),
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl CacheResponses ===
+
+impl<P, S, A, B> Proxy<http::Request<A>, S> for CacheResponses<P>
+where
+    P: Proxy<http::Request<A>, S, Response = http::Response<B>>,
+    P::Error: Into<Error>,
+    S: tower::Service<P::Request>,
+    B: Body + Send + 'static,
+    B::Data: Send + 'static,
+    B::Error: Into<Error>,
+{
+    type Request = P::Request;
+    type Response = http::Response<BoxBody>;
+    type Error = Error;
+    type Future = ResponseFuture<P::Future>;
+
+    fn proxy(&self, svc: &mut S, req: http::Request<A>) -> Self::Future {
+        let cache = match self.cache.as_ref() {
+            Some(cache) if req.method() == http::Method::GET => cache,
+            _ => return ResponseFuture::Forward(self.inner.proxy(svc, req)),
+        };
+
+        let control = CacheControl::from_headers(req.headers());
+        if control.no_store {
+            return ResponseFuture::Forward(self.inner.proxy(svc, req));
+        }
+
+        let key = Key::from_request(&req);
+        if control.allows_cached_response() {
+            if let Some(rsp) = cache.get(&key, req.headers()) {
+                return ResponseFuture::Cached(Some(rsp));
+            }
+        }
+
+        ResponseFuture::Record {
+            request: Some(Request {
+                cache: cache.clone(),
+                key,
+                headers: req.headers().clone(),
+            }),
+            inner: self.inner.proxy(svc, req),
+        }
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F, B, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<http::Response<B>, E>>,
+    E: Into<Error>,
+    B: Body + Send + 'static,
+    B::Data: Send + 'static,
+    B::Error: Into<Error>,
+{
+    type Output = Result<http::Response<BoxBody>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            ResponseFutureProj::Forward(f) => {
+                let rsp = ready!(f.poll(cx)).map_err(Into::into)?;
+                Poll::Ready(Ok(rsp.map(BoxBody::new)))
+            }
+            ResponseFutureProj::Record { inner, request } => {
+                let rsp = ready!(inner.poll(cx)).map_err(Into::into)?;
+                let request = request.take().expect("polled after ready");
+                let recording = match request.record(&rsp) {
+                    Some(recording) => recording,
+                    None => return Poll::Ready(Ok(rsp.map(BoxBody::new))),
+                };
+
+                if rsp.body().is_end_stream() {
+                    recording.store(None);
+                    return Poll::Ready(Ok(rsp.map(BoxBody::new)));
+                }
+
+                Poll::Ready(Ok(rsp.map(|inner| {
+                    BoxBody::new(RecordBody {
+                        inner,
+                        recording: Some(recording),
+                    })
+                })))
+            }
+            ResponseFutureProj::Cached(rsp) => {
+                Poll::Ready(Ok(rsp.take().expect("polled after ready")))
+            }
+        }
+    }
+}
+
+// === impl Request ===
+
+impl Request {
+    /// Returns a `Recording` if the response may be stored.
+    fn record<B>(self, rsp: &http::Response<B>) -> Option<Recording> {
+        if !is_cacheable_status(rsp.status()) {
+            return None;
+        }
+
+        let authorized = self.headers.contains_key(header::AUTHORIZATION);
+        let freshness = CacheControl::from_headers(rsp.headers()).shared_freshness(authorized)?;
+
+        let mut vary = Vary::new();
+        let names = rsp
+            .headers()
+            .get_all(header::VARY)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+            .filter(|n| !n.is_empty());
+        for name in names {
+            // Responses that vary on anything can't be matched to requests.
+            if name == "*" {
+                return None;
+            }
+            let name = name.parse::<header::HeaderName>().ok()?;
+            let value = self.headers.get(&name).cloned();
+            vary.push((name, value));
+        }
+
+        let age = rsp
+            .headers()
+            .get(header::AGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or_default();
+        if age >= freshness {
+            return None;
+        }
+
+        Some(Recording {
+            cache: self.cache,
+            key: self.key,
+            vary,
+            freshness,
+            age,
+            status: rsp.status(),
+            version: rsp.version(),
+            headers: rsp.headers().clone(),
+            body: BytesMut::new(),
+        })
+    }
+}
+
+/// Indicates whether responses with the given status may be cached without
+/// explicit freshness information, per RFC 7231 §6.1.
+///
+/// Other statuses are not cached at all, even when they set a freshness
+/// lifetime.
+fn is_cacheable_status(status: http::StatusCode) -> bool {
+    matches!(
+        status.as_u16(),
+        200 | 203 | 204 | 300 | 301 | 404 | 405 | 410 | 414 | 501
+    )
+}
+
+// === impl Recording ===
+
+impl Recording {
+    fn store(self, trailers: Option<HeaderMap>) {
+        trace!(key = ?self.key, "Storing response");
+        let response = Stored {
+            status: self.status,
+            version: self.version,
+            headers: self.headers,
+            body: self.body.freeze(),
+            trailers,
+        };
+        self.cache
+            .insert(self.key, self.vary, response, self.freshness, self.age);
+    }
+}
+
+// === impl RecordBody ===
+
+impl<B> Body for RecordBody<B>
+where
+    B: Body,
+    B::Error: Into<Error>,
+{
+    type Data = Bytes;
+    type Error = Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let mut this = self.project();
+        let data = match ready!(this.inner.as_mut().poll_data(cx)) {
+            Some(Ok(mut data)) => data.copy_to_bytes(data.remaining()),
+            Some(Err(e)) => {
+                // Incomplete responses are not stored.
+                *this.recording = None;
+                return Poll::Ready(Some(Err(e.into())));
+            }
+            None => {
+                // If the body has no trailers, it is complete.
+                if this.inner.is_end_stream() {
+                    if let Some(recording) = this.recording.take() {
+                        recording.store(None);
+                    }
+                }
+                return Poll::Ready(None);
+            }
+        };
+
+        if let Some(recording) = this.recording.as_mut() {
+            if recording.body.len() + data.len() > recording.cache.max_body_bytes() {
+                trace!(key = ?recording.key, "Response body too large to store");
+                *this.recording = None;
+            } else {
+                recording.body.extend_from_slice(&data);
+            }
+        }
+        Poll::Ready(Some(Ok(data)))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let trailers = ready!(this.inner.poll_trailers(cx)).map_err(Into::into);
+        match trailers.as_ref() {
+            Ok(trailers) => {
+                if let Some(recording) = this.recording.take() {
+                    recording.store(trailers.clone());
+                }
+            }
+            Err(_) => *this.recording = None,
+        }
+        Poll::Ready(trailers)
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Metrics};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use tower::ServiceExt;
+
+    fn proxy(cacheable: bool) -> CacheResponses<()> {
+        let cache = Cache::new(
+            Config {
+                max_bytes: 1024,
+                max_body_bytes: 1024,
+            },
+            Metrics::default(),
+        );
+        CacheResponses {
+            cache: Some(cache).filter(|_| cacheable),
+            inner: (),
+        }
+    }
+
+    async fn send(
+        proxy: &CacheResponses<()>,
+        calls: &Arc<AtomicUsize>,
+        req: http::request::Builder,
+        rsp_headers: &[(header::HeaderName, &'static str)],
+    ) -> http::Response<BoxBody> {
+        let calls = calls.clone();
+        let rsp_headers = rsp_headers.to_vec();
+        let mut svc = tower::service_fn(move |_: http::Request<()>| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            let mut rsp = http::Response::builder();
+            for (name, value) in rsp_headers.iter() {
+                rsp = rsp.header(name, *value);
+            }
+            futures::future::ok::<_, Error>(rsp.body(hyper::Body::from("hello")).unwrap())
+        });
+        let svc = svc.ready().await.unwrap();
+        let rsp = proxy.proxy(svc, req.body(()).unwrap()).await.unwrap();
+        let (parts, body) = rsp.into_parts();
+        let body = hyper::body::to_bytes(body).await.unwrap();
+        assert_eq!(body, "hello");
+        http::Response::from_parts(parts, BoxBody::default())
+    }
+
+    fn get() -> http::request::Builder {
+        http::Request::builder().uri("http://example.com/things")
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn caches_fresh_responses() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let p = proxy(true);
+        let max_age = [(header::CACHE_CONTROL, "max-age=10")];
+
+        let rsp = send(&p, &calls, get(), &max_age).await;
+        assert!(rsp.headers().get(header::AGE).is_none());
+        let rsp = send(&p, &calls, get(), &max_age).await;
+        assert_eq!(rsp.headers()[header::AGE], "0");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Requests that ask for a fresh response are forwarded.
+        let no_cache = get().header(header::CACHE_CONTROL, "no-cache");
+        send(&p, &calls, no_cache, &max_age).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        tokio::time::advance(Duration::from_secs(11)).await;
+        send(&p, &calls, get(), &max_age).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    /// Sends a request twice, returning the number of requests that were
+    /// forwarded.
+    async fn send_twice(
+        proxy: &CacheResponses<()>,
+        req: impl Fn() -> http::request::Builder,
+        rsp_headers: &[(header::HeaderName, &'static str)],
+    ) -> usize {
+        let calls = Arc::new(AtomicUsize::new(0));
+        send(proxy, &calls, req(), rsp_headers).await;
+        send(proxy, &calls, req(), rsp_headers).await;
+        calls.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn does_not_store() {
+        let max_age = [(header::CACHE_CONTROL, "max-age=10")];
+
+        // Routes that are not cacheable.
+        assert_eq!(send_twice(&proxy(false), get, &max_age).await, 2);
+        // Responses without a freshness lifetime.
+        assert_eq!(send_twice(&proxy(true), get, &[]).await, 2);
+        // Responses that may not be shared.
+        let private = [(header::CACHE_CONTROL, "max-age=10, private")];
+        assert_eq!(send_twice(&proxy(true), get, &private).await, 2);
+        let vary = [(header::CACHE_CONTROL, "max-age=10"), (header::VARY, "*")];
+        assert_eq!(send_twice(&proxy(true), get, &vary).await, 2);
+        let stale = [(header::CACHE_CONTROL, "max-age=10"), (header::AGE, "10")];
+        assert_eq!(send_twice(&proxy(true), get, &stale).await, 2);
+        // Requests other than GETs.
+        let post = || get().method("POST");
+        assert_eq!(send_twice(&proxy(true), post, &max_age).await, 2);
+        // Requests that may not be stored.
+        let no_store = || get().header(header::CACHE_CONTROL, "no-store");
+        assert_eq!(send_twice(&proxy(true), no_store, &max_age).await, 2);
+        // Authorized requests, unless the response is explicitly public.
+        let authz = || get().header(header::AUTHORIZATION, "Bearer foo");
+        assert_eq!(send_twice(&proxy(true), authz, &max_age).await, 2);
+        let public = [(header::CACHE_CONTROL, "max-age=10, public")];
+        assert_eq!(send_twice(&proxy(true), authz, &public).await, 1);
+    }
+}
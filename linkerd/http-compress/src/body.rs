@@ -0,0 +1,124 @@
+use crate::codec::Codec;
+use bytes::{Buf, Bytes};
+use futures::ready;
+use http::{HeaderMap, HeaderValue};
+use linkerd_error::Error;
+use pin_project::pin_project;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// A response body that may be compressed or decompressed.
+#[pin_project]
+#[derive(Debug)]
+pub struct Body<B> {
+    #[pin]
+    inner: B,
+    state: State,
+}
+
+#[derive(Debug)]
+enum State {
+    Passthru,
+    Coding(Codec),
+    Done,
+}
+
+// === impl Body ===
+
+impl<B> Body<B> {
+    pub(crate) fn passthru(inner: B) -> Self {
+        Self {
+            inner,
+            state: State::Passthru,
+        }
+    }
+
+    pub(crate) fn coding(inner: B, codec: Codec) -> Self {
+        Self {
+            inner,
+            state: State::Coding(codec),
+        }
+    }
+}
+
+impl<B> http_body::Body for Body<B>
+where
+    B: http_body::Body,
+    B::Error: Into<Error>,
+{
+    type Data = Bytes;
+    type Error = Error;
+
+    fn is_end_stream(&self) -> bool {
+        match self.state {
+            State::Coding(_) => false,
+            State::Passthru | State::Done => self.inner.is_end_stream(),
+        }
+    }
+
+    fn poll_data(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Error>>> {
+        let mut this = self.project();
+        loop {
+            let codec = match this.state {
+                State::Coding(codec) => codec,
+                State::Passthru => {
+                    return this.inner.poll_data(cx).map(|data| {
+                        data.map(|res| {
+                            res.map(|mut data| data.copy_to_bytes(data.remaining()))
+                                .map_err(Into::into)
+                        })
+                    })
+                }
+                State::Done => return Poll::Ready(None),
+            };
+
+            match ready!(this.inner.as_mut().poll_data(cx)) {
+                Some(Ok(mut data)) => {
+                    while data.has_remaining() {
+                        let chunk = data.chunk();
+                        let len = chunk.len();
+                        codec.write(chunk)?;
+                        data.advance(len);
+                    }
+                    let out = codec.flush()?;
+                    if !out.is_empty() {
+                        return Poll::Ready(Some(Ok(out)));
+                    }
+                }
+                Some(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                None => {
+                    let out = match std::mem::replace(this.state, State::Done) {
+                        State::Coding(codec) => codec.finish()?,
+                        _ => unreachable!("body must be coding"),
+                    };
+                    if out.is_empty() {
+                        return Poll::Ready(None);
+                    }
+                    return Poll::Ready(Some(Ok(out)));
+                }
+            }
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap<HeaderValue>>, Error>> {
+        self.project().inner.poll_trailers(cx).map_err(Into::into)
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        match self.state {
+            State::Passthru => self.inner.size_hint(),
+            State::Coding(_) | State::Done => http_body::SizeHint::default(),
+        }
+    }
+}
+
+impl<B: Default> Default for Body<B> {
+    fn default() -> Self {
+        Self::passthru(B::default())
+    }
+}
@@ -0,0 +1,108 @@
+use crate::Encoding;
+use bytes::Bytes;
+use flate2::write::{GzDecoder, GzEncoder};
+use std::io::{self, Write};
+
+/// The size of brotli's internal buffers.
+const BROTLI_BUFFER_SIZE: usize = 4096;
+
+/// A fast brotli compression level, comparable to gzip's default.
+const BROTLI_QUALITY: u32 = 4;
+
+/// The base-2 logarithm of brotli's window size.
+const BROTLI_LGWIN: u32 = 22;
+
+/// Encodes or decodes a stream of bytes.
+///
+/// Each chunk is flushed through the codec as it is written so that streaming
+/// responses are not delayed by the proxy.
+pub(crate) enum Codec {
+    GzipEncode(GzEncoder<Vec<u8>>),
+    GzipDecode(GzDecoder<Vec<u8>>),
+    BrotliEncode(Box<brotli::CompressorWriter<Vec<u8>>>),
+    BrotliDecode(Box<brotli::DecompressorWriter<Vec<u8>>>),
+}
+
+// === impl Codec ===
+
+impl Codec {
+    pub(crate) fn encode(encoding: Encoding) -> Self {
+        match encoding {
+            Encoding::Gzip => Self::GzipEncode(GzEncoder::new(Vec::new(), Default::default())),
+            Encoding::Brotli => Self::BrotliEncode(Box::new(brotli::CompressorWriter::new(
+                Vec::new(),
+                BROTLI_BUFFER_SIZE,
+                BROTLI_QUALITY,
+                BROTLI_LGWIN,
+            ))),
+        }
+    }
+
+    pub(crate) fn decode(encoding: Encoding) -> Self {
+        match encoding {
+            Encoding::Gzip => Self::GzipDecode(GzDecoder::new(Vec::new())),
+            Encoding::Brotli => Self::BrotliDecode(Box::new(brotli::DecompressorWriter::new(
+                Vec::new(),
+                BROTLI_BUFFER_SIZE,
+            ))),
+        }
+    }
+
+    /// Writes a chunk through the codec.
+    pub(crate) fn write(&mut self, chunk: &[u8]) -> io::Result<()> {
+        match self {
+            Self::GzipEncode(w) => w.write_all(chunk),
+            Self::GzipDecode(w) => w.write_all(chunk),
+            Self::BrotliEncode(w) => w.write_all(chunk),
+            Self::BrotliDecode(w) => w.write_all(chunk),
+        }
+    }
+
+    /// Flushes the codec, returning all of the output produced so far.
+    pub(crate) fn flush(&mut self) -> io::Result<Bytes> {
+        let out = match self {
+            Self::GzipEncode(w) => {
+                w.flush()?;
+                w.get_mut()
+            }
+            Self::GzipDecode(w) => {
+                w.flush()?;
+                w.get_mut()
+            }
+            Self::BrotliEncode(w) => {
+                w.flush()?;
+                w.get_mut()
+            }
+            Self::BrotliDecode(w) => {
+                w.flush()?;
+                w.get_mut()
+            }
+        };
+        Ok(std::mem::take(out).into())
+    }
+
+    /// Completes the stream, returning any remaining output.
+    pub(crate) fn finish(self) -> io::Result<Bytes> {
+        let out = match self {
+            Self::GzipEncode(w) => w.finish()?,
+            Self::GzipDecode(w) => w.finish()?,
+            Self::BrotliEncode(w) => w.into_inner(),
+            Self::BrotliDecode(w) => w.into_inner().map_err(|_| {
+                io::Error::new(io::ErrorKind::UnexpectedEof, "incomplete brotli stream")
+            })?,
+        };
+        Ok(out.into())
+    }
+}
+
+impl std::fmt::Debug for Codec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::GzipEncode(_) => "GzipEncode",
+            Self::GzipDecode(_) => "GzipDecode",
+            Self::BrotliEncode(_) => "BrotliEncode",
+            Self::BrotliDecode(_) => "BrotliDecode",
+        };
+        f.write_str(name)
+    }
+}
@@ -0,0 +1,290 @@
+use crate::{codec::Codec, Body, Encoding};
+use futures::ready;
+use http::header::{self, HeaderValue};
+use linkerd_stack::layer;
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tracing::debug;
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Responses with a known length smaller than this are not compressed.
+    pub min_size: u64,
+
+    /// The content types of responses that may be compressed. An entry that
+    /// ends with `/` (e.g. `text/`) matches all types with that prefix.
+    pub content_types: Vec<String>,
+}
+
+/// Compresses responses with an encoding accepted by the client.
+///
+/// When no configuration is provided, responses are passed through
+/// unmodified.
+#[derive(Clone, Debug)]
+pub struct Compress<S> {
+    inner: S,
+    config: Option<Arc<Config>>,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    compress: Option<(Encoding, Arc<Config>)>,
+}
+
+// === impl Config ===
+
+impl Config {
+    /// Returns true if the response may be compressed.
+    fn should_compress<B>(&self, rsp: &http::Response<B>) -> bool {
+        let status = rsp.status();
+        if status.is_informational()
+            || status == http::StatusCode::NO_CONTENT
+            || status == http::StatusCode::NOT_MODIFIED
+        {
+            return false;
+        }
+
+        let headers = rsp.headers();
+        if headers.contains_key(header::CONTENT_ENCODING)
+            || headers.contains_key(header::CONTENT_RANGE)
+        {
+            return false;
+        }
+
+        let no_transform = headers.get_all(header::CACHE_CONTROL).iter().any(|v| {
+            v.to_str()
+                .map(|v| {
+                    v.split(',')
+                        .any(|d| d.trim().eq_ignore_ascii_case("no-transform"))
+                })
+                .unwrap_or(false)
+        });
+        if no_transform {
+            return false;
+        }
+
+        let too_small = headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok()?.parse::<u64>().ok())
+            .map(|len| len < self.min_size)
+            .unwrap_or(false);
+        if too_small {
+            return false;
+        }
+
+        headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| self.is_compressible(v))
+            .unwrap_or(false)
+    }
+
+    fn is_compressible(&self, content_type: &str) -> bool {
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        self.content_types.iter().any(|t| {
+            if t.ends_with('/') {
+                essence.starts_with(t.as_str())
+            } else {
+                essence == *t
+            }
+        })
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            min_size: 1024,
+            content_types: vec![
+                "text/".to_string(),
+                "application/javascript".to_string(),
+                "application/json".to_string(),
+                "application/xml".to_string(),
+                "image/svg+xml".to_string(),
+            ],
+        }
+    }
+}
+
+// === impl Compress ===
+
+impl<S> Compress<S> {
+    pub fn layer(config: Option<Config>) -> impl layer::Layer<S, Service = Self> + Clone {
+        let config = config.map(Arc::new);
+        layer::mk(move |inner| Self {
+            inner,
+            config: config.clone(),
+        })
+    }
+}
+
+impl<S, A, B> tower::Service<http::Request<A>> for Compress<S>
+where
+    S: tower::Service<http::Request<A>, Response = http::Response<B>>,
+{
+    type Response = http::Response<Body<B>>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<A>) -> Self::Future {
+        let compress = match self.config.as_ref() {
+            Some(config) if req.method() != http::Method::HEAD => {
+                Encoding::preferred(req.headers()).map(|e| (e, config.clone()))
+            }
+            _ => None,
+        };
+
+        ResponseFuture {
+            inner: self.inner.call(req),
+            compress,
+        }
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F, B, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<http::Response<B>, E>>,
+{
+    type Output = Result<http::Response<Body<B>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let rsp = ready!(this.inner.poll(cx))?;
+
+        let encoding = match this.compress.take() {
+            Some((encoding, config)) if config.should_compress(&rsp) => encoding,
+            _ => return Poll::Ready(Ok(rsp.map(Body::passthru))),
+        };
+
+        debug!(encoding = encoding.as_str(), "Compressing response");
+        let (mut parts, body) = rsp.into_parts();
+        parts.headers.remove(header::CONTENT_LENGTH);
+        parts.headers.insert(
+            header::CONTENT_ENCODING,
+            HeaderValue::from_static(encoding.as_str()),
+        );
+        parts
+            .headers
+            .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+        let body = Body::coding(body, Codec::encode(encoding));
+        Poll::Ready(Ok(http::Response::from_parts(parts, body)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Decompress;
+    use bytes::Bytes;
+    use std::{convert::Infallible, io::Read};
+    use tower::{service_fn, Layer, ServiceExt};
+
+    fn response(content_type: &'static str, body: String) -> http::Response<hyper::Body> {
+        http::Response::builder()
+            .header(header::CONTENT_TYPE, content_type)
+            .header(header::CONTENT_LENGTH, body.len())
+            .body(body.into())
+            .unwrap()
+    }
+
+    async fn call<S, B>(svc: S, accept: Option<&'static str>) -> (http::HeaderMap, Bytes)
+    where
+        S: tower::Service<http::Request<()>, Response = http::Response<B>, Error = Infallible>,
+        B: http_body::Body,
+        B::Error: std::fmt::Debug,
+    {
+        let mut req = http::Request::builder();
+        if let Some(accept) = accept {
+            req = req.header(header::ACCEPT_ENCODING, accept);
+        }
+        let rsp = svc.oneshot(req.body(()).unwrap()).await.unwrap();
+        let (parts, body) = rsp.into_parts();
+        (parts.headers, hyper::body::to_bytes(body).await.unwrap())
+    }
+
+    fn text() -> String {
+        "hello world ".repeat(200)
+    }
+
+    #[test]
+    fn should_compress() {
+        let config = Config::default();
+        assert!(config.should_compress(&response("text/html; charset=utf-8", text())));
+        assert!(config.should_compress(&response("application/json", text())));
+        assert!(!config.should_compress(&response("application/json", "{}".into())));
+        assert!(!config.should_compress(&response("image/png", text())));
+        assert!(!config.should_compress(&response("application/grpc", text())));
+
+        let mut rsp = response("text/plain", text());
+        rsp.headers_mut()
+            .insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        assert!(!config.should_compress(&rsp));
+
+        let mut rsp = response("text/plain", text());
+        rsp.headers_mut().insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("public, no-transform"),
+        );
+        assert!(!config.should_compress(&rsp));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn compresses_accepted_encoding() {
+        let svc = Compress::layer(Some(Config::default())).layer(service_fn(
+            |_: http::Request<()>| async { Ok::<_, Infallible>(response("text/plain", text())) },
+        ));
+
+        let (headers, body) = call(svc.clone(), Some("deflate, gzip")).await;
+        assert_eq!(headers[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(headers[header::VARY], "accept-encoding");
+        assert!(!headers.contains_key(header::CONTENT_LENGTH));
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(&body[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, text());
+
+        let (headers, body) = call(svc, None).await;
+        assert!(!headers.contains_key(header::CONTENT_ENCODING));
+        assert_eq!(body, text());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn decompresses_unaccepted_encoding() {
+        let svc = Compress::layer(Some(Config::default())).layer(service_fn(
+            |_: http::Request<()>| async { Ok::<_, Infallible>(response("text/plain", text())) },
+        ));
+        let svc = Decompress::layer(true).layer(svc);
+
+        // The client doesn't accept any encoding, so the response is
+        // compressed with brotli and decoded by the client's proxy.
+        let (headers, body) = call(svc.clone(), None).await;
+        assert!(!headers.contains_key(header::CONTENT_ENCODING));
+        assert_eq!(body, text());
+
+        // The client accepts gzip, so the response is compressed with gzip
+        // and is not decoded.
+        let (headers, _) = call(svc, Some("gzip")).await;
+        assert_eq!(headers[header::CONTENT_ENCODING], "gzip");
+    }
+}
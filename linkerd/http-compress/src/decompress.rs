@@ -0,0 +1,129 @@
+use crate::{codec::Codec, Body, Encoding};
+use futures::ready;
+use http::header::{self, HeaderValue};
+use linkerd_stack::layer;
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tracing::debug;
+
+/// Requests compressed responses on behalf of clients, decoding responses
+/// with encodings that the client did not accept.
+#[derive(Clone, Debug)]
+pub struct Decompress<S> {
+    inner: S,
+    enabled: bool,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    accepted: Option<Accepted>,
+}
+
+/// The supported encodings accepted by the client.
+#[derive(Copy, Clone, Debug)]
+struct Accepted {
+    brotli: bool,
+    gzip: bool,
+}
+
+// === impl Decompress ===
+
+impl<S> Decompress<S> {
+    pub fn layer(enabled: bool) -> impl layer::Layer<S, Service = Self> + Clone {
+        layer::mk(move |inner| Self { inner, enabled })
+    }
+}
+
+impl<S, A, B> tower::Service<http::Request<A>> for Decompress<S>
+where
+    S: tower::Service<http::Request<A>, Response = http::Response<B>>,
+{
+    type Response = http::Response<Body<B>>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<A>) -> Self::Future {
+        let mut accepted = None;
+        if self.enabled && req.method() != http::Method::HEAD {
+            let client = Accepted {
+                brotli: Encoding::Brotli.is_accepted(req.headers()),
+                gzip: Encoding::Gzip.is_accepted(req.headers()),
+            };
+            // If the client accepts all of the supported encodings, there's
+            // nothing to decode. Otherwise, all supported encodings are
+            // requested, preferring those that the client accepts so that
+            // they need not be decoded.
+            let accept_encoding = match (client.brotli, client.gzip) {
+                (true, true) => None,
+                (true, false) => Some("br, gzip;q=0.5"),
+                (false, true) => Some("gzip, br;q=0.5"),
+                (false, false) => Some("br, gzip"),
+            };
+            if let Some(accept_encoding) = accept_encoding {
+                req.headers_mut().insert(
+                    header::ACCEPT_ENCODING,
+                    HeaderValue::from_static(accept_encoding),
+                );
+                accepted = Some(client);
+            }
+        }
+
+        ResponseFuture {
+            inner: self.inner.call(req),
+            accepted,
+        }
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F, B, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<http::Response<B>, E>>,
+{
+    type Output = Result<http::Response<Body<B>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let rsp = ready!(this.inner.poll(cx))?;
+
+        let encoding = match (this.accepted.take(), Encoding::from_headers(rsp.headers())) {
+            (Some(accepted), Some(encoding)) if !accepted.accepts(encoding) => encoding,
+            _ => return Poll::Ready(Ok(rsp.map(Body::passthru))),
+        };
+        let status = rsp.status();
+        if status == http::StatusCode::NO_CONTENT || status == http::StatusCode::NOT_MODIFIED {
+            return Poll::Ready(Ok(rsp.map(Body::passthru)));
+        }
+
+        debug!(encoding = encoding.as_str(), "Decompressing response");
+        let (mut parts, body) = rsp.into_parts();
+        parts.headers.remove(header::CONTENT_ENCODING);
+        parts.headers.remove(header::CONTENT_LENGTH);
+        let body = Body::coding(body, Codec::decode(encoding));
+        Poll::Ready(Ok(http::Response::from_parts(parts, body)))
+    }
+}
+
+// === impl Accepted ===
+
+impl Accepted {
+    fn accepts(&self, encoding: Encoding) -> bool {
+        match encoding {
+            Encoding::Brotli => self.brotli,
+            Encoding::Gzip => self.gzip,
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Compresses and decompresses HTTP response bodies.
+//!
+//! [`Compress`] encodes responses with an encoding accepted by the client, so
+//! that less data is sent over slow (e.g. cross-cluster) links. [`Decompress`]
+//! requests encoded responses on a client's behalf and decodes them when the
+//! client did not accept the encoding.
+
+#![deny(warnings, rust_2018_idioms)]
+#![forbid(unsafe_code)]
+
+mod body;
+mod codec;
+mod compress;
+mod decompress;
+
+pub use self::{
+    body::Body,
+    compress::{Compress, Config},
+    decompress::Decompress,
+};
+use http::header::{HeaderMap, ACCEPT_ENCODING};
+
+/// A content coding supported by the proxy.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+// === impl Encoding ===
+
+impl Encoding {
+    /// Supported encodings, in order of preference.
+    const ALL: [Encoding; 2] = [Encoding::Brotli, Encoding::Gzip];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Brotli => "br",
+            Self::Gzip => "gzip",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("br") {
+            Some(Self::Brotli)
+        } else if name.eq_ignore_ascii_case("gzip") || name.eq_ignore_ascii_case("x-gzip") {
+            Some(Self::Gzip)
+        } else {
+            None
+        }
+    }
+
+    /// Reads the encoding from a `content-encoding` header, if it is a
+    /// supported encoding.
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let value = headers.get(http::header::CONTENT_ENCODING)?.to_str().ok()?;
+        Self::from_name(value.trim())
+    }
+
+    /// Returns the client's preferred encoding, if it accepts any of the
+    /// supported encodings.
+    fn preferred(headers: &HeaderMap) -> Option<Self> {
+        let mut preferred = None;
+        for encoding in Self::ALL.iter().copied() {
+            let q = encoding.qvalue(headers);
+            if q > 0 && preferred.map(|(_, best)| q > best).unwrap_or(true) {
+                preferred = Some((encoding, q));
+            }
+        }
+        preferred.map(|(encoding, _)| encoding)
+    }
+
+    /// Returns true if the client accepts this encoding.
+    fn is_accepted(&self, headers: &HeaderMap) -> bool {
+        self.qvalue(headers) > 0
+    }
+
+    /// Returns the quality value (in thousandths) that the `accept-encoding`
+    /// header assigns to this encoding.
+    ///
+    /// An explicit entry for the encoding takes precedence over a `*` entry.
+    fn qvalue(&self, headers: &HeaderMap) -> u16 {
+        let mut wildcard = 0;
+        for value in headers.get_all(ACCEPT_ENCODING) {
+            let value = match value.to_str() {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+            for entry in value.split(',') {
+                let mut parts = entry.split(';');
+                let name = parts.next().unwrap_or_default().trim();
+                let q = parts
+                    .filter_map(|p| {
+                        let p = p.trim();
+                        p.strip_prefix("q=").or_else(|| p.strip_prefix("Q="))
+                    })
+                    .next()
+                    .map(parse_qvalue)
+                    .unwrap_or(Some(1000));
+                let q = match q {
+                    Some(q) => q,
+                    None => continue,
+                };
+                if name == "*" {
+                    wildcard = q;
+                } else if Self::from_name(name) == Some(*self) {
+                    return q;
+                }
+            }
+        }
+        wildcard
+    }
+}
+
+/// Parses a quality value (e.g. `0.5`) as thousandths.
+fn parse_qvalue(s: &str) -> Option<u16> {
+    let q = s.trim().parse::<f32>().ok()?;
+    if !(0.0..=1.0).contains(&q) {
+        return None;
+    }
+    Some((q * 1000.0).round() as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    fn accept(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_ENCODING, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn preferred() {
+        for (value, expected) in &[
+            ("gzip", Some(Encoding::Gzip)),
+            ("gzip, deflate, br", Some(Encoding::Brotli)),
+            ("br;q=0.5, gzip", Some(Encoding::Gzip)),
+            ("br;q=0, gzip;q=0.1", Some(Encoding::Gzip)),
+            ("x-gzip", Some(Encoding::Gzip)),
+            ("*", Some(Encoding::Brotli)),
+            ("br;q=0, *;q=0.5", Some(Encoding::Gzip)),
+            ("deflate, identity", None),
+            ("gzip;q=0", None),
+            ("gzip;q=2", None),
+            ("", None),
+        ] {
+            assert_eq!(Encoding::preferred(&accept(value)), *expected, "{}", value);
+        }
+        assert_eq!(Encoding::preferred(&HeaderMap::new()), None);
+    }
+}
@@ -0,0 +1,301 @@
+use crate::{proto, BoxBody, CheckRequest, Decision, HeaderMutations};
+use http::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    StatusCode,
+};
+use http_body::Body;
+use linkerd_error::Error;
+use std::{collections::HashMap, convert::TryFrom, str::FromStr};
+use tonic::{self as grpc, client::GrpcService, codec::ProstCodec};
+use tracing::debug;
+
+#[derive(Clone, Debug)]
+pub(crate) struct Client<S> {
+    inner: grpc::client::Grpc<S>,
+}
+
+// === impl Client ===
+
+impl<S> Client<S> {
+    pub(crate) fn new(svc: S) -> Self {
+        Self {
+            inner: grpc::client::Grpc::new(svc),
+        }
+    }
+}
+
+impl<S> Client<S>
+where
+    S: GrpcService<BoxBody> + Clone + Send + 'static,
+    S::ResponseBody: Send + Sync + 'static,
+    <S::ResponseBody as Body>::Data: Send,
+    <S::ResponseBody as Body>::Error: Into<Error> + Send,
+    S::Future: Send,
+{
+    pub(crate) async fn check(mut self, req: CheckRequest) -> Result<Decision, Error> {
+        self.inner.ready().await.map_err(Into::into)?;
+        let rsp = self
+            .inner
+            .unary(
+                grpc::Request::new(to_proto(req)),
+                http::uri::PathAndQuery::from_static(proto::CHECK_PATH),
+                ProstCodec::default(),
+            )
+            .await?;
+        Ok(decision(rsp.into_inner()))
+    }
+}
+
+fn to_proto(req: CheckRequest) -> proto::CheckRequest {
+    let mut headers = HashMap::<String, String>::new();
+    for (name, value) in req.headers.iter() {
+        let value = match value.to_str() {
+            Ok(v) => v,
+            Err(_) => {
+                debug!(header = %name, "Omitting non-ASCII header value");
+                continue;
+            }
+        };
+        // Multiple values are combined as described in RFC 7230 section 3.2.2.
+        headers
+            .entry(name.as_str().to_string())
+            .and_modify(|v| {
+                v.push(',');
+                v.push_str(value);
+            })
+            .or_insert_with(|| value.to_string());
+    }
+
+    let protocol = match req.version {
+        http::Version::HTTP_10 => "HTTP/1.0",
+        http::Version::HTTP_11 => "HTTP/1.1",
+        http::Version::HTTP_2 => "HTTP/2",
+        _ => "",
+    };
+    let host = req
+        .uri
+        .authority()
+        .map(|a| a.as_str().to_string())
+        .or_else(|| {
+            req.headers
+                .get(http::header::HOST)
+                .and_then(|h| h.to_str().ok())
+                .map(String::from)
+        })
+        .unwrap_or_default();
+
+    let source = proto::Peer {
+        address: req.source.map(|addr| proto::Address {
+            socket_address: Some(proto::SocketAddress {
+                address: addr.ip().to_string(),
+                port_value: addr.port().into(),
+            }),
+        }),
+        principal: req.source_principal.unwrap_or_default(),
+    };
+
+    proto::CheckRequest {
+        attributes: Some(proto::AttributeContext {
+            source: Some(source),
+            destination: None,
+            request: Some(proto::Request {
+                http: Some(proto::HttpRequest {
+                    method: req.method.as_str().to_string(),
+                    headers,
+                    path: req
+                        .uri
+                        .path_and_query()
+                        .map(|p| p.as_str())
+                        .unwrap_or("/")
+                        .to_string(),
+                    host,
+                    scheme: req.uri.scheme_str().unwrap_or("http").to_string(),
+                    protocol: protocol.to_string(),
+                }),
+            }),
+        }),
+    }
+}
+
+fn decision(rsp: proto::CheckResponse) -> Decision {
+    use proto::check_response::HttpResponse;
+
+    let code = rsp.status.map(|s| s.code).unwrap_or(0);
+    if code == grpc::Code::Ok as i32 {
+        let mut mutations = HeaderMutations::default();
+        if let Some(HttpResponse::OkResponse(ok)) = rsp.http_response {
+            for opt in ok.headers {
+                if let Some((name, value)) = opt.header.as_ref().and_then(header) {
+                    if opt.append.unwrap_or(false) {
+                        mutations.append.append(name, value);
+                    } else {
+                        mutations.set.insert(name, value);
+                    }
+                }
+            }
+            for name in ok.headers_to_remove {
+                match HeaderName::from_str(&name) {
+                    Ok(name) => mutations.remove.push(name),
+                    Err(_) => debug!(%name, "Ignoring invalid header name"),
+                }
+            }
+        }
+        return Decision::Allow(mutations);
+    }
+
+    let denied = match rsp.http_response {
+        Some(HttpResponse::DeniedResponse(denied)) => denied,
+        _ => proto::DeniedHttpResponse::default(),
+    };
+    let status = denied
+        .status
+        .and_then(|s| u16::try_from(s.code).ok())
+        .and_then(|c| StatusCode::from_u16(c).ok())
+        .unwrap_or(StatusCode::FORBIDDEN);
+    let mut headers = HeaderMap::new();
+    for opt in denied.headers {
+        if let Some((name, value)) = opt.header.as_ref().and_then(header) {
+            headers.append(name, value);
+        }
+    }
+
+    let mut rsp = http::Response::new(hyper::Body::from(denied.body));
+    *rsp.status_mut() = status;
+    *rsp.headers_mut() = headers;
+    Decision::Deny(rsp)
+}
+
+fn header(h: &proto::HeaderValue) -> Option<(HeaderName, HeaderValue)> {
+    match (
+        HeaderName::from_str(&h.key),
+        HeaderValue::from_str(&h.value),
+    ) {
+        (Ok(name), Ok(value)) => Some((name, value)),
+        _ => {
+            debug!(header = %h.key, "Ignoring invalid header");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proto::check_response::HttpResponse;
+
+    fn hdr(key: &str, value: &str, append: Option<bool>) -> proto::HeaderValueOption {
+        proto::HeaderValueOption {
+            header: Some(proto::HeaderValue {
+                key: key.to_string(),
+                value: value.to_string(),
+            }),
+            append,
+        }
+    }
+
+    #[test]
+    fn ok_response_mutates_headers() {
+        let rsp = proto::CheckResponse {
+            status: Some(proto::Status::default()),
+            http_response: Some(HttpResponse::OkResponse(proto::OkHttpResponse {
+                headers: vec![
+                    hdr("x-user", "alice", None),
+                    hdr("x-group", "admins", Some(true)),
+                ],
+                headers_to_remove: vec!["authorization".to_string()],
+            })),
+        };
+        let mutations = match decision(rsp) {
+            Decision::Allow(m) => m,
+            d => panic!("unexpected decision: {:?}", d),
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-user", HeaderValue::from_static("mallory"));
+        headers.insert("x-group", HeaderValue::from_static("users"));
+        headers.insert("authorization", HeaderValue::from_static("Bearer foo"));
+        mutations.apply(&mut headers);
+
+        assert_eq!(
+            headers.get_all("x-user").iter().collect::<Vec<_>>(),
+            ["alice"]
+        );
+        assert_eq!(
+            headers.get_all("x-group").iter().collect::<Vec<_>>(),
+            ["users", "admins"]
+        );
+        assert!(headers.get("authorization").is_none());
+    }
+
+    #[tokio::test]
+    async fn denied_response() {
+        let rsp = proto::CheckResponse {
+            status: Some(proto::Status {
+                code: grpc::Code::PermissionDenied as i32,
+                message: "nope".to_string(),
+            }),
+            http_response: Some(HttpResponse::DeniedResponse(proto::DeniedHttpResponse {
+                status: Some(proto::HttpStatus { code: 401 }),
+                headers: vec![hdr("www-authenticate", "Basic", None)],
+                body: "go away".to_string(),
+            })),
+        };
+        let rsp = match decision(rsp) {
+            Decision::Deny(rsp) => rsp,
+            d => panic!("unexpected decision: {:?}", d),
+        };
+        assert_eq!(rsp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(rsp.headers()["www-authenticate"], "Basic");
+        let body = hyper::body::to_bytes(rsp.into_body()).await.unwrap();
+        assert_eq!(body, "go away");
+    }
+
+    #[test]
+    fn denied_without_response_is_forbidden() {
+        let rsp = proto::CheckResponse {
+            status: Some(proto::Status {
+                code: grpc::Code::PermissionDenied as i32,
+                message: String::new(),
+            }),
+            http_response: None,
+        };
+        match decision(rsp) {
+            Decision::Deny(rsp) => assert_eq!(rsp.status(), StatusCode::FORBIDDEN),
+            d => panic!("unexpected decision: {:?}", d),
+        }
+    }
+
+    #[test]
+    fn request_attributes() {
+        let mut headers = HeaderMap::new();
+        headers.append("x-foo", HeaderValue::from_static("a"));
+        headers.append("x-foo", HeaderValue::from_static("b"));
+        let req = CheckRequest {
+            source: Some(([10, 1, 2, 3], 5550).into()),
+            source_principal: Some("foo.ns1.serviceaccount.identity.linkerd.cluster.local".into()),
+            method: http::Method::POST,
+            uri: "http://foo.ns1.svc.cluster.local:8080/bar?baz=1"
+                .parse()
+                .unwrap(),
+            version: http::Version::HTTP_11,
+            headers,
+        };
+
+        let attrs = to_proto(req).attributes.expect("must have attributes");
+        let source = attrs.source.expect("must have a source");
+        assert_eq!(
+            source.principal,
+            "foo.ns1.serviceaccount.identity.linkerd.cluster.local"
+        );
+        let addr = source.address.unwrap().socket_address.unwrap();
+        assert_eq!(addr.address, "10.1.2.3");
+        assert_eq!(addr.port_value, 5550);
+
+        let http = attrs.request.unwrap().http.unwrap();
+        assert_eq!(http.method, "POST");
+        assert_eq!(http.path, "/bar?baz=1");
+        assert_eq!(http.host, "foo.ns1.svc.cluster.local:8080");
+        assert_eq!(http.protocol, "HTTP/1.1");
+        assert_eq!(http.headers["x-foo"], "a,b");
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{BoxBody, CheckRequest, Decision, HeaderMutations};
+use futures::future;
+use http::header::{self, HeaderName};
+use http_body::Body;
+use linkerd_error::Error;
+use std::sync::Arc;
+use tonic::client::GrpcService;
+use tracing::trace;
+
+#[derive(Clone, Debug)]
+pub(crate) struct Client<S> {
+    inner: S,
+    prefix: http::uri::PathAndQuery,
+    allowed_request_headers: Arc<[HeaderName]>,
+    allowed_upstream_headers: Arc<[HeaderName]>,
+}
+
+// === impl Client ===
+
+impl<S> Client<S> {
+    pub(crate) fn new(
+        inner: S,
+        prefix: http::uri::PathAndQuery,
+        allowed_request_headers: Vec<HeaderName>,
+        allowed_upstream_headers: Vec<HeaderName>,
+    ) -> Self {
+        Self {
+            inner,
+            prefix,
+            allowed_request_headers: allowed_request_headers.into(),
+            allowed_upstream_headers: allowed_upstream_headers.into(),
+        }
+    }
+}
+
+impl<S> Client<S>
+where
+    S: GrpcService<BoxBody> + Clone + Send + 'static,
+    S::ResponseBody: Send + Sync + 'static,
+    <S::ResponseBody as Body>::Data: Send,
+    <S::ResponseBody as Body>::Error: Into<Error> + Send,
+    S::Future: Send,
+{
+    pub(crate) async fn check(mut self, req: CheckRequest) -> Result<Decision, Error> {
+        let path = check_path(&self.prefix, &req.uri)?;
+        trace!(%path, "Checking request");
+
+        let mut check = http::Request::builder().method(req.method).uri(path);
+        for name in self.allowed_request_headers.iter() {
+            for value in req.headers.get_all(name) {
+                check = check.header(name, value);
+            }
+        }
+        let check = check.body(tonic::body::empty_body())?;
+
+        future::poll_fn(|cx| self.inner.poll_ready(cx))
+            .await
+            .map_err(Into::into)?;
+        let rsp = self.inner.call(check).await.map_err(Into::into)?;
+
+        let (parts, body) = rsp.into_parts();
+        if parts.status.is_success() {
+            let mut mutations = HeaderMutations::default();
+            for name in self.allowed_upstream_headers.iter() {
+                for value in parts.headers.get_all(name) {
+                    mutations.set.append(name, value.clone());
+                }
+            }
+            return Ok(Decision::Allow(mutations));
+        }
+
+        // The denied response is returned to the client as-is, except for
+        // headers that describe the authorization service's connection.
+        let body = hyper::body::to_bytes(body).await.map_err(Into::into)?;
+        let mut rsp = http::Response::from_parts(parts, hyper::Body::from(body));
+        for name in &[header::CONNECTION, header::TRANSFER_ENCODING] {
+            rsp.headers_mut().remove(name);
+        }
+        Ok(Decision::Deny(rsp))
+    }
+}
+
+/// Appends the path of the request being checked to the authorization
+/// service's path prefix.
+///
+/// The client is responsible for setting the request's scheme and authority.
+fn check_path(prefix: &http::uri::PathAndQuery, req: &http::Uri) -> Result<http::Uri, http::Error> {
+    let path = format!(
+        "{}{}",
+        prefix.path().trim_end_matches('/'),
+        req.path_and_query().map(|p| p.as_str()).unwrap_or("/")
+    );
+    http::Uri::builder().path_and_query(path.as_str()).build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::{HeaderValue, StatusCode};
+    use std::sync::Mutex;
+
+    #[test]
+    fn check_path_appends_path() {
+        let prefix = "/auth/".parse().unwrap();
+        let uri = check_path(&prefix, &"http://foo.ns1:80/bar?baz=1".parse().unwrap()).unwrap();
+        assert_eq!(uri, "/auth/bar?baz=1");
+
+        let prefix = "/".parse().unwrap();
+        let uri = check_path(&prefix, &"/bar".parse().unwrap()).unwrap();
+        assert_eq!(uri, "/bar");
+    }
+
+    /// Records the check request and responds with the given status.
+    fn authz(
+        status: StatusCode,
+        seen: Arc<Mutex<Option<http::request::Parts>>>,
+    ) -> impl GrpcService<
+        BoxBody,
+        ResponseBody = hyper::Body,
+        Error = Error,
+        Future = future::Ready<Result<http::Response<hyper::Body>, Error>>,
+    > + Clone {
+        tower::service_fn(move |req: http::Request<BoxBody>| {
+            *seen.lock().unwrap() = Some(req.into_parts().0);
+            let rsp = http::Response::builder()
+                .status(status)
+                .header("x-user", "alice")
+                .header("x-other", "foo")
+                .header(header::CONNECTION, "close")
+                .body(hyper::Body::from("denied"))
+                .unwrap();
+            future::ok(rsp)
+        })
+    }
+
+    fn request() -> CheckRequest {
+        let mut headers = http::HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer x"));
+        headers.insert("x-secret", HeaderValue::from_static("s3cr3t"));
+        CheckRequest {
+            source: None,
+            source_principal: None,
+            method: http::Method::POST,
+            uri: "http://foo.ns1:8080/bar?baz=1".parse().unwrap(),
+            version: http::Version::HTTP_11,
+            headers,
+        }
+    }
+
+    fn client<S>(inner: S) -> Client<S> {
+        Client::new(
+            inner,
+            http::uri::PathAndQuery::from_static("/auth"),
+            vec![header::AUTHORIZATION],
+            vec![HeaderName::from_static("x-user")],
+        )
+    }
+
+    #[tokio::test]
+    async fn allows_on_success() {
+        let seen = Arc::new(Mutex::new(None));
+        let decision = client(authz(StatusCode::NO_CONTENT, seen.clone()))
+            .check(request())
+            .await
+            .expect("check must succeed");
+
+        let check = seen.lock().unwrap().take().expect("must be checked");
+        assert_eq!(check.method, http::Method::POST);
+        assert_eq!(check.uri, "/auth/bar?baz=1");
+        assert_eq!(check.headers[header::AUTHORIZATION], "Bearer x");
+        assert!(check.headers.get("x-secret").is_none());
+
+        let mutations = match decision {
+            Decision::Allow(m) => m,
+            d => panic!("unexpected decision: {:?}", d),
+        };
+        let mut headers = http::HeaderMap::new();
+        mutations.apply(&mut headers);
+        assert_eq!(headers["x-user"], "alice");
+        assert!(headers.get("x-other").is_none());
+    }
+
+    #[tokio::test]
+    async fn denies_with_response() {
+        let seen = Arc::new(Mutex::new(None));
+        let decision = client(authz(StatusCode::UNAUTHORIZED, seen))
+            .check(request())
+            .await
+            .expect("check must succeed");
+
+        let rsp = match decision {
+            Decision::Deny(rsp) => rsp,
+            d => panic!("unexpected decision: {:?}", d),
+        };
+        assert_eq!(rsp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(rsp.headers()["x-other"], "foo");
+        assert!(rsp.headers().get(header::CONNECTION).is_none());
+        let body = hyper::body::to_bytes(rsp.into_body()).await.unwrap();
+        assert_eq!(body, "denied");
+    }
+}
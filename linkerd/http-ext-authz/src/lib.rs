@@ -0,0 +1,210 @@
+//! Checks HTTP requests against an external authorization service, in the
+//! style of Envoy's `ext_authz` filter.
+//!
+//! Authorization services may either implement the
+//! `envoy.service.auth.v3.Authorization` gRPC API or be plain HTTP servers.
+//! HTTP services are sent a bodiless copy of each request and permit the
+//! request by responding with a 2xx status. In either case, the authorization
+//! service is reached through the caller's client, so that checks are subject
+//! to the same transport security as other control plane traffic.
+
+#![deny(warnings, rust_2018_idioms)]
+#![forbid(unsafe_code)]
+
+mod grpc;
+mod http_service;
+mod proto;
+
+use http::header::{HeaderMap, HeaderName};
+use http_body::Body;
+use linkerd_error::Error;
+use std::{fmt, future::Future, net::SocketAddr, time::Duration};
+use thiserror::Error;
+pub use tonic::{body::BoxBody, client::GrpcService};
+use tracing::{debug, warn};
+
+/// Configures how requests are checked.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Bounds the time spent waiting for an authorization decision.
+    pub timeout: Duration,
+
+    /// Permits requests when the authorization service cannot be reached or
+    /// does not respond in time. Otherwise, such requests are rejected.
+    pub failure_mode_allow: bool,
+
+    /// The request headers forwarded to an HTTP authorization service. gRPC
+    /// authorization services are sent all request headers.
+    pub allowed_request_headers: Vec<HeaderName>,
+
+    /// The headers copied from an HTTP authorization service's response onto
+    /// a permitted request. gRPC authorization services describe header
+    /// mutations explicitly.
+    pub allowed_upstream_headers: Vec<HeaderName>,
+}
+
+/// Describes a request to be authorized.
+#[derive(Clone, Debug)]
+pub struct CheckRequest {
+    /// The client's address.
+    pub source: Option<SocketAddr>,
+
+    /// The client's verified identity, if the connection is meshed.
+    pub source_principal: Option<String>,
+
+    pub method: http::Method,
+    pub uri: http::Uri,
+    pub version: http::Version,
+    pub headers: HeaderMap,
+}
+
+/// The authorization service's decision about a request.
+#[derive(Debug)]
+pub enum Decision {
+    /// The request is permitted after the given header mutations are applied.
+    Allow(HeaderMutations),
+
+    /// The request is denied and the given response should be returned to the
+    /// client.
+    Deny(http::Response<hyper::Body>),
+}
+
+/// Headers to be modified on a permitted request.
+#[derive(Clone, Debug, Default)]
+pub struct HeaderMutations {
+    set: HeaderMap,
+    append: HeaderMap,
+    remove: Vec<HeaderName>,
+}
+
+/// Indicates that an authorization decision could not be obtained.
+#[derive(Debug, Error)]
+#[error("external authorization failed: {0}")]
+pub struct AuthorizationFailed(#[source] Error);
+
+#[derive(Debug, Error)]
+#[error("authorization service did not respond within {0:?}")]
+struct CheckTimeout(Duration);
+
+/// Obtains authorization decisions from an external service.
+///
+/// `S` is the HTTP/2 client used to reach the authorization service.
+#[derive(Clone)]
+pub struct Authorizer<S> {
+    client: Client<S>,
+    timeout: Duration,
+    failure_mode_allow: bool,
+}
+
+#[derive(Clone)]
+enum Client<S> {
+    Grpc(grpc::Client<S>),
+    Http(Box<http_service::Client<S>>),
+}
+
+// === impl Authorizer ===
+
+impl<S> Authorizer<S> {
+    /// Checks requests with an `envoy.service.auth.v3.Authorization` gRPC
+    /// service.
+    pub fn grpc(svc: S, config: Config) -> Self {
+        Self {
+            client: Client::Grpc(grpc::Client::new(svc)),
+            timeout: config.timeout,
+            failure_mode_allow: config.failure_mode_allow,
+        }
+    }
+
+    /// Checks requests with an HTTP service.
+    ///
+    /// The request's path is appended to `prefix`.
+    pub fn http(svc: S, prefix: http::uri::PathAndQuery, config: Config) -> Self {
+        Self {
+            client: Client::Http(Box::new(http_service::Client::new(
+                svc,
+                prefix,
+                config.allowed_request_headers,
+                config.allowed_upstream_headers,
+            ))),
+            timeout: config.timeout,
+            failure_mode_allow: config.failure_mode_allow,
+        }
+    }
+}
+
+impl<S> Authorizer<S>
+where
+    S: GrpcService<BoxBody> + Clone + Send + 'static,
+    S::ResponseBody: Send + Sync + 'static,
+    <S::ResponseBody as Body>::Data: Send,
+    <S::ResponseBody as Body>::Error: Into<Error> + Send,
+    S::Future: Send,
+{
+    /// Obtains a decision for the described request.
+    ///
+    /// If the authorization service fails and the authorizer is configured to
+    /// fail open, the request is permitted without modification.
+    pub fn check(
+        &self,
+        req: CheckRequest,
+    ) -> impl Future<Output = Result<Decision, AuthorizationFailed>> + Send + 'static {
+        let client = self.client.clone();
+        let timeout = self.timeout;
+        let failure_mode_allow = self.failure_mode_allow;
+        async move {
+            let res = match client {
+                Client::Grpc(c) => tokio::time::timeout(timeout, c.check(req)).await,
+                Client::Http(c) => tokio::time::timeout(timeout, c.check(req)).await,
+            };
+            let error = match res {
+                Ok(Ok(decision)) => {
+                    debug!(?decision);
+                    return Ok(decision);
+                }
+                Ok(Err(error)) => error,
+                Err(_) => CheckTimeout(timeout).into(),
+            };
+
+            if failure_mode_allow {
+                warn!(%error, "Authorization service failed; permitting request");
+                return Ok(Decision::Allow(HeaderMutations::default()));
+            }
+            Err(AuthorizationFailed(error))
+        }
+    }
+}
+
+impl<S> fmt::Debug for Authorizer<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let client = match self.client {
+            Client::Grpc(_) => "grpc",
+            Client::Http(_) => "http",
+        };
+        f.debug_struct("Authorizer")
+            .field("client", &client)
+            .field("timeout", &self.timeout)
+            .field("failure_mode_allow", &self.failure_mode_allow)
+            .finish()
+    }
+}
+
+// === impl HeaderMutations ===
+
+impl HeaderMutations {
+    /// Applies the mutations to a request's headers.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        for name in self.remove.iter().chain(self.set.keys()) {
+            headers.remove(name);
+        }
+        for (name, value) in &self.set {
+            headers.append(name, value.clone());
+        }
+        for (name, value) in &self.append {
+            headers.append(name, value.clone());
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.append.is_empty() && self.remove.is_empty()
+    }
+}
@@ -0,0 +1,138 @@
+//! A subset of the `envoy.service.auth.v3` protobuf API.
+//!
+//! Only the fields used by the proxy are described here. Fields are tagged
+//! identically to the upstream definitions so that messages are
+//! wire-compatible with ext_authz servers; unknown fields are ignored when
+//! decoding.
+
+use std::collections::HashMap;
+
+pub const CHECK_PATH: &str = "/envoy.service.auth.v3.Authorization/Check";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CheckRequest {
+    #[prost(message, optional, tag = "1")]
+    pub attributes: Option<AttributeContext>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AttributeContext {
+    #[prost(message, optional, tag = "1")]
+    pub source: Option<Peer>,
+    #[prost(message, optional, tag = "2")]
+    pub destination: Option<Peer>,
+    #[prost(message, optional, tag = "4")]
+    pub request: Option<Request>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Peer {
+    #[prost(message, optional, tag = "1")]
+    pub address: Option<Address>,
+    #[prost(string, tag = "4")]
+    pub principal: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Address {
+    #[prost(message, optional, tag = "1")]
+    pub socket_address: Option<SocketAddress>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SocketAddress {
+    #[prost(string, tag = "2")]
+    pub address: String,
+    #[prost(uint32, tag = "3")]
+    pub port_value: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Request {
+    #[prost(message, optional, tag = "2")]
+    pub http: Option<HttpRequest>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct HttpRequest {
+    #[prost(string, tag = "2")]
+    pub method: String,
+    #[prost(map = "string, string", tag = "3")]
+    pub headers: HashMap<String, String>,
+    #[prost(string, tag = "4")]
+    pub path: String,
+    #[prost(string, tag = "5")]
+    pub host: String,
+    #[prost(string, tag = "6")]
+    pub scheme: String,
+    #[prost(string, tag = "10")]
+    pub protocol: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CheckResponse {
+    #[prost(message, optional, tag = "1")]
+    pub status: Option<Status>,
+    #[prost(oneof = "check_response::HttpResponse", tags = "2, 3")]
+    pub http_response: Option<check_response::HttpResponse>,
+}
+
+pub mod check_response {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum HttpResponse {
+        #[prost(message, tag = "2")]
+        DeniedResponse(super::DeniedHttpResponse),
+        #[prost(message, tag = "3")]
+        OkResponse(super::OkHttpResponse),
+    }
+}
+
+/// A `google.rpc.Status`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Status {
+    #[prost(int32, tag = "1")]
+    pub code: i32,
+    #[prost(string, tag = "2")]
+    pub message: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DeniedHttpResponse {
+    #[prost(message, optional, tag = "1")]
+    pub status: Option<HttpStatus>,
+    #[prost(message, repeated, tag = "2")]
+    pub headers: Vec<HeaderValueOption>,
+    #[prost(string, tag = "3")]
+    pub body: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OkHttpResponse {
+    #[prost(message, repeated, tag = "2")]
+    pub headers: Vec<HeaderValueOption>,
+    #[prost(string, repeated, tag = "5")]
+    pub headers_to_remove: Vec<String>,
+}
+
+/// An `envoy.type.v3.HttpStatus`, whose code is the numeric HTTP status.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct HttpStatus {
+    #[prost(int32, tag = "1")]
+    pub code: i32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct HeaderValueOption {
+    #[prost(message, optional, tag = "1")]
+    pub header: Option<HeaderValue>,
+    #[prost(message, optional, tag = "2")]
+    pub append: Option<bool>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct HeaderValue {
+    #[prost(string, tag = "1")]
+    pub key: String,
+    #[prost(string, tag = "2")]
+    pub value: String,
+}
@@ -0,0 +1,341 @@
+use crate::{Metrics, IDEMPOTENCY_KEY, IDEMPOTENT_REPLAYED};
+use bytes::Bytes;
+use http::{header::HeaderValue, HeaderMap};
+use linkerd_http_box::BoxBody;
+use parking_lot::Mutex;
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::Instant;
+use tracing::trace;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Config {
+    /// The maximum number of responses that are stored.
+    pub capacity: usize,
+
+    /// The largest response body that is stored.
+    pub max_body_bytes: usize,
+}
+
+/// A bounded store of responses, keyed by request and idempotency key.
+///
+/// When the cache is full, the oldest response is evicted to make room for a
+/// new one.
+#[derive(Clone, Debug)]
+pub struct Cache {
+    config: Config,
+    entries: Arc<Mutex<Entries>>,
+    metrics: Metrics,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Key {
+    method: http::Method,
+    authority: Option<http::uri::Authority>,
+    path: String,
+    key: HeaderValue,
+}
+
+/// A complete response.
+#[derive(Debug)]
+pub(crate) struct Stored {
+    pub(crate) status: http::StatusCode,
+    pub(crate) version: http::Version,
+    pub(crate) headers: HeaderMap,
+    pub(crate) body: Bytes,
+    pub(crate) trailers: Option<HeaderMap>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    by_key: HashMap<Key, Entry>,
+    /// Keys in the order they were stored, tagged with the sequence number of
+    /// the entry they were stored with, so that replaced entries are skipped.
+    order: VecDeque<(u64, Key)>,
+    next_seq: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    seq: u64,
+    expires: Instant,
+    response: Arc<Stored>,
+}
+
+/// Replays a stored response's body and trailers.
+#[derive(Debug)]
+struct Replay {
+    data: Option<Bytes>,
+    trailers: Option<HeaderMap>,
+}
+
+// === impl Cache ===
+
+impl Cache {
+    pub fn new(config: Config, metrics: Metrics) -> Self {
+        Self {
+            config,
+            entries: Default::default(),
+            metrics,
+        }
+    }
+
+    pub(crate) fn max_body_bytes(&self) -> usize {
+        self.config.max_body_bytes
+    }
+
+    /// Returns a replay of the stored response for `key`, if one has not
+    /// expired.
+    pub(crate) fn get(&self, key: &Key) -> Option<http::Response<BoxBody>> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock();
+        let stored = match entries.by_key.get(key) {
+            Some(entry) if entry.expires > now => Some(entry.response.clone()),
+            Some(_) => {
+                entries.by_key.remove(key);
+                self.metrics.expired();
+                None
+            }
+            None => None,
+        };
+        drop(entries);
+
+        match stored {
+            Some(stored) => {
+                trace!(?key, "Replaying response");
+                self.metrics.hit();
+                Some(stored.replay())
+            }
+            None => {
+                self.metrics.miss();
+                None
+            }
+        }
+    }
+
+    /// Stores a response for `key`, unless an unexpired response is already
+    /// stored for it.
+    pub(crate) fn insert(&self, key: Key, response: Stored, ttl: Duration) {
+        if self.config.capacity == 0 {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut entries = self.entries.lock();
+        let Entries {
+            by_key,
+            order,
+            next_seq,
+        } = &mut *entries;
+
+        if let Some(entry) = by_key.get(&key) {
+            if entry.expires > now {
+                return;
+            }
+        }
+
+        // Drop entries that have expired or been replaced from the front of
+        // the queue, and then evict the oldest entries until there is room.
+        while let Some((seq, oldest)) = order.front() {
+            let remove = match by_key.get(oldest) {
+                Some(entry) if entry.seq == *seq => {
+                    if entry.expires <= now {
+                        self.metrics.expired();
+                    } else if by_key.len() >= self.config.capacity {
+                        self.metrics.evicted();
+                    } else {
+                        break;
+                    }
+                    true
+                }
+                _ => false,
+            };
+            let (_, oldest) = order.pop_front().expect("queue must not be empty");
+            if remove {
+                by_key.remove(&oldest);
+            }
+        }
+
+        let seq = *next_seq;
+        *next_seq += 1;
+        let entry = Entry {
+            seq,
+            expires: now + ttl,
+            response: Arc::new(response),
+        };
+        if by_key.insert(key.clone(), entry).is_some() {
+            self.metrics.expired();
+        }
+        self.metrics.stored();
+        order.push_back((seq, key));
+
+        // Entries that expire before they reach the front of the queue leave
+        // stale keys behind, so the queue is compacted as it grows.
+        if order.len() > self.config.capacity * 2 {
+            order.retain(|(seq, key)| by_key.get(key).map(|e| e.seq == *seq).unwrap_or(false));
+        }
+    }
+}
+
+// === impl Key ===
+
+impl Key {
+    pub(crate) fn from_request<B>(req: &http::Request<B>) -> Option<Self> {
+        let key = req.headers().get(IDEMPOTENCY_KEY)?.clone();
+        Some(Self {
+            method: req.method().clone(),
+            authority: req.uri().authority().cloned(),
+            path: req
+                .uri()
+                .path_and_query()
+                .map(|pq| pq.as_str().to_string())
+                .unwrap_or_default(),
+            key,
+        })
+    }
+}
+
+// === impl Stored ===
+
+impl Stored {
+    fn replay(&self) -> http::Response<BoxBody> {
+        let body = Replay {
+            data: Some(self.body.clone()).filter(|b| !b.is_empty()),
+            trailers: self.trailers.clone(),
+        };
+        let mut rsp = http::Response::new(BoxBody::new(body));
+        *rsp.status_mut() = self.status;
+        *rsp.version_mut() = self.version;
+        *rsp.headers_mut() = self.headers.clone();
+        rsp.headers_mut()
+            .insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+        rsp
+    }
+}
+
+// === impl Replay ===
+
+impl http_body::Body for Replay {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Poll::Ready(self.get_mut().data.take().map(Ok))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(self.get_mut().trailers.take()))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.data.is_none() && self.trailers.is_none()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        let len = self.data.as_ref().map(|d| d.len()).unwrap_or(0);
+        http_body::SizeHint::with_exact(len as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(k: &'static str) -> Key {
+        let req = http::Request::builder()
+            .uri("http://example.com/orders")
+            .method("POST")
+            .header(IDEMPOTENCY_KEY, k)
+            .body(())
+            .unwrap();
+        Key::from_request(&req).unwrap()
+    }
+
+    fn stored(body: &'static str) -> Stored {
+        Stored {
+            status: http::StatusCode::CREATED,
+            version: http::Version::HTTP_11,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(body.as_bytes()),
+            trailers: None,
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn expires_entries() {
+        let cache = Cache::new(
+            Config {
+                capacity: 10,
+                max_body_bytes: 1024,
+            },
+            Metrics::default(),
+        );
+        assert!(cache.get(&key("a")).is_none());
+
+        cache.insert(key("a"), stored("a"), Duration::from_secs(10));
+        let rsp = cache.get(&key("a")).expect("must be cached");
+        assert_eq!(rsp.status(), http::StatusCode::CREATED);
+        assert_eq!(rsp.headers()[IDEMPOTENT_REPLAYED], "true");
+        let body = hyper::body::to_bytes(rsp.into_body()).await.unwrap();
+        assert_eq!(body, "a");
+
+        tokio::time::advance(Duration::from_secs(11)).await;
+        assert!(cache.get(&key("a")).is_none());
+        assert!(cache.entries.lock().by_key.is_empty());
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn evicts_oldest() {
+        let cache = Cache::new(
+            Config {
+                capacity: 2,
+                max_body_bytes: 1024,
+            },
+            Metrics::default(),
+        );
+        let ttl = Duration::from_secs(10);
+        cache.insert(key("a"), stored("a"), ttl);
+        cache.insert(key("b"), stored("b"), ttl);
+        // The first response for a key is retained.
+        cache.insert(key("b"), stored("x"), ttl);
+        assert!(cache.get(&key("a")).is_some());
+
+        cache.insert(key("c"), stored("c"), ttl);
+        assert!(cache.get(&key("a")).is_none());
+        let rsp = cache.get(&key("b")).expect("must be cached");
+        let body = hyper::body::to_bytes(rsp.into_body()).await.unwrap();
+        assert_eq!(body, "b");
+        assert!(cache.get(&key("c")).is_some());
+        assert_eq!(cache.entries.lock().by_key.len(), 2);
+    }
+
+    #[test]
+    fn keys_include_request() {
+        let req = http::Request::builder()
+            .uri("http://example.com/orders?id=1")
+            .method("POST")
+            .header(IDEMPOTENCY_KEY, "a")
+            .body(())
+            .unwrap();
+        let k = Key::from_request(&req).unwrap();
+        assert_ne!(k, key("a"));
+
+        let req = http::Request::builder()
+            .uri("http://example.com/orders")
+            .body(())
+            .unwrap();
+        assert!(Key::from_request(&req).is_none());
+    }
+}
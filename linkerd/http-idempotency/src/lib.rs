@@ -0,0 +1,35 @@
+//! Replays cached responses to requests that repeat an idempotency key.
+//!
+//! Clients mark requests that are safe to deduplicate with an
+//! `Idempotency-Key` header. On routes that enable deduplication, the first
+//! complete, non-5xx response to each key is stored in a bounded in-memory
+//! [`Cache`] for the route's TTL, and subsequent requests with the same
+//! method, authority, path, and key are answered from the cache (with an
+//! `idempotent-replayed: true` header) rather than being forwarded again.
+//!
+//! Requests with the same key that are in flight concurrently are all
+//! forwarded; only completed responses are replayed.
+
+#![deny(warnings, rust_2018_idioms)]
+#![forbid(unsafe_code)]
+
+mod cache;
+mod metrics;
+mod service;
+
+pub use self::{
+    cache::{Cache, Config},
+    metrics::Metrics,
+    service::{Idempotent, NewIdempotent, ResponseFuture},
+};
+use std::time::Duration;
+
+/// The request header that identifies repeated submissions of a request.
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+/// The response header that marks responses replayed from the cache.
+pub const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+
+/// How long a route's responses are retained in the cache.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Ttl(pub Duration);
@@ -0,0 +1,383 @@
+use crate::{
+    cache::{Key, Stored},
+    Cache, Ttl,
+};
+use bytes::{Buf, Bytes, BytesMut};
+use futures::ready;
+use http::HeaderMap;
+use http_body::Body;
+use linkerd_error::Error;
+use linkerd_http_box::BoxBody;
+use linkerd_stack::{layer, NewService, Param, Proxy};
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tracing::trace;
+
+/// Builds `Idempotent` proxies for targets that configure a `Ttl`.
+#[derive(Clone, Debug)]
+pub struct NewIdempotent<N> {
+    cache: Option<Cache>,
+    inner: N,
+}
+
+/// Replays cached responses to requests that repeat an idempotency key, and
+/// stores the responses to requests that do not.
+#[derive(Clone, Debug)]
+pub struct Idempotent<P> {
+    cache: Option<(Cache, Duration)>,
+    inner: P,
+}
+
+#[pin_project(project = ResponseFutureProj)]
+#[derive(Debug)]
+pub enum ResponseFuture<F> {
+    Forward(#[pin] F),
+    Record {
+        #[pin]
+        inner: F,
+        recording: Option<Recording>,
+    },
+    Cached(Option<http::Response<BoxBody>>),
+}
+
+/// Accumulates a response so that it can be stored once it completes.
+#[derive(Debug)]
+pub struct Recording {
+    cache: Cache,
+    key: Key,
+    ttl: Duration,
+    status: http::StatusCode,
+    version: http::Version,
+    headers: HeaderMap,
+    body: BytesMut,
+}
+
+/// Records a response body as it is read.
+#[pin_project]
+#[derive(Debug)]
+struct RecordBody<B> {
+    #[pin]
+    inner: B,
+    recording: Option<Recording>,
+}
+
+// === impl NewIdempotent ===
+
+impl<N> NewIdempotent<N> {
+    pub fn layer(cache: Option<Cache>) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            cache: cache.clone(),
+            inner,
+        })
+    }
+}
+
+impl<T, N> NewService<T> for NewIdempotent<N>
+where
+    T: Param<Option<Ttl>>,
+    N: NewService<T>,
+{
+    type Service = Idempotent<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let ttl: Option<Ttl> = target.param();
+        let cache = match (self.cache.clone(), ttl) {
+            (Some(cache), Some(Ttl(ttl))) => Some((cache, ttl)),
+            _ => None,
+        };
+        Idempotent {
+            cache,
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl Idempotent ===
+
+impl<P, S, A, B> Proxy<http::Request<A>, S> for Idempotent<P>
+where
+    P: Proxy<http::Request<A>, S, Response = http::Response<B>>,
+    P::Error: Into<Error>,
+    S: tower::Service<P::Request>,
+    B: Body + Send + 'static,
+    B::Data: Send + 'static,
+    B::Error: Into<Error>,
+{
+    type Request = P::Request;
+    type Response = http::Response<BoxBody>;
+    type Error = Error;
+    type Future = ResponseFuture<P::Future>;
+
+    fn proxy(&self, svc: &mut S, req: http::Request<A>) -> Self::Future {
+        let (cache, ttl) = match self.cache.as_ref() {
+            Some((cache, ttl)) => (cache, *ttl),
+            None => return ResponseFuture::Forward(self.inner.proxy(svc, req)),
+        };
+        let key = match Key::from_request(&req) {
+            Some(key) => key,
+            None => return ResponseFuture::Forward(self.inner.proxy(svc, req)),
+        };
+
+        if let Some(rsp) = cache.get(&key) {
+            return ResponseFuture::Cached(Some(rsp));
+        }
+
+        ResponseFuture::Record {
+            inner: self.inner.proxy(svc, req),
+            recording: Some(Recording {
+                cache: cache.clone(),
+                key,
+                ttl,
+                status: http::StatusCode::OK,
+                version: http::Version::default(),
+                headers: HeaderMap::new(),
+                body: BytesMut::new(),
+            }),
+        }
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F, B, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<http::Response<B>, E>>,
+    E: Into<Error>,
+    B: Body + Send + 'static,
+    B::Data: Send + 'static,
+    B::Error: Into<Error>,
+{
+    type Output = Result<http::Response<BoxBody>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            ResponseFutureProj::Forward(f) => {
+                let rsp = ready!(f.poll(cx)).map_err(Into::into)?;
+                Poll::Ready(Ok(rsp.map(BoxBody::new)))
+            }
+            ResponseFutureProj::Record { inner, recording } => {
+                let rsp = ready!(inner.poll(cx)).map_err(Into::into)?;
+                let mut recording = recording.take().expect("polled after ready");
+
+                // Server errors may be transient, so they are not replayed.
+                if rsp.status().is_server_error() {
+                    return Poll::Ready(Ok(rsp.map(BoxBody::new)));
+                }
+
+                recording.status = rsp.status();
+                recording.version = rsp.version();
+                recording.headers = rsp.headers().clone();
+                if rsp.body().is_end_stream() {
+                    recording.store(None);
+                    return Poll::Ready(Ok(rsp.map(BoxBody::new)));
+                }
+
+                Poll::Ready(Ok(rsp.map(|inner| {
+                    BoxBody::new(RecordBody {
+                        inner,
+                        recording: Some(recording),
+                    })
+                })))
+            }
+            ResponseFutureProj::Cached(rsp) => {
+                Poll::Ready(Ok(rsp.take().expect("polled after ready")))
+            }
+        }
+    }
+}
+
+// === impl Recording ===
+
+impl Recording {
+    fn store(self, trailers: Option<HeaderMap>) {
+        trace!(key = ?self.key, "Storing response");
+        let response = Stored {
+            status: self.status,
+            version: self.version,
+            headers: self.headers,
+            body: self.body.freeze(),
+            trailers,
+        };
+        self.cache.insert(self.key, response, self.ttl);
+    }
+}
+
+// === impl RecordBody ===
+
+impl<B> Body for RecordBody<B>
+where
+    B: Body,
+    B::Error: Into<Error>,
+{
+    type Data = Bytes;
+    type Error = Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let mut this = self.project();
+        let data = match ready!(this.inner.as_mut().poll_data(cx)) {
+            Some(Ok(mut data)) => data.copy_to_bytes(data.remaining()),
+            Some(Err(e)) => {
+                // Incomplete responses are not stored.
+                *this.recording = None;
+                return Poll::Ready(Some(Err(e.into())));
+            }
+            None => {
+                // If the body has no trailers, it is complete.
+                if this.inner.is_end_stream() {
+                    if let Some(recording) = this.recording.take() {
+                        recording.store(None);
+                    }
+                }
+                return Poll::Ready(None);
+            }
+        };
+
+        if let Some(recording) = this.recording.as_mut() {
+            if recording.body.len() + data.len() > recording.cache.max_body_bytes() {
+                trace!(key = ?recording.key, "Response body too large to store");
+                *this.recording = None;
+            } else {
+                recording.body.extend_from_slice(&data);
+            }
+        }
+        Poll::Ready(Some(Ok(data)))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let trailers = ready!(this.inner.poll_trailers(cx)).map_err(Into::into);
+        match trailers.as_ref() {
+            Ok(trailers) => {
+                if let Some(recording) = this.recording.take() {
+                    recording.store(trailers.clone());
+                }
+            }
+            Err(_) => *this.recording = None,
+        }
+        Poll::Ready(trailers)
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Metrics, IDEMPOTENCY_KEY, IDEMPOTENT_REPLAYED};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use tower::ServiceExt;
+
+    fn cache(max_body_bytes: usize) -> Cache {
+        Cache::new(
+            Config {
+                capacity: 10,
+                max_body_bytes,
+            },
+            Metrics::default(),
+        )
+    }
+
+    async fn send(
+        proxy: &Idempotent<()>,
+        calls: &Arc<AtomicUsize>,
+        status: http::StatusCode,
+        key: Option<&'static str>,
+    ) -> (http::Response<BoxBody>, Bytes) {
+        let calls = calls.clone();
+        let mut svc = tower::service_fn(move |_: http::Request<()>| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            let rsp = http::Response::builder()
+                .status(status)
+                .body(hyper::Body::from("hello"))
+                .unwrap();
+            futures::future::ok::<_, Error>(rsp)
+        });
+        let mut req = http::Request::builder()
+            .method("POST")
+            .uri("http://example.com/orders");
+        if let Some(key) = key {
+            req = req.header(IDEMPOTENCY_KEY, key);
+        }
+        let svc = svc.ready().await.unwrap();
+        let rsp = proxy.proxy(svc, req.body(()).unwrap()).await.unwrap();
+        let (parts, body) = rsp.into_parts();
+        let body = hyper::body::to_bytes(body).await.unwrap();
+        (http::Response::from_parts(parts, BoxBody::default()), body)
+    }
+
+    fn proxy(cache: Cache, ttl: Option<Ttl>) -> Idempotent<()> {
+        Idempotent {
+            cache: ttl.map(|Ttl(ttl)| (cache, ttl)),
+            inner: (),
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn replays_responses() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let proxy = proxy(cache(1024), Some(Ttl(Duration::from_secs(10))));
+
+        let (rsp, body) = send(&proxy, &calls, http::StatusCode::CREATED, Some("a")).await;
+        assert_eq!(rsp.status(), http::StatusCode::CREATED);
+        assert!(rsp.headers().get(IDEMPOTENT_REPLAYED).is_none());
+        assert_eq!(body, "hello");
+
+        let (rsp, body) = send(&proxy, &calls, http::StatusCode::CREATED, Some("a")).await;
+        assert_eq!(rsp.status(), http::StatusCode::CREATED);
+        assert_eq!(rsp.headers()[IDEMPOTENT_REPLAYED], "true");
+        assert_eq!(body, "hello");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Requests with other keys, or without a key, are forwarded.
+        send(&proxy, &calls, http::StatusCode::CREATED, Some("b")).await;
+        send(&proxy, &calls, http::StatusCode::CREATED, None).await;
+        send(&proxy, &calls, http::StatusCode::CREATED, None).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn does_not_store() {
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        // Server errors are not replayed.
+        let p = proxy(cache(1024), Some(Ttl(Duration::from_secs(10))));
+        send(&p, &calls, http::StatusCode::BAD_GATEWAY, Some("a")).await;
+        send(&p, &calls, http::StatusCode::BAD_GATEWAY, Some("a")).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Bodies that exceed the limit are not stored.
+        let p = proxy(cache(2), Some(Ttl(Duration::from_secs(10))));
+        let (_, body) = send(&p, &calls, http::StatusCode::OK, Some("a")).await;
+        assert_eq!(body, "hello");
+        send(&p, &calls, http::StatusCode::OK, Some("a")).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        // Routes without a TTL are not cached.
+        let p = proxy(cache(1024), None);
+        send(&p, &calls, http::StatusCode::OK, Some("a")).await;
+        send(&p, &calls, http::StatusCode::OK, Some("a")).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 6);
+    }
+}
@@ -0,0 +1,166 @@
+use serde_json::{Map, Value};
+use std::{fmt, sync::Arc};
+
+/// The claims of a validated token.
+///
+/// Validated claims are set as a request extension so that they may be used
+/// to authorize requests.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Claims(Arc<Map<String, Value>>);
+
+/// Requires that requests to a route present a validated token with the given
+/// claims.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequiredClaims {
+    /// If unset, requests with any method match.
+    pub method: Option<String>,
+
+    /// Requests match if this is their path or one of its parent paths.
+    pub path_prefix: String,
+
+    /// Each claim must have the given value or, if the claim is a list, must
+    /// contain it.
+    pub claims: Vec<(String, String)>,
+}
+
+// === impl Claims ===
+
+impl Claims {
+    pub(crate) fn new(claims: Map<String, Value>) -> Self {
+        Self(Arc::new(claims))
+    }
+
+    /// Returns the token's subject (`sub`), if one was set.
+    pub fn subject(&self) -> Option<&str> {
+        self.get("sub").and_then(Value::as_str)
+    }
+
+    /// Returns the token's issuer (`iss`), if one was set.
+    pub fn issuer(&self) -> Option<&str> {
+        self.get("iss").and_then(Value::as_str)
+    }
+
+    /// Returns the token's audiences (`aud`).
+    ///
+    /// The audience claim may be encoded as either a single string or a list
+    /// of strings.
+    pub fn audiences(&self) -> impl Iterator<Item = &str> {
+        let (one, many) = match self.get("aud") {
+            Some(Value::String(aud)) => (Some(aud.as_str()), None),
+            Some(Value::Array(auds)) => (None, Some(auds.iter().filter_map(Value::as_str))),
+            _ => (None, None),
+        };
+        one.into_iter().chain(many.into_iter().flatten())
+    }
+
+    /// Returns an arbitrary claim.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0.get(name)
+    }
+
+    /// Returns true if the named claim is the given string or is a list that
+    /// contains it.
+    pub fn contains(&self, name: &str, value: &str) -> bool {
+        match self.get(name) {
+            Some(Value::String(v)) => v == value,
+            Some(Value::Array(vs)) => vs.iter().any(|v| v.as_str() == Some(value)),
+            _ => false,
+        }
+    }
+}
+
+// === impl RequiredClaims ===
+
+impl RequiredClaims {
+    pub fn matches(&self, method: &str, path: &str) -> bool {
+        if !self.method.as_ref().map(|m| m == method).unwrap_or(true) {
+            return false;
+        }
+        match path.strip_prefix(self.path_prefix.as_str()) {
+            Some(rest) => {
+                rest.is_empty() || rest.starts_with('/') || self.path_prefix.ends_with('/')
+            }
+            None => false,
+        }
+    }
+
+    /// Returns true if the request's claims, if it presented a valid token,
+    /// satisfy this route's requirements.
+    pub fn permits(&self, claims: Option<&Claims>) -> bool {
+        match claims {
+            Some(claims) => self.claims.iter().all(|(n, v)| claims.contains(n, v)),
+            None => false,
+        }
+    }
+}
+
+impl fmt::Display for RequiredClaims {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.method {
+            Some(ref method) => write!(f, "{} {}", method, self.path_prefix),
+            None => self.path_prefix.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(json: Value) -> Claims {
+        match json {
+            Value::Object(claims) => Claims::new(claims),
+            _ => panic!("claims must be an object"),
+        }
+    }
+
+    #[test]
+    fn matches_route() {
+        let route = RequiredClaims {
+            method: Some("POST".to_string()),
+            path_prefix: "/admin".to_string(),
+            claims: vec![],
+        };
+        assert!(route.matches("POST", "/admin"));
+        assert!(route.matches("POST", "/admin/users"));
+        assert!(!route.matches("POST", "/administrator"));
+        assert!(!route.matches("GET", "/admin"));
+        assert!(!route.matches("POST", "/"));
+
+        let route = RequiredClaims {
+            method: None,
+            path_prefix: "/".to_string(),
+            claims: vec![],
+        };
+        assert!(route.matches("GET", "/"));
+        assert!(route.matches("DELETE", "/users/1"));
+    }
+
+    #[test]
+    fn permits_claims() {
+        let route = RequiredClaims {
+            method: None,
+            path_prefix: "/".to_string(),
+            claims: vec![
+                ("iss".to_string(), "https://issuer.example.com".to_string()),
+                ("groups".to_string(), "admin".to_string()),
+            ],
+        };
+        assert!(route.permits(Some(&claims(serde_json::json!({
+            "iss": "https://issuer.example.com",
+            "groups": ["dev", "admin"],
+        })))));
+        assert!(!route.permits(Some(&claims(serde_json::json!({
+            "iss": "https://issuer.example.com",
+            "groups": ["dev"],
+        })))));
+        assert!(!route.permits(Some(&claims(serde_json::json!({
+            "iss": "https://issuer.example.com",
+            "groups": "dev",
+        })))));
+        assert!(!route.permits(Some(&claims(serde_json::json!({
+            "groups": "admin",
+        })))));
+        assert!(!route.permits(None));
+    }
+}
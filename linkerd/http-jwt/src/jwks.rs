@@ -0,0 +1,189 @@
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use serde_json::Value;
+use thiserror::Error;
+use tracing::debug;
+
+/// A set of keys used to verify token signatures, as described by a JSON Web
+/// Key Set (RFC 7517).
+#[derive(Clone, Debug, Default)]
+pub struct Jwks {
+    keys: Vec<Key>,
+}
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+#[error("invalid JWKS: {0}")]
+pub struct InvalidJwks(&'static str);
+
+/// A signing algorithm supported for token verification.
+///
+/// Symmetric (`HS*`) algorithms and unsigned (`none`) tokens are never
+/// accepted.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Algorithm {
+    Rs256,
+    Rs384,
+    Rs512,
+    Es256,
+    Es384,
+}
+
+#[derive(Clone, Debug)]
+struct Key {
+    id: Option<String>,
+    alg: Option<Algorithm>,
+    material: Material,
+}
+
+#[derive(Clone, Debug)]
+enum Material {
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    EcP256 { point: Vec<u8> },
+    EcP384 { point: Vec<u8> },
+}
+
+// === impl Jwks ===
+
+impl Jwks {
+    /// Parses a JSON-encoded JSON Web Key Set.
+    ///
+    /// Keys that are not used for signatures or that use unsupported key
+    /// types are ignored.
+    pub fn from_json(json: &[u8]) -> Result<Self, InvalidJwks> {
+        let set = serde_json::from_slice::<Value>(json).map_err(|_| InvalidJwks("not JSON"))?;
+        let keys = set
+            .get("keys")
+            .and_then(Value::as_array)
+            .ok_or(InvalidJwks("missing keys"))?;
+
+        let mut jwks = Jwks::default();
+        for jwk in keys {
+            if let Some(key) = Key::from_jwk(jwk)? {
+                jwks.keys.push(key);
+            }
+        }
+        Ok(jwks)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Verifies `sig` over `msg` with any key that matches the token's key ID
+    /// and algorithm.
+    pub(crate) fn verify(&self, kid: Option<&str>, alg: Algorithm, msg: &[u8], sig: &[u8]) -> bool {
+        self.keys
+            .iter()
+            .filter(|k| kid.is_none() || k.id.as_deref() == kid)
+            .any(|k| k.verify(alg, msg, sig))
+    }
+}
+
+// === impl Algorithm ===
+
+impl Algorithm {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "RS256" => Some(Self::Rs256),
+            "RS384" => Some(Self::Rs384),
+            "RS512" => Some(Self::Rs512),
+            "ES256" => Some(Self::Es256),
+            "ES384" => Some(Self::Es384),
+            _ => None,
+        }
+    }
+}
+
+// === impl Key ===
+
+impl Key {
+    fn from_jwk(jwk: &Value) -> Result<Option<Self>, InvalidJwks> {
+        let field = |name| jwk.get(name).and_then(Value::as_str);
+        let decode = |name| -> Result<Vec<u8>, InvalidJwks> {
+            let v = field(name).ok_or(InvalidJwks("missing key parameter"))?;
+            base64::decode_config(v, base64::URL_SAFE_NO_PAD)
+                .map_err(|_| InvalidJwks("key parameter is not base64url-encoded"))
+        };
+
+        if field("use").map(|u| u != "sig").unwrap_or(false) {
+            debug!(kid = ?field("kid"), "Ignoring non-signing key");
+            return Ok(None);
+        }
+
+        let alg = match field("alg") {
+            None => None,
+            Some(alg) => match Algorithm::from_name(alg) {
+                Some(alg) => Some(alg),
+                None => {
+                    debug!(kid = ?field("kid"), %alg, "Ignoring key with unsupported algorithm");
+                    return Ok(None);
+                }
+            },
+        };
+
+        let material = match (field("kty"), field("crv")) {
+            (Some("RSA"), _) => Material::Rsa {
+                n: decode("n")?,
+                e: decode("e")?,
+            },
+            (Some("EC"), Some(crv @ "P-256")) | (Some("EC"), Some(crv @ "P-384")) => {
+                // Encode the public key as an uncompressed point.
+                let mut point = vec![0x04];
+                point.extend(decode("x")?);
+                point.extend(decode("y")?);
+                if crv == "P-256" {
+                    Material::EcP256 { point }
+                } else {
+                    Material::EcP384 { point }
+                }
+            }
+            (kty, crv) => {
+                debug!(kid = ?field("kid"), ?kty, ?crv, "Ignoring unsupported key type");
+                return Ok(None);
+            }
+        };
+
+        Ok(Some(Key {
+            id: field("kid").map(String::from),
+            alg,
+            material,
+        }))
+    }
+
+    fn verify(&self, alg: Algorithm, msg: &[u8], sig: &[u8]) -> bool {
+        if self.alg.map(|a| a != alg).unwrap_or(false) {
+            return false;
+        }
+
+        let rsa = |n: &[u8], e: &[u8], params| {
+            RsaPublicKeyComponents { n, e }
+                .verify(params, msg, sig)
+                .is_ok()
+        };
+        match (&self.material, alg) {
+            (Material::Rsa { n, e }, Algorithm::Rs256) => {
+                rsa(n, e, &signature::RSA_PKCS1_2048_8192_SHA256)
+            }
+            (Material::Rsa { n, e }, Algorithm::Rs384) => {
+                rsa(n, e, &signature::RSA_PKCS1_2048_8192_SHA384)
+            }
+            (Material::Rsa { n, e }, Algorithm::Rs512) => {
+                rsa(n, e, &signature::RSA_PKCS1_2048_8192_SHA512)
+            }
+            (Material::EcP256 { point }, Algorithm::Es256) => {
+                UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point)
+                    .verify(msg, sig)
+                    .is_ok()
+            }
+            (Material::EcP384 { point }, Algorithm::Es384) => {
+                UnparsedPublicKey::new(&signature::ECDSA_P384_SHA384_FIXED, point)
+                    .verify(msg, sig)
+                    .is_ok()
+            }
+            _ => false,
+        }
+    }
+}
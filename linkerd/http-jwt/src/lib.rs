@@ -0,0 +1,53 @@
+//! Validates bearer JSON Web Tokens (JWTs) on HTTP requests.
+
+#![deny(warnings, rust_2018_idioms)]
+#![forbid(unsafe_code)]
+
+mod claims;
+mod jwks;
+mod service;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+mod validator;
+
+pub use self::{
+    claims::{Claims, RequiredClaims},
+    jwks::{InvalidJwks, Jwks},
+    service::{NewValidateJwt, ValidateJwt},
+    validator::{Config, JwksSource, Validator},
+};
+use thiserror::Error;
+
+/// Indicates that a request's bearer token could not be validated.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum InvalidToken {
+    #[error("missing bearer token")]
+    Missing,
+
+    #[error("malformed bearer token")]
+    Malformed,
+
+    #[error("token validity period is out of range")]
+    InvalidTimestamp,
+
+    #[error("unsupported token algorithm: {0}")]
+    UnsupportedAlgorithm(String),
+
+    #[error("token signature could not be verified")]
+    InvalidSignature,
+
+    #[error("token has no expiration time")]
+    MissingExpiration,
+
+    #[error("token has expired")]
+    Expired,
+
+    #[error("token is not yet valid")]
+    NotYetValid,
+
+    #[error("token was not issued by a trusted issuer")]
+    InvalidIssuer,
+
+    #[error("token is not intended for this audience")]
+    InvalidAudience,
+}
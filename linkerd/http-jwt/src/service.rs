@@ -0,0 +1,271 @@
+use crate::{InvalidToken, Validator};
+use futures::{future, TryFutureExt};
+use linkerd_error::Error;
+use linkerd_stack::{layer, NewService};
+use std::task::{Context, Poll};
+use tracing::{debug, trace};
+
+/// Builds `ValidateJwt` services.
+///
+/// When no validator is configured, requests are passed through unmodified.
+#[derive(Clone, Debug)]
+pub struct NewValidateJwt<N> {
+    inner: N,
+    validator: Option<Validator>,
+}
+
+/// Validates a request's bearer token, if one is present.
+#[derive(Clone, Debug)]
+pub struct ValidateJwt<S> {
+    inner: S,
+    validator: Option<Validator>,
+}
+
+// === impl NewValidateJwt ===
+
+impl<N> NewValidateJwt<N> {
+    pub fn layer(validator: Option<Validator>) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            inner,
+            validator: validator.clone(),
+        })
+    }
+}
+
+impl<T, N: NewService<T>> NewService<T> for NewValidateJwt<N> {
+    type Service = ValidateJwt<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        ValidateJwt {
+            inner: self.inner.new_service(target),
+            validator: self.validator.clone(),
+        }
+    }
+}
+
+// === impl ValidateJwt ===
+
+impl<S, B> tower::Service<http::Request<B>> for ValidateJwt<S>
+where
+    S: tower::Service<http::Request<B>>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::Either<
+        future::ErrInto<S::Future, Error>,
+        future::Ready<Result<S::Response, Error>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        if let Some(validator) = self.validator.as_ref() {
+            match bearer_token(req.headers()) {
+                Some(Ok(token)) => match validator.validate(token) {
+                    Ok(claims) => {
+                        trace!(sub = ?claims.subject(), "Validated bearer token");
+                        // Makes the claims available to authorization
+                        // policies.
+                        req.extensions_mut().insert(claims);
+                    }
+                    Err(error) => {
+                        debug!(%error, "Invalid bearer token");
+                        return future::Either::Right(future::err(error.into()));
+                    }
+                },
+                Some(Err(error)) => {
+                    debug!(%error, "Invalid authorization header");
+                    return future::Either::Right(future::err(error.into()));
+                }
+                None if validator.is_required() => {
+                    debug!("Missing bearer token");
+                    return future::Either::Right(future::err(InvalidToken::Missing.into()));
+                }
+                None => {}
+            }
+        }
+
+        future::Either::Left(self.inner.call(req).err_into::<Error>())
+    }
+}
+
+/// Reads a bearer token from the request's `authorization` header.
+///
+/// Returns `None` if the request has no bearer credentials; other
+/// authorization schemes are passed through to the application.
+fn bearer_token(headers: &http::HeaderMap) -> Option<Result<&str, InvalidToken>> {
+    let value = headers.get(http::header::AUTHORIZATION)?.to_str().ok()?;
+    let mut parts = value.splitn(2, ' ');
+    let scheme = parts.next()?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+    match parts.next().map(str::trim) {
+        Some(token) if !token.is_empty() => Some(Ok(token)),
+        _ => Some(Err(InvalidToken::Malformed)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util::Signer, Claims, Config, JwksSource};
+    use linkerd_stack::layer::Layer;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    use tower::ServiceExt;
+
+    fn validator(signer: &Signer, required: bool) -> Validator {
+        Validator::new(Config {
+            jwks: JwksSource::Static(signer.jwks("a")),
+            issuer: Some("https://issuer.example.com".to_string()),
+            audiences: vec![],
+            leeway: Duration::from_secs(30),
+            require_expiration: true,
+            required,
+            required_claims: vec![],
+        })
+    }
+
+    fn token(signer: &Signer) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        signer.sign(
+            "a",
+            serde_json::json!({
+                "iss": "https://issuer.example.com",
+                "sub": "alice",
+                "exp": now + 60,
+            }),
+        )
+    }
+
+    async fn call(
+        validator: Option<Validator>,
+        authorization: Option<String>,
+    ) -> Result<(), Error> {
+        let mut new_svc = NewValidateJwt::layer(validator)
+            .layer(|_: ()| tower::service_fn(|_: http::Request<()>| future::ok::<_, Error>(())));
+        let req = authorization
+            .into_iter()
+            .fold(http::Request::builder(), |req, auth| {
+                req.header(http::header::AUTHORIZATION, auth)
+            })
+            .body(())
+            .unwrap();
+        new_svc.new_service(()).oneshot(req).await
+    }
+
+    fn invalid_token(result: Result<(), Error>) -> InvalidToken {
+        result
+            .expect_err("request must be rejected")
+            .downcast_ref::<InvalidToken>()
+            .expect("error must be an InvalidToken")
+            .clone()
+    }
+
+    #[tokio::test]
+    async fn valid_token() {
+        let signer = Signer::new();
+        call(
+            Some(validator(&signer, true)),
+            Some(format!("Bearer {}", token(&signer))),
+        )
+        .await
+        .expect("request must be permitted");
+    }
+
+    #[tokio::test]
+    async fn sets_claims() {
+        let signer = Signer::new();
+        let mut new_svc = NewValidateJwt::layer(Some(validator(&signer, true))).layer(|_: ()| {
+            tower::service_fn(|req: http::Request<()>| {
+                let sub = req
+                    .extensions()
+                    .get::<Claims>()
+                    .and_then(|c| c.subject().map(String::from));
+                future::ok::<_, Error>(sub)
+            })
+        });
+        let req = http::Request::builder()
+            .header(
+                http::header::AUTHORIZATION,
+                format!("Bearer {}", token(&signer)),
+            )
+            .body(())
+            .unwrap();
+        let sub = new_svc
+            .new_service(())
+            .oneshot(req)
+            .await
+            .expect("request must be permitted");
+        assert_eq!(sub.as_deref(), Some("alice"));
+    }
+
+    #[tokio::test]
+    async fn invalid_token_rejected() {
+        let signer = Signer::new();
+        let untrusted = Signer::new();
+        assert_eq!(
+            invalid_token(
+                call(
+                    Some(validator(&signer, false)),
+                    Some(format!("Bearer {}", token(&untrusted))),
+                )
+                .await
+            ),
+            InvalidToken::InvalidSignature
+        );
+        assert_eq!(
+            invalid_token(call(Some(validator(&signer, false)), Some("Bearer ".to_string())).await),
+            InvalidToken::Malformed
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_token() {
+        let signer = Signer::new();
+        call(Some(validator(&signer, false)), None)
+            .await
+            .expect("optional tokens may be omitted");
+        assert_eq!(
+            invalid_token(call(Some(validator(&signer, true)), None).await),
+            InvalidToken::Missing
+        );
+    }
+
+    #[tokio::test]
+    async fn other_schemes_pass_through() {
+        let signer = Signer::new();
+        // Other schemes are left to the application, even when a token is
+        // required.
+        assert_eq!(
+            invalid_token(
+                call(
+                    Some(validator(&signer, true)),
+                    Some("Basic YWxpY2U6c2VjcmV0".to_string())
+                )
+                .await
+            ),
+            InvalidToken::Missing
+        );
+        call(
+            Some(validator(&signer, false)),
+            Some("Basic YWxpY2U6c2VjcmV0".to_string()),
+        )
+        .await
+        .expect("request must be permitted");
+    }
+
+    #[tokio::test]
+    async fn disabled() {
+        call(None, Some("Bearer not-a-token".to_string()))
+            .await
+            .expect("tokens must not be validated");
+    }
+}
@@ -0,0 +1,64 @@
+use crate::Jwks;
+use ring::{
+    rand::SystemRandom,
+    signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING},
+};
+use serde_json::Value;
+
+/// Signs tokens with an ES256 key.
+pub struct Signer {
+    key: EcdsaKeyPair,
+    rng: SystemRandom,
+}
+
+impl Signer {
+    pub fn new() -> Self {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+            .expect("key must be generated");
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref())
+            .expect("key must be valid");
+        Self { key, rng }
+    }
+
+    pub fn jwks(&self, kid: &str) -> Jwks {
+        // Skip the uncompressed point prefix.
+        let point = &self.key.public_key().as_ref()[1..];
+        let enc = |b| base64::encode_config(b, base64::URL_SAFE_NO_PAD);
+        let json = serde_json::json!({
+            "keys": [{
+                "kty": "EC",
+                "crv": "P-256",
+                "kid": kid,
+                "use": "sig",
+                "x": enc(&point[..32]),
+                "y": enc(&point[32..]),
+            }]
+        });
+        Jwks::from_json(json.to_string().as_bytes()).expect("JWKS must be valid")
+    }
+
+    pub fn sign(&self, kid: &str, claims: Value) -> String {
+        let enc = |v: Value| base64::encode_config(v.to_string(), base64::URL_SAFE_NO_PAD);
+        let msg = format!(
+            "{}.{}",
+            enc(serde_json::json!({ "alg": "ES256", "kid": kid })),
+            enc(claims)
+        );
+        let sig = self
+            .key
+            .sign(&self.rng, msg.as_bytes())
+            .expect("message must be signed");
+        format!(
+            "{}.{}",
+            msg,
+            base64::encode_config(sig.as_ref(), base64::URL_SAFE_NO_PAD)
+        )
+    }
+}
+
+impl Default for Signer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
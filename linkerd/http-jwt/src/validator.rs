@@ -0,0 +1,499 @@
+use crate::{jwks::Algorithm, Claims, InvalidToken, Jwks, RequiredClaims};
+use futures::StreamExt;
+use hyper_rustls::HttpsConnector;
+use linkerd_error::Error;
+use linkerd_exp_backoff::ExponentialBackoff;
+use parking_lot::RwLock;
+use serde_json::{Map, Value};
+use std::{
+    future::Future,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, info, warn};
+
+/// Retries failed key set fetches so that a transient failure, e.g. at
+/// startup, doesn't cause tokens to be rejected for a whole refresh interval.
+const FETCH_BACKOFF: ExponentialBackoff = ExponentialBackoff {
+    min: Duration::from_secs(1),
+    max: Duration::from_secs(30),
+    jitter: 0.5,
+};
+
+/// Configures token validation.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub jwks: JwksSource,
+
+    /// If set, tokens must have been issued by this issuer.
+    pub issuer: Option<String>,
+
+    /// If non-empty, tokens must be intended for at least one of these
+    /// audiences.
+    pub audiences: Vec<String>,
+
+    /// Tolerates clock skew when checking a token's validity period.
+    pub leeway: Duration,
+
+    /// Rejects tokens that do not have an expiration time (`exp`).
+    pub require_expiration: bool,
+
+    /// Rejects requests that do not include a bearer token.
+    pub required: bool,
+
+    /// Restricts routes to requests whose tokens have the given claims. The
+    /// first route that matches a request applies.
+    pub required_claims: Vec<RequiredClaims>,
+}
+
+/// Describes where token verification keys are obtained.
+#[derive(Clone, Debug)]
+pub enum JwksSource {
+    Static(Jwks),
+    Url { uri: http::Uri, refresh: Duration },
+}
+
+#[derive(Clone, Debug)]
+pub struct Validator {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    jwks: RwLock<Jwks>,
+    fetch: Option<(http::Uri, Duration)>,
+    issuer: Option<String>,
+    audiences: Vec<String>,
+    leeway: Duration,
+    require_expiration: bool,
+    required: bool,
+    required_claims: Arc<[RequiredClaims]>,
+}
+
+// === impl Validator ===
+
+impl Validator {
+    pub fn new(config: Config) -> Self {
+        let (jwks, fetch) = match config.jwks {
+            JwksSource::Static(jwks) => (jwks, None),
+            JwksSource::Url { uri, refresh } => (Jwks::default(), Some((uri, refresh))),
+        };
+        Self {
+            inner: Arc::new(Inner {
+                jwks: RwLock::new(jwks),
+                fetch,
+                issuer: config.issuer,
+                audiences: config.audiences,
+                leeway: config.leeway,
+                require_expiration: config.require_expiration,
+                required: config.required,
+                required_claims: config.required_claims.into(),
+            }),
+        }
+    }
+
+    /// Indicates whether requests without a bearer token are rejected.
+    pub fn is_required(&self) -> bool {
+        self.inner.required
+    }
+
+    /// Returns the routes that require tokens with specific claims.
+    pub fn required_claims(&self) -> Arc<[RequiredClaims]> {
+        self.inner.required_claims.clone()
+    }
+
+    /// Returns a task that periodically fetches the key set, if the
+    /// validator was configured with a JWKS URL.
+    ///
+    /// Tokens are rejected until the key set has been fetched successfully.
+    pub fn refresh(&self) -> Option<impl Future<Output = ()> + Send + 'static> {
+        let (uri, refresh) = self.inner.fetch.clone()?;
+        let inner = self.inner.clone();
+        let client = hyper::Client::builder().build(HttpsConnector::with_webpki_roots());
+        Some(refresh_jwks(inner, refresh, move || {
+            let client = client.clone();
+            let uri = uri.clone();
+            async move {
+                match fetch(&client, &uri).await {
+                    Ok(jwks) => {
+                        info!(%uri, keys = jwks.len(), "Fetched JWKS");
+                        Ok(jwks)
+                    }
+                    Err(error) => {
+                        warn!(%uri, %error, "Failed to fetch JWKS");
+                        Err(error)
+                    }
+                }
+            }
+        }))
+    }
+
+    /// Validates a compact-serialized token, returning its claims.
+    pub(crate) fn validate(&self, token: &str) -> Result<Claims, InvalidToken> {
+        self.validate_at(token, SystemTime::now())
+    }
+
+    fn validate_at(&self, token: &str, now: SystemTime) -> Result<Claims, InvalidToken> {
+        let mut parts = token.split('.');
+        let (header, payload, sig) = match (parts.next(), parts.next(), parts.next(), parts.next())
+        {
+            (Some(h), Some(p), Some(s), None) => (h, p, s),
+            _ => return Err(InvalidToken::Malformed),
+        };
+
+        let header = decode_json(header)?;
+        let alg = header
+            .get("alg")
+            .and_then(Value::as_str)
+            .ok_or(InvalidToken::Malformed)?;
+        let alg = Algorithm::from_name(alg)
+            .ok_or_else(|| InvalidToken::UnsupportedAlgorithm(alg.to_string()))?;
+        let kid = header.get("kid").and_then(Value::as_str);
+
+        // The signature covers the encoded header and payload.
+        let msg = &token[..header_len(token)];
+        let sig = base64::decode_config(sig, base64::URL_SAFE_NO_PAD)
+            .map_err(|_| InvalidToken::Malformed)?;
+        if !self
+            .inner
+            .jwks
+            .read()
+            .verify(kid, alg, msg.as_bytes(), &sig)
+        {
+            debug!(?kid, ?alg, "No key verified the token's signature");
+            return Err(InvalidToken::InvalidSignature);
+        }
+
+        let claims = Claims::new(decode_json(payload)?);
+        let now = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+        match timestamp(&claims, "exp")? {
+            Some(exp) => {
+                let exp = exp
+                    .checked_add(self.inner.leeway)
+                    .ok_or(InvalidToken::InvalidTimestamp)?;
+                if now >= exp {
+                    return Err(InvalidToken::Expired);
+                }
+            }
+            None if self.inner.require_expiration => {
+                return Err(InvalidToken::MissingExpiration);
+            }
+            None => {}
+        }
+        if let Some(nbf) = timestamp(&claims, "nbf")? {
+            let now = now
+                .checked_add(self.inner.leeway)
+                .ok_or(InvalidToken::InvalidTimestamp)?;
+            if now < nbf {
+                return Err(InvalidToken::NotYetValid);
+            }
+        }
+
+        if let Some(issuer) = self.inner.issuer.as_deref() {
+            if claims.issuer() != Some(issuer) {
+                return Err(InvalidToken::InvalidIssuer);
+            }
+        }
+
+        if !self.inner.audiences.is_empty()
+            && !claims
+                .audiences()
+                .any(|aud| self.inner.audiences.iter().any(|a| a == aud))
+        {
+            return Err(InvalidToken::InvalidAudience);
+        }
+
+        Ok(claims)
+    }
+}
+
+/// Fetches the key set every `refresh` interval. Failed fetches are retried
+/// with a backoff.
+async fn refresh_jwks<F, Fut>(inner: Arc<Inner>, refresh: Duration, mut fetch: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Jwks, Error>>,
+{
+    let mut backoff = FETCH_BACKOFF.stream();
+    loop {
+        match fetch().await {
+            Ok(jwks) => {
+                *inner.jwks.write() = jwks;
+                backoff = FETCH_BACKOFF.stream();
+                tokio::time::sleep(refresh).await;
+            }
+            Err(_) => {
+                if backoff.next().await.is_none() {
+                    tokio::time::sleep(refresh).await;
+                }
+            }
+        }
+    }
+}
+
+async fn fetch(
+    client: &hyper::Client<HttpsConnector<hyper::client::HttpConnector>>,
+    uri: &http::Uri,
+) -> Result<Jwks, Error> {
+    // Keys must be authenticated, so they are never fetched in plaintext.
+    if uri.scheme() != Some(&http::uri::Scheme::HTTPS) {
+        return Err("JWKS URL must use https".into());
+    }
+
+    let rsp = client.get(uri.clone()).await?;
+    if !rsp.status().is_success() {
+        return Err(format!("unexpected response status: {}", rsp.status()).into());
+    }
+    let body = hyper::body::to_bytes(rsp.into_body()).await?;
+    Ok(Jwks::from_json(&body)?)
+}
+
+/// Returns the length of the `header.payload` portion of a token.
+fn header_len(token: &str) -> usize {
+    token.rfind('.').unwrap_or(0)
+}
+
+fn decode_json(part: &str) -> Result<Map<String, Value>, InvalidToken> {
+    let json = base64::decode_config(part, base64::URL_SAFE_NO_PAD)
+        .map_err(|_| InvalidToken::Malformed)?;
+    match serde_json::from_slice(&json) {
+        Ok(Value::Object(obj)) => Ok(obj),
+        _ => Err(InvalidToken::Malformed),
+    }
+}
+
+/// Reads a NumericDate claim (seconds since the UNIX epoch).
+///
+/// Fractional seconds are truncated. Dates that can't be represented are
+/// rejected.
+fn timestamp(claims: &Claims, name: &str) -> Result<Option<Duration>, InvalidToken> {
+    let value = match claims.get(name) {
+        None => return Ok(None),
+        Some(v) => v,
+    };
+    if let Some(secs) = value.as_u64() {
+        return Ok(Some(Duration::from_secs(secs)));
+    }
+    match value.as_f64() {
+        Some(secs) if secs.is_finite() && secs >= 0.0 => {
+            // `u64::MAX as f64` rounds up to 2^64, so this excludes every
+            // value that would saturate when cast.
+            if secs >= u64::MAX as f64 {
+                return Err(InvalidToken::InvalidTimestamp);
+            }
+            Ok(Some(Duration::from_secs(secs as u64)))
+        }
+        Some(_) => Err(InvalidToken::InvalidTimestamp),
+        None => Err(InvalidToken::Malformed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Signer;
+
+    const NOW: u64 = 1_600_000_000;
+
+    fn validator(jwks: Jwks) -> Validator {
+        Validator::new(config(jwks))
+    }
+
+    fn config(jwks: Jwks) -> Config {
+        Config {
+            jwks: JwksSource::Static(jwks),
+            issuer: Some("https://issuer.example.com".to_string()),
+            audiences: vec!["web".to_string()],
+            leeway: Duration::from_secs(30),
+            require_expiration: true,
+            required: false,
+            required_claims: vec![],
+        }
+    }
+
+    fn validate(v: &Validator, token: &str) -> Result<Claims, InvalidToken> {
+        v.validate_at(token, UNIX_EPOCH + Duration::from_secs(NOW))
+    }
+
+    #[test]
+    fn valid() {
+        let signer = Signer::new();
+        let v = validator(signer.jwks("a"));
+        let token = signer.sign(
+            "a",
+            serde_json::json!({
+                "iss": "https://issuer.example.com",
+                "sub": "alice",
+                "aud": ["api", "web"],
+                "exp": NOW + 60,
+                "nbf": NOW - 60,
+            }),
+        );
+        let claims = validate(&v, &token).expect("token must be valid");
+        assert_eq!(claims.subject(), Some("alice"));
+        assert_eq!(claims.audiences().collect::<Vec<_>>(), vec!["api", "web"]);
+    }
+
+    #[test]
+    fn untrusted_key() {
+        let signer = Signer::new();
+        let v = validator(Signer::new().jwks("a"));
+        let token = signer.sign(
+            "a",
+            serde_json::json!({ "iss": "https://issuer.example.com", "aud": "web" }),
+        );
+        assert_eq!(validate(&v, &token), Err(InvalidToken::InvalidSignature));
+    }
+
+    #[test]
+    fn unknown_kid() {
+        let signer = Signer::new();
+        let v = validator(signer.jwks("a"));
+        let token = signer.sign(
+            "b",
+            serde_json::json!({ "iss": "https://issuer.example.com", "aud": "web" }),
+        );
+        assert_eq!(validate(&v, &token), Err(InvalidToken::InvalidSignature));
+    }
+
+    #[test]
+    fn expired() {
+        let signer = Signer::new();
+        let v = validator(signer.jwks("a"));
+        let claims = |exp| serde_json::json!({ "iss": "https://issuer.example.com", "aud": "web", "exp": exp });
+        assert_eq!(
+            validate(&v, &signer.sign("a", claims(NOW - 60))),
+            Err(InvalidToken::Expired)
+        );
+        // Tolerated by the configured leeway.
+        validate(&v, &signer.sign("a", claims(NOW - 10))).expect("token must be valid");
+    }
+
+    #[test]
+    fn missing_expiration() {
+        let signer = Signer::new();
+        let token = signer.sign(
+            "a",
+            serde_json::json!({ "iss": "https://issuer.example.com", "aud": "web" }),
+        );
+        assert_eq!(
+            validate(&validator(signer.jwks("a")), &token),
+            Err(InvalidToken::MissingExpiration)
+        );
+
+        let v = Validator::new(Config {
+            require_expiration: false,
+            ..config(signer.jwks("a"))
+        });
+        validate(&v, &token).expect("token must be valid");
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn retries_failed_fetches() {
+        tokio::time::pause();
+        let signer = Signer::new();
+        let v = validator(Jwks::default());
+        let token = signer.sign(
+            "a",
+            serde_json::json!({ "iss": "https://issuer.example.com", "aud": "web", "exp": NOW + 60 }),
+        );
+
+        // The first fetch fails, so the key set remains empty.
+        let mut fetches = 0;
+        let jwks = signer.jwks("a");
+        let refresh = refresh_jwks(v.inner.clone(), Duration::from_secs(60 * 60), move || {
+            fetches += 1;
+            let rsp: Result<Jwks, Error> = if fetches == 1 {
+                Err("connection refused".into())
+            } else {
+                Ok(jwks.clone())
+            };
+            futures::future::ready(rsp)
+        });
+        let task = tokio::spawn(refresh);
+        tokio::task::yield_now().await;
+        assert_eq!(validate(&v, &token), Err(InvalidToken::InvalidSignature));
+
+        // The fetch is retried long before the refresh interval elapses.
+        tokio::time::sleep(FETCH_BACKOFF.max).await;
+        validate(&v, &token).expect("token must be valid");
+        task.abort();
+    }
+
+    #[test]
+    fn out_of_range_timestamps() {
+        let signer = Signer::new();
+        let v = validator(signer.jwks("a"));
+        let claims = |exp: Value| serde_json::json!({ "iss": "https://issuer.example.com", "aud": "web", "exp": exp });
+        // Adding the leeway would overflow.
+        assert_eq!(
+            validate(&v, &signer.sign("a", claims(u64::MAX.into()))),
+            Err(InvalidToken::InvalidTimestamp)
+        );
+        // Too large to be represented.
+        assert_eq!(
+            validate(&v, &signer.sign("a", claims(1e30.into()))),
+            Err(InvalidToken::InvalidTimestamp)
+        );
+        assert_eq!(
+            validate(&v, &signer.sign("a", claims((-1).into()))),
+            Err(InvalidToken::InvalidTimestamp)
+        );
+        assert_eq!(
+            validate(&v, &signer.sign("a", claims("tomorrow".into()))),
+            Err(InvalidToken::Malformed)
+        );
+        // Fractional dates are accepted.
+        validate(&v, &signer.sign("a", claims((NOW as f64 + 60.5).into())))
+            .expect("token must be valid");
+    }
+
+    #[test]
+    fn not_yet_valid() {
+        let signer = Signer::new();
+        let v = validator(signer.jwks("a"));
+        let token = signer.sign(
+            "a",
+            serde_json::json!({
+                "iss": "https://issuer.example.com",
+                "aud": "web",
+                "exp": NOW + 120,
+                "nbf": NOW + 60,
+            }),
+        );
+        assert_eq!(validate(&v, &token), Err(InvalidToken::NotYetValid));
+    }
+
+    #[test]
+    fn wrong_issuer_and_audience() {
+        let signer = Signer::new();
+        let v = validator(signer.jwks("a"));
+        let token = signer.sign(
+            "a",
+            serde_json::json!({ "iss": "https://evil.example.com", "aud": "web", "exp": NOW + 60 }),
+        );
+        assert_eq!(validate(&v, &token), Err(InvalidToken::InvalidIssuer));
+
+        let token = signer.sign(
+            "a",
+            serde_json::json!({ "iss": "https://issuer.example.com", "aud": "api", "exp": NOW + 60 }),
+        );
+        assert_eq!(validate(&v, &token), Err(InvalidToken::InvalidAudience));
+    }
+
+    #[test]
+    fn rejects_unsigned() {
+        let v = validator(Signer::new().jwks("a"));
+        let enc = |v: Value| base64::encode_config(v.to_string(), base64::URL_SAFE_NO_PAD);
+        let token = format!(
+            "{}.{}.",
+            enc(serde_json::json!({ "alg": "none" })),
+            enc(serde_json::json!({ "iss": "https://issuer.example.com", "aud": "web" }))
+        );
+        assert_eq!(
+            validate(&v, &token),
+            Err(InvalidToken::UnsupportedAlgorithm("none".to_string()))
+        );
+        assert_eq!(validate(&v, "not-a-token"), Err(InvalidToken::Malformed));
+    }
+}
@@ -1,11 +1,12 @@
 #![deny(warnings, rust_2018_idioms)]
 #![forbid(unsafe_code)]
 
-pub use self::{requests::Requests, retries::Retries};
+pub use self::{queue_time::QueueTime, requests::Requests, retries::Retries};
 use linkerd_metrics::SharedStore;
 use parking_lot::Mutex;
 use std::{fmt, hash::Hash, time::Duration};
 
+pub mod queue_time;
 pub mod requests;
 pub mod retries;
 
@@ -0,0 +1,245 @@
+//! Measures how long requests wait in the proxy's buffers before they are
+//! dispatched.
+//!
+//! [`Enqueue`] marks when a request enters a buffer and [`Record`], on the
+//! buffer's inner service, records how long the request waited once it is
+//! dispatched.
+
+use futures::ready;
+use http::header::{HeaderName, HeaderValue};
+use linkerd_metrics::{latency, metrics, FmtLabels, FmtMetrics, Histogram};
+use linkerd_stack::layer;
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    hash::Hash,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::time::Instant;
+
+/// The response header that describes how long a request waited in each
+/// buffer, in milliseconds.
+pub const L5D_QUEUE_MS: &str = "l5d-queue-ms";
+
+metrics! {
+    request_queue_duration_ms: Histogram<latency::Ms> {
+        "Time requests spent waiting in the proxy's buffers before being dispatched, in milliseconds."
+    }
+}
+
+/// Queue-time histograms by stack.
+#[derive(Debug)]
+pub struct QueueTime<L: Hash + Eq>(Arc<Mutex<HashMap<L, Arc<Histogram<latency::Ms>>>>>);
+
+/// Marks when a request is enqueued.
+#[derive(Clone, Debug)]
+pub struct Enqueue<S>(S);
+
+/// Records how long a request was queued when it is dispatched.
+#[derive(Clone, Debug)]
+pub struct Record<S> {
+    inner: S,
+    histogram: Arc<Histogram<latency::Ms>>,
+    header: bool,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    header: Option<HeaderValue>,
+}
+
+/// A request extension set by `Enqueue`.
+#[derive(Copy, Clone, Debug)]
+struct Enqueued(Instant);
+
+// === impl QueueTime ===
+
+impl<L: Hash + Eq> QueueTime<L> {
+    /// Returns a layer that records the queue time of requests in the
+    /// labeled stack.
+    ///
+    /// If `header` is true, the queue time is added to responses in the
+    /// `l5d-queue-ms` header.
+    pub fn layer<S>(
+        &self,
+        labels: L,
+        header: bool,
+    ) -> impl layer::Layer<S, Service = Record<S>> + Clone {
+        let histogram = self
+            .0
+            .lock()
+            .entry(labels)
+            .or_insert_with(|| Arc::new(Histogram::new(latency::BOUNDS)))
+            .clone();
+        layer::mk(move |inner| Record {
+            inner,
+            histogram: histogram.clone(),
+            header,
+        })
+    }
+}
+
+impl<L: Hash + Eq> Default for QueueTime<L> {
+    fn default() -> Self {
+        Self(Default::default())
+    }
+}
+
+impl<L: Hash + Eq> Clone for QueueTime<L> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<L: FmtLabels + Hash + Eq> FmtMetrics for QueueTime<L> {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let by_stack = self.0.lock();
+        if by_stack.is_empty() {
+            return Ok(());
+        }
+
+        request_queue_duration_ms.fmt_help(f)?;
+        request_queue_duration_ms.fmt_scopes(f, by_stack.iter(), |h| h.as_ref())
+    }
+}
+
+// === impl Enqueue ===
+
+impl<S> Enqueue<S> {
+    pub fn layer() -> impl layer::Layer<S, Service = Self> + Clone {
+        layer::mk(Self)
+    }
+}
+
+impl<B, S> tower::Service<http::Request<B>> for Enqueue<S>
+where
+    S: tower::Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        req.extensions_mut().insert(Enqueued(Instant::now()));
+        self.0.call(req)
+    }
+}
+
+// === impl Record ===
+
+impl<A, B, S> tower::Service<http::Request<A>> for Record<S>
+where
+    S: tower::Service<http::Request<A>, Response = http::Response<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<A>) -> Self::Future {
+        let header = req
+            .extensions_mut()
+            .remove::<Enqueued>()
+            .and_then(|Enqueued(t0)| {
+                let queued = t0.elapsed();
+                self.histogram.add(queued);
+                if self.header {
+                    Some(HeaderValue::from(queued.as_millis() as u64))
+                } else {
+                    None
+                }
+            });
+
+        ResponseFuture {
+            inner: self.inner.call(req),
+            header,
+        }
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<B, E, F> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<http::Response<B>, E>>,
+{
+    type Output = Result<http::Response<B>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut rsp = ready!(this.inner.poll(cx))?;
+        if let Some(queued) = this.header.take() {
+            // Each buffer adds its own value, so that nested buffers (and
+            // other proxies) are reported separately.
+            rsp.headers_mut()
+                .append(HeaderName::from_static(L5D_QUEUE_MS), queued);
+        }
+        Poll::Ready(Ok(rsp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_stack::layer::Layer;
+    use tower::{Service, ServiceExt};
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn records_queue_time() {
+        let queue_time = QueueTime::<()>::default();
+        let mut svc = queue_time.layer((), true).layer(tower::service_fn(
+            |_: http::Request<()>| async move {
+                Ok::<_, ()>(
+                    http::Response::builder()
+                        .header(L5D_QUEUE_MS, "3")
+                        .body(())
+                        .unwrap(),
+                )
+            },
+        ));
+
+        // Simulate a request that waited in a buffer.
+        let mut req = http::Request::new(());
+        req.extensions_mut().insert(Enqueued(Instant::now()));
+        tokio::time::sleep(std::time::Duration::from_millis(25)).await;
+
+        let rsp = svc.ready().await.unwrap().call(req).await.unwrap();
+        let values = rsp
+            .headers()
+            .get_all(L5D_QUEUE_MS)
+            .iter()
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec!["3", "25"]);
+
+        let histogram = queue_time.0.lock().get(&()).unwrap().clone();
+        histogram.assert_bucket_exactly(30.0, 1.0);
+
+        // Requests that weren't buffered aren't recorded.
+        let rsp = svc
+            .ready()
+            .await
+            .unwrap()
+            .call(http::Request::new(()))
+            .await
+            .unwrap();
+        assert_eq!(rsp.headers().get_all(L5D_QUEUE_MS).iter().count(), 1);
+        histogram.assert_bucket_exactly(30.0, 1.0);
+    }
+}
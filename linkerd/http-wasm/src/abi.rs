@@ -0,0 +1,646 @@
+//! Host functions implementing a subset of the proxy-wasm ABI.
+
+use bytes::Bytes;
+use http::{header, uri, HeaderMap, HeaderValue};
+use linkerd_error::Error;
+use std::{
+    convert::TryFrom,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, error, info, level_filters::LevelFilter, trace, warn, Level};
+use wasmtime::{Caller, Extern, ExternType, Linker, Module, Trap, Val, ValType};
+
+/// The context ID of a filter instance's root context.
+pub(crate) const ROOT_CONTEXT: i32 = 1;
+
+// Status codes returned by host functions.
+const OK: i32 = 0;
+const NOT_FOUND: i32 = 1;
+const BAD_ARGUMENT: i32 = 2;
+const UNIMPLEMENTED: i32 = 12;
+
+// Header map types.
+const MAP_REQUEST_HEADERS: i32 = 0;
+const MAP_RESPONSE_HEADERS: i32 = 2;
+
+// Buffer types.
+const BUFFER_VM_CONFIGURATION: i32 = 6;
+const BUFFER_PLUGIN_CONFIGURATION: i32 = 7;
+
+/// The host functions provided by this module. Any other functions imported
+/// by a module are stubbed to return `Unimplemented`.
+const HOST_FUNCTIONS: &[&str] = &[
+    "proxy_log",
+    "proxy_get_log_level",
+    "proxy_get_buffer_bytes",
+    "proxy_get_header_map_pairs",
+    "proxy_get_header_map_value",
+    "proxy_add_header_map_value",
+    "proxy_replace_header_map_value",
+    "proxy_remove_header_map_value",
+    "proxy_set_header_map_pairs",
+    "proxy_send_local_response",
+    "proxy_get_current_time_nanoseconds",
+    "proxy_set_effective_context",
+];
+
+/// The state shared between the proxy and a filter instance.
+#[derive(Debug, Default)]
+pub(crate) struct Host {
+    pub(crate) name: String,
+    pub(crate) configuration: Bytes,
+    pub(crate) request: Option<http::request::Parts>,
+    pub(crate) response: Option<http::response::Parts>,
+    pub(crate) local_response: Option<LocalResponse>,
+}
+
+/// A response sent by a filter in place of the inner service's response.
+#[derive(Debug)]
+pub(crate) struct LocalResponse {
+    pub(crate) status: http::StatusCode,
+    pub(crate) headers: HeaderMap,
+    pub(crate) body: Bytes,
+}
+
+/// A mutable view of a request or response head.
+enum Head<'a> {
+    Request(&'a mut http::request::Parts),
+    Response(&'a mut http::response::Parts),
+}
+
+type Pairs = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Builds a linker that provides the host functions to the given module.
+pub(crate) fn linker(module: &Module) -> Result<Linker<Host>, Error> {
+    let mut linker = Linker::new(module.engine());
+    linker.allow_shadowing(true);
+
+    linker.func_wrap(
+        "env",
+        "proxy_log",
+        |mut caller: Caller<'_, Host>, level: i32, ptr: i32, len: i32| {
+            let msg = read(&mut caller, ptr, len)?;
+            let msg = String::from_utf8_lossy(&msg);
+            let filter = caller.data().name.as_str();
+            match level {
+                0 => trace!(%filter, "{}", msg),
+                1 => debug!(%filter, "{}", msg),
+                2 => info!(%filter, "{}", msg),
+                3 => warn!(%filter, "{}", msg),
+                _ => error!(%filter, "{}", msg),
+            }
+            Ok(OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_get_log_level",
+        |mut caller: Caller<'_, Host>, level_ptr: i32| {
+            let level = match LevelFilter::current().into_level() {
+                Some(level) if level == Level::TRACE => 0,
+                Some(level) if level == Level::DEBUG => 1,
+                Some(level) if level == Level::INFO => 2,
+                Some(level) if level == Level::WARN => 3,
+                _ => 4,
+            };
+            write_u32(&mut caller, level_ptr, level)?;
+            Ok(OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_get_buffer_bytes",
+        |mut caller: Caller<'_, Host>,
+         buffer: i32,
+         start: i32,
+         max_size: i32,
+         ptr_ptr: i32,
+         size_ptr: i32| {
+            let bytes = match buffer {
+                BUFFER_PLUGIN_CONFIGURATION => caller.data().configuration.clone(),
+                BUFFER_VM_CONFIGURATION => Bytes::new(),
+                _ => return Ok(UNIMPLEMENTED),
+            };
+            let start = (start as u32 as usize).min(bytes.len());
+            let end = start
+                .saturating_add(max_size as u32 as usize)
+                .min(bytes.len());
+            if start == end {
+                return Ok(NOT_FOUND);
+            }
+            return_bytes(&mut caller, &bytes[start..end], ptr_ptr, size_ptr)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_get_header_map_pairs",
+        |mut caller: Caller<'_, Host>, map: i32, ptr_ptr: i32, size_ptr: i32| {
+            let pairs = match caller.data_mut().head(map) {
+                Some(head) => head.pairs(),
+                None => return Ok(BAD_ARGUMENT),
+            };
+            return_bytes(&mut caller, &serialize_pairs(&pairs), ptr_ptr, size_ptr)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_get_header_map_value",
+        |mut caller: Caller<'_, Host>,
+         map: i32,
+         key_ptr: i32,
+         key_len: i32,
+         ptr_ptr: i32,
+         size_ptr: i32| {
+            let key = read(&mut caller, key_ptr, key_len)?;
+            let value = match caller.data_mut().head(map) {
+                Some(head) => head.get(&key),
+                None => return Ok(BAD_ARGUMENT),
+            };
+            match value {
+                Some(value) => return_bytes(&mut caller, &value, ptr_ptr, size_ptr),
+                None => Ok(NOT_FOUND),
+            }
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_add_header_map_value",
+        |mut caller: Caller<'_, Host>,
+         map: i32,
+         key_ptr: i32,
+         key_len: i32,
+         value_ptr: i32,
+         value_len: i32| {
+            let key = read(&mut caller, key_ptr, key_len)?;
+            let value = read(&mut caller, value_ptr, value_len)?;
+            Ok(match caller.data_mut().head(map) {
+                Some(mut head) => status(head.add(&key, &value)),
+                None => BAD_ARGUMENT,
+            })
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_replace_header_map_value",
+        |mut caller: Caller<'_, Host>,
+         map: i32,
+         key_ptr: i32,
+         key_len: i32,
+         value_ptr: i32,
+         value_len: i32| {
+            let key = read(&mut caller, key_ptr, key_len)?;
+            let value = read(&mut caller, value_ptr, value_len)?;
+            Ok(match caller.data_mut().head(map) {
+                Some(mut head) => status(head.replace(&key, &value)),
+                None => BAD_ARGUMENT,
+            })
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_remove_header_map_value",
+        |mut caller: Caller<'_, Host>, map: i32, key_ptr: i32, key_len: i32| {
+            let key = read(&mut caller, key_ptr, key_len)?;
+            Ok(match caller.data_mut().head(map) {
+                Some(mut head) => status(head.remove(&key)),
+                None => BAD_ARGUMENT,
+            })
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_set_header_map_pairs",
+        |mut caller: Caller<'_, Host>, map: i32, ptr: i32, len: i32| {
+            let pairs = match deserialize_pairs(&read(&mut caller, ptr, len)?) {
+                Some(pairs) => pairs,
+                None => return Ok(BAD_ARGUMENT),
+            };
+            Ok(match caller.data_mut().head(map) {
+                Some(mut head) => status(head.set_pairs(pairs)),
+                None => BAD_ARGUMENT,
+            })
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_send_local_response",
+        |mut caller: Caller<'_, Host>,
+         status_code: i32,
+         _details_ptr: i32,
+         _details_len: i32,
+         body_ptr: i32,
+         body_len: i32,
+         headers_ptr: i32,
+         headers_len: i32,
+         _grpc_status: i32| {
+            let status = match u16::try_from(status_code)
+                .ok()
+                .and_then(|s| http::StatusCode::from_u16(s).ok())
+            {
+                Some(status) => status,
+                None => return Ok(BAD_ARGUMENT),
+            };
+            let body = Bytes::from(read(&mut caller, body_ptr, body_len)?);
+            let pairs = match deserialize_pairs(&read(&mut caller, headers_ptr, headers_len)?) {
+                Some(pairs) => pairs,
+                None => return Ok(BAD_ARGUMENT),
+            };
+            let mut headers = HeaderMap::with_capacity(pairs.len());
+            for (key, value) in pairs {
+                match (
+                    header::HeaderName::from_bytes(&key),
+                    HeaderValue::from_bytes(&value),
+                ) {
+                    (Ok(key), Ok(value)) => {
+                        headers.append(key, value);
+                    }
+                    _ => return Ok(BAD_ARGUMENT),
+                }
+            }
+            caller.data_mut().local_response = Some(LocalResponse {
+                status,
+                headers,
+                body,
+            });
+            Ok(OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_get_current_time_nanoseconds",
+        |mut caller: Caller<'_, Host>, time_ptr: i32| {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(0);
+            write(&mut caller, time_ptr, &nanos.to_le_bytes())?;
+            Ok(OK)
+        },
+    )?;
+
+    // Each instance handles a single stream at a time, so there is never
+    // more than one context to choose from.
+    linker.func_wrap("env", "proxy_set_effective_context", |_: i32| OK)?;
+
+    for import in module.imports() {
+        let name = match import.name() {
+            Some(name) => name,
+            None => continue,
+        };
+        if import.module() == "env" && HOST_FUNCTIONS.contains(&name) {
+            continue;
+        }
+        if let ExternType::Func(ty) = import.ty() {
+            let results = ty.results().collect::<Vec<_>>();
+            let func = format!("{}.{}", import.module(), name);
+            linker.func_new(
+                import.module(),
+                name,
+                ty,
+                move |_: Caller<'_, Host>, _: &[Val], out: &mut [Val]| {
+                    debug!(%func, "Unimplemented host function");
+                    for (val, ty) in out.iter_mut().zip(&results) {
+                        *val = match ty {
+                            ValType::I32 => Val::I32(UNIMPLEMENTED),
+                            ValType::I64 => Val::I64(0),
+                            ValType::F32 => Val::F32(0),
+                            ValType::F64 => Val::F64(0),
+                            _ => return Err(Trap::new(format!("{} is not supported", func))),
+                        };
+                    }
+                    Ok(())
+                },
+            )?;
+        }
+    }
+
+    Ok(linker)
+}
+
+fn status(res: Result<(), ()>) -> i32 {
+    match res {
+        Ok(()) => OK,
+        Err(()) => BAD_ARGUMENT,
+    }
+}
+
+// === Guest memory ===
+
+fn memory(caller: &mut Caller<'_, Host>) -> Result<wasmtime::Memory, Trap> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| Trap::new("module does not export its memory"))
+}
+
+fn read(caller: &mut Caller<'_, Host>, ptr: i32, len: i32) -> Result<Vec<u8>, Trap> {
+    let memory = memory(caller)?;
+    let mut buf = vec![0; len as u32 as usize];
+    memory
+        .read(&*caller, ptr as u32 as usize, &mut buf)
+        .map_err(|e| Trap::new(e.to_string()))?;
+    Ok(buf)
+}
+
+fn write(caller: &mut Caller<'_, Host>, ptr: i32, data: &[u8]) -> Result<(), Trap> {
+    let memory = memory(caller)?;
+    memory
+        .write(&mut *caller, ptr as u32 as usize, data)
+        .map_err(|e| Trap::new(e.to_string()))
+}
+
+fn write_u32(caller: &mut Caller<'_, Host>, ptr: i32, value: u32) -> Result<(), Trap> {
+    write(caller, ptr, &value.to_le_bytes())
+}
+
+/// Allocates `len` bytes in the guest's memory.
+fn allocate(caller: &mut Caller<'_, Host>, len: usize) -> Result<i32, Trap> {
+    let alloc = caller
+        .get_export("proxy_on_memory_allocate")
+        .or_else(|| caller.get_export("malloc"))
+        .and_then(Extern::into_func)
+        .ok_or_else(|| Trap::new("module does not export an allocator"))?
+        .typed::<i32, i32, _>(&*caller)
+        .map_err(|e| Trap::new(e.to_string()))?;
+    let len = i32::try_from(len).map_err(|_| Trap::new("allocation too large"))?;
+    alloc.call(&mut *caller, len)
+}
+
+/// Copies `data` into a newly allocated guest buffer and writes its address
+/// and size to the given pointers.
+fn return_bytes(
+    caller: &mut Caller<'_, Host>,
+    data: &[u8],
+    ptr_ptr: i32,
+    size_ptr: i32,
+) -> Result<i32, Trap> {
+    let ptr = allocate(caller, data.len())?;
+    write(caller, ptr, data)?;
+    write_u32(caller, ptr_ptr, ptr as u32)?;
+    write_u32(caller, size_ptr, data.len() as u32)?;
+    Ok(OK)
+}
+
+// === Header maps ===
+
+/// Serializes header pairs as a count, followed by the length of each key
+/// and value, followed by each null-terminated key and value.
+fn serialize_pairs(pairs: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+    let size = 4 + pairs
+        .iter()
+        .map(|(k, v)| 8 + k.len() + v.len() + 2)
+        .sum::<usize>();
+    let mut buf = Vec::with_capacity(size);
+    buf.extend_from_slice(&(pairs.len() as u32).to_le_bytes());
+    for (k, v) in pairs {
+        buf.extend_from_slice(&(k.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&(v.len() as u32).to_le_bytes());
+    }
+    for (k, v) in pairs {
+        buf.extend_from_slice(k);
+        buf.push(0);
+        buf.extend_from_slice(v);
+        buf.push(0);
+    }
+    buf
+}
+
+fn deserialize_pairs(buf: &[u8]) -> Option<Pairs> {
+    fn u32_at(buf: &[u8], at: usize) -> Option<usize> {
+        let bytes = buf.get(at..at + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    }
+
+    if buf.is_empty() {
+        return Some(Vec::new());
+    }
+    let count = u32_at(buf, 0)?;
+    let mut data = 4usize.checked_add(count.checked_mul(8)?)?;
+    let mut pairs = Vec::with_capacity(count.min(buf.len() / 8));
+    for i in 0..count {
+        let key_len = u32_at(buf, 4 + i * 8)?;
+        let value_len = u32_at(buf, 8 + i * 8)?;
+        let key = buf.get(data..data.checked_add(key_len)?)?.to_vec();
+        data = data.checked_add(key_len + 1)?;
+        let value = buf.get(data..data.checked_add(value_len)?)?.to_vec();
+        data = data.checked_add(value_len + 1)?;
+        pairs.push((key, value));
+    }
+    Some(pairs)
+}
+
+// === impl Host ===
+
+impl Host {
+    fn head(&mut self, map: i32) -> Option<Head<'_>> {
+        match map {
+            MAP_REQUEST_HEADERS => self.request.as_mut().map(Head::Request),
+            MAP_RESPONSE_HEADERS => self.response.as_mut().map(Head::Response),
+            _ => None,
+        }
+    }
+}
+
+// === impl Head ===
+
+impl Head<'_> {
+    fn headers(&self) -> &HeaderMap {
+        match self {
+            Head::Request(req) => &req.headers,
+            Head::Response(rsp) => &rsp.headers,
+        }
+    }
+
+    fn headers_mut(&mut self) -> &mut HeaderMap {
+        match self {
+            Head::Request(req) => &mut req.headers,
+            Head::Response(rsp) => &mut rsp.headers,
+        }
+    }
+
+    fn pseudo_headers(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Head::Request(req) => {
+                let authority = req
+                    .uri
+                    .authority()
+                    .map(|a| a.to_string())
+                    .or_else(|| {
+                        req.headers
+                            .get(header::HOST)
+                            .and_then(|h| h.to_str().ok())
+                            .map(String::from)
+                    })
+                    .unwrap_or_default();
+                let path = req
+                    .uri
+                    .path_and_query()
+                    .map(|p| p.to_string())
+                    .unwrap_or_else(|| "/".to_string());
+                vec![
+                    (":method", req.method.to_string()),
+                    (
+                        ":scheme",
+                        req.uri.scheme_str().unwrap_or("http").to_string(),
+                    ),
+                    (":authority", authority),
+                    (":path", path),
+                ]
+            }
+            Head::Response(rsp) => vec![(":status", rsp.status.as_str().to_string())],
+        }
+    }
+
+    /// Returns all headers, with pseudo-headers first.
+    fn pairs(&self) -> Pairs {
+        self.pseudo_headers()
+            .into_iter()
+            .map(|(k, v)| (k.as_bytes().to_vec(), v.into_bytes()))
+            .chain(
+                self.headers()
+                    .iter()
+                    .map(|(k, v)| (k.as_str().as_bytes().to_vec(), v.as_bytes().to_vec())),
+            )
+            .collect()
+    }
+
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        if key.starts_with(b":") {
+            return self
+                .pseudo_headers()
+                .into_iter()
+                .find(|(k, _)| k.as_bytes() == key)
+                .map(|(_, v)| v.into_bytes());
+        }
+        self.headers()
+            .get(header::HeaderName::from_bytes(key).ok()?)
+            .map(|v| v.as_bytes().to_vec())
+    }
+
+    fn add(&mut self, key: &[u8], value: &[u8]) -> Result<(), ()> {
+        if key.starts_with(b":") {
+            return self.set_pseudo_header(key, value);
+        }
+        let (key, value) = parse_header(key, value)?;
+        self.headers_mut().append(key, value);
+        Ok(())
+    }
+
+    fn replace(&mut self, key: &[u8], value: &[u8]) -> Result<(), ()> {
+        if key.starts_with(b":") {
+            return self.set_pseudo_header(key, value);
+        }
+        let (key, value) = parse_header(key, value)?;
+        self.headers_mut().insert(key, value);
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &[u8]) -> Result<(), ()> {
+        // Pseudo-headers may not be removed.
+        if key.starts_with(b":") {
+            return Err(());
+        }
+        let key = header::HeaderName::from_bytes(key).map_err(|_| ())?;
+        self.headers_mut().remove(key);
+        Ok(())
+    }
+
+    fn set_pairs(&mut self, pairs: Pairs) -> Result<(), ()> {
+        let mut headers = HeaderMap::with_capacity(pairs.len());
+        for (key, value) in pairs {
+            if key.starts_with(b":") {
+                self.set_pseudo_header(&key, &value)?;
+            } else {
+                let (key, value) = parse_header(&key, &value)?;
+                headers.append(key, value);
+            }
+        }
+        *self.headers_mut() = headers;
+        Ok(())
+    }
+
+    fn set_pseudo_header(&mut self, key: &[u8], value: &[u8]) -> Result<(), ()> {
+        match (self, key) {
+            (Head::Request(req), b":method") => {
+                req.method = http::Method::from_bytes(value).map_err(|_| ())?;
+            }
+            (Head::Request(req), b":path") => {
+                let path = uri::PathAndQuery::try_from(value).map_err(|_| ())?;
+                let mut parts = req.uri.clone().into_parts();
+                parts.path_and_query = Some(path);
+                req.uri = http::Uri::from_parts(parts).map_err(|_| ())?;
+            }
+            (Head::Request(req), b":authority") => {
+                let authority = uri::Authority::try_from(value).map_err(|_| ())?;
+                if req.uri.authority().is_some() {
+                    let mut parts = req.uri.clone().into_parts();
+                    parts.authority = Some(authority.clone());
+                    req.uri = http::Uri::from_parts(parts).map_err(|_| ())?;
+                }
+                let host = HeaderValue::from_str(authority.as_str()).map_err(|_| ())?;
+                req.headers.insert(header::HOST, host);
+            }
+            (Head::Response(rsp), b":status") => {
+                rsp.status = http::StatusCode::from_bytes(value).map_err(|_| ())?;
+            }
+            _ => return Err(()),
+        }
+        Ok(())
+    }
+}
+
+fn parse_header(key: &[u8], value: &[u8]) -> Result<(header::HeaderName, HeaderValue), ()> {
+    let key = header::HeaderName::from_bytes(key).map_err(|_| ())?;
+    let value = HeaderValue::from_bytes(value).map_err(|_| ())?;
+    Ok((key, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrips_pairs() {
+        let pairs = vec![
+            (b":path".to_vec(), b"/foo".to_vec()),
+            (b"x-empty".to_vec(), Vec::new()),
+            (b"x-foo".to_vec(), b"bar".to_vec()),
+        ];
+        let buf = serialize_pairs(&pairs);
+        assert_eq!(&buf[..4], &3u32.to_le_bytes());
+        assert_eq!(deserialize_pairs(&buf), Some(pairs));
+
+        assert_eq!(deserialize_pairs(&[]), Some(Vec::new()));
+        assert_eq!(deserialize_pairs(&buf[..buf.len() - 2]), None);
+    }
+
+    #[test]
+    fn sets_pseudo_headers() {
+        let (mut parts, ()) = http::Request::builder()
+            .uri("http://foo.example.com/foo?bar")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let mut head = Head::Request(&mut parts);
+        assert_eq!(head.get(b":path"), Some(b"/foo?bar".to_vec()));
+
+        head.replace(b":path", b"/baz").unwrap();
+        head.replace(b":authority", b"bar.example.com").unwrap();
+        head.add(b"x-foo", b"1").unwrap();
+        assert!(head.remove(b":path").is_err());
+        assert!(head.replace(b":status", b"200").is_err());
+        assert_eq!(parts.uri, "http://bar.example.com/baz");
+        assert_eq!(parts.headers[header::HOST], "bar.example.com");
+        assert_eq!(parts.headers["x-foo"], "1");
+    }
+}
@@ -0,0 +1,372 @@
+use crate::{
+    abi::{self, Host, LocalResponse, ROOT_CONTEXT},
+    FilterError, LoadError,
+};
+use bytes::Bytes;
+use linkerd_error::Error;
+use parking_lot::Mutex;
+use std::{fmt, path::PathBuf, sync::Arc, time::Duration};
+use tokio::task::JoinHandle;
+use tracing::debug;
+use wasmtime::{Engine, InterruptHandle, Linker, Module, Store, WasmParams, WasmResults};
+
+/// Configures a single filter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FilterConfig {
+    /// The filter's name, used in logs and errors.
+    pub name: String,
+
+    /// The path to the filter's WASM module.
+    pub module: PathBuf,
+
+    /// An optional path to a file whose contents are provided to the filter
+    /// as its plugin configuration.
+    pub configuration: Option<PathBuf>,
+}
+
+/// Limits applied to every filter.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// The amount of fuel (roughly, the number of WASM instructions) a filter
+    /// may consume while processing a single request or response.
+    pub fuel: u64,
+
+    /// How long a filter may run while processing a single request or
+    /// response.
+    pub timeout: Duration,
+
+    /// The maximum number of idle instances retained for each filter.
+    pub pool_size: usize,
+}
+
+/// A list of loaded filters, applied to requests in order and to responses in
+/// reverse order.
+#[derive(Clone, Debug)]
+pub struct Filters(Arc<[Arc<Filter>]>);
+
+pub(crate) struct Filter {
+    name: String,
+    configuration: Bytes,
+    limits: Limits,
+    module: Module,
+    linker: Linker<Host>,
+    pool: Mutex<Vec<Instance>>,
+}
+
+/// A filter's handling of a request or response head.
+pub(crate) enum Outcome<P> {
+    Continue(P),
+    Respond(LocalResponse),
+}
+
+/// A filter instance processing a single request.
+pub(crate) struct Stream {
+    filter: Arc<Filter>,
+    instance: Instance,
+    context: i32,
+}
+
+struct Instance {
+    store: Store<Host>,
+    instance: wasmtime::Instance,
+    fuel_added: u64,
+    next_context: i32,
+}
+
+// === impl Filters ===
+
+impl Filters {
+    /// Compiles and instantiates each filter's module.
+    pub fn load(configs: Vec<FilterConfig>, limits: Limits) -> Result<Self, LoadError> {
+        let mut config = wasmtime::Config::new();
+        config.consume_fuel(true).interruptable(true);
+        let engine = Engine::new(&config).map_err(|e| LoadError {
+            name: "*".to_string(),
+            source: e.into(),
+        })?;
+
+        let filters = configs
+            .into_iter()
+            .map(|config| {
+                let name = config.name.clone();
+                Filter::load(&engine, config, limits)
+                    .map(Arc::new)
+                    .map_err(|source| LoadError { name, source })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self(filters.into()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Arc<Filter>> {
+        self.0.iter()
+    }
+}
+
+// === impl Filter ===
+
+impl Filter {
+    fn load(engine: &Engine, config: FilterConfig, limits: Limits) -> Result<Self, Error> {
+        let module = Module::from_file(engine, &config.module)?;
+        let configuration = match config.configuration {
+            Some(path) => std::fs::read(path)?.into(),
+            None => Bytes::new(),
+        };
+        let linker = abi::linker(&module)?;
+        let filter = Self {
+            name: config.name,
+            configuration,
+            limits,
+            module,
+            linker,
+            pool: Mutex::new(Vec::with_capacity(limits.pool_size)),
+        };
+
+        // Instantiate the module eagerly so that invalid modules or
+        // configurations are reported at startup.
+        let instance = filter.instantiate(filter.store())?;
+        filter.release(instance);
+        debug!(filter = %filter.name, "Loaded");
+        Ok(filter)
+    }
+
+    fn store(&self) -> Store<Host> {
+        let host = Host {
+            name: self.name.clone(),
+            configuration: self.configuration.clone(),
+            ..Host::default()
+        };
+        Store::new(self.module.engine(), host)
+    }
+
+    fn instantiate(&self, mut store: Store<Host>) -> Result<Instance, Error> {
+        let instance = self.linker.instantiate(&mut store, &self.module)?;
+        let mut instance = Instance {
+            store,
+            instance,
+            fuel_added: 0,
+            next_context: ROOT_CONTEXT + 1,
+        };
+        instance.refuel(self.limits.fuel)?;
+
+        // Reactor modules must be initialized before any other export is
+        // called.
+        instance.call::<(), ()>("_initialize", ())?;
+        instance.call::<(i32, i32), ()>("proxy_on_context_create", (ROOT_CONTEXT, 0))?;
+        if instance.call::<(i32, i32), i32>("proxy_on_vm_start", (ROOT_CONTEXT, 0))? == Some(0) {
+            return Err("filter failed to start".into());
+        }
+        let len = self.configuration.len() as i32;
+        if instance.call::<(i32, i32), i32>("proxy_on_configure", (ROOT_CONTEXT, len))? == Some(0) {
+            return Err("filter rejected its configuration".into());
+        }
+        Ok(instance)
+    }
+
+    /// Takes an idle instance from the pool or instantiates a new one.
+    ///
+    /// Modules run while they are instantiated, so instantiation is subject
+    /// to the same limits as the filter's other calls.
+    async fn acquire(self: &Arc<Self>) -> Result<Instance, FilterError> {
+        if let Some(instance) = self.pool.lock().pop() {
+            return Ok(instance);
+        }
+
+        let store = self.store();
+        let interrupt = self.interrupt_handle(&store)?;
+        let filter = self.clone();
+        let task = tokio::task::spawn_blocking(move || filter.instantiate(store));
+        self.limit(interrupt, task).await
+    }
+
+    fn release(&self, instance: Instance) {
+        let mut pool = self.pool.lock();
+        if pool.len() < self.limits.pool_size {
+            pool.push(instance);
+        }
+    }
+
+    fn interrupt_handle(&self, store: &Store<Host>) -> Result<InterruptHandle, FilterError> {
+        store.interrupt_handle().map_err(|e| FilterError::Failed {
+            name: self.name.clone(),
+            source: e.into(),
+        })
+    }
+
+    /// Awaits a blocking call into the filter, interrupting it if it exceeds
+    /// the filter's time limit.
+    async fn limit<T>(
+        &self,
+        interrupt: InterruptHandle,
+        mut task: JoinHandle<Result<T, Error>>,
+    ) -> Result<T, FilterError> {
+        let name = self.name.clone();
+        tokio::select! {
+            res = &mut task => match res {
+                Ok(Ok(t)) => Ok(t),
+                Ok(Err(source)) => Err(FilterError::Failed { name, source }),
+                Err(e) => Err(FilterError::Failed { name, source: e.into() }),
+            },
+            _ = tokio::time::sleep(self.limits.timeout) => {
+                interrupt.interrupt();
+                let _ = task.await;
+                Err(FilterError::Timeout(name))
+            }
+        }
+    }
+
+    /// Processes a request head, returning the stream so that the response
+    /// may be processed by the same instance.
+    pub(crate) async fn on_request(
+        self: Arc<Self>,
+        req: http::request::Parts,
+    ) -> Result<(Stream, Outcome<http::request::Parts>), FilterError> {
+        let instance = self.acquire().await?;
+        let stream = Stream {
+            filter: self,
+            instance,
+            context: 0,
+        };
+        stream
+            .run(move |instance, context| {
+                instance
+                    .call::<(i32, i32), ()>("proxy_on_context_create", (context, ROOT_CONTEXT))?;
+                let headers = req.headers.len() as i32;
+                instance.store.data_mut().request = Some(req);
+                let res = instance.call::<(i32, i32, i32), i32>(
+                    "proxy_on_request_headers",
+                    (context, headers, 0),
+                );
+                let req = instance.store.data_mut().request.take();
+                res?;
+                instance.outcome(req)
+            })
+            .await
+    }
+}
+
+impl fmt::Debug for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Filter")
+            .field("name", &self.name)
+            .field("limits", &self.limits)
+            .finish()
+    }
+}
+
+// === impl Stream ===
+
+impl Stream {
+    /// Processes a response head and returns the instance to its filter's
+    /// pool.
+    pub(crate) async fn on_response(
+        self,
+        rsp: http::response::Parts,
+    ) -> Result<Outcome<http::response::Parts>, FilterError> {
+        let (stream, outcome) = self
+            .run(move |instance, context| {
+                let headers = rsp.headers.len() as i32;
+                instance.store.data_mut().response = Some(rsp);
+                let res = instance.call::<(i32, i32, i32), i32>(
+                    "proxy_on_response_headers",
+                    (context, headers, 0),
+                );
+                let rsp = instance.store.data_mut().response.take();
+                res?;
+                instance.outcome(rsp)
+            })
+            .await?;
+        stream.finish().await;
+        Ok(outcome)
+    }
+
+    /// Completes the stream's context and returns the instance to its
+    /// filter's pool.
+    ///
+    /// Instances whose filter failed or timed out are never returned to the
+    /// pool, since they may be left in an inconsistent state.
+    pub(crate) async fn finish(self) {
+        let res = self
+            .run(|instance, context| {
+                instance.call::<i32, i32>("proxy_on_done", context)?;
+                instance.call::<i32, ()>("proxy_on_delete", context)?;
+                Ok(())
+            })
+            .await;
+        match res {
+            Ok((stream, ())) => stream.filter.release(stream.instance),
+            Err(error) => debug!(%error, "Failed to complete stream"),
+        }
+    }
+
+    /// Runs `f` on a blocking thread, interrupting it if it exceeds the
+    /// filter's time limit.
+    async fn run<F, T>(mut self, f: F) -> Result<(Self, T), FilterError>
+    where
+        F: FnOnce(&mut Instance, i32) -> Result<T, Error> + Send + 'static,
+        T: Send + 'static,
+    {
+        let filter = self.filter.clone();
+        let interrupt = filter.interrupt_handle(&self.instance.store)?;
+        if self.context == 0 {
+            self.context = self.instance.next_context();
+        }
+
+        let fuel = filter.limits.fuel;
+        let task = tokio::task::spawn_blocking(move || {
+            self.instance
+                .refuel(fuel)
+                .and_then(|()| f(&mut self.instance, self.context))
+                .map(move |t| (self, t))
+        });
+        filter.limit(interrupt, task).await
+    }
+}
+
+// === impl Instance ===
+
+impl Instance {
+    fn next_context(&mut self) -> i32 {
+        let context = self.next_context;
+        self.next_context = self.next_context.checked_add(1).unwrap_or(ROOT_CONTEXT + 1);
+        context
+    }
+
+    /// Tops up the instance's fuel to `fuel`.
+    fn refuel(&mut self, fuel: u64) -> Result<(), Error> {
+        let consumed = self.store.fuel_consumed().unwrap_or(0);
+        let remaining = self.fuel_added.saturating_sub(consumed);
+        if remaining < fuel {
+            self.store.add_fuel(fuel - remaining)?;
+            self.fuel_added += fuel - remaining;
+        }
+        Ok(())
+    }
+
+    /// Calls an export, if the module provides it.
+    fn call<P, R>(&mut self, name: &str, params: P) -> Result<Option<R>, Error>
+    where
+        P: WasmParams,
+        R: WasmResults,
+    {
+        let func = match self.instance.get_func(&mut self.store, name) {
+            Some(func) => func,
+            None => return Ok(None),
+        };
+        let res = func
+            .typed::<P, R, _>(&self.store)?
+            .call(&mut self.store, params)?;
+        Ok(Some(res))
+    }
+
+    fn outcome<P>(&mut self, head: Option<P>) -> Result<Outcome<P>, Error> {
+        if let Some(rsp) = self.store.data_mut().local_response.take() {
+            return Ok(Outcome::Respond(rsp));
+        }
+        head.map(Outcome::Continue)
+            .ok_or_else(|| "filter did not return the message head".into())
+    }
+}
@@ -0,0 +1,50 @@
+//! Experimental WebAssembly (WASM) filters for HTTP requests and responses.
+//!
+//! Filters are WASM modules that implement a subset of the [proxy-wasm ABI]
+//! (version 0.2): they may inspect and modify request and response headers
+//! (including the `:path`, `:authority`, and `:status` pseudo-headers) and
+//! may answer requests with a local response. Request and response bodies,
+//! timers, and outbound calls are not supported; modules that import other
+//! host functions are loaded, but those functions fail with `Unimplemented`.
+//!
+//! Each filter's instances are pooled so that modules need not be
+//! instantiated for every request, and each invocation of a filter is bounded
+//! by a fuel (instruction) budget and a time limit.
+//!
+//! [proxy-wasm ABI]: https://github.com/proxy-wasm/spec
+
+#![deny(warnings, rust_2018_idioms)]
+#![forbid(unsafe_code)]
+
+mod abi;
+mod filter;
+mod service;
+
+pub use self::{
+    filter::{FilterConfig, Filters, Limits},
+    service::{FilterHttp, NewFilterHttp},
+};
+use thiserror::Error;
+
+/// Indicates that a filter could not be loaded.
+#[derive(Debug, Error)]
+#[error("failed to load WASM filter {name}: {source}")]
+pub struct LoadError {
+    name: String,
+    #[source]
+    source: linkerd_error::Error,
+}
+
+/// Indicates that a filter failed while processing a request or response.
+#[derive(Debug, Error)]
+pub enum FilterError {
+    #[error("WASM filter {0} exceeded its time limit")]
+    Timeout(String),
+
+    #[error("WASM filter {name} failed: {source}")]
+    Failed {
+        name: String,
+        #[source]
+        source: linkerd_error::Error,
+    },
+}
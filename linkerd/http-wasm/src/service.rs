@@ -0,0 +1,281 @@
+use crate::{abi::LocalResponse, filter::Outcome, Filters};
+use futures::prelude::*;
+use linkerd_error::Error;
+use linkerd_http_box::BoxBody;
+use linkerd_stack::{layer, NewService};
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tracing::debug;
+
+/// Builds `FilterHttp` services.
+///
+/// When no filters are configured, requests are passed through unmodified.
+#[derive(Clone, Debug)]
+pub struct NewFilterHttp<N> {
+    inner: N,
+    filters: Option<Filters>,
+}
+
+/// Applies WASM filters to the heads of HTTP requests and responses.
+///
+/// Requests pass through filters in order and responses in reverse order. A
+/// filter may answer a request with a local response, in which case neither
+/// the remaining filters nor the inner service see the request.
+#[derive(Clone, Debug)]
+pub struct FilterHttp<S> {
+    inner: S,
+    filters: Option<Filters>,
+}
+
+// === impl NewFilterHttp ===
+
+impl<N> NewFilterHttp<N> {
+    pub fn layer(filters: Option<Filters>) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            inner,
+            filters: filters.clone(),
+        })
+    }
+}
+
+impl<T, N: NewService<T>> NewService<T> for NewFilterHttp<N> {
+    type Service = FilterHttp<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        FilterHttp {
+            inner: self.inner.new_service(target),
+            filters: self.filters.clone(),
+        }
+    }
+}
+
+// === impl FilterHttp ===
+
+impl<S> FilterHttp<S> {
+    pub fn layer(filters: Option<Filters>) -> impl layer::Layer<S, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            inner,
+            filters: filters.clone(),
+        })
+    }
+}
+
+impl<S> tower::Service<http::Request<BoxBody>> for FilterHttp<S>
+where
+    S: tower::Service<http::Request<BoxBody>, Response = http::Response<BoxBody>>
+        + Clone
+        + Send
+        + 'static,
+    S::Error: Into<Error>,
+    S::Future: Send,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<BoxBody>) -> Self::Future {
+        let filters = match self.filters.as_ref() {
+            Some(filters) if !filters.is_empty() => filters.clone(),
+            _ => {
+                let call = self.inner.call(req);
+                return Box::pin(async move { call.await.map_err(Into::into) });
+            }
+        };
+
+        // The inner service has already been driven to readiness, so take it
+        // and leave a clone in its place.
+        let mut inner = {
+            let clone = self.inner.clone();
+            std::mem::replace(&mut self.inner, clone)
+        };
+        Box::pin(async move {
+            let (mut head, body) = req.into_parts();
+            let mut streams = Vec::new();
+            for filter in filters.iter() {
+                let (stream, outcome) = filter.clone().on_request(head).await?;
+                match outcome {
+                    Outcome::Continue(h) => {
+                        head = h;
+                        streams.push(stream);
+                    }
+                    Outcome::Respond(rsp) => {
+                        debug!(status = %rsp.status, "Request answered by filter");
+                        stream.finish().await;
+                        return Ok(local_response(rsp));
+                    }
+                }
+            }
+
+            let rsp = match inner.call(http::Request::from_parts(head, body)).await {
+                Ok(rsp) => rsp,
+                Err(error) => {
+                    for stream in streams {
+                        stream.finish().await;
+                    }
+                    return Err(error.into());
+                }
+            };
+
+            let (mut head, body) = rsp.into_parts();
+            while let Some(stream) = streams.pop() {
+                match stream.on_response(head).await? {
+                    Outcome::Continue(h) => head = h,
+                    Outcome::Respond(rsp) => {
+                        debug!(status = %rsp.status, "Response replaced by filter");
+                        for stream in streams {
+                            stream.finish().await;
+                        }
+                        return Ok(local_response(rsp));
+                    }
+                }
+            }
+            Ok(http::Response::from_parts(head, body))
+        })
+    }
+}
+
+fn local_response(rsp: LocalResponse) -> http::Response<BoxBody> {
+    let mut local = http::Response::new(BoxBody::new(hyper::Body::from(rsp.body)));
+    *local.status_mut() = rsp.status;
+    *local.headers_mut() = rsp.headers;
+    local
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FilterConfig, FilterError, Limits};
+    use linkerd_stack::layer::Layer;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    /// Adds a header to requests and responses, and answers requests that
+    /// have an `x-deny` header with a 403.
+    const HEADERS: &str = r#"
+        (module
+          (import "env" "proxy_get_header_map_value"
+            (func $get (param i32 i32 i32 i32 i32) (result i32)))
+          (import "env" "proxy_add_header_map_value"
+            (func $add (param i32 i32 i32 i32 i32) (result i32)))
+          (import "env" "proxy_send_local_response"
+            (func $respond (param i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (global $heap (mut i32) (i32.const 1024))
+          (data (i32.const 0) "x-request-filtered")
+          (data (i32.const 32) "x-response-filtered")
+          (data (i32.const 64) "true")
+          (data (i32.const 96) "x-deny")
+          (func (export "proxy_on_memory_allocate") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $heap))
+            (global.set $heap (i32.add (global.get $heap) (local.get $len)))
+            (local.get $ptr))
+          (func (export "proxy_on_request_headers") (param i32 i32 i32) (result i32)
+            (if (i32.eqz (call $get (i32.const 0) (i32.const 96) (i32.const 6) (i32.const 128) (i32.const 132)))
+              (then
+                ;; An empty header map is serialized as a zero count.
+                (drop (call $respond (i32.const 403) (i32.const 0) (i32.const 0)
+                  (i32.const 0) (i32.const 0) (i32.const 256) (i32.const 4) (i32.const -1)))
+                (return (i32.const 1))))
+            (drop (call $add (i32.const 0) (i32.const 0) (i32.const 18) (i32.const 64) (i32.const 4)))
+            (i32.const 0))
+          (func (export "proxy_on_response_headers") (param i32 i32 i32) (result i32)
+            (drop (call $add (i32.const 2) (i32.const 32) (i32.const 19) (i32.const 64) (i32.const 4)))
+            (i32.const 0)))
+    "#;
+
+    /// Never finishes processing a request.
+    const SPIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "proxy_on_request_headers") (param i32 i32 i32) (result i32)
+            (loop $spin (br $spin))
+            (i32.const 0)))
+    "#;
+
+    fn load(name: &str, wat: &str, limits: Limits) -> Filters {
+        let module = std::env::temp_dir().join(format!(
+            "linkerd-http-wasm-{}-{}.wat",
+            std::process::id(),
+            name
+        ));
+        std::fs::write(&module, wat).expect("module must be written");
+        let filters = Filters::load(
+            vec![FilterConfig {
+                name: name.to_string(),
+                module: module.clone(),
+                configuration: None,
+            }],
+            limits,
+        );
+        let _ = std::fs::remove_file(module);
+        filters.expect("filter must load")
+    }
+
+    fn limits() -> Limits {
+        Limits {
+            fuel: 1 << 40,
+            timeout: Duration::from_millis(100),
+            // Without pooling, every request instantiates the module.
+            pool_size: 0,
+        }
+    }
+
+    async fn call(
+        filters: &Filters,
+        req: http::Request<BoxBody>,
+    ) -> Result<http::Response<BoxBody>, Error> {
+        let inner = tower::service_fn(|req: http::Request<BoxBody>| async move {
+            assert_eq!(req.headers()["x-request-filtered"], "true");
+            Ok::<_, Error>(http::Response::new(BoxBody::default()))
+        });
+        FilterHttp::layer(Some(filters.clone()))
+            .layer(inner)
+            .oneshot(req)
+            .await
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn filters_request_and_response_heads() {
+        let filters = load("headers", HEADERS, limits());
+        for _ in 0..2 {
+            let rsp = call(&filters, http::Request::new(BoxBody::default()))
+                .await
+                .expect("request must succeed");
+            assert_eq!(rsp.status(), http::StatusCode::OK);
+            assert_eq!(rsp.headers()["x-response-filtered"], "true");
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn filter_responds_locally() {
+        let filters = load("deny", HEADERS, limits());
+        let req = http::Request::builder()
+            .header("x-deny", "1")
+            .body(BoxBody::default())
+            .unwrap();
+        let rsp = call(&filters, req).await.expect("request must succeed");
+        assert_eq!(rsp.status(), http::StatusCode::FORBIDDEN);
+        assert!(rsp.headers().get("x-response-filtered").is_none());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn filter_times_out() {
+        let filters = load("spin", SPIN, limits());
+        let error = call(&filters, http::Request::new(BoxBody::default()))
+            .await
+            .expect_err("request must fail");
+        assert!(
+            matches!(error.downcast_ref::<FilterError>(), Some(FilterError::Timeout(name)) if name == "spin"),
+            "unexpected error: {}",
+            error
+        );
+    }
+}
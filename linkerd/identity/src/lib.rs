@@ -29,7 +29,10 @@ struct SigningKey(Arc<EcdsaKeyPair>);
 struct Signer(Arc<EcdsaKeyPair>);
 
 #[derive(Clone)]
-pub struct TrustAnchors(Arc<rustls::ClientConfig>);
+pub struct TrustAnchors {
+    client: Arc<rustls::ClientConfig>,
+    max_intermediates: Option<usize>,
+}
 
 #[derive(Clone, Debug)]
 pub struct TokenSource(Arc<String>);
@@ -45,12 +48,39 @@ pub struct Crt {
 pub struct CrtKey {
     id: LocalId,
     expiry: SystemTime,
+    serial: Option<Vec<u8>>,
     client_config: Arc<rustls::ClientConfig>,
     server_config: Arc<rustls::ServerConfig>,
+    client_verifier: Arc<dyn rustls::ClientCertVerifier>,
+    max_intermediates: Option<usize>,
+}
+
+/// Trust anchors for a federated trust domain.
+///
+/// Client certificates issued by a federated trust domain's anchors are only
+/// accepted when the client's identity is within that trust domain.
+#[derive(Clone)]
+pub struct FederatedTrustDomain {
+    domain: linkerd_dns_name::Suffix,
+    verifier: Arc<dyn rustls::ClientCertVerifier>,
 }
 
 struct CertResolver(rustls::sign::CertifiedKey);
 
+/// Limits the number of intermediate certificates a TLS client may present.
+struct ClientCertVerifier {
+    inner: Arc<dyn rustls::ClientCertVerifier>,
+    max_intermediates: usize,
+}
+
+/// Accepts client certificates issued by the local trust anchors or, for
+/// identities within a federated trust domain, by that domain's anchors.
+struct FederatedClientCertVerifier {
+    local: Arc<dyn rustls::ClientCertVerifier>,
+    domains: Arc<[FederatedTrustDomain]>,
+    max_intermediates: Option<usize>,
+}
+
 #[derive(Clone, Debug, Error)]
 #[error(transparent)]
 pub struct InvalidCrt(rustls::TLSError);
@@ -215,7 +245,10 @@ impl TokenSource {
 impl TrustAnchors {
     #[cfg(any(test, feature = "test-util"))]
     fn empty() -> Self {
-        TrustAnchors(Arc::new(rustls::ClientConfig::new()))
+        TrustAnchors {
+            client: Arc::new(rustls::ClientConfig::new()),
+            max_intermediates: None,
+        }
     }
 
     pub fn from_pem(s: &str) -> Option<Self> {
@@ -243,11 +276,41 @@ impl TrustAnchors {
         // more tested.
         c.enable_tickets = false;
 
-        Some(TrustAnchors(Arc::new(c)))
+        Some(TrustAnchors {
+            client: Arc::new(c),
+            max_intermediates: None,
+        })
+    }
+
+    /// Limits the number of intermediate certificates that may be present in
+    /// a certificate chain, both for the local identity and for the
+    /// certificates presented by TLS clients.
+    ///
+    /// By default, the chain length is only bounded by webpki's own limits.
+    pub fn with_max_intermediates(self, max: usize) -> Self {
+        Self {
+            max_intermediates: Some(max),
+            ..self
+        }
+    }
+
+    pub fn max_intermediates(&self) -> Option<usize> {
+        self.max_intermediates
     }
 
     pub fn certify(&self, key: Key, crt: Crt) -> Result<CrtKey, InvalidCrt> {
-        let mut client = self.0.as_ref().clone();
+        let mut client = self.client.as_ref().clone();
+
+        if let Some(max) = self.max_intermediates {
+            // The chain includes the leaf certificate.
+            if crt.chain.len() > max + 1 {
+                return Err(InvalidCrt(rustls::TLSError::General(format!(
+                    "certificate chain has {} intermediates; at most {} are permitted",
+                    crt.chain.len() - 1,
+                    max
+                ))));
+            }
+        }
 
         // Ensure the certificate is valid for the services we terminate for
         // TLS. This assumes that server cert validation does the same or
@@ -268,6 +331,8 @@ impl TrustAnchors {
             .map_err(InvalidCrt)?;
         debug!("certified {}", crt.id);
 
+        let serial = crt.chain.first().and_then(|c| parse_serial(c.as_ref()));
+
         let k = SigningKey(key.0);
         let key = rustls::sign::CertifiedKey::new(crt.chain, Arc::new(Box::new(k)));
         let resolver = Arc::new(CertResolver(key));
@@ -284,28 +349,40 @@ impl TrustAnchors {
         // TODO: lock down the verification further.
         //
         // TODO: Change Rustls's API to Avoid needing to clone `root_cert_store`.
-        let mut server = rustls::ServerConfig::new(
-            rustls::AllowAnyAnonymousOrAuthenticatedClient::new(self.0.root_store.clone()),
-        );
+        let verifier =
+            rustls::AllowAnyAnonymousOrAuthenticatedClient::new(self.client.root_store.clone());
+        let verifier = match self.max_intermediates {
+            Some(max_intermediates) => Arc::new(ClientCertVerifier {
+                inner: verifier,
+                max_intermediates,
+            }),
+            None => verifier,
+        };
+        let mut server = rustls::ServerConfig::new(verifier.clone());
         server.versions = TLS_VERSIONS.to_vec();
         server.cert_resolver = resolver;
 
         Ok(CrtKey {
             id: crt.id,
             expiry: crt.expiry,
+            serial,
             client_config: Arc::new(client),
             server_config: Arc::new(server),
+            client_verifier: verifier,
+            max_intermediates: self.max_intermediates,
         })
     }
 
     pub fn client_config(&self) -> Arc<rustls::ClientConfig> {
-        self.0.clone()
+        self.client.clone()
     }
 }
 
 impl fmt::Debug for TrustAnchors {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("TrustAnchors").finish()
+        f.debug_struct("TrustAnchors")
+            .field("max_intermediates", &self.max_intermediates)
+            .finish()
     }
 }
 
@@ -351,6 +428,11 @@ impl CrtKey {
         &self.id
     }
 
+    /// Returns the leaf certificate's serial number, if it could be parsed.
+    pub fn serial(&self) -> Option<&[u8]> {
+        self.serial.as_deref()
+    }
+
     pub fn client_config(&self) -> Arc<rustls::ClientConfig> {
         self.client_config.clone()
     }
@@ -358,6 +440,25 @@ impl CrtKey {
     pub fn server_config(&self) -> Arc<rustls::ServerConfig> {
         self.server_config.clone()
     }
+
+    /// Returns a server configuration that also accepts clients with
+    /// identities in the given federated trust domains.
+    pub fn federated_server_config(
+        &self,
+        domains: Arc<[FederatedTrustDomain]>,
+    ) -> Arc<rustls::ServerConfig> {
+        if domains.is_empty() {
+            return self.server_config();
+        }
+
+        let mut server = self.server_config.as_ref().clone();
+        server.set_client_certificate_verifier(Arc::new(FederatedClientCertVerifier {
+            local: self.client_verifier.clone(),
+            domains,
+            max_intermediates: self.max_intermediates,
+        }));
+        Arc::new(server)
+    }
 }
 
 impl fmt::Debug for CrtKey {
@@ -369,6 +470,54 @@ impl fmt::Debug for CrtKey {
     }
 }
 
+/// Extracts the serial number from a DER-encoded X.509 certificate.
+///
+/// The certificate has already been validated by webpki, so this only walks
+/// far enough into the `TBSCertificate` to read the serial number.
+fn parse_serial(der: &[u8]) -> Option<Vec<u8>> {
+    const SEQUENCE: u8 = 0x30;
+    const INTEGER: u8 = 0x02;
+    const EXPLICIT_VERSION: u8 = 0xa0;
+
+    /// Reads a single DER TLV with the given tag, returning its value and the
+    /// remaining input.
+    fn read(input: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+        let (&t, input) = input.split_first()?;
+        if t != tag {
+            return None;
+        }
+        let (&len, mut input) = input.split_first()?;
+        let len = if len & 0x80 == 0 {
+            len as usize
+        } else {
+            let n = (len & 0x7f) as usize;
+            if n == 0 || n > 4 || input.len() < n {
+                return None;
+            }
+            let (bytes, rest) = input.split_at(n);
+            input = rest;
+            bytes.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize)
+        };
+        if input.len() < len {
+            return None;
+        }
+        Some(input.split_at(len))
+    }
+
+    let (crt, _) = read(der, SEQUENCE)?;
+    let (mut tbs, _) = read(crt, SEQUENCE)?;
+    if tbs.first() == Some(&EXPLICIT_VERSION) {
+        tbs = read(tbs, EXPLICIT_VERSION)?.1;
+    }
+    let (serial, _) = read(tbs, INTEGER)?;
+
+    // Strip the leading zero used to keep the integer positive.
+    match serial {
+        [0, rest @ ..] if !rest.is_empty() => Some(rest.to_vec()),
+        serial => Some(serial.to_vec()),
+    }
+}
+
 // === impl CertResolver ===
 
 impl rustls::ResolvesClientCert for CertResolver {
@@ -428,6 +577,154 @@ impl rustls::ResolvesServerCert for CertResolver {
     }
 }
 
+// === impl ClientCertVerifier ===
+
+impl rustls::ClientCertVerifier for ClientCertVerifier {
+    fn offer_client_auth(&self) -> bool {
+        self.inner.offer_client_auth()
+    }
+
+    fn client_auth_mandatory(&self, sni: Option<&webpki::DNSName>) -> Option<bool> {
+        self.inner.client_auth_mandatory(sni)
+    }
+
+    fn client_auth_root_subjects(
+        &self,
+        sni: Option<&webpki::DNSName>,
+    ) -> Option<rustls::DistinguishedNames> {
+        self.inner.client_auth_root_subjects(sni)
+    }
+
+    fn verify_client_cert(
+        &self,
+        presented_certs: &[rustls::Certificate],
+        sni: Option<&webpki::DNSName>,
+    ) -> Result<rustls::ClientCertVerified, rustls::TLSError> {
+        // The presented chain includes the client's leaf certificate.
+        if presented_certs.len() > self.max_intermediates + 1 {
+            debug!(
+                intermediates = presented_certs.len() - 1,
+                max = self.max_intermediates,
+                "client certificate chain is too long"
+            );
+            return Err(rustls::TLSError::General(
+                "client certificate chain exceeds the maximum verification depth".to_string(),
+            ));
+        }
+        self.inner.verify_client_cert(presented_certs, sni)
+    }
+}
+
+// === impl FederatedClientCertVerifier ===
+
+impl rustls::ClientCertVerifier for FederatedClientCertVerifier {
+    fn offer_client_auth(&self) -> bool {
+        self.local.offer_client_auth()
+    }
+
+    fn client_auth_mandatory(&self, sni: Option<&webpki::DNSName>) -> Option<bool> {
+        self.local.client_auth_mandatory(sni)
+    }
+
+    fn client_auth_root_subjects(
+        &self,
+        sni: Option<&webpki::DNSName>,
+    ) -> Option<rustls::DistinguishedNames> {
+        let mut subjects = self.local.client_auth_root_subjects(sni)?;
+        for domain in self.domains.iter() {
+            subjects.extend(
+                domain
+                    .verifier
+                    .client_auth_root_subjects(sni)
+                    .unwrap_or_default(),
+            );
+        }
+        Some(subjects)
+    }
+
+    fn verify_client_cert(
+        &self,
+        presented_certs: &[rustls::Certificate],
+        sni: Option<&webpki::DNSName>,
+    ) -> Result<rustls::ClientCertVerified, rustls::TLSError> {
+        let error = match self.local.verify_client_cert(presented_certs, sni) {
+            Ok(verified) => return Ok(verified),
+            Err(error) => error,
+        };
+
+        // The local verifier enforces the chain length bound, so it must be
+        // checked again before trying the federated anchors.
+        if let Some(max) = self.max_intermediates {
+            if presented_certs.len() > max + 1 {
+                return Err(error);
+            }
+        }
+
+        // Only the trust domains that contain the client's identity may vouch
+        // for it.
+        let name = match presented_certs.first().and_then(|c| leaf_name(c.as_ref())) {
+            Some(name) => name,
+            None => return Err(error),
+        };
+        for domain in self.domains.iter().filter(|d| d.domain.contains(&name)) {
+            if let Ok(verified) = domain.verifier.verify_client_cert(presented_certs, sni) {
+                debug!(%name, domain = %domain.domain, "Verified federated client certificate");
+                return Ok(verified);
+            }
+        }
+        Err(error)
+    }
+}
+
+/// Returns the first DNS name in a DER-encoded certificate.
+fn leaf_name(der: &[u8]) -> Option<linkerd_dns_name::Name> {
+    let crt = webpki::EndEntityCert::from(der).ok()?;
+    match crt.dns_names().ok()?.first()? {
+        webpki::GeneralDNSNameRef::DNSName(n) => Some(n.to_owned().into()),
+        webpki::GeneralDNSNameRef::Wildcard(_) => None,
+    }
+}
+
+// === impl FederatedTrustDomain ===
+
+impl FederatedTrustDomain {
+    /// Reads a federated trust domain's anchors from a PEM-encoded bundle.
+    pub fn from_pem(domain: linkerd_dns_name::Suffix, s: &str) -> Option<Self> {
+        use std::io::Cursor;
+
+        let mut roots = rustls::RootCertStore::empty();
+        let (added, skipped) = roots.add_pem_file(&mut Cursor::new(s)).ok()?;
+        if skipped != 0 {
+            warn!(%domain, "skipped {} federated trust anchors", skipped);
+        }
+        if added == 0 {
+            return None;
+        }
+
+        Some(Self {
+            domain,
+            verifier: rustls::AllowAnyAnonymousOrAuthenticatedClient::new(roots),
+        })
+    }
+
+    pub fn domain(&self) -> &linkerd_dns_name::Suffix {
+        &self.domain
+    }
+
+    /// Indicates whether the given identity is within this trust domain.
+    pub fn contains(&self, Name(ref name): &Name) -> bool {
+        self.domain.contains(name)
+    }
+}
+
+impl fmt::Debug for FederatedTrustDomain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FederatedTrustDomain")
+            .field("domain", &self.domain)
+            .finish()
+    }
+}
+
 // === impl LocalId ===
 
 impl From<Name> for LocalId {
@@ -457,12 +754,27 @@ impl fmt::Display for LocalId {
 #[cfg(test)]
 mod tests {
     use super::test_util::*;
+    use super::{rustls, FederatedTrustDomain};
 
     #[test]
     fn can_construct_client_and_server_config_from_valid_settings() {
         FOO_NS1.validate().expect("foo.ns1 must be valid");
     }
 
+    #[test]
+    fn parses_leaf_serial() {
+        let crt_key = FOO_NS1.validate().expect("foo.ns1 must be valid");
+        assert_eq!(
+            crt_key.serial(),
+            Some(
+                &[
+                    0x58, 0x1c, 0x43, 0xd1, 0x07, 0x56, 0x53, 0x76, 0x42, 0x79, 0x73, 0xd8, 0x3c,
+                    0x36, 0xc2, 0xef, 0x5d, 0x49, 0xa6, 0xa8
+                ][..]
+            )
+        );
+    }
+
     #[test]
     fn recognize_ca_did_not_issue_cert() {
         let s = Identity {
@@ -482,6 +794,37 @@ mod tests {
         assert!(s.validate().is_err(), "identity should not be valid");
     }
 
+    #[test]
+    fn can_construct_configs_from_chain_with_intermediate() {
+        FOO_NS1_INT1
+            .validate()
+            .expect("foo.ns1 must be valid with its intermediate");
+    }
+
+    #[test]
+    fn recognize_missing_intermediate() {
+        let s = Identity {
+            intermediates: &[],
+            ..FOO_NS1_INT1
+        };
+        assert!(s.validate().is_err(), "chain must include the intermediate");
+    }
+
+    #[test]
+    fn recognize_too_many_intermediates() {
+        let certify = |max| {
+            FOO_NS1_INT1
+                .trust_anchors()
+                .with_max_intermediates(max)
+                .certify(FOO_NS1_INT1.key(), FOO_NS1_INT1.crt())
+        };
+        assert!(
+            certify(0).is_err(),
+            "chain must not exceed the maximum depth"
+        );
+        certify(1).expect("chain must be within the maximum depth");
+    }
+
     #[test]
     #[ignore] // XXX this doesn't fail because we don't actually check the key against the cert...
     fn recognize_private_key_is_not_valid_for_cert() {
@@ -491,4 +834,36 @@ mod tests {
         };
         assert!(s.validate().is_err(), "identity should not be valid");
     }
+
+    #[test]
+    fn federated_trust_domains_are_scoped() {
+        let server = BAR_NS1.validate().expect("bar.ns1 must be valid");
+        let ca2 = std::str::from_utf8(include_bytes!("testdata/ca2.pem")).unwrap();
+        let foo_ca2 = [rustls::Certificate(
+            include_bytes!("testdata/foo-ns1-ca2/crt.der").to_vec(),
+        )];
+        let verify = |domain: &str| {
+            let domain = FederatedTrustDomain::from_pem(domain.parse().unwrap(), ca2)
+                .expect("ca2 must be valid");
+            server
+                .federated_server_config(vec![domain].into())
+                .get_verifier()
+                .verify_client_cert(&foo_ca2, None)
+        };
+
+        assert!(
+            server
+                .server_config()
+                .get_verifier()
+                .verify_client_cert(&foo_ca2, None)
+                .is_err(),
+            "ca2 must not be trusted locally"
+        );
+        verify("ns1.serviceaccount.identity.linkerd.cluster.local")
+            .expect("ca2 must be trusted for identities in its domain");
+        assert!(
+            verify("ns2.serviceaccount.identity.linkerd.cluster.local").is_err(),
+            "ca2 must not be trusted for identities outside its domain"
+        );
+    }
 }
@@ -5,6 +5,7 @@ pub struct Identity {
     pub name: &'static str,
     pub trust_anchors: &'static [u8],
     pub crt: &'static [u8],
+    pub intermediates: &'static [&'static [u8]],
     pub key: &'static [u8],
 }
 
@@ -12,13 +13,33 @@ pub static FOO_NS1: Identity = Identity {
     name: "foo.ns1.serviceaccount.identity.linkerd.cluster.local",
     trust_anchors: include_bytes!("testdata/ca1.pem"),
     crt: include_bytes!("testdata/foo-ns1-ca1/crt.der"),
+    intermediates: &[],
     key: include_bytes!("testdata/foo-ns1-ca1/key.p8"),
 };
 
+/// Issued by an intermediate CA that is itself issued by `ca1`.
+pub static FOO_NS1_INT1: Identity = Identity {
+    name: "foo.ns1.serviceaccount.identity.linkerd.cluster.local",
+    trust_anchors: include_bytes!("testdata/ca1.pem"),
+    crt: include_bytes!("testdata/foo-ns1-ca1-int1/crt.der"),
+    intermediates: &[include_bytes!("testdata/ca1-int1.der")],
+    key: include_bytes!("testdata/foo-ns1-ca1-int1/key.p8"),
+};
+
+/// The same name as `FOO_NS1`, issued by `ca2`.
+pub static FOO_NS1_CA2: Identity = Identity {
+    name: "foo.ns1.serviceaccount.identity.linkerd.cluster.local",
+    trust_anchors: include_bytes!("testdata/ca2.pem"),
+    crt: include_bytes!("testdata/foo-ns1-ca2/crt.der"),
+    intermediates: &[],
+    key: include_bytes!("testdata/foo-ns1-ca2/key.p8"),
+};
+
 pub static BAR_NS1: Identity = Identity {
     name: "bar.ns1.serviceaccount.identity.linkerd.cluster.local",
     trust_anchors: include_bytes!("testdata/ca1.pem"),
     crt: include_bytes!("testdata/bar-ns1-ca1/crt.der"),
+    intermediates: &[],
     key: include_bytes!("testdata/bar-ns1-ca1/key.p8"),
 };
 
@@ -37,7 +58,8 @@ impl Identity {
 
         let n = Name::from_str(self.name).expect("name must be valid");
         let der = self.crt.iter().copied().collect();
-        Crt::new(LocalId(n), der, vec![], SystemTime::now() + HOUR)
+        let intermediates = self.intermediates.iter().map(|c| c.to_vec()).collect();
+        Crt::new(LocalId(n), der, intermediates, SystemTime::now() + HOUR)
     }
 
     pub fn validate(&self) -> Result<CrtKey, InvalidCrt> {
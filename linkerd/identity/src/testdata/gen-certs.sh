@@ -17,6 +17,25 @@ ca() {
   rm "${filename}.csr"
 }
 
+intermediate() {
+  ca_name=$1
+  name=$2
+  filename=$3
+
+  echo "{\"names\":[{\"CN\": \"${name}\",\"OU\":\"None\"}]}" \
+    | cfssl genkey - \
+    | cfssljson -bare "${filename}"
+
+  cfssl sign -ca "${ca_name}.pem" -ca-key "${ca_name}-key.pem" -config=ca-config.json \
+      -profile intermediate "${filename}.csr" \
+    | cfssljson -bare "${filename}"
+  rm "${filename}.csr"
+
+  openssl x509 -inform pem -outform der \
+    -in "${filename}.pem" \
+    -out "${filename}.der"
+}
+
 ee() {
   ca_name=$1
   ee_name=$2
@@ -50,6 +69,7 @@ ee() {
 
 ca "Cluster-local CA 1" ca1
 ca "Cluster-local CA 1" ca2 # Same name, different key pair.
+intermediate ca1 "Cluster-local Intermediate CA 1" ca1-int1
 
 # The controller itself.
 ee ca1 controller linkerd linkerd
@@ -57,3 +77,7 @@ ee ca1 controller linkerd linkerd
 ee ca1 foo ns1 linkerd
 ee ca2 foo ns1 linkerd # Same, but different CA
 ee ca1 bar ns1 linkerd # Different service.
+ee ca1-int1 foo ns1 linkerd # Same, but issued by an intermediate CA
+
+# Tests never sign with the intermediate CA, so its key is not kept.
+rm ca1-int1-key.pem
@@ -15,7 +15,7 @@ pub use self::{
     sensor::{Sensor, SensorIo},
 };
 pub use std::io::*;
-use std::net::SocketAddr;
+use std::{net::SocketAddr, time::Duration};
 pub use tokio::io::{
     duplex, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream, ReadBuf,
 };
@@ -88,3 +88,32 @@ impl PeerAddr for tokio::io::DuplexStream {
         Ok(([0, 0, 0, 0], 0).into())
     }
 }
+
+// === Reset ===
+
+pub trait Reset {
+    /// Configures the connection so that it is reset (i.e. with a TCP RST),
+    /// rather than shut down gracefully, when it is dropped.
+    fn reset_on_close(&self) -> Result<()>;
+}
+
+impl Reset for tokio::net::TcpStream {
+    fn reset_on_close(&self) -> Result<()> {
+        // Closing a socket with a zero linger timeout discards any unsent
+        // data and resets the connection.
+        self.set_linger(Some(Duration::from_secs(0)))
+    }
+}
+
+#[cfg(feature = "tokio-test")]
+impl Reset for tokio_test::io::Mock {
+    fn reset_on_close(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Reset for tokio::io::DuplexStream {
+    fn reset_on_close(&self) -> Result<()> {
+        Ok(())
+    }
+}
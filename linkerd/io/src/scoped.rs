@@ -77,6 +77,13 @@ impl<I: io::PeerAddr> io::PeerAddr for ScopedIo<I> {
     }
 }
 
+impl<I: io::Reset> io::Reset for ScopedIo<I> {
+    #[inline]
+    fn reset_on_close(&self) -> io::Result<()> {
+        self.io.reset_on_close().map_err(self.scope.err())
+    }
+}
+
 impl<I: io::AsyncRead> io::AsyncRead for ScopedIo<I> {
     #[inline]
     fn poll_read(
@@ -1,4 +1,4 @@
-use crate::{IoSlice, PeerAddr, Poll};
+use crate::{IoSlice, Peek, PeerAddr, Poll, Reset};
 use futures::ready;
 use linkerd_errno::Errno;
 use pin_project::pin_project;
@@ -82,3 +82,16 @@ impl<T: PeerAddr, S> PeerAddr for SensorIo<T, S> {
         self.io.peer_addr()
     }
 }
+
+#[async_trait::async_trait]
+impl<T: Peek + Send + Sync, S: Send + Sync> Peek for SensorIo<T, S> {
+    async fn peek(&self, buf: &mut [u8]) -> Result<usize> {
+        self.io.peek(buf).await
+    }
+}
+
+impl<T: Reset, S> Reset for SensorIo<T, S> {
+    fn reset_on_close(&self) -> Result<()> {
+        self.io.reset_on_close()
+    }
+}
@@ -0,0 +1,45 @@
+//! Adaptively sheds load when observed latency indicates that a service is
+//! overloaded.
+//!
+//! Each port's services share a concurrency limit that is adjusted according
+//! to the gradient between the long-term and most recent response latencies:
+//! as latency rises above its long-term average, the limit shrinks; while
+//! latency is stable, the limit grows. Requests that would exceed the limit
+//! fail immediately with a [`Shed`] error rather than being queued.
+//!
+//! Requests are admitted according to their [`Priority`]: low-priority
+//! requests may only use part of the limit, so they are shed first, and
+//! high-priority requests are never shed (though they count against the limit
+//! for other requests).
+
+#![deny(warnings, rust_2018_idioms)]
+#![forbid(unsafe_code)]
+
+mod limit;
+mod metrics;
+mod service;
+
+pub use self::{
+    limit::{Config, Limit},
+    metrics::Metrics,
+    service::{LoadShed, NewLoadShed, ResponseFuture},
+};
+pub use linkerd_server_policy::Priority;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Indicates that a request was rejected because the service is overloaded.
+#[derive(Copy, Clone, Debug, Error)]
+#[error("service overloaded")]
+pub struct Shed {
+    retry_after: Duration,
+}
+
+// === impl Shed ===
+
+impl Shed {
+    /// Returns how long the client should wait before retrying the request.
+    pub fn retry_after(&self) -> Duration {
+        self.retry_after
+    }
+}
@@ -0,0 +1,239 @@
+use linkerd_server_policy::Priority;
+use parking_lot::Mutex;
+use std::{sync::Arc, time::Duration};
+use tokio::time::Instant;
+use tracing::trace;
+
+/// Configures adaptive load shedding.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// The concurrency limit before any latency has been observed.
+    pub initial_limit: usize,
+
+    /// The concurrency limit never shrinks below this value.
+    pub min_limit: usize,
+
+    /// The concurrency limit never grows beyond this value.
+    pub max_limit: usize,
+
+    /// How long shed clients are asked to wait before retrying.
+    pub retry_after: Duration,
+}
+
+/// An adaptive concurrency limit, shared by clones.
+#[derive(Clone, Debug)]
+pub struct Limit(Arc<Mutex<State>>);
+
+/// Tracks an admitted request so that its latency may be sampled.
+///
+/// The request is no longer counted as in-flight once the permit is dropped.
+#[derive(Debug)]
+pub(crate) struct Permit {
+    limit: Limit,
+    started_at: Instant,
+    in_flight: usize,
+}
+
+#[derive(Debug)]
+struct State {
+    limit: f64,
+    min_limit: f64,
+    max_limit: f64,
+    in_flight: usize,
+    // An exponentially-weighted moving average of response latencies, in
+    // seconds.
+    long_rtt: Option<f64>,
+}
+
+/// Latency may rise this much above its long-term average before the limit
+/// shrinks.
+const TOLERANCE: f64 = 2.0;
+
+/// The number of samples over which the long-term latency is averaged.
+const LONG_WINDOW: f64 = 600.0;
+
+/// The weight of each new limit in the smoothed limit.
+const SMOOTHING: f64 = 0.2;
+
+/// The share of the limit that low-priority requests may use, so that they
+/// are shed before other requests.
+const LOW_PRIORITY_SHARE: f64 = 0.75;
+
+// === impl Config ===
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            initial_limit: 100,
+            min_limit: 10,
+            max_limit: 1_000,
+            retry_after: Duration::from_secs(1),
+        }
+    }
+}
+
+// === impl Limit ===
+
+impl Limit {
+    pub fn new(config: &Config) -> Self {
+        let min_limit = config.min_limit.max(1) as f64;
+        let max_limit = (config.max_limit as f64).max(min_limit);
+        Self(Arc::new(Mutex::new(State {
+            limit: (config.initial_limit as f64).clamp(min_limit, max_limit),
+            min_limit,
+            max_limit,
+            in_flight: 0,
+            long_rtt: None,
+        })))
+    }
+
+    /// Returns the current concurrency limit.
+    pub fn current(&self) -> usize {
+        self.0.lock().limit as usize
+    }
+
+    /// Admits a request if fewer than the limit for its priority are in
+    /// flight.
+    pub(crate) fn try_acquire(&self, priority: Priority) -> Option<Permit> {
+        let mut state = self.0.lock();
+        let limit = match priority {
+            Priority::High => f64::INFINITY,
+            Priority::Normal => state.limit.floor(),
+            Priority::Low => (state.limit * LOW_PRIORITY_SHARE).floor().max(1.0),
+        };
+        if state.in_flight as f64 >= limit {
+            return None;
+        }
+        state.in_flight += 1;
+        Some(Permit {
+            limit: self.clone(),
+            started_at: Instant::now(),
+            in_flight: state.in_flight,
+        })
+    }
+}
+
+// === impl Permit ===
+
+impl Permit {
+    /// Records the latency of a completed request.
+    pub(crate) fn complete(self) {
+        let rtt = self.started_at.elapsed();
+        self.limit.0.lock().sample(rtt, self.in_flight);
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.limit.0.lock().in_flight -= 1;
+    }
+}
+
+// === impl State ===
+
+impl State {
+    fn sample(&mut self, rtt: Duration, in_flight: usize) {
+        // Avoid dividing by zero for immediate responses.
+        let rtt = rtt.as_secs_f64().max(1e-6);
+        let long_rtt = match self.long_rtt {
+            None => rtt,
+            Some(long) => long + (rtt - long) * (2.0 / (LONG_WINDOW + 1.0)),
+        };
+        self.long_rtt = Some(long_rtt);
+
+        // Only adjust the limit while it's being approached; otherwise an idle
+        // service's limit would grow without bound.
+        if (in_flight as f64) < self.limit / 2.0 {
+            return;
+        }
+
+        let gradient = (TOLERANCE * long_rtt / rtt).clamp(0.5, 1.0);
+        let queue = self.limit.sqrt();
+        let new_limit = self.limit * gradient + queue;
+        self.limit = (self.limit * (1.0 - SMOOTHING) + new_limit * SMOOTHING)
+            .clamp(self.min_limit, self.max_limit);
+        trace!(limit = self.limit, rtt, long_rtt, "Updated limit");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config {
+            initial_limit: 20,
+            min_limit: 5,
+            max_limit: 100,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn limits_in_flight() {
+        let limit = Limit::new(&config());
+        let permits = (0..20)
+            .map(|_| {
+                limit
+                    .try_acquire(Priority::Normal)
+                    .expect("must be admitted")
+            })
+            .collect::<Vec<_>>();
+        assert!(limit.try_acquire(Priority::Normal).is_none());
+        drop(permits);
+        assert!(limit.try_acquire(Priority::Normal).is_some());
+    }
+
+    #[test]
+    fn sheds_low_priority_first() {
+        let limit = Limit::new(&config());
+        let permits = (0..15)
+            .map(|_| limit.try_acquire(Priority::Low).expect("must be admitted"))
+            .collect::<Vec<_>>();
+        assert!(limit.try_acquire(Priority::Low).is_none());
+
+        let more = (0..5)
+            .map(|_| {
+                limit
+                    .try_acquire(Priority::Normal)
+                    .expect("must be admitted")
+            })
+            .collect::<Vec<_>>();
+        assert!(limit.try_acquire(Priority::Normal).is_none());
+
+        // High-priority requests are admitted beyond the limit.
+        let high = limit.try_acquire(Priority::High).expect("must be admitted");
+        drop((permits, more, high));
+        assert!(limit.try_acquire(Priority::Low).is_some());
+    }
+
+    #[test]
+    fn adapts_to_latency() {
+        let limit = Limit::new(&config());
+        let sample = |rtt_ms: u64| {
+            let mut state = limit.0.lock();
+            let in_flight = state.limit as usize;
+            state.sample(Duration::from_millis(rtt_ms), in_flight);
+        };
+
+        // Stable latency grows the limit.
+        for _ in 0..50 {
+            sample(10);
+        }
+        let grown = limit.current();
+        assert!(grown > 20, "limit must grow: {}", grown);
+
+        // A latency spike well beyond the tolerance shrinks the limit.
+        for _ in 0..20 {
+            sample(200);
+        }
+        let shrunk = limit.current();
+        assert!(shrunk < grown, "limit must shrink: {} >= {}", shrunk, grown);
+
+        // The limit is bounded.
+        for _ in 0..200 {
+            sample(10);
+        }
+        assert_eq!(limit.current(), 100);
+    }
+}
@@ -0,0 +1,63 @@
+use crate::Priority;
+use linkerd_metrics::{Counter, FmtLabels, FmtMetrics, Metric};
+use parking_lot::Mutex;
+use std::{collections::HashMap, fmt, sync::Arc};
+
+/// Counts shed requests by port and priority.
+#[derive(Clone, Debug)]
+pub struct Metrics {
+    metric: Metric<'static, &'static str, Counter>,
+    by_target: Arc<Mutex<HashMap<Labels, Arc<Counter>>>>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct Labels {
+    port: u16,
+    priority: Priority,
+}
+
+// === impl Metrics ===
+
+impl Metrics {
+    pub fn new(metric: Metric<'static, &'static str, Counter>) -> Self {
+        Self {
+            metric,
+            by_target: Default::default(),
+        }
+    }
+
+    pub(crate) fn counter(&self, port: u16, priority: Priority) -> Arc<Counter> {
+        self.by_target
+            .lock()
+            .entry(Labels { port, priority })
+            .or_default()
+            .clone()
+    }
+}
+
+impl FmtMetrics for Metrics {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let by_target = self.by_target.lock();
+        if by_target.is_empty() {
+            return Ok(());
+        }
+
+        self.metric.fmt_help(f)?;
+        for (labels, counter) in by_target.iter() {
+            self.metric.fmt_metric_labeled(f, counter, labels)?;
+        }
+        Ok(())
+    }
+}
+
+// === impl Labels ===
+
+impl FmtLabels for Labels {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "target_port=\"{}\",priority=\"{}\"",
+            self.port, self.priority
+        )
+    }
+}
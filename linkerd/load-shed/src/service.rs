@@ -0,0 +1,233 @@
+use crate::{
+    limit::{Config, Limit, Permit},
+    Metrics, Priority, Shed,
+};
+use futures::{future, TryFutureExt};
+use linkerd_error::Error;
+use linkerd_metrics::Counter;
+use linkerd_stack::{layer, NewService, Param};
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tracing::debug;
+
+/// Builds `LoadShed` services that share a limit for each target port.
+///
+/// Requests are admitted according to the target's priority.
+///
+/// When load shedding is not configured, requests are passed through
+/// unmodified.
+#[derive(Clone, Debug)]
+pub struct NewLoadShed<N> {
+    inner: N,
+    config: Option<Config>,
+    limits: Arc<Mutex<HashMap<u16, Limit>>>,
+    metrics: Metrics,
+}
+
+/// Rejects requests that would exceed the current concurrency limit.
+#[derive(Clone, Debug)]
+pub struct LoadShed<S> {
+    inner: S,
+    shed: Option<Shedder>,
+}
+
+#[derive(Clone, Debug)]
+struct Shedder {
+    limit: Limit,
+    priority: Priority,
+    shed: Shed,
+    shed_total: Arc<Counter>,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    permit: Option<Permit>,
+}
+
+// === impl NewLoadShed ===
+
+impl<N> NewLoadShed<N> {
+    pub fn layer(
+        config: Option<Config>,
+        metrics: Metrics,
+    ) -> impl layer::Layer<N, Service = Self> + Clone {
+        let limits = Arc::new(Mutex::new(HashMap::new()));
+        layer::mk(move |inner| Self {
+            inner,
+            config: config.clone(),
+            limits: limits.clone(),
+            metrics: metrics.clone(),
+        })
+    }
+}
+
+impl<T, N> NewService<T> for NewLoadShed<N>
+where
+    T: Param<u16> + Param<Priority>,
+    N: NewService<T>,
+{
+    type Service = LoadShed<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let shed = self.config.as_ref().map(|config| {
+            let port: u16 = target.param();
+            let priority: Priority = target.param();
+            let limit = self
+                .limits
+                .lock()
+                .entry(port)
+                .or_insert_with(|| Limit::new(config))
+                .clone();
+            Shedder {
+                limit,
+                priority,
+                shed: Shed {
+                    retry_after: config.retry_after,
+                },
+                shed_total: self.metrics.counter(port, priority),
+            }
+        });
+
+        LoadShed {
+            inner: self.inner.new_service(target),
+            shed,
+        }
+    }
+}
+
+// === impl LoadShed ===
+
+impl<S, Req> tower::Service<Req> for LoadShed<S>
+where
+    S: tower::Service<Req>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::Either<
+        ResponseFuture<future::ErrInto<S::Future, Error>>,
+        future::Ready<Result<S::Response, Error>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        let permit = match self.shed.as_ref() {
+            None => None,
+            Some(shedder) => match shedder.limit.try_acquire(shedder.priority) {
+                Some(permit) => Some(permit),
+                None => {
+                    debug!(
+                        limit = shedder.limit.current(),
+                        priority = %shedder.priority,
+                        "Shedding request"
+                    );
+                    shedder.shed_total.incr();
+                    return future::Either::Right(future::err(shedder.shed.into()));
+                }
+            },
+        };
+
+        future::Either::Left(ResponseFuture {
+            inner: self.inner.call(req).err_into::<Error>(),
+            permit,
+        })
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F: Future> Future for ResponseFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let out = futures::ready!(this.inner.poll(cx));
+        if let Some(permit) = this.permit.take() {
+            permit.complete();
+        }
+        Poll::Ready(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_metrics::metrics;
+    use linkerd_stack::layer::Layer;
+    use tower::{Service, ServiceExt};
+
+    metrics! {
+        test_load_shed_total: Counter { "Test shed requests" }
+    }
+
+    #[derive(Clone)]
+    struct Target(u16, Priority);
+
+    impl Param<u16> for Target {
+        fn param(&self) -> u16 {
+            self.0
+        }
+    }
+
+    impl Param<Priority> for Target {
+        fn param(&self) -> Priority {
+            self.1
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn sheds_requests_beyond_limit() {
+        let metrics = Metrics::new(test_load_shed_total);
+        let config = Config {
+            initial_limit: 1,
+            min_limit: 1,
+            ..Config::default()
+        };
+        let mut new_shed = NewLoadShed::layer(Some(config), metrics.clone()).layer(|_: Target| {
+            tower::service_fn(|hang: bool| async move {
+                if hang {
+                    future::pending::<()>().await;
+                }
+                Ok::<_, Error>(())
+            })
+        });
+
+        // Services for the same port share a limit.
+        let mut svc0 = new_shed.new_service(Target(8080, Priority::Normal));
+        let mut svc1 = new_shed.new_service(Target(8080, Priority::Normal));
+        let pending = svc0.ready().await.unwrap().call(true);
+        let err = svc1
+            .ready()
+            .await
+            .unwrap()
+            .call(false)
+            .await
+            .expect_err("request must be shed");
+        let shed = err.downcast_ref::<Shed>().expect("must be shed");
+        assert_eq!(shed.retry_after(), Config::default().retry_after);
+        assert_eq!(u64::from(&*metrics.counter(8080, Priority::Normal)), 1);
+
+        // Other ports have their own limits.
+        let mut svc2 = new_shed.new_service(Target(9090, Priority::Normal));
+        svc2.ready().await.unwrap().call(false).await.unwrap();
+
+        // Once the pending request is dropped, another may be admitted.
+        drop(pending);
+        svc1.ready().await.unwrap().call(false).await.unwrap();
+        assert_eq!(u64::from(&*metrics.counter(8080, Priority::Normal)), 1);
+    }
+}
@@ -23,7 +23,7 @@ pub use self::summary::Summary;
 pub use self::{
     counter::Counter,
     gauge::Gauge,
-    histogram::Histogram,
+    histogram::{Bounds, Bucket, Histogram},
     prom::{FmtLabels, FmtMetric, FmtMetrics, Metric},
     scopes::Scopes,
     serve::Serve,
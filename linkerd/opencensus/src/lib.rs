@@ -2,8 +2,13 @@
 #![forbid(unsafe_code)]
 
 pub mod metrics;
+mod sender;
 
-use futures::stream::{Stream, StreamExt};
+pub use self::sender::{channel, SpanDropped, SpanSender};
+use futures::{
+    future,
+    stream::{Stream, StreamExt},
+};
 use http_body::Body as HttpBody;
 use linkerd_error::Error;
 use metrics::Registry;
@@ -18,8 +23,23 @@ use tokio_stream::wrappers::ReceiverStream;
 use tonic::{self as grpc, body::BoxBody, client::GrpcService};
 use tracing::{debug, trace};
 
-pub async fn export_spans<T, S>(client: T, node: Node, spans: S, metrics: Registry)
-where
+/// Configures how spans are batched before they are exported.
+#[derive(Copy, Clone, Debug)]
+pub struct BatchConfig {
+    /// The maximum number of spans sent in each export request.
+    pub max_size: usize,
+
+    /// The maximum time that a span is held before its batch is sent.
+    pub flush_interval: time::Duration,
+}
+
+pub async fn export_spans<T, S>(
+    client: T,
+    node: Node,
+    spans: S,
+    batch: BatchConfig,
+    metrics: Registry,
+) where
     T: GrpcService<BoxBody> + Clone,
     T::Error: Into<Error>,
     <T::ResponseBody as HttpBody>::Error: Into<Error> + Send + Sync,
@@ -27,7 +47,9 @@ where
     S: Stream<Item = Span> + Unpin,
 {
     debug!("Span exporter running");
-    SpanExporter::new(client, node, spans, metrics).run().await
+    SpanExporter::new(client, node, spans, batch, metrics)
+        .run()
+        .await
 }
 
 /// SpanExporter sends a Stream of spans to the given TraceService gRPC service.
@@ -35,6 +57,7 @@ struct SpanExporter<T, S> {
     client: T,
     node: Node,
     spans: S,
+    batch: BatchConfig,
     metrics: Registry,
 }
 
@@ -51,14 +74,12 @@ where
     T::ResponseBody: Send + Sync + 'static,
     S: Stream<Item = Span> + Unpin,
 {
-    const MAX_BATCH_SIZE: usize = 1000;
-    const MAX_BATCH_IDLE: time::Duration = time::Duration::from_secs(10);
-
-    fn new(client: T, node: Node, spans: S, metrics: Registry) -> Self {
+    fn new(client: T, node: Node, spans: S, batch: BatchConfig, metrics: Registry) -> Self {
         Self {
             client,
             node,
             spans,
+            batch,
             metrics,
         }
     }
@@ -68,11 +89,12 @@ where
             client,
             node,
             mut spans,
+            batch,
             mut metrics,
         } = self;
 
         // Holds the batch of pending spans. Cleared as the spans are flushed.
-        // Contains no more than MAX_BATCH_SIZE spans.
+        // Contains no more than `batch.max_size` spans.
         let mut accum = Vec::new();
 
         let mut svc = TraceServiceClient::new(client);
@@ -91,7 +113,7 @@ where
                     Ok(_rsp) => {
                         // The response future completed. Continue exporting spans until the
                         // stream stops accepting them.
-                        if let Err(SpanRxClosed) = Self::export(&tx, &mut spans, &mut accum, &mut node).await {
+                        if let Err(SpanRxClosed) = Self::export(&tx, &mut spans, &batch, &mut accum, &mut node, &mut metrics).await {
                             // No more spans.
                             return;
                         }
@@ -100,7 +122,7 @@ where
                         debug!(%error, "Response future failed; restarting");
                     }
                 },
-                res = Self::export(&tx, &mut spans, &mut accum, &mut node) => match res {
+                res = Self::export(&tx, &mut spans, &batch, &mut accum, &mut node, &mut metrics) => match res {
                     // The export stream closed; reconnect.
                     Ok(()) => {},
                     // No more spans.
@@ -116,12 +138,14 @@ where
     async fn export(
         tx: &mpsc::Sender<ExportTraceServiceRequest>,
         spans: &mut S,
+        batch: &BatchConfig,
         accum: &mut Vec<Span>,
         node: &mut Option<Node>,
+        metrics: &mut Registry,
     ) -> Result<(), SpanRxClosed> {
         loop {
             // Collect spans into a batch.
-            let collect = Self::collect_batch(spans, accum).await;
+            let collect = Self::collect_batch(spans, batch, accum).await;
 
             // If we collected spans, flush them.
             if !accum.is_empty() {
@@ -139,6 +163,7 @@ where
                             spans = msg.spans.len(),
                             "Sending batch"
                         );
+                        metrics.send(msg.spans.len() as u64);
                         tx.send(msg);
                     }
                     Err(error) => {
@@ -160,35 +185,166 @@ where
 
     /// Collects spans from the proxy into `accum`.
     ///
-    /// Returns an error when the span sream has completed. An error may be
-    /// returned after accumulating spans.
-    async fn collect_batch(spans: &mut S, accum: &mut Vec<Span>) -> Result<(), SpanRxClosed> {
+    /// Returns when the batch is full or when its oldest span has been held for
+    /// the flush interval. Returns an error when the span sream has completed.
+    /// An error may be returned after accumulating spans.
+    async fn collect_batch(
+        spans: &mut S,
+        batch: &BatchConfig,
+        accum: &mut Vec<Span>,
+    ) -> Result<(), SpanRxClosed> {
+        // Spans left over from a lost stream are flushed immediately.
+        let mut flush_at = if accum.is_empty() {
+            None
+        } else {
+            Some(time::Instant::now())
+        };
+
         loop {
-            if accum.len() == Self::MAX_BATCH_SIZE {
-                trace!(capacity = Self::MAX_BATCH_SIZE, "Batch capacity reached");
+            if accum.len() >= batch.max_size {
+                trace!(capacity = batch.max_size, "Batch capacity reached");
                 return Ok(());
             }
 
+            // Don't hold spans indefinitely, even while more spans are
+            // available. The flush timer starts with the first span of a batch.
+            let flush = async move {
+                match flush_at {
+                    Some(deadline) => time::sleep_until(deadline).await,
+                    None => future::pending().await,
+                }
+            };
+
             tokio::select! {
                 biased;
 
+                () = flush => {
+                    trace!(spans = accum.len(), "Flushing spans due to flush interval");
+                    return Ok(());
+                }
+
                 res = spans.next() => match res {
                     Some(span) => {
                         trace!(?span, "Adding to batch");
                         accum.push(span);
+                        if flush_at.is_none() {
+                            flush_at = Some(time::Instant::now() + batch.flush_interval);
+                        }
                     }
                     None => return Err(SpanRxClosed),
                 },
-
-                // Don't hold spans indefinitely. Return if we hit an idle
-                // timeout and spans have been collected.
-                _ = time::sleep(Self::MAX_BATCH_IDLE) => {
-                    if !accum.is_empty() {
-                        trace!(spans = accum.len(), "Flushing spans due to inactivitiy");
-                        return Ok(());
-                    }
-                }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_metrics::FmtMetrics;
+    use std::task::{Context, Poll};
+
+    /// A client that is never called, since these tests drive the export
+    /// stream directly.
+    #[derive(Clone)]
+    struct NoClient;
+
+    type Exporter = SpanExporter<NoClient, ReceiverStream<Span>>;
+
+    pub(crate) fn metric(report: &metrics::Report, name: &str) -> u64 {
+        let text = report.as_display().to_string();
+        let line = text
+            .lines()
+            .find(|l| l.split(' ').next() == Some(name))
+            .unwrap_or_else(|| panic!("missing {} in:\n{}", name, text));
+        line.rsplit(' ').next().unwrap().parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn sends_batches() {
+        let (mut registry, report) = metrics::new();
+        let (spans_tx, spans_rx) = mpsc::channel(10);
+        for _ in 0..5 {
+            spans_tx.try_send(Span::default()).unwrap();
+        }
+        drop(spans_tx);
+
+        let (tx, mut rx) = mpsc::channel(10);
+        let batch = BatchConfig {
+            max_size: 2,
+            flush_interval: time::Duration::from_secs(10),
+        };
+        let res = Exporter::export(
+            &tx,
+            &mut ReceiverStream::new(spans_rx),
+            &batch,
+            &mut Vec::new(),
+            &mut Some(Node::default()),
+            &mut registry,
+        )
+        .await;
+        assert!(matches!(res, Err(SpanRxClosed)));
+        drop(tx);
+
+        let mut batches = Vec::new();
+        while let Some(req) = rx.recv().await {
+            batches.push((req.node.is_some(), req.spans.len()));
+        }
+        // The node is only sent with the first batch and the remaining spans
+        // are flushed when the span stream ends.
+        assert_eq!(batches, vec![(true, 2), (false, 2), (false, 1)]);
+        assert_eq!(metric(&report, "opencensus_span_export_requests"), 3);
+        assert_eq!(metric(&report, "opencensus_span_exports"), 5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn flushes_after_interval() {
+        const FLUSH: time::Duration = time::Duration::from_secs(10);
+        let (mut registry, _report) = metrics::new();
+        let (spans_tx, spans_rx) = mpsc::channel(10);
+        let (tx, mut rx) = mpsc::channel(10);
+        tokio::spawn(async move {
+            let batch = BatchConfig {
+                max_size: 10,
+                flush_interval: FLUSH,
+            };
+            Exporter::export(
+                &tx,
+                &mut ReceiverStream::new(spans_rx),
+                &batch,
+                &mut Vec::new(),
+                &mut None,
+                &mut registry,
+            )
+            .await
+        });
+
+        let start = time::Instant::now();
+        spans_tx.send(Span::default()).await.unwrap();
+        time::sleep(FLUSH / 2).await;
+        spans_tx.send(Span::default()).await.unwrap();
+
+        // The batch is flushed once its first span has been held for the
+        // flush interval, even though it isn't full.
+        let req = rx.recv().await.expect("batch must be flushed");
+        assert_eq!(req.spans.len(), 2);
+        assert!(start.elapsed() >= FLUSH);
+        assert!(start.elapsed() < FLUSH + FLUSH / 2);
+    }
+
+    // === impl NoClient ===
+
+    impl tower::Service<http::Request<BoxBody>> for NoClient {
+        type Response = http::Response<BoxBody>;
+        type Error = Error;
+        type Future = future::Pending<Result<Self::Response, Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Error>> {
+            unreachable!("the client must not be used")
+        }
+
+        fn call(&mut self, _: http::Request<BoxBody>) -> Self::Future {
+            unreachable!("the client must not be used")
+        }
+    }
+}
@@ -5,7 +5,8 @@ use std::sync::Arc;
 metrics! {
     opencensus_span_export_streams: Counter { "Total count of opened span export streams" },
     opencensus_span_export_requests: Counter { "Total count of span export request messages" },
-    opencensus_span_exports: Counter { "Total count of spans exported" }
+    opencensus_span_exports: Counter { "Total count of spans exported" },
+    opencensus_span_drops: Counter { "Total count of spans dropped because the exporter could not keep up" }
 }
 
 #[derive(Debug)]
@@ -13,6 +14,7 @@ struct Metrics {
     streams: Counter,
     requests: Counter,
     spans: Counter,
+    drops: Counter,
 }
 
 #[derive(Clone, Debug)]
@@ -26,6 +28,7 @@ pub fn new() -> (Registry, Report) {
         streams: Counter::default(),
         requests: Counter::default(),
         spans: Counter::default(),
+        drops: Counter::default(),
     };
     let shared = Arc::new(metrics);
     (Registry(shared.clone()), Report(shared))
@@ -40,6 +43,10 @@ impl Registry {
         self.0.requests.incr();
         self.0.spans.add(spans);
     }
+
+    pub fn dropped(&self) {
+        self.0.drops.incr()
+    }
 }
 
 impl FmtMetrics for Report {
@@ -53,6 +60,9 @@ impl FmtMetrics for Report {
         opencensus_span_exports.fmt_help(f)?;
         opencensus_span_exports.fmt_metric(f, &self.0.spans)?;
 
+        opencensus_span_drops.fmt_help(f)?;
+        opencensus_span_drops.fmt_metric(f, &self.0.drops)?;
+
         Ok(())
     }
 }
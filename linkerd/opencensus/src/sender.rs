@@ -0,0 +1,146 @@
+use crate::metrics::Registry;
+use opencensus_proto::trace::v1::Span;
+use std::{fmt, time::Duration};
+use tokio::{sync::mpsc, time};
+use tracing::debug;
+
+/// Sends spans to the exporter.
+///
+/// Spans are buffered until the exporter is ready to send them. When the buffer
+/// is full, spans are dropped, unless a send timeout is configured: then,
+/// `send` waits for up to the timeout for the buffer to have capacity, applying
+/// backpressure to the caller. This suits low-volume traces that must not be
+/// lost to a burst of spans.
+///
+/// Dropped spans are recorded in the exporter's metrics.
+#[derive(Clone, Debug)]
+pub struct SpanSender {
+    tx: mpsc::Sender<Span>,
+    send_timeout: Option<Duration>,
+    metrics: Registry,
+}
+
+#[derive(Debug)]
+pub struct SpanDropped(());
+
+/// Creates a sender with a buffer of `capacity` spans, and the receiver that
+/// the exporter reads spans from.
+pub fn channel(
+    capacity: usize,
+    send_timeout: Option<Duration>,
+    metrics: Registry,
+) -> (SpanSender, mpsc::Receiver<Span>) {
+    let (tx, rx) = mpsc::channel(capacity);
+    let sender = SpanSender {
+        tx,
+        send_timeout,
+        metrics,
+    };
+    (sender, rx)
+}
+
+// === impl SpanSender ===
+
+impl SpanSender {
+    /// Sends a span without waiting, dropping it if the buffer is full.
+    pub fn try_send(&self, span: Span) -> Result<(), SpanDropped> {
+        self.tx.try_send(span).map_err(|_| self.dropped())
+    }
+
+    /// Sends a span, waiting for the buffer to have capacity if a send timeout
+    /// is configured.
+    pub async fn send(&self, span: Span) -> Result<(), SpanDropped> {
+        let timeout = match self.send_timeout {
+            Some(timeout) => timeout,
+            None => return self.try_send(span),
+        };
+
+        match time::timeout(timeout, self.tx.send(span)).await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(_)) => Err(self.dropped()),
+            Err(_) => {
+                debug!(?timeout, "Span buffer did not have capacity");
+                Err(self.dropped())
+            }
+        }
+    }
+
+    fn dropped(&self) -> SpanDropped {
+        self.metrics.dropped();
+        SpanDropped(())
+    }
+}
+
+// === impl SpanDropped ===
+
+impl fmt::Display for SpanDropped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "span buffer is full or closed")
+    }
+}
+
+impl std::error::Error for SpanDropped {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{metrics, tests::metric};
+
+    #[tokio::test]
+    async fn drops_when_full() {
+        let (registry, report) = metrics::new();
+        let (tx, _rx) = channel(1, None, registry);
+
+        tx.try_send(Span::default()).expect("buffer has capacity");
+        tx.try_send(Span::default()).expect_err("buffer is full");
+        tx.send(Span::default()).await.expect_err("buffer is full");
+        assert_eq!(metric(&report, "opencensus_span_drops"), 2);
+    }
+
+    #[tokio::test]
+    async fn drops_when_closed() {
+        let (registry, report) = metrics::new();
+        let (tx, rx) = channel(1, Some(Duration::from_secs(1)), registry);
+        drop(rx);
+
+        tx.try_send(Span::default())
+            .expect_err("receiver is closed");
+        tx.send(Span::default())
+            .await
+            .expect_err("receiver is closed");
+        assert_eq!(metric(&report, "opencensus_span_drops"), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_capacity() {
+        const TIMEOUT: Duration = Duration::from_secs(10);
+        let (registry, report) = metrics::new();
+        let (tx, mut rx) = channel(1, Some(TIMEOUT), registry);
+        tx.try_send(Span::default()).expect("buffer has capacity");
+
+        tokio::spawn(async move {
+            time::sleep(TIMEOUT / 2).await;
+            while rx.recv().await.is_some() {}
+        });
+        let start = time::Instant::now();
+        tx.send(Span::default())
+            .await
+            .expect("buffer must have capacity before the timeout");
+        assert!(start.elapsed() >= TIMEOUT / 2);
+        assert!(start.elapsed() < TIMEOUT);
+        assert_eq!(metric(&report, "opencensus_span_drops"), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn drops_after_timeout() {
+        const TIMEOUT: Duration = Duration::from_secs(10);
+        let (registry, report) = metrics::new();
+        let (tx, _rx) = channel(1, Some(TIMEOUT), registry);
+        tx.try_send(Span::default()).expect("buffer has capacity");
+
+        let start = time::Instant::now();
+        tx.send(Span::default()).await.expect_err("buffer is full");
+        assert!(start.elapsed() >= TIMEOUT);
+        assert_eq!(metric(&report, "opencensus_span_drops"), 1);
+    }
+}
@@ -0,0 +1,272 @@
+//! Merges the addresses of dual-stack endpoints.
+//!
+//! The destination service resolves a dual-stack pod as two endpoints: one
+//! with an IPv6 address and one with an IPv4 address. When both addresses of
+//! a pod are resolved, they are merged into a single IPv6 endpoint with the
+//! IPv4 address as its fallback, so that the endpoint's connections may race
+//! both addresses rather than balancing over each independently.
+
+use crate::{core::resolve::Update, metadata::Metadata};
+use futures::{prelude::*, ready};
+use pin_project::pin_project;
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// The endpoint label that identifies the pod an address belongs to.
+const POD_LABEL: &str = "pod";
+
+#[derive(Clone, Debug)]
+pub struct DualStack<R> {
+    inner: R,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct Resolution<S> {
+    #[pin]
+    inner: S,
+    endpoints: Endpoints,
+    pending: VecDeque<Update<Metadata>>,
+}
+
+/// Tracks the addresses resolved by the destination service and the merged
+/// endpoints that were published from them.
+#[derive(Debug, Default)]
+struct Endpoints {
+    resolved: HashMap<SocketAddr, Metadata>,
+    published: HashMap<SocketAddr, Metadata>,
+}
+
+// === impl DualStack ===
+
+impl<R> DualStack<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner }
+    }
+
+    pub fn layer() -> impl linkerd_stack::layer::Layer<R, Service = Self> + Clone {
+        linkerd_stack::layer::mk(Self::new)
+    }
+}
+
+impl<T, R, S> tower::Service<T> for DualStack<R>
+where
+    R: tower::Service<T, Response = S>,
+    S: TryStream<Ok = Update<Metadata>>,
+{
+    type Response = Resolution<S>;
+    type Error = R::Error;
+    type Future = future::MapOk<R::Future, fn(S) -> Resolution<S>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&mut self, target: T) -> Self::Future {
+        self.inner
+            .call(target)
+            .map_ok(Resolution::new as fn(S) -> Resolution<S>)
+    }
+}
+
+// === impl Resolution ===
+
+impl<S> Resolution<S> {
+    fn new(inner: S) -> Self {
+        Self {
+            inner,
+            endpoints: Endpoints::default(),
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+impl<S> Stream for Resolution<S>
+where
+    S: TryStream<Ok = Update<Metadata>>,
+{
+    type Item = Result<Update<Metadata>, S::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            if let Some(update) = this.pending.pop_front() {
+                return Poll::Ready(Some(Ok(update)));
+            }
+
+            match ready!(this.inner.as_mut().try_poll_next(cx)) {
+                Some(Ok(update)) => this.pending.extend(this.endpoints.update(update)),
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+// === impl Endpoints ===
+
+impl Endpoints {
+    /// Applies an update from the destination service, returning the updates
+    /// to publish.
+    fn update(&mut self, update: Update<Metadata>) -> Vec<Update<Metadata>> {
+        match update {
+            Update::Reset(eps) => {
+                self.resolved = eps.into_iter().collect();
+                self.published = self.merge();
+                let eps = self
+                    .published
+                    .iter()
+                    .map(|(a, m)| (*a, m.clone()))
+                    .collect();
+                vec![Update::Reset(eps)]
+            }
+            Update::Add(eps) => {
+                self.resolved.extend(eps);
+                self.publish()
+            }
+            Update::Remove(addrs) => {
+                for addr in addrs.iter() {
+                    self.resolved.remove(addr);
+                }
+                self.publish()
+            }
+            Update::DoesNotExist => {
+                self.resolved.clear();
+                self.published.clear();
+                vec![Update::DoesNotExist]
+            }
+        }
+    }
+
+    /// Publishes the difference between the previously published endpoints
+    /// and the current merged endpoints.
+    fn publish(&mut self) -> Vec<Update<Metadata>> {
+        let merged = self.merge();
+
+        let removed = self
+            .published
+            .keys()
+            .filter(|a| !merged.contains_key(a))
+            .copied()
+            .collect::<Vec<_>>();
+        let added = merged
+            .iter()
+            .filter(|(a, m)| self.published.get(a) != Some(m))
+            .map(|(a, m)| (*a, m.clone()))
+            .collect::<Vec<_>>();
+        self.published = merged;
+
+        let mut updates = Vec::with_capacity(2);
+        if !removed.is_empty() {
+            updates.push(Update::Remove(removed));
+        }
+        if !added.is_empty() {
+            updates.push(Update::Add(added));
+        }
+        updates
+    }
+
+    /// Merges each pod's addresses when the pod has exactly one IPv6 and one
+    /// IPv4 address on a port. All other addresses are left as-is.
+    fn merge(&self) -> HashMap<SocketAddr, Metadata> {
+        let mut pods = HashMap::<(&str, u16), (Vec<SocketAddr>, Vec<SocketAddr>)>::new();
+        for (addr, meta) in self.resolved.iter() {
+            if let Some(pod) = meta.labels().get(POD_LABEL) {
+                let (v6, v4) = pods.entry((pod.as_str(), addr.port())).or_default();
+                if addr.is_ipv6() {
+                    v6.push(*addr);
+                } else {
+                    v4.push(*addr);
+                }
+            }
+        }
+
+        let mut merged = self.resolved.clone();
+        for (v6, v4) in pods.values() {
+            if let ([v6], [v4]) = (v6.as_slice(), v4.as_slice()) {
+                merged.remove(v4);
+                if let Some(meta) = merged.remove(v6) {
+                    merged.insert(*v6, meta.with_fallback_addr(*v4));
+                }
+            }
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProtocolHint;
+
+    fn meta(pod: &str) -> Metadata {
+        Metadata::new(
+            Some(("pod".to_string(), pod.to_string())),
+            ProtocolHint::Unknown,
+            None,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn merges_dual_stack_pods() {
+        let v6 = SocketAddr::new("fd00::1".parse().unwrap(), 8080);
+        let v4 = SocketAddr::new([10, 0, 0, 1].into(), 8080);
+
+        let mut eps = Endpoints::default();
+        assert_eq!(
+            eps.update(Update::Add(vec![(v4, meta("a"))])),
+            vec![Update::Add(vec![(v4, meta("a"))])],
+        );
+
+        // When the pod's IPv6 address is resolved, the IPv4 endpoint is
+        // replaced by a merged IPv6 endpoint.
+        assert_eq!(
+            eps.update(Update::Add(vec![(v6, meta("a"))])),
+            vec![
+                Update::Remove(vec![v4]),
+                Update::Add(vec![(v6, meta("a").with_fallback_addr(v4))]),
+            ],
+        );
+
+        // Addresses of other pods are not merged.
+        let other = SocketAddr::new("fd00::2".parse().unwrap(), 8080);
+        assert_eq!(
+            eps.update(Update::Add(vec![(other, meta("b"))])),
+            vec![Update::Add(vec![(other, meta("b"))])],
+        );
+
+        // When the pod's IPv6 address is removed, its IPv4 endpoint is
+        // restored.
+        assert_eq!(
+            eps.update(Update::Remove(vec![v6])),
+            vec![Update::Remove(vec![v6]), Update::Add(vec![(v4, meta("a"))])],
+        );
+    }
+
+    #[test]
+    fn does_not_merge_without_pod() {
+        let v6 = SocketAddr::new("fd00::1".parse().unwrap(), 8080);
+        let v4 = SocketAddr::new([10, 0, 0, 1].into(), 8080);
+
+        let mut eps = Endpoints::default();
+        let updates = eps.update(Update::Reset(vec![
+            (v6, Metadata::default()),
+            (v4, Metadata::default()),
+        ]));
+        match updates.as_slice() {
+            [Update::Reset(eps)] => {
+                assert_eq!(eps.len(), 2);
+                assert!(eps.iter().all(|(_, m)| m.fallback_addr().is_none()));
+            }
+            updates => panic!("unexpected updates: {:?}", updates),
+        }
+    }
+}
@@ -6,16 +6,18 @@ use linkerd2_proxy_api as api;
 use linkerd_addr::NameAddr;
 use linkerd_proxy_core as core;
 
+mod dual_stack;
 mod metadata;
 pub mod pb;
 mod resolve;
 
-pub use self::metadata::{Metadata, ProtocolHint};
+pub use self::dual_stack::DualStack;
+pub use self::metadata::{Labels, Metadata, ProtocolHint};
 pub use self::resolve::Resolve;
 
 // TODO this should hold a `NameAddr`; but this currently isn't possible due to
 // outbound target types.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ConcreteAddr(pub NameAddr);
 
 impl std::fmt::Display for ConcreteAddr {
@@ -1,6 +1,6 @@
 use http::uri::Authority;
 use linkerd_tls::client::ServerId;
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, net::SocketAddr};
 
 /// Endpoint labels are lexographically ordered by key.
 pub type Labels = BTreeMap<String, String>;
@@ -22,6 +22,10 @@ pub struct Metadata {
 
     /// Used to override the the authority if needed
     authority_override: Option<Authority>,
+
+    /// An address of the other IP family for the same endpoint, used if
+    /// connecting to the endpoint's primary address is slow or fails.
+    fallback_addr: Option<SocketAddr>,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -43,6 +47,7 @@ impl Default for Metadata {
             authority_override: None,
             opaque_transport_port: None,
             protocol_hint: ProtocolHint::Unknown,
+            fallback_addr: None,
         }
     }
 }
@@ -61,6 +66,14 @@ impl Metadata {
             opaque_transport_port,
             identity,
             authority_override,
+            fallback_addr: None,
+        }
+    }
+
+    pub fn with_fallback_addr(self, addr: SocketAddr) -> Self {
+        Self {
+            fallback_addr: Some(addr),
+            ..self
         }
     }
 
@@ -84,4 +97,8 @@ impl Metadata {
     pub fn authority_override(&self) -> Option<&Authority> {
         self.authority_override.as_ref()
     }
+
+    pub fn fallback_addr(&self) -> Option<SocketAddr> {
+        self.fallback_addr
+    }
 }
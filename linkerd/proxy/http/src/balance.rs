@@ -1,3 +1,5 @@
+pub mod affinity;
+
 use crate::Error;
 use hyper::body::HttpBody;
 pub use hyper_balance::{PendingUntilFirstData, PendingUntilFirstDataBody};
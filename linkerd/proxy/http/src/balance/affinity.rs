@@ -0,0 +1,389 @@
+//! Balances requests with cookie-based session affinity.
+//!
+//! Requests that carry an affinity cookie naming an available endpoint are
+//! dispatched to that endpoint. All other requests are balanced with P2C and
+//! their responses set a cookie naming the selected endpoint, so that a
+//! client's subsequent requests stick to it. When a cookie's endpoint is no
+//! longer available, the request is balanced and the cookie is replaced.
+
+use super::{PeakEwmaDiscover, PendingUntilFirstData};
+use crate::Error;
+use futures::{prelude::*, ready};
+use http::header::{HeaderValue, COOKIE, SET_COOKIE};
+use hyper::body::HttpBody;
+use pin_project::pin_project;
+use rand::thread_rng;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tower::{
+    discover::{Change, Discover},
+    load::Load,
+    ready_cache::ReadyCache,
+};
+use tracing::{debug, trace};
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// The name of the cookie that identifies a client's endpoint.
+    pub cookie_name: String,
+
+    /// How long a client sticks to an endpoint after its cookie is set.
+    pub ttl: Duration,
+}
+
+/// Configures a stack to balance requests over discovered endpoints with
+/// session affinity.
+#[derive(Debug)]
+pub struct Layer<A, B> {
+    config: Config,
+    decay: Duration,
+    default_rtt: Duration,
+    _marker: PhantomData<fn(A) -> B>,
+}
+
+pub struct Balance<D, A>
+where
+    D: Discover,
+    D::Key: Hash,
+{
+    config: Config,
+    discover: D,
+    services: ReadyCache<D::Key, D::Service, http::Request<A>>,
+    /// Maps the hashes that are set in cookies to the endpoints they identify.
+    keys: HashMap<u64, D::Key>,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    set_cookie: Option<HeaderValue>,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("endpoint discovery ended")]
+pub struct DiscoveryEnded(());
+
+// === impl Layer ===
+
+pub fn layer<A, B>(default_rtt: Duration, decay: Duration, config: Config) -> Layer<A, B> {
+    Layer {
+        config,
+        decay,
+        default_rtt,
+        _marker: PhantomData,
+    }
+}
+
+impl<A, B> Clone for Layer<A, B> {
+    fn clone(&self) -> Self {
+        Self {
+            config: self.config.clone(),
+            decay: self.decay,
+            default_rtt: self.default_rtt,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<D, S, A, B> tower::layer::Layer<D> for Layer<A, B>
+where
+    A: HttpBody,
+    B: HttpBody,
+    D: Discover<Service = S>,
+    D::Key: Hash + Eq,
+    S: tower::Service<http::Request<A>, Response = http::Response<B>>,
+    S::Error: Into<Error>,
+    Balance<PeakEwmaDiscover<D, PendingUntilFirstData>, A>: tower::Service<http::Request<A>>,
+{
+    type Service = Balance<PeakEwmaDiscover<D, PendingUntilFirstData>, A>;
+
+    fn layer(&self, discover: D) -> Self::Service {
+        let instrument = PendingUntilFirstData::default();
+        let loaded = PeakEwmaDiscover::new(discover, self.default_rtt, self.decay, instrument);
+        Balance::new(loaded, self.config.clone())
+    }
+}
+
+// === impl Balance ===
+
+impl<D, A> Balance<D, A>
+where
+    D: Discover,
+    D::Key: Hash + Eq,
+    D::Service: tower::Service<http::Request<A>>,
+{
+    pub fn new(discover: D, config: Config) -> Self {
+        Self {
+            config,
+            discover,
+            services: ReadyCache::default(),
+            keys: HashMap::new(),
+        }
+    }
+}
+
+impl<D, A, B> tower::Service<http::Request<A>> for Balance<D, A>
+where
+    D: Discover + Unpin,
+    D::Key: Hash + Eq + Clone,
+    D::Error: Into<Error>,
+    D::Service: tower::Service<http::Request<A>, Response = http::Response<B>> + Load,
+    <D::Service as tower::Service<http::Request<A>>>::Error: Into<Error>,
+    <D::Service as Load>::Metric: PartialOrd + std::fmt::Debug,
+{
+    type Response = http::Response<B>;
+    type Error = Error;
+    type Future = ResponseFuture<<D::Service as tower::Service<http::Request<A>>>::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.update_from_discover(cx)?;
+        self.promote_pending_to_ready(cx);
+        if self.services.ready_len() == 0 {
+            trace!(pending = self.services.pending_len(), "No ready endpoints");
+            return Poll::Pending;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<A>) -> Self::Future {
+        let key = cookie_hash(&req, &self.config.cookie_name)
+            .and_then(|hash| self.keys.get(&hash))
+            .cloned();
+        let (index, set_cookie) = match key {
+            Some(key) => match self.services.get_ready(&key) {
+                Some((index, _, _)) => (index, None),
+                // The endpoint is busy, so the request is balanced. The
+                // cookie is retained so that later requests return to it.
+                None if self.services.pending_contains(&key) => (self.p2c_ready_index(), None),
+                None => self.balance_with_cookie(),
+            },
+            None => self.balance_with_cookie(),
+        };
+        ResponseFuture {
+            inner: self.services.call_ready_index(index, req),
+            set_cookie,
+        }
+    }
+}
+
+impl<D, A, B> Balance<D, A>
+where
+    D: Discover + Unpin,
+    D::Key: Hash + Eq + Clone,
+    D::Error: Into<Error>,
+    D::Service: tower::Service<http::Request<A>, Response = http::Response<B>> + Load,
+    <D::Service as tower::Service<http::Request<A>>>::Error: Into<Error>,
+    <D::Service as Load>::Metric: PartialOrd + std::fmt::Debug,
+{
+    fn update_from_discover(&mut self, cx: &mut Context<'_>) -> Result<(), Error> {
+        loop {
+            match Pin::new(&mut self.discover).poll_discover(cx) {
+                Poll::Pending => return Ok(()),
+                Poll::Ready(None) => return Err(DiscoveryEnded(()).into()),
+                Poll::Ready(Some(Err(e))) => return Err(e.into()),
+                Poll::Ready(Some(Ok(Change::Remove(key)))) => {
+                    trace!("remove");
+                    self.keys.remove(&key_hash(&key));
+                    self.services.evict(&key);
+                }
+                Poll::Ready(Some(Ok(Change::Insert(key, svc)))) => {
+                    trace!("insert");
+                    self.keys.insert(key_hash(&key), key.clone());
+                    self.services.push(key, svc);
+                }
+            }
+        }
+    }
+
+    fn promote_pending_to_ready(&mut self, cx: &mut Context<'_>) {
+        loop {
+            match self.services.poll_pending(cx) {
+                Poll::Ready(Ok(())) | Poll::Pending => return,
+                Poll::Ready(Err(error)) => {
+                    // An individual service was lost; continue processing
+                    // pending services.
+                    debug!(%error, "Dropping failed endpoint");
+                }
+            }
+        }
+    }
+
+    /// Balances a request and returns the cookie that identifies the selected
+    /// endpoint.
+    fn balance_with_cookie(&mut self) -> (usize, Option<HeaderValue>) {
+        let index = self.p2c_ready_index();
+        let (key, _) = self
+            .services
+            .get_ready_index(index)
+            .expect("index must be ready");
+        let cookie = format!(
+            "{}={:016x}; Max-Age={}; Path=/; HttpOnly",
+            self.config.cookie_name,
+            key_hash(key),
+            self.config.ttl.as_secs(),
+        );
+        (index, HeaderValue::from_str(&cookie).ok())
+    }
+
+    /// Chooses the less loaded of two random ready endpoints.
+    fn p2c_ready_index(&self) -> usize {
+        match self.services.ready_len() {
+            0 => unreachable!("called before ready"),
+            1 => 0,
+            len => {
+                let idxs = rand::seq::index::sample(&mut thread_rng(), len, 2);
+                let (a, b) = (idxs.index(0), idxs.index(1));
+                let load = |i| {
+                    let (_, svc) = self.services.get_ready_index(i).expect("invalid index");
+                    svc.load()
+                };
+                let (aload, bload) = (load(a), load(b));
+                trace!(a.index = a, a.load = ?aload, b.index = b, b.load = ?bload, "p2c");
+                if aload <= bload {
+                    a
+                } else {
+                    b
+                }
+            }
+        }
+    }
+}
+
+/// Returns the endpoint hash set in the request's affinity cookie, if any.
+fn cookie_hash<A>(req: &http::Request<A>, name: &str) -> Option<u64> {
+    req.headers()
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|c| {
+            let (n, v) = c.trim().split_once('=')?;
+            if n != name {
+                return None;
+            }
+            u64::from_str_radix(v, 16).ok()
+        })
+        .next()
+}
+
+fn key_hash<K: Hash>(key: &K) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+// === impl ResponseFuture ===
+
+impl<F, B, E> Future for ResponseFuture<F>
+where
+    F: TryFuture<Ok = http::Response<B>, Error = E>,
+    E: Into<Error>,
+{
+    type Output = Result<http::Response<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut rsp = ready!(this.inner.try_poll(cx)).map_err(Into::into)?;
+        if let Some(cookie) = this.set_cookie.take() {
+            rsp.headers_mut().append(SET_COOKIE, cookie);
+        }
+        Poll::Ready(Ok(rsp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cookie_hash() {
+        let req = http::Request::builder()
+            .header(COOKIE, "a=xyz; l5d-affinity=00000000000000ff; c=d")
+            .body(())
+            .unwrap();
+        assert_eq!(cookie_hash(&req, "l5d-affinity"), Some(0xff));
+        assert_eq!(cookie_hash(&req, "a"), None);
+        assert_eq!(cookie_hash(&req, "other"), None);
+
+        let req = http::Request::builder()
+            .header(COOKIE, "a=b")
+            .header(COOKIE, "l5d-affinity=2a")
+            .body(())
+            .unwrap();
+        assert_eq!(cookie_hash(&req, "l5d-affinity"), Some(0x2a));
+    }
+
+    #[tokio::test]
+    async fn sticks_to_cookie_endpoint() {
+        use std::convert::Infallible;
+        use tower::{load::Constant, service_fn, Service, ServiceExt};
+
+        let endpoint = |id: u32| {
+            Constant::new(
+                service_fn(move |_: http::Request<()>| {
+                    future::ok::<_, Infallible>(http::Response::new(id))
+                }),
+                0,
+            )
+        };
+        let discover = stream::iter(vec![
+            Ok::<_, Infallible>(Change::Insert(1u32, endpoint(1))),
+            Ok(Change::Insert(2, endpoint(2))),
+        ])
+        .chain(stream::pending());
+        let mut balance = Balance::new(
+            discover,
+            Config {
+                cookie_name: "l5d-affinity".to_string(),
+                ttl: Duration::from_secs(60),
+            },
+        );
+
+        // Requests without a cookie are balanced and set a cookie.
+        let rsp = balance
+            .ready()
+            .await
+            .unwrap()
+            .call(http::Request::new(()))
+            .await
+            .unwrap();
+        let id = *rsp.body();
+        let cookie = rsp.headers().get(SET_COOKIE).expect("must set a cookie");
+        let cookie = cookie
+            .to_str()
+            .unwrap()
+            .split(';')
+            .next()
+            .unwrap()
+            .to_string();
+        assert_eq!(cookie, format!("l5d-affinity={:016x}", key_hash(&id)));
+
+        // Requests with the cookie are sent to the same endpoint.
+        for _ in 0..10 {
+            let req = http::Request::builder()
+                .header(COOKIE, &cookie)
+                .body(())
+                .unwrap();
+            let rsp = balance.ready().await.unwrap().call(req).await.unwrap();
+            assert_eq!(*rsp.body(), id);
+            assert!(rsp.headers().get(SET_COOKIE).is_none());
+        }
+
+        // Requests whose cookie names an unknown endpoint are balanced and
+        // set a new cookie.
+        let req = http::Request::builder()
+            .header(COOKIE, "l5d-affinity=0")
+            .body(())
+            .unwrap();
+        let rsp = balance.ready().await.unwrap().call(req).await.unwrap();
+        assert!(rsp.headers().get(SET_COOKIE).is_some());
+    }
+}
@@ -72,7 +72,7 @@ where
     C::Future: Unpin + Send + 'static,
     C::Error: Into<Error>,
     C::Connection: Unpin + Send + 'static,
-    B: hyper::body::HttpBody + Send + 'static,
+    B: hyper::body::HttpBody + Send + Unpin + 'static,
     B::Data: Send,
     B::Error: Into<Error> + Send + Sync,
 {
@@ -0,0 +1,355 @@
+//! Propagates request deadlines across hops.
+//!
+//! gRPC clients set a `grpc-timeout` header describing how long they will
+//! wait for a response, and plain HTTP clients may set a configurable header
+//! (`l5d-deadline` by default) with the number of milliseconds remaining.
+//! `Enforce` fails requests that are not answered within the remaining time
+//! with a `DeadlineExceeded` error and records the deadline as a request
+//! extension. `Propagate` rewrites these headers with the time that remains
+//! when the request is dispatched, so that each hop sees the time spent by
+//! prior hops deducted from its deadline.
+
+use futures::ready;
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use linkerd_error::Error;
+use linkerd_stack::layer;
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use thiserror::Error;
+use tokio::time::{self, Instant};
+use tracing::{debug, trace};
+
+pub const GRPC_TIMEOUT: &str = "grpc-timeout";
+pub const L5D_DEADLINE: &str = "l5d-deadline";
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// The header that carries the milliseconds remaining before a plain HTTP
+    /// request's deadline.
+    pub header: HeaderName,
+}
+
+/// The instant by which a request must be answered.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Deadline(pub Instant);
+
+/// An error indicating that a request was not answered before its deadline.
+#[derive(Debug, Error)]
+#[error("request deadline of {:?} exceeded", self.0)]
+pub struct DeadlineExceeded(Duration);
+
+/// Fails requests that are not answered before the deadline set by their
+/// headers.
+#[derive(Clone, Debug)]
+pub struct Enforce<S> {
+    config: Option<Config>,
+    inner: S,
+}
+
+/// Rewrites requests' deadline headers with the time that remains before
+/// their `Deadline`.
+#[derive(Clone, Debug)]
+pub struct Propagate<S> {
+    config: Option<Config>,
+    inner: S,
+}
+
+#[pin_project(project = EnforceFutureProj)]
+#[derive(Debug)]
+pub enum EnforceFuture<F> {
+    Passthru(#[pin] F),
+    Deadline(#[pin] time::Timeout<F>, Duration),
+}
+
+// === impl Config ===
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            header: HeaderName::from_static(L5D_DEADLINE),
+        }
+    }
+}
+
+impl Config {
+    /// Returns the time remaining for a request, as described by its headers.
+    ///
+    /// When both headers are set, the shorter timeout is used. Invalid
+    /// values are ignored.
+    fn timeout(&self, headers: &HeaderMap) -> Option<Duration> {
+        let grpc = headers
+            .get(GRPC_TIMEOUT)
+            .and_then(|v| parse_grpc_timeout(v.to_str().ok()?));
+        let http = headers
+            .get(&self.header)
+            .and_then(|v| v.to_str().ok()?.trim().parse().ok())
+            .map(Duration::from_millis);
+        match (grpc, http) {
+            (Some(g), Some(h)) => Some(g.min(h)),
+            (g, h) => g.or(h),
+        }
+    }
+
+    fn set_remaining(&self, headers: &mut HeaderMap, remaining: Duration) {
+        if headers.contains_key(GRPC_TIMEOUT) {
+            headers.insert(GRPC_TIMEOUT, encode_grpc_timeout(remaining));
+        }
+        if headers.contains_key(&self.header) {
+            headers.insert(
+                self.header.clone(),
+                HeaderValue::from(remaining.as_millis() as u64),
+            );
+        }
+    }
+}
+
+// === impl Enforce ===
+
+impl<S> Enforce<S> {
+    pub fn layer(config: Option<Config>) -> impl layer::Layer<S, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            config: config.clone(),
+            inner,
+        })
+    }
+}
+
+impl<S, B> tower::Service<http::Request<B>> for Enforce<S>
+where
+    S: tower::Service<http::Request<B>>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = EnforceFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        let timeout = match self.config.as_ref().and_then(|c| c.timeout(req.headers())) {
+            Some(timeout) => timeout,
+            None => return EnforceFuture::Passthru(self.inner.call(req)),
+        };
+
+        trace!(?timeout, "Enforcing deadline");
+        let deadline = Instant::now() + timeout;
+        req.extensions_mut().insert(Deadline(deadline));
+        EnforceFuture::Deadline(time::timeout_at(deadline, self.inner.call(req)), timeout)
+    }
+}
+
+impl<F, T, E> Future for EnforceFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<Error>,
+{
+    type Output = Result<T, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            EnforceFutureProj::Passthru(f) => f.poll(cx).map_err(Into::into),
+            EnforceFutureProj::Deadline(f, timeout) => {
+                let rsp = ready!(f.poll(cx)).map_err(|_| {
+                    debug!(timeout = ?*timeout, "Deadline exceeded");
+                    DeadlineExceeded(*timeout)
+                })?;
+                Poll::Ready(rsp.map_err(Into::into))
+            }
+        }
+    }
+}
+
+// === impl Propagate ===
+
+impl<S> Propagate<S> {
+    pub fn layer(config: Option<Config>) -> impl layer::Layer<S, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            config: config.clone(),
+            inner,
+        })
+    }
+}
+
+impl<S, B> tower::Service<http::Request<B>> for Propagate<S>
+where
+    S: tower::Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        if let Some(config) = self.config.as_ref() {
+            if let Some(Deadline(deadline)) = req.extensions().get::<Deadline>().copied() {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                trace!(?remaining, "Propagating deadline");
+                config.set_remaining(req.headers_mut(), remaining);
+            }
+        }
+        self.inner.call(req)
+    }
+}
+
+// === impl DeadlineExceeded ===
+
+impl DeadlineExceeded {
+    /// Get the request's timeout when it was received.
+    pub fn timeout(&self) -> Duration {
+        self.0
+    }
+}
+
+/// Parses a `grpc-timeout` value: at most 8 digits followed by a unit.
+fn parse_grpc_timeout(s: &str) -> Option<Duration> {
+    if s.len() < 2 || s.len() > 9 {
+        return None;
+    }
+    let (value, unit) = s.split_at(s.len() - 1);
+    if !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let value = value.parse::<u64>().ok()?;
+    match unit {
+        "H" => Some(Duration::from_secs(value * 60 * 60)),
+        "M" => Some(Duration::from_secs(value * 60)),
+        "S" => Some(Duration::from_secs(value)),
+        "m" => Some(Duration::from_millis(value)),
+        "u" => Some(Duration::from_micros(value)),
+        "n" => Some(Duration::from_nanos(value)),
+        _ => None,
+    }
+}
+
+/// Encodes a `grpc-timeout` value using the most precise unit that fits in 8
+/// digits.
+fn encode_grpc_timeout(timeout: Duration) -> HeaderValue {
+    const MAX: u128 = 99_999_999;
+    let nanos = timeout.as_nanos();
+    let (value, unit) = if nanos <= MAX {
+        (nanos, "n")
+    } else if timeout.as_micros() <= MAX {
+        (timeout.as_micros(), "u")
+    } else if timeout.as_millis() <= MAX {
+        (timeout.as_millis(), "m")
+    } else if u128::from(timeout.as_secs()) <= MAX {
+        (u128::from(timeout.as_secs()), "S")
+    } else if u128::from(timeout.as_secs() / 60) <= MAX {
+        (u128::from(timeout.as_secs() / 60), "M")
+    } else {
+        (u128::from(timeout.as_secs() / 60 / 60).min(MAX), "H")
+    };
+    HeaderValue::from_str(&format!("{}{}", value, unit)).expect("timeout must be a valid header")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::ServiceExt;
+
+    #[test]
+    fn grpc_timeouts() {
+        assert_eq!(parse_grpc_timeout("1H"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_grpc_timeout("2M"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_grpc_timeout("3S"), Some(Duration::from_secs(3)));
+        assert_eq!(parse_grpc_timeout("100m"), Some(Duration::from_millis(100)));
+        assert_eq!(parse_grpc_timeout("5u"), Some(Duration::from_micros(5)));
+        assert_eq!(
+            parse_grpc_timeout("99999999n"),
+            Some(Duration::from_nanos(99_999_999))
+        );
+        assert_eq!(parse_grpc_timeout("100"), None);
+        assert_eq!(parse_grpc_timeout("m"), None);
+        assert_eq!(parse_grpc_timeout("-1S"), None);
+        assert_eq!(parse_grpc_timeout("+1S"), None);
+        assert_eq!(parse_grpc_timeout("123456789S"), None);
+        assert_eq!(parse_grpc_timeout("1s"), None);
+
+        for d in &[
+            Duration::from_nanos(5),
+            Duration::from_millis(250),
+            Duration::from_secs(30),
+            Duration::from_secs(60 * 60 * 24 * 365 * 10),
+        ] {
+            let encoded = encode_grpc_timeout(*d);
+            let decoded = parse_grpc_timeout(encoded.to_str().unwrap()).unwrap();
+            assert!(decoded <= *d, "{:?} => {:?}", d, encoded);
+            assert!(
+                *d - decoded < *d / 100 + Duration::from_nanos(1),
+                "{:?} => {:?}",
+                d,
+                encoded
+            );
+        }
+    }
+
+    #[test]
+    fn uses_shortest_timeout() {
+        let config = Config::default();
+        let mut headers = HeaderMap::new();
+        assert_eq!(config.timeout(&headers), None);
+
+        headers.insert(L5D_DEADLINE, HeaderValue::from_static("1500"));
+        assert_eq!(config.timeout(&headers), Some(Duration::from_millis(1500)));
+
+        headers.insert(GRPC_TIMEOUT, HeaderValue::from_static("1S"));
+        assert_eq!(config.timeout(&headers), Some(Duration::from_secs(1)));
+
+        headers.insert(L5D_DEADLINE, HeaderValue::from_static("bogus"));
+        assert_eq!(config.timeout(&headers), Some(Duration::from_secs(1)));
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn enforces_and_propagates() {
+        let (inner, mut handle) = tower_test::mock::pair::<http::Request<()>, http::Response<()>>();
+        let enforce = Enforce::layer(Some(Config::default()));
+        let propagate = Propagate::layer(Some(Config::default()));
+        let svc = layer::Layer::layer(&enforce, layer::Layer::layer(&propagate, inner));
+
+        let req = http::Request::builder()
+            .header(GRPC_TIMEOUT, "10S")
+            .header(L5D_DEADLINE, "5000")
+            .body(())
+            .unwrap();
+        handle.allow(1);
+        let rsp = tokio::spawn(svc.oneshot(req));
+
+        let (req, _send) = handle.next_request().await.expect("must receive request");
+        assert_eq!(req.headers()[L5D_DEADLINE], "5000");
+        assert_eq!(req.headers()[GRPC_TIMEOUT], "5000000u");
+
+        time::sleep(Duration::from_secs(6)).await;
+        let err = rsp.await.unwrap().expect_err("must time out");
+        assert!(err.is::<DeadlineExceeded>(), "{}", err);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn passthru_when_disabled() {
+        let (inner, mut handle) = tower_test::mock::pair::<http::Request<()>, http::Response<()>>();
+        let svc = layer::Layer::layer(&Enforce::layer(None), inner);
+
+        let req = http::Request::builder()
+            .header(GRPC_TIMEOUT, "1n")
+            .body(())
+            .unwrap();
+        handle.allow(1);
+        let rsp = tokio::spawn(svc.oneshot(req));
+
+        let (req, send) = handle.next_request().await.expect("must receive request");
+        assert!(req.extensions().get::<Deadline>().is_none());
+        send.send_response(http::Response::new(()));
+        rsp.await.unwrap().expect("must not time out");
+    }
+}
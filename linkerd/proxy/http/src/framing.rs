@@ -0,0 +1,722 @@
+//! Rejects HTTP/1 requests with ambiguous message framing.
+//!
+//! When the proxy and the application may disagree on where a request ends
+//! (e.g. because it has both `Content-Length` and `Transfer-Encoding` headers),
+//! a client may be able to smuggle a request past the proxy's policies. Hyper
+//! tolerates some of these requests by discarding the conflicting headers
+//! before they can be inspected, so request heads and chunked body framing are
+//! instead checked as they're read from the connection. Connections that send
+//! ambiguously-framed requests fail with an [`InvalidFraming`] error.
+
+use crate::Version;
+use bytes::{Buf, BytesMut};
+use linkerd_io as io;
+use linkerd_metrics::{Counter, FmtLabels, FmtMetrics, Metric};
+use linkerd_stack::{layer, NewService, Param};
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use std::{
+    collections::HashMap,
+    fmt,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tracing::debug;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Reason {
+    /// The request has both `Content-Length` and `Transfer-Encoding` headers.
+    ConflictingFraming,
+
+    /// The request has more than one `Content-Length` value, even if the
+    /// values are equal.
+    DuplicateContentLength,
+
+    /// The request's `Content-Length` is not a decimal number.
+    InvalidContentLength,
+
+    /// The request's `Transfer-Encoding` does not end with a single `chunked`
+    /// coding or includes malformed codings.
+    InvalidTransferEncoding,
+
+    /// A chunk size line includes malformed extensions or trailing bytes.
+    InvalidChunkExtension,
+}
+
+#[derive(Debug, Error)]
+#[error("invalid HTTP/1 request framing: {0}")]
+pub struct InvalidFraming(pub Reason);
+
+/// Counts rejected requests by reason.
+#[derive(Clone, Debug)]
+pub struct Metrics {
+    metric: Metric<'static, &'static str, Counter>,
+    by_reason: Arc<Mutex<HashMap<Reason, Arc<Counter>>>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct NewStrictFraming<N> {
+    inner: N,
+    enabled: bool,
+    metrics: Metrics,
+}
+
+#[derive(Clone, Debug)]
+pub struct StrictFraming<S> {
+    inner: S,
+    metrics: Option<Metrics>,
+}
+
+/// Wraps a server's IO so that the framing of the requests it reads is
+/// checked.
+#[pin_project]
+#[derive(Debug)]
+pub struct StrictFramingIo<I> {
+    #[pin]
+    io: I,
+    scan: Option<Scan>,
+}
+
+#[derive(Debug)]
+struct Scan {
+    metrics: Metrics,
+    state: State,
+
+    /// Bytes read from the connection that have not yet been scanned.
+    read: BytesMut,
+
+    /// Scanned bytes that may be returned to the server.
+    ready: BytesMut,
+    eof: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+    Head,
+
+    /// The given number of body bytes are returned without being scanned.
+    Body(u64),
+
+    ChunkSize,
+
+    /// The given number of chunk bytes are returned without being scanned.
+    ChunkData(u64),
+
+    /// The line ending that follows chunk data.
+    ChunkEnd,
+
+    Trailers,
+
+    /// The remainder of the connection is not scanned, e.g. because it has
+    /// been upgraded or because the server will fail to parse it.
+    Passthrough,
+}
+
+const READ_CAPACITY: usize = 8 * 1024;
+
+/// Request heads larger than this are not scanned and are left for the server
+/// to reject.
+const MAX_HEAD_LEN: usize = 64 * 1024;
+
+/// Chunk size and trailer lines larger than this are not scanned and are left
+/// for the server to reject.
+const MAX_LINE_LEN: usize = 16 * 1024;
+
+/// Matches Hyper's limit on the number of request headers.
+const MAX_HEADERS: usize = 100;
+
+// === impl Reason ===
+
+impl Reason {
+    fn as_label(&self) -> &'static str {
+        match self {
+            Self::ConflictingFraming => "conflicting_framing",
+            Self::DuplicateContentLength => "duplicate_content_length",
+            Self::InvalidContentLength => "invalid_content_length",
+            Self::InvalidTransferEncoding => "invalid_transfer_encoding",
+            Self::InvalidChunkExtension => "invalid_chunk_extension",
+        }
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ConflictingFraming => "both content-length and transfer-encoding are set".fmt(f),
+            Self::DuplicateContentLength => "multiple content-length values".fmt(f),
+            Self::InvalidContentLength => "invalid content-length".fmt(f),
+            Self::InvalidTransferEncoding => "invalid transfer-encoding".fmt(f),
+            Self::InvalidChunkExtension => "invalid chunk extension".fmt(f),
+        }
+    }
+}
+
+impl FmtLabels for Reason {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "reason=\"{}\"", self.as_label())
+    }
+}
+
+// === impl Metrics ===
+
+impl Metrics {
+    pub fn new(metric: Metric<'static, &'static str, Counter>) -> Self {
+        Self {
+            metric,
+            by_reason: Default::default(),
+        }
+    }
+
+    fn counter(&self, reason: Reason) -> Arc<Counter> {
+        self.by_reason.lock().entry(reason).or_default().clone()
+    }
+}
+
+impl FmtMetrics for Metrics {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let by_reason = self.by_reason.lock();
+        if by_reason.is_empty() {
+            return Ok(());
+        }
+
+        self.metric.fmt_help(f)?;
+        for (reason, counter) in by_reason.iter() {
+            self.metric.fmt_metric_labeled(f, counter, reason)?;
+        }
+        Ok(())
+    }
+}
+
+// === impl NewStrictFraming ===
+
+impl<N> NewStrictFraming<N> {
+    /// HTTP/1 connections are only checked if `enabled` is set.
+    pub fn layer(enabled: bool, metrics: Metrics) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            inner,
+            enabled,
+            metrics: metrics.clone(),
+        })
+    }
+}
+
+impl<T, N> NewService<T> for NewStrictFraming<N>
+where
+    T: Param<Version>,
+    N: NewService<T>,
+{
+    type Service = StrictFraming<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let metrics = match target.param() {
+            Version::Http1 if self.enabled => Some(self.metrics.clone()),
+            _ => None,
+        };
+        StrictFraming {
+            metrics,
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl StrictFraming ===
+
+impl<I, S> tower::Service<I> for StrictFraming<S>
+where
+    S: tower::Service<StrictFramingIo<I>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, io: I) -> Self::Future {
+        let scan = self.metrics.clone().map(Scan::new);
+        self.inner.call(StrictFramingIo { io, scan })
+    }
+}
+
+// === impl StrictFramingIo ===
+
+impl<I: io::PeerAddr> io::PeerAddr for StrictFramingIo<I> {
+    #[inline]
+    fn peer_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.io.peer_addr()
+    }
+}
+
+impl<I: io::AsyncRead> io::AsyncRead for StrictFramingIo<I> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut io::ReadBuf<'_>,
+    ) -> io::Poll<()> {
+        let mut this = self.project();
+        let scan = match this.scan {
+            Some(scan) => scan,
+            None => return this.io.poll_read(cx, buf),
+        };
+
+        loop {
+            if !scan.ready.is_empty() {
+                let len = buf.remaining().min(scan.ready.len());
+                buf.put_slice(&scan.ready[..len]);
+                scan.ready.advance(len);
+                return Poll::Ready(Ok(()));
+            }
+
+            if scan.eof {
+                return Poll::Ready(Ok(()));
+            }
+
+            if scan.state == State::Passthrough && scan.read.is_empty() {
+                return this.io.poll_read(cx, buf);
+            }
+
+            scan.read.reserve(READ_CAPACITY);
+            let sz = futures::ready!(io::poll_read_buf(this.io.as_mut(), cx, &mut scan.read))?;
+            scan.eof = sz == 0;
+            scan.scan().map_err(|reason| {
+                debug!(%reason, "Rejecting request with invalid framing");
+                scan.metrics.counter(reason).incr();
+                io::Error::new(io::ErrorKind::InvalidData, InvalidFraming(reason))
+            })?;
+        }
+    }
+}
+
+impl<I: io::AsyncWrite> io::AsyncWrite for StrictFramingIo<I> {
+    #[inline]
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Poll<()> {
+        self.project().io.poll_shutdown(cx)
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Poll<()> {
+        self.project().io.poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> io::Poll<usize> {
+        self.project().io.poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> io::Poll<usize> {
+        self.project().io.poll_write_vectored(cx, bufs)
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+}
+
+// === impl Scan ===
+
+impl Scan {
+    fn new(metrics: Metrics) -> Self {
+        Self {
+            metrics,
+            state: State::Head,
+            read: BytesMut::new(),
+            ready: BytesMut::new(),
+            eof: false,
+        }
+    }
+
+    /// Scans all bytes that have been read, moving them to the `ready` buffer
+    /// unless more bytes are needed to determine whether they're framed
+    /// correctly.
+    fn scan(&mut self) -> Result<(), Reason> {
+        loop {
+            match self.state {
+                State::Passthrough => {
+                    let read = self.read.split();
+                    self.ready.extend_from_slice(&read);
+                    return Ok(());
+                }
+
+                State::Body(remaining) => match self.forward(remaining) {
+                    None => return Ok(()),
+                    Some(0) => self.state = State::Head,
+                    Some(remaining) => self.state = State::Body(remaining),
+                },
+
+                State::ChunkData(remaining) => match self.forward(remaining) {
+                    None => return Ok(()),
+                    Some(0) => self.state = State::ChunkEnd,
+                    Some(remaining) => self.state = State::ChunkData(remaining),
+                },
+
+                State::Head => {
+                    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+                    let mut req = httparse::Request::new(&mut headers);
+                    let (len, next) = match req.parse(&self.read) {
+                        Ok(httparse::Status::Complete(len)) => (len, check_head(&req)?),
+                        Ok(httparse::Status::Partial)
+                            if !self.eof && self.read.len() <= MAX_HEAD_LEN =>
+                        {
+                            return Ok(())
+                        }
+                        // The server fails to parse the request, so the
+                        // connection isn't scanned further.
+                        _ => (0, State::Passthrough),
+                    };
+                    self.advance(len);
+                    self.state = next;
+                }
+
+                State::ChunkSize => {
+                    let len = match self.line() {
+                        Some(len) => len,
+                        None if self.state == State::Passthrough => continue,
+                        None => return Ok(()),
+                    };
+                    let next = check_chunk_size(&self.read[..len])?;
+                    self.advance(len);
+                    self.state = next;
+                }
+
+                State::ChunkEnd => {
+                    if self.read.len() < 2 {
+                        if !self.eof {
+                            return Ok(());
+                        }
+                        self.state = State::Passthrough;
+                        continue;
+                    }
+                    if &self.read[..2] == b"\r\n" {
+                        self.advance(2);
+                        self.state = State::ChunkSize;
+                    } else {
+                        self.state = State::Passthrough;
+                    }
+                }
+
+                State::Trailers => {
+                    let len = match self.line() {
+                        Some(len) => len,
+                        None if self.state == State::Passthrough => continue,
+                        None => return Ok(()),
+                    };
+                    // The trailers end with an empty line.
+                    if matches!(&self.read[..len], b"\r\n" | b"\n") {
+                        self.state = State::Head;
+                    }
+                    self.advance(len);
+                }
+            }
+        }
+    }
+
+    /// Moves up to `remaining` bytes to the `ready` buffer, returning the
+    /// number of bytes that remain, or `None` if no bytes have been read.
+    fn forward(&mut self, remaining: u64) -> Option<u64> {
+        if self.read.is_empty() {
+            return None;
+        }
+        let len = remaining.min(self.read.len() as u64);
+        self.advance(len as usize);
+        Some(remaining - len)
+    }
+
+    fn advance(&mut self, len: usize) {
+        let scanned = self.read.split_to(len);
+        self.ready.extend_from_slice(&scanned);
+    }
+
+    /// Returns the length of the next line, including its line ending, once it
+    /// has been read completely.
+    fn line(&mut self) -> Option<usize> {
+        match self.read.iter().position(|b| *b == b'\n') {
+            Some(i) => Some(i + 1),
+            None => {
+                if self.eof || self.read.len() > MAX_LINE_LEN {
+                    self.state = State::Passthrough;
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Checks a request head's framing headers, returning the state of the bytes
+/// that follow it.
+fn check_head(req: &httparse::Request<'_, '_>) -> Result<State, Reason> {
+    let mut content_length = None;
+    let mut transfer_encoding = false;
+    let mut chunked = false;
+    let mut upgrade = false;
+    for header in req.headers.iter() {
+        if header.name.eq_ignore_ascii_case("content-length") {
+            if content_length.is_some() || header.value.contains(&b',') {
+                return Err(Reason::DuplicateContentLength);
+            }
+            let len = trim(header.value);
+            if len.is_empty() || !len.iter().all(u8::is_ascii_digit) {
+                return Err(Reason::InvalidContentLength);
+            }
+            let len = std::str::from_utf8(len)
+                .ok()
+                .and_then(|len| len.parse::<u64>().ok())
+                .ok_or(Reason::InvalidContentLength)?;
+            content_length = Some(len);
+        } else if header.name.eq_ignore_ascii_case("transfer-encoding") {
+            transfer_encoding = true;
+            for coding in header.value.split(|b| *b == b',') {
+                // Once the message is chunked, it may not be encoded further.
+                let coding = trim(coding);
+                if chunked || coding.is_empty() || !coding.iter().all(is_tchar) {
+                    return Err(Reason::InvalidTransferEncoding);
+                }
+                chunked = coding.eq_ignore_ascii_case(b"chunked");
+            }
+        } else if header.name.eq_ignore_ascii_case("upgrade") {
+            upgrade = true;
+        }
+    }
+
+    if transfer_encoding {
+        if content_length.is_some() {
+            return Err(Reason::ConflictingFraming);
+        }
+        if !chunked {
+            return Err(Reason::InvalidTransferEncoding);
+        }
+    }
+
+    if upgrade || req.method == Some("CONNECT") {
+        return Ok(State::Passthrough);
+    }
+    if chunked {
+        return Ok(State::ChunkSize);
+    }
+    match content_length {
+        None | Some(0) => Ok(State::Head),
+        Some(len) => Ok(State::Body(len)),
+    }
+}
+
+/// Checks a chunk size line, returning the state of the bytes that follow it.
+fn check_chunk_size(line: &[u8]) -> Result<State, Reason> {
+    // Malformed chunk sizes are rejected by the server.
+    let line = match line.strip_suffix(b"\r\n") {
+        Some(line) => line,
+        None => return Ok(State::Passthrough),
+    };
+    let digits = line.iter().take_while(|b| b.is_ascii_hexdigit()).count();
+    if digits == 0 || digits > 16 {
+        return Ok(State::Passthrough);
+    }
+    let size = std::str::from_utf8(&line[..digits])
+        .ok()
+        .and_then(|size| u64::from_str_radix(size, 16).ok());
+    let size = match size {
+        Some(size) => size,
+        None => return Ok(State::Passthrough),
+    };
+
+    if !is_chunk_ext(&line[digits..]) {
+        return Err(Reason::InvalidChunkExtension);
+    }
+
+    match size {
+        0 => Ok(State::Trailers),
+        size => Ok(State::ChunkData(size)),
+    }
+}
+
+/// Matches `*( BWS ";" BWS chunk-ext-name [ BWS "=" BWS chunk-ext-val ] )`, per
+/// RFC 7230.
+fn is_chunk_ext(mut ext: &[u8]) -> bool {
+    loop {
+        ext = trim(ext);
+        if ext.is_empty() {
+            return true;
+        }
+        ext = match ext.strip_prefix(b";") {
+            Some(ext) => trim(ext),
+            None => return false,
+        };
+
+        let name = ext.iter().take_while(|b| is_tchar(b)).count();
+        if name == 0 {
+            return false;
+        }
+        ext = trim(&ext[name..]);
+
+        if let Some(val) = ext.strip_prefix(b"=") {
+            let val = trim(val);
+            let len = if val.first() == Some(&b'"') {
+                quoted_string_len(val)
+            } else {
+                val.iter().take_while(|b| is_tchar(b)).count()
+            };
+            if len == 0 {
+                return false;
+            }
+            ext = &val[len..];
+        }
+    }
+}
+
+/// Returns the length of the quoted string at the start of `s`, or zero if it
+/// is malformed.
+fn quoted_string_len(s: &[u8]) -> usize {
+    let is_text = |b: u8| b == b'\t' || b == b' ' || (b'!'..=b'~').contains(&b) || b >= 0x80;
+    let mut i = 1;
+    while let Some(&b) = s.get(i) {
+        match b {
+            b'"' => return i + 1,
+            b'\\' => match s.get(i + 1) {
+                Some(&b) if is_text(b) => i += 2,
+                _ => return 0,
+            },
+            b if is_text(b) => i += 1,
+            _ => return 0,
+        }
+    }
+    0
+}
+
+/// Trims leading and trailing spaces and tabs.
+fn trim(bytes: &[u8]) -> &[u8] {
+    let is_ws = |b: &u8| *b == b' ' || *b == b'\t';
+    let start = bytes.iter().position(|b| !is_ws(b)).unwrap_or(bytes.len());
+    let end = bytes
+        .iter()
+        .rposition(|b| !is_ws(b))
+        .map_or(start, |i| i + 1);
+    &bytes[start..end]
+}
+
+/// Token characters, per RFC 7230.
+fn is_tchar(b: &u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_io::AsyncReadExt;
+    use linkerd_metrics::metrics;
+
+    metrics! {
+        test_invalid_framing_requests_total: Counter { "Test invalid framing requests" }
+    }
+
+    fn strict_io(reads: &[&[u8]]) -> (StrictFramingIo<tokio_test::io::Mock>, Metrics) {
+        let metrics = Metrics::new(test_invalid_framing_requests_total);
+        let mut io = tokio_test::io::Builder::new();
+        for read in reads {
+            io.read(read);
+        }
+        let io = StrictFramingIo {
+            io: io.build(),
+            scan: Some(Scan::new(metrics.clone())),
+        };
+        (io, metrics)
+    }
+
+    async fn rejected(reads: &[&[u8]]) -> Reason {
+        let (mut io, metrics) = strict_io(reads);
+        let mut buf = Vec::new();
+        let err = io.read_to_end(&mut buf).await.expect_err("must fail");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let reason = match err
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<InvalidFraming>())
+        {
+            Some(InvalidFraming(reason)) => *reason,
+            None => panic!("unexpected error: {}", err),
+        };
+        assert_eq!(u64::from(&*metrics.counter(reason)), 1);
+        reason
+    }
+
+    #[tokio::test]
+    async fn valid_framing() {
+        let reads: &[&[u8]] = &[
+            b"POST / HTTP/1.1\r\ncontent-length: 5\r\n\r\nab",
+            b"cdePOST / HTTP/1.1\r\ntransfer-encoding: gzip\r\ntransfer-encoding: Chunked\r\n\r\n",
+            b"3;a=b ; c=\"d;\\\"e\"\r\nfoo\r\n0\r\nx-trailer: 1\r\n\r\n",
+            b"GET / HTTP/1.1\r\n\r\n",
+        ];
+        let (mut io, metrics) = strict_io(reads);
+        let mut buf = Vec::new();
+        io.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, reads.concat());
+        assert!(metrics.by_reason.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_content_length() {
+        assert_eq!(
+            rejected(&[b"POST / HTTP/1.1\r\ncontent-length: 1\r\ncontent-length: 1\r\n\r\na"])
+                .await,
+            Reason::DuplicateContentLength
+        );
+        assert_eq!(
+            rejected(&[b"POST / HTTP/1.1\r\ncontent-length: 1, 1\r\n\r\na"]).await,
+            Reason::DuplicateContentLength
+        );
+        assert_eq!(
+            rejected(&[b"POST / HTTP/1.1\r\ncontent-length: +1\r\n\r\na"]).await,
+            Reason::InvalidContentLength
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_transfer_encoding() {
+        assert_eq!(
+            rejected(&[
+                b"POST / HTTP/1.1\r\ntransfer-encoding: chunked\r\ncontent-length: 3\r\n\r\n"
+            ])
+            .await,
+            Reason::ConflictingFraming
+        );
+        assert_eq!(
+            rejected(&[b"POST / HTTP/1.1\r\ntransfer-encoding: chunked, chunked\r\n\r\n"]).await,
+            Reason::InvalidTransferEncoding
+        );
+        assert_eq!(
+            rejected(&[b"POST / HTTP/1.1\r\ntransfer-encoding: identity\r\n\r\n"]).await,
+            Reason::InvalidTransferEncoding
+        );
+        assert_eq!(
+            rejected(&[b"POST / HTTP/1.1\r\ntransfer-encoding: gzip,,chunked\r\n\r\n"]).await,
+            Reason::InvalidTransferEncoding
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_chunk_extensions() {
+        assert_eq!(
+            rejected(&[
+                b"POST / HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\n",
+                b"3;a=\"b\r\nfoo\r\n0\r\n\r\n",
+            ])
+            .await,
+            Reason::InvalidChunkExtension
+        );
+        assert_eq!(
+            rejected(&[
+                b"POST / HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\n3\r\nfoo\r\n",
+                b"0 x\r\n\r\n",
+            ])
+            .await,
+            Reason::InvalidChunkExtension
+        );
+    }
+}
@@ -0,0 +1,449 @@
+//! Translates gRPC-Web requests into native gRPC.
+//!
+//! Browsers can't control HTTP/2 framing or read trailers, so gRPC-Web clients
+//! send `application/grpc-web` requests (optionally base64-encoded as
+//! `application/grpc-web-text`) over HTTP/1.1 or HTTP/2. These requests are
+//! rewritten as HTTP/2 gRPC requests for the application, and the
+//! application's trailers are encoded into the response body as a final
+//! gRPC-Web trailers frame.
+//!
+//! See https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-WEB.md.
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use futures::prelude::*;
+use http::header::{self, HeaderMap, HeaderValue};
+use http_body::Body;
+use linkerd_error::Error;
+use linkerd_http_box::BoxBody;
+use linkerd_stack::layer;
+use pin_project::pin_project;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tracing::{debug, trace};
+
+const GRPC: &str = "application/grpc";
+const GRPC_WEB: &str = "application/grpc-web";
+const GRPC_WEB_TEXT: &str = "application/grpc-web-text";
+
+/// The flag set on a gRPC-Web frame that holds trailers rather than a message.
+const TRAILERS_FLAG: u8 = 0x80;
+
+/// Translates gRPC-Web requests to gRPC, if enabled.
+#[derive(Clone, Debug)]
+pub struct GrpcWeb<S> {
+    inner: S,
+    enabled: bool,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    encoding: Option<Encoding>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Encoding {
+    Binary,
+    Text,
+}
+
+/// Decodes a base64-encoded `application/grpc-web-text` request body.
+#[pin_project]
+struct DecodeText {
+    #[pin]
+    inner: BoxBody,
+    buf: BytesMut,
+}
+
+/// Encodes a gRPC response body's trailers as a gRPC-Web trailers frame.
+#[pin_project]
+struct EncodeTrailers {
+    #[pin]
+    inner: BoxBody,
+    encoding: Encoding,
+    state: State,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+    Data,
+    Trailers,
+    Done,
+}
+
+// === impl GrpcWeb ===
+
+impl<S> GrpcWeb<S> {
+    pub fn layer(enabled: bool) -> impl layer::Layer<S, Service = Self> + Clone {
+        layer::mk(move |inner| Self { inner, enabled })
+    }
+}
+
+impl<S> tower::Service<http::Request<BoxBody>> for GrpcWeb<S>
+where
+    S: tower::Service<http::Request<BoxBody>, Response = http::Response<BoxBody>>,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<BoxBody>) -> Self::Future {
+        let grpc_web = if self.enabled {
+            Encoding::from_content_type(req.headers())
+        } else {
+            None
+        };
+        let (encoding, suffix) = match grpc_web {
+            Some(grpc_web) => grpc_web,
+            None => {
+                return ResponseFuture {
+                    inner: self.inner.call(req),
+                    encoding: None,
+                }
+            }
+        };
+
+        debug!(?encoding, "Translating gRPC-Web request");
+        let content_type = HeaderValue::from_str(&format!("{}{}", GRPC, suffix))
+            .expect("content-type must be valid");
+        let headers = req.headers_mut();
+        headers.insert(header::CONTENT_TYPE, content_type);
+        headers.insert(header::TE, HeaderValue::from_static("trailers"));
+        // The body may be re-encoded, so its length is not preserved.
+        headers.remove(header::CONTENT_LENGTH);
+        // gRPC requires HTTP/2 to the application, regardless of how the
+        // browser reached us.
+        *req.version_mut() = http::Version::HTTP_2;
+        if encoding == Encoding::Text {
+            req = req.map(|inner| {
+                BoxBody::new(DecodeText {
+                    inner,
+                    buf: BytesMut::new(),
+                })
+            });
+        }
+
+        ResponseFuture {
+            inner: self.inner.call(req),
+            encoding: Some(encoding),
+        }
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F, E> Future for ResponseFuture<F>
+where
+    F: TryFuture<Ok = http::Response<BoxBody>, Error = E>,
+{
+    type Output = Result<http::Response<BoxBody>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut rsp = futures::ready!(this.inner.try_poll(cx))?;
+        let encoding = match *this.encoding {
+            Some(encoding) => encoding,
+            None => return Poll::Ready(Ok(rsp)),
+        };
+
+        // Only gRPC responses are translated; e.g. errors returned by a
+        // non-gRPC server are passed through unmodified.
+        let suffix = match rsp
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|ct| ct.strip_prefix(GRPC))
+            .filter(|s| s.is_empty() || s.starts_with('+'))
+        {
+            Some(suffix) => suffix.to_string(),
+            None => {
+                trace!("Response is not gRPC");
+                return Poll::Ready(Ok(rsp));
+            }
+        };
+
+        let content_type = HeaderValue::from_str(&format!("{}{}", encoding.as_str(), suffix))
+            .expect("content-type must be valid");
+        rsp.headers_mut().insert(header::CONTENT_TYPE, content_type);
+        rsp.headers_mut().remove(header::CONTENT_LENGTH);
+        let rsp = rsp.map(|inner| {
+            BoxBody::new(EncodeTrailers {
+                inner,
+                encoding,
+                state: State::Data,
+            })
+        });
+        Poll::Ready(Ok(rsp))
+    }
+}
+
+// === impl Encoding ===
+
+impl Encoding {
+    /// Returns the encoding and content-type suffix (e.g. `+proto`) of a
+    /// gRPC-Web request.
+    fn from_content_type(headers: &HeaderMap) -> Option<(Self, &str)> {
+        let ct = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+        let (encoding, suffix) = match ct.strip_prefix(GRPC_WEB_TEXT) {
+            Some(suffix) => (Self::Text, suffix),
+            None => (Self::Binary, ct.strip_prefix(GRPC_WEB)?),
+        };
+        if suffix.is_empty() || suffix.starts_with('+') {
+            Some((encoding, suffix))
+        } else {
+            None
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Binary => GRPC_WEB,
+            Self::Text => GRPC_WEB_TEXT,
+        }
+    }
+
+    fn encode(&self, bytes: Bytes) -> Bytes {
+        match self {
+            Self::Binary => bytes,
+            Self::Text => base64::encode(&bytes).into(),
+        }
+    }
+}
+
+// === impl DecodeText ===
+
+impl Body for DecodeText {
+    type Data = Bytes;
+    type Error = Error;
+
+    fn is_end_stream(&self) -> bool {
+        self.buf.is_empty() && self.inner.is_end_stream()
+    }
+
+    fn poll_data(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Error>>> {
+        let mut this = self.project();
+        loop {
+            match futures::ready!(this.inner.as_mut().poll_data(cx)) {
+                Some(data) => {
+                    let mut data = data?;
+                    this.buf.put(&mut data);
+                    let decoded = decode_text(this.buf)?;
+                    if !decoded.is_empty() {
+                        return Poll::Ready(Some(Ok(decoded)));
+                    }
+                }
+                None if this.buf.is_empty() => return Poll::Ready(None),
+                None => {
+                    return Poll::Ready(Some(Err(base64::DecodeError::InvalidLength.into())));
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Error>> {
+        self.project().inner.poll_trailers(cx)
+    }
+}
+
+/// Decodes as much of the buffered base64 text as is aligned to 4-byte
+/// quanta, leaving the remainder in the buffer.
+fn decode_text(buf: &mut BytesMut) -> Result<Bytes, base64::DecodeError> {
+    let mut input = buf.split_to(buf.len() - buf.len() % 4);
+    let mut decoded = BytesMut::with_capacity(input.len() / 4 * 3);
+    while !input.is_empty() {
+        // Each message may be padded independently, so padding may appear in
+        // the middle of the stream. Decode each padded segment separately.
+        let end = match input.iter().position(|b| *b == b'=') {
+            Some(i) => (i / 4 + 1) * 4,
+            None => input.len(),
+        };
+        let segment = input.split_to(end);
+        decoded.put(base64::decode(&segment)?.as_slice());
+    }
+    Ok(decoded.freeze())
+}
+
+// === impl EncodeTrailers ===
+
+impl Body for EncodeTrailers {
+    type Data = Bytes;
+    type Error = Error;
+
+    fn is_end_stream(&self) -> bool {
+        self.state == State::Done
+    }
+
+    fn poll_data(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Error>>> {
+        let mut this = self.project();
+        if *this.state == State::Data {
+            match futures::ready!(this.inner.as_mut().poll_data(cx)) {
+                Some(data) => {
+                    let mut data = data?;
+                    let bytes = data.copy_to_bytes(data.remaining());
+                    return Poll::Ready(Some(Ok(this.encoding.encode(bytes))));
+                }
+                None => *this.state = State::Trailers,
+            }
+        }
+
+        if *this.state == State::Trailers {
+            let trailers = futures::ready!(this.inner.poll_trailers(cx))?;
+            *this.state = State::Done;
+            if let Some(trailers) = trailers {
+                let frame = encode_trailers(&trailers);
+                return Poll::Ready(Some(Ok(this.encoding.encode(frame))));
+            }
+        }
+
+        Poll::Ready(None)
+    }
+
+    #[inline]
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Error>> {
+        // Trailers are always encoded in the body.
+        Poll::Ready(Ok(None))
+    }
+}
+
+fn encode_trailers(trailers: &HeaderMap) -> Bytes {
+    let mut block = BytesMut::new();
+    for (name, value) in trailers.iter() {
+        block.put(name.as_str().as_bytes());
+        block.put(&b": "[..]);
+        block.put(value.as_bytes());
+        block.put(&b"\r\n"[..]);
+    }
+
+    let mut frame = BytesMut::with_capacity(5 + block.len());
+    frame.put_u8(TRAILERS_FLAG);
+    frame.put_u32(block.len() as u32);
+    frame.put(block);
+    frame.freeze()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::{Layer, ServiceExt};
+
+    #[test]
+    fn content_types() {
+        for (ct, expected) in &[
+            ("application/grpc-web", Some((Encoding::Binary, ""))),
+            (
+                "application/grpc-web+proto",
+                Some((Encoding::Binary, "+proto")),
+            ),
+            ("application/grpc-web-text", Some((Encoding::Text, ""))),
+            (
+                "application/grpc-web-text+proto",
+                Some((Encoding::Text, "+proto")),
+            ),
+            ("application/grpc", None),
+            ("application/grpc-webfoo", None),
+            ("text/plain", None),
+        ] {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(ct));
+            assert_eq!(Encoding::from_content_type(&headers), *expected, "{}", ct);
+        }
+    }
+
+    #[test]
+    fn decodes_padded_segments() {
+        let mut buf = BytesMut::from(&b"YQ==YmM=ZG"[..]);
+        assert_eq!(decode_text(&mut buf).unwrap(), Bytes::from_static(b"abc"));
+        assert_eq!(&buf[..], b"ZG");
+        buf.put(&b"Vm"[..]);
+        assert_eq!(decode_text(&mut buf).unwrap(), Bytes::from_static(b"def"));
+        assert!(buf.is_empty());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn translates_text() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        const MSG: &[u8] = b"\x00\x00\x00\x00\x02hi";
+
+        let svc = GrpcWeb::layer(true).layer(tower::service_fn(
+            |req: http::Request<BoxBody>| async move {
+                assert_eq!(req.version(), http::Version::HTTP_2);
+                assert_eq!(
+                    req.headers()[header::CONTENT_TYPE],
+                    "application/grpc+proto"
+                );
+                assert_eq!(req.headers()[header::TE], "trailers");
+                let body = hyper::body::to_bytes(req.into_body()).await?;
+                assert_eq!(&body[..], MSG);
+
+                let (mut tx, body) = hyper::Body::channel();
+                tokio::spawn(async move {
+                    tx.send_data(Bytes::from_static(MSG)).await.unwrap();
+                    let mut trailers = HeaderMap::new();
+                    trailers.insert("grpc-status", HeaderValue::from_static("0"));
+                    tx.send_trailers(trailers).await.unwrap();
+                });
+                let rsp = http::Response::builder()
+                    .header(header::CONTENT_TYPE, "application/grpc+proto")
+                    .body(BoxBody::new(body))
+                    .unwrap();
+                Ok::<_, Error>(rsp)
+            },
+        ));
+
+        let req = http::Request::builder()
+            .method(http::Method::POST)
+            .version(http::Version::HTTP_11)
+            .header(header::CONTENT_TYPE, "application/grpc-web-text+proto")
+            .body(BoxBody::new(hyper::Body::from(base64::encode(MSG))))
+            .unwrap();
+        let rsp = svc.oneshot(req).await.expect("request must succeed");
+        assert_eq!(
+            rsp.headers()[header::CONTENT_TYPE],
+            "application/grpc-web-text+proto"
+        );
+
+        let mut body = rsp.into_body();
+        let text = hyper::body::to_bytes(&mut body).await.unwrap();
+        let mut expected = base64::encode(MSG);
+        expected.push_str(&base64::encode(b"\x80\x00\x00\x00\x10grpc-status: 0\r\n"));
+        assert_eq!(&text[..], expected.as_bytes());
+        let trailers = body.trailers().await.expect("trailers must succeed");
+        assert!(trailers.is_none(), "trailers must be encoded in the body");
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn disabled() {
+        let svc = GrpcWeb::layer(false).layer(tower::service_fn(
+            |req: http::Request<BoxBody>| async move {
+                assert_eq!(req.version(), http::Version::HTTP_11);
+                assert_eq!(req.headers()[header::CONTENT_TYPE], "application/grpc-web");
+                Ok::<_, Error>(http::Response::new(BoxBody::default()))
+            },
+        ));
+
+        let req = http::Request::builder()
+            .method(http::Method::POST)
+            .version(http::Version::HTTP_11)
+            .header(header::CONTENT_TYPE, "application/grpc-web")
+            .body(BoxBody::default())
+            .unwrap();
+        svc.oneshot(req).await.expect("request must succeed");
+    }
+}
@@ -14,12 +14,37 @@ use tracing::{debug, trace};
 #[derive(Copy, Clone, Debug)]
 pub struct WasAbsoluteForm(pub(crate) ());
 
+/// Marks requests that were received by a server that preserves the original
+/// casing of header names, so that the casing of response headers is preserved
+/// as well.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PreserveHeaderCase(pub(crate) bool);
+
 #[derive(Copy, Clone, Debug)]
 pub struct PoolSettings {
     pub max_idle: usize,
     pub idle_timeout: Duration,
 }
 
+/// Configures how clients may reuse HTTP/1 connections to a server.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ServerSettings {
+    /// Connections are closed once they have had no requests in flight for
+    /// this long.
+    pub idle_timeout: Option<Duration>,
+
+    /// Connections are closed once they have served this many requests.
+    pub max_requests: Option<usize>,
+
+    /// Whether responses are sent with `Connection: close` once the server
+    /// begins draining, so that clients stop reusing their connections.
+    pub close_on_drain: bool,
+
+    /// Whether header names are forwarded with the casing in which they were
+    /// received, rather than lowercased, for applications that depend on it.
+    pub preserve_header_case: bool,
+}
+
 /// Communicates with HTTP/1.x servers.
 ///
 /// The client handles both absolute-form and origin-form requests by lazily
@@ -32,6 +57,8 @@ pub struct Client<C, T, B> {
     target: T,
     absolute_form: Option<hyper::Client<HyperConnect<C, T>, B>>,
     origin_form: Option<hyper::Client<HyperConnect<C, T>, B>>,
+    preserve_case_absolute_form: Option<hyper::Client<HyperConnect<C, T>, B>>,
+    preserve_case_origin_form: Option<hyper::Client<HyperConnect<C, T>, B>>,
     pool: PoolSettings,
 }
 
@@ -42,6 +69,8 @@ impl<C, T, B> Client<C, T, B> {
             target,
             absolute_form: None,
             origin_form: None,
+            preserve_case_absolute_form: None,
+            preserve_case_origin_form: None,
             pool,
         }
     }
@@ -54,6 +83,8 @@ impl<C: Clone, T: Clone, B> Clone for Client<C, T, B> {
             target: self.target.clone(),
             absolute_form: self.absolute_form.clone(),
             origin_form: self.origin_form.clone(),
+            preserve_case_absolute_form: self.preserve_case_absolute_form.clone(),
+            preserve_case_origin_form: self.preserve_case_origin_form.clone(),
             pool: self.pool,
         }
     }
@@ -81,6 +112,12 @@ where
 
         // Configured by `normalize_uri` or `orig_proto::Downgrade`.
         let use_absolute_form = req.extensions_mut().remove::<WasAbsoluteForm>().is_some();
+        // Configured by the server that received the request.
+        let preserve_header_case = req
+            .extensions_mut()
+            .remove::<PreserveHeaderCase>()
+            .map(|PreserveHeaderCase(preserve)| preserve)
+            .unwrap_or(false);
         debug_assert!(req.uri().authority().is_some());
 
         let is_missing_host = req
@@ -97,6 +134,7 @@ where
             hyper::Client::builder()
                 .pool_max_idle_per_host(0)
                 .set_host(use_absolute_form)
+                .http1_preserve_header_case(preserve_header_case)
                 .build(HyperConnect::new(
                     self.connect.clone(),
                     self.target.clone(),
@@ -107,22 +145,38 @@ where
             // Otherwise, use a cached client to take advantage of the
             // connection pool. The client needs to be configured for absolute
             // (HTTP proxy-style) URIs, so we cache separate absolute/origin
-            // clients lazily.
-            let client = if use_absolute_form {
-                trace!("Using absolute-form client");
-                &mut self.absolute_form
-            } else {
-                trace!("Using origin-form client");
-                &mut self.origin_form
+            // clients lazily. Clients that preserve the casing of response
+            // headers are cached separately as well.
+            let client = match (use_absolute_form, preserve_header_case) {
+                (true, false) => {
+                    trace!("Using absolute-form client");
+                    &mut self.absolute_form
+                }
+                (false, false) => {
+                    trace!("Using origin-form client");
+                    &mut self.origin_form
+                }
+                (true, true) => {
+                    trace!("Using case-preserving absolute-form client");
+                    &mut self.preserve_case_absolute_form
+                }
+                (false, true) => {
+                    trace!("Using case-preserving origin-form client");
+                    &mut self.preserve_case_origin_form
+                }
             };
 
             if client.is_none() {
-                debug!(use_absolute_form, "Caching new client");
+                debug!(
+                    use_absolute_form,
+                    preserve_header_case, "Caching new client"
+                );
                 *client = Some(
                     hyper::Client::builder()
                         .pool_max_idle_per_host(self.pool.max_idle)
                         .pool_idle_timeout(self.pool.idle_timeout)
                         .set_host(use_absolute_form)
+                        .http1_preserve_header_case(preserve_header_case)
                         .build(HyperConnect::new(
                             self.connect.clone(),
                             self.target.clone(),
@@ -306,3 +360,216 @@ pub(crate) fn is_bad_request<B>(req: &http::Request<B>) -> bool {
 
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_io::{self as io, AsyncReadExt, AsyncWriteExt};
+    use tokio::sync::mpsc;
+
+    const POOL: PoolSettings = PoolSettings {
+        max_idle: 1,
+        idle_timeout: Duration::from_secs(1),
+    };
+
+    /// Tests that a client that expects `100 Continue` is told to continue by
+    /// the proxy, and that its request completes once it sends the body.
+    #[tokio::test(flavor = "current_thread")]
+    async fn expect_continue_round_trips() {
+        let _trace = linkerd_tracing::test::trace_init();
+        let mut io = proxy(connect_echo);
+
+        io.write_all(
+            b"POST /upload HTTP/1.1\r\n\
+              host: app.example.com\r\n\
+              expect: 100-continue\r\n\
+              content-length: 5\r\n\r\n",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            read_head(&mut io).await,
+            "HTTP/1.1 100 Continue\r\n\r\n",
+            "the client must be told to send its body"
+        );
+
+        io.write_all(b"hello").await.unwrap();
+        let head = read_head(&mut io).await;
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{}", head);
+        let mut body = [0u8; 5];
+        io.read_exact(&mut body).await.unwrap();
+        assert_eq!(&body, b"hello");
+    }
+
+    /// Tests that informational responses from the application do not
+    /// disrupt the final response.
+    ///
+    /// hyper's client does not expose informational responses, so they are
+    /// not forwarded to the client.
+    #[tokio::test(flavor = "current_thread")]
+    async fn informational_responses_are_skipped() {
+        let _trace = linkerd_tracing::test::trace_init();
+        let mut io = proxy(|_: ()| {
+            let (client_io, mut server_io) = io::duplex(4096);
+            tokio::spawn(async move {
+                let mut buf = [0u8; 1024];
+                let _ = server_io.read(&mut buf).await.unwrap();
+                server_io
+                    .write_all(
+                        b"HTTP/1.1 103 Early Hints\r\n\
+                          link: </style.css>; rel=preload\r\n\r\n\
+                          HTTP/1.1 200 OK\r\n\
+                          content-length: 2\r\n\r\n\
+                          ok",
+                    )
+                    .await
+                    .unwrap();
+                // Hold the connection open until the proxy closes it.
+                let _ = server_io.read(&mut buf).await;
+            });
+            future::ok::<_, Error>(client_io)
+        });
+
+        io.write_all(b"GET / HTTP/1.1\r\nhost: app.example.com\r\n\r\n")
+            .await
+            .unwrap();
+        let head = read_head(&mut io).await;
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{}", head);
+        let mut body = [0u8; 2];
+        io.read_exact(&mut body).await.unwrap();
+        assert_eq!(&body, b"ok");
+    }
+
+    /// Tests that, when configured, header names are forwarded with the casing
+    /// in which they were received in both directions.
+    #[tokio::test(flavor = "current_thread")]
+    async fn preserves_header_case() {
+        let _trace = linkerd_tracing::test::trace_init();
+        let (heads_tx, mut heads) = mpsc::unbounded_channel();
+        let mut io = proxy_with_header_case(connect_record_head(heads_tx), true);
+
+        io.write_all(
+            b"GET / HTTP/1.1\r\n\
+              Host: app.example.com\r\n\
+              X-Custom-Header: a\r\n\r\n",
+        )
+        .await
+        .unwrap();
+        let req = heads.recv().await.expect("request must be forwarded");
+        assert!(req.contains("\r\nX-Custom-Header: a\r\n"), "{}", req);
+
+        let rsp = read_head(&mut io).await;
+        assert!(rsp.starts_with("HTTP/1.1 200 OK\r\n"), "{}", rsp);
+        assert!(rsp.contains("\r\nX-Reply-Header: b\r\n"), "{}", rsp);
+    }
+
+    /// Tests that header names are lowercased by default.
+    #[tokio::test(flavor = "current_thread")]
+    async fn lowercases_headers_by_default() {
+        let _trace = linkerd_tracing::test::trace_init();
+        let (heads_tx, mut heads) = mpsc::unbounded_channel();
+        let mut io = proxy(connect_record_head(heads_tx));
+
+        io.write_all(
+            b"GET / HTTP/1.1\r\n\
+              Host: app.example.com\r\n\
+              X-Custom-Header: a\r\n\r\n",
+        )
+        .await
+        .unwrap();
+        let req = heads.recv().await.expect("request must be forwarded");
+        assert!(req.contains("\r\nx-custom-header: a\r\n"), "{}", req);
+
+        let rsp = read_head(&mut io).await;
+        assert!(rsp.starts_with("HTTP/1.1 200 OK\r\n"), "{}", rsp);
+        assert!(rsp.contains("\r\nx-reply-header: b\r\n"), "{}", rsp);
+    }
+
+    /// Serves a connection with a proxy that forwards requests to the
+    /// application with `Client`, returning the client's end of the
+    /// connection.
+    fn proxy<C, F>(connect: C) -> io::DuplexStream
+    where
+        C: FnMut(()) -> F + Clone + Send + Sync + 'static,
+        F: Future<Output = Result<io::DuplexStream, Error>> + Unpin + Send + 'static,
+    {
+        proxy_with_header_case(connect, false)
+    }
+
+    /// Like `proxy`, but configures whether the casing of header names is
+    /// preserved.
+    fn proxy_with_header_case<C, F>(connect: C, preserve_header_case: bool) -> io::DuplexStream
+    where
+        C: FnMut(()) -> F + Clone + Send + Sync + 'static,
+        F: Future<Output = Result<io::DuplexStream, Error>> + Unpin + Send + 'static,
+    {
+        let mut client = Client::<_, (), hyper::Body>::new(tower::service_fn(connect), (), POOL);
+        let svc = tower::service_fn(move |mut req: http::Request<hyper::Body>| {
+            // The proxy's server normalizes requests to include an authority.
+            let uri = format!("http://app.example.com{}", req.uri());
+            *req.uri_mut() = uri.parse().unwrap();
+            req.extensions_mut()
+                .insert(PreserveHeaderCase(preserve_header_case));
+            client.request(req)
+        });
+        let (client_io, server_io) = io::duplex(4096);
+        tokio::spawn(
+            hyper::server::conn::Http::new()
+                .http1_only(true)
+                .http1_preserve_header_case(preserve_header_case)
+                .serve_connection(server_io, svc),
+        );
+        client_io
+    }
+
+    /// Connects to an application that sends each request's head on `heads`
+    /// and responds with a mixed-case header.
+    fn connect_record_head(
+        heads: mpsc::UnboundedSender<String>,
+    ) -> impl FnMut(()) -> future::Ready<Result<io::DuplexStream, Error>> + Clone {
+        move |()| {
+            let (client_io, mut server_io) = io::duplex(4096);
+            let heads = heads.clone();
+            tokio::spawn(async move {
+                let _ = heads.send(read_head(&mut server_io).await);
+                server_io
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\n\
+                          X-Reply-Header: b\r\n\
+                          content-length: 0\r\n\r\n",
+                    )
+                    .await
+                    .unwrap();
+                // Hold the connection open until the proxy closes it.
+                let mut buf = [0u8; 1024];
+                let _ = server_io.read(&mut buf).await;
+            });
+            future::ok(client_io)
+        }
+    }
+
+    /// Connects to an application that echoes request bodies.
+    fn connect_echo(_: ()) -> future::Ready<Result<io::DuplexStream, Error>> {
+        let (client_io, server_io) = io::duplex(4096);
+        let app = tower::service_fn(|req: http::Request<hyper::Body>| async move {
+            let body = hyper::body::to_bytes(req.into_body()).await?;
+            Ok::<_, hyper::Error>(http::Response::new(hyper::Body::from(body)))
+        });
+        tokio::spawn(
+            hyper::server::conn::Http::new()
+                .http1_only(true)
+                .serve_connection(server_io, app),
+        );
+        future::ok(client_io)
+    }
+
+    /// Reads a response head, byte by byte so that nothing after it is
+    /// consumed.
+    async fn read_head(io: &mut io::DuplexStream) -> String {
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            head.push(io.read_u8().await.expect("must read response head"));
+        }
+        String::from_utf8(head).unwrap()
+    }
+}
@@ -10,6 +10,10 @@ use std::{
     future::Future,
     marker::PhantomData,
     pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
 };
 use tokio::io::{AsyncRead, AsyncWrite};
@@ -21,6 +25,19 @@ pub struct Settings {
     pub initial_stream_window_size: Option<u32>,
     pub initial_connection_window_size: Option<u32>,
     pub keepalive_timeout: Option<Duration>,
+
+    /// Whether servers advertise support for the extended CONNECT protocol
+    /// (RFC 8441), i.e. to bootstrap WebSockets over HTTP/2.
+    pub enable_connect_protocol: bool,
+
+    /// When set, connections are gracefully closed once they have been open
+    /// for this long: client connections are retired and replaced, and
+    /// servers send a GOAWAY so that clients reconnect.
+    pub max_connection_age: Option<Duration>,
+
+    /// When set, client connections are retired once they have dispatched
+    /// this many requests.
+    pub max_connection_requests: Option<usize>,
 }
 
 #[derive(Debug)]
@@ -33,6 +50,8 @@ pub struct Connect<C, B> {
 #[derive(Debug)]
 pub struct Connection<B> {
     tx: SendRequest<B>,
+    /// Set when the server has advertised support for extended CONNECT.
+    extended_connect: Arc<AtomicBool>,
 }
 
 // === impl Connect ===
@@ -66,7 +85,7 @@ where
     C::Future: Send + 'static,
     C::Connection: AsyncRead + AsyncWrite + Unpin + Send + 'static,
     C::Error: Into<Error>,
-    B: HttpBody + Send + 'static,
+    B: HttpBody + Send + Unpin + 'static,
     B::Data: Send,
     B::Error: Into<Error> + Send + Sync,
 {
@@ -83,6 +102,7 @@ where
             initial_connection_window_size,
             initial_stream_window_size,
             keepalive_timeout,
+            ..
         } = self.h2_settings;
 
         let connect = self
@@ -111,18 +131,35 @@ where
                         .http2_keep_alive_while_idle(true);
                 }
 
-                let (tx, conn) = builder
+                let (tx, mut conn) = builder
                     .handshake(io)
                     .instrument(trace_span!("handshake"))
                     .await?;
 
+                // The server's settings are only received once the connection
+                // is driven, so the connection task records whether extended
+                // CONNECT has been enabled each time it is polled.
+                let extended_connect = Arc::new(AtomicBool::new(false));
+                let enabled = extended_connect.clone();
+                let conn = future::poll_fn(move |cx| {
+                    let poll = Pin::new(&mut conn).poll(cx);
+                    if poll.is_pending() {
+                        let enabled_now = conn.http2_is_extended_connect_protocol_enabled();
+                        enabled.store(enabled_now, Ordering::Release);
+                    }
+                    poll
+                });
+
                 tokio::spawn(
                     conn.map_err(|error| debug!(%error, "failed"))
                         .instrument(trace_span!("conn"))
                         .in_current_span(),
                 );
 
-                Ok(Connection { tx })
+                Ok(Connection {
+                    tx,
+                    extended_connect,
+                })
             }
             .instrument(debug_span!("h2")),
         )
@@ -131,6 +168,14 @@ where
 
 // === impl Connection ===
 
+impl<B> Connection<B> {
+    /// Returns true if the server has advertised support for the extended
+    /// CONNECT protocol (RFC 8441).
+    pub(crate) fn is_extended_connect_enabled(&self) -> bool {
+        self.extended_connect.load(Ordering::Acquire)
+    }
+}
+
 impl<B> tower::Service<http::Request<B>> for Connection<B>
 where
     B: HttpBody + Send + 'static,
@@ -163,3 +208,59 @@ where
         self.tx.send_request(req)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_io as io;
+    use tower::{Service, ServiceExt};
+
+    /// Connects to an HTTP/2 server that may support extended CONNECT.
+    async fn connect(enable_connect_protocol: bool) -> Connection<hyper::Body> {
+        let (client_io, server_io) = io::duplex(4096);
+        let mut server = hyper::server::conn::Http::new();
+        server.http2_only(true);
+        if enable_connect_protocol {
+            server.http2_enable_connect_protocol();
+        }
+        tokio::spawn(server.serve_connection(
+            server_io,
+            tower::service_fn(|_: http::Request<hyper::Body>| {
+                future::ok::<_, hyper::Error>(http::Response::new(hyper::Body::empty()))
+            }),
+        ));
+
+        let mut client_io = Some(client_io);
+        let connect = tower::service_fn(move |_: ()| {
+            future::ok::<_, Error>(client_io.take().expect("must only connect once"))
+        });
+        let mut conn = Connect::new(connect, Settings::default())
+            .oneshot(())
+            .await
+            .expect("must connect");
+
+        // The server's settings precede its first response.
+        let req = http::Request::builder()
+            .version(http::Version::HTTP_2)
+            .uri("http://example.com/")
+            .body(hyper::Body::empty())
+            .unwrap();
+        let rsp = conn.ready().await.unwrap().call(req).await.unwrap();
+        assert_eq!(rsp.status(), http::StatusCode::OK);
+        conn
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn extended_connect_enabled() {
+        let _trace = linkerd_tracing::test::trace_init();
+        let conn = connect(true).await;
+        assert!(conn.is_extended_connect_enabled());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn extended_connect_disabled() {
+        let _trace = linkerd_tracing::test::trace_init();
+        let conn = connect(false).await;
+        assert!(!conn.is_extended_connect_enabled());
+    }
+}
@@ -24,7 +24,7 @@ pub struct Insert<S, L, V> {
 pub struct FnLazy<F>(F);
 
 #[derive(Clone, Debug)]
-pub struct ValLazy<V>(V);
+pub struct ValLazy<V>(pub(crate) V);
 
 /// Wraps an HTTP `Service` so that a `P`-typed `Param` is cloned into each
 /// request's extensions.
@@ -7,16 +7,22 @@ use linkerd_error::Error;
 pub mod balance;
 pub mod client;
 pub mod client_handle;
+pub mod deadline;
 pub mod detect;
+pub mod framing;
 mod glue;
+pub mod grpc_web;
 pub mod h1;
 pub mod h2;
 mod header_from_target;
 pub mod insert;
+pub mod malformed;
 pub mod normalize_uri;
 pub mod orig_proto;
 mod override_authority;
 mod retain;
+pub mod retire;
+mod reuse;
 mod server;
 pub mod strip_header;
 pub mod timeout;
@@ -32,6 +38,7 @@ pub use self::{
     normalize_uri::{MarkAbsoluteForm, NewNormalizeUri},
     override_authority::{AuthorityOverride, NewOverrideAuthority},
     retain::Retain,
+    retire::NewRetire,
     server::NewServeHttp,
     timeout::MakeTimeoutLayer,
     version::Version,
@@ -0,0 +1,666 @@
+//! Normalizes or rejects malformed HTTP/1 request heads.
+//!
+//! Hyper fails to parse request heads that include obsolete line folding (a
+//! header value continued on a line that begins with whitespace) or carriage
+//! returns that are not followed by a line feed, so these connections fail
+//! with a generic protocol error. Servers may instead be configured to
+//! normalize these bytes to spaces, as permitted by RFC 7230, or to reject
+//! these requests explicitly so that they're counted by cause.
+
+use crate::Version;
+use bytes::{Buf, BytesMut};
+use linkerd_io as io;
+use linkerd_metrics::{Counter, FmtLabels, FmtMetrics, Metric};
+use linkerd_stack::{layer, NewService, Param};
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use std::{
+    collections::HashMap,
+    fmt,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tracing::debug;
+
+/// Determines how malformed HTTP/1 requests are handled.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Policy {
+    /// Malformed bytes are replaced with spaces before the request is parsed.
+    Normalize,
+
+    /// The connection fails with a [`MalformedRequest`] error.
+    Reject,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Malformation {
+    /// A header value continued on a line that begins with a space or tab.
+    ObsFold,
+
+    /// A carriage return that is not followed by a line feed.
+    BareCr,
+}
+
+#[derive(Debug, Error)]
+#[error("malformed HTTP/1 request: {0}")]
+pub struct MalformedRequest(pub Malformation);
+
+/// Configures how malformed HTTP/1 requests are handled by target port.
+///
+/// Ports without a policy are not inspected, so malformed requests fail to be
+/// parsed by the server.
+#[derive(Clone, Debug, Default)]
+pub struct Policies(Arc<HashMap<u16, Policy>>);
+
+/// Counts malformed requests by port, malformation, and policy.
+#[derive(Clone, Debug)]
+pub struct Metrics {
+    metric: Metric<'static, &'static str, Counter>,
+    by_target: Arc<Mutex<HashMap<Labels, Arc<Counter>>>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct NewNormalizeMalformed<N> {
+    inner: N,
+    policies: Policies,
+    metrics: Metrics,
+}
+
+#[derive(Clone, Debug)]
+pub struct NormalizeMalformed<S> {
+    inner: S,
+    config: Option<Config>,
+}
+
+/// Wraps a server's IO so that the request heads it reads are inspected for
+/// malformations.
+#[pin_project]
+#[derive(Debug)]
+pub struct NormalizeIo<I> {
+    #[pin]
+    io: I,
+    scan: Option<Scan>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct Labels {
+    port: u16,
+    malformation: Malformation,
+    policy: Policy,
+}
+
+#[derive(Clone, Debug)]
+struct Config {
+    policy: Policy,
+    port: u16,
+    metrics: Metrics,
+}
+
+#[derive(Debug)]
+struct Scan {
+    config: Config,
+    state: State,
+
+    /// Bytes read from the connection that have not yet been scanned.
+    read: BytesMut,
+
+    /// Scanned bytes that may be returned to the server.
+    ready: BytesMut,
+    eof: bool,
+
+    /// The scanned bytes of the current request head.
+    head: BytesMut,
+
+    /// The length of the current line of the request head, excluding line
+    /// endings.
+    line_len: usize,
+    request_line: bool,
+    obs_fold: bool,
+    bare_cr: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+    Head,
+
+    /// The given number of body bytes are returned without being scanned.
+    Body(u64),
+
+    /// The remainder of the connection is not scanned, e.g. because its
+    /// request bodies are chunked or because it has been upgraded.
+    Passthrough,
+}
+
+const READ_CAPACITY: usize = 8 * 1024;
+
+/// Request heads larger than this are not scanned and are left for the server
+/// to reject.
+const MAX_HEAD_LEN: usize = 64 * 1024;
+
+/// Matches Hyper's limit on the number of request headers.
+const MAX_HEADERS: usize = 100;
+
+// === impl Policy ===
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Normalize => "normalize".fmt(f),
+            Self::Reject => "reject".fmt(f),
+        }
+    }
+}
+
+// === impl Malformation ===
+
+impl Malformation {
+    fn as_label(&self) -> &'static str {
+        match self {
+            Self::ObsFold => "obs_fold",
+            Self::BareCr => "bare_cr",
+        }
+    }
+}
+
+impl fmt::Display for Malformation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ObsFold => "obsolete line folding".fmt(f),
+            Self::BareCr => "bare carriage return".fmt(f),
+        }
+    }
+}
+
+// === impl Policies ===
+
+impl Policies {
+    pub fn new(policies: impl IntoIterator<Item = (u16, Policy)>) -> Self {
+        Self(Arc::new(policies.into_iter().collect()))
+    }
+
+    fn get(&self, port: u16) -> Option<Policy> {
+        self.0.get(&port).copied()
+    }
+}
+
+// === impl Metrics ===
+
+impl Metrics {
+    pub fn new(metric: Metric<'static, &'static str, Counter>) -> Self {
+        Self {
+            metric,
+            by_target: Default::default(),
+        }
+    }
+
+    fn counter(&self, port: u16, malformation: Malformation, policy: Policy) -> Arc<Counter> {
+        self.by_target
+            .lock()
+            .entry(Labels {
+                port,
+                malformation,
+                policy,
+            })
+            .or_default()
+            .clone()
+    }
+}
+
+impl FmtMetrics for Metrics {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let by_target = self.by_target.lock();
+        if by_target.is_empty() {
+            return Ok(());
+        }
+
+        self.metric.fmt_help(f)?;
+        for (labels, counter) in by_target.iter() {
+            self.metric.fmt_metric_labeled(f, counter, labels)?;
+        }
+        Ok(())
+    }
+}
+
+// === impl Labels ===
+
+impl FmtLabels for Labels {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "target_port=\"{}\",malformation=\"{}\",policy=\"{}\"",
+            self.port,
+            self.malformation.as_label(),
+            self.policy
+        )
+    }
+}
+
+// === impl NewNormalizeMalformed ===
+
+impl<N> NewNormalizeMalformed<N> {
+    pub fn layer(
+        policies: Policies,
+        metrics: Metrics,
+    ) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            inner,
+            policies: policies.clone(),
+            metrics: metrics.clone(),
+        })
+    }
+}
+
+impl<T, N> NewService<T> for NewNormalizeMalformed<N>
+where
+    T: Param<Version> + Param<u16>,
+    N: NewService<T>,
+{
+    type Service = NormalizeMalformed<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let config = match target.param() {
+            Version::Http1 => {
+                let port: u16 = target.param();
+                self.policies.get(port).map(|policy| Config {
+                    policy,
+                    port,
+                    metrics: self.metrics.clone(),
+                })
+            }
+            Version::H2 => None,
+        };
+        NormalizeMalformed {
+            config,
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl NormalizeMalformed ===
+
+impl<I, S> tower::Service<I> for NormalizeMalformed<S>
+where
+    S: tower::Service<NormalizeIo<I>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, io: I) -> Self::Future {
+        let scan = self.config.clone().map(Scan::new);
+        self.inner.call(NormalizeIo { io, scan })
+    }
+}
+
+// === impl NormalizeIo ===
+
+impl<I: io::PeerAddr> io::PeerAddr for NormalizeIo<I> {
+    #[inline]
+    fn peer_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.io.peer_addr()
+    }
+}
+
+impl<I: io::AsyncRead> io::AsyncRead for NormalizeIo<I> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut io::ReadBuf<'_>,
+    ) -> io::Poll<()> {
+        let mut this = self.project();
+        let scan = match this.scan {
+            Some(scan) => scan,
+            None => return this.io.poll_read(cx, buf),
+        };
+
+        loop {
+            if !scan.ready.is_empty() {
+                let len = buf.remaining().min(scan.ready.len());
+                buf.put_slice(&scan.ready[..len]);
+                scan.ready.advance(len);
+                return Poll::Ready(Ok(()));
+            }
+
+            if scan.eof {
+                return Poll::Ready(Ok(()));
+            }
+
+            if scan.state == State::Passthrough && scan.read.is_empty() {
+                return this.io.poll_read(cx, buf);
+            }
+
+            scan.read.reserve(READ_CAPACITY);
+            let sz = futures::ready!(io::poll_read_buf(this.io.as_mut(), cx, &mut scan.read))?;
+            scan.eof = sz == 0;
+            scan.scan().map_err(|malformation| {
+                io::Error::new(io::ErrorKind::InvalidData, MalformedRequest(malformation))
+            })?;
+        }
+    }
+}
+
+impl<I: io::AsyncWrite> io::AsyncWrite for NormalizeIo<I> {
+    #[inline]
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Poll<()> {
+        self.project().io.poll_shutdown(cx)
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Poll<()> {
+        self.project().io.poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> io::Poll<usize> {
+        self.project().io.poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> io::Poll<usize> {
+        self.project().io.poll_write_vectored(cx, bufs)
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+}
+
+// === impl Scan ===
+
+impl Scan {
+    fn new(config: Config) -> Self {
+        Self {
+            config,
+            state: State::Head,
+            read: BytesMut::new(),
+            ready: BytesMut::new(),
+            eof: false,
+            head: BytesMut::new(),
+            line_len: 0,
+            request_line: true,
+            obs_fold: false,
+            bare_cr: false,
+        }
+    }
+
+    /// Scans all bytes that have been read, moving them to the `ready` buffer
+    /// unless more bytes are needed to determine whether they're malformed.
+    fn scan(&mut self) -> Result<(), Malformation> {
+        loop {
+            match self.state {
+                State::Passthrough => {
+                    let read = self.read.split();
+                    self.ready.extend_from_slice(&read);
+                    return Ok(());
+                }
+
+                State::Body(remaining) => {
+                    if self.read.is_empty() {
+                        return Ok(());
+                    }
+                    let len = remaining.min(self.read.len() as u64);
+                    let body = self.read.split_to(len as usize);
+                    self.ready.extend_from_slice(&body);
+                    self.state = match remaining - len {
+                        0 => State::Head,
+                        remaining => State::Body(remaining),
+                    };
+                }
+
+                State::Head => {
+                    if !self.scan_head()? {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Scans the current request head, returning true once it is complete.
+    fn scan_head(&mut self) -> Result<bool, Malformation> {
+        let mut i = 0;
+        let complete = loop {
+            let eol = match self.read.get(i) {
+                None => break false,
+                Some(b'\n') => 1,
+                Some(b'\r') => match self.read.get(i + 1) {
+                    Some(b'\n') => 2,
+                    None if !self.eof => break false,
+                    _ => {
+                        self.malformed(Malformation::BareCr)?;
+                        self.read[i] = b' ';
+                        self.line_len += 1;
+                        i += 1;
+                        continue;
+                    }
+                },
+                Some(_) => {
+                    self.line_len += 1;
+                    i += 1;
+                    continue;
+                }
+            };
+
+            if self.line_len == 0 {
+                i += eol;
+                // Empty lines preceding the request line are ignored.
+                if self.request_line {
+                    continue;
+                }
+                break true;
+            }
+
+            let next = i + eol;
+            match self.read.get(next) {
+                Some(b' ') | Some(b'\t') if !self.request_line => {
+                    self.malformed(Malformation::ObsFold)?;
+                    for b in &mut self.read[i..next] {
+                        *b = b' ';
+                    }
+                    self.line_len += eol;
+                }
+                None if !self.eof => break false,
+                _ => {
+                    self.request_line = false;
+                    self.line_len = 0;
+                }
+            }
+            i = next;
+        };
+
+        let scanned = self.read.split_to(i);
+        self.head.extend_from_slice(&scanned);
+        self.ready.extend_from_slice(&scanned);
+
+        if complete {
+            self.record_normalized();
+            self.state = self.framing();
+            self.head.clear();
+            self.request_line = true;
+        } else if self.head.len() > MAX_HEAD_LEN {
+            debug!(len = self.head.len(), "Request head too large to scan");
+            self.state = State::Passthrough;
+        }
+        Ok(complete)
+    }
+
+    fn malformed(&mut self, malformation: Malformation) -> Result<(), Malformation> {
+        match self.config.policy {
+            Policy::Normalize => {
+                match malformation {
+                    Malformation::ObsFold => self.obs_fold = true,
+                    Malformation::BareCr => self.bare_cr = true,
+                }
+                Ok(())
+            }
+            Policy::Reject => {
+                debug!(%malformation, "Rejecting malformed request");
+                self.count(malformation);
+                Err(malformation)
+            }
+        }
+    }
+
+    /// Counts each malformation once per normalized request.
+    fn record_normalized(&mut self) {
+        if std::mem::take(&mut self.obs_fold) {
+            debug!("Normalized obsolete line folding");
+            self.count(Malformation::ObsFold);
+        }
+        if std::mem::take(&mut self.bare_cr) {
+            debug!("Normalized bare carriage return");
+            self.count(Malformation::BareCr);
+        }
+    }
+
+    fn count(&self, malformation: Malformation) {
+        let Config {
+            ref metrics,
+            port,
+            policy,
+        } = self.config;
+        metrics.counter(port, malformation, policy).incr();
+    }
+
+    /// Determines how the bytes following a complete request head are framed.
+    fn framing(&self) -> State {
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut req = httparse::Request::new(&mut headers);
+        match req.parse(&self.head) {
+            Ok(httparse::Status::Complete(_)) => {}
+            // The server fails to parse the request, so the connection isn't
+            // scanned further.
+            _ => return State::Passthrough,
+        }
+
+        if req.method == Some("CONNECT") {
+            return State::Passthrough;
+        }
+
+        let mut len = 0;
+        for header in req.headers.iter() {
+            if header.name.eq_ignore_ascii_case("transfer-encoding")
+                || header.name.eq_ignore_ascii_case("upgrade")
+            {
+                return State::Passthrough;
+            }
+            if header.name.eq_ignore_ascii_case("content-length") {
+                match std::str::from_utf8(header.value)
+                    .ok()
+                    .and_then(|v| v.trim().parse::<u64>().ok())
+                {
+                    Some(n) => len = n,
+                    None => return State::Passthrough,
+                }
+            }
+        }
+
+        match len {
+            0 => State::Head,
+            len => State::Body(len),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_io::AsyncReadExt;
+    use linkerd_metrics::metrics;
+
+    metrics! {
+        test_malformed_requests_total: Counter { "Test malformed requests" }
+    }
+
+    fn normalize_io(
+        policy: Policy,
+        reads: &[&[u8]],
+    ) -> (NormalizeIo<tokio_test::io::Mock>, Metrics) {
+        let metrics = Metrics::new(test_malformed_requests_total);
+        let mut io = tokio_test::io::Builder::new();
+        for read in reads {
+            io.read(read);
+        }
+        let scan = Scan::new(Config {
+            policy,
+            port: 8080,
+            metrics: metrics.clone(),
+        });
+        let io = NormalizeIo {
+            io: io.build(),
+            scan: Some(scan),
+        };
+        (io, metrics)
+    }
+
+    fn count(metrics: &Metrics, malformation: Malformation, policy: Policy) -> u64 {
+        u64::from(&*metrics.counter(8080, malformation, policy))
+    }
+
+    #[tokio::test]
+    async fn normalizes_obs_fold() {
+        let (mut io, metrics) = normalize_io(
+            Policy::Normalize,
+            &[
+                b"GET / HTTP/1.1\r\nx-folded: a\r\n",
+                b"\tb\r\nhost: ex\rample\r\n\r\n",
+            ],
+        );
+        let mut buf = Vec::new();
+        io.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(
+            buf,
+            b"GET / HTTP/1.1\r\nx-folded: a  \tb\r\nhost: ex ample\r\n\r\n"
+        );
+        assert_eq!(count(&metrics, Malformation::ObsFold, Policy::Normalize), 1);
+        assert_eq!(count(&metrics, Malformation::BareCr, Policy::Normalize), 1);
+    }
+
+    #[tokio::test]
+    async fn rejects_obs_fold() {
+        let (mut io, metrics) = normalize_io(
+            Policy::Reject,
+            &[b"GET / HTTP/1.1\r\nx-folded: a\r\n b\r\n\r\n"],
+        );
+        let mut buf = Vec::new();
+        let err = io.read_to_end(&mut buf).await.expect_err("must fail");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(
+            err.get_ref()
+                .and_then(|e| e.downcast_ref::<MalformedRequest>()),
+            Some(MalformedRequest(Malformation::ObsFold))
+        ));
+        assert_eq!(count(&metrics, Malformation::ObsFold, Policy::Reject), 1);
+    }
+
+    #[tokio::test]
+    async fn bodies_not_scanned() {
+        let (mut io, metrics) = normalize_io(
+            Policy::Reject,
+            &[
+                b"POST / HTTP/1.1\r\ncontent-length: 5\r\n\r\na\r",
+                b" \rb",
+                b"GET / HTTP/1.1\r\nupgrade: websocket\r\n\r\n\r \r",
+            ],
+        );
+        let mut buf = Vec::new();
+        io.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(
+            buf,
+            b"POST / HTTP/1.1\r\ncontent-length: 5\r\n\r\na\r \rbGET / HTTP/1.1\r\nupgrade: websocket\r\n\r\n\r \r"
+        );
+        assert_eq!(count(&metrics, Malformation::BareCr, Policy::Reject), 0);
+    }
+}
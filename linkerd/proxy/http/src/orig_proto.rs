@@ -1,6 +1,6 @@
 use super::{glue::UpgradeBody, h1, h2, upgrade};
 use futures::{future, prelude::*};
-use http::header::{HeaderValue, TRANSFER_ENCODING};
+use http::header::{HeaderValue, CONNECTION, TRANSFER_ENCODING, UPGRADE};
 use linkerd_error::Error;
 use linkerd_stack::layer;
 use std::{
@@ -12,7 +12,13 @@ use tracing::{debug, trace, warn};
 
 pub const L5D_ORIG_PROTO: &str = "l5d-orig-proto";
 
+const WEBSOCKET: &str = "websocket";
+
 /// Upgrades HTTP requests from their original protocol to HTTP2.
+///
+/// HTTP/1.1 WebSocket upgrades are sent as extended CONNECT (RFC 8441)
+/// requests when the server supports it; all other HTTP/1.1 upgrades are
+/// sent over HTTP/1.1.
 #[derive(Debug)]
 pub struct Upgrade<C, T, B> {
     http1: h1::Client<C, T, B>,
@@ -24,8 +30,14 @@ pub struct Upgrade<C, T, B> {
 #[derive(Clone, Debug)]
 pub struct Downgrade<S> {
     inner: S,
+    /// Watches the upgraded connections of extended CONNECT requests.
+    upgrade_drain_signal: drain::Watch,
 }
 
+type UpgradeFuture = Pin<
+    Box<dyn Future<Output = Result<http::Response<UpgradeBody>, hyper::Error>> + Send + 'static>,
+>;
+
 // ==== impl Upgrade =====
 
 impl<C, T, B> Upgrade<C, T, B> {
@@ -47,11 +59,7 @@ where
 {
     type Response = http::Response<UpgradeBody>;
     type Error = hyper::Error;
-    type Future = Pin<
-        Box<
-            dyn Future<Output = Result<http::Response<UpgradeBody>, hyper::Error>> + Send + 'static,
-        >,
-    >;
+    type Future = UpgradeFuture;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.h2.poll_ready(cx)
@@ -59,30 +67,21 @@ where
 
     fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
         debug_assert!(req.version() != http::Version::HTTP_2);
-        if req.extensions().get::<upgrade::Http11Upgrade>().is_some() {
+        if let Some(upgrade) = req.extensions_mut().remove::<upgrade::Http11Upgrade>() {
+            // Extended CONNECT requests must include an authority.
+            if is_websocket(&req)
+                && req.uri().authority().is_some()
+                && self.h2.is_extended_connect_enabled()
+            {
+                return self.extended_connect(req, upgrade);
+            }
             debug!("Skipping orig-proto upgrade due to HTTP/1.1 upgrade");
+            req.extensions_mut().insert(upgrade);
             return self.http1.request(req);
         }
 
         let orig_version = req.version();
-        let absolute_form = req
-            .extensions_mut()
-            .remove::<h1::WasAbsoluteForm>()
-            .is_some();
-        debug!(version = ?orig_version, absolute_form, "Upgrading request");
-
-        // absolute-form is far less common, origin-form is the usual,
-        // so only encode the extra information if it's different than
-        // the normal.
-        let header = match (orig_version, absolute_form) {
-            (http::Version::HTTP_11, false) => "HTTP/1.1",
-            (http::Version::HTTP_11, true) => "HTTP/1.1; absolute-form",
-            (http::Version::HTTP_10, false) => "HTTP/1.0",
-            (http::Version::HTTP_10, true) => "HTTP/1.0; absolute-form",
-            (v, _) => unreachable!("bad orig-proto version: {:?}", v),
-        };
-        req.headers_mut()
-            .insert(L5D_ORIG_PROTO, HeaderValue::from_static(header));
+        set_orig_proto(&mut req);
 
         // transfer-encoding is illegal in HTTP2
         req.headers_mut().remove(TRANSFER_ENCODING);
@@ -110,11 +109,125 @@ where
     }
 }
 
+impl<C, T, B> Upgrade<C, T, B>
+where
+    B: hyper::body::HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Error> + Send + Sync,
+{
+    /// Sends an HTTP/1.1 WebSocket upgrade as an HTTP/2 extended CONNECT
+    /// request so that the upgraded connection is carried on an HTTP/2
+    /// stream.
+    fn extended_connect(
+        &mut self,
+        mut req: http::Request<B>,
+        upgrade: upgrade::Http11Upgrade,
+    ) -> UpgradeFuture {
+        debug!("Upgrading WebSocket request to extended CONNECT");
+        set_orig_proto(&mut req);
+
+        // Extended CONNECT requests must include a scheme.
+        let mut uri = std::mem::take(req.uri_mut()).into_parts();
+        if uri.scheme.is_none() {
+            uri.scheme = Some(http::uri::Scheme::HTTP);
+        }
+        *req.uri_mut() = http::Uri::from_parts(uri).expect("URI must be valid");
+
+        *req.method_mut() = http::Method::CONNECT;
+        req.extensions_mut()
+            .insert(hyper::ext::Protocol::from_static(WEBSOCKET));
+        // Connection-level headers are illegal in HTTP2.
+        req.headers_mut().remove(CONNECTION);
+        req.headers_mut().remove(UPGRADE);
+        *req.version_mut() = http::Version::HTTP_2;
+
+        let call = tower::Service::call(&mut self.h2, req);
+        Box::pin(call.map_ok(move |mut rsp| {
+            rsp.headers_mut().remove(L5D_ORIG_PROTO);
+            *rsp.version_mut() = http::Version::HTTP_11;
+            if rsp.status().is_success() {
+                trace!("Extended CONNECT succeeded; switching protocols");
+                *rsp.status_mut() = http::StatusCode::SWITCHING_PROTOCOLS;
+                rsp.headers_mut()
+                    .insert(CONNECTION, HeaderValue::from_static("upgrade"));
+                rsp.headers_mut()
+                    .insert(UPGRADE, HeaderValue::from_static(WEBSOCKET));
+                upgrade.insert_half(hyper::upgrade::on(&mut rsp));
+            }
+            rsp.map(UpgradeBody::from)
+        }))
+    }
+}
+
+/// Records the request's original protocol in the `l5d-orig-proto` header.
+fn set_orig_proto<B>(req: &mut http::Request<B>) {
+    let orig_version = req.version();
+    let absolute_form = req
+        .extensions_mut()
+        .remove::<h1::WasAbsoluteForm>()
+        .is_some();
+    debug!(version = ?orig_version, absolute_form, "Upgrading request");
+
+    // absolute-form is far less common, origin-form is the usual,
+    // so only encode the extra information if it's different than
+    // the normal.
+    let header = match (orig_version, absolute_form) {
+        (http::Version::HTTP_11, false) => "HTTP/1.1",
+        (http::Version::HTTP_11, true) => "HTTP/1.1; absolute-form",
+        (http::Version::HTTP_10, false) => "HTTP/1.0",
+        (http::Version::HTTP_10, true) => "HTTP/1.0; absolute-form",
+        (v, _) => unreachable!("bad orig-proto version: {:?}", v),
+    };
+    req.headers_mut()
+        .insert(L5D_ORIG_PROTO, HeaderValue::from_static(header));
+}
+
 // ===== impl Downgrade =====
 
 impl<S> Downgrade<S> {
-    pub fn layer() -> impl layer::Layer<S, Service = Self> + Copy + Clone {
-        layer::mk(|inner| Self { inner })
+    pub fn layer(
+        upgrade_drain_signal: drain::Watch,
+    ) -> impl layer::Layer<S, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            inner,
+            upgrade_drain_signal: upgrade_drain_signal.clone(),
+        })
+    }
+
+    /// Translates an extended CONNECT request into the HTTP/1.1 upgrade
+    /// request from which it originated, returning false if the request is
+    /// not an extended CONNECT.
+    fn downgrade_extended_connect<A>(&self, req: &mut http::Request<A>) -> bool {
+        if req.method() != http::Method::CONNECT {
+            return false;
+        }
+        let protocol = match req.extensions_mut().remove::<hyper::ext::Protocol>() {
+            Some(protocol) => protocol,
+            None => return false,
+        };
+        let upgrade = match HeaderValue::from_str(protocol.as_str()) {
+            Ok(upgrade) => upgrade,
+            Err(_) => {
+                warn!(?protocol, "Invalid extended CONNECT protocol");
+                return false;
+            }
+        };
+        debug!(
+            ?protocol,
+            "Translating extended CONNECT to HTTP/1.1 upgrade"
+        );
+
+        *req.method_mut() = http::Method::GET;
+        req.headers_mut()
+            .insert(CONNECTION, HeaderValue::from_static("upgrade"));
+        req.headers_mut().insert(UPGRADE, upgrade);
+
+        // The HTTP/2 stream is upgraded once the response is sent, and it's
+        // joined with the client's HTTP/1.1 upgrade.
+        let halves = upgrade::Http11Upgrade::halves(self.upgrade_drain_signal.clone());
+        halves.server.insert_half(hyper::upgrade::on(&mut *req));
+        req.extensions_mut().insert(halves.client);
+        true
     }
 }
 
@@ -134,6 +247,7 @@ where
 
     fn call(&mut self, mut req: http::Request<A>) -> Self::Future {
         let mut upgrade_response = false;
+        let mut extended_connect = false;
 
         if req.version() == http::Version::HTTP_2 {
             if let Some(orig_proto) = req.headers_mut().remove(L5D_ORIG_PROTO) {
@@ -153,12 +267,15 @@ where
                     req.extensions_mut().insert(h1::WasAbsoluteForm(()));
                 }
                 upgrade_response = true;
+                extended_connect = self.downgrade_extended_connect(&mut req);
             }
         }
 
         let fut = self.inner.call(req);
 
-        if upgrade_response {
+        if extended_connect {
+            fut.map_ok(extended_connect_response)
+        } else if upgrade_response {
             fut.map_ok(|mut res| {
                 let orig_proto = match res.version() {
                     http::Version::HTTP_11 => "HTTP/1.1",
@@ -181,6 +298,158 @@ where
     }
 }
 
+/// Translates the response to a downgraded extended CONNECT request so that
+/// a successful HTTP/1.1 upgrade establishes the HTTP/2 tunnel.
+fn extended_connect_response<B>(mut res: http::Response<B>) -> http::Response<B> {
+    if res.status() == http::StatusCode::SWITCHING_PROTOCOLS {
+        trace!("Upgrade succeeded; accepting extended CONNECT");
+        *res.status_mut() = http::StatusCode::OK;
+        res.headers_mut().remove(CONNECTION);
+        res.headers_mut().remove(UPGRADE);
+    }
+
+    res.headers_mut()
+        .insert(L5D_ORIG_PROTO, HeaderValue::from_static("HTTP/1.1"));
+    *res.version_mut() = http::Version::HTTP_2;
+    res
+}
+
+/// Checks whether an HTTP/1.1 request is a WebSocket upgrade.
+fn is_websocket<B>(req: &http::Request<B>) -> bool {
+    req.method() == http::Method::GET
+        && req.version() == http::Version::HTTP_11
+        && req
+            .headers()
+            .get(UPGRADE)
+            .map(|v| v.as_bytes().eq_ignore_ascii_case(WEBSOCKET.as_bytes()))
+            .unwrap_or(false)
+}
+
 fn was_absolute_form(val: &[u8]) -> bool {
     val.len() >= "HTTP/1.1; absolute-form".len() && &val[10..23] == b"absolute-form"
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_io::{self as io, AsyncReadExt, AsyncWriteExt};
+    use linkerd_stack::layer::Layer;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    const POOL: h1::PoolSettings = h1::PoolSettings {
+        max_idle: 1,
+        idle_timeout: Duration::from_secs(1),
+    };
+
+    /// Tests that a WebSocket upgrade is carried between proxies as an
+    /// extended CONNECT request and that the application's upgraded
+    /// connection is joined with the client's.
+    #[tokio::test(flavor = "current_thread")]
+    async fn websocket_round_trips_over_extended_connect() {
+        let _trace = linkerd_tracing::test::trace_init();
+        let (_drain_tx, drain) = drain::channel();
+
+        // The inbound proxy's HTTP/2 server downgrades extended CONNECT
+        // requests to HTTP/1.1 upgrades for the application.
+        let (h2_client_io, h2_server_io) = io::duplex(4096);
+        let mut app =
+            h1::Client::<_, (), hyper::Body>::new(tower::service_fn(connect_app), (), POOL);
+        let downgrade = Downgrade::layer(drain.clone()).layer(tower::service_fn(
+            move |req: http::Request<hyper::Body>| {
+                assert_eq!(req.method(), http::Method::GET);
+                assert_eq!(req.version(), http::Version::HTTP_11);
+                assert_eq!(req.headers()[UPGRADE], WEBSOCKET);
+                app.request(req)
+            },
+        ));
+        let mut h2_server = hyper::server::conn::Http::new();
+        h2_server.http2_only(true).http2_enable_connect_protocol();
+        tokio::spawn(h2_server.serve_connection(h2_server_io, downgrade));
+
+        // The outbound proxy sends the client's upgrade over HTTP/2.
+        let mut h2_client_io = Some(h2_client_io);
+        let h2 = h2::Connect::new(
+            tower::service_fn(move |_: ()| {
+                future::ok::<_, Error>(h2_client_io.take().expect("must only connect once"))
+            }),
+            h2::Settings::default(),
+        )
+        .oneshot(())
+        .await
+        .expect("must connect");
+        // The server's settings are received as the connection is driven.
+        while !h2.is_extended_connect_enabled() {
+            tokio::task::yield_now().await;
+        }
+        let upgrade = Upgrade::new(h1::Client::new(tower::service_fn(no_connect), (), POOL), h2);
+        let (client_io, server_io) = io::duplex(4096);
+        let mut h1_server = hyper::server::conn::Http::new();
+        h1_server.http1_only(true);
+        tokio::spawn(
+            h1_server
+                .serve_connection(server_io, upgrade::Service::new(upgrade, drain))
+                .with_upgrades(),
+        );
+
+        let (mut client, conn) = hyper::client::conn::handshake(client_io).await.unwrap();
+        tokio::spawn(conn);
+        let req = http::Request::builder()
+            .uri("http://app.example.com/chat")
+            .header(http::header::HOST, "app.example.com")
+            .header(CONNECTION, "upgrade")
+            .header(UPGRADE, WEBSOCKET)
+            .body(hyper::Body::empty())
+            .unwrap();
+        let mut rsp = client
+            .ready()
+            .await
+            .unwrap()
+            .send_request(req)
+            .await
+            .unwrap();
+        assert_eq!(rsp.status(), http::StatusCode::SWITCHING_PROTOCOLS);
+        assert_eq!(rsp.headers()[UPGRADE], WEBSOCKET);
+        assert!(rsp.headers().get(L5D_ORIG_PROTO).is_none());
+
+        let mut upgraded = hyper::upgrade::on(&mut rsp).await.expect("must upgrade");
+        upgraded.write_all(b"hello").await.unwrap();
+        let mut buf = [0u8; 5];
+        upgraded.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello", "the application must echo over the tunnel");
+    }
+
+    /// Connects to an application that accepts WebSocket upgrades and echoes
+    /// the upgraded connection.
+    fn connect_app(_: ()) -> future::Ready<Result<io::DuplexStream, Error>> {
+        let (client_io, server_io) = io::duplex(4096);
+        let app = tower::service_fn(|mut req: http::Request<hyper::Body>| {
+            tokio::spawn(async move {
+                let mut io = hyper::upgrade::on(&mut req).await.expect("must upgrade");
+                let mut buf = [0u8; 5];
+                io.read_exact(&mut buf).await.unwrap();
+                io.write_all(&buf).await.unwrap();
+            });
+            let rsp = http::Response::builder()
+                .status(http::StatusCode::SWITCHING_PROTOCOLS)
+                .header(CONNECTION, "upgrade")
+                .header(UPGRADE, WEBSOCKET)
+                .body(hyper::Body::empty())
+                .unwrap();
+            future::ok::<_, hyper::Error>(rsp)
+        });
+        tokio::spawn(
+            hyper::server::conn::Http::new()
+                .http1_only(true)
+                .serve_connection(server_io, app)
+                .with_upgrades(),
+        );
+        future::ok(client_io)
+    }
+
+    /// Fails to connect, since WebSocket upgrades must not fall back to
+    /// HTTP/1.1 when the server supports extended CONNECT.
+    fn no_connect(_: ()) -> future::Ready<Result<io::DuplexStream, Error>> {
+        future::err(io::Error::from(io::ErrorKind::ConnectionRefused).into())
+    }
+}
@@ -0,0 +1,207 @@
+//! Retires HTTP/2 client connections after a maximum age or number of
+//! requests.
+//!
+//! Long-lived HTTP/2 connections otherwise pin traffic to the endpoint chosen
+//! when the connection was established. When a connection is retired, it is
+//! dropped so that it is closed gracefully (with a GOAWAY) once its in-flight
+//! requests complete, and a new client is built for subsequent requests. The
+//! service is not ready while the new connection is established, so requests
+//! may be balanced to other endpoints in the meantime.
+
+use crate::{client, h2};
+use linkerd_stack::{layer, NewService, Param};
+use std::{
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::Instant;
+use tracing::debug;
+
+#[derive(Clone, Debug)]
+pub struct NewRetire<N> {
+    inner: N,
+    max_age: Option<Duration>,
+    max_requests: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct Retire<T, N: NewService<T>> {
+    target: T,
+    new_inner: N,
+    inner: N::Service,
+    limits: Option<Limits>,
+    created_at: Instant,
+    requests: usize,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Limits {
+    max_age: Option<Duration>,
+    max_requests: Option<usize>,
+}
+
+// === impl NewRetire ===
+
+impl<N> NewRetire<N> {
+    pub fn layer(settings: h2::Settings) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            inner,
+            max_age: settings.max_connection_age,
+            max_requests: settings.max_connection_requests,
+        })
+    }
+}
+
+impl<T, N> NewService<T> for NewRetire<N>
+where
+    T: Param<client::Settings> + Clone,
+    N: NewService<T> + Clone,
+{
+    type Service = Retire<T, N>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        // HTTP/1 clients maintain a pool of connections, so only clients that
+        // use an HTTP/2 connection are retired.
+        let limits = match target.param() {
+            client::Settings::Http1 => None,
+            client::Settings::H2 | client::Settings::OrigProtoUpgrade => {
+                if self.max_age.is_none() && self.max_requests.is_none() {
+                    None
+                } else {
+                    Some(Limits {
+                        max_age: self.max_age,
+                        max_requests: self.max_requests,
+                    })
+                }
+            }
+        };
+
+        Retire {
+            inner: self.inner.new_service(target.clone()),
+            new_inner: self.inner.clone(),
+            target,
+            limits,
+            created_at: Instant::now(),
+            requests: 0,
+        }
+    }
+}
+
+// === impl Retire ===
+
+impl<T, N: NewService<T>> Retire<T, N> {
+    fn is_expired(&self, limits: Limits) -> bool {
+        if let Some(max) = limits.max_requests {
+            if self.requests >= max {
+                return true;
+            }
+        }
+        if let Some(max) = limits.max_age {
+            if self.created_at.elapsed() >= max {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl<T, N, S, Req> tower::Service<Req> for Retire<T, N>
+where
+    T: Clone,
+    N: NewService<T, Service = S>,
+    S: tower::Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if let Some(limits) = self.limits {
+            if self.is_expired(limits) {
+                debug!(requests = self.requests, "Retiring connection");
+                self.inner = self.new_inner.new_service(self.target.clone());
+                self.created_at = Instant::now();
+                self.requests = 0;
+            }
+        }
+
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.requests += 1;
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_stack::layer::Layer;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use tower::{Service, ServiceExt};
+
+    #[derive(Clone)]
+    struct Target(client::Settings);
+
+    impl Param<client::Settings> for Target {
+        fn param(&self) -> client::Settings {
+            self.0
+        }
+    }
+
+    /// Builds services that respond with the number of services built before
+    /// them.
+    fn new_counting(
+    ) -> impl NewService<Target, Service = tower::util::BoxService<(), usize, ()>> + Clone {
+        let built = Arc::new(AtomicUsize::new(0));
+        move |_: Target| {
+            let n = built.fetch_add(1, Ordering::SeqCst);
+            tower::util::BoxService::new(tower::service_fn(move |()| {
+                futures::future::ok::<_, ()>(n)
+            }))
+        }
+    }
+
+    fn settings(max_age: Option<Duration>, max_requests: Option<usize>) -> h2::Settings {
+        h2::Settings {
+            max_connection_age: max_age,
+            max_connection_requests: max_requests,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn retires_after_max_requests() {
+        let mut new_retire = NewRetire::layer(settings(None, Some(2))).layer(new_counting());
+
+        let mut svc = new_retire.new_service(Target(client::Settings::H2));
+        for expected in [0, 0, 1, 1, 2] {
+            assert_eq!(svc.ready().await.unwrap().call(()).await.unwrap(), expected);
+        }
+
+        // HTTP/1 clients are never retired.
+        let mut svc = new_retire.new_service(Target(client::Settings::Http1));
+        for _ in 0..3 {
+            assert_eq!(svc.ready().await.unwrap().call(()).await.unwrap(), 3);
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn retires_after_max_age() {
+        let max_age = Duration::from_secs(60);
+        let mut new_retire = NewRetire::layer(settings(Some(max_age), None)).layer(new_counting());
+
+        let mut svc = new_retire.new_service(Target(client::Settings::OrigProtoUpgrade));
+        assert_eq!(svc.ready().await.unwrap().call(()).await.unwrap(), 0);
+
+        tokio::time::sleep(max_age / 2).await;
+        assert_eq!(svc.ready().await.unwrap().call(()).await.unwrap(), 0);
+
+        tokio::time::sleep(max_age / 2).await;
+        assert_eq!(svc.ready().await.unwrap().call(()).await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,242 @@
+//! Limits how HTTP/1 server connections are reused by clients.
+//!
+//! HTTP/1 clients may hold connections open indefinitely, so they never
+//! observe changes (e.g. in routing) that only apply to new connections. A
+//! connection's responses are marked with `Connection: close` once it has
+//! served its maximum number of requests, or once the server begins draining,
+//! so that clients reconnect.
+
+use crate::h1::ServerSettings;
+use futures::prelude::*;
+use http::header::{HeaderValue, CONNECTION};
+use pin_project::pin_project;
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::{self, Instant};
+use tracing::debug;
+
+/// Tracks the requests on a single server connection.
+#[derive(Clone, Debug)]
+pub(crate) struct ConnReuse(Arc<State>);
+
+#[derive(Clone, Debug)]
+pub(crate) struct LimitReuse<S> {
+    inner: S,
+    reuse: ConnReuse,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub(crate) struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    in_flight: Option<InFlight>,
+    last: bool,
+}
+
+#[derive(Debug)]
+struct State {
+    max_requests: Option<usize>,
+    requests: AtomicUsize,
+    in_flight: AtomicUsize,
+    closing: AtomicBool,
+    last_active: Mutex<Instant>,
+}
+
+/// Marks a request as in flight until it is dropped.
+#[derive(Debug)]
+struct InFlight(ConnReuse);
+
+// === impl ConnReuse ===
+
+impl ConnReuse {
+    pub(crate) fn new(settings: &ServerSettings) -> Self {
+        Self(Arc::new(State {
+            max_requests: settings.max_requests,
+            requests: AtomicUsize::new(0),
+            in_flight: AtomicUsize::new(0),
+            closing: AtomicBool::new(false),
+            last_active: Mutex::new(Instant::now()),
+        }))
+    }
+
+    /// Marks all subsequent responses on the connection with `Connection: close`.
+    pub(crate) fn close(&self) {
+        self.0.closing.store(true, Ordering::Release);
+    }
+
+    /// Completes once the connection has had no requests in flight for
+    /// `timeout`.
+    pub(crate) async fn idle(self, timeout: Duration) {
+        loop {
+            let deadline = if self.0.in_flight.load(Ordering::Acquire) == 0 {
+                self.last_active() + timeout
+            } else {
+                Instant::now() + timeout
+            };
+            time::sleep_until(deadline).await;
+
+            if self.0.in_flight.load(Ordering::Acquire) == 0
+                && self.last_active().elapsed() >= timeout
+            {
+                debug!(?timeout, "Connection is idle");
+                return;
+            }
+        }
+    }
+
+    fn last_active(&self) -> Instant {
+        *self.0.last_active.lock().expect("lock poisoned")
+    }
+
+    fn touch(&self) {
+        *self.0.last_active.lock().expect("lock poisoned") = Instant::now();
+    }
+}
+
+// === impl LimitReuse ===
+
+impl<S> LimitReuse<S> {
+    pub(crate) fn new(inner: S, reuse: ConnReuse) -> Self {
+        Self { inner, reuse }
+    }
+}
+
+impl<S, A, B> tower::Service<http::Request<A>> for LimitReuse<S>
+where
+    S: tower::Service<http::Request<A>, Response = http::Response<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<A>) -> Self::Future {
+        let state = &self.reuse.0;
+        let n = state.requests.fetch_add(1, Ordering::AcqRel) + 1;
+        let last = state.max_requests.map(|max| n >= max).unwrap_or(false);
+        if last {
+            debug!(requests = n, "Connection has reached its maximum requests");
+        }
+
+        state.in_flight.fetch_add(1, Ordering::AcqRel);
+        self.reuse.touch();
+        ResponseFuture {
+            inner: self.inner.call(req),
+            in_flight: Some(InFlight(self.reuse.clone())),
+            last,
+        }
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F, B, E> Future for ResponseFuture<F>
+where
+    F: TryFuture<Ok = http::Response<B>, Error = E>,
+{
+    type Output = Result<http::Response<B>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut rsp = futures::ready!(this.inner.try_poll(cx))?;
+
+        // The request is no longer in flight once its response is ready.
+        let closing = match this.in_flight.take() {
+            Some(InFlight(ref reuse)) => reuse.0.closing.load(Ordering::Acquire),
+            None => false,
+        };
+        // Upgraded connections are no longer HTTP/1, so they are not closed.
+        if (*this.last || closing) && rsp.status() != http::StatusCode::SWITCHING_PROTOCOLS {
+            rsp.headers_mut()
+                .insert(CONNECTION, HeaderValue::from_static("close"));
+        }
+
+        Poll::Ready(Ok(rsp))
+    }
+}
+
+// === impl InFlight ===
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let Self(reuse) = self;
+        reuse.touch();
+        reuse.0.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_test::{assert_pending, assert_ready, task};
+    use tower::{util::BoxService, Service, ServiceExt};
+
+    type Svc = LimitReuse<BoxService<http::Request<()>, http::Response<()>, ()>>;
+
+    fn svc(reuse: ConnReuse) -> Svc {
+        let ok = tower::service_fn(|_: http::Request<()>| future::ok(http::Response::new(())));
+        LimitReuse::new(BoxService::new(ok), reuse)
+    }
+
+    async fn send(svc: &mut Svc) -> http::Response<()> {
+        svc.ready()
+            .await
+            .unwrap()
+            .call(http::Request::new(()))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn max_requests() {
+        let mut svc = svc(ConnReuse::new(&ServerSettings {
+            max_requests: Some(2),
+            ..Default::default()
+        }));
+        assert!(send(&mut svc).await.headers().get(CONNECTION).is_none());
+        assert_eq!(send(&mut svc).await.headers()[CONNECTION], "close");
+    }
+
+    #[tokio::test]
+    async fn close_on_drain() {
+        let reuse = ConnReuse::new(&ServerSettings::default());
+        let mut svc = svc(reuse.clone());
+        assert!(send(&mut svc).await.headers().get(CONNECTION).is_none());
+        reuse.close();
+        assert_eq!(send(&mut svc).await.headers()[CONNECTION], "close");
+    }
+
+    #[tokio::test]
+    async fn idle() {
+        time::pause();
+        let timeout = Duration::from_secs(10);
+        let reuse = ConnReuse::new(&ServerSettings::default());
+        let mut idle = task::spawn(reuse.clone().idle(timeout));
+        assert_pending!(idle.poll());
+
+        // The connection is not idle while a request is in flight.
+        let in_flight = InFlight(reuse.clone());
+        reuse.0.in_flight.fetch_add(1, Ordering::AcqRel);
+        time::sleep(timeout * 2).await;
+        assert_pending!(idle.poll());
+
+        drop(in_flight);
+        time::sleep(timeout / 2).await;
+        assert_pending!(idle.poll());
+
+        time::sleep(timeout).await;
+        assert_ready!(idle.poll());
+    }
+}
@@ -2,27 +2,33 @@ use crate::{
     self as http,
     client_handle::SetClientHandle,
     glue::{HyperServerSvc, UpgradeBody},
+    h1::{PreserveHeaderCase, ServerSettings as H1Settings},
     h2::Settings as H2Settings,
+    insert,
+    reuse::{ConnReuse, LimitReuse},
     trace, upgrade, Version,
 };
 use linkerd_error::Error;
 use linkerd_io::{self as io, PeerAddr};
-use linkerd_stack::{layer, NewService, Param};
+use linkerd_stack::{layer, ExtractParam, NewService, Param};
 use std::{
     future::Future,
     pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
-use tower::Service;
+use tower::{Layer, Service};
 use tracing::debug;
 
 type Server = hyper::server::conn::Http<trace::Executor>;
 
 #[derive(Clone, Debug)]
-pub struct NewServeHttp<N> {
+pub struct NewServeHttp<X, N> {
     inner: N,
     server: Server,
     drain: drain::Watch,
+    h1: X,
+    max_connection_age: Option<Duration>,
 }
 
 #[derive(Clone, Debug)]
@@ -31,25 +37,35 @@ pub struct ServeHttp<S> {
     server: Server,
     inner: S,
     drain: drain::Watch,
+    h1: H1Settings,
+    max_connection_age: Option<Duration>,
 }
 
 // === impl NewServeHttp ===
 
-impl<N> NewServeHttp<N> {
+impl<X: Clone, N> NewServeHttp<X, N> {
+    /// HTTP/1 settings are extracted from each target with `h1`, so that they
+    /// may vary by target (e.g. by port). A fixed `H1Settings` applies to all
+    /// targets.
     pub fn layer(
+        h1: X,
         h2: H2Settings,
         drain: drain::Watch,
     ) -> impl layer::Layer<N, Service = Self> + Clone {
-        layer::mk(move |inner| Self::new(h2, inner, drain.clone()))
+        layer::mk(move |inner| Self::new(h1.clone(), h2, inner, drain.clone()))
     }
 
     /// Creates a new `ServeHttp`.
-    fn new(h2: H2Settings, inner: N, drain: drain::Watch) -> Self {
+    fn new(h1: X, h2: H2Settings, inner: N, drain: drain::Watch) -> Self {
         let mut server = hyper::server::conn::Http::new().with_executor(trace::Executor::new());
         server
             .http2_initial_stream_window_size(h2.initial_stream_window_size)
             .http2_initial_connection_window_size(h2.initial_connection_window_size);
 
+        if h2.enable_connect_protocol {
+            server.http2_enable_connect_protocol();
+        }
+
         // Configure HTTP/2 PING frames
         if let Some(timeout) = h2.keepalive_timeout {
             // XXX(eliza): is this a reasonable interval between
@@ -64,13 +80,16 @@ impl<N> NewServeHttp<N> {
             inner,
             server,
             drain,
+            h1,
+            max_connection_age: h2.max_connection_age,
         }
     }
 }
 
-impl<T, N> NewService<T> for NewServeHttp<N>
+impl<T, X, N> NewService<T> for NewServeHttp<X, N>
 where
     T: Param<Version>,
+    X: ExtractParam<H1Settings, T>,
     N: NewService<T> + Clone,
 {
     type Service = ServeHttp<N::Service>;
@@ -78,12 +97,15 @@ where
     fn new_service(&mut self, target: T) -> Self::Service {
         let version = target.param();
         debug!(?version, "Creating HTTP service");
+        let h1 = self.h1.extract_param(&target);
         let inner = self.inner.new_service(target);
         ServeHttp {
             inner,
             version,
             server: self.server.clone(),
             drain: self.drain.clone(),
+            h1,
+            max_connection_age: self.max_connection_age,
         }
     }
 }
@@ -114,6 +136,8 @@ where
             inner,
             drain,
             mut server,
+            h1,
+            max_connection_age,
         } = self.clone();
         debug!(?version, "Handling as HTTP");
 
@@ -122,11 +146,21 @@ where
 
             match version {
                 Version::Http1 => {
+                    let reuse = ConnReuse::new(&h1);
+                    let svc = LimitReuse::new(svc, reuse.clone());
+                    // Clients preserve the casing of response headers if the
+                    // server preserves the casing of request headers.
+                    let svc = insert::Layer::new(insert::ValLazy(PreserveHeaderCase(
+                        h1.preserve_header_case,
+                    )))
+                    .layer(svc);
                     // Enable support for HTTP upgrades (CONNECT and websockets).
                     let mut conn = server
                         .http1_only(true)
+                        .http1_preserve_header_case(h1.preserve_header_case)
                         .serve_connection(io, upgrade::Service::new(svc, drain.clone()))
                         .with_upgrades();
+                    let idle = reuse.clone().idle(h1.idle_timeout.unwrap_or_default());
                     tokio::select! {
                         res = &mut conn => {
                             debug!(?res, "The client is shutting down the connection");
@@ -134,6 +168,9 @@ where
                         }
                         shutdown = drain.signaled() => {
                             debug!("The process is shutting down the connection");
+                            if h1.close_on_drain {
+                                reuse.close();
+                            }
                             Pin::new(&mut conn).graceful_shutdown();
                             shutdown.release_after(conn).await?;
                         }
@@ -142,12 +179,26 @@ where
                             Pin::new(&mut conn).graceful_shutdown();
                             conn.await?;
                         }
+                        () = idle, if h1.idle_timeout.is_some() => {
+                            debug!("The connection has been idle");
+                            Pin::new(&mut conn).graceful_shutdown();
+                            conn.await?;
+                        }
                     }
                 }
                 Version::H2 => {
                     let mut conn = server
                         .http2_only(true)
                         .serve_connection(io, HyperServerSvc::new(svc));
+                    // When a maximum connection age is configured, clients are
+                    // sent a GOAWAY once it elapses so that they reconnect
+                    // (and re-resolve the proxy) periodically.
+                    let max_age = async move {
+                        match max_connection_age {
+                            Some(age) => tokio::time::sleep(age).await,
+                            None => futures::future::pending().await,
+                        }
+                    };
                     tokio::select! {
                         res = &mut conn => {
                             debug!(?res, "The client is shutting down the connection");
@@ -163,6 +214,11 @@ where
                             Pin::new(&mut conn).graceful_shutdown();
                             conn.await?;
                         }
+                        () = max_age => {
+                            debug!("The connection has reached its maximum age");
+                            Pin::new(&mut conn).graceful_shutdown();
+                            conn.await?;
+                        }
                     }
                 }
             }
@@ -170,3 +226,110 @@ where
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoxBody;
+    use futures::FutureExt;
+    use std::sync::Arc;
+    use tokio::{sync::Notify, time};
+
+    type Serve = tokio::task::JoinHandle<Result<(), Error>>;
+
+    /// Serves an HTTP/2 connection whose responses are held until the
+    /// returned `Notify` is notified.
+    fn serve_h2(
+        max_connection_age: Option<Duration>,
+    ) -> (io::DuplexStream, Arc<Notify>, drain::Signal, Serve) {
+        let release = Arc::new(Notify::new());
+        let svc = {
+            let release = release.clone();
+            tower::service_fn(move |_: http::Request<UpgradeBody>| {
+                let release = release.clone();
+                async move {
+                    release.notified().await;
+                    Ok::<_, Error>(http::Response::new(BoxBody::default()))
+                }
+            })
+        };
+        let h2 = H2Settings {
+            max_connection_age,
+            ..Default::default()
+        };
+        let (drain_tx, drain) = drain::channel();
+        let mut server = NewServeHttp::new(
+            H1Settings::default(),
+            h2,
+            move |_: Version| svc.clone(),
+            drain,
+        )
+        .new_service(Version::H2);
+
+        let (client_io, server_io) = io::duplex(4096);
+        let serve = tokio::spawn(server.call(server_io));
+        (client_io, release, drain_tx, serve)
+    }
+
+    fn get() -> http::Request<()> {
+        http::Request::get("http://example.com/").body(()).unwrap()
+    }
+
+    /// Tests that, once an HTTP/2 connection reaches its maximum age, the
+    /// server sends a GOAWAY so that the client stops using the connection,
+    /// while in-flight requests complete.
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn h2_max_connection_age_sends_goaway() {
+        const MAX_AGE: Duration = Duration::from_secs(10);
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let (client_io, release, _drain_tx, serve) = serve_h2(Some(MAX_AGE));
+        let (mut client, conn) = h2::client::handshake(client_io).await.unwrap();
+        let start = time::Instant::now();
+        let conn = tokio::spawn(conn);
+
+        let (rsp, _) = client.send_request(get(), true).unwrap();
+        time::sleep(MAX_AGE + Duration::from_secs(1)).await;
+
+        // The request was in flight when the connection reached its maximum
+        // age, so it completes.
+        release.notify_one();
+        let rsp = rsp.await.expect("in-flight request must complete");
+        assert_eq!(rsp.status(), http::StatusCode::OK);
+
+        // Once the GOAWAY has been received, the client's connection
+        // completes gracefully and it may not send new requests.
+        conn.await
+            .unwrap()
+            .expect("connection must close gracefully");
+        assert!(start.elapsed() >= MAX_AGE);
+        assert!(client.send_request(get(), true).is_err());
+        serve.await.unwrap().expect("server must close gracefully");
+    }
+
+    /// Tests that HTTP/2 connections are not closed when no maximum age is
+    /// configured.
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn h2_without_max_connection_age() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let (client_io, release, _drain_tx, serve) = serve_h2(None);
+        let (client, conn) = h2::client::handshake(client_io).await.unwrap();
+        let conn = tokio::spawn(conn);
+
+        let (rsp, _) = client.clone().send_request(get(), true).unwrap();
+        time::sleep(Duration::from_secs(60 * 60)).await;
+        release.notify_one();
+        let rsp = rsp.await.expect("request must complete");
+        assert_eq!(rsp.status(), http::StatusCode::OK);
+
+        // The connection remains usable.
+        let mut client = client.ready().await.expect("connection must be open");
+        let (rsp, _) = client.send_request(get(), true).unwrap();
+        release.notify_one();
+        let rsp = rsp.await.expect("request must complete");
+        assert_eq!(rsp.status(), http::StatusCode::OK);
+        assert!(conn.now_or_never().is_none(), "connection must not close");
+        assert!(serve.now_or_never().is_none(), "server must not close");
+    }
+}
@@ -1,15 +1,31 @@
 use futures::{ready, TryFuture};
-use linkerd_stack::NewService;
+use http_body::Body;
+use linkerd_error::Error;
+use linkerd_stack::{layer, NewService, Proxy};
 use linkerd_timeout::Timeout;
 use pin_project::pin_project;
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use std::time::Duration;
+use thiserror::Error;
+use tokio::time::{self, Sleep};
 
 /// Implement on targets to determine if a service has a timeout.
 pub trait HasTimeout {
+    /// The total time allowed for a response, including any retries.
     fn timeout(&self) -> Option<Duration>;
+
+    /// The time allowed for each individual attempt to produce a response.
+    fn per_try_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// The time allowed without progress on a response, i.e. while waiting for
+    /// its headers or between frames of its body.
+    fn idle_timeout(&self) -> Option<Duration> {
+        None
+    }
 }
 
 /// An HTTP-specific optional timeout layer.
@@ -93,3 +109,396 @@ impl<F: TryFuture> Future for MakeFuture<F> {
         Poll::Ready(Ok(svc))
     }
 }
+
+/// Applies a target's per-try timeout to each attempt of a request.
+///
+/// This must be placed beneath the retry layer so that each attempt is timed
+/// independently. Timeouts fail with a `PerTryTimeout` error.
+#[derive(Clone, Debug)]
+pub struct NewPerTry<N> {
+    inner: N,
+}
+
+#[derive(Clone, Debug)]
+pub struct PerTry<P> {
+    inner: P,
+    duration: Option<Duration>,
+}
+
+#[pin_project(project = PerTryFutureProj)]
+pub enum PerTryFuture<F> {
+    Passthru(#[pin] F),
+    Timeout(#[pin] time::Timeout<F>, Duration),
+}
+
+/// An error indicating that a single attempt of a request timed out.
+#[derive(Debug, Error)]
+#[error("request attempt timed out after {:?}", self.0)]
+pub struct PerTryTimeout(pub(crate) Duration);
+
+/// Applies a target's idle timeout to responses.
+///
+/// Requests fail with an `IdleTimeout` error if the response headers are not
+/// received in time, and response bodies fail if no frame is received within
+/// the timeout while the body is being read.
+#[derive(Clone, Debug)]
+pub struct NewIdle<N> {
+    inner: N,
+}
+
+#[derive(Clone, Debug)]
+pub struct Idle<P> {
+    inner: P,
+    duration: Option<Duration>,
+}
+
+#[pin_project]
+pub struct IdleFuture<F> {
+    #[pin]
+    inner: F,
+    timer: Option<IdleTimer>,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct IdleBody<B> {
+    #[pin]
+    inner: B,
+    timer: Option<IdleTimer>,
+}
+
+/// An error indicating that a response made no progress within the idle timeout.
+#[derive(Debug, Error)]
+#[error("response idle for {:?}", self.0)]
+pub struct IdleTimeout(pub(crate) Duration);
+
+#[derive(Debug)]
+struct IdleTimer {
+    sleep: Pin<Box<Sleep>>,
+    duration: Duration,
+    waiting: bool,
+}
+
+// === impl NewPerTry ===
+
+impl<N> NewPerTry<N> {
+    pub fn layer() -> impl layer::Layer<N, Service = Self> + Clone + Copy {
+        layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<T, N> NewService<T> for NewPerTry<N>
+where
+    N: NewService<T>,
+    T: HasTimeout,
+{
+    type Service = PerTry<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        PerTry {
+            duration: target.per_try_timeout(),
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl PerTry ===
+
+impl<P, S, Req> Proxy<Req, S> for PerTry<P>
+where
+    P: Proxy<Req, S>,
+    S: tower::Service<P::Request>,
+{
+    type Request = P::Request;
+    type Response = P::Response;
+    type Error = Error;
+    type Future = PerTryFuture<P::Future>;
+
+    fn proxy(&self, svc: &mut S, req: Req) -> Self::Future {
+        let inner = self.inner.proxy(svc, req);
+        match self.duration {
+            None => PerTryFuture::Passthru(inner),
+            Some(t) => PerTryFuture::Timeout(time::timeout(t, inner), t),
+        }
+    }
+}
+
+impl<F, T, E> Future for PerTryFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<Error>,
+{
+    type Output = Result<T, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            PerTryFutureProj::Passthru(f) => f.poll(cx).map_err(Into::into),
+            PerTryFutureProj::Timeout(f, duration) => {
+                let rsp = ready!(f.poll(cx)).map_err(|_| PerTryTimeout(*duration))?;
+                Poll::Ready(rsp.map_err(Into::into))
+            }
+        }
+    }
+}
+
+// === impl PerTryTimeout ===
+
+impl PerTryTimeout {
+    /// Get the amount of time waited until this error was triggered.
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+}
+
+// === impl NewIdle ===
+
+impl<N> NewIdle<N> {
+    pub fn layer() -> impl layer::Layer<N, Service = Self> + Clone + Copy {
+        layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<T, N> NewService<T> for NewIdle<N>
+where
+    N: NewService<T>,
+    T: HasTimeout,
+{
+    type Service = Idle<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        Idle {
+            duration: target.idle_timeout(),
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl Idle ===
+
+impl<P, S, Req, B> Proxy<Req, S> for Idle<P>
+where
+    P: Proxy<Req, S, Response = http::Response<B>>,
+    P::Error: Into<Error>,
+    S: tower::Service<P::Request>,
+{
+    type Request = P::Request;
+    type Response = http::Response<IdleBody<B>>;
+    type Error = Error;
+    type Future = IdleFuture<P::Future>;
+
+    fn proxy(&self, svc: &mut S, req: Req) -> Self::Future {
+        IdleFuture {
+            inner: self.inner.proxy(svc, req),
+            timer: self.duration.map(IdleTimer::new),
+        }
+    }
+}
+
+impl<F, B> Future for IdleFuture<F>
+where
+    F: TryFuture<Ok = http::Response<B>>,
+    F::Error: Into<Error>,
+{
+    type Output = Result<http::Response<IdleBody<B>>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        match this.inner.try_poll(cx) {
+            Poll::Ready(res) => {
+                let rsp = res.map_err(Into::into)?;
+                let mut timer = this.timer.take();
+                if let Some(t) = timer.as_mut() {
+                    t.waiting = false;
+                }
+                Poll::Ready(Ok(rsp.map(|inner| IdleBody { inner, timer })))
+            }
+            Poll::Pending => {
+                if let Some(timer) = this.timer.as_mut() {
+                    timer.poll_elapsed(cx)?;
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+// === impl IdleBody ===
+
+impl<B> Body for IdleBody<B>
+where
+    B: Body,
+    B::Error: Into<Error>,
+{
+    type Data = B::Data;
+    type Error = Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        match this.inner.poll_data(cx) {
+            Poll::Ready(data) => {
+                if let Some(timer) = this.timer.as_mut() {
+                    timer.waiting = false;
+                }
+                Poll::Ready(data.map(|d| d.map_err(Into::into)))
+            }
+            Poll::Pending => match this.timer.as_mut().map(|t| t.poll_elapsed(cx)) {
+                Some(Err(e)) => Poll::Ready(Some(Err(e.into()))),
+                _ => Poll::Pending,
+            },
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        let this = self.project();
+        match this.inner.poll_trailers(cx) {
+            Poll::Ready(trailers) => {
+                // The response is complete, so the timer is no longer needed.
+                *this.timer = None;
+                Poll::Ready(trailers.map_err(Into::into))
+            }
+            Poll::Pending => {
+                if let Some(timer) = this.timer.as_mut() {
+                    timer.poll_elapsed(cx)?;
+                }
+                Poll::Pending
+            }
+        }
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl<B: Default> Default for IdleBody<B> {
+    fn default() -> Self {
+        Self {
+            inner: B::default(),
+            timer: None,
+        }
+    }
+}
+
+// === impl IdleTimer ===
+
+impl IdleTimer {
+    fn new(duration: Duration) -> Self {
+        Self {
+            sleep: Box::pin(time::sleep(duration)),
+            duration,
+            waiting: true,
+        }
+    }
+
+    /// Polls the timer while the response is not making progress.
+    ///
+    /// The idle period starts when the response is first found to be pending
+    /// after producing a frame, so time spent before the body is read again
+    /// does not count against the response.
+    fn poll_elapsed(&mut self, cx: &mut Context<'_>) -> Result<(), IdleTimeout> {
+        if !self.waiting {
+            self.waiting = true;
+            let deadline = time::Instant::now() + self.duration;
+            self.sleep.as_mut().reset(deadline);
+        }
+        match self.sleep.as_mut().poll(cx) {
+            Poll::Ready(()) => Err(IdleTimeout(self.duration)),
+            Poll::Pending => Ok(()),
+        }
+    }
+}
+
+// === impl IdleTimeout ===
+
+impl IdleTimeout {
+    /// Get the amount of time waited until this error was triggered.
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+    use linkerd_stack::ProxyService;
+    use tower::ServiceExt;
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn per_try_timeout() {
+        let proxy = PerTry {
+            inner: (),
+            duration: Some(Duration::from_secs(1)),
+        };
+        let svc = tower::service_fn(|()| future::pending::<Result<(), Error>>());
+        let err = ProxyService::new(proxy, svc)
+            .oneshot(())
+            .await
+            .expect_err("request must time out");
+        assert!(err.is::<PerTryTimeout>(), "{:?}", err);
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn idle_timeout_headers() {
+        let proxy = Idle {
+            inner: (),
+            duration: Some(Duration::from_secs(1)),
+        };
+        let svc =
+            tower::service_fn(|()| future::pending::<Result<http::Response<hyper::Body>, Error>>());
+        let err = ProxyService::new(proxy, svc)
+            .oneshot(())
+            .await
+            .expect_err("request must time out");
+        assert!(err.is::<IdleTimeout>(), "{:?}", err);
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn idle_timeout_body() {
+        let (mut tx, body) = hyper::Body::channel();
+        let mut body = Some(body);
+        let proxy = Idle {
+            inner: (),
+            duration: Some(Duration::from_secs(1)),
+        };
+        let svc = tower::service_fn(move |()| {
+            let body = body.take().expect("called once");
+            future::ok::<_, Error>(http::Response::new(body))
+        });
+        let rsp = ProxyService::new(proxy, svc)
+            .oneshot(())
+            .await
+            .expect("response must succeed");
+        let mut body = rsp.into_body();
+
+        tx.send_data("hello".into()).await.unwrap();
+        let data = body.data().await.unwrap().expect("data must be read");
+        assert_eq!(&data[..], b"hello");
+
+        // Time spent before the body is polled again isn't idle time.
+        time::sleep(Duration::from_secs(5)).await;
+        tx.send_data("world".into()).await.unwrap();
+        let data = body.data().await.unwrap().expect("data must be read");
+        assert_eq!(&data[..], b"world");
+
+        let err = body
+            .data()
+            .await
+            .expect("body must fail")
+            .expect_err("body must time out");
+        assert!(err.is::<IdleTimeout>(), "{:?}", err);
+        drop(tx);
+    }
+}
@@ -10,6 +10,30 @@ pub enum Version {
 #[error("unsupported HTTP version {:?}", self.0)]
 pub struct Unsupported(http::Version);
 
+const ALPN_HTTP1: &[u8] = b"http/1.1";
+const ALPN_H2: &[u8] = b"h2";
+
+impl Version {
+    /// Returns the version indicated by a negotiated ALPN protocol, if the
+    /// protocol is an HTTP protocol.
+    pub fn from_alpn(protocol: &[u8]) -> Option<Self> {
+        if protocol == ALPN_H2 {
+            Some(Self::H2)
+        } else if protocol == ALPN_HTTP1 {
+            Some(Self::Http1)
+        } else {
+            None
+        }
+    }
+
+    pub fn alpn(self) -> &'static [u8] {
+        match self {
+            Self::Http1 => ALPN_HTTP1,
+            Self::H2 => ALPN_H2,
+        }
+    }
+}
+
 impl std::convert::TryFrom<http::Version> for Version {
     type Error = Unsupported;
     fn try_from(v: http::Version) -> Result<Self, Unsupported> {
@@ -1,3 +1,4 @@
+use crate::{metrics::Refreshes, trust_anchors};
 use http_body::Body;
 use linkerd2_proxy_api::identity::{self as api, identity_client::IdentityClient};
 use linkerd_error::Error;
@@ -12,7 +13,7 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::watch;
 use tokio::time::{self, Sleep};
 use tonic::{self as grpc, body::BoxBody, client::GrpcService};
-use tracing::{debug, error, trace};
+use tracing::{debug, debug_span, error, trace, warn, Instrument};
 
 /// Configures the Identity service and local identity.
 #[derive(Clone, Debug)]
@@ -24,6 +25,7 @@ pub struct Config {
     pub local_id: id::LocalId,
     pub min_refresh: Duration,
     pub max_refresh: Duration,
+    pub trust_anchors_reload: Option<trust_anchors::Reload>,
 }
 
 /// Holds the process's local TLS identity state.
@@ -32,10 +34,11 @@ pub struct Config {
 #[pin_project]
 #[derive(Clone, Debug)]
 pub struct LocalCrtKey {
-    trust_anchors: id::TrustAnchors,
+    trust_anchors: watch::Receiver<id::TrustAnchors>,
     id: id::LocalId,
     crt_key: watch::Receiver<Option<id::CrtKey>>,
-    refreshes: Arc<Counter>,
+    refreshes: Arc<Refreshes>,
+    trust_anchor_reloads: Arc<Counter>,
 }
 
 /// Produces a `Local` identity once a certificate is available.
@@ -50,7 +53,9 @@ pub type CrtKeySender = watch::Sender<Option<id::CrtKey>>;
 #[derive(Debug)]
 pub struct Daemon {
     crt_key_watch: CrtKeySender,
-    refreshes: Arc<linkerd_metrics::Counter>,
+    refreshes: Arc<Refreshes>,
+    trust_anchors: watch::Receiver<id::TrustAnchors>,
+    reload: Option<trust_anchors::Watch>,
     config: Config,
 }
 
@@ -91,11 +96,18 @@ impl Daemon {
         let Self {
             crt_key_watch,
             refreshes,
+            mut trust_anchors,
+            reload,
             config,
         } = self;
 
+        if let Some(reload) = reload {
+            tokio::spawn(reload.run().instrument(debug_span!("trust_anchors")));
+        }
+
         debug!("Identity daemon running");
         let mut curr_expiry = UNIX_EPOCH;
+        let mut curr_crt = None;
 
         loop {
             match config.token.load() {
@@ -115,7 +127,10 @@ impl Daemon {
                     };
 
                     match rsp {
-                        Err(e) => error!("Failed to certify identity: {}", e),
+                        Err(e) => {
+                            error!("Failed to certify identity: {}", e);
+                            refreshes.failure();
+                        }
                         Ok(rsp) => {
                             let api::CertifyResponse {
                                 leaf_certificate,
@@ -123,9 +138,12 @@ impl Daemon {
                                 valid_until,
                             } = rsp.into_inner();
                             match valid_until.and_then(|d| SystemTime::try_from(d).ok()) {
-                                None => error!(
-                                    "Identity service did not specify a certificate expiration."
-                                ),
+                                None => {
+                                    error!(
+                                        "Identity service did not specify a certificate expiration."
+                                    );
+                                    refreshes.failure();
+                                }
                                 Some(expiry) => {
                                     let key = config.key.clone();
                                     let crt = id::Crt::new(
@@ -135,9 +153,11 @@ impl Daemon {
                                         expiry,
                                     );
 
-                                    match config.trust_anchors.certify(key, crt) {
+                                    let ta = trust_anchors.borrow().clone();
+                                    match ta.certify(key, crt.clone()) {
                                         Err(e) => {
                                             error!("Received invalid certificate: {}", e);
+                                            refreshes.failure();
                                         }
                                         Ok(crt_key) => {
                                             debug!("daemon certified until {:?}", expiry);
@@ -147,8 +167,9 @@ impl Daemon {
                                                 return;
                                             }
 
-                                            refreshes.incr();
+                                            refreshes.success();
                                             curr_expiry = expiry;
+                                            curr_crt = Some(crt);
                                         }
                                     }
                                 }
@@ -156,9 +177,47 @@ impl Daemon {
                         }
                     }
                 }
-                Err(e) => error!("Failed to read authentication token: {}", e),
+                Err(e) => {
+                    error!("Failed to read authentication token: {}", e);
+                    refreshes.failure();
+                }
+            }
+
+            let refresh = config.refresh(curr_expiry);
+            tokio::pin!(refresh);
+            loop {
+                tokio::select! {
+                    _ = &mut refresh => break,
+                    res = trust_anchors.changed() => {
+                        if res.is_err() {
+                            // The trust anchors are not being reloaded.
+                            (&mut refresh).await;
+                            break;
+                        }
+
+                        // Re-validate the current certificate against the new
+                        // trust anchors so that the TLS configurations can be
+                        // swapped without waiting for the next refresh.
+                        let crt = match curr_crt.clone() {
+                            Some(crt) => crt,
+                            None => break,
+                        };
+                        let ta = trust_anchors.borrow().clone();
+                        match ta.certify(config.key.clone(), crt) {
+                            Ok(crt_key) => {
+                                debug!("Updated certificate with new trust anchors");
+                                if crt_key_watch.send(Some(crt_key)).is_err() {
+                                    return;
+                                }
+                            }
+                            Err(error) => {
+                                warn!(%error, "Certificate is not valid for the new trust anchors; refreshing");
+                                break;
+                            }
+                        }
+                    }
+                }
             }
-            config.refresh(curr_expiry).await;
         }
     }
 }
@@ -168,16 +227,25 @@ impl Daemon {
 impl LocalCrtKey {
     pub fn new(config: &Config) -> (Self, Daemon) {
         let (s, w) = watch::channel(None);
-        let refreshes = Arc::new(Counter::new());
+        let (ta_tx, ta_rx) = watch::channel(config.trust_anchors.clone());
+        let refreshes = Arc::new(Refreshes::default());
+        let trust_anchor_reloads = Arc::new(Counter::new());
+        let reload = config
+            .trust_anchors_reload
+            .clone()
+            .map(|r| trust_anchors::Watch::new(r, ta_tx, trust_anchor_reloads.clone()));
         let l = Self {
             id: config.local_id.clone(),
-            trust_anchors: config.trust_anchors.clone(),
+            trust_anchors: ta_rx.clone(),
             crt_key: w,
             refreshes: refreshes.clone(),
+            trust_anchor_reloads,
         };
         let daemon = Daemon {
             config: config.clone(),
             refreshes,
+            trust_anchors: ta_rx,
+            reload,
             crt_key_watch: s,
         };
         (l, daemon)
@@ -194,7 +262,11 @@ impl LocalCrtKey {
     }
 
     pub fn metrics(&self) -> crate::metrics::Report {
-        crate::metrics::Report::new(self.crt_key.clone(), self.refreshes.clone())
+        crate::metrics::Report::new(
+            self.crt_key.clone(),
+            self.refreshes.clone(),
+            self.trust_anchor_reloads.clone(),
+        )
     }
 
     pub fn id(&self) -> &id::LocalId {
@@ -205,12 +277,17 @@ impl LocalCrtKey {
         self.id.as_ref()
     }
 
+    /// Returns the current certificate, if one has been issued.
+    pub fn crt_key(&self) -> Option<id::CrtKey> {
+        self.crt_key.borrow().clone()
+    }
+
     pub fn client_config(&self) -> tls::client::Config {
         if let Some(ref c) = *self.crt_key.borrow() {
             return c.client_config();
         }
 
-        self.trust_anchors.client_config()
+        self.trust_anchors.borrow().client_config()
     }
 
     pub fn server_config(&self) -> tls::server::Config {
@@ -220,6 +297,19 @@ impl LocalCrtKey {
 
         tls::server::empty_config()
     }
+
+    /// Returns a server configuration that also accepts clients with
+    /// identities in the given federated trust domains.
+    pub fn federated_server_config(
+        &self,
+        domains: &Arc<[id::FederatedTrustDomain]>,
+    ) -> tls::server::Config {
+        if let Some(ref c) = *self.crt_key.borrow() {
+            return c.federated_server_config(domains.clone());
+        }
+
+        tls::server::empty_config()
+    }
 }
 
 impl Param<tls::client::Config> for LocalCrtKey {
@@ -239,3 +329,9 @@ impl Param<id::LocalId> for LocalCrtKey {
         self.id().clone()
     }
 }
+
+impl Param<tls::server::TerminateSubdomains> for LocalCrtKey {
+    fn param(&self) -> tls::server::TerminateSubdomains {
+        tls::server::TerminateSubdomains(false)
+    }
+}
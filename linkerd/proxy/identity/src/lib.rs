@@ -3,5 +3,6 @@
 
 pub mod certify;
 pub mod metrics;
+pub mod trust_anchors;
 
 pub use self::certify::{AwaitCrt, CrtKeySender, LocalCrtKey};
@@ -1,5 +1,5 @@
 use linkerd_identity::CrtKey;
-use linkerd_metrics::{metrics, Counter, FmtMetrics, Gauge};
+use linkerd_metrics::{metrics, Counter, FmtLabels, FmtMetrics, Gauge};
 use std::{fmt, sync::Arc, time::UNIX_EPOCH};
 use tokio::sync::watch;
 
@@ -8,25 +8,49 @@ pub struct Report {
     inner: Option<Inner>,
 }
 
+/// Counts attempts to refresh the local identity certificate.
+#[derive(Debug, Default)]
+pub(crate) struct Refreshes {
+    success: Counter,
+    failure: Counter,
+}
+
 metrics! {
-    identity_cert_expiration_timestamp_seconds: Gauge {
+    identity_cert_expiry_timestamp_seconds: Gauge {
         "Time when the this proxy's current mTLS identity certificate will expire (in seconds since the UNIX epoch)."
     },
 
+    identity_cert_refresh_total: Counter {
+        "The total number of attempts to refresh this proxy's mTLS identity certificate with the Identity service."
+    },
+
+    identity_trust_anchors_reload_total: Counter {
+        "The total number of times this proxy's trust anchors have been reloaded from disk."
+    },
+
+    identity_cert_expiration_timestamp_seconds: Gauge {
+        "Deprecated: use identity_cert_expiry_timestamp_seconds. Time when the this proxy's current mTLS identity certificate will expire (in seconds since the UNIX epoch)."
+    },
+
     identity_cert_refresh_count: Counter {
-        "The total number of times this proxy's mTLS identity certificate has been refreshed by the Identity service."
+        "Deprecated: use identity_cert_refresh_total. The total number of times this proxy's mTLS identity certificate has been refreshed by the Identity service."
     }
 }
 
+#[derive(Copy, Clone, Debug)]
+struct Success(bool);
+
 impl Report {
     pub(crate) fn new(
         crt_key_watch: watch::Receiver<Option<CrtKey>>,
-        refreshes: Arc<Counter>,
+        refreshes: Arc<Refreshes>,
+        trust_anchor_reloads: Arc<Counter>,
     ) -> Self {
         Self {
             inner: Some(Inner {
                 crt_key_watch,
                 refreshes,
+                trust_anchor_reloads,
             }),
         }
     }
@@ -39,7 +63,8 @@ impl Report {
 #[derive(Debug, Clone)]
 struct Inner {
     crt_key_watch: watch::Receiver<Option<CrtKey>>,
-    refreshes: Arc<Counter>,
+    refreshes: Arc<Refreshes>,
+    trust_anchor_reloads: Arc<Counter>,
 }
 
 impl FmtMetrics for Report {
@@ -57,14 +82,90 @@ impl FmtMetrics for Report {
                 tracing::warn!(%error, "an identity would expire before the beginning of the UNIX epoch, something is probably wrong");
                 fmt::Error
             })?;
+            let expiry = Gauge::from(dur.as_secs());
+            identity_cert_expiry_timestamp_seconds.fmt_help(f)?;
+            identity_cert_expiry_timestamp_seconds.fmt_metric(f, &expiry)?;
             identity_cert_expiration_timestamp_seconds.fmt_help(f)?;
-            identity_cert_expiration_timestamp_seconds
-                .fmt_metric(f, &Gauge::from(dur.as_secs()))?;
+            identity_cert_expiration_timestamp_seconds.fmt_metric(f, &expiry)?;
         }
 
+        identity_cert_refresh_total.fmt_help(f)?;
+        identity_cert_refresh_total.fmt_metric_labeled(
+            f,
+            &this.refreshes.success,
+            &Success(true),
+        )?;
+        identity_cert_refresh_total.fmt_metric_labeled(
+            f,
+            &this.refreshes.failure,
+            &Success(false),
+        )?;
+
+        // Successful refreshes were previously counted without a label.
         identity_cert_refresh_count.fmt_help(f)?;
-        identity_cert_refresh_count.fmt_metric(f, &this.refreshes)?;
+        identity_cert_refresh_count.fmt_metric(f, &this.refreshes.success)?;
+
+        identity_trust_anchors_reload_total.fmt_help(f)?;
+        identity_trust_anchors_reload_total.fmt_metric(f, &this.trust_anchor_reloads)?;
 
         Ok(())
     }
 }
+
+// === impl Refreshes ===
+
+impl Refreshes {
+    pub(crate) fn success(&self) {
+        self.success.incr();
+    }
+
+    pub(crate) fn failure(&self) {
+        self.failure.incr();
+    }
+}
+
+// === impl Success ===
+
+impl FmtLabels for Success {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "success=\"{}\"", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_identity::test_util::FOO_NS1;
+
+    #[test]
+    fn reports_deprecated_names() {
+        let crt_key = FOO_NS1.validate().expect("foo.ns1 must be valid");
+        let expiry = crt_key
+            .expiry()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let (_tx, rx) = watch::channel(Some(crt_key));
+        let refreshes = Arc::new(Refreshes::default());
+        refreshes.success();
+        refreshes.success();
+        refreshes.failure();
+        let report = Report::new(rx, refreshes, Arc::new(Counter::new()));
+
+        let text = report.as_display().to_string();
+        for line in &[
+            format!("identity_cert_expiry_timestamp_seconds {}", expiry),
+            format!("identity_cert_expiration_timestamp_seconds {}", expiry),
+            "identity_cert_refresh_total{success=\"true\"} 2".to_string(),
+            "identity_cert_refresh_total{success=\"false\"} 1".to_string(),
+            "identity_cert_refresh_count 2".to_string(),
+        ] {
+            assert!(
+                text.lines().any(|l| l == line.as_str()),
+                "missing {:?} in:\n{}",
+                line,
+                text
+            );
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use linkerd_identity as id;
+use linkerd_metrics::Counter;
+use std::{path::PathBuf, sync::Arc, time::Duration};
+use tokio::{sync::watch, time};
+use tracing::{debug, info, warn};
+
+/// Configures the proxy to reload its trust anchors from a PEM-encoded file.
+#[derive(Clone, Debug)]
+pub struct Reload {
+    pub path: PathBuf,
+    pub interval: Duration,
+
+    /// The file's contents when the initial trust anchors were loaded.
+    pub loaded: Vec<u8>,
+}
+
+/// Polls a trust anchors file, publishing a new set of trust anchors each time
+/// the file's contents change.
+#[derive(Debug)]
+pub(crate) struct Watch {
+    config: Reload,
+    tx: watch::Sender<id::TrustAnchors>,
+    reloads: Arc<Counter>,
+}
+
+// === impl Watch ===
+
+impl Watch {
+    pub(crate) fn new(
+        config: Reload,
+        tx: watch::Sender<id::TrustAnchors>,
+        reloads: Arc<Counter>,
+    ) -> Self {
+        Self {
+            config,
+            tx,
+            reloads,
+        }
+    }
+
+    pub(crate) async fn run(self) {
+        let Self {
+            config,
+            tx,
+            reloads,
+        } = self;
+
+        // The initial trust anchors were loaded from this file during
+        // configuration, so only subsequent changes are published. The file is
+        // not re-read here, so that a change made before the watch starts is
+        // not missed.
+        let mut curr = config.loaded;
+        let mut interval = time::interval(config.interval);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+
+            let pem = match tokio::fs::read(&config.path).await {
+                Ok(pem) => pem,
+                Err(error) => {
+                    warn!(%error, path = %config.path.display(), "Failed to read trust anchors");
+                    continue;
+                }
+            };
+            if curr == pem {
+                continue;
+            }
+
+            let trust_anchors = match std::str::from_utf8(&pem)
+                .ok()
+                .and_then(id::TrustAnchors::from_pem)
+            {
+                Some(ta) => ta,
+                None => {
+                    warn!(path = %config.path.display(), "Ignoring invalid trust anchors");
+                    curr = pem;
+                    continue;
+                }
+            };
+            // Preserve any chain verification constraints that were configured
+            // on the initial trust anchors.
+            let trust_anchors = match tx.borrow().max_intermediates() {
+                Some(max) => trust_anchors.with_max_intermediates(max),
+                None => trust_anchors,
+            };
+            debug!(path = %config.path.display(), "Trust anchors changed");
+            if tx.send(trust_anchors).is_err() {
+                // All observers have been dropped, so there's nothing to update.
+                return;
+            }
+            info!(path = %config.path.display(), "Reloaded trust anchors");
+            reloads.incr();
+            curr = pem;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_identity::test_util::{FOO_NS1, FOO_NS1_CA2};
+
+    const INTERVAL: Duration = Duration::from_millis(10);
+
+    /// Writes `FOO_NS1`'s trust anchors to a new file and watches it.
+    fn watch(name: &str) -> (PathBuf, watch::Receiver<id::TrustAnchors>, Arc<Counter>) {
+        let path = std::env::temp_dir().join(format!(
+            "linkerd-trust-anchors-{}-{}.pem",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, FOO_NS1.trust_anchors).unwrap();
+
+        let (tx, rx) = watch::channel(FOO_NS1.trust_anchors().with_max_intermediates(1));
+        let reloads = Arc::new(Counter::new());
+        let reload = Reload {
+            path: path.clone(),
+            interval: INTERVAL,
+            loaded: FOO_NS1.trust_anchors.to_vec(),
+        };
+        tokio::spawn(Watch::new(reload, tx, reloads.clone()).run());
+        (path, rx, reloads)
+    }
+
+    async fn changed(rx: &mut watch::Receiver<id::TrustAnchors>) {
+        time::timeout(Duration::from_secs(5), rx.changed())
+            .await
+            .expect("trust anchors must be reloaded")
+            .expect("watch must not be dropped");
+    }
+
+    async fn unchanged(rx: &mut watch::Receiver<id::TrustAnchors>) {
+        assert!(
+            time::timeout(INTERVAL * 10, rx.changed()).await.is_err(),
+            "trust anchors must not be reloaded"
+        );
+    }
+
+    #[tokio::test]
+    async fn reloads_changed_trust_anchors() {
+        let (path, mut rx, reloads) = watch("changed");
+
+        // Rewriting the loaded contents is not a change.
+        std::fs::write(&path, FOO_NS1.trust_anchors).unwrap();
+        unchanged(&mut rx).await;
+        assert_eq!(reloads.value(), 0.0);
+
+        std::fs::write(&path, FOO_NS1_CA2.trust_anchors).unwrap();
+        changed(&mut rx).await;
+        let ta = rx.borrow().clone();
+        assert!(ta.certify(FOO_NS1_CA2.key(), FOO_NS1_CA2.crt()).is_ok());
+        assert!(ta.certify(FOO_NS1.key(), FOO_NS1.crt()).is_err());
+        assert_eq!(ta.max_intermediates(), Some(1));
+        assert_eq!(reloads.value(), 1.0);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn ignores_invalid_trust_anchors() {
+        let (path, mut rx, reloads) = watch("invalid");
+
+        std::fs::write(&path, "-----BEGIN CERTIFICATE-----\nbogus\n").unwrap();
+        unchanged(&mut rx).await;
+        assert_eq!(reloads.value(), 0.0);
+        assert!(rx.borrow().certify(FOO_NS1.key(), FOO_NS1.crt()).is_ok());
+
+        // A valid bundle is still picked up after an invalid one.
+        std::fs::write(&path, FOO_NS1_CA2.trust_anchors).unwrap();
+        changed(&mut rx).await;
+        assert!(rx
+            .borrow()
+            .certify(FOO_NS1_CA2.key(), FOO_NS1_CA2.crt())
+            .is_ok());
+        assert_eq!(reloads.value(), 1.0);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
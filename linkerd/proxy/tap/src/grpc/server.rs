@@ -539,7 +539,10 @@ fn base_event<B, I: Inspect>(req: &http::Request<B>, inspect: &I) -> api::TapEve
                 }
                 Conditional::Some(tls::ServerTls::Passthru { sni }) => {
                     m.labels.insert("tls".to_owned(), "passthru".to_owned());
-                    m.labels.insert("sni".to_owned(), sni.to_string());
+                    m.labels.insert(
+                        "sni".to_owned(),
+                        sni.map(|sni| sni.to_string()).unwrap_or_default(),
+                    );
                 }
             }
             Some(m)
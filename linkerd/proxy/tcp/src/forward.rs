@@ -1,11 +1,15 @@
 use futures::prelude::*;
-use linkerd_duplex::Duplex;
+use linkerd_duplex::{Duplex, IdleTimeout, RateLimit};
 use linkerd_error::Error;
-use linkerd_stack::layer;
+use linkerd_metrics::Counter;
+use linkerd_stack::{layer, NewService, Param};
 use std::{
+    collections::HashMap,
     future::Future,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
 use tokio::io::{AsyncRead, AsyncWrite};
 use tower::Service;
@@ -13,11 +17,50 @@ use tower::Service;
 #[derive(Clone, Debug)]
 pub struct Forward<C> {
     connect: C,
+    limits: Vec<RateLimit>,
+    idle: Option<Idle>,
 }
 
+/// Builds `Forward` services that limit the rate at which bytes are copied
+/// according to the target's destination port.
+#[derive(Clone, Debug)]
+pub struct NewForward<N> {
+    inner: N,
+    limits: RateLimits,
+    idle: Option<Idle>,
+}
+
+/// Closes forwarded connections on which no data has been copied in either
+/// direction for a timeout, counting each connection that is closed.
+#[derive(Clone, Debug)]
+pub struct Idle {
+    timeout: Duration,
+    closed: Arc<Counter>,
+}
+
+/// Byte-rate limits for forwarded connections, by destination port.
+///
+/// Per-connection limits apply to each connection independently, while
+/// per-port limits apply to all connections on a port combined. Bytes copied
+/// in either direction count against each limit.
+#[derive(Clone, Debug, Default)]
+pub struct RateLimits(Arc<HashMap<u16, PortLimits>>);
+
+#[derive(Clone, Debug, Default)]
+struct PortLimits {
+    per_connection: Option<u64>,
+    per_port: Option<RateLimit>,
+}
+
+// === impl Forward ===
+
 impl<C> Forward<C> {
     fn new(connect: C) -> Self {
-        Self { connect }
+        Self {
+            connect,
+            limits: Vec::new(),
+            idle: None,
+        }
     }
 
     pub fn layer() -> impl layer::Layer<C, Service = Self> + Clone + Copy {
@@ -43,11 +86,100 @@ where
     }
 
     fn call(&mut self, src_io: I) -> Self::Future {
+        let limits = self.limits.clone();
+        let idle = self.idle.clone();
         Box::pin(
             self.connect
                 .call(())
                 .err_into::<Error>()
-                .and_then(|dst_io| Duplex::new(src_io, dst_io).err_into::<Error>()),
+                .and_then(move |dst_io| {
+                    let duplex = Duplex::new(src_io, dst_io).with_rate_limits(limits);
+                    match idle {
+                        None => duplex.err_into::<Error>().left_future(),
+                        Some(Idle { timeout, closed }) => duplex
+                            .with_idle_timeout(timeout)
+                            .map_err(move |e| {
+                                if IdleTimeout::is(&e) {
+                                    closed.incr();
+                                }
+                                e.into()
+                            })
+                            .right_future(),
+                    }
+                }),
         )
     }
 }
+
+// === impl NewForward ===
+
+impl<N> NewForward<N> {
+    pub fn layer(
+        limits: RateLimits,
+        idle: Option<Idle>,
+    ) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            inner,
+            limits: limits.clone(),
+            idle: idle.clone(),
+        })
+    }
+}
+
+impl<T, N> NewService<T> for NewForward<N>
+where
+    T: Param<u16>,
+    N: NewService<T>,
+{
+    type Service = Forward<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let limits = self.limits.get(target.param());
+        Forward {
+            connect: self.inner.new_service(target),
+            limits,
+            idle: self.idle.clone(),
+        }
+    }
+}
+
+// === impl Idle ===
+
+impl Idle {
+    pub fn new(timeout: Duration, closed: Arc<Counter>) -> Self {
+        Self { timeout, closed }
+    }
+}
+
+// === impl RateLimits ===
+
+impl RateLimits {
+    /// Creates rate limits from per-connection and per-port rates, in bytes
+    /// per second, by destination port.
+    pub fn new(
+        per_connection: impl IntoIterator<Item = (u16, u64)>,
+        per_port: impl IntoIterator<Item = (u16, u64)>,
+    ) -> Self {
+        let mut ports = HashMap::<u16, PortLimits>::new();
+        for (port, rate) in per_connection {
+            ports.entry(port).or_default().per_connection = Some(rate);
+        }
+        for (port, rate) in per_port {
+            ports.entry(port).or_default().per_port = Some(RateLimit::new(rate));
+        }
+        Self(Arc::new(ports))
+    }
+
+    fn get(&self, port: u16) -> Vec<RateLimit> {
+        let limits = match self.0.get(&port) {
+            Some(limits) => limits,
+            None => return Vec::new(),
+        };
+        limits
+            .per_connection
+            .map(RateLimit::new)
+            .into_iter()
+            .chain(limits.per_port.clone())
+            .collect()
+    }
+}
@@ -4,4 +4,4 @@
 pub mod balance;
 pub mod forward;
 
-pub use self::forward::Forward;
+pub use self::forward::{Forward, Idle, NewForward, RateLimits};
@@ -10,6 +10,7 @@ mod connect;
 pub mod listen;
 pub mod metrics;
 pub mod orig_dst;
+pub mod udp;
 
 pub use self::{
     addrs::{ClientAddr, ListenAddr, Local, OrigDstAddr, Remote, ServerAddr},
@@ -1,5 +1,6 @@
 use crate::{addrs::*, Keepalive};
 use futures::prelude::*;
+use linkerd_addr::canonical_socket_addr;
 use linkerd_io as io;
 use linkerd_stack::Param;
 use std::{fmt, pin::Pin};
@@ -15,6 +16,7 @@ pub trait Bind<T> {
         + io::AsyncWrite
         + io::Peek
         + io::PeerAddr
+        + io::Reset
         + fmt::Debug
         + Unpin
         + Send
@@ -67,7 +69,9 @@ where
             let tcp = res?;
             super::set_nodelay_or_warn(&tcp);
             let tcp = super::set_keepalive_or_warn(tcp, keepalive)?;
-            let client = Remote(ClientAddr(tcp.peer_addr()?));
+            // Dual-stack listeners report IPv4 clients with IPv4-mapped
+            // addresses.
+            let client = Remote(ClientAddr(canonical_socket_addr(tcp.peer_addr()?)));
             Ok((Addrs { server, client }, tcp))
         });
 
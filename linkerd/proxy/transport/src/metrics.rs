@@ -2,8 +2,8 @@ use futures::{ready, TryFuture};
 use linkerd_errno::Errno;
 use linkerd_io as io;
 use linkerd_metrics::{
-    metrics, Counter, FmtLabels, FmtMetric, FmtMetrics, Gauge, LastUpdate, Metric, NewMetrics,
-    Store,
+    latency, metrics, Bounds, Bucket, Counter, FmtLabels, FmtMetric, FmtMetrics, Gauge, Histogram,
+    LastUpdate, Metric, Store,
 };
 use linkerd_stack::{layer, NewService, Param};
 use parking_lot::Mutex;
@@ -27,16 +27,74 @@ metrics! {
     tcp_read_bytes_total: Counter { "Total count of bytes read from peers" },
     tcp_write_bytes_total: Counter { "Total count of bytes written to peers" },
 
-    tcp_close_total: Counter { "Total count of closed connections" }
+    tcp_close_total: Counter { "Total count of closed connections" },
+
+    tcp_connection_duration_ms: Histogram<latency::Ms> { "Connection lifetimes" },
+    tcp_connection_read_bytes: Histogram<u64> { "Total bytes read from peers, per connection" },
+    tcp_connection_write_bytes: Histogram<u64> { "Total bytes written to peers, per connection" },
+    tcp_connection_throughput_bytes_per_second: Histogram<u64> {
+        "Mean rate of bytes read from and written to peers over a connection's lifetime"
+    },
+
+    tcp_peer_open_total: Counter { "Total count of opened connections, by peer" },
+    tcp_peer_open_connections: Gauge { "Number of currently-open connections, by peer" },
+    tcp_peer_read_bytes_total: Counter { "Total count of bytes read from peers, by peer" },
+    tcp_peer_write_bytes_total: Counter { "Total count of bytes written to peers, by peer" }
 }
 
+/// The maximum number of bytes (inclusive) for each connection size bucket.
+const SIZE_BOUNDS: &Bounds = &Bounds(&[
+    Bucket::Le(1_000.0),
+    Bucket::Le(10_000.0),
+    Bucket::Le(100_000.0),
+    Bucket::Le(1_000_000.0),
+    Bucket::Le(10_000_000.0),
+    Bucket::Le(100_000_000.0),
+    Bucket::Le(1_000_000_000.0),
+    Bucket::Le(10_000_000_000.0),
+    Bucket::Inf,
+]);
+
+/// The maximum rate (inclusive), in bytes per second, for each connection
+/// throughput bucket.
+const THROUGHPUT_BOUNDS: &Bounds = &Bounds(&[
+    Bucket::Le(1_000.0),
+    Bucket::Le(10_000.0),
+    Bucket::Le(100_000.0),
+    Bucket::Le(1_000_000.0),
+    Bucket::Le(10_000_000.0),
+    Bucket::Le(100_000_000.0),
+    Bucket::Le(1_000_000_000.0),
+    Bucket::Inf,
+]);
+
+/// Connections shorter than this are treated as having lasted this long when
+/// computing their throughput, so that very short connections don't report
+/// inflated rates.
+const MIN_THROUGHPUT_DURATION: Duration = Duration::from_millis(1);
+
 pub fn new<K: Eq + Hash + FmtLabels>(retain_idle: Duration) -> (Registry<K>, Report<K>) {
     let inner = Arc::new(Mutex::new(Inner::new()));
     let report = Report {
         metrics: inner.clone(),
         retain_idle,
     };
-    (Registry(inner), report)
+    let registry = Registry {
+        inner,
+        rollup: None,
+    };
+    (registry, report)
+}
+
+/// Creates a `Rollup` that aggregates transport metrics by a `P`-typed class of
+/// peer. Transports are associated with a rollup via `Registry::with_rollup`.
+pub fn rollup<P: Eq + Hash + FmtLabels>(retain_idle: Duration) -> (Rollup<P>, RollupReport<P>) {
+    let inner = Arc::new(Mutex::new(Store::new()));
+    let report = RollupReport {
+        metrics: inner.clone(),
+        retain_idle,
+    };
+    (Rollup(inner), report)
 }
 
 /// Implements `FmtMetrics` to render prometheus-formatted metrics for all transports.
@@ -46,15 +104,39 @@ pub struct Report<K: Eq + Hash + FmtLabels> {
     retain_idle: Duration,
 }
 
+pub struct Registry<K: Eq + Hash + FmtLabels> {
+    inner: Arc<Mutex<Inner<K>>>,
+    rollup: Option<Arc<NewTotals<K>>>,
+}
+
+/// Aggregates the metrics of all transports that map to a `P`-typed peer.
+///
+/// Unlike a `Registry`, which tracks each class of transport, a rollup is
+/// intended to be keyed by a small, bounded set of labels.
 #[derive(Clone, Debug)]
-pub struct Registry<K: Eq + Hash + FmtLabels>(Arc<Mutex<Inner<K>>>);
+pub struct Rollup<P: Eq + Hash + FmtLabels>(Arc<Mutex<Store<P, Totals>>>);
+
+/// Implements `FmtMetrics` to render prometheus-formatted metrics for all peers
+/// in a `Rollup`.
+#[derive(Clone, Debug)]
+pub struct RollupReport<P: Eq + Hash + FmtLabels> {
+    metrics: Arc<Mutex<Store<P, Totals>>>,
+    retain_idle: Duration,
+}
+
+/// Obtains the peer totals for a class of transport, if it is rolled up.
+type NewTotals<K> = dyn Fn(&K) -> Option<Arc<Totals>> + Send + Sync;
 
 #[derive(Debug)]
 pub struct ConnectLayer<K: Eq + Hash + FmtLabels> {
-    registry: Arc<Mutex<Inner<K>>>,
+    registry: Registry<K>,
 }
 
-pub type MakeAccept<N, K, S> = NewMetrics<N, K, Metrics, Accept<S>>;
+#[derive(Debug)]
+pub struct MakeAccept<N, K: Eq + Hash + FmtLabels> {
+    inner: N,
+    registry: Registry<K>,
+}
 
 #[derive(Clone, Debug)]
 pub struct Accept<A> {
@@ -65,7 +147,7 @@ pub struct Accept<A> {
 #[derive(Debug)]
 pub struct Connect<K: Eq + Hash + FmtLabels, M> {
     inner: M,
-    registry: Arc<Mutex<Inner<K>>>,
+    registry: Registry<K>,
 }
 
 #[pin_project]
@@ -76,14 +158,33 @@ pub struct Connecting<F> {
 }
 
 /// Stores a class of transport's metrics.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Metrics {
     open_total: Counter,
     open_connections: Gauge,
     write_bytes_total: Counter,
     read_bytes_total: Counter,
 
+    connection_duration: Histogram<latency::Ms>,
+    connection_read_bytes: Histogram<u64>,
+    connection_write_bytes: Histogram<u64>,
+    connection_throughput: Histogram<u64>,
+
     by_eos: Arc<Mutex<ByEos>>,
+
+    /// The totals of the peer to which this class of transport rolls up, if
+    /// any.
+    rollup: Option<Arc<Totals>>,
+}
+
+/// Stores the metrics of a class of peer.
+#[derive(Debug)]
+pub struct Totals {
+    open_total: Counter,
+    open_connections: Gauge,
+    write_bytes_total: Counter,
+    read_bytes_total: Counter,
+    last_update: Mutex<Instant>,
 }
 
 #[derive(Debug)]
@@ -111,6 +212,8 @@ struct EosMetrics {
 pub struct Sensor {
     metrics: Option<Arc<Metrics>>,
     opened_at: Instant,
+    read_bytes: u64,
+    write_bytes: u64,
 }
 
 pub type SensorIo<T> = io::SensorIo<T, Sensor>;
@@ -124,22 +227,72 @@ type Inner<K> = Store<K, Metrics>;
 // ===== impl Registry =====
 
 impl<K: Eq + Hash + FmtLabels> Registry<K> {
+    /// Rolls the metrics of each class of transport up into the peer returned
+    /// by `peer`, if any.
+    pub fn with_rollup<P>(
+        self,
+        rollup: Rollup<P>,
+        peer: impl Fn(&K) -> Option<P> + Send + Sync + 'static,
+    ) -> Self
+    where
+        P: Eq + Hash + FmtLabels + Send + 'static,
+    {
+        let new_totals = move |key: &K| {
+            let peer = peer(key)?;
+            Some(rollup.0.lock().get_or_default(peer).clone())
+        };
+        Self {
+            inner: self.inner,
+            rollup: Some(Arc::new(new_totals)),
+        }
+    }
+
     pub fn layer_connect(&self) -> ConnectLayer<K> {
-        ConnectLayer::new(self.0.clone())
+        ConnectLayer::new(self.clone())
     }
 
-    pub fn layer_accept<M, T>(
-        &self,
-    ) -> impl layer::Layer<M, Service = MakeAccept<M, K, M::Service>> + Clone
-    where
-        M: NewService<T>,
-    {
-        MakeAccept::layer(self.0.clone())
+    pub fn layer_accept<M>(&self) -> impl layer::Layer<M, Service = MakeAccept<M, K>> + Clone {
+        let registry = self.clone();
+        layer::mk(move |inner| MakeAccept {
+            inner,
+            registry: registry.clone(),
+        })
+    }
+
+    fn metrics(&self, key: K) -> Arc<Metrics> {
+        let mut inner = self.inner.lock();
+        if let Some(metrics) = inner.get(&key) {
+            return metrics.clone();
+        }
+
+        let rollup = self.rollup.as_ref().and_then(|new_totals| new_totals(&key));
+        inner
+            .entry(key)
+            .or_insert_with(|| Arc::new(Metrics::new(rollup)))
+            .clone()
+    }
+}
+
+impl<K: Eq + Hash + FmtLabels> Clone for Registry<K> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            rollup: self.rollup.clone(),
+        }
+    }
+}
+
+impl<K: Eq + Hash + FmtLabels + fmt::Debug> fmt::Debug for Registry<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registry")
+            .field("inner", &self.inner)
+            .field("rollup", &self.rollup.is_some())
+            .finish()
     }
 }
 
 impl<K: Eq + Hash + FmtLabels> ConnectLayer<K> {
-    fn new(registry: Arc<Mutex<Inner<K>>>) -> Self {
+    fn new(registry: Registry<K>) -> Self {
         Self { registry }
     }
 }
@@ -161,6 +314,32 @@ impl<K: Eq + Hash + FmtLabels, M> tower::layer::Layer<M> for ConnectLayer<K> {
     }
 }
 
+// === impl MakeAccept ===
+
+impl<N, K, T> NewService<T> for MakeAccept<N, K>
+where
+    T: Param<K>,
+    K: Eq + Hash + FmtLabels,
+    N: NewService<T>,
+{
+    type Service = Accept<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let metrics = self.registry.metrics(target.param());
+        let inner = self.inner.new_service(target);
+        Accept { inner, metrics }
+    }
+}
+
+impl<N: Clone, K: Eq + Hash + FmtLabels> Clone for MakeAccept<N, K> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            registry: self.registry.clone(),
+        }
+    }
+}
+
 // === impl Accept ===
 
 impl<I, A> tower::Service<I> for Accept<A>
@@ -218,8 +397,7 @@ where
     }
 
     fn call(&mut self, target: T) -> Self::Future {
-        let labels = target.param();
-        let metrics = self.registry.lock().get_or_default(labels).clone();
+        let metrics = self.registry.metrics(target.param());
 
         Connecting {
             new_sensor: Some(NewSensor(metrics)),
@@ -299,6 +477,47 @@ impl<K: Eq + Hash + FmtLabels + 'static> FmtMetrics for Report<K> {
         tcp_close_total.fmt_help(f)?;
         Self::fmt_eos_by(&*metrics, f, tcp_close_total, |e| &e.close_total)?;
 
+        tcp_connection_duration_ms.fmt_help(f)?;
+        metrics.fmt_by(f, tcp_connection_duration_ms, |m| &m.connection_duration)?;
+
+        tcp_connection_read_bytes.fmt_help(f)?;
+        metrics.fmt_by(f, tcp_connection_read_bytes, |m| &m.connection_read_bytes)?;
+
+        tcp_connection_write_bytes.fmt_help(f)?;
+        metrics.fmt_by(f, tcp_connection_write_bytes, |m| &m.connection_write_bytes)?;
+
+        tcp_connection_throughput_bytes_per_second.fmt_help(f)?;
+        metrics.fmt_by(f, tcp_connection_throughput_bytes_per_second, |m| {
+            &m.connection_throughput
+        })?;
+
+        metrics.retain_since(Instant::now() - self.retain_idle);
+
+        Ok(())
+    }
+}
+
+// ===== impl RollupReport =====
+
+impl<P: Eq + Hash + FmtLabels + 'static> FmtMetrics for RollupReport<P> {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut metrics = self.metrics.lock();
+        if metrics.is_empty() {
+            return Ok(());
+        }
+
+        tcp_peer_open_total.fmt_help(f)?;
+        metrics.fmt_by(f, tcp_peer_open_total, |m| &m.open_total)?;
+
+        tcp_peer_open_connections.fmt_help(f)?;
+        metrics.fmt_by(f, tcp_peer_open_connections, |m| &m.open_connections)?;
+
+        tcp_peer_read_bytes_total.fmt_help(f)?;
+        metrics.fmt_by(f, tcp_peer_read_bytes_total, |m| &m.read_bytes_total)?;
+
+        tcp_peer_write_bytes_total.fmt_help(f)?;
+        metrics.fmt_by(f, tcp_peer_write_bytes_total, |m| &m.write_bytes_total)?;
+
         metrics.retain_since(Instant::now() - self.retain_idle);
 
         Ok(())
@@ -312,9 +531,16 @@ impl Sensor {
         metrics.open_total.incr();
         metrics.open_connections.incr();
         metrics.by_eos.lock().last_update = Instant::now();
+        if let Some(ref totals) = metrics.rollup {
+            totals.open_total.incr();
+            totals.open_connections.incr();
+            *totals.last_update.lock() = Instant::now();
+        }
         Self {
             metrics: Some(metrics),
             opened_at: Instant::now(),
+            read_bytes: 0,
+            write_bytes: 0,
         }
     }
 }
@@ -322,15 +548,25 @@ impl Sensor {
 impl io::Sensor for Sensor {
     fn record_read(&mut self, sz: usize) {
         if let Some(ref m) = self.metrics {
+            self.read_bytes += sz as u64;
             m.read_bytes_total.add(sz as u64);
             m.by_eos.lock().last_update = Instant::now();
+            if let Some(ref totals) = m.rollup {
+                totals.read_bytes_total.add(sz as u64);
+                *totals.last_update.lock() = Instant::now();
+            }
         }
     }
 
     fn record_write(&mut self, sz: usize) {
         if let Some(ref m) = self.metrics {
+            self.write_bytes += sz as u64;
             m.write_bytes_total.add(sz as u64);
             m.by_eos.lock().last_update = Instant::now();
+            if let Some(ref totals) = m.rollup {
+                totals.write_bytes_total.add(sz as u64);
+                *totals.last_update.lock() = Instant::now();
+            }
         }
     }
 
@@ -340,6 +576,18 @@ impl io::Sensor for Sensor {
         // on Drop).
         if let Some(m) = self.metrics.take() {
             m.open_connections.decr();
+            if let Some(ref totals) = m.rollup {
+                totals.open_connections.decr();
+                *totals.last_update.lock() = Instant::now();
+            }
+
+            let duration = self.opened_at.elapsed();
+            let bytes = self.read_bytes.saturating_add(self.write_bytes);
+            let secs = duration.max(MIN_THROUGHPUT_DURATION).as_secs_f64();
+            m.connection_duration.add(duration);
+            m.connection_read_bytes.add(self.read_bytes);
+            m.connection_write_bytes.add(self.write_bytes);
+            m.connection_throughput.add((bytes as f64 / secs) as u64);
 
             let mut by_eos = m.by_eos.lock();
             let class = by_eos
@@ -398,12 +646,58 @@ impl FmtLabels for Eos {
 
 // ===== impl Metrics =====
 
+impl Metrics {
+    fn new(rollup: Option<Arc<Totals>>) -> Self {
+        Self {
+            rollup,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            open_total: Counter::default(),
+            open_connections: Gauge::default(),
+            write_bytes_total: Counter::default(),
+            read_bytes_total: Counter::default(),
+            connection_duration: Histogram::default(),
+            connection_read_bytes: Histogram::new(SIZE_BOUNDS),
+            connection_write_bytes: Histogram::new(SIZE_BOUNDS),
+            connection_throughput: Histogram::new(THROUGHPUT_BOUNDS),
+            by_eos: Arc::default(),
+            rollup: None,
+        }
+    }
+}
+
 impl LastUpdate for Metrics {
     fn last_update(&self) -> Instant {
         self.by_eos.lock().last_update
     }
 }
 
+// ===== impl Totals =====
+
+impl Default for Totals {
+    fn default() -> Self {
+        Self {
+            open_total: Counter::default(),
+            open_connections: Gauge::default(),
+            write_bytes_total: Counter::default(),
+            read_bytes_total: Counter::default(),
+            last_update: Mutex::new(Instant::now()),
+        }
+    }
+}
+
+impl LastUpdate for Totals {
+    fn last_update(&self) -> Instant {
+        *self.last_update.lock()
+    }
+}
+
 // ===== impl ByEos =====
 
 impl Default for ByEos {
@@ -433,7 +727,7 @@ mod tests {
 
         let retain_idle_for = Duration::from_secs(1);
         let (r, report) = super::new(retain_idle_for);
-        let mut registry = r.0.lock();
+        let mut registry = r.inner.lock();
 
         let before_update = Instant::now();
         let metrics = registry.entry(Target(123)).or_default().clone();
@@ -464,4 +758,103 @@ mod tests {
 
         drop((registry, report));
     }
+
+    #[test]
+    fn connection_histograms() {
+        use super::{Metrics, Sensor};
+        use linkerd_io::Sensor as _;
+        use linkerd_metrics::{Bucket, Histogram};
+        use std::sync::Arc;
+
+        // Returns the upper bound of the only bucket with an observation.
+        fn observed(h: &Histogram<u64>) -> Bucket {
+            let mut buckets = h.into_iter().filter(|(_, c)| c.value() > 0.0);
+            let (bucket, count) = buckets.next().expect("histogram must be observed");
+            assert_eq!(count.value() as u64, 1);
+            assert!(buckets.next().is_none(), "only one bucket may be observed");
+            *bucket
+        }
+
+        let metrics = Arc::new(Metrics::default());
+        let mut sensor = Sensor::open(metrics.clone());
+        sensor.record_read(5_000);
+        sensor.record_write(50);
+        sensor.record_write(50);
+        sensor.record_close(None);
+        // Closing a sensor more than once has no effect.
+        drop(sensor);
+
+        assert_eq!(
+            observed(&metrics.connection_read_bytes),
+            Bucket::Le(10_000.0)
+        );
+        assert_eq!(
+            observed(&metrics.connection_write_bytes),
+            Bucket::Le(1_000.0)
+        );
+        observed(&metrics.connection_throughput);
+        assert_eq!(
+            metrics
+                .connection_duration
+                .into_iter()
+                .map(|(_, c)| c.value())
+                .sum::<f64>() as u64,
+            1
+        );
+    }
+
+    #[test]
+    fn rollup() {
+        use super::Sensor;
+        use linkerd_io::Sensor as _;
+        use linkerd_metrics::FmtLabels;
+        use std::{fmt, time::Duration};
+
+        #[derive(Clone, Debug, Hash, Eq, PartialEq)]
+        struct Target(usize);
+        impl FmtLabels for Target {
+            fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "n=\"{}\"", self.0)
+            }
+        }
+
+        #[derive(Clone, Debug, Hash, Eq, PartialEq)]
+        struct Parity(bool);
+        impl FmtLabels for Parity {
+            fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "even=\"{}\"", self.0)
+            }
+        }
+
+        let retain_idle_for = Duration::from_secs(1);
+        let (rollup, _report) = super::rollup::<Parity>(retain_idle_for);
+        let (registry, _report) = super::new::<Target>(retain_idle_for);
+        // Only even targets are rolled up.
+        let registry = registry.with_rollup(rollup.clone(), |Target(n)| {
+            if n % 2 == 0 {
+                Some(Parity(true))
+            } else {
+                None
+            }
+        });
+
+        let mut a = Sensor::open(registry.metrics(Target(0)));
+        let mut b = Sensor::open(registry.metrics(Target(2)));
+        let mut c = Sensor::open(registry.metrics(Target(1)));
+        a.record_read(10);
+        b.record_write(20);
+        c.record_read(30);
+        drop(a);
+
+        let totals = rollup.0.lock();
+        assert_eq!(totals.len(), 1, "only even targets must be rolled up");
+        let even = totals
+            .get(&Parity(true))
+            .expect("even targets must be rolled up");
+        assert_eq!(even.open_total.value(), 2.0);
+        assert_eq!(even.open_connections.value(), 1);
+        assert_eq!(even.read_bytes_total.value(), 10.0);
+        assert_eq!(even.write_bytes_total.value(), 20.0);
+        drop((b, c));
+    }
 }
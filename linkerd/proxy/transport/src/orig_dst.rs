@@ -78,11 +78,19 @@ where
 
 #[cfg(target_os = "linux")]
 fn orig_dst_addr(sock: &TcpStream) -> io::Result<OrigDstAddr> {
+    use linkerd_addr::{canonical_ip, canonical_socket_addr};
     use std::os::unix::io::AsRawFd;
 
     let fd = sock.as_raw_fd();
-    let r = unsafe { linux::so_original_dst(fd) };
-    r.map(OrigDstAddr)
+    // Connections redirected by ip6tables record their original destination
+    // at the IPv6 level. IPv4 connections, including those accepted by a
+    // dual-stack listener, record it at the IPv4 level.
+    let r = if canonical_ip(sock.local_addr()?.ip()).is_ipv6() {
+        unsafe { linux::so_original_dst6(fd) }
+    } else {
+        unsafe { linux::so_original_dst(fd) }
+    };
+    r.map(|addr| OrigDstAddr(canonical_socket_addr(addr)))
 }
 
 #[cfg(not(target_os = "linux"))]
@@ -94,20 +102,35 @@ fn orig_dst_addr(_: &TcpStream) -> io::Result<OrigDstAddr> {
 }
 
 #[cfg(target_os = "linux")]
-mod linux {
+pub(crate) mod linux {
     use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
     use std::os::unix::io::RawFd;
     use std::{io, mem};
     use tracing::warn;
 
+    /// From `linux/netfilter_ipv6/ip6_tables.h`.
+    const IP6T_SO_ORIGINAL_DST: libc::c_int = 80;
+
     pub unsafe fn so_original_dst(fd: RawFd) -> io::Result<SocketAddr> {
+        original_dst(fd, libc::SOL_IP, libc::SO_ORIGINAL_DST)
+    }
+
+    pub unsafe fn so_original_dst6(fd: RawFd) -> io::Result<SocketAddr> {
+        original_dst(fd, libc::SOL_IPV6, IP6T_SO_ORIGINAL_DST)
+    }
+
+    unsafe fn original_dst(
+        fd: RawFd,
+        level: libc::c_int,
+        name: libc::c_int,
+    ) -> io::Result<SocketAddr> {
         let mut sockaddr: libc::sockaddr_storage = mem::zeroed();
         let mut socklen: libc::socklen_t = mem::size_of::<libc::sockaddr_storage>() as u32;
 
         let ret = libc::getsockopt(
             fd,
-            libc::SOL_IP,
-            libc::SO_ORIGINAL_DST,
+            level,
+            name,
             &mut sockaddr as *mut _ as *mut _,
             &mut socklen as *mut _ as *mut _,
         );
@@ -124,7 +147,10 @@ mod linux {
     // https://github.com/rust-lang-nursery/net2-rs/blob/1b4cb4fb05fbad750b271f38221eab583b666e5e/src/socket.rs#L103
     //
     // Copyright (c) 2014 The Rust Project Developers
-    fn mk_addr(storage: &libc::sockaddr_storage, len: libc::socklen_t) -> io::Result<SocketAddr> {
+    pub(crate) fn mk_addr(
+        storage: &libc::sockaddr_storage,
+        len: libc::socklen_t,
+    ) -> io::Result<SocketAddr> {
         match storage.ss_family as libc::c_int {
             libc::AF_INET => {
                 assert!(len as usize >= mem::size_of::<libc::sockaddr_in>());
@@ -0,0 +1,605 @@
+//! Forwards UDP datagrams to their original destinations.
+//!
+//! Datagrams are redirected to the proxy with TPROXY, so the listener reads
+//! each datagram's original destination from its `IP_ORIGDSTADDR` control
+//! message. Each client/destination pair is tracked as a session with its own
+//! upstream socket. Replies are sent to the client from a socket bound to the
+//! original destination address, so that they appear to come from the peer
+//! the client addressed. Sessions are closed after being idle, and, when the
+//! maximum number of sessions is open, the session that has been idle the
+//! longest is evicted to make room for a new one.
+
+use crate::addrs::*;
+use bytes::Bytes;
+use linkerd_io as io;
+use linkerd_metrics::{metrics, Counter, FmtLabels, FmtMetrics, Gauge};
+use parking_lot::Mutex;
+use std::{collections::HashMap, fmt, net::SocketAddr, sync::Arc, time::Duration};
+use tokio::{net::UdpSocket, sync::mpsc, time};
+use tracing::{debug, debug_span, Instrument};
+
+metrics! {
+    udp_open_total: Counter { "Total count of opened UDP sessions" },
+    udp_open_sessions: Gauge { "Number of currently-open UDP sessions" },
+    udp_close_total: Counter { "Total count of closed UDP sessions" },
+    udp_evict_total: Counter { "Total count of UDP sessions evicted to make room for new sessions" },
+    udp_read_bytes_total: Counter { "Total count of bytes read from UDP peers" },
+    udp_write_bytes_total: Counter { "Total count of bytes written to UDP peers" },
+    udp_read_packets_total: Counter { "Total count of datagrams read from UDP peers" },
+    udp_write_packets_total: Counter { "Total count of datagrams written to UDP peers" }
+}
+
+/// The largest datagram that may be forwarded.
+const MAX_DATAGRAM_SIZE: usize = 65_535;
+
+/// The number of datagrams that may be buffered for a session before
+/// additional datagrams are dropped.
+const SESSION_CAPACITY: usize = 64;
+
+/// Receives datagrams along with their original destination addresses.
+#[derive(Debug)]
+pub struct Listener {
+    #[cfg(target_os = "linux")]
+    socket: tokio::io::unix::AsyncFd<std::net::UdpSocket>,
+}
+
+/// Records the sessions, bytes, and datagrams forwarded by UDP listeners.
+#[derive(Clone, Debug, Default)]
+pub struct Metrics(Arc<Inner>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    open_total: Counter,
+    open_sessions: Gauge,
+    close_total: Counter,
+    evict_total: Counter,
+    src: PeerMetrics,
+    dst: PeerMetrics,
+}
+
+#[derive(Debug, Default)]
+struct PeerMetrics {
+    read_bytes: Counter,
+    write_bytes: Counter,
+    read_packets: Counter,
+    write_packets: Counter,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Peer {
+    Src,
+    Dst,
+}
+
+/// Routes datagrams to the session for their client and original destination.
+type Sessions = Arc<Mutex<HashMap<(SocketAddr, SocketAddr), Handle>>>;
+
+/// Refers to a session from the listener. Dropping the handle closes the
+/// session.
+#[derive(Debug)]
+struct Handle {
+    id: u64,
+    tx: mpsc::Sender<Bytes>,
+    active: Arc<Mutex<time::Instant>>,
+}
+
+/// Forwards datagrams between a client and its original destination.
+struct Session {
+    upstream: UdpSocket,
+    reply: UdpSocket,
+    rx: mpsc::Receiver<Bytes>,
+    idle_timeout: Duration,
+    active: Arc<Mutex<time::Instant>>,
+    metrics: Metrics,
+}
+
+// === impl Listener ===
+
+/// Binds a transparent UDP listener on the given address.
+#[cfg(target_os = "linux")]
+pub fn bind(ListenAddr(addr): ListenAddr) -> io::Result<(Local<ServerAddr>, Listener)> {
+    let socket = linux::bind_transparent(addr)?;
+    linux::set_recv_orig_dst(&socket, addr.is_ipv6())?;
+    let local = Local(ServerAddr(socket.local_addr()?));
+    let socket = tokio::io::unix::AsyncFd::new(socket)?;
+    Ok((local, Listener { socket }))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn bind(_: ListenAddr) -> io::Result<(Local<ServerAddr>, Listener)> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "UDP original destinations are not supported on this operating system",
+    ))
+}
+
+impl Listener {
+    #[cfg(target_os = "linux")]
+    async fn recv(&self, buf: &mut [u8]) -> io::Result<(usize, Remote<ClientAddr>, OrigDstAddr)> {
+        use linkerd_addr::canonical_socket_addr;
+
+        loop {
+            let mut ready = self.socket.readable().await?;
+            match ready.try_io(|socket| linux::recv_orig_dst(socket.get_ref(), buf)) {
+                Ok(res) => {
+                    let (n, client, orig_dst) = res?;
+                    let client = Remote(ClientAddr(canonical_socket_addr(client)));
+                    let orig_dst = OrigDstAddr(canonical_socket_addr(orig_dst));
+                    return Ok((n, client, orig_dst));
+                }
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn recv(&self, _: &mut [u8]) -> io::Result<(usize, Remote<ClientAddr>, OrigDstAddr)> {
+        unreachable!("UDP listeners cannot be bound on this operating system")
+    }
+
+    /// Forwards datagrams to their original destinations, closing sessions
+    /// after they have been idle for `idle_timeout`. No more than
+    /// `max_sessions` sessions are open at once.
+    pub async fn serve(self, idle_timeout: Duration, max_sessions: usize, metrics: Metrics) {
+        let sessions = Sessions::default();
+        let mut next_id = 0;
+        let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+        loop {
+            let (n, Remote(ClientAddr(client)), OrigDstAddr(dst)) = match self.recv(&mut buf).await
+            {
+                Ok(recv) => recv,
+                Err(error) => {
+                    debug!(%error, "Failed to receive datagram");
+                    continue;
+                }
+            };
+            metrics.0.src.read(n);
+            let datagram = Bytes::copy_from_slice(&buf[..n]);
+
+            let datagram = match sessions.lock().get(&(client, dst)) {
+                Some(handle) => match handle.tx.try_send(datagram) {
+                    Ok(()) => continue,
+                    Err(mpsc::error::TrySendError::Full(_)) => {
+                        debug!(%client, %dst, "Session is not ready; dropping datagram");
+                        continue;
+                    }
+                    // If the session has closed, start a new one.
+                    Err(mpsc::error::TrySendError::Closed(datagram)) => datagram,
+                },
+                None => datagram,
+            };
+
+            next_id += 1;
+            if let Err(error) = Session::spawn(
+                next_id,
+                client,
+                dst,
+                datagram,
+                idle_timeout,
+                max_sessions,
+                sessions.clone(),
+                metrics.clone(),
+            )
+            .await
+            {
+                debug!(%error, %client, %dst, "Failed to open session");
+            }
+        }
+    }
+}
+
+// === impl Session ===
+
+impl Session {
+    #[allow(clippy::too_many_arguments)]
+    async fn spawn(
+        id: u64,
+        client: SocketAddr,
+        dst: SocketAddr,
+        datagram: Bytes,
+        idle_timeout: Duration,
+        max_sessions: usize,
+        sessions: Sessions,
+        metrics: Metrics,
+    ) -> io::Result<()> {
+        let upstream = {
+            let addr = if dst.is_ipv6() {
+                SocketAddr::from(([0; 16], 0))
+            } else {
+                SocketAddr::from(([0; 4], 0))
+            };
+            let socket = UdpSocket::bind(addr).await?;
+            socket.connect(dst).await?;
+            socket
+        };
+        let reply = reply_socket(dst, client)?;
+
+        let (tx, rx) = mpsc::channel(SESSION_CAPACITY);
+        tx.try_send(datagram)
+            .expect("new session must accept a datagram");
+        let active = Arc::new(Mutex::new(time::Instant::now()));
+        {
+            let mut sessions = sessions.lock();
+            if sessions.len() >= max_sessions {
+                if let Some((client, dst)) = evict_idlest(&mut sessions) {
+                    debug!(%client, %dst, "Evicted idle session");
+                    metrics.0.evict_total.incr();
+                }
+            }
+            let handle = Handle {
+                id,
+                tx,
+                active: active.clone(),
+            };
+            sessions.insert((client, dst), handle);
+        }
+
+        let mut session = Session {
+            upstream,
+            reply,
+            rx,
+            idle_timeout,
+            active,
+            metrics,
+        };
+        tokio::spawn(
+            async move {
+                session.run().await;
+                // The session is removed before its receiver is dropped, so
+                // that a new session for the same client and destination is
+                // not removed. A session that was evicted may have already
+                // been replaced.
+                let mut sessions = sessions.lock();
+                if sessions.get(&(client, dst)).map(|h| h.id) == Some(id) {
+                    sessions.remove(&(client, dst));
+                }
+                drop(sessions);
+                drop(session);
+            }
+            .instrument(debug_span!("udp", %client, %dst)),
+        );
+        Ok(())
+    }
+
+    async fn run(&mut self) {
+        debug!("Session opened");
+        self.metrics.0.open();
+
+        let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+        let idle = time::sleep(self.idle_timeout);
+        tokio::pin!(idle);
+        loop {
+            tokio::select! {
+                datagram = self.rx.recv() => {
+                    let datagram = match datagram {
+                        Some(datagram) => datagram,
+                        None => break,
+                    };
+                    match self.upstream.send(&datagram).await {
+                        Ok(n) => self.metrics.0.dst.write(n),
+                        Err(error) => {
+                            debug!(%error, "Failed to send datagram to destination");
+                            break;
+                        }
+                    }
+                }
+                res = self.upstream.recv(&mut buf) => {
+                    let n = match res {
+                        Ok(n) => n,
+                        Err(error) => {
+                            debug!(%error, "Failed to receive datagram from destination");
+                            break;
+                        }
+                    };
+                    self.metrics.0.dst.read(n);
+                    match self.reply.send(&buf[..n]).await {
+                        Ok(n) => self.metrics.0.src.write(n),
+                        Err(error) => {
+                            debug!(%error, "Failed to send datagram to client");
+                            break;
+                        }
+                    }
+                }
+                () = &mut idle => {
+                    debug!("Session idle");
+                    break;
+                }
+            }
+            let now = time::Instant::now();
+            *self.active.lock() = now;
+            idle.as_mut().reset(now + self.idle_timeout);
+        }
+
+        self.metrics.0.close();
+        debug!("Session closed");
+    }
+}
+
+/// Removes the session that has been idle the longest, closing it.
+fn evict_idlest(
+    sessions: &mut HashMap<(SocketAddr, SocketAddr), Handle>,
+) -> Option<(SocketAddr, SocketAddr)> {
+    let key = *sessions
+        .iter()
+        .min_by_key(|(_, handle)| *handle.active.lock())?
+        .0;
+    sessions.remove(&key);
+    Some(key)
+}
+
+/// Binds a socket to the original destination address, connected to the
+/// client, so that replies appear to come from the original destination.
+#[cfg(target_os = "linux")]
+fn reply_socket(dst: SocketAddr, client: SocketAddr) -> io::Result<UdpSocket> {
+    let socket = linux::bind_transparent(dst)?;
+    socket.connect(client)?;
+    UdpSocket::from_std(socket)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reply_socket(_: SocketAddr, _: SocketAddr) -> io::Result<UdpSocket> {
+    unreachable!("UDP listeners cannot be bound on this operating system")
+}
+
+// === impl Metrics ===
+
+impl Inner {
+    fn open(&self) {
+        self.open_total.incr();
+        self.open_sessions.incr();
+    }
+
+    fn close(&self) {
+        self.close_total.incr();
+        self.open_sessions.decr();
+    }
+}
+
+impl PeerMetrics {
+    fn read(&self, n: usize) {
+        self.read_bytes.add(n as u64);
+        self.read_packets.incr();
+    }
+
+    fn write(&self, n: usize) {
+        self.write_bytes.add(n as u64);
+        self.write_packets.incr();
+    }
+}
+
+impl FmtMetrics for Metrics {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Inner {
+            open_total,
+            open_sessions,
+            close_total,
+            evict_total,
+            src,
+            dst,
+        } = &*self.0;
+
+        udp_open_total.fmt_help(f)?;
+        udp_open_total.fmt_metric(f, open_total)?;
+        udp_open_sessions.fmt_help(f)?;
+        udp_open_sessions.fmt_metric(f, open_sessions)?;
+        udp_close_total.fmt_help(f)?;
+        udp_close_total.fmt_metric(f, close_total)?;
+        udp_evict_total.fmt_help(f)?;
+        udp_evict_total.fmt_metric(f, evict_total)?;
+
+        udp_read_bytes_total.fmt_help(f)?;
+        udp_read_bytes_total.fmt_metric_labeled(f, &src.read_bytes, &Peer::Src)?;
+        udp_read_bytes_total.fmt_metric_labeled(f, &dst.read_bytes, &Peer::Dst)?;
+        udp_write_bytes_total.fmt_help(f)?;
+        udp_write_bytes_total.fmt_metric_labeled(f, &src.write_bytes, &Peer::Src)?;
+        udp_write_bytes_total.fmt_metric_labeled(f, &dst.write_bytes, &Peer::Dst)?;
+        udp_read_packets_total.fmt_help(f)?;
+        udp_read_packets_total.fmt_metric_labeled(f, &src.read_packets, &Peer::Src)?;
+        udp_read_packets_total.fmt_metric_labeled(f, &dst.read_packets, &Peer::Dst)?;
+        udp_write_packets_total.fmt_help(f)?;
+        udp_write_packets_total.fmt_metric_labeled(f, &src.write_packets, &Peer::Src)?;
+        udp_write_packets_total.fmt_metric_labeled(f, &dst.write_packets, &Peer::Dst)?;
+
+        Ok(())
+    }
+}
+
+impl FmtLabels for Peer {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Peer::Src => f.write_str("peer=\"src\""),
+            Peer::Dst => f.write_str("peer=\"dst\""),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use crate::orig_dst::linux::mk_addr;
+    use std::{
+        io, mem,
+        net::{SocketAddr, UdpSocket},
+        os::unix::io::AsRawFd,
+        ptr,
+    };
+
+    /// From `linux/in6.h`.
+    const IPV6_TRANSPARENT: libc::c_int = 75;
+    const IPV6_RECVORIGDSTADDR: libc::c_int = 74;
+    const IPV6_ORIGDSTADDR: libc::c_int = IPV6_RECVORIGDSTADDR;
+
+    /// Binds a non-blocking socket that may use a non-local address.
+    pub fn bind_transparent(addr: SocketAddr) -> io::Result<UdpSocket> {
+        use socket2::{Domain, Protocol, Socket, Type};
+
+        let socket = Socket::new(Domain::for_address(addr), Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        if addr.is_ipv6() {
+            setsockopt(&socket, libc::SOL_IPV6, IPV6_TRANSPARENT)?;
+        } else {
+            setsockopt(&socket, libc::SOL_IP, libc::IP_TRANSPARENT)?;
+        }
+        socket.set_nonblocking(true)?;
+        socket.bind(&addr.into())?;
+        Ok(socket.into())
+    }
+
+    /// Configures the socket to record each datagram's original destination.
+    pub fn set_recv_orig_dst(socket: &UdpSocket, ipv6: bool) -> io::Result<()> {
+        setsockopt(socket, libc::SOL_IP, libc::IP_RECVORIGDSTADDR)?;
+        if ipv6 {
+            setsockopt(socket, libc::SOL_IPV6, IPV6_RECVORIGDSTADDR)?;
+        }
+        Ok(())
+    }
+
+    /// Receives a datagram, returning its size, source, and original
+    /// destination.
+    pub fn recv_orig_dst(
+        socket: &UdpSocket,
+        buf: &mut [u8],
+    ) -> io::Result<(usize, SocketAddr, SocketAddr)> {
+        unsafe {
+            let mut src: libc::sockaddr_storage = mem::zeroed();
+            let mut iov = libc::iovec {
+                iov_base: buf.as_mut_ptr() as *mut _,
+                iov_len: buf.len(),
+            };
+            // Aligned for `cmsghdr`.
+            let mut control = [0u64; 16];
+            let mut msg: libc::msghdr = mem::zeroed();
+            msg.msg_name = &mut src as *mut _ as *mut _;
+            msg.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as u32;
+            msg.msg_iov = &mut iov;
+            msg.msg_iovlen = 1;
+            msg.msg_control = control.as_mut_ptr() as *mut _;
+            msg.msg_controllen = mem::size_of_val(&control) as _;
+
+            let n = libc::recvmsg(socket.as_raw_fd(), &mut msg, 0);
+            if n < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let src = mk_addr(&src, msg.msg_namelen)?;
+
+            let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+            while !cmsg.is_null() {
+                let hdr = &*cmsg;
+                if (hdr.cmsg_level == libc::SOL_IP && hdr.cmsg_type == libc::IP_ORIGDSTADDR)
+                    || (hdr.cmsg_level == libc::SOL_IPV6 && hdr.cmsg_type == IPV6_ORIGDSTADDR)
+                {
+                    let data = libc::CMSG_DATA(cmsg);
+                    let len = (hdr.cmsg_len as usize - (data as usize - cmsg as usize))
+                        .min(mem::size_of::<libc::sockaddr_storage>());
+                    let mut dst: libc::sockaddr_storage = mem::zeroed();
+                    ptr::copy_nonoverlapping(data, &mut dst as *mut _ as *mut u8, len);
+                    let dst = mk_addr(&dst, len as u32)?;
+                    return Ok((n as usize, src, dst));
+                }
+                cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "datagram has no original destination",
+        ))
+    }
+
+    fn setsockopt(socket: &impl AsRawFd, level: libc::c_int, name: libc::c_int) -> io::Result<()> {
+        let enable: libc::c_int = 1;
+        let ret = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                level,
+                name,
+                &enable as *const _ as *const _,
+                mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        if ret != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn forwards_until_idle() {
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let upstream = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        upstream
+            .connect(server.local_addr().unwrap())
+            .await
+            .unwrap();
+        // Replies are sent from an ordinary socket, since binding to the
+        // original destination requires privileges.
+        let reply = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        reply.connect(client.local_addr().unwrap()).await.unwrap();
+
+        let metrics = Metrics::default();
+        let (tx, rx) = mpsc::channel(SESSION_CAPACITY);
+        let mut session = Session {
+            upstream,
+            reply,
+            rx,
+            idle_timeout: Duration::from_millis(100),
+            active: Arc::new(Mutex::new(time::Instant::now())),
+            metrics: metrics.clone(),
+        };
+        let task = tokio::spawn(async move { session.run().await });
+
+        tx.send(Bytes::from_static(b"ping")).await.unwrap();
+        let mut buf = [0; 16];
+        let (n, from) = server.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"ping");
+        server.send_to(b"pong!", from).await.unwrap();
+        let n = client.recv(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"pong!");
+
+        // The session closes once it has been idle.
+        task.await.unwrap();
+        assert_eq!(metrics.0.open_total.value(), 1.0);
+        assert_eq!(metrics.0.close_total.value(), 1.0);
+        assert_eq!(metrics.0.open_sessions.value(), 0);
+        assert_eq!(metrics.0.dst.write_bytes.value(), 4.0);
+        assert_eq!(metrics.0.dst.read_bytes.value(), 5.0);
+        assert_eq!(metrics.0.src.write_bytes.value(), 5.0);
+        assert_eq!(metrics.0.src.write_packets.value(), 1.0);
+    }
+
+    #[tokio::test]
+    async fn evicts_idlest_session() {
+        let now = time::Instant::now();
+        let mut sessions = HashMap::new();
+        let mut rxs = Vec::new();
+        for (id, idle) in [(1, 10), (2, 30), (3, 20)].iter() {
+            let (tx, rx) = mpsc::channel(1);
+            rxs.push(rx);
+            let key = (
+                SocketAddr::from(([127, 0, 0, 1], 1000 + *id as u16)),
+                SocketAddr::from(([10, 0, 0, 1], 53)),
+            );
+            let handle = Handle {
+                id: *id,
+                tx,
+                active: Arc::new(Mutex::new(now - Duration::from_secs(*idle))),
+            };
+            sessions.insert(key, handle);
+        }
+
+        let (client, _) = evict_idlest(&mut sessions).expect("must evict a session");
+        assert_eq!(client.port(), 1002);
+        assert_eq!(sessions.len(), 2);
+        // Evicting a session drops its handle, which closes the session.
+        assert!(rxs[1].recv().await.is_none());
+
+        let (client, _) = evict_idlest(&mut sessions).expect("must evict a session");
+        assert_eq!(client.port(), 1003);
+    }
+}
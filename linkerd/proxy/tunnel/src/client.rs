@@ -0,0 +1,86 @@
+use crate::{Stream, INITIAL_CONNECTION_WINDOW_SIZE, INITIAL_STREAM_WINDOW_SIZE};
+use bytes::Bytes;
+use h2::client::SendRequest;
+use linkerd_error::Error;
+use linkerd_io as io;
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use thiserror::Error;
+use tracing::{debug, trace, Instrument};
+
+/// A tunnel connection, on which streams may be opened.
+#[derive(Clone, Debug)]
+pub struct Client {
+    send: SendRequest<Bytes>,
+    peer: SocketAddr,
+    closed: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Error)]
+#[error("tunnel refused stream with status {0}")]
+pub struct Refused(http::StatusCode);
+
+// === impl Client ===
+
+impl Client {
+    /// Establishes a tunnel on the given connection to `peer`.
+    ///
+    /// The connection is driven on a background task until it fails or all
+    /// clones of the client are dropped.
+    pub async fn handshake<I>(io: I, peer: SocketAddr) -> Result<Self, Error>
+    where
+        I: io::AsyncRead + io::AsyncWrite + Send + Unpin + 'static,
+    {
+        let (send, conn) = h2::client::Builder::new()
+            .initial_window_size(INITIAL_STREAM_WINDOW_SIZE)
+            .initial_connection_window_size(INITIAL_CONNECTION_WINDOW_SIZE)
+            .handshake(io)
+            .await?;
+
+        let closed = Arc::new(AtomicBool::new(false));
+        tokio::spawn({
+            let closed = closed.clone();
+            async move {
+                match conn.await {
+                    Ok(()) => debug!("Tunnel closed"),
+                    Err(error) => debug!(%error, "Tunnel failed"),
+                }
+                closed.store(true, Ordering::Release);
+            }
+            .in_current_span()
+        });
+
+        Ok(Self { send, peer, closed })
+    }
+
+    /// Indicates whether the tunnel connection has closed, in which case no
+    /// more streams may be opened on it.
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    /// Opens a stream to the given target.
+    pub async fn open(&self, target: http::uri::Authority) -> Result<Stream, Error> {
+        let mut send = self.send.clone().ready().await?;
+
+        let mut uri = http::uri::Parts::default();
+        uri.authority = Some(target);
+        let req = http::Request::builder()
+            .method(http::Method::CONNECT)
+            .uri(http::Uri::from_parts(uri)?)
+            .body(())?;
+        trace!(uri = %req.uri(), "Opening stream");
+        let (rsp, send) = send.send_request(req, false)?;
+
+        let rsp = rsp.await?;
+        if rsp.status() != http::StatusCode::OK {
+            return Err(Refused(rsp.status()).into());
+        }
+        Ok(Stream::new(send, rsp.into_body(), self.peer))
+    }
+}
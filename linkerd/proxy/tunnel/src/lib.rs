@@ -0,0 +1,32 @@
+//! Multiplexes TCP connections between proxies as streams on a shared HTTP/2
+//! connection.
+//!
+//! Establishing an mTLS connection to a remote gateway is expensive over
+//! high-latency links, and gateways may limit the number of connections each
+//! client may hold open. When both proxies support it (as negotiated by
+//! ALPN), a client proxy holds a single long-lived HTTP/2 connection to each
+//! gateway and opens a `CONNECT` stream on it for each forwarded connection.
+//! The stream's `:authority` names the connection's target, as a transport
+//! header would.
+
+#![deny(warnings, rust_2018_idioms)]
+#![forbid(unsafe_code)]
+
+mod client;
+mod server;
+mod stream;
+
+pub use self::{
+    client::{Client, Refused},
+    server::{NewServeTunnel, ServeTunnel},
+    stream::Stream,
+};
+
+/// The ALPN protocol that indicates that a connection carries tunneled
+/// streams.
+pub const PROTOCOL: &[u8] = b"tunnel.l5d.io/v1";
+
+// Tunnels are intended for high-latency links, so they are configured with
+// larger flow control windows than HTTP/2 defaults.
+const INITIAL_STREAM_WINDOW_SIZE: u32 = 1024 * 1024;
+const INITIAL_CONNECTION_WINDOW_SIZE: u32 = 16 * 1024 * 1024;
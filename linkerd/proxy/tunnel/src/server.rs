@@ -0,0 +1,179 @@
+use crate::{Stream, INITIAL_CONNECTION_WINDOW_SIZE, INITIAL_STREAM_WINDOW_SIZE};
+use futures::prelude::*;
+use linkerd_error::Error;
+use linkerd_io as io;
+use linkerd_stack::{layer, NewService, Service};
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::ServiceExt;
+use tracing::{debug, debug_span, Instrument};
+
+/// Builds `ServeTunnel` services for each tunnel connection.
+#[derive(Clone, Debug)]
+pub struct NewServeTunnel<N> {
+    inner: N,
+}
+
+/// Serves a tunnel connection, dispatching each stream to an inner service
+/// built for the stream's target.
+///
+/// The inner stack is built with an `(Authority, T)` target.
+#[derive(Clone, Debug)]
+pub struct ServeTunnel<T, N> {
+    target: T,
+    inner: N,
+}
+
+// === impl NewServeTunnel ===
+
+impl<N> NewServeTunnel<N> {
+    pub fn layer() -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<T, N: Clone> NewService<T> for NewServeTunnel<N> {
+    type Service = ServeTunnel<T, N>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        ServeTunnel {
+            target,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+// === impl ServeTunnel ===
+
+impl<I, T, N, S> Service<I> for ServeTunnel<T, N>
+where
+    I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + Send + Unpin + 'static,
+    T: Clone + Send + 'static,
+    N: NewService<(http::uri::Authority, T), Service = S> + Clone + Send + 'static,
+    S: Service<Stream, Response = ()> + Send + 'static,
+    S::Error: Into<Error>,
+    S::Future: Send,
+{
+    type Response = ();
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, io: I) -> Self::Future {
+        let target = self.target.clone();
+        let mut inner = self.inner.clone();
+        Box::pin(async move {
+            let peer = io.peer_addr()?;
+            let mut conn = h2::server::Builder::new()
+                .initial_window_size(INITIAL_STREAM_WINDOW_SIZE)
+                .initial_connection_window_size(INITIAL_CONNECTION_WINDOW_SIZE)
+                .handshake::<_, bytes::Bytes>(io)
+                .await?;
+
+            while let Some(res) = conn.accept().await {
+                let (req, mut respond) = res?;
+                let authority = match req.uri().authority() {
+                    Some(a) if req.method() == http::Method::CONNECT => a.clone(),
+                    _ => {
+                        debug!(method = %req.method(), uri = %req.uri(), "Invalid tunnel request");
+                        let rsp = http::Response::builder()
+                            .status(http::StatusCode::BAD_REQUEST)
+                            .body(())
+                            .expect("response must be valid");
+                        let _ = respond.send_response(rsp, true);
+                        continue;
+                    }
+                };
+
+                let svc = inner.new_service((authority.clone(), target.clone()));
+                tokio::spawn(
+                    async move {
+                        let rsp = http::Response::builder()
+                            .status(http::StatusCode::OK)
+                            .body(())
+                            .expect("response must be valid");
+                        let send = respond.send_response(rsp, false)?;
+                        let stream = Stream::new(send, req.into_body(), peer);
+                        svc.oneshot(stream).err_into::<Error>().await
+                    }
+                    .map(|res| {
+                        if let Err(error) = res {
+                            debug!(%error, "Tunneled connection failed");
+                        }
+                    })
+                    .instrument(debug_span!("tunnel", dst = %authority)),
+                );
+            }
+
+            debug!("Tunnel closed");
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+    use io::{AsyncReadExt, AsyncWriteExt};
+    use linkerd_stack::layer::Layer;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn multiplexes_streams() {
+        let (client_io, server_io) = io::duplex(64 * 1024);
+        // Echoes each stream's data, prefixed by its target.
+        let echo = |(target, ()): (http::uri::Authority, ())| {
+            tower::service_fn(move |mut stream: Stream| {
+                let target = target.clone();
+                async move {
+                    stream.write_all(target.as_str().as_bytes()).await?;
+                    let mut buf = [0u8; 64];
+                    loop {
+                        let sz = stream.read(&mut buf).await?;
+                        if sz == 0 {
+                            break;
+                        }
+                        stream.write_all(&buf[..sz]).await?;
+                    }
+                    stream.shutdown().await?;
+                    Ok::<_, Error>(())
+                }
+            })
+        };
+        let mut server = NewServeTunnel::layer().layer(echo).new_service(());
+        let server = tokio::spawn(server.call(server_io));
+
+        let peer = ([192, 0, 2, 2], 4143).into();
+        let client = Client::handshake(client_io, peer).await.unwrap();
+        let mut a = client
+            .open("a.example.com:8080".parse().unwrap())
+            .await
+            .unwrap();
+        let mut b = client
+            .open("b.example.com:8080".parse().unwrap())
+            .await
+            .unwrap();
+
+        b.write_all(b"hello b").await.unwrap();
+        a.write_all(b"hello a").await.unwrap();
+        a.shutdown().await.unwrap();
+        b.shutdown().await.unwrap();
+
+        let mut buf = String::new();
+        a.read_to_string(&mut buf).await.unwrap();
+        assert_eq!(buf, "a.example.com:8080hello a");
+        let mut buf = String::new();
+        b.read_to_string(&mut buf).await.unwrap();
+        assert_eq!(buf, "b.example.com:8080hello b");
+
+        assert!(!client.is_closed());
+        drop((a, b, client));
+        server.await.unwrap().unwrap();
+    }
+}
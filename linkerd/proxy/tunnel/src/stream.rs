@@ -0,0 +1,131 @@
+use bytes::{Buf, Bytes};
+use futures::ready;
+use h2::{Reason, RecvStream, SendStream};
+use linkerd_io as io;
+use std::{
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// A tunneled connection.
+///
+/// Data is read from and written to a single HTTP/2 stream.
+#[derive(Debug)]
+pub struct Stream {
+    send: SendStream<Bytes>,
+    recv: RecvStream,
+    buf: Bytes,
+    peer: SocketAddr,
+}
+
+// === impl Stream ===
+
+impl Stream {
+    pub(crate) fn new(send: SendStream<Bytes>, recv: RecvStream, peer: SocketAddr) -> Self {
+        Self {
+            send,
+            recv,
+            buf: Bytes::new(),
+            peer,
+        }
+    }
+
+    /// Returns the error that reset the stream.
+    fn poll_reset(&mut self, cx: &mut Context<'_>) -> Poll<io::Error> {
+        let error = match ready!(self.send.poll_reset(cx)) {
+            Ok(Reason::NO_ERROR) | Ok(Reason::CANCEL) | Ok(Reason::STREAM_CLOSED) => {
+                io::ErrorKind::BrokenPipe.into()
+            }
+            Ok(reason) => h2_to_io_error(reason.into()),
+            Err(e) => h2_to_io_error(e),
+        };
+        Poll::Ready(error)
+    }
+}
+
+impl io::AsyncRead for Stream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut io::ReadBuf<'_>,
+    ) -> io::Poll<()> {
+        let this = self.get_mut();
+        if this.buf.is_empty() {
+            this.buf = loop {
+                match ready!(this.recv.poll_data(cx)) {
+                    None => return Poll::Ready(Ok(())),
+                    Some(Ok(data)) if data.is_empty() && !this.recv.is_end_stream() => continue,
+                    Some(Ok(data)) => break data,
+                    Some(Err(e)) => {
+                        return Poll::Ready(match e.reason() {
+                            Some(Reason::NO_ERROR) | Some(Reason::CANCEL) => Ok(()),
+                            Some(Reason::STREAM_CLOSED) => Err(io::ErrorKind::BrokenPipe.into()),
+                            _ => Err(h2_to_io_error(e)),
+                        })
+                    }
+                }
+            };
+        }
+
+        let sz = this.buf.len().min(buf.remaining());
+        buf.put_slice(&this.buf[..sz]);
+        this.buf.advance(sz);
+        let _ = this.recv.flow_control().release_capacity(sz);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl io::AsyncWrite for Stream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> io::Poll<usize> {
+        let this = self.get_mut();
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        // Errors are ignored here, since `poll_reset` describes why the
+        // stream failed.
+        this.send.reserve_capacity(buf.len());
+        let sent = match ready!(this.send.poll_capacity(cx)) {
+            None => Some(0),
+            Some(Ok(sz)) => this
+                .send
+                .send_data(Bytes::copy_from_slice(&buf[..sz]), false)
+                .ok()
+                .map(|()| sz),
+            Some(Err(_)) => None,
+        };
+        if let Some(sz) = sent {
+            return Poll::Ready(Ok(sz));
+        }
+
+        this.poll_reset(cx).map(Err)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> io::Poll<()> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Poll<()> {
+        let this = self.get_mut();
+        if this.send.send_data(Bytes::new(), true).is_ok() {
+            return Poll::Ready(Ok(()));
+        }
+
+        this.poll_reset(cx).map(Err)
+    }
+}
+
+impl io::PeerAddr for Stream {
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.peer)
+    }
+}
+
+pub(crate) fn h2_to_io_error(e: h2::Error) -> io::Error {
+    if e.is_io() {
+        e.into_io().expect("error must be an I/O error")
+    } else {
+        io::Error::new(io::ErrorKind::Other, e)
+    }
+}
@@ -3,6 +3,8 @@ mod network;
 pub use self::network::Network;
 use std::{
     collections::{HashMap, HashSet},
+    fmt,
+    net::SocketAddr,
     time,
 };
 
@@ -11,23 +13,116 @@ pub struct ServerPolicy {
     pub protocol: Protocol,
     pub authorizations: Vec<Authorization>,
     pub labels: HashMap<String, String>,
+
+    /// Indicates whether a connection's verified client identity should be
+    /// forwarded to the application in a request header.
+    pub forward_client_id: bool,
+
+    /// Overrides the address to which the port's connections are forwarded.
+    /// By default, connections are forwarded to the loopback address on their
+    /// original destination port.
+    pub forward_addr: Option<SocketAddr>,
+
+    /// HTTP requests that are permitted without authorization (e.g. health
+    /// checks). These requests are still recorded in metrics.
+    pub exempt_routes: Vec<ExemptRoute>,
+
+    /// HTTP probe requests that are answered by the proxy, reflecting the
+    /// application's readiness as determined by a local check.
+    pub probes: Vec<Probe>,
+
+    /// Overrides the response to HTTP requests that are denied because their
+    /// connection is not authorized. When set, unauthorized HTTP connections
+    /// are accepted so that their requests may be answered with this
+    /// response.
+    pub deny_response: Option<DenyResponse>,
+
+    /// Indicates whether the policy is only audited: connections that it
+    /// would deny are logged and counted, but permitted.
+    pub audit: bool,
+}
+
+/// A static HTTP response returned to unauthorized requests, e.g. to direct
+/// clients to the server's owners.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DenyResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// Identifies HTTP requests by method and path.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ExemptRoute {
+    /// If unset, requests with any method match.
+    pub method: Option<String>,
+    pub path: String,
+}
+
+/// A probe path that is answered by the proxy rather than the application.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Probe {
+    pub path: String,
+    pub check: ProbeCheck,
+}
+
+/// Determines whether the application is ready to serve probe requests.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ProbeCheck {
+    /// The application is ready if a TCP connection can be established. This
+    /// is suitable for applications that don't speak HTTP, e.g. server-first
+    /// protocols.
+    Tcp(SocketAddr),
+
+    /// The application is ready if it responds to an HTTP/1.1 `GET` request
+    /// with a successful (2xx or 3xx) status.
+    Http { addr: SocketAddr, path: String },
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Protocol {
-    Detect { timeout: time::Duration },
+    Detect {
+        timeout: time::Duration,
+    },
     Http1,
     Http2,
     Grpc,
     Opaque,
     Tls,
+    /// The server writes before the client (e.g. MySQL), so protocol
+    /// detection is skipped rather than waiting for the client to write.
+    ServerSpeaksFirst,
+    /// The server terminates its own TLS, so the proxy forwards connections
+    /// without attempting TLS termination or protocol detection.
+    PassthroughTls,
 }
 
+/// Permits clients that match both the authorization's networks and its
+/// authentication, e.g. so that an identity is only permitted from a given
+/// network.
+///
+/// A server's authorizations are evaluated in order, and the first that
+/// permits a client determines its labels and priority.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Authorization {
     pub networks: Vec<Network>,
     pub authentication: Authentication,
     pub labels: HashMap<String, String>,
+
+    /// The priority of the authorized clients' requests when the proxy is
+    /// overloaded.
+    pub priority: Priority,
+}
+
+/// Determines which requests are rejected first when the proxy is overloaded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Rejected before other requests, e.g. for batch clients.
+    Low,
+    Normal,
+    /// Never rejected due to overload, e.g. for health checks and control
+    /// traffic.
+    High,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -38,6 +133,12 @@ pub enum Authentication {
         identities: HashSet<String>,
         suffixes: Vec<Suffix>,
     },
+    /// Permits TLS connections that are not terminated by the proxy (i.e.
+    /// non-mesh TLS) when the client's SNI matches one of the given names.
+    TlsServerName {
+        names: HashSet<String>,
+        suffixes: Vec<Suffix>,
+    },
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -45,6 +146,84 @@ pub struct Suffix {
     ends_with: String,
 }
 
+// === impl Authorization ===
+
+impl Authorization {
+    /// Returns true if the client's address is in any of the authorization's
+    /// networks.
+    #[inline]
+    pub fn contains_client(&self, ip: &std::net::IpAddr) -> bool {
+        self.networks.iter().any(|n| n.contains(ip))
+    }
+}
+
+// === impl Authentication ===
+
+impl Authentication {
+    /// Returns true if the given name is one of the names, or is in one of the
+    /// suffixes, that this authentication permits. Always false for
+    /// authentications that don't name clients.
+    pub fn permits_name(&self, name: &str) -> bool {
+        match self {
+            Self::TlsAuthenticated {
+                identities: names,
+                suffixes,
+            }
+            | Self::TlsServerName { names, suffixes } => {
+                names.contains(name) || suffixes.iter().any(|s| s.contains(name))
+            }
+            Self::Unauthenticated | Self::TlsUnauthenticated => false,
+        }
+    }
+}
+
+// === impl Priority ===
+
+impl Default for Priority {
+    fn default() -> Self {
+        Self::Normal
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Low => "low".fmt(f),
+            Self::Normal => "normal".fmt(f),
+            Self::High => "high".fmt(f),
+        }
+    }
+}
+
+// === impl ExemptRoute ===
+
+impl ExemptRoute {
+    #[inline]
+    pub fn matches(&self, method: &str, path: &str) -> bool {
+        self.method.as_ref().map(|m| m == method).unwrap_or(true) && self.path == path
+    }
+}
+
+impl fmt::Display for ExemptRoute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.method {
+            Some(ref method) => write!(f, "{} {}", method, self.path),
+            None => self.path.fmt(f),
+        }
+    }
+}
+
+// === impl ProbeCheck ===
+
+impl fmt::Display for ProbeCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "tcp:{}", addr),
+            Self::Http { addr, path } => write!(f, "http:{}{}", addr, path),
+        }
+    }
+}
+
 // === impl Suffix ===
 
 impl From<Vec<String>> for Suffix {
@@ -106,5 +285,18 @@ mod network_tests {
             }
             TestResult::from_bool(net.contains(&addr.into()))
         }
+
+        fn contains_v4_mapped(addr: Ipv4Addr, exclude: Option<Ipv4Addr>) -> TestResult {
+            let net = Network {
+                net: Ipv4Net::default().into(),
+                except: exclude.into_iter().map(|a| IpNet::from(IpAddr::V4(a))).collect(),
+            };
+
+            let mapped = IpAddr::V6(addr.to_ipv6_mapped());
+            if exclude == Some(addr) {
+                return TestResult::from_bool(!net.contains(&mapped));
+            }
+            TestResult::from_bool(net.contains(&mapped))
+        }
     }
 }
@@ -48,9 +48,16 @@ impl From<std::net::Ipv6Addr> for Network {
 }
 
 impl Network {
+    /// Matches IPv4-mapped IPv6 addresses (i.e. `::ffff:a.b.c.d`) by both their
+    /// IPv6 and IPv4 forms, so that clients of dual-stack sockets match IPv4
+    /// networks.
     #[inline]
     pub fn contains(&self, ip: &std::net::IpAddr) -> bool {
-        self.net.contains(ip) && !self.except.iter().any(|net| net.contains(ip))
+        let ips = [*ip, to_ipv4_mapped(ip).unwrap_or(*ip)];
+        ips.iter().any(|ip| self.net.contains(ip))
+            && !ips
+                .iter()
+                .any(|ip| self.except.iter().any(|net| net.contains(ip)))
     }
 }
 
@@ -65,3 +72,17 @@ impl std::str::FromStr for Network {
         })
     }
 }
+
+fn to_ipv4_mapped(ip: &std::net::IpAddr) -> Option<std::net::IpAddr> {
+    match ip {
+        std::net::IpAddr::V6(ip) => match ip.segments() {
+            [0, 0, 0, 0, 0, 0xffff, hi, lo] => {
+                let [a, b] = hi.to_be_bytes();
+                let [c, d] = lo.to_be_bytes();
+                Some(std::net::Ipv4Addr::new(a, b, c, d).into())
+            }
+            _ => None,
+        },
+        std::net::IpAddr::V4(_) => None,
+    }
+}
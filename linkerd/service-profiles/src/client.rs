@@ -3,18 +3,34 @@ use futures::prelude::*;
 use http_body::Body;
 use linkerd2_proxy_api::destination::{self as api, destination_client::DestinationClient};
 use linkerd_error::{Infallible, Recover};
+use linkerd_metrics::{metrics, Counter, FmtMetrics};
 use linkerd_stack::{Param, Service};
 use linkerd_tonic_watch::StreamWatch;
-use std::task::{Context, Poll};
+use std::{
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+};
 use tonic::{body::BoxBody, client::GrpcService};
 use tracing::debug;
 
+metrics! {
+    profiles_resynced_total: Counter {
+        "Total number of profile watches that were re-issued after the profile client reconnected"
+    }
+}
+
 /// Creates watches on service profiles.
 #[derive(Clone, Debug)]
 pub struct Client<R, S> {
     watch: StreamWatch<R, Inner<S>>,
+    resynced: Arc<Counter>,
 }
 
+/// Reports the number of profile watches that have been resynced.
+#[derive(Clone, Debug)]
+pub struct Metrics(Arc<Counter>);
+
 /// Wraps the destination service to hide protobuf types.
 #[derive(Clone, Debug)]
 struct Inner<S> {
@@ -36,9 +52,14 @@ where
     R::Backoff: Unpin + Send,
 {
     pub fn new(recover: R, inner: S, context_token: String) -> Self {
-        Self {
-            watch: StreamWatch::new(recover, Inner::new(context_token, inner)),
-        }
+        let resynced = Arc::new(Counter::default());
+        let watch = StreamWatch::new(recover, Inner::new(context_token, inner))
+            .with_resynced_counter(resynced.clone());
+        Self { watch, resynced }
+    }
+
+    pub fn metrics(&self) -> Metrics {
+        Metrics(self.resynced.clone())
     }
 }
 
@@ -83,6 +104,15 @@ where
     }
 }
 
+// === impl Metrics ===
+
+impl FmtMetrics for Metrics {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        profiles_resynced_total.fmt_help(f)?;
+        profiles_resynced_total.fmt_metric(f, &*self.0)
+    }
+}
+
 // === impl Inner ===
 
 type InnerStream = futures::stream::BoxStream<'static, Result<Profile, tonic::Status>>;
@@ -0,0 +1,196 @@
+use super::{Fault, Route};
+use futures::{ready, TryFuture};
+use linkerd_stack::{layer, NewService, Param, Proxy};
+use pin_project::pin_project;
+use rand::Rng;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::time;
+use tracing::debug;
+
+/// Builds `InjectFault` proxies from a route's fault.
+#[derive(Clone, Debug)]
+pub struct NewInjectFault<N> {
+    inner: N,
+}
+
+/// Injects aborts and delays into the requests on a route.
+///
+/// Aborted requests are not forwarded. Delays are applied before the
+/// response is returned to the caller.
+#[derive(Clone, Debug)]
+pub struct InjectFault<P> {
+    fault: Option<Fault>,
+    inner: P,
+}
+
+#[pin_project(project = ResponseFutureProj)]
+#[derive(Debug)]
+pub enum ResponseFuture<F> {
+    Forward(#[pin] F),
+    Delay {
+        #[pin]
+        sleep: time::Sleep,
+        #[pin]
+        inner: F,
+    },
+    Abort(Option<http::StatusCode>),
+}
+
+// === impl NewInjectFault ===
+
+impl<N> NewInjectFault<N> {
+    pub fn layer() -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<T, N> NewService<T> for NewInjectFault<N>
+where
+    T: Param<Route>,
+    N: NewService<T>,
+{
+    type Service = InjectFault<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let route: Route = target.param();
+        let fault = route.fault().filter(|f| !f.is_empty()).cloned();
+        InjectFault {
+            fault,
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl InjectFault ===
+
+impl<P, S, A, B> Proxy<http::Request<A>, S> for InjectFault<P>
+where
+    P: Proxy<http::Request<A>, S, Response = http::Response<B>>,
+    S: tower::Service<P::Request>,
+    B: Default,
+{
+    type Request = P::Request;
+    type Response = P::Response;
+    type Error = P::Error;
+    type Future = ResponseFuture<P::Future>;
+
+    fn proxy(&self, svc: &mut S, req: http::Request<A>) -> Self::Future {
+        let fault = match self.fault.as_ref() {
+            Some(fault) => fault,
+            None => return ResponseFuture::Forward(self.inner.proxy(svc, req)),
+        };
+
+        let mut rng = rand::thread_rng();
+        if let Some((status, probability)) = fault.abort() {
+            if rng.gen_bool(probability) {
+                debug!(%status, "Injecting abort");
+                return ResponseFuture::Abort(Some(status));
+            }
+        }
+
+        if let Some((duration, probability)) = fault.delay() {
+            if rng.gen_bool(probability) {
+                debug!(?duration, "Injecting delay");
+                return ResponseFuture::Delay {
+                    sleep: time::sleep(duration),
+                    inner: self.inner.proxy(svc, req),
+                };
+            }
+        }
+
+        ResponseFuture::Forward(self.inner.proxy(svc, req))
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F, B> Future for ResponseFuture<F>
+where
+    F: TryFuture<Ok = http::Response<B>>,
+    B: Default,
+{
+    type Output = Result<http::Response<B>, F::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            ResponseFutureProj::Forward(inner) => inner.try_poll(cx),
+            ResponseFutureProj::Delay { sleep, inner } => {
+                ready!(sleep.poll(cx));
+                inner.try_poll(cx)
+            }
+            ResponseFutureProj::Abort(status) => {
+                let mut rsp = http::Response::new(B::default());
+                *rsp.status_mut() = status.take().expect("polled after ready");
+                Poll::Ready(Ok(rsp))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+    use std::{convert::Infallible, time::Duration};
+
+    type Rsp = Result<http::Response<()>, Infallible>;
+
+    fn inject(fault: Fault) -> InjectFault<()> {
+        InjectFault {
+            fault: Some(fault),
+            inner: (),
+        }
+    }
+
+    fn ok() -> tower::util::ServiceFn<fn(http::Request<()>) -> future::Ready<Rsp>> {
+        fn ok(_: http::Request<()>) -> future::Ready<Rsp> {
+            future::ok(http::Response::new(()))
+        }
+        tower::service_fn(ok as fn(_) -> _)
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn aborts() {
+        let fault = Fault::default().with_abort(http::StatusCode::SERVICE_UNAVAILABLE, 1.0);
+        let rsp = inject(fault)
+            .proxy(&mut ok(), http::Request::new(()))
+            .await
+            .unwrap();
+        assert_eq!(rsp.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+
+        let fault = Fault::default().with_abort(http::StatusCode::SERVICE_UNAVAILABLE, 0.0);
+        let rsp = inject(fault)
+            .proxy(&mut ok(), http::Request::new(()))
+            .await
+            .unwrap();
+        assert_eq!(rsp.status(), http::StatusCode::OK);
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn delays() {
+        let fault = Fault::default().with_delay(Duration::from_secs(3), 1.0);
+        let start = time::Instant::now();
+        let rsp = inject(fault)
+            .proxy(&mut ok(), http::Request::new(()))
+            .await
+            .unwrap();
+        assert_eq!(rsp.status(), http::StatusCode::OK);
+        assert!(start.elapsed() >= Duration::from_secs(3));
+    }
+
+    #[test]
+    fn clamps_probabilities() {
+        let fault = Fault::default()
+            .with_abort(http::StatusCode::SERVICE_UNAVAILABLE, f64::NAN)
+            .with_delay(Duration::from_secs(1), 2.0);
+        assert_eq!(
+            fault.abort(),
+            Some((http::StatusCode::SERVICE_UNAVAILABLE, 0.0))
+        );
+        assert_eq!(fault.delay(), Some((Duration::from_secs(1), 1.0)));
+    }
+}
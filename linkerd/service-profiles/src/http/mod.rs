@@ -8,14 +8,31 @@ use std::{
 };
 use tower::retry::budget::Budget;
 
+mod inject_fault;
+mod modify_headers;
+mod rewrite_path;
 pub mod route_request;
 
+pub use self::{
+    inject_fault::{InjectFault, NewInjectFault},
+    modify_headers::{ModifyHeaders, NewModifyHeaders},
+    rewrite_path::{NewRewritePath, RewritePath},
+};
+
 #[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct Route {
     labels: Labels,
     response_classes: ResponseClasses,
     retries: Option<Retries>,
     timeout: Option<Duration>,
+    per_try_timeout: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    request_headers: Arc<HeaderModifier>,
+    response_headers: Arc<HeaderModifier>,
+    path_rewrite: Option<PathRewrite>,
+    fault: Option<Fault>,
+    idempotency_ttl: Option<Duration>,
+    cache_responses: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -47,6 +64,34 @@ pub enum ResponseMatch {
     },
 }
 
+/// Modifies the headers of a route's requests or responses.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HeaderModifier {
+    /// Headers whose values are replaced.
+    pub set: Vec<(http::header::HeaderName, http::HeaderValue)>,
+
+    /// Headers whose values are appended to any existing values.
+    pub add: Vec<(http::header::HeaderName, http::HeaderValue)>,
+
+    /// Headers that are removed.
+    pub remove: Vec<http::header::HeaderName>,
+}
+
+/// Replaces a matching path prefix before a request is forwarded.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PathRewrite {
+    prefix: String,
+    replacement: String,
+}
+
+/// Injects aborts and delays into a route's requests so that an application's
+/// resilience can be tested.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Fault {
+    abort: Option<(http::StatusCode, f64)>,
+    delay: Option<(Duration, f64)>,
+}
+
 #[derive(Clone, Debug)]
 pub struct Retries {
     budget: Arc<Budget>,
@@ -69,6 +114,14 @@ impl Route {
             response_classes: ResponseClasses(response_classes.into()),
             retries: None,
             timeout: None,
+            per_try_timeout: None,
+            idle_timeout: None,
+            request_headers: Default::default(),
+            response_headers: Default::default(),
+            path_rewrite: None,
+            fault: None,
+            idempotency_ttl: None,
+            cache_responses: false,
         }
     }
 
@@ -84,10 +137,21 @@ impl Route {
         self.retries.as_ref()
     }
 
+    /// The total time allowed for a response, including all retries.
     pub fn timeout(&self) -> Option<Duration> {
         self.timeout
     }
 
+    /// The time allowed for each attempt to produce a response.
+    pub fn per_try_timeout(&self) -> Option<Duration> {
+        self.per_try_timeout
+    }
+
+    /// The time allowed without progress on a response stream.
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
     pub fn set_retries(&mut self, budget: Arc<Budget>) {
         self.retries = Some(Retries { budget });
     }
@@ -95,6 +159,66 @@ impl Route {
     pub fn set_timeout(&mut self, timeout: Duration) {
         self.timeout = Some(timeout);
     }
+
+    pub fn set_per_try_timeout(&mut self, timeout: Duration) {
+        self.per_try_timeout = Some(timeout);
+    }
+
+    pub fn set_idle_timeout(&mut self, timeout: Duration) {
+        self.idle_timeout = Some(timeout);
+    }
+
+    pub fn request_headers(&self) -> &Arc<HeaderModifier> {
+        &self.request_headers
+    }
+
+    pub fn response_headers(&self) -> &Arc<HeaderModifier> {
+        &self.response_headers
+    }
+
+    pub fn set_request_headers(&mut self, modifier: HeaderModifier) {
+        self.request_headers = Arc::new(modifier);
+    }
+
+    pub fn set_response_headers(&mut self, modifier: HeaderModifier) {
+        self.response_headers = Arc::new(modifier);
+    }
+
+    pub fn path_rewrite(&self) -> Option<&PathRewrite> {
+        self.path_rewrite.as_ref()
+    }
+
+    pub fn set_path_rewrite(&mut self, rewrite: PathRewrite) {
+        self.path_rewrite = Some(rewrite);
+    }
+
+    pub fn fault(&self) -> Option<&Fault> {
+        self.fault.as_ref()
+    }
+
+    pub fn set_fault(&mut self, fault: Fault) {
+        self.fault = Some(fault);
+    }
+
+    /// How long responses to requests with an idempotency key are replayed
+    /// to requests that repeat the key. Routes without a TTL are not
+    /// deduplicated.
+    pub fn idempotency_ttl(&self) -> Option<Duration> {
+        self.idempotency_ttl
+    }
+
+    pub fn set_idempotency_ttl(&mut self, ttl: Duration) {
+        self.idempotency_ttl = Some(ttl);
+    }
+
+    /// Whether responses to GET requests on this route may be cached.
+    pub fn cache_responses(&self) -> bool {
+        self.cache_responses
+    }
+
+    pub fn set_cache_responses(&mut self, cache: bool) {
+        self.cache_responses = cache;
+    }
 }
 
 // === impl RequestMatch ===
@@ -172,6 +296,119 @@ impl ResponseMatch {
     }
 }
 
+// === impl HeaderModifier ===
+
+impl HeaderModifier {
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.add.is_empty() && self.remove.is_empty()
+    }
+
+    /// Applies the modifications to a header map. Headers are removed before
+    /// values are set or added.
+    pub fn apply(&self, headers: &mut http::HeaderMap) {
+        for name in &self.remove {
+            headers.remove(name);
+        }
+        for (name, value) in &self.set {
+            headers.insert(name.clone(), value.clone());
+        }
+        for (name, value) in &self.add {
+            headers.append(name.clone(), value.clone());
+        }
+    }
+}
+
+// === impl PathRewrite ===
+
+impl PathRewrite {
+    pub fn prefix(prefix: impl Into<String>, replacement: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            replacement: replacement.into(),
+        }
+    }
+
+    /// Returns the rewritten URI if its path starts with the prefix.
+    ///
+    /// Prefixes only match whole path segments, so `/v1` matches `/v1` and
+    /// `/v1/foo` but not `/v10`. The query string is preserved.
+    pub fn rewrite(&self, uri: &http::Uri) -> Option<http::Uri> {
+        let rest = uri.path().strip_prefix(self.prefix.trim_end_matches('/'))?;
+        if !rest.is_empty() && !rest.starts_with('/') {
+            return None;
+        }
+
+        let mut path = match self.replacement.trim_matches('/') {
+            "" if rest.is_empty() => "/".to_string(),
+            "" => rest.to_string(),
+            replacement => format!("/{}{}", replacement, rest),
+        };
+        if let Some(query) = uri.query() {
+            path.push('?');
+            path.push_str(query);
+        }
+
+        let mut parts = uri.clone().into_parts();
+        parts.path_and_query = Some(path.parse().ok()?);
+        http::Uri::from_parts(parts).ok()
+    }
+}
+
+// === impl Fault ===
+
+impl Fault {
+    /// Fails the given fraction of requests with `status`, without
+    /// forwarding them.
+    pub fn with_abort(self, status: http::StatusCode, probability: f64) -> Self {
+        Self {
+            abort: Some((status, clamp(probability))),
+            ..self
+        }
+    }
+
+    /// Delays the given fraction of requests by `duration`.
+    pub fn with_delay(self, duration: Duration, probability: f64) -> Self {
+        Self {
+            delay: Some((duration, clamp(probability))),
+            ..self
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.abort.is_none() && self.delay.is_none()
+    }
+
+    pub fn abort(&self) -> Option<(http::StatusCode, f64)> {
+        self.abort
+    }
+
+    pub fn delay(&self) -> Option<(Duration, f64)> {
+        self.delay
+    }
+}
+
+/// Limits a probability to `[0, 1]`, treating NaN as 0.
+fn clamp(probability: f64) -> f64 {
+    probability.max(0.0).min(1.0)
+}
+
+// Probabilities are clamped and never NaN, so faults may be compared and hashed by their
+// probabilities' bits.
+impl Eq for Fault {}
+
+impl Hash for Fault {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        if let Some((status, probability)) = self.abort {
+            status.hash(state);
+            probability.to_bits().hash(state);
+        }
+        if let Some((duration, probability)) = self.delay {
+            duration.hash(state);
+            probability.to_bits().hash(state);
+        }
+    }
+}
+
 // === impl Retries ===
 
 impl Retries {
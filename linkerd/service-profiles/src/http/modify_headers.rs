@@ -0,0 +1,137 @@
+use super::{HeaderModifier, Route};
+use futures::{ready, TryFuture};
+use linkerd_stack::{layer, NewService, Param, Proxy};
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+/// Builds `ModifyHeaders` proxies from a route's header modifiers.
+#[derive(Clone, Debug)]
+pub struct NewModifyHeaders<N> {
+    inner: N,
+}
+
+/// Modifies the headers of requests and responses on a route.
+#[derive(Clone, Debug)]
+pub struct ModifyHeaders<P> {
+    request: Arc<HeaderModifier>,
+    response: Arc<HeaderModifier>,
+    inner: P,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    response: Option<Arc<HeaderModifier>>,
+}
+
+// === impl NewModifyHeaders ===
+
+impl<N> NewModifyHeaders<N> {
+    pub fn layer() -> impl layer::Layer<N, Service = Self> + Clone + Copy {
+        layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<T, N> NewService<T> for NewModifyHeaders<N>
+where
+    T: Param<Route>,
+    N: NewService<T>,
+{
+    type Service = ModifyHeaders<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let route: Route = target.param();
+        ModifyHeaders {
+            request: route.request_headers().clone(),
+            response: route.response_headers().clone(),
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl ModifyHeaders ===
+
+impl<P, S, A, B> Proxy<http::Request<A>, S> for ModifyHeaders<P>
+where
+    P: Proxy<http::Request<A>, S, Response = http::Response<B>>,
+    S: tower::Service<P::Request>,
+{
+    type Request = P::Request;
+    type Response = P::Response;
+    type Error = P::Error;
+    type Future = ResponseFuture<P::Future>;
+
+    fn proxy(&self, svc: &mut S, mut req: http::Request<A>) -> Self::Future {
+        self.request.apply(req.headers_mut());
+        let response = if self.response.is_empty() {
+            None
+        } else {
+            Some(self.response.clone())
+        };
+        ResponseFuture {
+            inner: self.inner.proxy(svc, req),
+            response,
+        }
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F, B> Future for ResponseFuture<F>
+where
+    F: TryFuture<Ok = http::Response<B>>,
+{
+    type Output = Result<http::Response<B>, F::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut rsp = ready!(this.inner.try_poll(cx))?;
+        if let Some(modifier) = this.response.take() {
+            modifier.apply(rsp.headers_mut());
+        }
+        Poll::Ready(Ok(rsp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::{header::HeaderName, HeaderMap, HeaderValue};
+
+    #[test]
+    fn apply() {
+        let modifier = HeaderModifier {
+            set: vec![(
+                HeaderName::from_static("authorization"),
+                HeaderValue::from_static("Basic Zm9vOmJhcg=="),
+            )],
+            add: vec![(
+                HeaderName::from_static("x-via"),
+                HeaderValue::from_static("egress"),
+            )],
+            remove: vec![HeaderName::from_static("x-internal")],
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_static("Bearer abc"));
+        headers.insert("x-via", HeaderValue::from_static("app"));
+        headers.insert("x-internal", HeaderValue::from_static("secret"));
+        modifier.apply(&mut headers);
+
+        assert_eq!(headers["authorization"], "Basic Zm9vOmJhcg==");
+        assert_eq!(
+            headers.get_all("x-via").iter().collect::<Vec<_>>(),
+            vec!["app", "egress"]
+        );
+        assert!(!headers.contains_key("x-internal"));
+        assert!(!modifier.is_empty());
+        assert!(HeaderModifier::default().is_empty());
+    }
+}
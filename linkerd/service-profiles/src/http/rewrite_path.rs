@@ -0,0 +1,96 @@
+use super::{PathRewrite, Route};
+use linkerd_stack::{layer, NewService, Param, Proxy};
+use tracing::debug;
+
+/// Builds `RewritePath` proxies from a route's path rewrite.
+#[derive(Clone, Debug)]
+pub struct NewRewritePath<N> {
+    inner: N,
+}
+
+/// Rewrites the path of requests on a route before they are forwarded.
+#[derive(Clone, Debug)]
+pub struct RewritePath<P> {
+    rewrite: Option<PathRewrite>,
+    inner: P,
+}
+
+// === impl NewRewritePath ===
+
+impl<N> NewRewritePath<N> {
+    pub fn layer() -> impl layer::Layer<N, Service = Self> + Clone + Copy {
+        layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<T, N> NewService<T> for NewRewritePath<N>
+where
+    T: Param<Route>,
+    N: NewService<T>,
+{
+    type Service = RewritePath<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let route: Route = target.param();
+        RewritePath {
+            rewrite: route.path_rewrite().cloned(),
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl RewritePath ===
+
+impl<P, S, B> Proxy<http::Request<B>, S> for RewritePath<P>
+where
+    P: Proxy<http::Request<B>, S>,
+    S: tower::Service<P::Request>,
+{
+    type Request = P::Request;
+    type Response = P::Response;
+    type Error = P::Error;
+    type Future = P::Future;
+
+    fn proxy(&self, svc: &mut S, mut req: http::Request<B>) -> Self::Future {
+        if let Some(uri) = self.rewrite.as_ref().and_then(|r| r.rewrite(req.uri())) {
+            debug!(from = %req.uri(), to = %uri, "Rewriting path");
+            *req.uri_mut() = uri;
+        }
+        self.inner.proxy(svc, req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(prefix: &str, replacement: &str, uri: &str) -> Option<String> {
+        PathRewrite::prefix(prefix, replacement)
+            .rewrite(&uri.parse().unwrap())
+            .map(|uri| uri.to_string())
+    }
+
+    #[test]
+    fn rewrites_prefix() {
+        assert_eq!(
+            rewrite("/v1/foo", "/foo", "/v1/foo").as_deref(),
+            Some("/foo")
+        );
+        assert_eq!(
+            rewrite("/v1/foo", "/foo", "/v1/foo/bar?baz=1").as_deref(),
+            Some("/foo/bar?baz=1")
+        );
+        assert_eq!(
+            rewrite("/v1/", "/", "http://example.com/v1/foo").as_deref(),
+            Some("http://example.com/foo")
+        );
+        assert_eq!(rewrite("/v1", "/", "/v1").as_deref(), Some("/"));
+        assert_eq!(rewrite("/", "/api", "/foo").as_deref(), Some("/api/foo"));
+    }
+
+    #[test]
+    fn ignores_partial_segments() {
+        assert_eq!(rewrite("/v1", "/", "/v10/foo"), None);
+        assert_eq!(rewrite("/v1", "/", "/foo/v1"), None);
+    }
+}
@@ -21,10 +21,14 @@ mod client;
 mod default;
 pub mod discover;
 pub mod http;
+mod opaque;
 mod proto;
 pub mod split;
 
-pub use self::client::Client;
+pub use self::{
+    client::{Client, Metrics},
+    opaque::{NewRebuildOnOpaqueChange, RebuildOnOpaqueChange},
+};
 
 #[derive(Clone, Debug)]
 pub struct Receiver {
@@ -0,0 +1,129 @@
+use super::Receiver;
+use linkerd_stack::{layer, NewService};
+use std::task::{Context, Poll};
+use tracing::debug;
+
+/// Rebuilds a target's service when its profile's opaque-protocol hint
+/// changes.
+///
+/// Services are usually cached by their original destination, so the
+/// protocol hint that was current when the service was built would otherwise
+/// be used until the service is evicted. This ensures that new connections
+/// observe the control plane's current hint.
+#[derive(Clone, Debug)]
+pub struct NewRebuildOnOpaqueChange<N> {
+    inner: N,
+}
+
+#[derive(Debug)]
+pub struct RebuildOnOpaqueChange<T, N: NewService<(Option<Receiver>, T)>> {
+    target: T,
+    profile: Option<Receiver>,
+    opaque: bool,
+    new_inner: N,
+    inner: N::Service,
+}
+
+// === impl NewRebuildOnOpaqueChange ===
+
+impl<N> NewRebuildOnOpaqueChange<N> {
+    pub fn layer() -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<T, N> NewService<(Option<Receiver>, T)> for NewRebuildOnOpaqueChange<N>
+where
+    T: Clone,
+    N: NewService<(Option<Receiver>, T)> + Clone,
+{
+    type Service = RebuildOnOpaqueChange<T, N>;
+
+    fn new_service(&mut self, (profile, target): (Option<Receiver>, T)) -> Self::Service {
+        let opaque = profile
+            .as_ref()
+            .map(Receiver::is_opaque_protocol)
+            .unwrap_or(false);
+        let inner = self.inner.new_service((profile.clone(), target.clone()));
+        RebuildOnOpaqueChange {
+            target,
+            profile,
+            opaque,
+            new_inner: self.inner.clone(),
+            inner,
+        }
+    }
+}
+
+// === impl RebuildOnOpaqueChange ===
+
+impl<T, N, Req> tower::Service<Req> for RebuildOnOpaqueChange<T, N>
+where
+    T: Clone,
+    N: NewService<(Option<Receiver>, T)>,
+    N::Service: tower::Service<Req>,
+{
+    type Response = <N::Service as tower::Service<Req>>::Response;
+    type Error = <N::Service as tower::Service<Req>>::Error;
+    type Future = <N::Service as tower::Service<Req>>::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if let Some(profile) = self.profile.as_ref() {
+            let opaque = profile.is_opaque_protocol();
+            if opaque != self.opaque {
+                debug!(opaque, "Protocol hint changed; rebuilding service");
+                self.opaque = opaque;
+                self.inner = self
+                    .new_inner
+                    .new_service((self.profile.clone(), self.target.clone()));
+            }
+        }
+
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Profile;
+    use linkerd_stack::layer::Layer;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use tokio::sync::watch;
+    use tower::{Service, ServiceExt};
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn rebuilds_on_opaque_change() {
+        let (tx, rx) = watch::channel(Profile::default());
+        let built = Arc::new(AtomicUsize::new(0));
+        let mut new_svc = NewRebuildOnOpaqueChange::layer().layer({
+            let built = built.clone();
+            move |(profile, ()): (Option<Receiver>, ())| {
+                built.fetch_add(1, Ordering::SeqCst);
+                let opaque = profile.unwrap().is_opaque_protocol();
+                tower::service_fn(move |()| futures::future::ok::<_, ()>(opaque))
+            }
+        });
+
+        let mut svc = new_svc.new_service((Some(rx.into()), ()));
+        assert!(!svc.ready().await.unwrap().call(()).await.unwrap());
+        assert!(!svc.ready().await.unwrap().call(()).await.unwrap());
+        assert_eq!(built.load(Ordering::SeqCst), 1);
+
+        tx.send(Profile {
+            opaque_protocol: true,
+            ..Profile::default()
+        })
+        .unwrap();
+        assert!(svc.ready().await.unwrap().call(()).await.unwrap());
+        assert_eq!(built.load(Ordering::SeqCst), 2);
+    }
+}
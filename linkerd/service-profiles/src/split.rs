@@ -4,72 +4,127 @@ use indexmap::IndexSet;
 use linkerd_addr::NameAddr;
 use linkerd_error::Error;
 use linkerd_proxy_api_resolve::ConcreteAddr;
-use linkerd_stack::{layer, NewService, Param};
+use linkerd_stack::{layer, ExtractParam, NewService, Param};
+use pin_project::pin_project;
 use rand::distributions::{Distribution, WeightedIndex};
 use rand::{rngs::SmallRng, thread_rng, SeedableRng};
 use std::{
+    collections::HashMap,
     marker::PhantomData,
     pin::Pin,
+    str::FromStr,
     task::{Context, Poll},
 };
+use thiserror::Error;
 use tower::ready_cache::ReadyCache;
 use tracing::{debug, trace};
 
+mod health;
+
+use self::health::Backend;
+pub use self::health::{Isolation, Registry};
+
+/// The header that carries a one-off set of split weights for a request.
+pub const L5D_DST_WEIGHTS: &str = "l5d-dst-weights";
+
+/// The debugging header that describes the concrete target, and its weight,
+/// that a request was split to.
+pub const L5D_DST_CONCRETE: &str = "l5d-dst-concrete";
+
 pub fn layer<N, S, Req>() -> impl layer::Layer<N, Service = NewSplit<N, S, Req>> + Clone {
+    layer_with_overrides(NoOverrides(()))
+}
+
+/// Like `layer`, except that requests may override the profile's split
+/// weights with weights extracted by `overrides`.
+pub fn layer_with_overrides<N, S, Req, X: Clone>(
+    overrides: X,
+) -> impl layer::Layer<N, Service = NewSplit<N, S, Req, X>> + Clone {
+    layer_with_isolation(overrides, None, Registry::default())
+}
+
+/// Like `layer_with_overrides`, except that, when `isolation` is configured,
+/// targets that fail consistently are temporarily removed from the split.
+///
+/// The configured and effective weights of each target are recorded in
+/// `registry`.
+pub fn layer_with_isolation<N, S, Req, X: Clone>(
+    overrides: X,
+    isolation: Option<Isolation>,
+    registry: Registry,
+) -> impl layer::Layer<N, Service = NewSplit<N, S, Req, X>> + Clone {
     // This RNG doesn't need to be cryptographically secure. Small and fast is
     // preferable.
     layer::mk(move |inner| NewSplit {
         inner,
+        overrides: overrides.clone(),
+        isolation,
+        registry: registry.clone(),
         _service: PhantomData,
     })
 }
 
 #[derive(Debug)]
-pub struct NewSplit<N, S, Req> {
+pub struct NewSplit<N, S, Req, X = NoOverrides> {
     inner: N,
+    overrides: X,
+    isolation: Option<Isolation>,
+    registry: Registry,
     _service: PhantomData<fn(Req) -> S>,
 }
 
-pub struct Split<T, N, S, Req> {
+pub struct Split<T, N, S, Req, X = NoOverrides> {
     rng: SmallRng,
+    overrides: X,
+    isolation: Option<Isolation>,
+    registry: Registry,
     rx: ReceiverStream,
     target: T,
     new_service: N,
     distribution: WeightedIndex<u32>,
     addrs: IndexSet<NameAddr>,
+    backends: Vec<Backend>,
     services: ReadyCache<NameAddr, S, Req>,
 }
 
 // === impl NewSplit ===
 
-impl<N: Clone, S, Req> Clone for NewSplit<N, S, Req> {
+impl<N: Clone, S, Req, X: Clone> Clone for NewSplit<N, S, Req, X> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
+            overrides: self.overrides.clone(),
+            isolation: self.isolation,
+            registry: self.registry.clone(),
             _service: self._service,
         }
     }
 }
 
-impl<T, N, S, Req> NewService<T> for NewSplit<N, S, Req>
+impl<T, N, S, Req, X> NewService<T> for NewSplit<N, S, Req, X>
 where
     T: Clone + Param<LogicalAddr> + Param<Receiver>,
     N: NewService<(ConcreteAddr, T), Service = S> + Clone,
     S: tower::Service<Req>,
     S::Error: Into<Error>,
+    X: Clone,
 {
-    type Service = Split<T, N, S, Req>;
+    type Service = Split<T, N, S, Req, X>;
 
     fn new_service(&mut self, target: T) -> Self::Service {
         let rx: Receiver = target.param();
+        let LogicalAddr(logical) = target.param();
         let mut targets = rx.targets();
         if targets.is_empty() {
-            let LogicalAddr(addr) = target.param();
-            targets.push(Target { addr, weight: 1 })
+            targets.push(Target {
+                addr: logical.clone(),
+                weight: 1,
+            })
         }
         trace!(?targets, "Building split service");
 
         let mut addrs = IndexSet::with_capacity(targets.len());
+        let mut backends = Vec::with_capacity(targets.len());
         let mut weights = Vec::with_capacity(targets.len());
         let mut services = ReadyCache::default();
         let mut new_service = self.inner.clone();
@@ -78,6 +133,12 @@ where
                 addr.clone(),
                 new_service.new_service((ConcreteAddr(addr.clone()), target.clone())),
             );
+            backends.push(Backend::new(
+                &self.registry,
+                logical.clone(),
+                addr.clone(),
+                weight,
+            ));
             addrs.insert(addr);
             weights.push(weight);
         }
@@ -88,7 +149,11 @@ where
             new_service,
             services,
             addrs,
+            backends,
             distribution: WeightedIndex::new(weights).unwrap(),
+            overrides: self.overrides.clone(),
+            isolation: self.isolation,
+            registry: self.registry.clone(),
             rng: SmallRng::from_rng(&mut thread_rng()).expect("RNG must initialize"),
         }
     }
@@ -96,9 +161,40 @@ where
 
 // === impl Split ===
 
-impl<T, N, S, Req> tower::Service<Req> for Split<T, N, S, Req>
+impl<T, N, S, Req, X> Split<T, N, S, Req, X> {
+    /// Rebuilds the split's distribution from the effective weights of its
+    /// targets.
+    ///
+    /// If every target has been isolated, the configured weights are used so
+    /// that traffic is not dropped entirely.
+    fn update_distribution(&mut self) {
+        let effective = self
+            .backends
+            .iter()
+            .map(Backend::effective_weight)
+            .collect::<Vec<_>>();
+        let (distribution, weights) = match WeightedIndex::new(&effective) {
+            Ok(distribution) => (distribution, effective),
+            Err(_) => {
+                let configured = self
+                    .backends
+                    .iter()
+                    .map(Backend::weight)
+                    .collect::<Vec<_>>();
+                (WeightedIndex::new(&configured).unwrap(), configured)
+            }
+        };
+        for (backend, weight) in self.backends.iter().zip(weights) {
+            backend.record_effective_weight(weight);
+        }
+        self.distribution = distribution;
+    }
+}
+
+impl<T, N, S, Req, X> tower::Service<Req> for Split<T, N, S, Req, X>
 where
     Req: Send + 'static,
+    X: ExtractParam<Option<Overrides>, Req>,
     T: Clone + Param<LogicalAddr>,
     N: NewService<(ConcreteAddr, T), Service = S> + Clone,
     S: tower::Service<Req> + Send + 'static,
@@ -116,12 +212,17 @@ where
             update = Some(up);
         }
 
+        let mut changed = false;
+
         // Every time the profile updates, rebuild the distribution, reusing
         // services that existed in the prior state.
         if let Some(Profile { mut targets, .. }) = update {
+            let LogicalAddr(logical) = self.target.param();
             if targets.is_empty() {
-                let LogicalAddr(addr) = self.target.param();
-                targets.push(Target { addr, weight: 1 })
+                targets.push(Target {
+                    addr: logical.clone(),
+                    weight: 1,
+                })
             }
             debug!(?targets, "Updating");
 
@@ -131,7 +232,14 @@ where
             // removed.
             let mut prior_addrs =
                 std::mem::replace(&mut self.addrs, IndexSet::with_capacity(targets.len()));
-            let mut weights = Vec::with_capacity(targets.len());
+            let prior_backends =
+                std::mem::replace(&mut self.backends, Vec::with_capacity(targets.len()));
+            // Targets that remain in the split retain their health.
+            let mut prior_backends = prior_addrs
+                .iter()
+                .cloned()
+                .zip(prior_backends)
+                .collect::<HashMap<_, _>>();
 
             // Create an updated distribution and set of services.
             for Target { weight, addr } in targets.into_iter() {
@@ -145,11 +253,17 @@ where
                 } else {
                     trace!(%addr, "Target already exists");
                 }
+                let backend = match prior_backends.remove(&addr) {
+                    Some(mut backend) => {
+                        backend.set_weight(weight);
+                        backend
+                    }
+                    None => Backend::new(&self.registry, logical.clone(), addr.clone(), weight),
+                };
+                self.backends.push(backend);
                 self.addrs.insert(addr);
-                weights.push(weight);
             }
-
-            self.distribution = WeightedIndex::new(weights).unwrap();
+            changed = true;
 
             // Remove all prior services that did not exist in the new
             // set of targets.
@@ -158,19 +272,415 @@ where
             }
         }
 
+        // Isolate targets that are consistently failing and return isolated
+        // targets to the split once their probation has elapsed.
+        if let Some(isolation) = self.isolation {
+            let now = tokio::time::Instant::now();
+            for (addr, backend) in self.addrs.iter().zip(self.backends.iter_mut()) {
+                changed |= backend.update(addr, &isolation, now);
+            }
+        }
+
+        if changed {
+            self.update_distribution();
+        }
+
         // Wait for all target services to be ready. If any services fail, then
         // the whole service fails.
         Poll::Ready(ready!(self.services.poll_pending(cx)).map_err(Into::into))
     }
 
     fn call(&mut self, req: Req) -> Self::Future {
-        let idx = if self.addrs.len() == 1 {
-            0
-        } else {
-            self.distribution.sample(&mut self.rng)
+        // If the request overrides the split's weights, sample from a one-off
+        // distribution over the existing targets.
+        let overridden = self
+            .overrides
+            .extract_param(&req)
+            .and_then(|o| o.distribution(&self.addrs));
+        let idx = match overridden {
+            Some(distribution) => {
+                trace!("Overriding split weights");
+                distribution.sample(&mut self.rng)
+            }
+            None if self.addrs.len() == 1 => 0,
+            None => self.distribution.sample(&mut self.rng),
         };
         let addr = self.addrs.get_index(idx).expect("invalid index");
         trace!(?addr, "Dispatching");
-        Box::pin(self.services.call_ready(addr, req).err_into::<Error>())
+        let rsp = self.services.call_ready(addr, req).err_into::<Error>();
+        if self.isolation.is_none() {
+            return Box::pin(rsp);
+        }
+
+        let failures = self.backends[idx].failures().clone();
+        Box::pin(rsp.inspect(move |res| failures.record(res.is_ok())))
+    }
+}
+
+// === impl Overrides ===
+
+/// Weights that override a split's distribution for a single request.
+///
+/// Encoded as a comma-separated list of `name=weight` pairs, e.g.
+/// `svc-a=90,svc-b=10`. A name matches a split target if it is the target's
+/// `name:port`, its fully-qualified name, or a leading portion of its name
+/// (e.g. `svc-a` or `svc-a.ns` match `svc-a.ns.svc.cluster.local:8080`).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Overrides(Vec<(String, u32)>);
+
+#[derive(Clone, Debug, Error)]
+#[error("invalid split weights: {0}")]
+pub struct InvalidOverrides(String);
+
+impl Overrides {
+    /// Builds a distribution over `addrs` using the overridden weights.
+    ///
+    /// Targets that are not named are assigned no weight, and names that do
+    /// not match a target are ignored. If no target has a weight, `None` is
+    /// returned so that the split's distribution is used.
+    fn distribution(&self, addrs: &IndexSet<NameAddr>) -> Option<WeightedIndex<u32>> {
+        let weights = addrs.iter().map(|addr| {
+            self.0
+                .iter()
+                .find(|(name, _)| Self::matches(name, addr))
+                .map(|(_, weight)| *weight)
+                .unwrap_or(0)
+        });
+        WeightedIndex::new(weights).ok()
+    }
+
+    fn matches(name: &str, addr: &NameAddr) -> bool {
+        let fqdn = addr.name().without_trailing_dot();
+        if name == fqdn || name == addr.to_string() {
+            return true;
+        }
+        fqdn.strip_prefix(name)
+            .map(|rest| rest.starts_with('.'))
+            .unwrap_or(false)
+    }
+}
+
+impl FromStr for Overrides {
+    type Err = InvalidOverrides;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = Vec::new();
+        for pair in s.split(',') {
+            let pair = pair.trim();
+            if pair.is_empty() {
+                continue;
+            }
+            let mut parts = pair.splitn(2, '=');
+            let name = parts.next().unwrap_or_default().trim();
+            let weight = parts
+                .next()
+                .and_then(|w| w.trim().parse::<u32>().ok())
+                .ok_or_else(|| InvalidOverrides(pair.to_string()))?;
+            if name.is_empty() {
+                return Err(InvalidOverrides(pair.to_string()));
+            }
+            weights.push((name.trim_end_matches('.').to_string(), weight));
+        }
+        Ok(Self(weights))
+    }
+}
+
+// === impl NoOverrides ===
+
+/// Never overrides a split's weights.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NoOverrides(());
+
+impl<Req> ExtractParam<Option<Overrides>, Req> for NoOverrides {
+    #[inline]
+    fn extract_param(&self, _: &Req) -> Option<Overrides> {
+        None
+    }
+}
+
+// === impl OverrideHeader ===
+
+/// Extracts split weights from a request's `l5d-dst-weights` header, if
+/// enabled.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct OverrideHeader {
+    enabled: bool,
+}
+
+impl OverrideHeader {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+}
+
+impl<B> ExtractParam<Option<Overrides>, http::Request<B>> for OverrideHeader {
+    fn extract_param(&self, req: &http::Request<B>) -> Option<Overrides> {
+        if !self.enabled {
+            return None;
+        }
+        let value = req.headers().get(L5D_DST_WEIGHTS)?;
+        match value.to_str().ok()?.parse() {
+            Ok(overrides) => Some(overrides),
+            Err(error) => {
+                debug!(%error, "Ignoring split weights");
+                None
+            }
+        }
+    }
+}
+
+// === impl NewConcreteHeader ===
+
+/// Builds `ConcreteHeader` services for a split's concrete targets.
+#[derive(Clone, Debug)]
+pub struct NewConcreteHeader<N, X = NoOverrides> {
+    inner: N,
+    enabled: bool,
+    overrides: X,
+}
+
+/// Adds an `l5d-dst-concrete` header to responses, if enabled.
+///
+/// The header value is the concrete target's address followed by its weight
+/// in the profile's split, e.g. `svc-a.ns.svc.cluster.local:8080;weight=90/100`.
+/// When the request overrode the split's weights, the weight is reported as
+/// `override`.
+#[derive(Clone, Debug)]
+pub struct ConcreteHeader<S, X = NoOverrides> {
+    inner: S,
+    concrete: Option<(NameAddr, Receiver)>,
+    overrides: X,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct ConcreteHeaderFuture<F> {
+    #[pin]
+    inner: F,
+    value: Option<http::HeaderValue>,
+}
+
+impl<N, X: Clone> NewConcreteHeader<N, X> {
+    /// Builds a layer that adds the header when `enabled`, using `overrides`
+    /// to determine whether a request overrode the split's weights.
+    pub fn layer(enabled: bool, overrides: X) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            inner,
+            enabled,
+            overrides: overrides.clone(),
+        })
+    }
+}
+
+impl<T, N, X> NewService<T> for NewConcreteHeader<N, X>
+where
+    T: Param<ConcreteAddr> + Param<Receiver>,
+    N: NewService<T>,
+    X: Clone,
+{
+    type Service = ConcreteHeader<N::Service, X>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let concrete = if self.enabled {
+            let ConcreteAddr(addr) = target.param();
+            let rx: Receiver = target.param();
+            Some((addr, rx))
+        } else {
+            None
+        };
+        ConcreteHeader {
+            concrete,
+            overrides: self.overrides.clone(),
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl ConcreteHeader ===
+
+impl<S, X, A, B> tower::Service<http::Request<A>> for ConcreteHeader<S, X>
+where
+    S: tower::Service<http::Request<A>, Response = http::Response<B>>,
+    X: ExtractParam<Option<Overrides>, http::Request<A>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ConcreteHeaderFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<A>) -> Self::Future {
+        let value = self.concrete.as_ref().and_then(|(addr, rx)| {
+            let overridden = self.overrides.extract_param(&req).is_some();
+            concrete_header_value(addr, &rx.targets(), overridden)
+        });
+        ConcreteHeaderFuture {
+            inner: self.inner.call(req),
+            value,
+        }
+    }
+}
+
+fn concrete_header_value(
+    addr: &NameAddr,
+    targets: &[Target],
+    overridden: bool,
+) -> Option<http::HeaderValue> {
+    let value = if overridden {
+        format!("{};weight=override", addr)
+    } else if targets.is_empty() {
+        // Splits without targets dispatch all requests to the logical address.
+        format!("{};weight=1/1", addr)
+    } else {
+        let total = targets.iter().map(|t| t.weight as u64).sum::<u64>();
+        let weight = targets
+            .iter()
+            .filter(|t| t.addr == *addr)
+            .map(|t| t.weight as u64)
+            .sum::<u64>();
+        format!("{};weight={}/{}", addr, weight, total)
+    };
+    http::HeaderValue::from_str(&value).ok()
+}
+
+// === impl ConcreteHeaderFuture ===
+
+impl<F, B> Future for ConcreteHeaderFuture<F>
+where
+    F: TryFuture<Ok = http::Response<B>>,
+{
+    type Output = Result<http::Response<B>, F::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut rsp = ready!(this.inner.try_poll(cx))?;
+        if let Some(value) = this.value.take() {
+            rsp.headers_mut().insert(L5D_DST_CONCRETE, value);
+        }
+        Poll::Ready(Ok(rsp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs(names: &[&str]) -> IndexSet<NameAddr> {
+        names.iter().map(|n| n.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn parses_overrides() {
+        let o = "svc-a=90, svc-b.ns.svc.cluster.local.=10,"
+            .parse::<Overrides>()
+            .unwrap();
+        assert_eq!(
+            o,
+            Overrides(vec![
+                ("svc-a".to_string(), 90),
+                ("svc-b.ns.svc.cluster.local".to_string(), 10),
+            ])
+        );
+
+        assert!("svc-a".parse::<Overrides>().is_err());
+        assert!("svc-a=x".parse::<Overrides>().is_err());
+        assert!("=10".parse::<Overrides>().is_err());
+    }
+
+    #[test]
+    fn matches_targets() {
+        let addrs = addrs(&[
+            "svc-a.ns.svc.cluster.local:8080",
+            "svc-b.ns.svc.cluster.local:8080",
+        ]);
+
+        for name in &[
+            "svc-a",
+            "svc-a.ns",
+            "svc-a.ns.svc.cluster.local",
+            "svc-a.ns.svc.cluster.local:8080",
+        ] {
+            let o = format!("{}=1", name).parse::<Overrides>().unwrap();
+            let d = o.distribution(&addrs).expect("must match");
+            let mut rng = SmallRng::seed_from_u64(0);
+            for _ in 0..16 {
+                assert_eq!(d.sample(&mut rng), 0, "{}", name);
+            }
+        }
+
+        for name in &[
+            "svc",
+            "svc-a.other",
+            "svc-c",
+            "svc-a.ns.svc.cluster.local:80",
+        ] {
+            let o = format!("{}=1", name).parse::<Overrides>().unwrap();
+            assert!(o.distribution(&addrs).is_none(), "{}", name);
+        }
+
+        let o = "svc-a=0,svc-b=0".parse::<Overrides>().unwrap();
+        assert!(o.distribution(&addrs).is_none());
+    }
+
+    fn extract<B>(enabled: bool, req: &http::Request<B>) -> Option<Overrides> {
+        ExtractParam::<Option<Overrides>, _>::extract_param(&OverrideHeader::new(enabled), req)
+    }
+
+    #[test]
+    fn extracts_header() {
+        let req = http::Request::builder()
+            .header(L5D_DST_WEIGHTS, "svc-a=90,svc-b=10")
+            .body(())
+            .unwrap();
+        assert!(extract(false, &req).is_none());
+        assert_eq!(
+            extract(true, &req),
+            Some(Overrides(vec![
+                ("svc-a".to_string(), 90),
+                ("svc-b".to_string(), 10)
+            ]))
+        );
+
+        let req = http::Request::builder()
+            .header(L5D_DST_WEIGHTS, "svc-a")
+            .body(())
+            .unwrap();
+        assert!(extract(true, &req).is_none());
+    }
+
+    #[test]
+    fn concrete_header_values() {
+        let a = "svc-a.ns.svc.cluster.local:8080"
+            .parse::<NameAddr>()
+            .unwrap();
+        let b = "svc-b.ns.svc.cluster.local:8080"
+            .parse::<NameAddr>()
+            .unwrap();
+        let targets = vec![
+            Target {
+                addr: a.clone(),
+                weight: 90,
+            },
+            Target {
+                addr: b.clone(),
+                weight: 10,
+            },
+        ];
+
+        assert_eq!(
+            concrete_header_value(&b, &targets, false).unwrap(),
+            "svc-b.ns.svc.cluster.local:8080;weight=10/100"
+        );
+        assert_eq!(
+            concrete_header_value(&a, &targets, true).unwrap(),
+            "svc-a.ns.svc.cluster.local:8080;weight=override"
+        );
+        assert_eq!(
+            concrete_header_value(&a, &[], false).unwrap(),
+            "svc-a.ns.svc.cluster.local:8080;weight=1/1"
+        );
     }
 }
@@ -0,0 +1,247 @@
+use linkerd_addr::NameAddr;
+use linkerd_metrics::{metrics, Counter, FmtLabels, FmtMetrics, Gauge};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::time::Instant;
+use tracing::{debug, info};
+
+metrics! {
+    split_configured_weight: Gauge {
+        "The weight of a concrete target in its service profile's traffic split"
+    },
+    split_effective_weight: Gauge {
+        "The weight of a concrete target after failing targets are isolated from the traffic split"
+    },
+    split_isolated_total: Counter {
+        "Total number of times a concrete target was isolated from its traffic split due to failures"
+    }
+}
+
+/// Configures how a traffic split isolates failing targets.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Isolation {
+    /// The number of consecutive failed requests after which a target is
+    /// isolated from the split.
+    pub consecutive_failures: usize,
+
+    /// How long an isolated target receives no traffic before it is returned
+    /// to the split.
+    pub probation: Duration,
+}
+
+/// Tracks the configured and effective weights of split targets.
+#[derive(Clone, Debug, Default)]
+pub struct Registry(Arc<Mutex<HashMap<Labels, Arc<Metrics>>>>);
+
+#[derive(Debug, Default)]
+struct Metrics {
+    configured: Gauge,
+    effective: Gauge,
+    isolated_total: Counter,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Labels {
+    dst: NameAddr,
+    concrete_dst: NameAddr,
+}
+
+/// The health of a single target in a split.
+#[derive(Debug)]
+pub(super) struct Backend {
+    weight: u32,
+    failures: Failures,
+    isolated_until: Option<Instant>,
+    metrics: Arc<Metrics>,
+}
+
+/// Counts a target's consecutive failed requests.
+#[derive(Clone, Debug, Default)]
+pub(super) struct Failures(Arc<AtomicUsize>);
+
+// === impl Registry ===
+
+impl Registry {
+    fn metrics(&self, dst: NameAddr, concrete_dst: NameAddr) -> Arc<Metrics> {
+        let mut metrics = self.0.lock();
+        // Drop the metrics of targets that are no longer part of any split.
+        metrics.retain(|_, m| Arc::strong_count(m) > 1);
+        metrics
+            .entry(Labels { dst, concrete_dst })
+            .or_insert_with(Default::default)
+            .clone()
+    }
+}
+
+impl FmtMetrics for Registry {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut metrics = self.0.lock();
+        metrics.retain(|_, m| Arc::strong_count(m) > 1);
+        if metrics.is_empty() {
+            return Ok(());
+        }
+
+        split_configured_weight.fmt_help(f)?;
+        split_configured_weight.fmt_scopes(f, metrics.iter(), |m| &m.configured)?;
+
+        split_effective_weight.fmt_help(f)?;
+        split_effective_weight.fmt_scopes(f, metrics.iter(), |m| &m.effective)?;
+
+        split_isolated_total.fmt_help(f)?;
+        split_isolated_total.fmt_scopes(f, metrics.iter(), |m| &m.isolated_total)?;
+
+        Ok(())
+    }
+}
+
+// === impl Labels ===
+
+impl FmtLabels for Labels {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "dst=\"{}\",concrete_dst=\"{}\"",
+            self.dst, self.concrete_dst
+        )
+    }
+}
+
+// === impl Backend ===
+
+impl Backend {
+    pub(super) fn new(registry: &Registry, dst: NameAddr, addr: NameAddr, weight: u32) -> Self {
+        let metrics = registry.metrics(dst, addr);
+        metrics.configured.set(weight.into());
+        metrics.effective.set(weight.into());
+        Self {
+            weight,
+            failures: Failures::default(),
+            isolated_until: None,
+            metrics,
+        }
+    }
+
+    pub(super) fn set_weight(&mut self, weight: u32) {
+        self.weight = weight;
+        self.metrics.configured.set(weight.into());
+    }
+
+    pub(super) fn failures(&self) -> &Failures {
+        &self.failures
+    }
+
+    pub(super) fn weight(&self) -> u32 {
+        self.weight
+    }
+
+    /// The weight of the target after isolation is applied.
+    pub(super) fn effective_weight(&self) -> u32 {
+        if self.isolated_until.is_some() {
+            0
+        } else {
+            self.weight
+        }
+    }
+
+    pub(super) fn record_effective_weight(&self, weight: u32) {
+        self.metrics.effective.set(weight.into());
+    }
+
+    /// Isolates the target if it has failed too many times in a row, or
+    /// returns it to the split once its probation has elapsed.
+    ///
+    /// Returns true if the target's effective weight changed.
+    pub(super) fn update(&mut self, addr: &NameAddr, isolation: &Isolation, now: Instant) -> bool {
+        match self.isolated_until {
+            Some(until) if now >= until => {
+                debug!(%addr, "Returning target to split");
+                self.isolated_until = None;
+                self.failures.reset();
+                true
+            }
+            Some(_) => false,
+            None if self.failures.get() >= isolation.consecutive_failures => {
+                info!(
+                    %addr,
+                    failures = self.failures.get(),
+                    probation = ?isolation.probation,
+                    "Isolating failing target from split",
+                );
+                self.isolated_until = Some(now + isolation.probation);
+                self.metrics.isolated_total.incr();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// === impl Failures ===
+
+impl Failures {
+    /// Records the outcome of a request.
+    pub(super) fn record(&self, success: bool) {
+        if success {
+            self.reset();
+        } else {
+            self.0.fetch_add(1, Ordering::Release);
+        }
+    }
+
+    fn get(&self) -> usize {
+        self.0.load(Ordering::Acquire)
+    }
+
+    fn reset(&self) {
+        self.0.store(0, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn isolates_failing_targets() {
+        let isolation = Isolation {
+            consecutive_failures: 2,
+            probation: Duration::from_secs(10),
+        };
+        let registry = Registry::default();
+        let dst = "svc.ns.svc.cluster.local:8080".parse::<NameAddr>().unwrap();
+        let addr = "svc-b.ns.svc.cluster.local:8080"
+            .parse::<NameAddr>()
+            .unwrap();
+        let mut backend = Backend::new(&registry, dst, addr.clone(), 10);
+        let now = Instant::now();
+
+        // Successes reset the count of consecutive failures.
+        backend.failures().record(false);
+        backend.failures().record(true);
+        backend.failures().record(false);
+        assert!(!backend.update(&addr, &isolation, now));
+        assert_eq!(backend.effective_weight(), 10);
+
+        backend.failures().record(false);
+        assert!(backend.update(&addr, &isolation, now));
+        assert_eq!(backend.effective_weight(), 0);
+        assert_eq!(backend.weight(), 10);
+        assert_eq!(backend.metrics.isolated_total.value(), 1.0);
+
+        // The target remains isolated until its probation elapses.
+        assert!(!backend.update(&addr, &isolation, now + Duration::from_secs(5)));
+        assert_eq!(backend.effective_weight(), 0);
+
+        assert!(backend.update(&addr, &isolation, now + Duration::from_secs(10)));
+        assert_eq!(backend.effective_weight(), 10);
+        assert!(!backend.update(&addr, &isolation, now + Duration::from_secs(10)));
+    }
+}
@@ -19,15 +19,17 @@ pub struct FailFast<S> {
     scope: &'static str,
     inner: S,
     max_unavailable: Duration,
+    retry_after: Option<Duration>,
     wait: Pin<Box<Sleep>>,
     state: State,
 }
 
 /// An error representing that an operation timed out.
-#[derive(Debug, Error)]
+#[derive(Copy, Clone, Debug, Error)]
 #[error("{} service in fail-fast", self.scope)]
 pub struct FailFastError {
     scope: &'static str,
+    retry_after: Option<Duration>,
 }
 
 #[derive(Debug)]
@@ -40,7 +42,7 @@ enum State {
 #[pin_project(project = ResponseFutureProj)]
 pub enum ResponseFuture<F> {
     Inner(#[pin] F),
-    FailFast(&'static str),
+    FailFast(FailFastError),
 }
 
 // === impl FailFast ===
@@ -49,11 +51,22 @@ impl<S> FailFast<S> {
     pub fn layer(
         scope: &'static str,
         max_unavailable: Duration,
+    ) -> impl layer::Layer<S, Service = Self> + Clone + Copy {
+        Self::layer_with_retry_after(scope, max_unavailable, None)
+    }
+
+    /// Like `layer`, but fail-fast errors suggest that clients retry after
+    /// `retry_after`, if set.
+    pub fn layer_with_retry_after(
+        scope: &'static str,
+        max_unavailable: Duration,
+        retry_after: Option<Duration>,
     ) -> impl layer::Layer<S, Service = Self> + Clone + Copy {
         layer::mk(move |inner| Self {
             scope,
             inner,
             max_unavailable,
+            retry_after,
             // The sleep is reset whenever the service becomes unavailable; this
             // initial one will never actually be used, so it's okay to start it
             // now.
@@ -76,6 +89,7 @@ where
             scope: self.scope,
             inner: self.inner.clone(),
             max_unavailable: self.max_unavailable,
+            retry_after: self.retry_after,
 
             // Reset the state and sleep; each clone of the underlying services
             // may become ready independently (e.g. semaphore).
@@ -147,12 +161,24 @@ where
     fn call(&mut self, req: T) -> Self::Future {
         match self.state {
             State::Open => ResponseFuture::Inner(self.inner.call(req)),
-            State::FailFast => ResponseFuture::FailFast(self.scope),
+            State::FailFast => ResponseFuture::FailFast(FailFastError {
+                scope: self.scope,
+                retry_after: self.retry_after,
+            }),
             State::Waiting => panic!("poll_ready must be called"),
         }
     }
 }
 
+// === impl FailFastError ===
+
+impl FailFastError {
+    /// Returns how long clients should wait before retrying, if configured.
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.retry_after
+    }
+}
+
 impl<F> Future for ResponseFuture<F>
 where
     F: TryFuture,
@@ -163,14 +189,14 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         match self.project() {
             ResponseFutureProj::Inner(f) => f.try_poll(cx).map_err(Into::into),
-            ResponseFutureProj::FailFast(scope) => Poll::Ready(Err(FailFastError { scope }.into())),
+            ResponseFutureProj::FailFast(error) => Poll::Ready(Err((*error).into())),
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::FailFast;
+    use super::{FailFast, FailFastError};
     use std::time::Duration;
     use tokio_test::{assert_pending, assert_ready, assert_ready_ok};
     use tower::layer::Layer;
@@ -192,7 +218,8 @@ mod test {
         assert_ready_ok!(service.poll_ready());
 
         let err = service.call(()).await.err().expect("should failfast");
-        assert!(err.is::<super::FailFastError>());
+        let err = err.downcast_ref::<FailFastError>().expect("must fail fast");
+        assert_eq!(err.retry_after(), None);
 
         // Then the inner service becomes available.
         handle.allow(1);
@@ -205,4 +232,24 @@ mod test {
         let ret = fut.await;
         assert!(ret.is_ok());
     }
+
+    #[tokio::test]
+    async fn suggests_retry_after() {
+        let max_unavailable = Duration::from_millis(100);
+        let retry_after = Duration::from_secs(2);
+        let (service, mut handle) = mock::pair::<(), ()>();
+        let mut service = Spawn::new(
+            FailFast::layer_with_retry_after("Test", max_unavailable, Some(retry_after))
+                .layer(service),
+        );
+
+        handle.allow(0);
+        assert_pending!(service.poll_ready());
+        tokio::time::sleep(max_unavailable + Duration::from_millis(1)).await;
+        assert_ready_ok!(service.poll_ready());
+
+        let err = service.call(()).await.err().expect("should failfast");
+        let err = err.downcast_ref::<FailFastError>().expect("must fail fast");
+        assert_eq!(err.retry_after(), Some(retry_after));
+    }
 }
@@ -3,6 +3,7 @@ use futures::{
     prelude::*,
 };
 use linkerd_conditional::Conditional;
+use linkerd_dns_name as dns;
 use linkerd_identity as id;
 use linkerd_io as io;
 use linkerd_stack::{layer, Param};
@@ -14,6 +15,7 @@ use std::{
     sync::Arc,
     task::{Context, Poll},
 };
+use thiserror::Error;
 pub use tokio_rustls::client::TlsStream;
 use tokio_rustls::rustls::{self, Session};
 use tracing::{debug, trace};
@@ -54,6 +56,14 @@ pub enum NoClientTls {
 /// known TLS identity.
 pub type ConditionalClientTls = Conditional<ClientTls, NoClientTls>;
 
+/// Indicates that a server presented a certificate that does not name the
+/// identity that discovery provided for the endpoint.
+#[derive(Clone, Debug, Error)]
+#[error("server certificate does not match the expected identity {expected}")]
+pub struct IdentityMismatch {
+    expected: ServerId,
+}
+
 pub type Config = Arc<rustls::ClientConfig>;
 
 #[derive(Clone, Debug)]
@@ -146,7 +156,18 @@ where
         let connect = self.inner.call(target);
         Either::Right(Box::pin(async move {
             let io = connect.await?;
-            let io = handshake.connect((&server_id.0).into(), io).await?;
+            let io = handshake
+                .connect((&server_id.0).into(), io)
+                .await
+                .map_err(|e| IdentityMismatch::map_handshake_error(e, &server_id))?;
+            // The peer certificate has been validated for the server name, but
+            // webpki also accepts wildcard names. Endpoint identities are
+            // pinned exactly, so the certificate must name the identity
+            // verbatim.
+            if !names_server_id(io.get_ref().1, &server_id) {
+                debug!(server.id = %server_id, "Server certificate does not name the expected identity");
+                return Err(IdentityMismatch::new(server_id).into());
+            }
             if let Some(alpn) = io.get_ref().1.get_alpn_protocol() {
                 debug!(alpn = ?std::str::from_utf8(alpn));
             }
@@ -155,6 +176,77 @@ where
     }
 }
 
+/// Returns true if the end-entity certificate presented by the server has a
+/// (non-wildcard) DNS SAN that exactly matches the expected identity.
+fn names_server_id(session: &rustls::ClientSession, ServerId(expected): &ServerId) -> bool {
+    use webpki::GeneralDNSNameRef;
+
+    let certs = match session.get_peer_certificates() {
+        Some(certs) => certs,
+        None => return false,
+    };
+    let end_cert = match certs
+        .first()
+        .and_then(|c| webpki::EndEntityCert::from(c.as_ref()).ok())
+    {
+        Some(end_cert) => end_cert,
+        None => return false,
+    };
+    let dns_names = match end_cert.dns_names() {
+        Ok(names) => names,
+        Err(_) => return false,
+    };
+
+    let expected: &str = expected.as_ref();
+    dns_names.into_iter().any(|n| match n {
+        GeneralDNSNameRef::DNSName(n) => {
+            let name = dns::Name::from(n.to_owned());
+            AsRef::<str>::as_ref(&name).eq_ignore_ascii_case(expected)
+        }
+        // Wildcards never pin an endpoint identity.
+        GeneralDNSNameRef::Wildcard(_) => false,
+    })
+}
+
+// === impl IdentityMismatch ===
+
+impl IdentityMismatch {
+    fn new(expected: ServerId) -> Self {
+        Self { expected }
+    }
+
+    pub fn expected(&self) -> &ServerId {
+        &self.expected
+    }
+
+    /// Handshake failures caused by the server presenting a certificate that
+    /// is not valid for the expected name are surfaced as an
+    /// `IdentityMismatch` so that they may be distinguished from other TLS
+    /// failures.
+    fn map_handshake_error(error: io::Error, expected: &ServerId) -> io::Error {
+        let mismatch = error
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<rustls::TLSError>())
+            .map(|e| {
+                matches!(
+                    e,
+                    rustls::TLSError::WebPKIError(webpki::Error::CertNotValidForName)
+                )
+            })
+            .unwrap_or(false);
+        if mismatch {
+            return Self::new(expected.clone()).into();
+        }
+        error
+    }
+}
+
+impl From<IdentityMismatch> for io::Error {
+    fn from(e: IdentityMismatch) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
 // === impl ServerId ===
 
 impl From<id::Name> for ServerId {
@@ -220,3 +312,35 @@ impl fmt::Debug for AlpnProtocols {
         dbg.finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_name_mismatch() {
+        let server_id =
+            ServerId::from_str("foo.ns1.serviceaccount.identity.linkerd.cluster.local").unwrap();
+
+        let error = io::Error::new(
+            io::ErrorKind::InvalidData,
+            rustls::TLSError::WebPKIError(webpki::Error::CertNotValidForName),
+        );
+        let error = IdentityMismatch::map_handshake_error(error, &server_id);
+        let mismatch = error
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<IdentityMismatch>())
+            .expect("error must be an identity mismatch");
+        assert_eq!(mismatch.expected(), &server_id);
+
+        let error = io::Error::new(
+            io::ErrorKind::InvalidData,
+            rustls::TLSError::WebPKIError(webpki::Error::UnknownIssuer),
+        );
+        let error = IdentityMismatch::map_handshake_error(error, &server_id);
+        assert!(error
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<IdentityMismatch>())
+            .is_none());
+    }
+}
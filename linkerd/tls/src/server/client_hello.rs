@@ -6,12 +6,19 @@ use tracing::trace;
 #[derive(Debug, Eq, PartialEq)]
 pub struct Incomplete;
 
+/// A TLS ClientHello, as detected on an accepted connection.
+#[derive(Debug, Eq, PartialEq)]
+pub struct ClientHello {
+    /// The SNI presented by the client, if any.
+    pub sni: Option<ServerId>,
+}
+
 /// Determintes whether the given `input` looks like the start of a TLS
 /// connection.
 ///
 /// The determination is made based on whether the input looks like (the start
-/// of) a valid ClientHello that a reasonable TLS client might send, and the
-/// SNI matches the given identity.
+/// of) a valid ClientHello that a reasonable TLS client might send. If so, the
+/// SNI is extracted, if one is present.
 ///
 /// XXX: Once the TLS record header is matched, the determination won't be
 /// made until the entire TLS record including the entire ClientHello handshake
@@ -22,7 +29,7 @@ pub struct Incomplete;
 /// This assumes that the ClientHello is small and is sent in a single TLS
 /// record, which is what all reasonable implementations do. (If they were not
 /// to, they wouldn't interoperate with picky servers.)
-pub fn parse_sni(input: &[u8]) -> Result<Option<ServerId>, Incomplete> {
+pub fn parse_client_hello(input: &[u8]) -> Result<Option<ClientHello>, Incomplete> {
     let r = untrusted::Input::from(input).read_all(untrusted::EndOfInput, |input| {
         let r = extract_sni(input);
         input.skip_to_end(); // Ignore anything after what we parsed.
@@ -30,29 +37,33 @@ pub fn parse_sni(input: &[u8]) -> Result<Option<ServerId>, Incomplete> {
     });
     match r {
         Ok(Some(sni)) => {
-            let sni = id::Name::try_from(sni.as_slice_less_safe())
-                .ok()
-                .map(ServerId);
-            trace!(?sni, "parse_sni: parsed correctly up to SNI");
-            Ok(sni)
+            let sni = sni.and_then(|sni| {
+                id::Name::try_from(sni.as_slice_less_safe())
+                    .ok()
+                    .map(ServerId)
+            });
+            trace!(?sni, "parse_client_hello: parsed ClientHello");
+            Ok(Some(ClientHello { sni }))
         }
         Ok(None) => {
-            trace!("parse_sni: failed to parse up to SNI");
+            trace!("parse_client_hello: failed to parse ClientHello");
             Ok(None)
         }
         Err(untrusted::EndOfInput) => {
-            trace!("parse_sni: needs more input");
+            trace!("parse_client_hello: needs more input");
             Err(Incomplete)
         }
     }
 }
 
-/// The result is `Ok(Some(hostname))` if the SNI extension was found, `Ok(None)`
-/// if we affirmatively rejected the input before we found the SNI extension, or
-/// `Err(EndOfInput)` if we don't have enough input to continue.
+/// The result is `Ok(Some(Some(hostname)))` if the SNI extension was found,
+/// `Ok(Some(None))` if the input is a ClientHello without a usable SNI
+/// extension, `Ok(None)` if we affirmatively rejected the input before we
+/// parsed the ClientHello's extensions, or `Err(EndOfInput)` if we don't have
+/// enough input to continue.
 fn extract_sni<'a>(
     input: &mut untrusted::Reader<'a>,
-) -> Result<Option<untrusted::Input<'a>>, untrusted::EndOfInput> {
+) -> Result<Option<Option<untrusted::Input<'a>>>, untrusted::EndOfInput> {
     // TLS ciphertext record header.
 
     if input.read_byte()? != 22 {
@@ -127,10 +138,10 @@ fn extract_sni<'a>(
                     });
 
                     input.skip_to_end(); // Ignore stuff after SNI
-                    return r;
+                    return r.map(Some);
                 }
 
-                Ok(None) // No SNI extension.
+                Ok(Some(None)) // No SNI extension.
             })
         })
     });
@@ -195,7 +206,7 @@ mod tests {
     fn mismatch_http_1_0_request() {
         assert_eq!(
             Ok(None),
-            parse_sni(b"GET /TheProject.html HTTP/1.0\r\n\r\n"),
+            parse_client_hello(b"GET /TheProject.html HTTP/1.0\r\n\r\n"),
         );
     }
 
@@ -205,13 +216,48 @@ mod tests {
         let identity = id::Name::from_str("example.com").unwrap();
 
         let mut i = 0;
-        while let Err(Incomplete) = parse_sni(&input[..i]) {
+        while let Err(Incomplete) = parse_client_hello(&input[..i]) {
             i += 1;
         }
 
         // The same result will be returned for all longer prefixes.
         for i in i..input.len() {
-            assert_eq!(Ok(Some(ServerId(identity.clone()))), parse_sni(&input[..i]))
+            assert_eq!(
+                Ok(Some(ClientHello {
+                    sni: Some(ServerId(identity.clone()))
+                })),
+                parse_client_hello(&input[..i])
+            )
+        }
+    }
+
+    #[test]
+    fn client_hello_without_sni() {
+        let input = include_bytes!("testdata/example-com-client-hello.bin");
+        assert_eq!(
+            Ok(Some(ClientHello { sni: None })),
+            parse_client_hello(&strip_sni(input))
+        );
+    }
+
+    /// Rewrites a ClientHello so that its SNI extension is replaced by an
+    /// unknown extension type.
+    fn strip_sni(input: &[u8]) -> Vec<u8> {
+        let mut input = input.to_vec();
+        // Record header (5B), handshake header (4B), version (2B), random (32B).
+        let mut i = 5 + 4 + 2 + 32;
+        i += 1 + input[i] as usize; // session_id
+        i += 2 + (usize::from(input[i]) << 8 | usize::from(input[i + 1])); // cipher_suites
+        i += 1 + input[i] as usize; // compression_methods
+        let end = i + 2 + (usize::from(input[i]) << 8 | usize::from(input[i + 1]));
+        i += 2;
+        while i < end {
+            if input[i] == 0 && input[i + 1] == 0 {
+                input[i + 1] = 0xff;
+                return input;
+            }
+            i += 4 + (usize::from(input[i + 2]) << 8 | usize::from(input[i + 3]));
         }
+        panic!("ClientHello must include an SNI extension");
     }
 }
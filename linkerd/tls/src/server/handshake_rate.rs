@@ -0,0 +1,89 @@
+use std::sync::{Arc, Mutex};
+use tokio::time::Instant;
+
+/// Limits the rate at which TLS handshakes are performed for a group of
+/// clients.
+///
+/// Handshakes are admitted while tokens remain in a bucket that holds up to
+/// one second's worth of handshakes and that is refilled continuously at the
+/// configured rate.
+#[derive(Clone, Debug)]
+pub struct HandshakeRate(Arc<Mutex<Bucket>>);
+
+#[derive(Debug)]
+struct Bucket {
+    per_second: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+// === impl HandshakeRate ===
+
+impl HandshakeRate {
+    /// Creates a limit that admits `per_second` handshakes each second.
+    pub fn new(per_second: u32) -> Self {
+        let per_second = f64::from(per_second.max(1));
+        Self(Arc::new(Mutex::new(Bucket {
+            per_second,
+            tokens: per_second,
+            refilled: Instant::now(),
+        })))
+    }
+
+    /// Admits a handshake, if the limit permits it.
+    pub fn try_acquire(&self) -> bool {
+        let mut bucket = self.0.lock().expect("handshake rate lock poisoned");
+        bucket.refill();
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+
+    /// Returns true if the limit's bucket is full, i.e. because no handshakes
+    /// have been admitted for at least a second.
+    pub fn is_idle(&self) -> bool {
+        let mut bucket = self.0.lock().expect("handshake rate lock poisoned");
+        bucket.refill();
+        bucket.tokens >= bucket.per_second
+    }
+}
+
+// === impl Bucket ===
+
+impl Bucket {
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.saturating_duration_since(self.refilled);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.per_second).min(self.per_second);
+        self.refilled = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::{self, Duration};
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn refills() {
+        time::pause();
+        let rate = HandshakeRate::new(2);
+        assert!(rate.is_idle());
+        assert!(rate.try_acquire());
+        assert!(rate.try_acquire());
+        assert!(!rate.try_acquire(), "the bucket must be empty");
+        assert!(!rate.is_idle());
+
+        time::advance(Duration::from_millis(500)).await;
+        assert!(rate.try_acquire(), "a token must be refilled");
+        assert!(!rate.try_acquire());
+
+        time::advance(Duration::from_secs(5)).await;
+        assert!(rate.is_idle());
+        assert!(rate.try_acquire());
+        assert!(rate.try_acquire());
+        assert!(!rate.try_acquire(), "tokens must not exceed the burst");
+    }
+}
@@ -1,5 +1,8 @@
 mod client_hello;
+mod handshake_rate;
 
+use self::client_hello::ClientHello;
+pub use self::handshake_rate::HandshakeRate;
 use crate::{LocalId, NegotiatedProtocol, ServerId};
 use bytes::BytesMut;
 use futures::prelude::*;
@@ -42,9 +45,10 @@ pub enum ServerTls {
         client_id: Option<ClientId>,
         negotiated_protocol: Option<NegotiatedProtocol>,
     },
-    Passthru {
-        sni: ServerId,
-    },
+    /// A TLS connection that is not terminated by this proxy, either because
+    /// its SNI does not match the local identity or because the client did
+    /// not present an SNI.
+    Passthru { sni: Option<ServerId> },
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
@@ -81,15 +85,34 @@ pub struct NewDetectTls<P, L, N> {
 #[derive(Copy, Clone, Debug)]
 pub struct Timeout(pub Duration);
 
+/// Limits the rate of TLS handshakes for a connection's client, if set.
+#[derive(Clone, Debug, Default)]
+pub struct HandshakeLimit(pub Option<HandshakeRate>);
+
+/// Configures whether TLS is terminated for connections whose SNI is a
+/// subdomain of the local identity (e.g. `<cluster>.<local-id>`).
+///
+/// The local certificate must be valid for these names (i.e. via a wildcard
+/// SAN); otherwise, the handshake fails.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TerminateSubdomains(pub bool);
+
 #[derive(Clone, Debug, Error)]
 #[error("TLS detection timed out")]
 pub struct ServerTlsTimeoutError(());
 
+/// Indicates that a TLS handshake was shed, before it was performed, because
+/// the client exceeded its handshake rate limit.
+#[derive(Clone, Debug, Error)]
+#[error("TLS handshake rate limit exceeded")]
+pub struct HandshakeRateLimitedError(());
+
 #[derive(Clone, Debug)]
 pub struct DetectTls<T, P, L, N> {
     target: T,
     local_identity: Option<L>,
     timeout: Timeout,
+    handshake_limit: HandshakeLimit,
     params: P,
     inner: N,
 }
@@ -121,7 +144,10 @@ impl<P, L, N> NewDetectTls<P, L, N> {
 
 impl<T, P, L, N> NewService<T> for NewDetectTls<P, L, N>
 where
-    P: ExtractParam<Timeout, T> + ExtractParam<Option<L>, T> + Clone,
+    P: ExtractParam<Timeout, T>
+        + ExtractParam<Option<L>, T>
+        + ExtractParam<HandshakeLimit, T>
+        + Clone,
     N: Clone,
 {
     type Service = DetectTls<T, P, L, N>;
@@ -129,10 +155,12 @@ where
     fn new_service(&mut self, target: T) -> Self::Service {
         let timeout = self.params.extract_param(&target);
         let local_identity = self.params.extract_param(&target);
+        let handshake_limit = self.params.extract_param(&target);
         DetectTls {
             target,
             local_identity,
             timeout,
+            handshake_limit,
             params: self.params.clone(),
             inner: self.inner.clone(),
         }
@@ -145,7 +173,7 @@ where
     T: Clone + Send + 'static,
     P: InsertParam<ConditionalServerTls, T> + Clone + Send + Sync + 'static,
     P::Target: Send + 'static,
-    L: Param<LocalId> + Param<Config>,
+    L: Param<LocalId> + Param<Config> + Param<TerminateSubdomains>,
     N: NewService<P::Target, Service = NSvc> + Clone + Send + 'static,
     NSvc: tower::Service<Io<I>, Response = ()> + Send + 'static,
     NSvc::Error: Into<Error>,
@@ -168,24 +196,35 @@ where
             Some(local) => {
                 let config: Config = local.param();
                 let LocalId(local_id) = local.param();
+                let TerminateSubdomains(subdomains) = local.param();
+                let HandshakeLimit(handshake_rate) = self.handshake_limit.clone();
 
-                // Detect the SNI from a ClientHello (or timeout).
+                // Detect a ClientHello (or timeout).
                 let Timeout(timeout) = self.timeout;
-                let detect = time::timeout(timeout, detect_sni(io));
+                let detect = time::timeout(timeout, detect_client_hello(io));
                 Box::pin(async move {
-                    let (sni, io) = detect.await.map_err(|_| ServerTlsTimeoutError(()))??;
+                    let (hello, io) = detect.await.map_err(|_| ServerTlsTimeoutError(()))??;
 
-                    let (peer, io) = match sni {
+                    let (peer, io) = match hello {
                         // If we detected an SNI matching this proxy, terminate TLS.
-                        Some(ServerId(id)) if id == local_id => {
-                            trace!("Identified local SNI");
+                        Some(ClientHello {
+                            sni: Some(ServerId(id)),
+                        }) if id == local_id || (subdomains && is_subdomain(&id, &local_id)) => {
+                            trace!(sni = %id, "Identified local SNI");
+                            // Shed the handshake before doing any cryptographic work for it.
+                            if let Some(rate) = handshake_rate {
+                                if !rate.try_acquire() {
+                                    debug!(sni = %id, "TLS handshake rate limit exceeded");
+                                    return Err(HandshakeRateLimitedError(()).into());
+                                }
+                            }
                             let (peer, io) = handshake(config, io).await?;
                             (Conditional::Some(peer), EitherIo::Left(io))
                         }
-                        // If we detected another SNI, continue proxying the
-                        // opaque stream.
-                        Some(sni) => {
-                            debug!(%sni, "Identified foreign SNI");
+                        // If we detected another SNI (or no SNI at all),
+                        // continue proxying the opaque stream.
+                        Some(ClientHello { sni }) => {
+                            debug!(?sni, "Identified non-mesh TLS");
                             let peer = ServerTls::Passthru { sni };
                             (Conditional::Some(peer), EitherIo::Right(io))
                         }
@@ -216,8 +255,28 @@ where
     }
 }
 
-/// Peek or buffer the provided stream to determine an SNI value.
-async fn detect_sni<I>(mut io: I) -> io::Result<(Option<ServerId>, DetectIo<I>)>
+/// Returns the SNI presented by the client on a terminated TLS connection.
+pub fn server_name<I>(io: &Io<I>) -> Option<ServerId> {
+    match io {
+        EitherIo::Left(tls) => {
+            let (_, session) = tls.get_ref();
+            let name = session.get_sni_hostname()?;
+            id::Name::from_str(name).ok().map(ServerId)
+        }
+        EitherIo::Right(_) => None,
+    }
+}
+
+fn is_subdomain(name: &id::Name, parent: &id::Name) -> bool {
+    let (name, parent) = (name.as_ref(), parent.as_ref());
+    name.len() > parent.len()
+        && name.ends_with(parent)
+        && name.as_bytes()[name.len() - parent.len() - 1] == b'.'
+}
+
+/// Peek or buffer the provided stream to determine whether it starts with a TLS
+/// ClientHello.
+async fn detect_client_hello<I>(mut io: I) -> io::Result<(Option<ClientHello>, DetectIo<I>)>
 where
     I: io::Peek + io::AsyncRead + io::AsyncWrite + Send + Sync + Unpin,
 {
@@ -232,9 +291,9 @@ where
     debug!(sz, "Peeked bytes from TCP stream");
     // Peek may return 0 bytes if the socket is not peekable.
     if sz > 0 {
-        match client_hello::parse_sni(&buf) {
-            Ok(sni) => {
-                return Ok((sni, EitherIo::Left(io)));
+        match client_hello::parse_client_hello(&buf) {
+            Ok(hello) => {
+                return Ok((hello, EitherIo::Left(io)));
             }
 
             Err(client_hello::Incomplete) => {}
@@ -248,9 +307,9 @@ where
     debug!(buf.capacity = %buf.capacity(), "Reading bytes from TCP stream");
     while io.read_buf(&mut buf).await? != 0 {
         debug!(buf.len = %buf.len(), "Read bytes from TCP stream");
-        match client_hello::parse_sni(buf.as_ref()) {
-            Ok(sni) => {
-                return Ok((sni, EitherIo::Right(PrefixedIo::new(buf.freeze(), io))));
+        match client_hello::parse_client_hello(buf.as_ref()) {
+            Ok(hello) => {
+                return Ok((hello, EitherIo::Right(PrefixedIo::new(buf.freeze(), io))));
             }
 
             Err(client_hello::Incomplete) => {
@@ -383,12 +442,17 @@ mod tests {
                 .expect("Write must suceed");
         });
 
-        let (sni, io) = detect_sni(server_io)
+        let (hello, io) = detect_client_hello(server_io)
             .await
-            .expect("SNI detection must not fail");
+            .expect("ClientHello detection must not fail");
 
         let identity = id::Name::from_str("example.com").unwrap();
-        assert_eq!(sni, Some(ServerId(identity)));
+        assert_eq!(
+            hello,
+            Some(ClientHello {
+                sni: Some(ServerId(identity))
+            })
+        );
 
         match io {
             EitherIo::Left(_) => panic!("Detected IO should be buffered"),
@@ -397,6 +461,21 @@ mod tests {
 
         client_task.await.expect("Client must not fail");
     }
+
+    #[test]
+    fn subdomains() {
+        let parent = id::Name::from_str("gateway.linkerd.cluster.local").unwrap();
+        for (name, expected) in &[
+            ("gateway.linkerd.cluster.local", false),
+            ("cluster-b.gateway.linkerd.cluster.local", true),
+            ("a.b.gateway.linkerd.cluster.local", true),
+            ("xgateway.linkerd.cluster.local", false),
+            ("linkerd.cluster.local", false),
+        ] {
+            let name = id::Name::from_str(name).unwrap();
+            assert_eq!(is_subdomain(&name, &parent), *expected, "{}", name);
+        }
+    }
 }
 
 #[cfg(fuzzing)]
@@ -404,6 +483,6 @@ pub mod fuzz_logic {
     use super::*;
 
     pub fn fuzz_entry(input: &[u8]) {
-        let _ = client_hello::parse_sni(input);
+        let _ = client_hello::parse_client_hello(input);
     }
 }
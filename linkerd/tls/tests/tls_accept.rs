@@ -81,6 +81,66 @@ async fn proxy_to_proxy_tls_works() {
     assert_eq!(&server_result.result.expect("ping")[..], PING);
 }
 
+#[tokio::test(flavor = "current_thread")]
+async fn proxy_to_proxy_tls_works_with_intermediates() {
+    let server_tls = id::test_util::FOO_NS1_INT1.validate().unwrap();
+    let client_tls = id::test_util::BAR_NS1.validate().unwrap();
+    let server_id = tls::ServerId(server_tls.name().clone());
+    let (client_result, server_result) = run_test(
+        Conditional::Some((client_tls.clone(), server_id.clone())),
+        |conn| write_then_read(conn, PING),
+        Some(server_tls),
+        |(_, conn)| read_then_write(conn, PING.len(), PONG),
+    )
+    .await;
+    assert_eq!(
+        client_result.tls,
+        Some(Conditional::Some(tls::ClientTls {
+            server_id,
+            alpn: None,
+        }))
+    );
+    assert_eq!(&client_result.result.expect("pong")[..], PONG);
+    assert_eq!(
+        server_result.tls,
+        Some(Conditional::Some(tls::ServerTls::Established {
+            client_id: Some(tls::ClientId(client_tls.name().clone())),
+            negotiated_protocol: None,
+        }))
+    );
+    assert_eq!(&server_result.result.expect("ping")[..], PING);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn proxy_to_proxy_tls_works_with_client_intermediates() {
+    let server_tls = id::test_util::BAR_NS1.validate().unwrap();
+    let client_tls = id::test_util::FOO_NS1_INT1.validate().unwrap();
+    let server_id = tls::ServerId(server_tls.name().clone());
+    let (client_result, server_result) = run_test(
+        Conditional::Some((client_tls.clone(), server_id.clone())),
+        |conn| write_then_read(conn, PING),
+        Some(server_tls),
+        |(_, conn)| read_then_write(conn, PING.len(), PONG),
+    )
+    .await;
+    assert_eq!(
+        client_result.tls,
+        Some(Conditional::Some(tls::ClientTls {
+            server_id,
+            alpn: None,
+        }))
+    );
+    assert_eq!(&client_result.result.expect("pong")[..], PONG);
+    assert_eq!(
+        server_result.tls,
+        Some(Conditional::Some(tls::ServerTls::Established {
+            client_id: Some(tls::ClientId(client_tls.name().clone())),
+            negotiated_protocol: None,
+        }))
+    );
+    assert_eq!(&server_result.result.expect("ping")[..], PING);
+}
+
 #[tokio::test(flavor = "current_thread")]
 async fn proxy_to_proxy_tls_pass_through_when_identity_does_not_match() {
     let server_tls = id::test_util::FOO_NS1.validate().unwrap();
@@ -107,7 +167,7 @@ async fn proxy_to_proxy_tls_pass_through_when_identity_does_not_match() {
     assert_eq!(
         server_result.tls,
         Some(Conditional::Some(tls::ServerTls::Passthru {
-            sni: tls::ServerId(sni)
+            sni: Some(tls::ServerId(sni))
         }))
     );
     assert_eq!(&server_result.result.unwrap()[..], START_OF_TLS);
@@ -345,6 +405,12 @@ impl Param<tls::LocalId> for Tls {
     }
 }
 
+impl Param<tls::server::TerminateSubdomains> for Tls {
+    fn param(&self) -> tls::server::TerminateSubdomains {
+        tls::server::TerminateSubdomains(false)
+    }
+}
+
 // === impl Server ===
 
 impl Param<ListenAddr> for Server {
@@ -376,6 +442,12 @@ impl<T> ExtractParam<Option<Tls>, T> for ServerParams {
     }
 }
 
+impl<T> ExtractParam<tls::server::HandshakeLimit, T> for ServerParams {
+    fn extract_param(&self, _: &T) -> tls::server::HandshakeLimit {
+        tls::server::HandshakeLimit(None)
+    }
+}
+
 impl<T> InsertParam<tls::ConditionalServerTls, T> for ServerParams {
     type Target = (tls::ConditionalServerTls, T);
 
@@ -3,8 +3,12 @@
 
 use futures::prelude::*;
 use linkerd_error::Recover;
+use linkerd_metrics::Counter;
 use linkerd_stack::{Service, ServiceExt};
-use std::task::{Context, Poll};
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
 use tokio::sync::watch;
 use tracing::{debug, trace};
 
@@ -17,6 +21,23 @@ use tracing::{debug, trace};
 pub struct StreamWatch<R, S> {
     recover: R,
     inner: S,
+    resync: Resync,
+}
+
+/// Coordinates the watches spawned by a `StreamWatch`.
+///
+/// When a watch's stream fails and is re-established (e.g. because the control
+/// plane restarted), all other live watches re-issue their lookups so that they
+/// don't continue to publish state that may be stale.
+///
+/// Reconnects are tracked as a generation number: each watch records the
+/// generation at which its stream was established and resyncs whenever a newer
+/// generation is published.
+#[derive(Clone, Debug)]
+struct Resync {
+    tx: Arc<watch::Sender<u64>>,
+    rx: watch::Receiver<u64>,
+    resynced: Arc<Counter>,
 }
 
 type Result<U> = std::result::Result<U, tonic::Status>;
@@ -31,7 +52,23 @@ type OuterRsp<U> = tonic::Response<watch::Receiver<U>>;
 
 impl<R, S> StreamWatch<R, S> {
     pub fn new(recover: R, inner: S) -> Self {
-        Self { recover, inner }
+        Self {
+            recover,
+            inner,
+            resync: Resync::default(),
+        }
+    }
+
+    /// Counts the watches that have been resynced after another watch
+    /// reconnected.
+    pub fn with_resynced_counter(self, resynced: Arc<Counter>) -> Self {
+        Self {
+            resync: Resync {
+                resynced,
+                ..self.resync
+            },
+            ..self
+        }
     }
 }
 
@@ -49,13 +86,14 @@ where
         S::Future: Send,
     {
         // Get an update and stream or return None.
+        let generation = self.resync.generation();
         let (init, rsp) = self.init(&target, None).await?;
 
         Ok(rsp.map(move |inner| {
             // Spawn a background task to keep the profile watch up-to-date until all copies of `rx`
             // have dropped.
             let (tx, rx) = watch::channel(init);
-            tokio::spawn(self.publish_updates(target, tx, inner));
+            tokio::spawn(self.publish_updates(target, tx, inner, generation));
             rx
         }))
     }
@@ -118,17 +156,21 @@ where
 
     // Publishes updates on `tx` from the stream, recovering and applying backoff backoff as
     // necessary.
+    //
+    // `generation` is the resync generation at which `stream` was established.
     async fn publish_updates<T, U>(
         mut self,
         target: T,
         tx: watch::Sender<U>,
         mut stream: InnerStream<U>,
+        mut generation: u64,
     ) where
         T: Clone + Send + Sync + 'static,
         U: Send + Sync + 'static,
         S: Service<T, Response = InnerRsp<U>, Error = tonic::Status>,
         S::Future: Send,
     {
+        let mut reconnects = self.resync.rx.clone();
         loop {
             tokio::select! {
                 biased;
@@ -139,9 +181,40 @@ where
                     return;
                 },
 
+                // If another watch has reconnected since this stream was established, re-issue
+                // the lookup so that updates published before the reconnect aren't retained.
+                _ = reconnects.changed() => {
+                    if *reconnects.borrow() <= generation {
+                        continue;
+                    }
+
+                    debug!("Resyncing");
+                    let current = self.resync.generation();
+                    let res = tokio::select! {
+                        biased;
+                        _ = tx.closed() => {
+                            trace!("Receivers dropped");
+                            return;
+                        },
+                        res = self.init(&target, None) => res,
+                    };
+                    match res {
+                        Ok((profile, rsp)) => {
+                            generation = current;
+                            stream = rsp.into_inner();
+                            self.resync.resynced.incr();
+                            let _ = tx.send(profile);
+                        }
+                        Err(status) => {
+                            debug!(%status, "Profile stream failed");
+                            return;
+                        }
+                    }
+                },
+
                 // Otherwise, continue to get new profile versions and update the watch. The stream
                 // may be re-instantiated each time
-                res = self.recovering_next(&target, &mut stream) => match res {
+                res = self.recovering_next(&target, &mut stream, &mut generation) => match res {
                     Ok(profile) => {
                         // If sending the update fails, then we'll just look and hit the closed case above.
                         let _ = tx.send(profile);
@@ -158,8 +231,14 @@ where
     /// Gets the next profile from the stream
     ///
     /// If the stream or lookup fails in a recoverable way, back-offs are applied and `stream` is
-    /// updated to point at the updated stream..
-    async fn recovering_next<T, U>(&mut self, target: &T, stream: &mut InnerStream<U>) -> Result<U>
+    /// updated to point at the updated stream. Once the stream is re-established, other watches
+    /// are notified to resync and `generation` is updated.
+    async fn recovering_next<T, U>(
+        &mut self,
+        target: &T,
+        stream: &mut InnerStream<U>,
+        generation: &mut u64,
+    ) -> Result<U>
     where
         T: Clone + Send + Sync + 'static,
         S: Service<T, Response = InnerRsp<U>, Error = tonic::Status>,
@@ -168,11 +247,13 @@ where
         match Self::next(stream).await {
             Ok(u) => Ok(u),
             Err(status) => {
+                let failed = self.resync.generation();
                 // Use the streaming error to get a backoff that can be applied if the next lookup
                 // fails.
                 let backoff = self.recover.recover(status)?;
                 let (item, rsp) = self.init(target, Some(backoff)).await?;
                 *stream = rsp.into_inner();
+                *generation = self.resync.reconnected(failed);
                 Ok(item)
             }
         }
@@ -188,6 +269,42 @@ where
     }
 }
 
+// === impl Resync ===
+
+impl Default for Resync {
+    fn default() -> Self {
+        let (tx, rx) = watch::channel(0);
+        Self {
+            tx: Arc::new(tx),
+            rx,
+            resynced: Arc::new(Counter::default()),
+        }
+    }
+}
+
+impl Resync {
+    fn generation(&self) -> u64 {
+        *self.rx.borrow()
+    }
+
+    /// Notes that a watch re-established its stream after it failed at the `failed`
+    /// generation, returning the generation of the new stream.
+    ///
+    /// If another watch has already reconnected since then, all watches have already been
+    /// notified, so a new generation is not published.
+    fn reconnected(&self, failed: u64) -> u64 {
+        let current = self.generation();
+        if current != failed {
+            return current;
+        }
+
+        debug!("Reconnected; resyncing watches");
+        let next = current + 1;
+        let _ = self.tx.send(next);
+        next
+    }
+}
+
 impl<T, U, R, S> Service<T> for StreamWatch<R, S>
 where
     T: Clone + Send + Sync + 'static,
@@ -330,4 +447,59 @@ mod tests {
         let watch = waiting.await;
         assert_eq!(*watch.borrow(), 123);
     }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn resync_on_reconnect() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        time::pause();
+
+        let (mock, mut handle) = mk_svc::<u8, u16>();
+        let resynced = Arc::new(Counter::default());
+        let watch = StreamWatch::new(recover::Immediately::default(), mock)
+            .with_resynced_counter(resynced.clone());
+
+        // Establish a watch for each of two targets.
+        let mut streams = Vec::new();
+        let mut watches = Vec::new();
+        for target in 0..2u8 {
+            handle.allow(1);
+            let (tx, rx) = mpsc::channel::<Result<u16>>(3);
+            tx.send(Ok(target as u16)).await.unwrap();
+            let send_req = handle.next_request().map(move |req| {
+                let (t, rsp) = req.unwrap();
+                assert_eq!(t, target);
+                rsp.send_response(tonic::Response::new(Box::pin(ReceiverStream::new(rx))))
+            });
+            let (_, rx) = tokio::join!(send_req, watch.clone().spawn_watch(target));
+            watches.push(rx.unwrap().into_inner());
+            streams.push(tx);
+        }
+
+        // The first watch's stream fails and is re-established.
+        streams[0]
+            .send(Err(tonic::Status::ok("disconnect")))
+            .await
+            .unwrap();
+        handle.allow(1);
+        let (tx0, rx0) = mpsc::channel(3);
+        tx0.send(Ok(100u16)).await.unwrap();
+        let (t, rsp) = handle.next_request().await.unwrap();
+        assert_eq!(t, 0);
+        rsp.send_response(tonic::Response::new(Box::pin(ReceiverStream::new(rx0))));
+
+        // The second watch re-issues its lookup.
+        handle.allow(1);
+        let (tx1, rx1) = mpsc::channel(3);
+        tx1.send(Ok(101u16)).await.unwrap();
+        let (t, rsp) = handle.next_request().await.unwrap();
+        assert_eq!(t, 1);
+        rsp.send_response(tonic::Response::new(Box::pin(ReceiverStream::new(rx1))));
+
+        tokio::task::yield_now().await;
+
+        assert_eq!(*watches[0].borrow(), 100);
+        assert_eq!(*watches[1].borrow(), 101);
+        assert_eq!(resynced.value(), 1.0);
+    }
 }
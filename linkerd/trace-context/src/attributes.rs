@@ -0,0 +1,309 @@
+use linkerd_stack::{layer, NewService, Proxy};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+};
+use thiserror::Error;
+
+/// Mesh-specific metadata that may be added to spans, so that traces can be
+/// sliced by mesh dimensions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Attribute {
+    /// The identity of the peer on the other side of the span's connection.
+    PeerIdentity,
+    /// The logical destination of the request.
+    LogicalDst,
+    /// The concrete destination chosen for the request by a traffic split.
+    ConcreteDst,
+    /// The name of the service profile route that matched the request.
+    Route,
+    /// The attempt number of a retried request.
+    RetryAttempt,
+    /// Whether the request was sent on a connection that had already carried
+    /// a request.
+    ConnectionReused,
+}
+
+/// The set of mesh-specific attributes added to spans.
+#[derive(Clone, Debug, Default)]
+pub struct Attributes(Arc<HashSet<Attribute>>);
+
+#[derive(Debug, Error)]
+#[error("unknown span attribute: {0}")]
+pub struct InvalidAttribute(String);
+
+/// Records the mesh-specific attributes of a sampled request's span.
+///
+/// `TraceContext` adds a `SpanAttributes` to the extensions of each sampled
+/// request if any attributes are enabled. Attributes that describe the
+/// request, rather than a single hop, are inherited by the spans of inner
+/// `TraceContext`s.
+#[derive(Clone, Debug)]
+pub struct SpanAttributes {
+    enabled: Attributes,
+    values: Arc<Mutex<HashMap<Attribute, String>>>,
+}
+
+/// Sets an attribute on the span of each request from the stack's target.
+#[derive(Clone, Debug)]
+pub struct NewSetAttribute<F, N> {
+    inner: N,
+    attribute: Attribute,
+    value: F,
+}
+
+#[derive(Clone, Debug)]
+pub struct SetAttribute<S> {
+    inner: S,
+    attribute: Attribute,
+    value: Option<String>,
+}
+
+/// Records whether each request is sent on a connection that has already
+/// carried a request.
+///
+/// This must be applied to a service that is built for each connection.
+#[derive(Clone, Debug)]
+pub struct SetConnectionReused<S> {
+    inner: S,
+    requests: Arc<AtomicUsize>,
+}
+
+// === impl Attribute ===
+
+impl Attribute {
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::PeerIdentity => "peer.identity",
+            Self::LogicalDst => "dst.logical",
+            Self::ConcreteDst => "dst.concrete",
+            Self::Route => "route",
+            Self::RetryAttempt => "retry.attempt",
+            Self::ConnectionReused => "connection.reused",
+        }
+    }
+
+    /// Returns true if the attribute describes the request, rather than the
+    /// connection that carries it.
+    fn is_inherited(&self) -> bool {
+        !matches!(self, Self::PeerIdentity | Self::ConnectionReused)
+    }
+}
+
+impl FromStr for Attribute {
+    type Err = InvalidAttribute;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "peer.identity" => Ok(Self::PeerIdentity),
+            "dst.logical" => Ok(Self::LogicalDst),
+            "dst.concrete" => Ok(Self::ConcreteDst),
+            "route" => Ok(Self::Route),
+            "retry.attempt" => Ok(Self::RetryAttempt),
+            "connection.reused" => Ok(Self::ConnectionReused),
+            _ => Err(InvalidAttribute(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.key().fmt(f)
+    }
+}
+
+// === impl Attributes ===
+
+impl Attributes {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, attribute: Attribute) -> bool {
+        self.0.contains(&attribute)
+    }
+}
+
+impl std::iter::FromIterator<Attribute> for Attributes {
+    fn from_iter<I: IntoIterator<Item = Attribute>>(iter: I) -> Self {
+        Self(Arc::new(iter.into_iter().collect()))
+    }
+}
+
+// === impl SpanAttributes ===
+
+impl SpanAttributes {
+    /// Returns `None` if no attributes are enabled.
+    ///
+    /// If an outer span's attributes are provided, its inherited attributes
+    /// are copied into the new span's attributes.
+    pub(crate) fn new(enabled: &Attributes, outer: Option<&Self>) -> Option<Self> {
+        if enabled.is_empty() {
+            return None;
+        }
+
+        let values = outer.map(Self::inherited).unwrap_or_default();
+        Some(Self {
+            enabled: enabled.clone(),
+            values: Arc::new(Mutex::new(values)),
+        })
+    }
+
+    /// Returns a copy of these attributes that may be modified without
+    /// changing this span's attributes, e.g. for a retried request.
+    pub fn child(&self) -> Self {
+        Self {
+            enabled: self.enabled.clone(),
+            values: Arc::new(Mutex::new(self.inherited())),
+        }
+    }
+
+    /// Sets the value of an attribute, if it is enabled.
+    pub fn set(&self, attribute: Attribute, value: impl ToString) {
+        if self.enabled.contains(attribute) {
+            if let Ok(mut values) = self.values.lock() {
+                values.insert(attribute, value.to_string());
+            }
+        }
+    }
+
+    pub fn get(&self, attribute: Attribute) -> Option<String> {
+        self.values.lock().ok()?.get(&attribute).cloned()
+    }
+
+    /// Adds the recorded attributes to a span's labels.
+    pub(crate) fn add_labels(&self, labels: &mut HashMap<&'static str, String>) {
+        if let Ok(values) = self.values.lock() {
+            for (attribute, value) in values.iter() {
+                labels.insert(attribute.key(), value.clone());
+            }
+        }
+    }
+
+    fn inherited(&self) -> HashMap<Attribute, String> {
+        match self.values.lock() {
+            Ok(values) => values
+                .iter()
+                .filter(|(attribute, _)| attribute.is_inherited())
+                .map(|(attribute, value)| (*attribute, value.clone()))
+                .collect(),
+            Err(_) => HashMap::new(),
+        }
+    }
+}
+
+// === impl NewSetAttribute ===
+
+impl<F: Clone, N> NewSetAttribute<F, N> {
+    pub fn layer(attribute: Attribute, value: F) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            inner,
+            attribute,
+            value: value.clone(),
+        })
+    }
+}
+
+impl<T, V, F, N> NewService<T> for NewSetAttribute<F, N>
+where
+    F: Fn(&T) -> Option<V>,
+    V: ToString,
+    N: NewService<T>,
+{
+    type Service = SetAttribute<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let value = (self.value)(&target).map(|v| v.to_string());
+        SetAttribute {
+            inner: self.inner.new_service(target),
+            attribute: self.attribute,
+            value,
+        }
+    }
+}
+
+// === impl SetAttribute ===
+
+impl<S> SetAttribute<S> {
+    fn set<B>(&self, req: &http::Request<B>) {
+        if let Some(value) = self.value.as_ref() {
+            if let Some(attributes) = req.extensions().get::<SpanAttributes>() {
+                attributes.set(self.attribute, value);
+            }
+        }
+    }
+}
+
+impl<B, S> tower::Service<http::Request<B>> for SetAttribute<S>
+where
+    S: tower::Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        self.set(&req);
+        self.inner.call(req)
+    }
+}
+
+impl<B, P, S> Proxy<http::Request<B>, S> for SetAttribute<P>
+where
+    P: Proxy<http::Request<B>, S>,
+    S: tower::Service<P::Request>,
+{
+    type Request = P::Request;
+    type Response = P::Response;
+    type Error = P::Error;
+    type Future = P::Future;
+
+    fn proxy(&self, svc: &mut S, req: http::Request<B>) -> Self::Future {
+        self.set(&req);
+        self.inner.proxy(svc, req)
+    }
+}
+
+// === impl SetConnectionReused ===
+
+impl<S> SetConnectionReused<S> {
+    pub fn layer() -> impl layer::Layer<S, Service = Self> + Clone {
+        layer::mk(|inner| Self {
+            inner,
+            requests: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+}
+
+impl<B, S> tower::Service<http::Request<B>> for SetConnectionReused<S>
+where
+    S: tower::Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let reused = self.requests.fetch_add(1, Ordering::Relaxed) > 0;
+        if let Some(attributes) = req.extensions().get::<SpanAttributes>() {
+            attributes.set(Attribute::ConnectionReused, reused);
+        }
+        self.inner.call(req)
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{Id, Span, SpanSink};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+use tracing::{info, trace};
+
+/// Emits spans that are children of a sampled request's span.
+///
+/// `TraceContext` adds a `ChildSpans` to the extensions of each sampled
+/// request so that inner services can record where time was spent while
+/// handling it.
+#[derive(Clone)]
+pub struct ChildSpans {
+    trace_id: Id,
+    parent_id: Id,
+    sink: Arc<Mutex<dyn SpanSink + Send>>,
+}
+
+/// A span that was recorded while a connection was being established, before
+/// any of its requests were traced.
+#[derive(Debug)]
+pub(crate) struct Deferred {
+    span_name: &'static str,
+    start: SystemTime,
+    end: SystemTime,
+}
+
+tokio::task_local! {
+    static DEFERRED: RefCell<Vec<Deferred>>;
+}
+
+/// Drives a connection's future so that spans deferred while the connection
+/// is established are emitted as children of its first traced request.
+pub async fn scope_connection<F: Future>(connection: F) -> F::Output {
+    DEFERRED.scope(RefCell::new(Vec::new()), connection).await
+}
+
+/// Records a span to be emitted with the current connection's first traced
+/// request.
+///
+/// The span is dropped if the connection is not scoped by `scope_connection`.
+pub(crate) fn defer(span_name: &'static str, start: SystemTime, end: SystemTime) {
+    let _ = DEFERRED.try_with(|deferred| {
+        deferred.borrow_mut().push(Deferred {
+            span_name,
+            start,
+            end,
+        })
+    });
+}
+
+/// Takes the spans deferred on the current connection, so that they are only
+/// emitted with its first request.
+pub(crate) fn take_deferred() -> Vec<Deferred> {
+    DEFERRED
+        .try_with(|deferred| std::mem::take(&mut *deferred.borrow_mut()))
+        .unwrap_or_default()
+}
+
+// === impl ChildSpans ===
+
+impl ChildSpans {
+    pub(crate) fn new<K>(trace_id: Id, parent_id: Id, sink: K) -> Self
+    where
+        K: SpanSink + Send + 'static,
+    {
+        Self {
+            trace_id,
+            parent_id,
+            sink: Arc::new(Mutex::new(sink)),
+        }
+    }
+
+    /// Emits a span named `span_name` as a child of the request's span.
+    pub fn emit(&self, span_name: impl Into<String>, start: SystemTime, end: SystemTime) {
+        let span = Span {
+            trace_id: self.trace_id.clone(),
+            span_id: Id::new_span_id(&mut rand::thread_rng()),
+            parent_id: self.parent_id.clone(),
+            span_name: span_name.into(),
+            start,
+            end,
+            labels: HashMap::new(),
+        };
+        trace!(?span);
+        if let Ok(mut sink) = self.sink.lock() {
+            if let Err(error) = sink.try_send(span) {
+                info!(%error, "Span dropped");
+            }
+        }
+    }
+
+    /// Emits spans that were deferred while the request's connection was
+    /// established.
+    pub(crate) fn emit_deferred(&self, deferred: Vec<Deferred>) {
+        for Deferred {
+            span_name,
+            start,
+            end,
+        } in deferred
+        {
+            self.emit(span_name, start, end);
+        }
+    }
+}
+
+impl fmt::Debug for ChildSpans {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChildSpans")
+            .field("trace_id", &self.trace_id)
+            .field("parent_id", &self.parent_id)
+            .finish()
+    }
+}
@@ -1,22 +1,35 @@
 #![deny(warnings, rust_2018_idioms)]
 #![forbid(unsafe_code)]
 
+mod attributes;
+mod children;
 mod propagation;
+mod ready;
 mod service;
 
-pub use self::service::TraceContext;
+pub use self::{
+    attributes::{
+        Attribute, Attributes, InvalidAttribute, NewSetAttribute, SetAttribute,
+        SetConnectionReused, SpanAttributes,
+    },
+    children::{scope_connection, ChildSpans},
+    ready::{DeferredReadySpan, ReadySpan},
+    service::TraceContext,
+};
 use bytes::Bytes;
 use linkerd_error::Error;
 use rand::Rng;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
 use std::time::SystemTime;
 use thiserror::Error;
 
 const SPAN_ID_LEN: usize = 8;
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct Id(Vec<u8>);
 
 #[derive(Debug, Default)]
@@ -41,8 +54,18 @@ pub trait SpanSink {
     fn is_enabled(&self) -> bool;
 
     fn try_send(&mut self, span: Span) -> Result<(), Error>;
+
+    /// Sends a span, waiting for the sink to accept it if the sink applies
+    /// backpressure.
+    ///
+    /// By default, this is the same as `try_send`.
+    fn send(&mut self, span: Span) -> SendFuture {
+        Box::pin(futures::future::ready(self.try_send(span)))
+    }
 }
 
+pub type SendFuture = Pin<Box<dyn Future<Output = Result<(), Error>> + Send + 'static>>;
+
 impl<K: SpanSink> SpanSink for Option<K> {
     #[inline]
     fn is_enabled(&self) -> bool {
@@ -53,6 +76,11 @@ impl<K: SpanSink> SpanSink for Option<K> {
     fn try_send(&mut self, span: Span) -> Result<(), Error> {
         self.as_mut().expect("Must be enabled").try_send(span)
     }
+
+    #[inline]
+    fn send(&mut self, span: Span) -> SendFuture {
+        self.as_mut().expect("Must be enabled").send(span)
+    }
 }
 
 // === impl Id ===
@@ -0,0 +1,351 @@
+use crate::children::{self, ChildSpans};
+use futures::{future::Either, prelude::*};
+use linkerd_stack::layer;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::SystemTime,
+};
+
+/// A layer that records how long an inner service takes to become ready.
+///
+/// When the service becomes ready after waiting, the wait is emitted as a
+/// child of the next request's span, if that request is sampled. This
+/// describes cold-path latency (e.g. connecting to an endpoint) that is
+/// otherwise hidden within the proxy's span.
+#[derive(Debug)]
+pub struct ReadySpan<S> {
+    inner: S,
+    span_name: &'static str,
+    waiting: Waiting,
+}
+
+/// Like `ReadySpan`, but for services that are not called with requests
+/// (e.g. services that accept connections).
+///
+/// The wait is emitted as a child of the span of the connection's first
+/// request, if the connection is scoped by `scope_connection`. The wait is
+/// recorded when the response future is first polled, so that this may be
+/// used within a buffer.
+#[derive(Debug)]
+pub struct DeferredReadySpan<S> {
+    inner: S,
+    span_name: &'static str,
+    waiting: Waiting,
+}
+
+#[derive(Debug, Default)]
+struct Waiting {
+    since: Option<SystemTime>,
+    waited: Option<(SystemTime, SystemTime)>,
+}
+
+// === impl ReadySpan ===
+
+impl<S> ReadySpan<S> {
+    pub fn layer(span_name: &'static str) -> impl layer::Layer<S, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            inner,
+            span_name,
+            waiting: Waiting::default(),
+        })
+    }
+}
+
+impl<S: Clone> Clone for ReadySpan<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            span_name: self.span_name,
+            waiting: Waiting::default(),
+        }
+    }
+}
+
+impl<B, S> tower::Service<http::Request<B>> for ReadySpan<S>
+where
+    S: tower::Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        let poll = self.inner.poll_ready(cx);
+        self.waiting.record(poll)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        if let Some((start, end)) = self.waiting.waited.take() {
+            if let Some(children) = req.extensions().get::<ChildSpans>() {
+                children.emit(self.span_name, start, end);
+            }
+        }
+        self.inner.call(req)
+    }
+}
+
+// === impl DeferredReadySpan ===
+
+impl<S> DeferredReadySpan<S> {
+    pub fn layer(span_name: &'static str) -> impl layer::Layer<S, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            inner,
+            span_name,
+            waiting: Waiting::default(),
+        })
+    }
+}
+
+impl<S: Clone> Clone for DeferredReadySpan<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            span_name: self.span_name,
+            waiting: Waiting::default(),
+        }
+    }
+}
+
+impl<T, S> tower::Service<T> for DeferredReadySpan<S>
+where
+    S: tower::Service<T>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Either<
+        S::Future,
+        Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send + 'static>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        let poll = self.inner.poll_ready(cx);
+        self.waiting.record(poll)
+    }
+
+    fn call(&mut self, target: T) -> Self::Future {
+        let future = self.inner.call(target);
+        match self.waiting.waited.take() {
+            Some((start, end)) => {
+                let span_name = self.span_name;
+                Either::Right(Box::pin(
+                    future::lazy(move |_| children::defer(span_name, start, end))
+                        .then(move |()| future),
+                ))
+            }
+            None => Either::Left(future),
+        }
+    }
+}
+
+// === impl Waiting ===
+
+impl Waiting {
+    fn record<E>(&mut self, poll: Poll<Result<(), E>>) -> Poll<Result<(), E>> {
+        match poll {
+            Poll::Pending => {
+                if self.since.is_none() {
+                    self.since = Some(SystemTime::now());
+                }
+            }
+            Poll::Ready(Ok(())) => {
+                if let Some(start) = self.since.take() {
+                    self.waited = Some((start, SystemTime::now()));
+                }
+            }
+            Poll::Ready(Err(_)) => {
+                self.since = None;
+            }
+        }
+        poll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{scope_connection, Attributes, Span, SpanSink, TraceContext};
+    use linkerd_error::Error;
+    use linkerd_stack::layer::Layer;
+    use std::{
+        convert::Infallible,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+    use tower::Service;
+
+    const TRACE_ID: &str = "0123456789abcdef0123456789abcdef";
+
+    /// Tests that a wait for the service to become ready is emitted as a child
+    /// of the request's span, covering the time that the service was pending.
+    #[tokio::test(flavor = "current_thread")]
+    async fn ready_span_is_child_of_request() {
+        let sink = Sink::default();
+        let gate = Gate::default();
+        let mut svc = TraceContext::layer(sink.clone(), Attributes::default())
+            .layer(ReadySpan::layer("ready").layer(gate.clone()));
+
+        let before = SystemTime::now();
+        assert!(poll_ready::<http::Request<()>, _>(&mut svc)
+            .await
+            .is_pending());
+        std::thread::sleep(Duration::from_millis(10));
+        gate.open();
+        assert!(poll_ready::<http::Request<()>, _>(&mut svc)
+            .await
+            .is_ready());
+        let after = SystemTime::now();
+        svc.call(sampled_request()).await.unwrap();
+
+        let spans = sink.take();
+        assert_eq!(spans.len(), 2, "{:?}", spans);
+        let (child, parent) = (&spans[0], &spans[1]);
+        assert_eq!(child.span_name, "ready");
+        assert_eq!(child.trace_id.0, parent.trace_id.0);
+        assert_eq!(child.parent_id.0, parent.span_id.0);
+        assert!(before <= child.start);
+        assert!(child.end <= after);
+        assert!(child.end.duration_since(child.start).unwrap() >= Duration::from_millis(10));
+
+        // Once the wait has been emitted, subsequent requests to the ready
+        // service do not emit it again.
+        assert!(poll_ready::<http::Request<()>, _>(&mut svc)
+            .await
+            .is_ready());
+        svc.call(sampled_request()).await.unwrap();
+        let spans = sink.take();
+        assert_eq!(spans.len(), 1, "{:?}", spans);
+    }
+
+    /// Tests that a wait for a connection-level service is emitted as a child
+    /// of the span of the connection's first request.
+    #[tokio::test(flavor = "current_thread")]
+    async fn deferred_ready_span_is_child_of_first_request() {
+        let sink = Sink::default();
+        scope_connection(async {
+            let gate = Gate::default();
+            let mut connect = DeferredReadySpan::layer("connect").layer(gate.clone());
+            let before = SystemTime::now();
+            assert!(poll_ready::<(), _>(&mut connect).await.is_pending());
+            std::thread::sleep(Duration::from_millis(10));
+            gate.open();
+            assert!(poll_ready::<(), _>(&mut connect).await.is_ready());
+            let after = SystemTime::now();
+            // The wait is recorded when the response future is polled.
+            connect.call(()).await.unwrap();
+
+            let mut svc =
+                TraceContext::layer(sink.clone(), Attributes::default()).layer(Gate::opened());
+            svc.call(sampled_request()).await.unwrap();
+            let spans = sink.take();
+            assert_eq!(spans.len(), 2, "{:?}", spans);
+            let (child, parent) = (&spans[0], &spans[1]);
+            assert_eq!(child.span_name, "connect");
+            assert_eq!(child.trace_id.0, parent.trace_id.0);
+            assert_eq!(child.parent_id.0, parent.span_id.0);
+            assert!(before <= child.start);
+            assert!(child.end <= after);
+            assert!(child.end.duration_since(child.start).unwrap() >= Duration::from_millis(10));
+
+            // Only the connection's first request emits the deferred span.
+            svc.call(sampled_request()).await.unwrap();
+            let spans = sink.take();
+            assert_eq!(spans.len(), 1, "{:?}", spans);
+        })
+        .await;
+    }
+
+    /// Tests that a deferred wait is dropped if the connection is not scoped.
+    #[tokio::test(flavor = "current_thread")]
+    async fn deferred_ready_span_requires_connection_scope() {
+        let sink = Sink::default();
+        let gate = Gate::default();
+        let mut connect = DeferredReadySpan::layer("connect").layer(gate.clone());
+        assert!(poll_ready::<(), _>(&mut connect).await.is_pending());
+        gate.open();
+        assert!(poll_ready::<(), _>(&mut connect).await.is_ready());
+        connect.call(()).await.unwrap();
+
+        let mut svc =
+            TraceContext::layer(sink.clone(), Attributes::default()).layer(Gate::opened());
+        svc.call(sampled_request()).await.unwrap();
+        let spans = sink.take();
+        assert_eq!(spans.len(), 1, "{:?}", spans);
+        assert_eq!(spans[0].span_name, "/");
+    }
+
+    fn sampled_request() -> http::Request<()> {
+        http::Request::builder()
+            .uri("http://example.com/")
+            .header("x-b3-traceid", TRACE_ID)
+            .header("x-b3-spanid", "0123456789abcdef")
+            .header("x-b3-sampled", "1")
+            .body(())
+            .unwrap()
+    }
+
+    async fn poll_ready<T, S: Service<T>>(svc: &mut S) -> Poll<Result<(), S::Error>> {
+        future::poll_fn(|cx| Poll::Ready(svc.poll_ready(cx))).await
+    }
+
+    /// Records the spans that are emitted.
+    #[derive(Clone, Default)]
+    struct Sink(Arc<Mutex<Vec<Span>>>);
+
+    /// A service that is not ready until it is opened.
+    #[derive(Clone, Default)]
+    struct Gate(Arc<AtomicBool>);
+
+    impl Sink {
+        fn take(&self) -> Vec<Span> {
+            std::mem::take(&mut *self.0.lock().unwrap())
+        }
+    }
+
+    impl SpanSink for Sink {
+        fn is_enabled(&self) -> bool {
+            true
+        }
+
+        fn try_send(&mut self, span: Span) -> Result<(), Error> {
+            self.0.lock().unwrap().push(span);
+            Ok(())
+        }
+    }
+
+    impl Gate {
+        fn opened() -> Self {
+            let gate = Self::default();
+            gate.open();
+            gate
+        }
+
+        fn open(&self) {
+            self.0.store(true, Ordering::Release);
+        }
+    }
+
+    impl<T> Service<T> for Gate {
+        type Response = http::Response<()>;
+        type Error = Infallible;
+        type Future = future::Ready<Result<http::Response<()>, Infallible>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            if self.0.load(Ordering::Acquire) {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&mut self, _: T) -> Self::Future {
+            future::ok(http::Response::new(()))
+        }
+    }
+}
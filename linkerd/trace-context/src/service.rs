@@ -1,5 +1,8 @@
-use crate::{propagation, Span, SpanSink};
-use futures::{future::Either, prelude::*};
+use crate::{
+    children::{self, ChildSpans},
+    propagation, Attributes, Span, SpanAttributes, SpanSink,
+};
+use futures::future::Either;
 use linkerd_stack::layer;
 use std::{
     collections::HashMap,
@@ -18,20 +21,28 @@ use tracing::{debug, info, trace};
 /// random span id setting it into the `traceparent` header before forwarding
 /// the request. If the sampled bit of the header was set, we emit metadata
 /// about the span to the given SpanSink when the span is complete, i.e. when
-/// we receive the response.
+/// we receive the response. Sampled requests carry a `ChildSpans` extension
+/// so that inner services may emit spans as children of the request's span,
+/// and a `SpanAttributes` extension if any mesh-specific attributes are
+/// enabled.
 #[derive(Clone, Debug)]
 pub struct TraceContext<K, S> {
     inner: S,
     sink: K,
+    attributes: Attributes,
 }
 
 // === impl TraceContext ===
 
 impl<K: Clone, S> TraceContext<K, S> {
-    pub fn layer(sink: K) -> impl layer::Layer<S, Service = TraceContext<K, S>> + Clone {
+    pub fn layer(
+        sink: K,
+        attributes: Attributes,
+    ) -> impl layer::Layer<S, Service = TraceContext<K, S>> + Clone {
         layer::mk(move |inner| TraceContext {
             inner,
             sink: sink.clone(),
+            attributes: attributes.clone(),
         })
     }
 
@@ -70,6 +81,7 @@ where
     S: tower::Service<http::Request<ReqB>, Response = http::Response<RspB>>,
     S::Error: Send,
     S::Future: Send + 'static,
+    RspB: Send,
 {
     type Response = S::Response;
     type Error = S::Error;
@@ -85,6 +97,9 @@ where
 
     fn call(&mut self, mut req: http::Request<ReqB>) -> Self::Future {
         if self.sink.is_enabled() {
+            // Spans deferred while the connection was established are only
+            // emitted with its first request.
+            let deferred = children::take_deferred();
             if let Some(context) = propagation::unpack_trace_context(&req) {
                 // Update the trace ID if the request set one and the proxy is configured to emit
                 // spans.
@@ -94,11 +109,28 @@ where
                 if context.is_sampled() {
                     // If the request has been marked for sampling, record its metadata.
                     let start = SystemTime::now();
+                    let children = ChildSpans::new(
+                        context.trace_id.clone(),
+                        span_id.clone(),
+                        self.sink.clone(),
+                    );
+                    children.emit_deferred(deferred);
+                    req.extensions_mut().insert(children);
+                    let attributes = SpanAttributes::new(&self.attributes, req.extensions().get());
+                    if let Some(attributes) = attributes.clone() {
+                        req.extensions_mut().insert(attributes);
+                    }
                     let req_labels = Self::request_labels(&req);
                     let mut sink = self.sink.clone();
                     let span_name = req.uri().path().to_owned();
-                    return Either::Right(Box::pin(self.inner.call(req).map_ok(move |rsp| {
+                    let call = self.inner.call(req);
+                    return Either::Right(Box::pin(async move {
+                        let rsp = call.await?;
                         // Emit the completed span with the response metadata.
+                        let mut labels = Self::add_response_labels(req_labels, &rsp);
+                        if let Some(attributes) = attributes {
+                            attributes.add_labels(&mut labels);
+                        }
                         let span = Span {
                             span_id,
                             trace_id: context.trace_id,
@@ -106,14 +138,17 @@ where
                             span_name,
                             start,
                             end: SystemTime::now(),
-                            labels: Self::add_response_labels(req_labels, &rsp),
+                            labels,
                         };
                         trace!(?span);
-                        if let Err(error) = sink.try_send(span) {
+                        // The response is held until the sink accepts the
+                        // span, so that a sink that applies backpressure slows
+                        // the traffic it is tracing rather than dropping spans.
+                        if let Err(error) = sink.send(span).await {
                             info!(%error, "Span dropped");
                         }
-                        rsp
-                    })));
+                        Ok(rsp)
+                    }));
                 }
             }
         }
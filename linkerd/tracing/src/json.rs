@@ -0,0 +1,312 @@
+//! Formats events as JSON objects with stable field names.
+//!
+//! Each event is written as a single line containing a JSON object with the
+//! following fields:
+//!
+//! - `timestamp`: the wall-clock time at which the event was recorded, as an
+//!   RFC 3339 UTC timestamp with microsecond precision;
+//! - `uptime`: the number of seconds since the process started logging;
+//! - `level`: one of `TRACE`, `DEBUG`, `INFO`, `WARN`, or `ERROR`;
+//! - `target`: the module path (or explicit target) that emitted the event;
+//! - `thread_id`: the ID of the thread that recorded the event;
+//! - `message`: the event's message, omitted if the event has none;
+//! - `fields`: an object containing the event's other fields;
+//! - `spans`: the names of the spans in the event's context, from the
+//!   outermost to the innermost;
+//! - `span_fields`: an object containing the fields of all spans in the
+//!   event's context, keyed by `<span name>.<field name>`.
+//!
+//! Errors are always formatted as arrays of strings: fields recorded as
+//! `std::error::Error`s contain each error in the chain of sources, starting
+//! with the outermost error; and `error` fields recorded as other values
+//! contain a single element.
+
+use crate::uptime::Uptime;
+use serde_json::{Map, Value};
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{
+    field::{Field, Visit},
+    Event, Subscriber,
+};
+use tracing_log::NormalizeEvent;
+use tracing_subscriber::{
+    fmt::{
+        format::{FormatEvent, JsonFields},
+        FmtContext, FormattedFields,
+    },
+    registry::LookupSpan,
+};
+
+pub(crate) struct Format {
+    uptime: Uptime,
+    thread_ids: bool,
+}
+
+#[derive(Debug, Default)]
+struct Fields {
+    message: Option<String>,
+    fields: Map<String, Value>,
+}
+
+// === impl Format ===
+
+impl Format {
+    pub(crate) fn new(thread_ids: bool) -> Self {
+        Self {
+            uptime: Uptime::starting_now(),
+            thread_ids,
+        }
+    }
+}
+
+impl<S> FormatEvent<S, JsonFields> for Format
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, JsonFields>,
+        writer: &mut dyn fmt::Write,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        // Events emitted via the `log` crate carry their metadata as fields.
+        let normalized = event.normalized_metadata();
+        let meta = normalized.as_ref().unwrap_or_else(|| event.metadata());
+
+        let mut rec = Fields::default();
+        event.record(&mut rec);
+
+        let mut obj = Map::new();
+        obj.insert("timestamp".into(), rfc3339(SystemTime::now()).into());
+        obj.insert("uptime".into(), self.uptime.elapsed().as_secs_f64().into());
+        obj.insert("level".into(), meta.level().to_string().into());
+        obj.insert("target".into(), meta.target().into());
+        if self.thread_ids {
+            let id = format!("{:?}", std::thread::current().id());
+            obj.insert("thread_id".into(), id.into());
+        }
+        if let Some(message) = rec.message {
+            obj.insert("message".into(), message.into());
+        }
+        obj.insert("fields".into(), rec.fields.into());
+
+        let mut spans = Vec::new();
+        let mut span_fields = Map::new();
+        ctx.visit_spans(|span| {
+            spans.push(Value::from(span.name()));
+            let ext = span.extensions();
+            let fields = ext
+                .get::<FormattedFields<JsonFields>>()
+                .and_then(|f| serde_json::from_str::<Map<String, Value>>(f).ok());
+            for (k, v) in fields.into_iter().flatten() {
+                span_fields.insert(format!("{}.{}", span.name(), k), v);
+            }
+            Ok::<(), fmt::Error>(())
+        })?;
+        obj.insert("spans".into(), spans.into());
+        obj.insert("span_fields".into(), span_fields.into());
+
+        writeln!(writer, "{}", Value::Object(obj))
+    }
+}
+
+// === impl Fields ===
+
+impl Fields {
+    fn insert(&mut self, field: &Field, value: Value) {
+        match field.name() {
+            "message" => {
+                let msg = match value {
+                    Value::String(s) => s,
+                    v => v.to_string(),
+                };
+                self.message = Some(msg);
+            }
+            "error" => {
+                let chain = match value {
+                    Value::Array(chain) => chain,
+                    v => vec![v],
+                };
+                self.fields.insert("error".into(), chain.into());
+            }
+            // Fields added to events emitted via the `log` crate duplicate the
+            // normalized metadata.
+            name if name.starts_with("log.") => {}
+            name => {
+                self.fields.insert(name.into(), value);
+            }
+        }
+    }
+}
+
+impl Visit for Fields {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.into());
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        let mut chain = vec![Value::from(value.to_string())];
+        let mut source = value.source();
+        while let Some(e) = source {
+            chain.push(e.to_string().into());
+            source = e.source();
+        }
+        self.insert(field, chain.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, format!("{:?}", value).into());
+    }
+}
+
+/// Formats a time as an RFC 3339 UTC timestamp with microsecond precision.
+fn rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+
+    // Converts days since the epoch to a civil date. See
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3_600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60,
+        since_epoch.subsec_micros()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+    use tracing_subscriber::prelude::*;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    #[derive(Debug)]
+    struct Outer(Inner);
+
+    #[derive(Debug)]
+    struct Inner;
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl fmt::Display for Outer {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("connection failed")
+        }
+    }
+
+    impl std::error::Error for Outer {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    impl fmt::Display for Inner {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("connection refused")
+        }
+    }
+
+    impl std::error::Error for Inner {}
+
+    #[test]
+    fn formats_events() {
+        let buf = Buffer::default();
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .fmt_fields(JsonFields::default())
+                .event_format(Format::new(false))
+                .with_writer({
+                    let buf = buf.clone();
+                    move || buf.clone()
+                }),
+        );
+        tracing::subscriber::with_default(subscriber, || {
+            let _outbound = tracing::info_span!("outbound").entered();
+            let _tcp = tracing::info_span!("tcp", orig_dst = "192.0.2.1:80").entered();
+            let error = Outer(Inner);
+            tracing::warn!(
+                error = &error as &(dyn std::error::Error + 'static),
+                attempts = 3,
+                "Failed to connect"
+            );
+        });
+
+        let line = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        let event = serde_json::from_str::<Value>(&line).expect("event must be JSON");
+        assert_eq!(event["level"], "WARN");
+        assert_eq!(event["target"], module_path!());
+        assert_eq!(event["message"], "Failed to connect");
+        assert_eq!(
+            event["fields"],
+            serde_json::json!({
+                "error": ["connection failed", "connection refused"],
+                "attempts": 3,
+            })
+        );
+        assert_eq!(event["spans"], serde_json::json!(["outbound", "tcp"]));
+        assert_eq!(
+            event["span_fields"],
+            serde_json::json!({ "tcp.orig_dst": "192.0.2.1:80" })
+        );
+        assert!(event["timestamp"].is_string());
+        assert!(event["uptime"].is_number());
+        assert!(event.get("thread_id").is_none());
+    }
+
+    #[test]
+    fn formats_timestamps() {
+        let at = |secs, micros: u32| UNIX_EPOCH + Duration::new(secs, micros * 1_000);
+        assert_eq!(rfc3339(at(0, 0)), "1970-01-01T00:00:00.000000Z");
+        assert_eq!(
+            rfc3339(at(1_700_000_000, 123_456)),
+            "2023-11-14T22:13:20.123456Z"
+        );
+        assert_eq!(rfc3339(at(951_782_400, 0)), "2000-02-29T00:00:00.000000Z");
+    }
+}
@@ -1,6 +1,7 @@
 #![deny(warnings, rust_2018_idioms)]
 #![forbid(unsafe_code)]
 
+mod json;
 pub mod level;
 pub mod test;
 mod uptime;
@@ -22,6 +23,8 @@ type Registry =
     Layered<reload::Layer<EnvFilter, tracing_subscriber::Registry>, tracing_subscriber::Registry>;
 
 const ENV_LOG_LEVEL: &str = "LINKERD2_PROXY_LOG";
+/// Either `PLAIN` or `JSON`. JSON logs are formatted with stable field names
+/// that are described in the `json` module.
 const ENV_LOG_FORMAT: &str = "LINKERD2_PROXY_LOG_FORMAT";
 
 const DEFAULT_LOG_LEVEL: &str = "warn,linkerd=info";
@@ -121,23 +124,13 @@ impl Settings {
         let (tasks, tasks_layer) = TasksLayer::<format::JsonFields>::new();
         let registry = registry.with(tasks_layer);
 
-        let fmt = tracing_subscriber::fmt::format()
-            .with_timer(Uptime::starting_now())
-            .with_thread_ids(!self.is_test)
-            // Configure the formatter to output JSON logs.
-            .json()
-            // Output the current span context as a JSON list.
-            .with_span_list(true)
-            // Don't output a field for the current span, since this
-            // would duplicate information already in the span list.
-            .with_current_span(false);
-
         let fmt = tracing_subscriber::fmt::layer()
-            // Use the JSON event formatter.
-            .event_format(fmt)
             // Since we're using the JSON event formatter, we must also
             // use the JSON field formatter.
-            .fmt_fields(format::JsonFields::default());
+            .fmt_fields(format::JsonFields::default())
+            // Use the JSON event formatter, which outputs stable field names.
+            // See the `json` module for a description of each field.
+            .event_format(json::Format::new(!self.is_test));
 
         let dispatch = if self.is_test {
             registry.with(fmt.with_test_writer()).into()
@@ -12,6 +12,10 @@ impl Uptime {
         }
     }
 
+    pub(crate) fn elapsed(&self) -> Duration {
+        Instant::now() - self.start_time
+    }
+
     fn format(d: Duration, w: &mut dyn fmt::Write) -> fmt::Result {
         let micros = d.subsec_micros();
         write!(w, "[{:>6}.{:06}s]", d.as_secs(), micros)
@@ -20,7 +24,7 @@ impl Uptime {
 
 impl tracing_subscriber::fmt::time::FormatTime for Uptime {
     fn format_time(&self, w: &mut dyn fmt::Write) -> fmt::Result {
-        Self::format(Instant::now() - self.start_time, w)
+        Self::format(self.elapsed(), w)
     }
 }
 
@@ -12,7 +12,7 @@ use linkerd_dns_name::Name;
 use linkerd_error::Error;
 use linkerd_io::{self as io, AsyncReadExt, AsyncWriteExt};
 use prost::Message;
-use std::str::FromStr;
+use std::{collections::BTreeMap, net::SocketAddr, str::FromStr};
 use tracing::trace;
 
 mod proto {
@@ -29,6 +29,9 @@ pub struct TransportHeader {
 
     /// Indicates whether a protocol is known for the connection.
     pub protocol: Option<SessionProtocol>,
+
+    /// Additional metadata, only encoded in version 2 headers.
+    pub extensions: Extensions,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -37,13 +40,93 @@ pub enum SessionProtocol {
     Http2,
 }
 
+/// Optional metadata carried by version 2 headers.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Extensions {
+    /// The address of the client that originated the connection.
+    pub client_addr: Option<SocketAddr>,
+
+    /// The name of the cluster that the connection targets.
+    pub cluster: Option<String>,
+
+    /// Trace context propagation fields (e.g. `traceparent`).
+    pub trace_context: BTreeMap<String, String>,
+}
+
+/// The version of the transport header protocol, as negotiated by ALPN.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Version {
+    V1,
+    V2,
+}
+
 pub const PROTOCOL: &[u8] = b"transport.l5d.io/v1";
+pub const PROTOCOL_V2: &[u8] = b"transport.l5d.io/v2";
+
+/// The supported ALPN protocols, in order of preference.
+pub const PROTOCOLS: &[&[u8]] = &[PROTOCOL_V2, PROTOCOL];
+
 const PREFACE: &[u8] = b"transport.l5d.io/v1\r\n\r\n";
+const PREFACE_V2: &[u8] = b"transport.l5d.io/v2\r\n\r\n";
 const PREFACE_AND_SIZE_LEN: usize = PREFACE.len() + 4;
 
+// === impl Version ===
+
+impl Version {
+    /// Returns the version indicated by a negotiated ALPN protocol, if the
+    /// protocol is a transport header protocol.
+    pub fn from_alpn(protocol: &[u8]) -> Option<Self> {
+        if protocol == PROTOCOL_V2 {
+            Some(Self::V2)
+        } else if protocol == PROTOCOL {
+            Some(Self::V1)
+        } else {
+            None
+        }
+    }
+
+    pub fn alpn(self) -> &'static [u8] {
+        match self {
+            Self::V1 => PROTOCOL,
+            Self::V2 => PROTOCOL_V2,
+        }
+    }
+
+    fn preface(self) -> &'static [u8] {
+        match self {
+            Self::V1 => PREFACE,
+            Self::V2 => PREFACE_V2,
+        }
+    }
+
+    fn from_preface(preface: &[u8]) -> Option<Self> {
+        if preface == PREFACE_V2 {
+            Some(Self::V2)
+        } else if preface == PREFACE {
+            Some(Self::V1)
+        } else {
+            None
+        }
+    }
+}
+
+// === impl Extensions ===
+
+impl Extensions {
+    pub fn is_empty(&self) -> bool {
+        self.client_addr.is_none() && self.cluster.is_none() && self.trace_context.is_empty()
+    }
+}
+
+// === impl TransportHeader ===
+
 impl TransportHeader {
-    pub async fn write(&self, io: &mut (impl io::AsyncWrite + Unpin)) -> Result<usize, Error> {
-        let mut buf = self.encode_prefaced_buf()?;
+    pub async fn write(
+        &self,
+        version: Version,
+        io: &mut (impl io::AsyncWrite + Unpin),
+    ) -> Result<usize, Error> {
+        let mut buf = self.encode_prefaced_buf(version)?;
         let mut sz = 0usize;
 
         while !buf.is_empty() {
@@ -55,15 +138,17 @@ impl TransportHeader {
     }
 
     #[inline]
-    pub fn encode_prefaced_buf(&self) -> Result<Bytes, Error> {
+    pub fn encode_prefaced_buf(&self, version: Version) -> Result<Bytes, Error> {
         let mut buf = BytesMut::new();
-        self.encode_prefaced(&mut buf)?;
+        self.encode_prefaced(version, &mut buf)?;
         Ok(buf.freeze())
     }
 
     /// Encodes the connection header to a byte buffer.
-    pub fn encode_prefaced(&self, buf: &mut BytesMut) -> Result<(), Error> {
-        let header = self.to_proto();
+    ///
+    /// Extensions are omitted from version 1 headers.
+    pub fn encode_prefaced(&self, version: Version, buf: &mut BytesMut) -> Result<(), Error> {
+        let header = self.to_proto(version);
         let header_len = header.encoded_len();
         if header_len > std::u32::MAX as usize {
             return Err(io::Error::new(
@@ -74,7 +159,7 @@ impl TransportHeader {
         }
 
         buf.reserve(PREFACE_AND_SIZE_LEN);
-        buf.put(PREFACE);
+        buf.put(version.preface());
         debug_assert!(buf.capacity() >= 4);
         buf.put_u32(header_len as u32);
         header.encode(buf)?;
@@ -83,8 +168,8 @@ impl TransportHeader {
     }
 
     #[inline]
-    fn to_proto(&self) -> proto::Header {
-        proto::Header {
+    fn to_proto(&self, version: Version) -> proto::Header {
+        let mut header = proto::Header {
             port: self.port as i32,
             name: self
                 .name
@@ -103,7 +188,24 @@ impl TransportHeader {
                     )),
                 },
             }),
+            ..Default::default()
+        };
+
+        if version == Version::V2 {
+            let Extensions {
+                client_addr,
+                cluster,
+                trace_context,
+            } = &self.extensions;
+            header.client_addr = client_addr.map(|a| a.to_string()).unwrap_or_default();
+            header.cluster = cluster.clone().unwrap_or_default();
+            header.trace_context = trace_context
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
         }
+
+        header
     }
 
     /// Attempts to decode a connection header from an I/O stream.
@@ -124,10 +226,12 @@ impl TransportHeader {
             }
         }
 
-        // Advance the buffer past the preface if it matches.
-        if &buf.chunk()[..PREFACE.len()] != PREFACE {
-            return Ok(None);
-        }
+        // Advance the buffer past the preface if it matches. All versions'
+        // prefaces have the same length.
+        let version = match Version::from_preface(&buf.chunk()[..PREFACE.len()]) {
+            Some(version) => version,
+            None => return Ok(None),
+        };
         buf.advance(PREFACE.len());
 
         // Read the message length. If it is larger than our allowed buffer
@@ -155,11 +259,11 @@ impl TransportHeader {
         // Take the bytes needed to parse the message and leave the remaining
         // bytes in the caller-provided buffer.
         let msg = buf.split_to(msg_len);
-        Self::decode(msg.freeze())
+        Self::decode(version, msg.freeze())
     }
 
     // Decodes a protobuf message from the buffer.
-    fn decode<B: Buf>(buf: B) -> io::Result<Option<Self>> {
+    fn decode<B: Buf>(version: Version, buf: B) -> io::Result<Option<Self>> {
         let h = proto::Header::decode(buf)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
 
@@ -185,10 +289,29 @@ impl TransportHeader {
             })
         });
 
+        // Version 1 peers do not set extensions, so they are ignored.
+        let extensions = match version {
+            Version::V1 => Extensions::default(),
+            Version::V2 => Extensions {
+                client_addr: if h.client_addr.is_empty() {
+                    None
+                } else {
+                    let a = h
+                        .client_addr
+                        .parse()
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    Some(a)
+                },
+                cluster: Some(h.cluster).filter(|c| !c.is_empty()),
+                trace_context: h.trace_context.into_iter().collect(),
+            },
+        };
+
         Ok(Some(Self {
             port: h.port as u16,
             name,
             protocol,
+            extensions,
         }))
     }
 }
@@ -203,10 +326,13 @@ mod tests {
             port: 4040,
             name: Some(Name::from_str("foo.bar.example.com").unwrap()),
             protocol: Some(SessionProtocol::Http2),
+            extensions: Extensions::default(),
         };
         let mut rx = {
             let mut buf = BytesMut::new();
-            header.encode_prefaced(&mut buf).expect("must encode");
+            header
+                .encode_prefaced(Version::V1, &mut buf)
+                .expect("must encode");
             buf.put_slice(b"12345");
             std::io::Cursor::new(buf.freeze())
         };
@@ -237,11 +363,15 @@ mod tests {
             port: 4040,
             name: Some(Name::from_str("foo.bar.example.com").unwrap()),
             protocol: None,
+            extensions: Extensions::default(),
         };
         let mut rx = {
             let msg = {
                 let mut buf = BytesMut::new();
-                header.to_proto().encode(&mut buf).expect("must encode");
+                header
+                    .to_proto(Version::V1)
+                    .encode(&mut buf)
+                    .expect("must encode");
                 buf.freeze()
             };
             let len = {
@@ -271,6 +401,73 @@ mod tests {
             .expect("I/O must still have data");
         assert_eq!(&buf, b"12345");
     }
+
+    fn with_extensions() -> TransportHeader {
+        TransportHeader {
+            port: 4040,
+            name: Some(Name::from_str("foo.bar.example.com").unwrap()),
+            protocol: None,
+            extensions: Extensions {
+                client_addr: Some(([10, 1, 2, 3], 5678).into()),
+                cluster: Some("east".to_string()),
+                trace_context: Some((
+                    "traceparent".to_string(),
+                    "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01".to_string(),
+                ))
+                .into_iter()
+                .collect(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn roundtrip_extensions() {
+        let header = with_extensions();
+        let mut rx = {
+            let buf = header
+                .encode_prefaced_buf(Version::V2)
+                .expect("must encode");
+            std::io::Cursor::new(buf)
+        };
+        let mut buf = BytesMut::with_capacity(1024);
+        let h = TransportHeader::read_prefaced(&mut rx, &mut buf)
+            .await
+            .expect("decodes")
+            .expect("decodes");
+        assert_eq!(header, h);
+    }
+
+    #[tokio::test]
+    async fn v1_omits_extensions() {
+        let header = with_extensions();
+        let buf = header
+            .encode_prefaced_buf(Version::V1)
+            .expect("must encode");
+        assert!(buf.starts_with(PREFACE));
+
+        let mut rx = std::io::Cursor::new(buf);
+        let mut buf = BytesMut::new();
+        let h = TransportHeader::read_prefaced(&mut rx, &mut buf)
+            .await
+            .expect("decodes")
+            .expect("decodes");
+        assert!(h.extensions.is_empty());
+        assert_eq!(
+            h,
+            TransportHeader {
+                extensions: Extensions::default(),
+                ..header
+            }
+        );
+    }
+
+    #[test]
+    fn versions_from_alpn() {
+        for &version in &[Version::V1, Version::V2] {
+            assert_eq!(Version::from_alpn(version.alpn()), Some(version));
+        }
+        assert_eq!(Version::from_alpn(b"h2"), None);
+    }
 }
 
 #[cfg(fuzzing)]
@@ -283,6 +480,7 @@ pub mod fuzz_logic {
         data: Vec<u8>,
         port: u16,
         protocol: bool,
+        v2: bool,
     }
 
     pub async fn fuzz_entry_structured(transport_header: TransportHeaderSpec) {
@@ -296,10 +494,21 @@ pub mod fuzz_logic {
                 port: transport_header.port,
                 name: Name::from_str(fuzz_name).ok(),
                 protocol: Some(fuzz_proto),
+                extensions: Extensions {
+                    cluster: Some(fuzz_name.to_string()),
+                    ..Extensions::default()
+                },
+            };
+            let version = if transport_header.v2 {
+                Version::V2
+            } else {
+                Version::V1
             };
             let mut rx = {
                 let mut buf = BytesMut::new();
-                header.encode_prefaced(&mut buf).expect("must encode");
+                header
+                    .encode_prefaced(version, &mut buf)
+                    .expect("must encode");
                 std::io::Cursor::new(buf.freeze())
             };
             let mut buf = BytesMut::new();
@@ -6,6 +6,7 @@
 
 use linkerd_app::{core::transport::BindTcp, trace, Config};
 use linkerd_signal as signal;
+use std::{future::Future, time::Duration};
 use tokio::sync::mpsc;
 pub use tracing::{debug, error, info, warn};
 
@@ -54,6 +55,9 @@ fn main() {
 
         info!("Admin interface on {}", app.admin_addr());
         info!("Inbound interface on {}", app.inbound_addr());
+        for addr in app.additional_inbound_addrs() {
+            info!("Inbound interface on {}", addr);
+        }
         info!("Outbound interface on {}", app.outbound_addr());
 
         match app.tap_addr() {
@@ -61,6 +65,10 @@ fn main() {
             Some(addr) => info!("Tap interface on {}", addr),
         }
 
+        if let Some(addr) = app.udp_addr() {
+            info!("UDP interface on {}", addr);
+        }
+
         match app.local_identity() {
             None => warn!("Identity is DISABLED"),
             Some(identity) => {
@@ -96,15 +104,125 @@ fn main() {
             }
         }
 
+        let mut admin_drain = app.admin_drain();
+        let shutdown_delay = app.shutdown_delay();
         let drain = app.spawn();
-        tokio::select! {
-            _ = signal::shutdown() => {
-                info!("Received shutdown signal");
-            }
-            _ = shutdown_rx.recv() => {
-                info!("Received shutdown via admin interface");
+        let admin = async move {
+            tokio::select! {
+                _ = shutdown_rx.recv() => {
+                    info!("Received shutdown via admin interface");
+                }
+                _ = admin_drain.requested() => {
+                    info!("Received drain via admin interface");
+                }
             }
-        }
+        };
+        await_shutdown(signal::shutdown, admin, shutdown_delay).await;
         drain.drain().await;
     });
 }
+
+/// Completes when the proxy should begin to drain.
+///
+/// Draining is delayed by `delay` after a shutdown signal so that the proxy
+/// keeps serving while endpoints are deregistered. A second signal or a
+/// shutdown requested via the admin interface ends the delay early.
+async fn await_shutdown<S, F, A>(mut next_signal: S, admin: A, delay: Duration)
+where
+    S: FnMut() -> F,
+    F: Future<Output = ()>,
+    A: Future<Output = ()>,
+{
+    tokio::pin!(admin);
+    tokio::select! {
+        _ = next_signal() => {
+            info!("Received shutdown signal");
+        }
+        _ = &mut admin => return,
+    }
+
+    if delay == Duration::from_secs(0) {
+        return;
+    }
+    info!(?delay, "Delaying drain");
+    tokio::select! {
+        _ = tokio::time::sleep(delay) => {}
+        _ = next_signal() => {
+            info!("Received second shutdown signal; draining immediately");
+        }
+        _ = &mut admin => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+    use std::{pin::Pin, sync::Arc};
+    use tokio::{sync::Notify, time};
+
+    const DELAY: Duration = Duration::from_secs(10);
+
+    type Signal = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+    /// Returns a handle that delivers signals and a source of signals.
+    fn signals() -> (Arc<Notify>, impl FnMut() -> Signal) {
+        let tx = Arc::new(Notify::new());
+        let rx = tx.clone();
+        let signal = move || -> Signal {
+            let rx = rx.clone();
+            Box::pin(async move { rx.notified().await })
+        };
+        (tx, signal)
+    }
+
+    #[tokio::test]
+    async fn delays_drain_after_signal() {
+        time::pause();
+        let (tx, signal) = signals();
+        tx.notify_one();
+
+        let start = time::Instant::now();
+        await_shutdown(signal, future::pending(), DELAY).await;
+        assert!(start.elapsed() >= DELAY);
+    }
+
+    #[tokio::test]
+    async fn second_signal_ends_delay() {
+        time::pause();
+        let (tx, signal) = signals();
+        tx.notify_one();
+        tokio::spawn(async move {
+            time::sleep(Duration::from_secs(1)).await;
+            tx.notify_one();
+        });
+
+        let start = time::Instant::now();
+        await_shutdown(signal, future::pending(), DELAY).await;
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_secs(1) && elapsed < DELAY);
+    }
+
+    #[tokio::test]
+    async fn admin_shutdown_ends_delay() {
+        time::pause();
+        let (tx, signal) = signals();
+        tx.notify_one();
+
+        let start = time::Instant::now();
+        let admin = time::sleep(Duration::from_secs(2));
+        await_shutdown(signal, admin, DELAY).await;
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_secs(2) && elapsed < DELAY);
+    }
+
+    #[tokio::test]
+    async fn admin_shutdown_is_not_delayed() {
+        time::pause();
+        let (_tx, signal) = signals();
+
+        let start = time::Instant::now();
+        await_shutdown(signal, future::ready(()), DELAY).await;
+        assert_eq!(start.elapsed(), Duration::from_secs(0));
+    }
+}